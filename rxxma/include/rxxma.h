@@ -0,0 +1,39 @@
+/* C interface of rxxma, built with `cargo build --release --features ffi`. */
+
+#ifndef RXXMA_H
+#define RXXMA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define AMXX_OK 0
+#define AMXX_ERR_NULL_ARGUMENT -1
+#define AMXX_ERR_INVALID_INPUT -2
+#define AMXX_ERR_PANIC -3
+
+/*
+ * Decompiles .amxx or .amx file contents.
+ * On AMXX_OK *out receives the source, otherwise *err (if err is not NULL)
+ * receives the error message. Both must be released with amxx_free_string.
+ */
+int32_t amxx_decompile(const uint8_t *buf, size_t len, char **out, char **err);
+
+/*
+ * Same contract as amxx_decompile, *out receives PluginInfo as JSON:
+ * {"format":"Amxx","sections":[...],"cod_size":...,"dat_size":...,
+ *  "publics":[...],"natives":[...]}
+ */
+int32_t amxx_inspect_json(const uint8_t *buf, size_t len, char **out, char **err);
+
+/* Releases string returned by this library, NULL is ignored. */
+void amxx_free_string(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RXXMA_H */
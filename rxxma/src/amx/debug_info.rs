@@ -0,0 +1,370 @@
+// Symbolic information amxxpc appends after the image of debug builds
+// (amxdbg.h). Addresses are cell sized, everything else has fixed width.
+
+use std::ffi::CString;
+use std::io::{BufRead, Cursor};
+
+use crate::error::{read_at, AmxError};
+use byteorder::{LittleEndian, ReadBytesExt};
+
+pub const DEBUG_MAGIC: u16 = 0xF1EF;
+
+// Symbol identifier kinds
+pub const IDENT_VARIABLE: u8 = 1;
+pub const IDENT_REFERENCE: u8 = 2;
+pub const IDENT_ARRAY: u8 = 3;
+pub const IDENT_REFARRAY: u8 = 4;
+pub const IDENT_FUNCTION: u8 = 9;
+
+// Symbol storage classes
+pub const VCLASS_GLOBAL: u8 = 0;
+pub const VCLASS_LOCAL: u8 = 1;
+pub const VCLASS_STATIC: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugFile {
+    // Cod address where code of file starts
+    pub address: usize,
+    pub name: CString,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugLine {
+    pub address: usize,
+    // Zero based
+    pub line: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugDimension {
+    pub tag: i16,
+    // Cells, 0 for unknown size
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugSymbol {
+    // DAT address, frame offset for locals, cod address for functions
+    pub address: i64,
+    pub tag: i16,
+    // Cod range symbol is visible in
+    pub code_start: usize,
+    pub code_end: usize,
+    pub ident: u8,
+    pub vclass: u8,
+    pub dimensions: Vec<DebugDimension>,
+    pub name: CString,
+}
+
+impl DebugSymbol {
+    pub fn is_function(&self) -> bool {
+        self.ident == IDENT_FUNCTION
+    }
+
+    // Frame relative, arguments have positive offsets
+    pub fn is_local(&self) -> bool {
+        self.vclass == VCLASS_LOCAL
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugTag {
+    pub id: i16,
+    pub name: CString,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugAutomaton {
+    pub id: i16,
+    // DAT address of state variable
+    pub address: usize,
+    pub name: CString,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugState {
+    pub id: i16,
+    pub automaton: i16,
+    pub name: CString,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugInfo {
+    pub files: Vec<DebugFile>,
+    pub lines: Vec<DebugLine>,
+    pub symbols: Vec<DebugSymbol>,
+    pub tags: Vec<DebugTag>,
+    pub automatons: Vec<DebugAutomaton>,
+    pub states: Vec<DebugState>,
+}
+
+struct Reader<'a> {
+    cursor: Cursor<&'a [u8]>,
+    cellsize: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn cell(&mut self) -> Result<i64, AmxError> {
+        let cellsize = self.cellsize;
+        read_at(&mut self.cursor, "debug cell", |r| match cellsize {
+            8 => r.read_i64::<LittleEndian>(),
+            _ => r.read_i32::<LittleEndian>().map(i64::from),
+        })
+    }
+
+    fn address(&mut self) -> Result<usize, AmxError> {
+        let cell = self.cell()?;
+        if self.cellsize == 8 {
+            Ok(cell as usize)
+        } else {
+            Ok(cell as u32 as usize)
+        }
+    }
+
+    fn i16(&mut self) -> Result<i16, AmxError> {
+        read_at(&mut self.cursor, "debug short", |r| {
+            r.read_i16::<LittleEndian>()
+        })
+    }
+
+    fn u8(&mut self) -> Result<u8, AmxError> {
+        read_at(&mut self.cursor, "debug char", |r| r.read_u8())
+    }
+
+    fn name(&mut self) -> Result<CString, AmxError> {
+        let mut name = vec![];
+        let offset = self.cursor.position() as usize;
+        self.cursor.read_until(0, &mut name)?;
+        if name.pop() != Some(0) {
+            return Err(AmxError::Eof {
+                what: "debug name",
+                offset,
+            });
+        }
+        // Terminating zero is the only one read_until takes
+        Ok(CString::new(name).unwrap())
+    }
+}
+
+impl DebugInfo {
+    // Chunk starting with debug header
+    pub fn parse(bytes: &[u8], cellsize: usize) -> Result<DebugInfo, AmxError> {
+        let mut reader = Reader {
+            cursor: Cursor::new(bytes),
+            cellsize,
+        };
+        let size = read_at(&mut reader.cursor, "debug size", |r| {
+            r.read_u32::<LittleEndian>()
+        })? as usize;
+        if size > bytes.len() {
+            return Err(AmxError::Eof {
+                what: "debug info",
+                offset: bytes.len(),
+            });
+        }
+        let magic = read_at(&mut reader.cursor, "debug magic", |r| {
+            r.read_u16::<LittleEndian>()
+        })?;
+        if magic != DEBUG_MAGIC {
+            return Err(AmxError::InvalidDebugMagic(DEBUG_MAGIC, magic));
+        }
+        // File and amx version, flags
+        reader.u8()?;
+        reader.u8()?;
+        reader.i16()?;
+
+        let mut counts = [0usize; 6];
+        for count in counts.iter_mut() {
+            *count = reader.i16()? as u16 as usize;
+        }
+        let [files, lines, symbols, tags, automatons, states] = counts;
+
+        let mut info = DebugInfo {
+            files: vec![],
+            lines: vec![],
+            symbols: vec![],
+            tags: vec![],
+            automatons: vec![],
+            states: vec![],
+        };
+        for _ in 0..files {
+            let address = reader.address()?;
+            let name = reader.name()?;
+            info.files.push(DebugFile { address, name });
+        }
+        for _ in 0..lines {
+            let address = reader.address()?;
+            let line = read_at(&mut reader.cursor, "debug line", |r| {
+                r.read_i32::<LittleEndian>()
+            })?;
+            info.lines.push(DebugLine { address, line });
+        }
+        for _ in 0..symbols {
+            let address = reader.cell()?;
+            let tag = reader.i16()?;
+            let code_start = reader.address()?;
+            let code_end = reader.address()?;
+            let ident = reader.u8()?;
+            let vclass = reader.u8()?;
+            let dimension_count = reader.i16()?;
+            let name = reader.name()?;
+            let mut dimensions = vec![];
+            for _ in 0..dimension_count {
+                let tag = reader.i16()?;
+                let size = reader.address()?;
+                dimensions.push(DebugDimension { tag, size });
+            }
+            info.symbols.push(DebugSymbol {
+                address,
+                tag,
+                code_start,
+                code_end,
+                ident,
+                vclass,
+                dimensions,
+                name,
+            });
+        }
+        for _ in 0..tags {
+            let id = reader.i16()?;
+            let name = reader.name()?;
+            info.tags.push(DebugTag { id, name });
+        }
+        for _ in 0..automatons {
+            let id = reader.i16()?;
+            let address = reader.address()?;
+            let name = reader.name()?;
+            info.automatons.push(DebugAutomaton { id, address, name });
+        }
+        for _ in 0..states {
+            let id = reader.i16()?;
+            let automaton = reader.i16()?;
+            let name = reader.name()?;
+            info.states.push(DebugState {
+                id,
+                automaton,
+                name,
+            });
+        }
+
+        Ok(info)
+    }
+
+    // Function symbol whose code contains cod address
+    pub fn function_at(&self, address: usize) -> Option<&DebugSymbol> {
+        self.symbols
+            .iter()
+            .find(|s| s.is_function() && s.code_start <= address && address < s.code_end)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::ffi::CString;
+    use std::ops::Range;
+
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::{DebugInfo, DEBUG_MAGIC, IDENT_ARRAY, IDENT_FUNCTION, VCLASS_LOCAL};
+
+    // Debug chunk of 32 bit plugin: one file, one line, function `main`
+    // at 0x8..0x40 with local array `name[32]` at -128, one tag
+    pub fn debug_chunk() -> Vec<u8> {
+        let mut body = vec![];
+        // Files
+        body.write_u32::<LittleEndian>(0).unwrap();
+        body.extend_from_slice(b"plugin.sma\0");
+        // Lines
+        body.write_u32::<LittleEndian>(0x8).unwrap();
+        body.write_i32::<LittleEndian>(4).unwrap();
+        // Symbols
+        body.write_u32::<LittleEndian>(0x8).unwrap();
+        body.write_i16::<LittleEndian>(0).unwrap();
+        body.write_u32::<LittleEndian>(0x8).unwrap();
+        body.write_u32::<LittleEndian>(0x40).unwrap();
+        body.push(IDENT_FUNCTION);
+        body.push(0);
+        body.write_i16::<LittleEndian>(0).unwrap();
+        body.extend_from_slice(b"main\0");
+        body.write_i32::<LittleEndian>(-128).unwrap();
+        body.write_i16::<LittleEndian>(0).unwrap();
+        body.write_u32::<LittleEndian>(0x10).unwrap();
+        body.write_u32::<LittleEndian>(0x38).unwrap();
+        body.push(IDENT_ARRAY);
+        body.push(VCLASS_LOCAL);
+        body.write_i16::<LittleEndian>(1).unwrap();
+        body.extend_from_slice(b"name\0");
+        body.write_i16::<LittleEndian>(0).unwrap();
+        body.write_u32::<LittleEndian>(32).unwrap();
+        // Tags
+        body.write_i16::<LittleEndian>(1).unwrap();
+        body.extend_from_slice(b"Float\0");
+
+        chunk(&[1, 1, 2, 1, 0, 0], &body)
+    }
+
+    // Symbol record without dimensions
+    pub fn debug_symbol(
+        name: &str,
+        address: i32,
+        code: Range<u32>,
+        ident: u8,
+        vclass: u8,
+    ) -> Vec<u8> {
+        let mut symbol = vec![];
+        symbol.write_i32::<LittleEndian>(address).unwrap();
+        symbol.write_i16::<LittleEndian>(0).unwrap();
+        symbol.write_u32::<LittleEndian>(code.start).unwrap();
+        symbol.write_u32::<LittleEndian>(code.end).unwrap();
+        symbol.push(ident);
+        symbol.push(vclass);
+        symbol.write_i16::<LittleEndian>(0).unwrap();
+        symbol.extend_from_slice(name.as_bytes());
+        symbol.push(0);
+        symbol
+    }
+
+    // Debug chunk of 32 bit plugin with symbols only
+    pub fn symbols_chunk(symbols: &[Vec<u8>]) -> Vec<u8> {
+        chunk(&[0, 0, symbols.len() as i16, 0, 0, 0], &symbols.concat())
+    }
+
+    fn chunk(counts: &[i16], body: &[u8]) -> Vec<u8> {
+        let mut chunk = vec![];
+        chunk
+            .write_u32::<LittleEndian>(22 + body.len() as u32)
+            .unwrap();
+        chunk.write_u16::<LittleEndian>(DEBUG_MAGIC).unwrap();
+        chunk.push(8);
+        chunk.push(8);
+        chunk.write_i16::<LittleEndian>(0).unwrap();
+        for count in counts {
+            chunk.write_i16::<LittleEndian>(*count).unwrap();
+        }
+        chunk.extend_from_slice(body);
+        chunk
+    }
+
+    #[test]
+    fn it_parse_debug_info() {
+        let info = DebugInfo::parse(&debug_chunk(), 4).unwrap();
+
+        assert_eq!(info.files[0].name, CString::new("plugin.sma").unwrap());
+        assert_eq!(info.lines[0].line, 4);
+        assert_eq!(info.symbols.len(), 2);
+        assert_eq!(info.symbols[1].address, -128);
+        assert_eq!(info.symbols[1].dimensions[0].size, 32);
+        assert_eq!(info.tags[0].name, CString::new("Float").unwrap());
+        assert_eq!(
+            info.function_at(0x20).map(|f| f.name.clone()),
+            Some(CString::new("main").unwrap())
+        );
+    }
+
+    #[test]
+    fn it_reject_truncated_debug_info() {
+        let chunk = debug_chunk();
+        assert!(DebugInfo::parse(&chunk[..chunk.len() - 3], 4).is_err());
+        assert!(DebugInfo::parse(&chunk[..4], 4).is_err());
+    }
+}
@@ -1,11 +1,21 @@
+pub mod debug_info;
 mod native;
 mod opcode;
 mod opcode_type;
+mod opcodes;
 pub mod plugin;
 mod public;
+mod pubvar;
+mod tag;
+pub mod writer;
+pub use self::debug_info::DebugInfo;
 pub use self::native::Native;
 pub use self::opcode::Opcode;
 pub use self::opcode_type::*;
-pub use self::plugin::Plugin;
+pub use self::opcodes::Opcodes;
 pub use self::plugin::CELLSIZE;
+pub use self::plugin::{AmxFlags, DatString, Plugin};
 pub use self::public::Public;
+pub use self::pubvar::PubVar;
+pub use self::tag::Tag;
+pub use self::writer::Writer;
@@ -1,7 +1,15 @@
 use std::ffi::CString;
 
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Native {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serialize::lossy_name")
+    )]
     pub name: CString,
     pub address: usize,
 }
@@ -1,14 +1,19 @@
-use std::io;
+use std::convert::TryFrom;
 use std::io::{Read, Seek, SeekFrom};
 use std::str;
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use enum_primitive::FromPrimitive;
 use log::trace;
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
 use super::opcode_type::*;
+use super::CELLSIZE;
+use crate::error::AmxError;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Opcode {
     pub code: OpcodeType,
     pub address: usize,
@@ -16,85 +21,132 @@ pub struct Opcode {
 }
 
 impl Opcode {
-    pub fn read_from<T: Read + Seek>(
+    pub fn read_from<T: Read + Seek>(cod_reader: &mut T) -> Result<Option<Vec<Opcode>>, AmxError> {
+        Opcode::read_cells(cod_reader, CELLSIZE)
+    }
+
+    // Like `read_from` for cod of given cellsize (4 or 8)
+    pub fn read_cells<T: Read + Seek>(
         cod_reader: &mut T,
-    ) -> Result<Option<Vec<Opcode>>, &'static str> {
+        cellsize: usize,
+    ) -> Result<Option<Vec<Opcode>>, AmxError> {
         // In case we return multiple
         let mut opcodes: Vec<Opcode> = vec![];
 
         let address = Opcode::read_addr(cod_reader)?;
 
-        // FIXME: Check for invalid opcode
-        let code = match cod_reader.read_u32::<LittleEndian>() {
-            Ok(c) => c,
-            Err(_) => return Ok(None), // Return no opcode, end of cod section
+        let code = match Opcode::read_param(cod_reader, cellsize)? {
+            Some(c) => c,
+            None => return Ok(None), // Return no opcode, end of cod section
         };
         // for debugging purposes
         trace!("0x{:X}\tOpcode: {}", address, code);
 
         let enum_code = match OpcodeType::from_u32(code) {
-            Some(c) => c,
-            None => return Err("invalid opcode found"),
+            Some(c) if !c.is_pseudo() => c,
+            _ => return Err(invalid(address, "invalid opcode found")),
         };
         // for debugging purposes
         trace!("As enum: {:?}", enum_code);
 
-        // TODO: Test param
-        let param = if SINGLE_PARAM_OPCODES.contains(&code) {
+        // Only first param is kept, rest belongs to obsolete debug opcodes
+        let mut param = None;
+        for _ in 0..enum_code.params() {
             trace!("Reading param");
-            match Opcode::read_param(cod_reader) {
-                Ok(p) => Some(p),
-                Err(_) => return Err("opcode declared to have param but it's .COD EOF instead"),
+            let p = match Opcode::read_param(cod_reader, cellsize)? {
+                Some(p) => p,
+                None => {
+                    return Err(invalid(
+                        address,
+                        "opcode declared to have param but it's .COD EOF instead",
+                    ))
+                }
+            };
+            param.get_or_insert(p);
+        }
+
+        if enum_code == OP_FILE || enum_code == OP_SYMBOL {
+            // Skip file or symbol name
+            let size = i64::from(param.unwrap_or(0));
+            if cod_reader.seek(SeekFrom::Current(size)).is_err() {
+                return Err(invalid(address, "debug opcode size points past .COD"));
             }
-        } else {
-            None
-        };
+        }
 
-        let opcode = Opcode {
+        let mut opcode = Opcode {
             code: enum_code,
             address,
             param,
         };
 
-        if opcode.code == OP_SHL || opcode.code == OP_SSHR {
-            // FIXME: Check compiler for
-            // CONST.pri  0x1
-            // LOAD.alt  0x2528C     ; weaponid
-            // SHL  0xC
-            // UNKNOWN OP CODE: 0x4030C02
-            match Opcode::read_param(cod_reader) {
-                Ok(p) => p,
-                Err(_) => return Err("EOF on SHL Hack"),
-            };
+        if opcode.code == OP_CASETBL {
+            // Number of cases is the param, case table follows
+            let (number_of_cases, table) = Opcode::read_case_table(cod_reader, cellsize)?;
+            opcode.param = Some(number_of_cases);
+            opcodes.push(opcode);
+            opcodes.extend(table);
+        } else {
+            opcodes.push(opcode);
         }
 
-        let is_casetbl = opcode.code == OP_CASETBL;
-        opcodes.push(opcode);
+        Ok(Some(opcodes))
+    }
 
-        if is_casetbl {
-            match Opcode::read_case_table(cod_reader) {
-                Ok(v) => opcodes.extend(v),
-                Err(e) => return Err(e),
-            };
-        };
+    // Like `read_from`, but undecodable cell becomes OP_UNKNOWN
+    pub fn read_from_lenient<T: Read + Seek>(
+        cod_reader: &mut T,
+    ) -> Result<Option<Vec<Opcode>>, AmxError> {
+        Opcode::read_cells_lenient(cod_reader, CELLSIZE)
+    }
 
-        Ok(Some(opcodes))
+    // Like `read_cells`, but undecodable cell becomes OP_UNKNOWN with raw
+    // cell as param and reading resynchronizes at the next cell
+    pub fn read_cells_lenient<T: Read + Seek>(
+        cod_reader: &mut T,
+        cellsize: usize,
+    ) -> Result<Option<Vec<Opcode>>, AmxError> {
+        let address = Opcode::read_addr(cod_reader)?;
+
+        match Opcode::read_cells(cod_reader, cellsize) {
+            Err(e) => {
+                trace!("0x{:X}	Unknown opcode: {}", address, e);
+                if cod_reader.seek(SeekFrom::Start(address as u64)).is_err() {
+                    return Err(invalid(address, "wtf: cannot seek on reader"));
+                }
+                // Cell too wide for param is skipped without it
+                let raw = match Opcode::read_param(cod_reader, cellsize) {
+                    Ok(Some(r)) => Some(r),
+                    Ok(None) => return Ok(None),
+                    Err(_) => None,
+                };
+                Ok(Some(vec![Opcode {
+                    code: OP_UNKNOWN,
+                    address,
+                    param: raw,
+                }]))
+            }
+            result => result,
+        }
     }
 
-    fn read_case_table<T: Read + Seek>(cod_reader: &mut T) -> Result<Vec<Opcode>, &'static str> {
+    fn read_case_table<T: Read + Seek>(
+        cod_reader: &mut T,
+        cellsize: usize,
+    ) -> Result<(u32, Vec<Opcode>), AmxError> {
         trace!("Process case table");
+        let table = Opcode::read_addr(cod_reader)?;
         let mut opcodes: Vec<Opcode> = vec![];
 
-        let number_of_jumps = match Opcode::read_param(cod_reader) {
-            Ok(p) => p,
-            Err(_) => return Err("casetbl number of jumps unexpected EOF"),
+        let number_of_jumps = match Opcode::read_param(cod_reader, cellsize)? {
+            Some(p) => p,
+            None => return Err(invalid(table, "casetbl number of jumps unexpected EOF")),
         };
         trace!("Case table number of jumps: {}", number_of_jumps);
 
         let address = Opcode::read_addr(cod_reader)?;
-        let none_found_param = match Opcode::read_param(cod_reader) {
-            Ok(p) => p,
-            Err(_) => return Err("casetbl 'none found' param: unexpected EOF"),
+        let none_found_param = match Opcode::read_param(cod_reader, cellsize)? {
+            Some(p) => p,
+            None => return Err(invalid(table, "casetbl 'none found' param: unexpected EOF")),
         };
 
         // for debugging purposes
@@ -110,9 +162,9 @@ impl Opcode {
         for i in 0..number_of_jumps {
             trace!("Process casetbl case #{}", i);
             let address = Opcode::read_addr(cod_reader)?;
-            let case_param = match Opcode::read_param(cod_reader) {
-                Ok(p) => p,
-                Err(_) => return Err("casetbl 'case' param: unexpected EOF"),
+            let case_param = match Opcode::read_param(cod_reader, cellsize)? {
+                Some(p) => p,
+                None => return Err(invalid(table, "casetbl 'case' param: unexpected EOF")),
             };
             trace!("CASE {}", case_param);
             let case_op = Opcode {
@@ -123,36 +175,59 @@ impl Opcode {
             opcodes.push(case_op);
 
             let address = Opcode::read_addr(cod_reader)?;
-            let case_jmp_param = match Opcode::read_param(cod_reader) {
-                Ok(p) => p,
-                Err(_) => return Err("casetbl 'case' param: unexpected EOF"),
+            let case_jmp_param = match Opcode::read_param(cod_reader, cellsize)? {
+                Some(p) => p,
+                None => return Err(invalid(table, "casetbl 'case jump' param: unexpected EOF")),
             };
             trace!("CASEJMP {}", case_jmp_param);
             let case_jmp = Opcode {
-                code: OP_CASENONE,
+                code: OP_CASEJMP,
                 address,
-                param: Some(case_param),
+                param: Some(case_jmp_param),
             };
             opcodes.push(case_jmp);
         }
 
-        Ok(opcodes)
+        Ok((number_of_jumps, opcodes))
     }
 
-    fn read_param<T: Read + Seek>(cod_reader: &mut T) -> Result<u32, io::Error> {
-        cod_reader.read_u32::<LittleEndian>()
+    // Cell at reader position, None at .COD EOF. 64 bit cell has to fit in
+    // 32 bits, either unsigned or sign extended negative number.
+    fn read_param<T: Read + Seek>(
+        cod_reader: &mut T,
+        cellsize: usize,
+    ) -> Result<Option<u32>, AmxError> {
+        let offset = Opcode::read_addr(cod_reader)?;
+        let cell = if cellsize == 8 {
+            cod_reader.read_u64::<LittleEndian>()
+        } else {
+            cod_reader.read_u32::<LittleEndian>().map(u64::from)
+        };
+        let cell = match cell {
+            Ok(c) => c,
+            Err(_) => return Ok(None),
+        };
+        match u32::try_from(cell).or_else(|_| i32::try_from(cell as i64).map(|c| c as u32)) {
+            Ok(c) => Ok(Some(c)),
+            Err(_) => Err(invalid(offset, "64 bit cell does not fit in 32 bits")),
+        }
     }
 
-    fn read_addr<T: Read + Seek>(cod_reader: &mut T) -> Result<usize, &'static str> {
-        let address = match cod_reader.seek(SeekFrom::Current(0)) {
+    fn read_addr<T: Read + Seek>(cod_reader: &mut T) -> Result<usize, AmxError> {
+        let address = match cod_reader.stream_position() {
             Ok(c) => c,
-            Err(_) => return Err("wtf: cannot seek on reader"),
+            Err(_) => return Err(invalid(0, "wtf: cannot seek on reader")),
         };
 
         Ok(address as usize)
     }
 }
 
+// Offsets are relative to the reader start, `Opcodes` rebases them
+fn invalid(offset: usize, reason: &'static str) -> AmxError {
+    AmxError::InvalidOpcode { offset, reason }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Opcode;
@@ -171,4 +246,113 @@ mod tests {
         let mut cursor = Cursor::new([]);
         assert!(Opcode::read_from(&mut cursor).unwrap().is_none());
     }
+
+    #[test]
+    fn it_resynchronize_after_unknown_opcode() {
+        // Junk cell; RETN
+        let mut cursor = Cursor::new([0xFF, 0, 0, 0, 0x30, 0, 0, 0]);
+        assert!(Opcode::read_from(&mut cursor).is_err());
+
+        cursor.set_position(0);
+        let junk = Opcode::read_from_lenient(&mut cursor).unwrap().unwrap();
+        let retn = Opcode::read_from_lenient(&mut cursor).unwrap().unwrap();
+
+        assert_eq!(junk[0].code, OP_UNKNOWN);
+        assert_eq!(junk[0].param, Some(0xFF));
+        assert_eq!(retn[0].code, OP_RETN);
+        assert_eq!(retn[0].address, 4);
+        assert!(Opcode::read_from_lenient(&mut cursor).unwrap().is_none());
+    }
+
+    #[test]
+    fn it_read_shl_without_param() {
+        // SHL; JZER 0x10
+        let mut cursor = Cursor::new([0x41, 0, 0, 0, 0x35, 0, 0, 0, 0x10, 0, 0, 0]);
+        let shl = Opcode::read_from(&mut cursor).unwrap().unwrap();
+        let jzer = Opcode::read_from(&mut cursor).unwrap().unwrap();
+
+        assert_eq!(shl[0].code, OP_SHL);
+        assert_eq!(shl[0].param, None);
+        assert_eq!(jzer[0].code, OP_JZER);
+        assert_eq!(jzer[0].param, Some(0x10));
+    }
+
+    #[test]
+    fn it_skip_all_params() {
+        // LINE 1 2; NOP
+        let mut cursor = Cursor::new([0x7D, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 0x86, 0, 0, 0]);
+        let line = Opcode::read_from(&mut cursor).unwrap().unwrap();
+        let nop = Opcode::read_from(&mut cursor).unwrap().unwrap();
+
+        assert_eq!(line[0].code, OP_LINE);
+        assert_eq!(line[0].param, Some(1));
+        assert_eq!(nop[0].code, OP_NOP);
+    }
+
+    #[test]
+    fn it_read_case_table() {
+        // CASETBL 2 cases, none 0x40, 1 -> 0x20, 5 -> 0x30; NOP
+        let cells: Vec<u8> = [0x82, 2, 0x40, 1, 0x20, 5, 0x30, 0x86]
+            .iter()
+            .flat_map(|&c: &u32| c.to_le_bytes().to_vec())
+            .collect();
+        let mut cursor = Cursor::new(cells);
+        let table = Opcode::read_from(&mut cursor).unwrap().unwrap();
+        let nop = Opcode::read_from(&mut cursor).unwrap().unwrap();
+
+        let decoded: Vec<_> = table.iter().map(|o| (o.code, o.address, o.param)).collect();
+        assert_eq!(
+            decoded,
+            [
+                (OP_CASETBL, 0x0, Some(2)),
+                (OP_CASENONE, 0x8, Some(0x40)),
+                (OP_CASE, 0xC, Some(1)),
+                (OP_CASEJMP, 0x10, Some(0x20)),
+                (OP_CASE, 0x14, Some(5)),
+                (OP_CASEJMP, 0x18, Some(0x30)),
+            ]
+        );
+        assert_eq!(nop[0].code, OP_NOP);
+        assert_eq!(nop[0].address, 0x1C);
+    }
+
+    #[test]
+    fn it_err_on_truncated_case_table() {
+        let cells: Vec<u8> = [0x82, 3, 0x40, 1, 0x20]
+            .iter()
+            .flat_map(|&c: &u32| c.to_le_bytes().to_vec())
+            .collect();
+        assert!(Opcode::read_from(&mut Cursor::new(cells)).is_err());
+    }
+
+    #[test]
+    fn it_reject_pseudo_opcodes() {
+        let mut cursor = Cursor::new([0x8A, 0, 0, 0]);
+        assert!(Opcode::read_from(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn it_read_64_bit_cells() {
+        let mut cursor = Cursor::new([
+            0x27, 0, 0, 0, 0, 0, 0, 0, 0xFC, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        ]);
+        let opcodes = Opcode::read_cells(&mut cursor, 8).unwrap().unwrap();
+        assert_eq!(opcodes[0].code, OP_PUSH_C);
+        assert_eq!(opcodes[0].param, Some(0xFFFF_FFFC));
+    }
+
+    #[test]
+    fn it_err_on_wide_64_bit_cells() {
+        // PUSH.C 0x1_0000_0000
+        let mut cursor = Cursor::new([0x27, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+        assert!(Opcode::read_cells(&mut cursor, 8).is_err());
+
+        cursor.set_position(0);
+        let push = Opcode::read_cells_lenient(&mut cursor, 8).unwrap().unwrap();
+        let wide = Opcode::read_cells_lenient(&mut cursor, 8).unwrap().unwrap();
+        assert_eq!(push[0].code, OP_UNKNOWN);
+        assert_eq!(push[0].param, Some(0x27));
+        assert_eq!(wide[0].code, OP_UNKNOWN);
+        assert_eq!(wide[0].param, None);
+    }
 }
@@ -1,5 +1,10 @@
 use std::fmt;
 
+use enum_primitive::FromPrimitive;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+
 enum_from_primitive! {
 #[derive(Copy, Clone, Debug, PartialEq)]
 #[allow(non_camel_case_types)]
@@ -143,253 +148,298 @@ pub enum OpcodeType {
     OP_SYMTAG,  // obsolete
     OP_BREAK, // End of AMXX op codes
     // List of rxxma pseudo opcodes, careful!
-    // TODO: Opcode types with multiple params
     OP_CASENONE,
     OP_CASE,
-    OP_CASEJMP
+    OP_CASEJMP,
+    OP_UNKNOWN // undecodable cell, param is its raw value
 }}
 
 pub use self::OpcodeType::*;
+use self::OperandKind::*;
+use self::StackEffect::*;
 
-pub const SINGLE_PARAM_OPCODES: [u32; 74] = [
-    OP_LOAD_PRI as u32,
-    OP_LOAD_ALT as u32,
-    OP_LOAD_S_PRI as u32,
-    OP_LOAD_S_ALT as u32,
-    OP_LREF_PRI as u32,
-    OP_LREF_ALT as u32,
-    OP_LREF_S_PRI as u32,
-    OP_LREF_S_ALT as u32,
-    OP_LODB_I as u32,
-    OP_CONST_PRI as u32,
-    OP_CONST_ALT as u32,
-    OP_ADDR_PRI as u32,
-    OP_ADDR_ALT as u32,
-    OP_STOR_PRI as u32,
-    OP_STOR_ALT as u32,
-    OP_STOR_S_PRI as u32,
-    OP_STOR_S_ALT as u32,
-    OP_SREF_PRI as u32,
-    OP_SREF_ALT as u32,
-    OP_SREF_S_PRI as u32,
-    OP_SREF_S_ALT as u32,
-    OP_STRB_I as u32,
-    OP_LIDX_B as u32,
-    OP_IDXADDR_B as u32,
-    OP_ALIGN_PRI as u32,
-    OP_ALIGN_ALT as u32,
-    OP_LCTRL as u32,
-    OP_SCTRL as u32,
-    OP_PUSH_R as u32,
-    OP_PUSH_C as u32,
-    OP_PUSH as u32,
-    OP_PUSH_S as u32,
-    OP_STACK as u32,
-    OP_HEAP as u32,
-    OP_CALL as u32,
-    OP_JUMP as u32,
-    OP_JREL as u32,
-    OP_JZER as u32,
-    OP_JNZ as u32,
-    OP_JEQ as u32,
-    OP_JNEQ as u32,
-    OP_JLESS as u32,
-    OP_JLEQ as u32,
-    OP_JGRTR as u32,
-    OP_JGEQ as u32,
-    OP_JSLESS as u32,
-    OP_JSLEQ as u32,
-    OP_JSGRTR as u32,
-    OP_JSGEQ as u32,
-    OP_SHL as u32,
-    OP_SHR as u32,
-    OP_SSHR as u32,
-    OP_SHL_C_PRI as u32,
-    OP_SHL_C_ALT as u32,
-    OP_SHR_C_PRI as u32,
-    OP_SHR_C_ALT as u32,
-    OP_ADD_C as u32,
-    OP_SMUL_C as u32,
-    OP_ZERO as u32,
-    OP_ZERO_S as u32,
-    OP_EQ_C_PRI as u32,
-    OP_EQ_C_ALT as u32,
-    OP_INC as u32,
-    OP_INC_S as u32,
-    OP_DEC as u32,
-    OP_DEC_S as u32,
-    OP_MOVS as u32,
-    OP_CMPS as u32,
-    OP_FILL as u32,
-    OP_HALT as u32,
-    OP_BOUNDS as u32,
-    OP_SYSREQ_C as u32,
-    OP_SWITCH as u32,
-    OP_PUSHADDR as u32,
-];
+impl OpcodeType {
+    pub fn info(self) -> &'static OpcodeInfo {
+        &OPCODE_INFO[self as usize]
+    }
+
+    // Number of operand cells following opcode in cod. Case table of CASETBL
+    // is read separately, first operand of FILE and SYMBOL is byte size
+    // of the rest of their operands.
+    pub fn params(self) -> usize {
+        self.info().operands
+    }
+
+    // rxxma pseudo opcodes never appear in cod
+    pub fn is_pseudo(self) -> bool {
+        self as u32 > OP_BREAK as u32
+    }
+
+    // Real opcode printed as mnemonic, case insensitive
+    pub fn from_mnemonic(mnemonic: &str) -> Option<OpcodeType> {
+        let id = OPCODE_INFO[1..=OP_BREAK as usize]
+            .iter()
+            .position(|info| info.mnemonic.eq_ignore_ascii_case(mnemonic))?;
+        OpcodeType::from_usize(id + 1)
+    }
+}
+
+// What opcode param is, pseudo opcodes included
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OperandKind {
+    NoOperand,
+    Constant,
+    // Global in DAT
+    Dat,
+    // Offset from FRM, arguments or locals
+    Frame,
+    // Cod address, relative for JREL, case table records are operands
+    // themselves
+    Jump,
+    // Cod address jumped to when PRI (and ALT) pass the test
+    Branch,
+    // Cod address of CASETBL
+    CaseTable,
+    // Cod address of PROC
+    Function,
+    // Native table index, native address for SYSREQ.D
+    Native,
+}
+
+// Change of STK in cells, pushed cells are positive
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StackEffect {
+    Cells(i32),
+    // Depends on param (STACK, PUSH.R) or on stack contents (RETN)
+    Variable,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OpcodeInfo {
+    pub mnemonic: &'static str,
+    // See `OpcodeType::params`
+    pub operands: usize,
+    pub operand: OperandKind,
+    pub stack: StackEffect,
+}
+
+const fn info(
+    mnemonic: &'static str,
+    operands: usize,
+    operand: OperandKind,
+    stack: StackEffect,
+) -> OpcodeInfo {
+    OpcodeInfo {
+        mnemonic,
+        operands,
+        operand,
+        stack,
+    }
+}
 
-const OPCODE_FMT_NAMES: [&str; 141] = [
-    "INVALID",    // invalid opcode
-    "LOAD.pri",   // Load address into PRI.
-    "LOAD.alt",   // Load address into ALT.
-    "LOAD.S.pri", // Load stack offset into PRI.
-    "LOAD.S.alt", // Load stack offset into ALT.
-    "LREF.pri",   // Load address ref into PRI.
-    "LREF.alt",   // Load address ref into ALT.
-    "LREF.S.pri", // Load stack offset ref into PRI.
-    "LREF.S.alt", // Load stack offset ref into ALT.
-    "LOAD.I",     // PRI = [PRI]
-    "LOBD.I",     // PRI = [PRI + Param bytes]
-    "CONST.pri",  // PRI = value TODO: Better const reading
-    "CONST.alt",  // ALT = value
-    "ADDR.pri",   // PRI = FRM + offs
-    "ADDR.alt",   // ALT = FRM + offs
-    "STOR.pri",   // [ Param ] = PRI
-    "STOR.alt",   // [ Param ] = ALT
-    "STOR.S.pri", // [ Stack + Param ] = PRI
-    "STOR.S.alt", // [ Stack + Param ] = ALT
-    "SREF.pri",   // [ [ Param ] ] = PRI
-    "SREF.alt",   // [ [ Param ] ] = ALT
-    "SREF.S.pri", // [ [ Stack + Param ] ] = PRI
-    "SREF.S.alt", // [ [ Stack + Param ] ] = ALT
-    "STOR.I",     // [ ALT ] = PRI
-    "STRB.I",     // [ ALT ] = PRI (Param = number of bytes written)
-    "LIDX",       // PRI = [ ALT + (PRI * sizeof(cell)) ]
-    "LIDX.B",     // PRI = [ ALT + (PRI << param)]
-    "IDXADDR",    // PRI = ALT + (PRI * sizeof(cell))
-    "IDXADDR.B",  // PRI = ALT + (PRI << param)
-    "ALIGN.pri",  // PRI ^= cellsize - param
-    "ALIGN.pri",  // ALT ^= cellsize - param
-    "LCTRL",      // PRI is set to special register value.
-    "SCTRL",      // the special register is set to PRI
-    "MOVE.pri",   // PRI = ALT
-    "MOVE.alt",   // ALT = PRI
-    "XCHG",       // Exchange alt and pri
-    "PUSH.pri",   // [STK] = PRI; STK -= sizeof(cell)
-    "PUSH.alt",   // [STK] = ALT; STK -= sizeof(cell)
-    "PUSH.R",     // obsolete
-    "PUSH.C",     // TODO: Better handling. [STK] = param; STK -= sizeof(cell)
-    "PUSH",       // [STK] = [PARAM]; STK -= sizeof(cell)
-    "PUSH.S",     // [STK] = [FRM + param]; STK -= sizeof(cell)
-    "POP.pri",    // STK += sizeof(cell) ; PRI = [STK]
-    "POP.alt",    // STK += sizeof(cell) ; ALT = [STK]
-    "STACK",      // ALT = STK; STK += param
-    "HEAP",       // ALT = HEA; HEA += param
-    "PROC",       // [STK] = FRM; STK -= sizeof(cell); FRM = [STK]
-    "RET",        // STK += cellsize; FRM = [STK]; STK += cellsize; CIP = [STK]
-    "RETN",       // STK += cellsize; FRM = [STK]; STK += cellsize; CIP = [STK]; STK += [STK]
-    "CALL",       // [STK] = CIP + 5; STK = STK - cellsize; CIP = param
-    "CALL.pri",   // [STK] = CIP + 1; STK -= cellsize; CIP = pri
-    "JUMP",       // CIP = param
-    "JREL",       // CIP += param
-    "JZER",       // if (PRI==0) CIP = [CIP + 1]
-    "JNZ",        // if (PRI!=0) CIP = [ CIP + 1 ]
-    "JEQ",        // if PRI==ALT CIP = [ CIP + 1 ]
-    "JNEQ",       // if PRI!=ALT CIP = [ CIP + 1 ]
-    "JLESS",      // if PRI<ALT CIP = [ CIP + 1 ]
-    "JLEQ",       // if PRI<=ALT CIP = [ CIP + 1 ]
-    "JGRTR",      // if PRI>ALT CIP = [ CIP + 1 ]
-    "JGEQ",       // if PRI>=ALT CIP = [ CIP + 1 ]
-    "JSLESS",     // if (SIGNED) PRI<ALT CIP = [ CIP + 1 ]
-    "JSLEQ",      // if SIGNED PRI<=ALT CIP = [ CIP + 1 ]
-    "JSGRTR",     // if SIGNED PRI>ALT CIP = [ CIP + 1 ]
-    "JSGEQ",      // if SIGNED PRI>=ALT CIP = [ CIP + 1 ]
-    "SHL",        // PRI = PRI << ALT
-    "SHR",        // PRI = PRI >> ALT
-    "SSHR",       // PRI = PRI >> ALT SIGNED
-    "SHL.C.pri",  // PRI = PRI << param
-    "SHL.C.alt",  // ALT = ALT << param
-    "SHR.C.pri",  // PRI = PRI >> param
-    "SHR.C.alt",  // ALT = ALT >> param
-    "SMUL",       // PRI *= ALT SIGNED
-    "SDIV",       // PRI = PRI / ALT SIGNED (ALT = PRI mod ALT)
-    "SDIV.alt",   // PRI = ALT / PRI SIGNED (ALT = PRI mod ALT)
-    "UMUL",       // PRI *= ALT UNSIGNED
-    "UDIV",       // PRI = PRI / ALT UNSIGNED (ALT = PRI mod ALT)
-    "UDIV.alt",   // PRI = ALT / PRI UNSIGNED (ALT = PRI mod ALT)
-    "ADD",        // PRI += ALT
-    "SUB",        // PRI -= ALT
-    "SUB.alt",    // PRI = ALT - PRI
-    "AND",        // PRI &= ALT
-    "OR",         // PRI |= ALT
-    "XOR",        // PRI ^= ALT
-    "NOT",        // PRI = !ALT
-    "NEG",        // PRI = -PRI
-    "INVERT",     // PRI = ~PRI
-    "ADD.C",      // PRI += param
-    "SMUL.C",     // PRI *= param
-    "ZERO.pri",   // PRI=0
-    "ZERO.alt",   // ALT=0
-    "ZERO",       // [ param ] = 0
-    "ZERO.S",     // [ FRM + param ] = 0
-    "SIGN.pri",   // sign extent the byte in PRI or ALT to a cell
-    "SIGN.alt",   // sign extent the byte in PRI or ALT to a cell
-    "EQ",         // PRI = PRI == ALT ? 1 : 0
-    "NEQ",        // PRI = PRI != ALT ? 1 : 0
-    "LESS",       // PRI = PRI < ALT ? 1 : 0
-    "LEQ",        // PRI = PRI <= ALT ? 1 : 0
-    "GRTR",       // PRI = PRI > ALT ? 1 : 0
-    "GEQ",        // PRI = PRI >= ALT ? 1 : 0
-    "SLESS",      // PRI = PRI < ALT ? 1 : 0
-    "SLEQ",       // PRI = PRI <= ALT ? 1 : 0
-    "SGRTR",      // PRI = PRI > ALT ? 1 : 0
-    "SGEQ",       // PRI = PRI >= ALT ? 1 : 0
-    "EQ.C.pri",   // PRI = PRI == param ? 1 : 0
-    "EQ.C.alt",   // PRI = ALT == param ? 1 : 0
-    "INC.pri",    // PRI++
-    "INC.alt",    // ALT++
-    "INC",        // [ param ] ++
-    "INC.S",      // [ FRM + param ] ++
-    "INC.I",      // [PRI]++
-    "DEC.pri",    // PRI--
-    "DEC.alt",    // ALT--
-    "DEC",        // [ param ] --
-    "DEC.S",      // [ FRM + param ] --
-    "DEC.I",      // [PRI]--
-    "MOVS",       // [ALT] = [PRI] (param is # of bytes)
-    "CMPS",       // compare [ALT] to [PRI] (param is # of bytes)
-    "FILL",       // Fill memory at [ALT] with value at [PRI], param is # of bytes
-    "HALT",       // Halt operation.
-    "BOUNDS",     // Aborts if PRI > param or PRI < 0
-    "SYSREQ.pri", // native, native id is in PRI
-    "SYSREQ.C",   // native, id is param.
-    "OP_FILE",    // obsolete | !WARNING! No fmt value for OP_FILE
-    "OP_LINE",    // obsolete | !WARNING! No fmt value for OP_LINE
-    "OP_SYMBOL",  // obsolete | !WARNING! No fmt value for OP_SYMBOL
-    "OP_SRANGE",  // obsolete | !WARNING! No fmt value for OP_SRANGE
-    "JUMP.pri",   // CIP = pri
-    "SWITCH",     // Compare PRI to the value of the passed casetbl, jump accordingly.
-    "CASETBL",    // TODO: Multiple params
-    "SWAP.pri",   // [STK] = PRI; PRI = old [STK]
-    "SWAP.alt",   // [STK] = ALT; ALT = old [STK]
-    "PUSH.ADR",   // [STK] = FRM + param; STK-=sizeofcell;
-    "NOP",        // No Operation
-    "SYSREQ.D",
-    "OP_SYMTAG", // obsolete | !WARNING! No fmt value for OP_SYMTAG
-    "BREAK",     // Breakpoint
+// Indexed by opcode
+pub static OPCODE_INFO: [OpcodeInfo; 142] = [
+    info("INVALID", 0, NoOperand, Cells(0)),    // invalid opcode
+    info("LOAD.pri", 1, Dat, Cells(0)),         // Load address into PRI.
+    info("LOAD.alt", 1, Dat, Cells(0)),         // Load address into ALT.
+    info("LOAD.S.pri", 1, Frame, Cells(0)),     // Load stack offset into PRI.
+    info("LOAD.S.alt", 1, Frame, Cells(0)),     // Load stack offset into ALT.
+    info("LREF.pri", 1, Dat, Cells(0)),         // Load address ref into PRI.
+    info("LREF.alt", 1, Dat, Cells(0)),         // Load address ref into ALT.
+    info("LREF.S.pri", 1, Frame, Cells(0)),     // Load stack offset ref into PRI.
+    info("LREF.S.alt", 1, Frame, Cells(0)),     // Load stack offset ref into ALT.
+    info("LOAD.I", 0, NoOperand, Cells(0)),     // PRI = [PRI]
+    info("LOBD.I", 1, Constant, Cells(0)),      // PRI = [PRI + Param bytes]
+    info("CONST.pri", 1, Constant, Cells(0)),   // PRI = value TODO: Better const reading
+    info("CONST.alt", 1, Constant, Cells(0)),   // ALT = value
+    info("ADDR.pri", 1, Frame, Cells(0)),       // PRI = FRM + offs
+    info("ADDR.alt", 1, Frame, Cells(0)),       // ALT = FRM + offs
+    info("STOR.pri", 1, Dat, Cells(0)),         // [ Param ] = PRI
+    info("STOR.alt", 1, Dat, Cells(0)),         // [ Param ] = ALT
+    info("STOR.S.pri", 1, Frame, Cells(0)),     // [ Stack + Param ] = PRI
+    info("STOR.S.alt", 1, Frame, Cells(0)),     // [ Stack + Param ] = ALT
+    info("SREF.pri", 1, Dat, Cells(0)),         // [ [ Param ] ] = PRI
+    info("SREF.alt", 1, Dat, Cells(0)),         // [ [ Param ] ] = ALT
+    info("SREF.S.pri", 1, Frame, Cells(0)),     // [ [ Stack + Param ] ] = PRI
+    info("SREF.S.alt", 1, Frame, Cells(0)),     // [ [ Stack + Param ] ] = ALT
+    info("STOR.I", 0, NoOperand, Cells(0)),     // [ ALT ] = PRI
+    info("STRB.I", 1, Constant, Cells(0)),      // [ ALT ] = PRI (Param = number of bytes written)
+    info("LIDX", 0, NoOperand, Cells(0)),       // PRI = [ ALT + (PRI * sizeof(cell)) ]
+    info("LIDX.B", 1, Constant, Cells(0)),      // PRI = [ ALT + (PRI << param)]
+    info("IDXADDR", 0, NoOperand, Cells(0)),    // PRI = ALT + (PRI * sizeof(cell))
+    info("IDXADDR.B", 1, Constant, Cells(0)),   // PRI = ALT + (PRI << param)
+    info("ALIGN.pri", 1, Constant, Cells(0)),   // PRI ^= cellsize - param
+    info("ALIGN.pri", 1, Constant, Cells(0)),   // ALT ^= cellsize - param
+    info("LCTRL", 1, Constant, Cells(0)),       // PRI is set to special register value.
+    info("SCTRL", 1, Constant, Cells(0)),       // the special register is set to PRI
+    info("MOVE.pri", 0, NoOperand, Cells(0)),   // PRI = ALT
+    info("MOVE.alt", 0, NoOperand, Cells(0)),   // ALT = PRI
+    info("XCHG", 0, NoOperand, Cells(0)),       // Exchange alt and pri
+    info("PUSH.pri", 0, NoOperand, Cells(1)),   // [STK] = PRI; STK -= sizeof(cell)
+    info("PUSH.alt", 0, NoOperand, Cells(1)),   // [STK] = ALT; STK -= sizeof(cell)
+    info("PUSH.R", 1, Constant, Variable),      // obsolete
+    info("PUSH.C", 1, Constant, Cells(1)), // TODO: Better handling. [STK] = param; STK -= sizeof(cell)
+    info("PUSH", 1, Dat, Cells(1)),        // [STK] = [PARAM]; STK -= sizeof(cell)
+    info("PUSH.S", 1, Frame, Cells(1)),    // [STK] = [FRM + param]; STK -= sizeof(cell)
+    info("POP.pri", 0, NoOperand, Cells(-1)), // STK += sizeof(cell) ; PRI = [STK]
+    info("POP.alt", 0, NoOperand, Cells(-1)), // STK += sizeof(cell) ; ALT = [STK]
+    info("STACK", 1, Constant, Variable),  // ALT = STK; STK += param
+    info("HEAP", 1, Constant, Cells(0)),   // ALT = HEA; HEA += param
+    info("PROC", 0, NoOperand, Cells(1)),  // [STK] = FRM; STK -= sizeof(cell); FRM = [STK]
+    info("RET", 0, NoOperand, Cells(-2)), // STK += cellsize; FRM = [STK]; STK += cellsize; CIP = [STK]
+    info("RETN", 0, NoOperand, Variable), // STK += cellsize; FRM = [STK]; STK += cellsize; CIP = [STK]; STK += [STK]
+    info("CALL", 1, Function, Cells(1)),  // [STK] = CIP + 5; STK = STK - cellsize; CIP = param
+    info("CALL.pri", 0, NoOperand, Cells(1)), // [STK] = CIP + 1; STK -= cellsize; CIP = pri
+    info("JUMP", 1, Jump, Cells(0)),      // CIP = param
+    info("JREL", 1, Jump, Cells(0)),      // CIP += param
+    info("JZER", 1, Branch, Cells(0)),    // if (PRI==0) CIP = [CIP + 1]
+    info("JNZ", 1, Branch, Cells(0)),     // if (PRI!=0) CIP = [ CIP + 1 ]
+    info("JEQ", 1, Branch, Cells(0)),     // if PRI==ALT CIP = [ CIP + 1 ]
+    info("JNEQ", 1, Branch, Cells(0)),    // if PRI!=ALT CIP = [ CIP + 1 ]
+    info("JLESS", 1, Branch, Cells(0)),   // if PRI<ALT CIP = [ CIP + 1 ]
+    info("JLEQ", 1, Branch, Cells(0)),    // if PRI<=ALT CIP = [ CIP + 1 ]
+    info("JGRTR", 1, Branch, Cells(0)),   // if PRI>ALT CIP = [ CIP + 1 ]
+    info("JGEQ", 1, Branch, Cells(0)),    // if PRI>=ALT CIP = [ CIP + 1 ]
+    info("JSLESS", 1, Branch, Cells(0)),  // if (SIGNED) PRI<ALT CIP = [ CIP + 1 ]
+    info("JSLEQ", 1, Branch, Cells(0)),   // if SIGNED PRI<=ALT CIP = [ CIP + 1 ]
+    info("JSGRTR", 1, Branch, Cells(0)),  // if SIGNED PRI>ALT CIP = [ CIP + 1 ]
+    info("JSGEQ", 1, Branch, Cells(0)),   // if SIGNED PRI>=ALT CIP = [ CIP + 1 ]
+    info("SHL", 0, NoOperand, Cells(0)),  // PRI = PRI << ALT
+    info("SHR", 0, NoOperand, Cells(0)),  // PRI = PRI >> ALT
+    info("SSHR", 0, NoOperand, Cells(0)), // PRI = PRI >> ALT SIGNED
+    info("SHL.C.pri", 1, Constant, Cells(0)), // PRI = PRI << param
+    info("SHL.C.alt", 1, Constant, Cells(0)), // ALT = ALT << param
+    info("SHR.C.pri", 1, Constant, Cells(0)), // PRI = PRI >> param
+    info("SHR.C.alt", 1, Constant, Cells(0)), // ALT = ALT >> param
+    info("SMUL", 0, NoOperand, Cells(0)), // PRI *= ALT SIGNED
+    info("SDIV", 0, NoOperand, Cells(0)), // PRI = PRI / ALT SIGNED (ALT = PRI mod ALT)
+    info("SDIV.alt", 0, NoOperand, Cells(0)), // PRI = ALT / PRI SIGNED (ALT = PRI mod ALT)
+    info("UMUL", 0, NoOperand, Cells(0)), // PRI *= ALT UNSIGNED
+    info("UDIV", 0, NoOperand, Cells(0)), // PRI = PRI / ALT UNSIGNED (ALT = PRI mod ALT)
+    info("UDIV.alt", 0, NoOperand, Cells(0)), // PRI = ALT / PRI UNSIGNED (ALT = PRI mod ALT)
+    info("ADD", 0, NoOperand, Cells(0)),  // PRI += ALT
+    info("SUB", 0, NoOperand, Cells(0)),  // PRI -= ALT
+    info("SUB.alt", 0, NoOperand, Cells(0)), // PRI = ALT - PRI
+    info("AND", 0, NoOperand, Cells(0)),  // PRI &= ALT
+    info("OR", 0, NoOperand, Cells(0)),   // PRI |= ALT
+    info("XOR", 0, NoOperand, Cells(0)),  // PRI ^= ALT
+    info("NOT", 0, NoOperand, Cells(0)),  // PRI = !ALT
+    info("NEG", 0, NoOperand, Cells(0)),  // PRI = -PRI
+    info("INVERT", 0, NoOperand, Cells(0)), // PRI = ~PRI
+    info("ADD.C", 1, Constant, Cells(0)), // PRI += param
+    info("SMUL.C", 1, Constant, Cells(0)), // PRI *= param
+    info("ZERO.pri", 0, NoOperand, Cells(0)), // PRI=0
+    info("ZERO.alt", 0, NoOperand, Cells(0)), // ALT=0
+    info("ZERO", 1, Dat, Cells(0)),       // [ param ] = 0
+    info("ZERO.S", 1, Frame, Cells(0)),   // [ FRM + param ] = 0
+    info("SIGN.pri", 0, NoOperand, Cells(0)), // sign extent the byte in PRI or ALT to a cell
+    info("SIGN.alt", 0, NoOperand, Cells(0)), // sign extent the byte in PRI or ALT to a cell
+    info("EQ", 0, NoOperand, Cells(0)),   // PRI = PRI == ALT ? 1 : 0
+    info("NEQ", 0, NoOperand, Cells(0)),  // PRI = PRI != ALT ? 1 : 0
+    info("LESS", 0, NoOperand, Cells(0)), // PRI = PRI < ALT ? 1 : 0
+    info("LEQ", 0, NoOperand, Cells(0)),  // PRI = PRI <= ALT ? 1 : 0
+    info("GRTR", 0, NoOperand, Cells(0)), // PRI = PRI > ALT ? 1 : 0
+    info("GEQ", 0, NoOperand, Cells(0)),  // PRI = PRI >= ALT ? 1 : 0
+    info("SLESS", 0, NoOperand, Cells(0)), // PRI = PRI < ALT ? 1 : 0
+    info("SLEQ", 0, NoOperand, Cells(0)), // PRI = PRI <= ALT ? 1 : 0
+    info("SGRTR", 0, NoOperand, Cells(0)), // PRI = PRI > ALT ? 1 : 0
+    info("SGEQ", 0, NoOperand, Cells(0)), // PRI = PRI >= ALT ? 1 : 0
+    info("EQ.C.pri", 1, Constant, Cells(0)), // PRI = PRI == param ? 1 : 0
+    info("EQ.C.alt", 1, Constant, Cells(0)), // PRI = ALT == param ? 1 : 0
+    info("INC.pri", 0, NoOperand, Cells(0)), // PRI++
+    info("INC.alt", 0, NoOperand, Cells(0)), // ALT++
+    info("INC", 1, Dat, Cells(0)),        // [ param ] ++
+    info("INC.S", 1, Frame, Cells(0)),    // [ FRM + param ] ++
+    info("INC.I", 0, NoOperand, Cells(0)), // [PRI]++
+    info("DEC.pri", 0, NoOperand, Cells(0)), // PRI--
+    info("DEC.alt", 0, NoOperand, Cells(0)), // ALT--
+    info("DEC", 1, Dat, Cells(0)),        // [ param ] --
+    info("DEC.S", 1, Frame, Cells(0)),    // [ FRM + param ] --
+    info("DEC.I", 0, NoOperand, Cells(0)), // [PRI]--
+    info("MOVS", 1, Constant, Cells(0)),  // [ALT] = [PRI] (param is # of bytes)
+    info("CMPS", 1, Constant, Cells(0)),  // compare [ALT] to [PRI] (param is # of bytes)
+    info("FILL", 1, Constant, Cells(0)), // Fill memory at [ALT] with value at [PRI], param is # of bytes
+    info("HALT", 1, Constant, Cells(0)), // Halt operation.
+    info("BOUNDS", 1, Constant, Cells(0)), // Aborts if PRI > param or PRI < 0
+    info("SYSREQ.pri", 0, NoOperand, Cells(0)), // native, native id is in PRI
+    info("SYSREQ.C", 1, Native, Cells(0)), // native, id is param.
+    info("OP_FILE", 1, Constant, Cells(0)), // obsolete | !WARNING! No fmt value for OP_FILE
+    info("OP_LINE", 2, Constant, Cells(0)), // obsolete | !WARNING! No fmt value for OP_LINE
+    info("OP_SYMBOL", 1, Constant, Cells(0)), // obsolete | !WARNING! No fmt value for OP_SYMBOL
+    info("OP_SRANGE", 2, Constant, Cells(0)), // obsolete | !WARNING! No fmt value for OP_SRANGE
+    info("JUMP.pri", 0, NoOperand, Cells(0)), // CIP = pri
+    info("SWITCH", 1, CaseTable, Cells(0)), // Compare PRI to the value of the passed casetbl, jump accordingly.
+    info("CASETBL", 0, NoOperand, Cells(0)), // TODO: Multiple params
+    info("SWAP.pri", 0, NoOperand, Cells(0)), // [STK] = PRI; PRI = old [STK]
+    info("SWAP.alt", 0, NoOperand, Cells(0)), // [STK] = ALT; ALT = old [STK]
+    info("PUSH.ADR", 1, Frame, Cells(1)),   // [STK] = FRM + param; STK-=sizeofcell;
+    info("NOP", 0, NoOperand, Cells(0)),    // No Operation
+    info("SYSREQ.D", 1, Native, Cells(0)),
+    info("OP_SYMTAG", 1, Constant, Cells(0)), // obsolete | !WARNING! No fmt value for OP_SYMTAG
+    info("BREAK", 0, NoOperand, Cells(0)),    // Breakpoint
     // End of AMXX op codes
     // --------------------
     // List of rxxma pseudo opcodes, careful!
-    "CASENONE",
-    "CASE",
-    "CASEJMP",
+    info("CASENONE", 0, Jump, Cells(0)),
+    info("CASE", 0, Constant, Cells(0)),
+    info("CASEJMP", 0, Jump, Cells(0)),
+    info("UNKNOWN", 0, Constant, Cells(0)),
 ];
 
 impl fmt::Display for OpcodeType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let id = *self as usize;
-        // TODO: Return original name when out of bounds
-        let fmt_name = OPCODE_FMT_NAMES[id];
-        write!(f, "{}", fmt_name)
+        write!(f, "{}", self.info().mnemonic)
+    }
+}
+
+// As mnemonic
+#[cfg(feature = "serde")]
+impl Serialize for OpcodeType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::OpcodeType;
     use super::OpcodeType::*;
 
     #[test]
     fn has_fmt() {
         assert_eq!("LOAD.pri", format!("{}", OP_LOAD_PRI));
     }
+
+    #[test]
+    fn it_parse_mnemonic() {
+        assert_eq!(OpcodeType::from_mnemonic("push.c"), Some(OP_PUSH_C));
+        assert_eq!(OpcodeType::from_mnemonic("SYSREQ.C"), Some(OP_SYSREQ_C));
+        assert_eq!(OpcodeType::from_mnemonic("CASENONE"), None);
+        assert_eq!(OpcodeType::from_mnemonic("INVALID"), None);
+    }
+
+    #[test]
+    fn it_describe_operands() {
+        use super::{OperandKind, StackEffect};
+
+        assert_eq!(OP_LOAD_PRI.info().operand, OperandKind::Dat);
+        assert_eq!(OP_PUSH_S.info().operand, OperandKind::Frame);
+        assert_eq!(OP_JSGEQ.info().operand, OperandKind::Branch);
+        assert_eq!(OP_SWITCH.info().operand, OperandKind::CaseTable);
+        assert_eq!(OP_CASEJMP.info().operand, OperandKind::Jump);
+        assert_eq!(OP_CALL.info().operand, OperandKind::Function);
+        assert_eq!(OP_SYSREQ_C.info().operand, OperandKind::Native);
+        assert_eq!(OP_RET.info().operand, OperandKind::NoOperand);
+        assert_eq!(OP_PUSH_C.info().stack, StackEffect::Cells(1));
+        assert_eq!(OP_POP_ALT.info().stack, StackEffect::Cells(-1));
+        assert_eq!(OP_STACK.info().stack, StackEffect::Variable);
+    }
+
+    #[test]
+    fn it_count_params() {
+        assert_eq!(OP_SHL.params(), 0);
+        assert_eq!(OP_SHL_C_PRI.params(), 1);
+        assert_eq!(OP_SYSREQ_D.params(), 1);
+        assert_eq!(OP_LINE.params(), 2);
+    }
 }
@@ -0,0 +1,79 @@
+// Opcodes decoded on demand while iterating over cod, so taking first few
+// instructions does not decode the whole segment.
+
+use std::collections::VecDeque;
+use std::io::Cursor;
+
+use super::Opcode;
+use crate::error::AmxError;
+
+pub struct Opcodes<'a> {
+    reader: Cursor<&'a [u8]>,
+    // File offset of cod, errors report file offsets
+    cod: usize,
+    cellsize: usize,
+    lenient: bool,
+    // Case table records decoded together with CASETBL
+    pending: VecDeque<Opcode>,
+    // Nothing is decoded after cod end or error
+    done: bool,
+}
+
+impl<'a> Opcodes<'a> {
+    // `reader` is positioned at the first opcode to decode
+    pub(crate) fn new(
+        reader: Cursor<&'a [u8]>,
+        cod: usize,
+        cellsize: usize,
+        lenient: bool,
+    ) -> Opcodes<'a> {
+        Opcodes {
+            reader,
+            cod,
+            cellsize,
+            lenient,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Opcodes<'a> {
+    type Item = Result<Opcode, AmxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(opcode) = self.pending.pop_front() {
+            return Some(Ok(opcode));
+        }
+        if self.done {
+            return None;
+        }
+
+        let read = if self.lenient {
+            Opcode::read_cells_lenient(&mut self.reader, self.cellsize)
+        } else {
+            Opcode::read_cells(&mut self.reader, self.cellsize)
+        };
+        match read {
+            Ok(Some(opcodes)) => {
+                self.pending.extend(opcodes);
+                self.pending.pop_front().map(Ok)
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(AmxError::InvalidOpcode { offset, reason }) => {
+                self.done = true;
+                Some(Err(AmxError::InvalidOpcode {
+                    offset: self.cod + offset,
+                    reason,
+                }))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
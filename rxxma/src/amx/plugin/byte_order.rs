@@ -0,0 +1,211 @@
+use byteorder::{ByteOrder, LittleEndian};
+use log::warn;
+
+use super::{AmxFlags, AMXMOD_MAGIC, INLINE_NAME_SIZE};
+use crate::error::AmxError;
+
+// Header fields
+const SIZE: usize = 0;
+const MAGIC: usize = 4;
+const FLAGS: usize = 8;
+const DEFSIZE: usize = 10;
+const COD: usize = 12;
+const HEA: usize = 20;
+const PUBLICS: usize = 32;
+const NAMETABLE: usize = 52;
+const HEADER_SIZE: usize = 56;
+
+// Header of image compiled for big endian target has magic bytes swapped
+pub(crate) fn is_big_endian(bin: &[u8]) -> bool {
+    bin.get(MAGIC..MAGIC + 2)
+        .map(LittleEndian::read_u16)
+        .is_some_and(|magic| magic == AMXMOD_MAGIC.swap_bytes())
+}
+
+// Header fields telling which cells to swap
+struct Layout {
+    size: usize,
+    compact: bool,
+    defsize: usize,
+    cellsize: usize,
+    inline_names: bool,
+    cod: usize,
+    hea: usize,
+    publics: usize,
+    nametable: usize,
+}
+
+impl Layout {
+    // None when defsize is invalid, parser reports it
+    fn read(header: &[u8]) -> Option<Layout> {
+        let field = |offset| LittleEndian::read_u32(&header[offset..]) as usize;
+        let defsize = usize::from(LittleEndian::read_u16(&header[DEFSIZE..]));
+        let cellsize = match defsize {
+            8 => 4,
+            16 => 8,
+            size if size == 4 + INLINE_NAME_SIZE => 4,
+            size if size == 8 + INLINE_NAME_SIZE => 8,
+            _ => return None,
+        };
+        let flags = AmxFlags::from_bits_truncate(LittleEndian::read_u16(&header[FLAGS..]));
+
+        Some(Layout {
+            size: field(SIZE),
+            compact: flags.contains(AmxFlags::COMPACT),
+            defsize,
+            cellsize,
+            inline_names: defsize == cellsize + INLINE_NAME_SIZE,
+            cod: field(COD),
+            hea: field(HEA),
+            publics: field(PUBLICS),
+            nametable: field(NAMETABLE),
+        })
+    }
+}
+
+// Every header field but one byte versions
+fn swap_header(bin: &mut [u8]) {
+    for &(offset, size) in &[(SIZE, 4), (MAGIC, 2), (FLAGS, 2), (DEFSIZE, 2)] {
+        bin[offset..offset + size].reverse();
+    }
+    for field in bin[COD..HEADER_SIZE].chunks_mut(4) {
+        field.reverse();
+    }
+}
+
+fn segment<'b>(
+    bin: &'b mut [u8],
+    start: usize,
+    end: usize,
+    reason: &'static str,
+) -> Result<&'b mut [u8], AmxError> {
+    bin.get_mut(start..end).ok_or(AmxError::Malformed {
+        reason,
+        offset: start,
+    })
+}
+
+// Table records, nametable length and cells of cod and dat. Compact cod
+// and dat are byte streams, the same on any target.
+fn swap_segments(bin: &mut [u8], layout: &Layout) -> Result<(), AmxError> {
+    let tables_end = if layout.inline_names {
+        layout.cod
+    } else {
+        layout.nametable
+    };
+    let tables = segment(bin, layout.publics, tables_end, "tables outside image")?;
+    for record in tables.chunks_mut(layout.defsize) {
+        let value = layout.cellsize.min(record.len());
+        record[..value].reverse();
+        if !layout.inline_names && record.len() == layout.defsize {
+            record[layout.cellsize..layout.cellsize + 4].reverse();
+        }
+    }
+
+    if !layout.inline_names && layout.nametable < layout.cod {
+        let end = layout.nametable + 2;
+        segment(bin, layout.nametable, end, "nametable outside image")?.reverse();
+    }
+
+    if !layout.compact {
+        let cells = segment(bin, layout.cod, layout.hea, "cod and dat outside image")?;
+        for cell in cells.chunks_mut(layout.cellsize) {
+            cell.reverse();
+        }
+    }
+    Ok(())
+}
+
+// Image of big endian target converted to little endian one, as compiler
+// for x86 would emit it. Debug info is dropped, its records stay as is.
+pub(crate) fn to_little_endian(bin: &[u8]) -> Result<Vec<u8>, AmxError> {
+    let mut converted = bin.to_vec();
+    if converted.len() < HEADER_SIZE {
+        return Err(AmxError::Eof {
+            what: "amx header",
+            offset: converted.len(),
+        });
+    }
+    swap_header(&mut converted);
+
+    if let Some(layout) = Layout::read(&converted) {
+        swap_segments(&mut converted, &layout)?;
+        if layout.size < converted.len() {
+            warn!("Big endian image, debug info dropped");
+            converted.truncate(layout.size);
+        }
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{is_big_endian, swap_header, swap_segments, Layout};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    // What compiler for big endian target emits
+    fn to_big_endian(bin: &[u8]) -> Vec<u8> {
+        let mut converted = bin.to_vec();
+        let layout = Layout::read(&converted).unwrap();
+        swap_segments(&mut converted, &layout).unwrap();
+        swap_header(&mut converted);
+        converted
+    }
+
+    fn build(builder: &mut PluginBuilder) -> Vec<u8> {
+        let client_print = builder.native("client_print");
+        let hello = builder.string("hello");
+        builder.pubvar("counter", &[0x0102_0304]);
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, hello)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, client_print)
+            .op_param(OP_STACK, 8)
+            .op(OP_RETN);
+        builder.build()
+    }
+
+    #[test]
+    fn it_load_big_endian_image() {
+        let bin = build(&mut PluginBuilder::new());
+        let big_endian = to_big_endian(&bin);
+        assert!(is_big_endian(&big_endian));
+        assert!(!is_big_endian(&bin));
+
+        let original = Plugin::try_from(bin).unwrap();
+        let converted = Plugin::try_from(&big_endian[..]).unwrap();
+        assert_eq!(converted, original);
+        assert_eq!(
+            converted.natives().unwrap()[0].name.to_str(),
+            Ok("client_print")
+        );
+        assert_eq!(converted.read_string(0).as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn it_load_big_endian_inline_names() {
+        let bin = build(PluginBuilder::new().inline_names());
+        let converted = Plugin::try_from(to_big_endian(&bin)).unwrap();
+
+        assert_eq!(converted, Plugin::try_from(bin).unwrap());
+        assert_eq!(
+            converted.publics().unwrap()[0].name.to_str(),
+            Ok("plugin_init")
+        );
+    }
+
+    #[test]
+    fn it_drop_debug_info() {
+        let bin = build(PluginBuilder::new().debug_info(b"debug"));
+        let converted = Plugin::try_from(to_big_endian(&bin)).unwrap();
+
+        assert_eq!(converted.bin.len(), bin.len() - 5);
+        assert_eq!(converted.debug_info().unwrap(), None);
+    }
+}
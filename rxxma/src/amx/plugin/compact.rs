@@ -0,0 +1,172 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::AmxFlags;
+use crate::error::AmxError;
+
+// Header fields
+const SIZE: usize = 0;
+const FLAGS: usize = 8;
+const HEA: usize = 20;
+// Trailing zeroes of dat compiler may drop, e.g. large zero initialized
+// arrays. Header hea is not trusted with larger allocations.
+const MAX_ZERO_FILL: usize = 64 * 1024 * 1024;
+
+// Cells of compact encoded (-C compiler option) cod and dat. Every cell
+// is stored as 7 bit groups, most significant first, all but the last
+// one with high bit set. Bit 6 of the first group is the sign.
+fn expand_cells(code: &[u8], offset: usize, cellsize: usize) -> Result<Vec<u8>, AmxError> {
+    let mut cells = vec![0; cellsize];
+    let mut result = Vec::with_capacity(code.len() * cellsize);
+    let mut groups = code.iter().enumerate().peekable();
+
+    while let Some(&(start, &first)) = groups.peek() {
+        let mut value: u64 = if first & 0x40 != 0 { u64::MAX } else { 0 };
+        loop {
+            let (_, &group) = groups.next().ok_or(AmxError::Malformed {
+                reason: "truncated compact cell",
+                offset: offset + start,
+            })?;
+            value = (value << 7) | u64::from(group & 0x7F);
+            if group & 0x80 == 0 {
+                break;
+            }
+        }
+
+        if cellsize == 8 {
+            LittleEndian::write_u64(&mut cells, value);
+        } else {
+            LittleEndian::write_u32(&mut cells, value as u32);
+        }
+        result.extend_from_slice(&cells);
+    }
+    Ok(result)
+}
+
+// Image with cod and dat of compact plugin expanded to `hea`, the size
+// they take in memory. Debug info following compact data is kept, header
+// size and flags describe expanded image.
+pub(crate) fn expand(
+    bin: &[u8],
+    size: usize,
+    cod: usize,
+    hea: usize,
+    cellsize: usize,
+) -> Result<Vec<u8>, AmxError> {
+    let code = bin.get(cod..size).ok_or(AmxError::Malformed {
+        reason: "compact cod outside image",
+        offset: cod,
+    })?;
+    let cells = expand_cells(code, cod, cellsize)?;
+    // Compiler drops trailing zeroes of dat
+    if cod + cells.len() > hea {
+        return Err(AmxError::Malformed {
+            reason: "compact data expands past heap start",
+            offset: cod,
+        });
+    }
+    if hea - (cod + cells.len()) > MAX_ZERO_FILL {
+        return Err(AmxError::Malformed {
+            reason: "compact data zero fill too large",
+            offset: HEA,
+        });
+    }
+
+    let mut expanded = bin[..cod].to_vec();
+    expanded.extend_from_slice(&cells);
+    expanded.resize(hea, 0);
+    expanded.extend_from_slice(&bin[size..]);
+
+    LittleEndian::write_u32(&mut expanded[SIZE..], hea as u32);
+    let flags = LittleEndian::read_u16(&expanded[FLAGS..]) & !AmxFlags::COMPACT.bits();
+    LittleEndian::write_u16(&mut expanded[FLAGS..], flags);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use byteorder::{ByteOrder, LittleEndian};
+
+    use super::{expand_cells, AmxFlags};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::error::AmxError;
+    use crate::util::tests::PluginBuilder;
+
+    // What compiler does with -C
+    fn compact(bin: &[u8]) -> Vec<u8> {
+        let cod = LittleEndian::read_u32(&bin[12..]) as usize;
+        let hea = LittleEndian::read_u32(&bin[20..]) as usize;
+        let mut compacted = bin[..cod].to_vec();
+        for cell in bin[cod..hea].chunks(4) {
+            let value = LittleEndian::read_u32(cell) as i32;
+            let mut groups = vec![(value & 0x7F) as u8];
+            let mut rest = value >> 7;
+            // Keep sign bit of the leading group
+            while !(rest == 0 && groups[0] & 0x40 == 0 || rest == -1 && groups[0] & 0x40 != 0) {
+                groups.insert(0, (rest & 0x7F) as u8 | 0x80);
+                rest >>= 7;
+            }
+            compacted.extend_from_slice(&groups);
+        }
+        let size = compacted.len() as u32;
+        LittleEndian::write_u32(&mut compacted[0..], size);
+        compacted[8] |= AmxFlags::COMPACT.bits() as u8;
+        compacted.extend_from_slice(&bin[hea..]);
+        compacted
+    }
+
+    #[test]
+    fn it_expand_cells() {
+        let cells = expand_cells(&[0x00, 0x7F, 0x81, 0x00, 0xFF, 0x7F, 0x40], 0, 4).unwrap();
+        let values: Vec<i32> = cells.chunks(4).map(LittleEndian::read_i32).collect();
+        assert_eq!(values, [0, -1, 128, -1, -64]);
+
+        let cells = expand_cells(&[0x7E], 0, 8).unwrap();
+        assert_eq!(LittleEndian::read_i64(&cells), -2);
+        assert!(expand_cells(&[0x00, 0x81], 0x74, 4).is_err());
+    }
+
+    #[test]
+    fn it_load_compact_plugin() {
+        let mut builder = PluginBuilder::new();
+        let hello = builder.string("hello");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, hello)
+            .op_param(OP_STACK, 0xFFFF_FFFC)
+            .op_param(OP_CONST_PRI, 0x1234_5678)
+            .op(OP_RETN);
+        let bin = builder.debug_info(b"debug").build();
+        let original = Plugin::try_from(bin.clone()).unwrap();
+
+        let compacted = compact(&bin);
+        assert!(compacted.len() < bin.len());
+        let expanded = Plugin::try_from(compacted).unwrap();
+
+        assert_eq!(expanded.opcodes().unwrap(), original.opcodes().unwrap());
+        assert_eq!(
+            expanded.read_string(hello as usize).as_deref(),
+            Some("hello")
+        );
+        assert_eq!(expanded, original);
+    }
+
+    #[test]
+    fn it_err_on_huge_zero_fill() {
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init").op(OP_PROC).op(OP_RETN);
+        let mut compacted = compact(&builder.build());
+        compacted[20..24].copy_from_slice(&0xF000_0000u32.to_le_bytes());
+
+        assert_eq!(
+            Plugin::try_from(compacted),
+            Err(AmxError::Malformed {
+                reason: "compact data zero fill too large",
+                offset: 20,
+            })
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::convert::TryFrom;
+use std::io::{Read, Seek};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use super::{byte_order, AmxFlags, Plugin, HEADER_SIZE};
+use crate::error::{read_to, AmxError};
+
+// Header fields
+const SIZE: usize = 0;
+const FLAGS: usize = 8;
+// Size field of debug chunk
+const DEBUG_SIZE: usize = 4;
+
+impl Plugin<'static> {
+    // Parses image starting at current stream position. Reads `size`
+    // bytes header declares and debug chunk of debug builds, stream is
+    // left right after them.
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<Plugin<'static>, AmxError> {
+        let mut bin = vec![];
+        read_to(reader, &mut bin, HEADER_SIZE, "amx header")?;
+        // Debug info of big endian images is dropped anyway
+        let big_endian = byte_order::is_big_endian(&bin);
+        let (size, flags) = match big_endian {
+            true => (
+                BigEndian::read_u32(&bin[SIZE..]),
+                BigEndian::read_u16(&bin[FLAGS..]),
+            ),
+            false => (
+                LittleEndian::read_u32(&bin[SIZE..]),
+                LittleEndian::read_u16(&bin[FLAGS..]),
+            ),
+        };
+        read_to(reader, &mut bin, size as usize, "amx image")?;
+
+        let flags = AmxFlags::from_bits_truncate(flags);
+        if flags.contains(AmxFlags::DEBUG) && !big_endian {
+            let start = bin.len();
+            // Stripped debug builds have no chunk at all
+            if read_to(reader, &mut bin, start + DEBUG_SIZE, "debug size").is_ok() {
+                let debug_size = LittleEndian::read_u32(&bin[start..]) as usize;
+                read_to(reader, &mut bin, start + debug_size, "debug info")?;
+            } else {
+                bin.truncate(start);
+            }
+        }
+
+        Plugin::try_from(bin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::io::{Cursor, Read};
+
+    use super::Plugin;
+    use crate::error::AmxError;
+    use crate::util::tests::load_fixture;
+
+    #[test]
+    fn it_read_image_from_stream() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let mut stream = amxmod_bin.clone();
+        stream.extend_from_slice(b"trailer");
+        let mut reader = Cursor::new(stream);
+
+        let amxmod_plugin = Plugin::from_reader(&mut reader).unwrap();
+        assert_eq!(amxmod_plugin, Plugin::try_from(amxmod_bin).unwrap());
+        assert!(amxmod_plugin.debug_info().unwrap().is_some());
+
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "trailer");
+    }
+
+    #[test]
+    fn it_err_on_truncated_image() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let size = amxmod_bin.len();
+
+        assert!(matches!(
+            Plugin::from_reader(&mut Cursor::new(amxmod_bin[..size - 1].to_vec())),
+            Err(AmxError::Eof {
+                what: "debug info",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Plugin::from_reader(&mut Cursor::new(amxmod_bin[..40].to_vec())),
+            Err(AmxError::Eof {
+                what: "amx header",
+                offset: 0
+            })
+        ));
+    }
+}
@@ -1,20 +1,38 @@
+mod byte_order;
+mod compact;
+mod from_reader;
+mod name_table;
+mod relocation;
+mod strings;
 mod try_from_vec_u8;
 
-use super::super::util::ReadByteString;
-use super::{Native, Opcode, Public};
-use byteorder::{LittleEndian, ReadBytesExt};
-use failure::{Error, ResultExt};
-use std::ffi::CString;
-use std::io::Cursor;
-use std::str;
+use self::name_table::inline_name;
+pub use self::name_table::NameTable;
+pub use self::strings::DatString;
+
+use super::{DebugInfo, Native, Opcode, Opcodes, PubVar, Public, Tag};
+use crate::analysis::{
+    commands, cvars, functions, hooks, metadata, Command, Cvar, Function, Hook, Metadata,
+};
+use crate::error::AmxError;
+use crate::fingerprint::PluginFingerprint;
+use byteorder::{ByteOrder, LittleEndian};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+use std::borrow::Cow;
+use std::ffi::{CStr, CString};
+use std::io::{Cursor, Read};
+use std::ops::Range;
 
 pub enum ConstantParam {
     Cell(u32),
     String(CString),
 }
 
+// Header flags field. DEBUG and COMPACT are set by compiler, the rest
+// only in images dumped from server memory.
 bitflags! {
-    pub struct Flags: u16 {
+    pub struct AmxFlags: u16 {
         // const AMX_FLAG_CHAR16 = 0x01; // no longer used
         const DEBUG = 0x02; // symbolic info available
         const COMPACT = 0x04; // compact encoding
@@ -27,9 +45,13 @@ bitflags! {
     }
 }
 
+// Header fields, image itself is not serialized. Image is borrowed when
+// parsed from slice and copied only once patched.
 #[derive(Debug, PartialEq)]
-pub struct Plugin {
-    flags: Flags,
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Plugin<'a> {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_flags"))]
+    flags: AmxFlags,
     defsize: u16,
     cod: usize,
     dat: usize,
@@ -41,108 +63,360 @@ pub struct Plugin {
     libraries: usize,
     pubvars: usize,
     tags: usize,
+    // Equals cod when names are stored inline in table records
     nametable: usize,
-    pub bin: Vec<u8>,
+    // 4 or 8, derived from defsize
+    cellsize: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub bin: Cow<'a, [u8]>,
 }
 
-const AMXMOD_MAGIC: u16 = 0xF1E0;
-const FILE_VERSION: u8 = 8;
-const AMX_VERSION: u8 = 8;
+#[cfg(feature = "serde")]
+fn serialize_flags<S: Serializer>(flags: &AmxFlags, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u16(flags.bits())
+}
+
+pub(crate) const AMXMOD_MAGIC: u16 = 0xF1E0;
+pub(crate) const FILE_VERSION: u8 = 8;
+pub(crate) const AMX_VERSION: u8 = 8;
+// Early AMX Mod builds, names inline in table records up to version 7
+pub(crate) const MIN_FILE_VERSION: u8 = 6;
+// Inline name with terminator, sEXPMAX of Small compiler is 19
+pub(crate) const INLINE_NAME_SIZE: usize = 20;
 pub const CELLSIZE: usize = 4;
 
-impl Plugin {
-    fn cod_slice(&self) -> Result<&[u8], Error> {
-        self.bin
-            .get(self.cod..self.dat)
-            .ok_or_else(|| format_err!("cod slice mismatch"))
+// Header size and file offsets of its segment fields
+const HEADER_SIZE: usize = 56;
+const HEADER_FILE_VERSION: usize = 6;
+const HEADER_COD: usize = 12;
+const HEADER_DAT: usize = 16;
+const HEADER_HEA: usize = 20;
+const HEADER_STP: usize = 24;
+const HEADER_PUBLICS: usize = 32;
+const HEADER_NATIVES: usize = 36;
+const HEADER_LIBRARIES: usize = 40;
+const HEADER_PUBVARS: usize = 44;
+const HEADER_TAGS: usize = 48;
+const HEADER_NAMETABLE: usize = 52;
+
+impl Plugin<'_> {
+    pub(crate) fn cod_slice(&self) -> Result<&[u8], AmxError> {
+        self.bin.get(self.cod..self.dat).ok_or(AmxError::Malformed {
+            reason: "cod slice mismatch",
+            offset: self.cod,
+        })
     }
 
-    fn dat_slice(&self) -> Result<&[u8], Error> {
+    pub fn dat_slice(&self) -> Result<&[u8], AmxError> {
+        self.bin.get(self.dat..self.hea).ok_or(AmxError::Malformed {
+            reason: "dat slice mismatch",
+            offset: self.dat,
+        })
+    }
+
+    pub(crate) fn dat_slice_mut(&mut self) -> Result<&mut [u8], AmxError> {
         self.bin
-            .get(self.dat..self.hea)
-            .ok_or_else(|| format_err!("dat slice mismatch"))
+            .to_mut()
+            .get_mut(self.dat..self.hea)
+            .ok_or(AmxError::Malformed {
+                reason: "dat slice mismatch",
+                offset: self.dat,
+            })
     }
 
-    fn publics_slice(&self) -> Result<&[u8], Error> {
+    fn publics_slice(&self) -> Result<&[u8], AmxError> {
         self.bin
             .get(self.publics..self.natives)
-            .ok_or_else(|| format_err!("publics slice mismatch"))
+            .ok_or(AmxError::Malformed {
+                reason: "publics slice mismatch",
+                offset: self.publics,
+            })
     }
 
-    fn natives_slice(&self) -> Result<&[u8], Error> {
+    fn natives_slice(&self) -> Result<&[u8], AmxError> {
         self.bin
             .get(self.natives..self.libraries)
-            .ok_or_else(|| format_err!("natives slice mismatch"))
+            .ok_or(AmxError::Malformed {
+                reason: "natives slice mismatch",
+                offset: self.natives,
+            })
+    }
+
+    fn libraries_slice(&self) -> Result<&[u8], AmxError> {
+        self.bin
+            .get(self.libraries..self.pubvars)
+            .ok_or(AmxError::Malformed {
+                reason: "libraries slice mismatch",
+                offset: self.libraries,
+            })
+    }
+
+    fn pubvars_slice(&self) -> Result<&[u8], AmxError> {
+        self.bin
+            .get(self.pubvars..self.tags)
+            .ok_or(AmxError::Malformed {
+                reason: "pubvars slice mismatch",
+                offset: self.pubvars,
+            })
+    }
+
+    fn tags_slice(&self) -> Result<&[u8], AmxError> {
+        self.bin
+            .get(self.tags..self.nametable)
+            .ok_or(AmxError::Malformed {
+                reason: "tags slice mismatch",
+                offset: self.tags,
+            })
+    }
+
+    pub fn cod_size(&self) -> usize {
+        self.dat.saturating_sub(self.cod)
     }
 
-    pub fn opcodes(&self) -> Result<Vec<Opcode>, Error> {
+    // Memory between data end and stack top, shared by heap and stack
+    pub fn heap_budget(&self) -> usize {
+        self.stp.saturating_sub(self.hea)
+    }
+
+    // Cod address of main(), none when plugin has no main
+    pub fn main(&self) -> Option<usize> {
+        if self.cip == u32::MAX as usize {
+            None
+        } else {
+            Some(self.cip)
+        }
+    }
+
+    pub fn cellsize(&self) -> usize {
+        self.cellsize
+    }
+
+    pub fn flags(&self) -> AmxFlags {
+        self.flags
+    }
+
+    pub fn file_version(&self) -> u8 {
+        self.bin[HEADER_FILE_VERSION]
+    }
+
+    // Table records hold names themselves instead of nametable offsets,
+    // as compilers before file version 7 emitted them
+    pub fn inline_names(&self) -> bool {
+        self.defsize as usize == self.cellsize + INLINE_NAME_SIZE
+    }
+
+    // First cell of `bytes`
+    pub(crate) fn read_cell(&self, bytes: &[u8]) -> u64 {
+        if self.cellsize == 8 {
+            LittleEndian::read_u64(bytes)
+        } else {
+            u64::from(LittleEndian::read_u32(bytes))
+        }
+    }
+
+    // Memory required by image, heap and stack (amxx section memsize)
+    pub fn memsize(&self) -> usize {
+        self.stp
+    }
+
+    // Raw amx image, header included
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bin.to_vec()
+    }
+
+    // Plugin owning its image, for keeping after source bytes are gone
+    pub fn into_owned(self) -> Plugin<'static> {
+        Plugin {
+            bin: Cow::Owned(self.bin.into_owned()),
+            ..self
+        }
+    }
+
+    pub fn opcodes(&self) -> Result<Vec<Opcode>, AmxError> {
+        self.opcode_iter()?.collect()
+    }
+
+    // Like `opcodes`, but undecodable cells become OP_UNKNOWN instead of
+    // failing
+    pub fn opcodes_lenient(&self) -> Result<Vec<Opcode>, AmxError> {
+        self.opcode_iter_lenient()?.collect()
+    }
+
+    // Decodes opcodes one by one as iterator advances, decoding stops after
+    // the first error
+    pub fn opcode_iter(&self) -> Result<Opcodes<'_>, AmxError> {
+        self.read_opcodes(false)
+    }
+
+    pub fn opcode_iter_lenient(&self) -> Result<Opcodes<'_>, AmxError> {
+        self.read_opcodes(true)
+    }
+
+    fn read_opcodes(&self, lenient: bool) -> Result<Opcodes<'_>, AmxError> {
+        if self.flags.contains(AmxFlags::JITC) {
+            return Err(AmxError::Unsupported(
+                "cod of JIT compiled image is machine code",
+            ));
+        }
+        if self.flags.contains(AmxFlags::BYTEOPC) {
+            return Err(AmxError::Unsupported("byte sized opcodes"));
+        }
         let mut cod_reader = Cursor::new(self.cod_slice()?);
 
         // Skip first two opcodes for some reason
+        let mut skip = vec![0; 2 * self.cellsize];
         cod_reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on first opcode skip")?;
-        cod_reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on second opcode skip")?;
-
-        let mut opcodes: Vec<Opcode> = Vec::new();
-        loop {
-            match Opcode::read_from(&mut cod_reader) {
-                // TODO: Test all cases
-                Ok(Some(o)) => opcodes.extend(o),
-                Ok(None) => break,
-                Err(e) => return Err(format_err!("{}", e)),
-            }
-        }
+            .read_exact(&mut skip)
+            .map_err(|_| AmxError::Eof {
+                what: "first opcodes",
+                offset: self.cod,
+            })?;
 
-        Ok(opcodes)
+        Ok(Opcodes::new(cod_reader, self.cod, self.cellsize, lenient))
     }
 
-    pub fn natives(&self) -> Result<Vec<Native>, Error> {
-        let slice = self.natives_slice().unwrap();
-        let result = slice
-            .chunks(8) // Take natives by native struct
-            .map(|n_struct| {
-                // FIXME: Error handling
-                let mut address = &n_struct[0..4];
-                let address = address.read_u32::<LittleEndian>().unwrap() as usize;
-                let mut name_offset = &n_struct[4..8];
-                let name_offset = name_offset.read_u32::<LittleEndian>().unwrap() as usize;
-                let name = self.bin[name_offset..].read_string_zero().unwrap();
+    // Function cod address belongs to, functions last from OP_PROC to the
+    // next one
+    pub fn function_at(&self, address: usize) -> Result<Option<Function>, AmxError> {
+        Ok(functions(self)?.into_iter().find(|f| f.contains(address)))
+    }
+
+    // Name, version and author passed to register_plugin, None when
+    // plugin does not register
+    pub fn metadata(&self) -> Result<Option<Metadata>, AmxError> {
+        metadata(self)
+    }
 
-                Native { name, address }
+    // Console commands registered by register_clcmd and alike
+    pub fn commands(&self) -> Result<Vec<Command>, AmxError> {
+        commands(self)
+    }
+
+    pub fn cvars(&self) -> Result<Vec<Cvar>, AmxError> {
+        cvars(self)
+    }
+
+    // Game events, Ham functions, engine forwards and messages plugin hooks
+    pub fn hooks(&self) -> Result<Vec<Hook>, AmxError> {
+        hooks(self)
+    }
+
+    pub fn natives(&self) -> Result<Vec<Native>, AmxError> {
+        self.read_table(self.natives, self.natives_slice()?)?
+            .into_iter()
+            .map(|(address, name)| Ok(Native { name, address }))
+            .collect()
+    }
+
+    pub fn publics(&self) -> Result<Vec<Public>, AmxError> {
+        self.read_table(self.publics, self.publics_slice()?)?
+            .into_iter()
+            .map(|(address, name)| Ok(Public { name, address }))
+            .collect()
+    }
+
+    // Libraries (modules) plugin requires to be loaded
+    pub fn libraries(&self) -> Result<Vec<CString>, AmxError> {
+        Ok(self
+            .read_table(self.libraries, self.libraries_slice()?)?
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect())
+    }
+
+    pub fn pubvars(&self) -> Result<Vec<PubVar>, AmxError> {
+        self.read_table(self.pubvars, self.pubvars_slice()?)?
+            .into_iter()
+            .map(|(address, name)| Ok(PubVar { name, address }))
+            .collect()
+    }
+
+    pub fn tags(&self) -> Result<Vec<Tag>, AmxError> {
+        self.read_table(self.tags, self.tags_slice()?)?
+            .into_iter()
+            .map(|(id, name)| {
+                Ok(Tag {
+                    id: id as u32,
+                    name,
+                })
             })
-            .collect();
-        Ok(result)
-    }
-
-    pub fn publics(&self) -> Result<Vec<Public>, Error> {
-        let slice = self.publics_slice()?;
-        let result = slice
-            .chunks(8) // Take natives by native struct
-            .map(|n_struct| {
-                // FIXME: Error handling
-                let mut address = &n_struct[0..4];
-                let address = address.read_u32::<LittleEndian>().unwrap() as usize;
-                let mut name_offset = &n_struct[4..8];
-                let name_offset = name_offset.read_u32::<LittleEndian>().unwrap() as usize;
-                let name = self.bin[name_offset..].read_string_zero().unwrap();
-
-                Public { name, address }
+            .collect()
+    }
+
+    pub fn name_table(&self) -> Result<NameTable<'_>, AmxError> {
+        let bytes = self
+            .bin
+            .get(self.nametable..self.cod)
+            .ok_or(AmxError::Malformed {
+                reason: "nametable slice mismatch",
+                offset: self.nametable,
+            })?;
+        NameTable::new(bytes, self.nametable)
+    }
+
+    // (value, name) records of publics, natives, libraries, pubvars
+    // or tags table
+    fn read_table(&self, start: usize, slice: &[u8]) -> Result<Vec<(usize, CString)>, AmxError> {
+        let names = match self.inline_names() {
+            true => None,
+            false => Some(self.name_table()?),
+        };
+        let defsize = self.defsize as usize;
+        slice
+            .chunks(defsize)
+            .enumerate()
+            .map(|(i, record)| {
+                if record.len() != defsize {
+                    return Err(AmxError::Malformed {
+                        reason: "truncated table record",
+                        offset: start + i * defsize,
+                    });
+                }
+                // Name or its offset follows cell sized value
+                let value = LittleEndian::read_u32(&record[0..4]) as usize;
+                let name = self.record_name(names.as_ref(), record, start + i * defsize)?;
+                Ok((value, name.to_owned()))
             })
-            .collect();
-        Ok(result)
+            .collect()
     }
 
-    pub fn read_constant_auto_type(&self, addr: usize) -> Result<ConstantParam, &str> {
-        if addr > (self.hea - self.dat) {
-            return Ok(ConstantParam::Cell(addr as u32));
+    // Name of table record at file offset `offset`
+    fn record_name<'n>(
+        &self,
+        names: Option<&NameTable<'n>>,
+        record: &'n [u8],
+        offset: usize,
+    ) -> Result<&'n CStr, AmxError> {
+        match names {
+            Some(names) => {
+                let name_offset = LittleEndian::read_u32(&record[self.cellsize..]) as usize;
+                names.name_at(name_offset)
+            }
+            None => inline_name(&record[self.cellsize..], offset + self.cellsize),
+        }
+    }
+
+    // Symbolic information of debug builds, it follows the image. Older
+    // file versions mark lines with FILE, LINE and SYMBOL opcodes instead.
+    pub fn debug_info(&self) -> Result<Option<DebugInfo>, AmxError> {
+        if !self.flags.contains(AmxFlags::DEBUG) || self.file_version() < FILE_VERSION {
+            return Ok(None);
+        }
+        let size = LittleEndian::read_u32(&self.bin) as usize;
+        match self.bin.get(size..) {
+            Some(chunk) if !chunk.is_empty() => DebugInfo::parse(chunk, self.cellsize).map(Some),
+            _ => Ok(None),
         }
+    }
+
+    pub fn read_constant_auto_type(&self, addr: usize) -> Result<ConstantParam, AmxError> {
+        let dat = match self.dat_slice()?.get(addr..) {
+            Some(dat) => dat,
+            None => return Ok(ConstantParam::Cell(addr as u32)),
+        };
 
-        // TODO: Error handling
-        let byte_slice: Vec<u8> = self.dat_slice().unwrap()[addr..]
-            .chunks(CELLSIZE)
+        let byte_slice: Vec<u8> = dat
+            .chunks(self.cellsize)
             .map(|x| x[0])
             .take_while(|&x| x != 0)
             .collect();
@@ -150,6 +424,175 @@ impl Plugin {
         let string = CString::new(byte_slice).unwrap();
         Ok(ConstantParam::String(string))
     }
+
+    // Segments follow header in order and fit into image, reported offset
+    // is the one of header field pointing outside
+    pub(crate) fn check_layout(&self) -> Result<(), AmxError> {
+        match self.layout_violations().into_iter().next() {
+            Some(violation) => Err(violation),
+            None => Ok(()),
+        }
+    }
+
+    fn layout_violations(&self) -> Vec<AmxError> {
+        let mut violations = vec![];
+        let fields = [
+            (HEADER_PUBLICS, self.publics),
+            (HEADER_NATIVES, self.natives),
+            (HEADER_LIBRARIES, self.libraries),
+            (HEADER_PUBVARS, self.pubvars),
+            (HEADER_TAGS, self.tags),
+            (HEADER_NAMETABLE, self.nametable),
+            (HEADER_COD, self.cod),
+            (HEADER_DAT, self.dat),
+            (HEADER_HEA, self.hea),
+        ];
+        let mut previous = HEADER_SIZE;
+        for &(field, value) in fields.iter() {
+            if value < previous {
+                violations.push(AmxError::Malformed {
+                    reason: "header offsets are out of order",
+                    offset: field,
+                });
+            }
+            if value > self.bin.len() {
+                violations.push(AmxError::Malformed {
+                    reason: "header offset points past image end",
+                    offset: field,
+                });
+            }
+            previous = previous.max(value);
+        }
+        if self.hea > self.stp {
+            violations.push(AmxError::Malformed {
+                reason: "stack top is below heap",
+                offset: HEADER_STP,
+            });
+        }
+        violations
+    }
+
+    // Byte ranges of header, tables and segments in image order, debug
+    // info is whatever follows DAT
+    pub fn segments(&self) -> Vec<(&'static str, Range<usize>)> {
+        let mut segments = vec![
+            ("header", 0..self.publics),
+            ("publics", self.publics..self.natives),
+            ("natives", self.natives..self.libraries),
+            ("libraries", self.libraries..self.pubvars),
+            ("pubvars", self.pubvars..self.tags),
+            ("tags", self.tags..self.nametable),
+            ("nametable", self.nametable..self.cod),
+            ("cod", self.cod..self.dat),
+            ("dat", self.dat..self.hea),
+        ];
+        if self.hea < self.bin.len() {
+            segments.push(("debug info", self.hea..self.bin.len()));
+        }
+        segments
+    }
+
+    // Every inconsistency of header and name tables, where `verify` stops
+    // at the first one. Tables are not read when segments are misplaced.
+    pub fn validate(&self) -> Vec<AmxError> {
+        let mut violations = self.layout_violations();
+        // Debug info follows image of `size` bytes
+        match self.bin.get(..4).map(LittleEndian::read_u32) {
+            Some(size) if (size as usize) < self.hea => violations.push(AmxError::Malformed {
+                reason: "image size is below heap start",
+                offset: 0,
+            }),
+            Some(size) if size as usize > self.bin.len() => violations.push(AmxError::Malformed {
+                reason: "image size points past image end",
+                offset: 0,
+            }),
+            _ => {}
+        }
+        if !violations.is_empty() {
+            return violations;
+        }
+
+        let names = match self.inline_names() {
+            true => None,
+            false => match self.name_table() {
+                Ok(names) => Some(names),
+                Err(e) => return vec![e],
+            },
+        };
+        let defsize = self.defsize as usize;
+        let tables = [
+            (self.publics, self.publics_slice()),
+            (self.natives, self.natives_slice()),
+            (self.libraries, self.libraries_slice()),
+            (self.pubvars, self.pubvars_slice()),
+            (self.tags, self.tags_slice()),
+        ];
+        for (start, table) in tables {
+            let table = match table {
+                Ok(table) => table,
+                Err(e) => {
+                    violations.push(e);
+                    continue;
+                }
+            };
+            for (i, record) in table.chunks(defsize).enumerate() {
+                if record.len() != defsize {
+                    violations.push(AmxError::Malformed {
+                        reason: "truncated table record",
+                        offset: start + i * defsize,
+                    });
+                    continue;
+                }
+                if let Err(e) = self.record_name(names.as_ref(), record, start + i * defsize) {
+                    violations.push(e);
+                }
+            }
+        }
+        violations
+    }
+
+    // Hashes identifying plugin, exact and normalized ones, the latter
+    // matching copies with edited strings
+    pub fn fingerprint(&self) -> Result<PluginFingerprint, AmxError> {
+        PluginFingerprint::from(self)
+    }
+
+    // Checks header offsets and name tables are consistent and cod decodes
+    pub fn verify(&self) -> Result<(), AmxError> {
+        if let Some(violation) = self.validate().into_iter().next() {
+            return Err(violation);
+        }
+        self.opcodes().map(|_| ())
+    }
+
+    // Strict version of read_constant_auto_type, None unless addr points
+    // to unpacked zero terminated string inside DAT.
+    pub fn read_string(&self, addr: usize) -> Option<String> {
+        self.read_string_bytes(addr)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    // Bytes of string read_string would return, left for caller to decode
+    pub fn read_string_bytes(&self, addr: usize) -> Option<Vec<u8>> {
+        if !addr.is_multiple_of(self.cellsize) {
+            return None;
+        }
+
+        let mut bytes = vec![];
+        for cell in self.dat_slice().ok()?.get(addr..)?.chunks(self.cellsize) {
+            if cell.len() != self.cellsize {
+                return None;
+            }
+
+            match self.read_cell(cell) {
+                0 => return Some(bytes),
+                c if c > 0xFF => return None,
+                c => bytes.push(c as u8),
+            }
+        }
+
+        None
+    }
 }
 
 #[cfg(test)]
@@ -159,9 +602,13 @@ mod tests {
 
     use super::ConstantParam;
     use super::Native;
-    use super::Plugin;
+    use super::PubVar;
     use super::Public;
-    use crate::util::tests::load_fixture;
+    use super::Tag;
+    use super::{AmxFlags, Plugin};
+    use crate::amx::debug_info::tests::debug_chunk;
+    use crate::error::AmxError;
+    use crate::util::tests::{load_fixture, PluginBuilder};
 
     // TODO: Support amx extraction in programm itself
     // fn extract_section_to_file(amxmodx_bin: &[u8], section_number: usize) {
@@ -186,6 +633,40 @@ mod tests {
         amxmod_plugin.opcodes().unwrap();
     }
 
+    #[test]
+    fn it_iterate_opcodes_lazily() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+        let opcodes = amxmod_plugin.opcodes().unwrap();
+
+        let first: Vec<_> = amxmod_plugin
+            .opcode_iter()
+            .unwrap()
+            .take(3)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(first, &opcodes[..3]);
+        assert_eq!(amxmod_plugin.opcode_iter().unwrap().count(), opcodes.len());
+    }
+
+    #[test]
+    fn it_refuse_opcodes_flags_make_undecodable() {
+        let mut amxmod_bin = load_fixture("simple.amx183");
+        assert_eq!(
+            Plugin::try_from(&amxmod_bin[..]).unwrap().flags(),
+            AmxFlags::DEBUG
+        );
+
+        // JITC
+        amxmod_bin[9] = 0x20;
+        let amxmod_plugin = Plugin::try_from(&amxmod_bin[..]).unwrap();
+        assert_eq!(amxmod_plugin.flags(), AmxFlags::DEBUG | AmxFlags::JITC);
+        assert!(matches!(
+            amxmod_plugin.opcodes(),
+            Err(AmxError::Unsupported(_))
+        ));
+    }
+
     #[test]
     fn it_read_natives() {
         let amxmod_bin = load_fixture("two_natives.amx183");
@@ -218,6 +699,68 @@ mod tests {
         assert_eq!(publics, expected_publics);
     }
 
+    #[test]
+    fn it_read_pubvars() {
+        let mut builder = PluginBuilder::new();
+        builder.string("padding");
+        let address = builder.pubvar("g_iCount", &[0]);
+        let amx_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let expected_pubvars = [PubVar {
+            name: CString::new("g_iCount").unwrap(),
+            address: address as usize,
+        }];
+        assert_eq!(amx_plugin.pubvars().unwrap(), expected_pubvars);
+        assert!(amx_plugin.verify().is_ok());
+    }
+
+    #[test]
+    fn it_read_libraries() {
+        let mut builder = PluginBuilder::new();
+        builder.library("fakemeta").library("hamsandwich");
+        let amx_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let expected_libraries = [
+            CString::new("fakemeta").unwrap(),
+            CString::new("hamsandwich").unwrap(),
+        ];
+        assert_eq!(amx_plugin.libraries().unwrap(), expected_libraries);
+        assert!(amx_plugin.verify().is_ok());
+    }
+
+    #[test]
+    fn it_read_debug_info() {
+        let mut builder = PluginBuilder::new();
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+        assert_eq!(plugin.debug_info().unwrap(), None);
+
+        builder.debug_info(&debug_chunk());
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+        let info = plugin.debug_info().unwrap().unwrap();
+        assert_eq!(info.symbols[0].name, CString::new("main").unwrap());
+    }
+
+    #[test]
+    fn it_read_tags() {
+        let mut builder = PluginBuilder::new();
+        builder.tag("Float", 0x4000_0001);
+        builder.tag("bool", 0x0000_0002);
+        let amx_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let expected_tags = [
+            Tag {
+                id: 0x4000_0001,
+                name: CString::new("Float").unwrap(),
+            },
+            Tag {
+                id: 0x0000_0002,
+                name: CString::new("bool").unwrap(),
+            },
+        ];
+        assert_eq!(amx_plugin.tags().unwrap(), expected_tags);
+        assert!(amx_plugin.verify().is_ok());
+    }
+
     #[test]
     fn it_read_string_by_addr() {
         let amxmod_bin = load_fixture("cell_constants.amx183");
@@ -241,4 +784,72 @@ mod tests {
 
         assert_eq!(99999999, number);
     }
+
+    #[test]
+    fn it_verify_plugin() {
+        let mut amxmod_bin = load_fixture("two_natives.amx183");
+        assert!(Plugin::try_from(amxmod_bin.clone())
+            .unwrap()
+            .verify()
+            .is_ok());
+
+        // Point first native name before nametable
+        amxmod_bin[68] = 0;
+        assert!(Plugin::try_from(amxmod_bin).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn it_validate_name_table() {
+        let mut amxmod_bin = load_fixture("two_natives.amx183");
+        assert_eq!(Plugin::try_from(amxmod_bin.clone()).unwrap().validate(), []);
+
+        // Point both native names before nametable
+        amxmod_bin[68] = 0;
+        amxmod_bin[76] = 0;
+        let violations = Plugin::try_from(amxmod_bin).unwrap().validate();
+
+        assert_eq!(violations.len(), 2);
+        assert!(violations.iter().all(|v| matches!(
+            v,
+            AmxError::Malformed {
+                reason: "name offset outside nametable",
+                ..
+            }
+        )));
+    }
+
+    #[test]
+    fn it_err_on_segments_outside_image() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        // DAT cut in the middle, hea points past image end
+        assert_eq!(
+            Plugin::try_from(amxmod_bin[..200].to_vec()),
+            Err(AmxError::Malformed {
+                reason: "header offset points past image end",
+                offset: 20,
+            })
+        );
+
+        // Natives table before publics one
+        let mut swapped_bin = amxmod_bin;
+        swapped_bin[36..40].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(
+            Plugin::try_from(swapped_bin),
+            Err(AmxError::Malformed {
+                reason: "header offsets are out of order",
+                offset: 36,
+            })
+        );
+    }
+
+    #[test]
+    fn it_read_strict_string_by_addr() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let amx_plugin = Plugin::try_from(amxmod_bin).unwrap();
+
+        assert_eq!(Some("0.1".to_owned()), amx_plugin.read_string(0x38));
+        // Unaligned and out of DAT
+        assert_eq!(None, amx_plugin.read_string(0x39));
+        assert_eq!(None, amx_plugin.read_string(0x1000));
+    }
 }
@@ -0,0 +1,103 @@
+use std::ffi::CStr;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::error::AmxError;
+
+// Names of publics, natives, libraries, pubvars and tags. Starts with
+// maximum name length, table records point inside by file offset.
+pub struct NameTable<'a> {
+    // nametable..cod
+    bytes: &'a [u8],
+    // File offset of table
+    offset: usize,
+}
+
+const MAX_LENGTH_SIZE: usize = 2;
+
+impl<'a> NameTable<'a> {
+    pub fn new(bytes: &'a [u8], offset: usize) -> Result<NameTable<'a>, AmxError> {
+        if bytes.len() < MAX_LENGTH_SIZE {
+            return Err(AmxError::Malformed {
+                reason: "nametable is too short",
+                offset,
+            });
+        }
+        Ok(NameTable { bytes, offset })
+    }
+
+    // Longest name loader accepts, as compiler declared it
+    pub fn max_length(&self) -> u16 {
+        LittleEndian::read_u16(self.bytes)
+    }
+
+    // Zero terminated name at file offset
+    pub fn name_at(&self, offset: usize) -> Result<&'a CStr, AmxError> {
+        let bytes = offset
+            .checked_sub(self.offset)
+            .filter(|&start| start >= MAX_LENGTH_SIZE)
+            .and_then(|start| self.bytes.get(start..))
+            .ok_or(AmxError::Malformed {
+                reason: "name offset outside nametable",
+                offset,
+            })?;
+        let end = bytes
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(AmxError::Malformed {
+                reason: "unterminated name",
+                offset,
+            })?;
+
+        Ok(CStr::from_bytes_with_nul(&bytes[..=end]).unwrap())
+    }
+}
+
+// Zero terminated name stored in table record itself, `offset` is file
+// offset of `bytes`
+pub(crate) fn inline_name(bytes: &[u8], offset: usize) -> Result<&CStr, AmxError> {
+    let end = bytes
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(AmxError::Malformed {
+            reason: "unterminated name",
+            offset,
+        })?;
+    Ok(CStr::from_bytes_with_nul(&bytes[..=end]).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use super::{inline_name, NameTable};
+
+    #[test]
+    fn it_read_names_by_file_offset() {
+        let table = NameTable::new(b"\x1F\x00one\0two\0bad", 0x40).unwrap();
+
+        assert_eq!(
+            table.name_at(0x42).unwrap(),
+            CString::new("one").unwrap().as_c_str()
+        );
+        assert_eq!(
+            table.name_at(0x46).unwrap(),
+            CString::new("two").unwrap().as_c_str()
+        );
+        assert!(table.name_at(0x40).is_err());
+        assert!(table.name_at(0x4A).is_err());
+        assert!(table.name_at(0x100).is_err());
+        assert!(table.name_at(0).is_err());
+    }
+
+    #[test]
+    fn it_read_inline_names() {
+        let record = b"plugin_init\0\0\0\0\0\0\0\0\0";
+
+        assert_eq!(
+            inline_name(record, 0x3C).unwrap(),
+            CString::new("plugin_init").unwrap().as_c_str()
+        );
+        assert!(inline_name(b"plugin_init", 0x3C).is_err());
+    }
+}
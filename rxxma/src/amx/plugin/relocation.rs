@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use byteorder::{ByteOrder, LittleEndian};
+use log::trace;
+
+use super::super::OpcodeType::*;
+use super::super::{Opcode, OpcodeType, OperandKind};
+use super::{AmxFlags, Plugin};
+use crate::error::AmxError;
+
+// Header flags field
+const FLAGS: usize = 8;
+
+// Cod address of operand cell converted to absolute address by relocation,
+// together with opcode it has to point at (None for any instruction)
+fn relocated_operand(opcode: &Opcode, cellsize: usize) -> Option<(usize, Option<OpcodeType>)> {
+    let expected = match opcode.code.info().operand {
+        OperandKind::Function => Some(OP_PROC),
+        OperandKind::CaseTable => Some(OP_CASETBL),
+        // JREL is relative, relocation leaves it alone
+        OperandKind::Jump if opcode.code == OP_JREL => return None,
+        OperandKind::Jump | OperandKind::Branch => None,
+        _ => return None,
+    };
+    // Case table records are the jump cell themselves
+    let cell = if opcode.code.params() == 0 {
+        opcode.address
+    } else {
+        opcode.address + cellsize
+    };
+    Some((cell, expected))
+}
+
+impl Plugin<'_> {
+    // Converts absolute jump and call operands of image dumped from memory
+    // (RELOC flag) back to cod relative values. Image dumped while AMX was
+    // still relocating (BROWSE flag) has only some operands absolute.
+    pub(crate) fn derelocate(&mut self) -> Result<(), AmxError> {
+        let browsing = !self.flags.contains(AmxFlags::RELOC);
+        let opcodes = self.opcodes()?;
+        // Instruction at address, every operand of every candidate base is
+        // looked up
+        let instructions: HashMap<usize, OpcodeType> = opcodes
+            .iter()
+            .filter(|o| !o.code.is_pseudo())
+            .map(|o| (o.address, o.code))
+            .collect();
+        let is_target = |target: u32, expected: Option<OpcodeType>| {
+            instructions
+                .get(&(target as usize))
+                .is_some_and(|&code| expected.is_none_or(|e| code == e))
+        };
+        let operands: Vec<(usize, Option<OpcodeType>, u32)> = opcodes
+            .iter()
+            .filter_map(|o| relocated_operand(o, self.cellsize))
+            .map(|(cell, expected)| {
+                let value = self.read_cell(&self.bin[self.cod + cell..]) as u32;
+                (cell, expected, value)
+            })
+            // Operands relocation has not reached yet
+            .filter(|&(_, expected, value)| !(browsing && is_target(value, expected)))
+            .collect();
+
+        let is_valid = |base: u32| {
+            operands
+                .iter()
+                .all(|&(_, expected, value)| is_target(value.wrapping_sub(base), expected))
+        };
+
+        // Code base is a difference between first operand and some
+        // instruction, search stops once the second valid one shows up
+        let base = match operands.first() {
+            Some(&(_, _, value)) => {
+                let candidates: Vec<u32> = opcodes
+                    .iter()
+                    .filter(|o| !o.code.is_pseudo() && o.address as u32 <= value)
+                    .map(|o| value - o.address as u32)
+                    .filter(|&base| is_valid(base))
+                    .take(2)
+                    .collect();
+                match candidates.as_slice() {
+                    [base] => *base,
+                    [] => return Err(AmxError::NoCodeBase),
+                    _ => return Err(AmxError::AmbiguousCodeBase),
+                }
+            }
+            None => 0,
+        };
+        trace!("code base:\t0x{:X}", base);
+
+        for &(cell, _, value) in operands.iter() {
+            let at = self.cod + cell;
+            let relative = value.wrapping_sub(base);
+            if self.cellsize == 8 {
+                LittleEndian::write_u64(&mut self.bin.to_mut()[at..], u64::from(relative));
+            } else {
+                LittleEndian::write_u32(&mut self.bin.to_mut()[at..], relative);
+            }
+        }
+        self.flags.remove(AmxFlags::RELOC | AmxFlags::BROWSE);
+        LittleEndian::write_u16(&mut self.bin.to_mut()[FLAGS..], self.flags.bits());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use byteorder::{ByteOrder, LittleEndian};
+
+    use super::super::Plugin;
+    use super::relocated_operand;
+    use crate::amx::OpcodeType::*;
+    use crate::util::tests::PluginBuilder;
+
+    fn build_plugin() -> Vec<u8> {
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init").op(OP_PROC);
+        let call = builder.here();
+        builder.op_param(OP_PUSH_C, 0).op_param(OP_CALL, 0);
+        let jump = builder.here();
+        builder.op_param(OP_JZER, 0).op(OP_ZERO_PRI).op(OP_RETN);
+        let stock = builder.here();
+        builder
+            .op(OP_PROC)
+            .op_param(OP_JUMP, stock + 12)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        builder.patch(call + 12, stock).patch(jump + 4, stock - 8);
+
+        builder.build()
+    }
+
+    // What loading image into server memory at `base` does
+    fn relocate(bin: &[u8], base: u32) -> Vec<u8> {
+        let plugin = Plugin::try_from(bin.to_vec()).unwrap();
+        let mut relocated = bin.to_vec();
+        for (cell, _) in plugin
+            .opcodes()
+            .unwrap()
+            .iter()
+            .filter_map(|o| relocated_operand(o, 4))
+        {
+            let at = plugin.cod + cell;
+            let value = LittleEndian::read_u32(&relocated[at..]);
+            LittleEndian::write_u32(&mut relocated[at..], value + base);
+        }
+        relocated[9] |= 0x80;
+
+        relocated
+    }
+
+    #[test]
+    fn it_derelocate_operands() {
+        let bin = build_plugin();
+        let relocated = Plugin::try_from(relocate(&bin, 0x0804_8000)).unwrap();
+        let original = Plugin::try_from(bin).unwrap();
+
+        assert_eq!(relocated.opcodes().unwrap(), original.opcodes().unwrap());
+        assert_eq!(relocated, original);
+    }
+
+    #[test]
+    fn it_derelocate_partially_relocated_image() {
+        let bin = build_plugin();
+        // Relocation stopped before JUMP of the stock, its operand is left
+        // relative
+        let mut browsed = relocate(&bin, 0x0804_8000);
+        let cod = LittleEndian::read_u32(&bin[12..]) as usize;
+        browsed[cod + 0x34..cod + 0x38].copy_from_slice(&bin[cod + 0x34..cod + 0x38]);
+        browsed[9] = 0x40;
+        let original = Plugin::try_from(bin).unwrap();
+
+        assert_eq!(Plugin::try_from(browsed).unwrap(), original);
+    }
+
+    #[test]
+    fn it_keep_image_when_base_is_unknown() {
+        let mut relocated = relocate(&build_plugin(), 0x0804_8000);
+        // CALL operand points nowhere for any base
+        let cod = LittleEndian::read_u32(&relocated[12..]) as usize;
+        LittleEndian::write_u32(&mut relocated[cod + 0x18..], 3);
+        let mut amxmod_plugin = Plugin::try_from(relocated).unwrap();
+
+        assert!(amxmod_plugin.derelocate().is_err());
+        assert!(amxmod_plugin.flags.contains(super::AmxFlags::RELOC));
+    }
+}
@@ -0,0 +1,166 @@
+// Zero terminated strings found in DAT, strings(1) alike. Unpacked strings
+// keep one character per cell, packed ones keep cellsize characters per cell
+// starting from the highest byte.
+
+use super::Plugin;
+use crate::error::AmxError;
+
+// Shorter strings are too likely to be plain numbers
+const MIN_UNPACKED_LENGTH: usize = 2;
+const MIN_PACKED_LENGTH: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatString {
+    // DAT address of first cell
+    pub address: usize,
+    // Bytes taken in DAT, terminator included
+    pub size: usize,
+    pub packed: bool,
+    pub bytes: Vec<u8>,
+}
+
+impl DatString {
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).into_owned()
+    }
+}
+
+// Printable characters of any single byte encoding and whitespace
+fn is_text(c: u64) -> bool {
+    (0x20..0x100).contains(&c) || c == 0x09 || c == 0x0A || c == 0x0D
+}
+
+impl Plugin<'_> {
+    // Unpacked string at cell index, or cell index text run ends at
+    fn unpacked_string(&self, cells: &[u64], start: usize) -> Result<DatString, usize> {
+        let end = cells[start..]
+            .iter()
+            .position(|&c| !is_text(c))
+            .map_or(cells.len(), |p| start + p);
+
+        if end - start < MIN_UNPACKED_LENGTH || cells.get(end) != Some(&0) {
+            return Err(end);
+        }
+
+        Ok(DatString {
+            address: start * self.cellsize,
+            size: (end + 1 - start) * self.cellsize,
+            packed: false,
+            bytes: cells[start..end].iter().map(|&c| c as u8).collect(),
+        })
+    }
+
+    fn packed_string(&self, cells: &[u64], start: usize) -> Option<DatString> {
+        let mut bytes = vec![];
+        for (i, &cell) in cells[start..].iter().enumerate() {
+            let cell_bytes = &cell.to_be_bytes()[8 - self.cellsize..];
+            match cell_bytes.iter().position(|&b| b == 0) {
+                Some(terminator) => {
+                    let (text, rest) = cell_bytes.split_at(terminator);
+                    if !text.iter().all(|&b| is_text(u64::from(b))) || rest.iter().any(|&b| b != 0)
+                    {
+                        return None;
+                    }
+                    bytes.extend_from_slice(text);
+                    if bytes.len() < MIN_PACKED_LENGTH {
+                        return None;
+                    }
+
+                    return Some(DatString {
+                        address: start * self.cellsize,
+                        size: (i + 1) * self.cellsize,
+                        packed: true,
+                        bytes,
+                    });
+                }
+                None if cell_bytes.iter().all(|&b| is_text(u64::from(b))) => {
+                    bytes.extend_from_slice(cell_bytes)
+                }
+                None => return None,
+            }
+        }
+
+        None
+    }
+
+    // Packed and unpacked zero terminated strings of DAT in address order
+    pub fn strings(&self) -> Result<Vec<DatString>, AmxError> {
+        let cells: Vec<u64> = self
+            .dat_slice()?
+            .chunks_exact(self.cellsize)
+            .map(|cell| self.read_cell(cell))
+            .collect();
+
+        let mut strings = vec![];
+        let mut i = 0;
+        while i < cells.len() {
+            match self.unpacked_string(&cells, i) {
+                Ok(string) => {
+                    i += string.size / self.cellsize;
+                    strings.push(string);
+                }
+                // Cells of text run have no packed strings inside
+                Err(end) if end > i => i = end,
+                Err(_) => match self.packed_string(&cells, i) {
+                    Some(string) => {
+                        i += string.size / self.cellsize;
+                        strings.push(string);
+                    }
+                    None => i += 1,
+                },
+            }
+        }
+
+        Ok(strings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::super::Plugin;
+    use crate::amx::OpcodeType::*;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    #[test]
+    fn it_extract_unpacked_strings() {
+        let amxmod_plugin = Plugin::try_from(load_fixture("simple.amx183")).unwrap();
+        let strings: Vec<(usize, String)> = amxmod_plugin
+            .strings()
+            .unwrap()
+            .iter()
+            .map(|s| (s.address, s.to_string_lossy()))
+            .collect();
+
+        assert_eq!(
+            strings,
+            [
+                (0x0, String::from("simple plugin")),
+                (0x38, String::from("0.1")),
+                (0x48, String::from("Fedcomp")),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_extract_packed_strings() {
+        let mut builder = PluginBuilder::new();
+        // "rcon_password" packed, then number and single character
+        let packed = builder.array(&[0x72636F6E, 0x5F706173, 0x73776F72, 0x64000000]);
+        builder.array(&[0x41424344, 0x41, 0]);
+        let unpacked = builder.string("ok");
+        builder.public("plugin_init").op(OP_PROC).op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let strings = amxmod_plugin.strings().unwrap();
+
+        assert_eq!(strings.len(), 2);
+        assert_eq!(strings[0].address, packed as usize);
+        assert_eq!(strings[0].size, 16);
+        assert!(strings[0].packed);
+        assert_eq!(strings[0].to_string_lossy(), "rcon_password");
+        assert_eq!(strings[1].address, unpacked as usize);
+        assert!(!strings[1].packed);
+    }
+}
@@ -1,148 +1,153 @@
-// TODO: Fix clippy error
-#![allow(clippy::enum_variant_names)]
-
+use std::borrow::Cow;
 use std::convert::{TryFrom, TryInto};
 use std::io::Cursor;
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use failure::{Error, ResultExt};
-use log::trace;
-
-use super::{Flags, Plugin, AMXMOD_MAGIC, AMX_VERSION, FILE_VERSION};
-
-#[derive(Debug, Fail)]
-enum AmxParseError {
-    #[fail(display = "Invalid amx magic, expected: 0x{:X}, got: 0x{:X}", _0, _1)]
-    InvalidMagic(u16, u16),
-    #[fail(display = "Invalid file version, expected: {}, got: {}", _0, _1)]
-    InvalidFileVersion(u8, u8),
-    #[fail(display = "Invalid amx version, expected: {}, got: {}", _0, _1)]
-    InvalidAmxVersion(u8, u8),
-    #[fail(
-        display = "Invalid bit value for amx flags (contains unknown flags) {}",
-        _0
-    )]
-    InvalidAmxFlags(u16),
-}
+use log::warn;
 
-impl TryFrom<Vec<u8>> for Plugin {
-    type Error = Error;
+use super::{
+    byte_order, compact, AmxFlags, Plugin, AMXMOD_MAGIC, AMX_VERSION, FILE_VERSION,
+    INLINE_NAME_SIZE, MIN_FILE_VERSION,
+};
+use crate::error::{read_at, AmxError};
+use crate::parse_trace::{ParseTrace, Tracer};
+
+impl TryFrom<Vec<u8>> for Plugin<'static> {
+    type Error = AmxError;
 
     fn try_from(bin: Vec<u8>) -> Result<Self, Self::Error> {
-        let mut reader = Cursor::new(&bin);
+        Plugin::parse(Cow::Owned(bin), None)
+    }
+}
 
-        {
-            let size = reader
-                .read_u32::<LittleEndian>()
-                .context("EOF on amx size")?;
-            trace!("size:\t{}", size);
-        }
+// Borrows image, nothing is copied until plugin is patched
+impl<'a> TryFrom<&'a [u8]> for Plugin<'a> {
+    type Error = AmxError;
+
+    fn try_from(bin: &'a [u8]) -> Result<Self, Self::Error> {
+        Plugin::parse(Cow::Borrowed(bin), None)
+    }
+}
+
+impl<'a> Plugin<'a> {
+    // Like `try_from`, collecting header fields into `trace`
+    pub fn parse_traced(bin: &'a [u8], trace: &mut ParseTrace) -> Result<Plugin<'a>, AmxError> {
+        Plugin::parse(Cow::Borrowed(bin), Some(trace))
+    }
+
+    fn parse(bin: Cow<'a, [u8]>, trace: Option<&mut ParseTrace>) -> Result<Plugin<'a>, AmxError> {
+        // Big endian image is converted once, the rest reads little endian
+        let bin = match byte_order::is_big_endian(&bin) {
+            true => Cow::Owned(byte_order::to_little_endian(&bin)?),
+            false => bin,
+        };
+        let mut reader = Cursor::new(&bin[..]);
+        let mut tracer = Tracer::new(trace);
+
+        let size = read_at(&mut reader, "amx size", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("size", &reader, size);
 
         // Magic
         {
             // TODO: test
-            let magic = reader
-                .read_u16::<LittleEndian>()
-                .context("EOF on amx magic")?;
+            let magic = read_at(&mut reader, "amx magic", |r| r.read_u16::<LittleEndian>())?;
             if magic != AMXMOD_MAGIC {
-                Err(AmxParseError::InvalidMagic(AMXMOD_MAGIC, magic))?;
+                return Err(AmxError::InvalidAmxMagic(AMXMOD_MAGIC, magic));
             }
-            trace!("magic:\t0x{:X}", magic);
+            tracer.field("magic", &reader, magic);
         }
 
         // File version
         {
             // TODO: test
-            let file_version = reader.read_u8().context("EOF on amx file version")?;
-            if file_version != FILE_VERSION {
-                Err(AmxParseError::InvalidFileVersion(
-                    FILE_VERSION,
-                    file_version,
-                ))?;
+            let file_version = read_at(&mut reader, "amx file version", |r| r.read_u8())?;
+            if !(MIN_FILE_VERSION..=FILE_VERSION).contains(&file_version) {
+                return Err(AmxError::InvalidAmxFileVersion(FILE_VERSION, file_version));
             }
-            trace!("file version {}", file_version);
+            tracer.field("file version", &reader, file_version);
         }
 
         // Amx version
         {
-            // TODO: Test incorrect
-            let amx_version = reader.read_u8().context("EOF on amx version")?;
-            if amx_version != AMX_VERSION {
-                Err(AmxParseError::InvalidAmxVersion(AMX_VERSION, amx_version))?;
+            // Oldest abstract machine able to run plugin
+            let amx_version = read_at(&mut reader, "amx version", |r| r.read_u8())?;
+            if amx_version > AMX_VERSION {
+                return Err(AmxError::InvalidAmxVersion(AMX_VERSION, amx_version));
             }
-            trace!("amx version:\t{}", amx_version);
+            tracer.field("amx version", &reader, amx_version);
         }
 
-        // TODO: Parse flags
-        let flags = reader
-            .read_u16::<LittleEndian>()
-            .context("EOF on amx flags")?;
-        trace!("raw flags:\t0x{:X}", flags);
-
-        let flags = Flags::from_bits(flags).ok_or_else(|| AmxParseError::InvalidAmxFlags(flags))?;
-        trace!("parsed flags:\t{:?}", flags);
-
-        let defsize = reader
-            .read_u16::<LittleEndian>()
-            .context("EOF on amx defsize")?;
-        trace!("defsize:\t{}", defsize);
-
-        let cod = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx cod")?;
-        trace!("cod:\t0x{:X}", cod);
-
-        let dat = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx dat")?;
-        trace!("dat:\t0x{:X}", dat);
-
-        let hea = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx hea")?;
-        trace!("hea:\t0x{:X}", hea);
-
-        let stp = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx stp")?;
-        trace!("stp:\t0x{:X}", stp);
-
-        let cip = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx cip")?;
-        trace!("cip:\t0x{:X}", cip);
-
-        let publics = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx publics")?;
-        trace!("publics:\t0x{:X}", publics);
-
-        let natives = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx natives")?;
-        trace!("natives:\t0x{:X}", natives);
-
-        let libraries = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx libraries")?;
-        trace!("libraries:\t0x{:X}", libraries);
-
-        let pubvars = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx pubvars")?;
-        trace!("pubvars:\t0x{:X}", pubvars);
-
-        let tags = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx tags")?;
-        trace!("tags:\t0x{:X}", tags);
-
-        let nametable = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on amx nametable")?;
-        trace!("nametable:\t0x{:X}", nametable);
-
-        Ok(Plugin {
+        let flags = read_at(&mut reader, "amx flags", |r| r.read_u16::<LittleEndian>())?;
+        tracer.field("flags", &reader, flags);
+
+        let mut flags = AmxFlags::from_bits(flags).ok_or(AmxError::InvalidAmxFlags(flags))?;
+
+        let defsize = read_at(&mut reader, "amx defsize", |r| r.read_u16::<LittleEndian>())?;
+        tracer.field("defsize", &reader, defsize);
+        // Public and native records hold cell sized address and name offset,
+        // or name itself in older file versions
+        let cellsize = match defsize as usize {
+            8 => 4,
+            16 => 8,
+            size if size == 4 + INLINE_NAME_SIZE => 4,
+            size if size == 8 + INLINE_NAME_SIZE => 8,
+            _ => return Err(AmxError::InvalidDefsize(defsize)),
+        };
+        let inline_names = defsize as usize == cellsize + INLINE_NAME_SIZE;
+
+        let cod = read_at(&mut reader, "amx cod", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("cod", &reader, cod);
+
+        let dat = read_at(&mut reader, "amx dat", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("dat", &reader, dat);
+
+        let hea = read_at(&mut reader, "amx hea", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("hea", &reader, hea);
+
+        let stp = read_at(&mut reader, "amx stp", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("stp", &reader, stp);
+
+        let cip = read_at(&mut reader, "amx cip", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("cip", &reader, cip);
+
+        let publics = read_at(&mut reader, "amx publics", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("publics", &reader, publics);
+
+        let natives = read_at(&mut reader, "amx natives", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("natives", &reader, natives);
+
+        let libraries = read_at(&mut reader, "amx libraries", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        tracer.field("libraries", &reader, libraries);
+
+        let pubvars = read_at(&mut reader, "amx pubvars", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("pubvars", &reader, pubvars);
+
+        let tags = read_at(&mut reader, "amx tags", |r| r.read_u32::<LittleEndian>())?;
+        tracer.field("tags", &reader, tags);
+
+        let nametable = read_at(&mut reader, "amx nametable", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        tracer.field("nametable", &reader, nametable);
+
+        // Header is never compact, offsets are the ones of expanded image
+        let bin = match flags.contains(AmxFlags::COMPACT) {
+            true => {
+                flags.remove(AmxFlags::COMPACT);
+                Cow::Owned(compact::expand(
+                    &bin,
+                    size as usize,
+                    cod as usize,
+                    hea as usize,
+                    cellsize,
+                )?)
+            }
+            false => bin,
+        };
+
+        let mut plugin = Plugin {
             flags,
             defsize,
             cod: cod.try_into().unwrap(),
@@ -155,24 +160,40 @@ impl TryFrom<Vec<u8>> for Plugin {
             libraries: libraries.try_into().unwrap(),
             pubvars: pubvars.try_into().unwrap(),
             tags: tags.try_into().unwrap(),
-            nametable: nametable.try_into().unwrap(),
-            bin: bin.clone(),
-        })
+            // Field is unused, tags table ends where cod starts
+            nametable: match inline_names {
+                true => cod.try_into().unwrap(),
+                false => nametable.try_into().unwrap(),
+            },
+            cellsize,
+            bin,
+        };
+        plugin.check_layout()?;
+
+        // Image dumped from memory, jump and call operands are absolute
+        if plugin.flags.intersects(AmxFlags::RELOC | AmxFlags::BROWSE) {
+            if let Err(e) = plugin.derelocate() {
+                warn!("Relocated image, jump targets left absolute: {}", e);
+            }
+        }
+
+        Ok(plugin)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::{Flags, Plugin};
+    use super::super::{AmxFlags, Plugin};
     use super::*;
-    use crate::util::tests::load_fixture;
+    use crate::amx::OpcodeType::*;
+    use crate::util::tests::{load_fixture, PluginBuilder};
 
     #[test]
     fn it_load_plugins_when_it_is_correct() {
         let amxmod_bin = load_fixture("simple.amx183");
         let extracted_plugin = Plugin::try_from(amxmod_bin.clone()).unwrap();
         let expected_plugin = Plugin {
-            flags: Flags::DEBUG,
+            flags: AmxFlags::DEBUG,
             defsize: 8,
             cod: 116,
             dat: 192,
@@ -185,8 +206,84 @@ mod tests {
             pubvars: 72,
             tags: 72,
             nametable: 80,
-            bin: amxmod_bin.to_vec(),
+            cellsize: 4,
+            bin: Cow::Owned(amxmod_bin.to_vec()),
         };
         assert_eq!(extracted_plugin, expected_plugin);
     }
+
+    #[test]
+    fn it_trace_header_fields() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let mut trace = ParseTrace::new();
+        let amxmod_plugin = Plugin::parse_traced(&amxmod_bin, &mut trace).unwrap();
+
+        assert_eq!(amxmod_plugin, Plugin::try_from(&amxmod_bin[..]).unwrap());
+        let fields: Vec<&str> = trace.entries.iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields[..4],
+            ["size", "magic", "file version", "amx version"]
+        );
+        assert_eq!(trace.entries.len(), 17);
+        let cod = trace.entry_at(13).unwrap();
+        assert_eq!(
+            (cod.field, cod.offset, cod.size, cod.value),
+            ("cod", 12, 4, 116)
+        );
+    }
+
+    #[test]
+    fn it_load_file_version_6_with_inline_names() {
+        let mut builder = PluginBuilder::new();
+        builder.inline_names();
+        let client_print = builder.native("client_print");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_SYSREQ_C, client_print)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(amxmod_plugin.file_version(), 6);
+        assert!(amxmod_plugin.inline_names());
+        assert_eq!(amxmod_plugin.cellsize(), 4);
+        let publics = amxmod_plugin.publics().unwrap();
+        assert_eq!(publics[0].name.to_str(), Ok("plugin_init"));
+        let natives = amxmod_plugin.natives().unwrap();
+        assert_eq!(natives[0].name.to_str(), Ok("client_print"));
+        assert_eq!(amxmod_plugin.validate(), []);
+        assert!(amxmod_plugin.verify().is_ok());
+    }
+
+    #[test]
+    fn it_reject_unknown_versions() {
+        let mut amxmod_bin = load_fixture("simple.amx183");
+        amxmod_bin[6] = 5;
+        assert_eq!(
+            Plugin::try_from(amxmod_bin.clone()),
+            Err(AmxError::InvalidAmxFileVersion(FILE_VERSION, 5))
+        );
+
+        amxmod_bin[6] = 7;
+        assert!(Plugin::try_from(amxmod_bin.clone()).is_ok());
+        amxmod_bin[7] = 9;
+        assert_eq!(
+            Plugin::try_from(amxmod_bin),
+            Err(AmxError::InvalidAmxVersion(AMX_VERSION, 9))
+        );
+    }
+
+    #[test]
+    fn it_borrow_image_until_patched() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let mut amxmod_plugin = Plugin::try_from(&amxmod_bin[..]).unwrap();
+        assert!(matches!(amxmod_plugin.bin, Cow::Borrowed(_)));
+        assert_eq!(amxmod_plugin, Plugin::try_from(amxmod_bin.clone()).unwrap());
+
+        amxmod_plugin.dat_slice_mut().unwrap()[0] = b'S';
+        assert!(matches!(amxmod_plugin.bin, Cow::Owned(_)));
+        assert_eq!(amxmod_bin[amxmod_plugin.dat], b's');
+    }
 }
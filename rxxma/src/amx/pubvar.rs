@@ -0,0 +1,16 @@
+use std::ffi::CString;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+// Variable declared `public`, address is inside DAT
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PubVar {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serialize::lossy_name")
+    )]
+    pub name: CString,
+    pub address: usize,
+}
@@ -0,0 +1,16 @@
+use std::ffi::CString;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+// Tag name with id the compiler gave it, flag bits included
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Tag {
+    pub id: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serialize::lossy_name")
+    )]
+    pub name: CString,
+}
@@ -0,0 +1,262 @@
+// Serializes plugin parts back into amx image. Layout follows amxxpc output:
+// header, publics, natives, libraries, pubvars and tags tables, nametable,
+// cod, dat and debug chunk. Opcodes are laid out one after another, jump
+// and call operands are written as they are.
+
+use std::ffi::CString;
+use std::io::Write;
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+use super::plugin::{AMXMOD_MAGIC, AMX_VERSION, FILE_VERSION};
+use super::OpcodeType::*;
+use super::{Native, Opcode, Plugin, PubVar, Public, Tag};
+use crate::error::AmxError;
+
+const HEADER_SIZE: usize = 56;
+// Header fields not exposed by Plugin
+const SIZE: usize = 0;
+const FLAGS: usize = 8;
+const CIP: usize = 28;
+
+// Cells opcode takes in cod
+pub(crate) fn opcode_cells(opcode: &Opcode) -> Result<Vec<u32>, AmxError> {
+    match opcode.code {
+        // Obsolete debug opcodes, only first operand is decoded
+        OP_FILE | OP_LINE | OP_SYMBOL | OP_SRANGE => Err(AmxError::Unassemblable {
+            code: opcode.code,
+            address: opcode.address,
+        }),
+        // Case table records and junk cells are bare operands
+        OP_CASENONE | OP_CASE | OP_CASEJMP | OP_UNKNOWN => Ok(vec![opcode.param.unwrap_or(0)]),
+        code if code == OP_CASETBL || code.params() > 0 => {
+            Ok(vec![code as u32, opcode.param.unwrap_or(0)])
+        }
+        code => Ok(vec![code as u32]),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Writer {
+    pub flags: u16,
+    // 4 or 8
+    pub cellsize: usize,
+    // Address of main(), 0xFFFFFFFF if there is none
+    pub cip: u32,
+    // Memory reserved for heap and stack after DAT
+    pub heap_budget: usize,
+    // First field of nametable
+    pub max_name_length: u16,
+    pub publics: Vec<Public>,
+    pub natives: Vec<Native>,
+    pub libraries: Vec<CString>,
+    pub pubvars: Vec<PubVar>,
+    pub tags: Vec<Tag>,
+    // Without two cells cod starts with, they are always written as HALT 0
+    pub opcodes: Vec<Opcode>,
+    pub dat: Vec<u8>,
+    // Appended after image as is
+    pub debug_info: Vec<u8>,
+}
+
+impl Writer {
+    pub fn from_plugin(plugin: &Plugin) -> Result<Writer, AmxError> {
+        let size = LittleEndian::read_u32(&plugin.bin[SIZE..]) as usize;
+
+        Ok(Writer {
+            flags: LittleEndian::read_u16(&plugin.bin[FLAGS..]),
+            cellsize: plugin.cellsize(),
+            cip: LittleEndian::read_u32(&plugin.bin[CIP..]),
+            heap_budget: plugin.heap_budget(),
+            max_name_length: plugin.name_table()?.max_length(),
+            publics: plugin.publics()?,
+            natives: plugin.natives()?,
+            libraries: plugin.libraries()?,
+            pubvars: plugin.pubvars()?,
+            tags: plugin.tags()?,
+            opcodes: plugin.opcodes()?,
+            dat: plugin.dat_slice()?.to_vec(),
+            debug_info: plugin.bin.get(size..).unwrap_or_default().to_vec(),
+        })
+    }
+
+    // Cells of opcodes, case tables included
+    fn cod(&self) -> Result<Vec<u8>, AmxError> {
+        let mut cells: Vec<u32> = vec![OP_HALT as u32, 0];
+        for opcode in self.opcodes.iter() {
+            cells.extend(opcode_cells(opcode)?);
+        }
+
+        let mut cod = Vec::with_capacity(cells.len() * self.cellsize);
+        for cell in cells {
+            self.write_cell(&mut cod, cell)?;
+        }
+        Ok(cod)
+    }
+
+    // Operands are decoded truncated to 32 bits, 64 bit cells get them
+    // sign extended
+    fn write_cell(&self, bin: &mut Vec<u8>, cell: u32) -> Result<(), AmxError> {
+        if self.cellsize == 8 {
+            bin.write_i64::<LittleEndian>(i64::from(cell as i32))?;
+        } else {
+            bin.write_u32::<LittleEndian>(cell)?;
+        }
+        Ok(())
+    }
+
+    pub fn write(&self) -> Result<Vec<u8>, AmxError> {
+        let records: Vec<(u32, &CString)> = self
+            .publics
+            .iter()
+            .map(|p| (p.address as u32, &p.name))
+            .chain(self.natives.iter().map(|n| (n.address as u32, &n.name)))
+            .chain(self.libraries.iter().map(|l| (0, l)))
+            .chain(self.pubvars.iter().map(|p| (p.address as u32, &p.name)))
+            .chain(self.tags.iter().map(|t| (t.id, &t.name)))
+            .collect();
+
+        // Cell sized value and name offset padded to cell
+        let defsize = 2 * self.cellsize;
+        let publics = HEADER_SIZE;
+        let natives = publics + self.publics.len() * defsize;
+        let libraries = natives + self.natives.len() * defsize;
+        let pubvars = libraries + self.libraries.len() * defsize;
+        let tags = pubvars + self.pubvars.len() * defsize;
+        let nametable = tags + self.tags.len() * defsize;
+
+        let mut names: Vec<u8> = vec![];
+        names.write_u16::<LittleEndian>(self.max_name_length)?;
+        let mut name_offsets = vec![];
+        for (_, name) in records.iter() {
+            name_offsets.push(nametable + names.len());
+            names.extend_from_slice(name.as_bytes_with_nul());
+        }
+        // Cod starts cell aligned
+        while !(nametable + names.len()).is_multiple_of(self.cellsize) {
+            names.push(0);
+        }
+
+        let code = self.cod()?;
+        let cod = nametable + names.len();
+        let dat = cod + code.len();
+        let hea = dat + self.dat.len();
+
+        let mut bin: Vec<u8> = Vec::with_capacity(hea + self.debug_info.len());
+        bin.write_u32::<LittleEndian>(hea as u32)?;
+        bin.write_u16::<LittleEndian>(AMXMOD_MAGIC)?;
+        bin.write_u8(FILE_VERSION)?;
+        bin.write_u8(AMX_VERSION)?;
+        bin.write_u16::<LittleEndian>(self.flags)?;
+        bin.write_u16::<LittleEndian>(defsize as u16)?;
+        for &value in &[
+            cod,
+            dat,
+            hea,
+            hea + self.heap_budget,
+            self.cip as usize,
+            publics,
+            natives,
+            libraries,
+            pubvars,
+            tags,
+            nametable,
+        ] {
+            bin.write_u32::<LittleEndian>(value as u32)?;
+        }
+
+        for ((value, _), name_offset) in records.iter().zip(name_offsets) {
+            self.write_cell(&mut bin, *value)?;
+            bin.write_u32::<LittleEndian>(name_offset as u32)?;
+            bin.write_all(&vec![0; self.cellsize - 4])?;
+        }
+        bin.extend(names);
+        bin.extend(code);
+        bin.extend_from_slice(&self.dat);
+        bin.extend_from_slice(&self.debug_info);
+
+        Ok(bin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::Writer;
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::amxx::File;
+    use crate::error::AmxError;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    #[test]
+    fn it_write_plugin_as_compiler_did() {
+        for fixture in &[
+            "simple.amx183",
+            "two_natives.amx183",
+            "cell_constants.amx183",
+        ] {
+            let amxmod_bin = load_fixture(fixture);
+            let plugin = Plugin::try_from(amxmod_bin.clone()).unwrap();
+
+            assert_eq!(
+                Writer::from_plugin(&plugin).unwrap().write().unwrap(),
+                amxmod_bin
+            );
+        }
+    }
+
+    #[test]
+    fn it_write_64_bit_plugin() {
+        let amxmodx_file = File::try_from(load_fixture("simple.amxx181")).unwrap();
+        let section = &amxmodx_file.sections().unwrap()[1];
+        let plugin = section.unpack_plugin().unwrap();
+
+        assert_eq!(plugin.cellsize(), 8);
+        assert_eq!(
+            Writer::from_plugin(&plugin).unwrap().write().unwrap(),
+            section.unpack_section().unwrap()
+        );
+    }
+
+    #[test]
+    fn it_write_case_table_and_patched_operand() {
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init").op(OP_PROC);
+        let switch = builder.here();
+        builder.op_param(OP_SWITCH, 0);
+        let table = builder.here();
+        builder
+            .patch(switch + 4, table)
+            .op_param(OP_CASETBL, 1)
+            .cells(&[table + 20, 7, table + 20])
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut writer = Writer::from_plugin(&plugin).unwrap();
+        assert_eq!(writer.write().unwrap(), plugin.to_bytes());
+
+        writer.opcodes[4].param = Some(8);
+        let written = Plugin::try_from(writer.write().unwrap()).unwrap();
+        let opcodes = written.opcodes().unwrap();
+        assert_eq!(opcodes[4].code, OP_CASE);
+        assert_eq!(opcodes[4].param, Some(8));
+        assert_eq!(opcodes, writer.opcodes);
+    }
+
+    #[test]
+    fn it_err_on_obsolete_debug_opcode() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let mut writer = Writer::from_plugin(&Plugin::try_from(amxmod_bin).unwrap()).unwrap();
+        writer.opcodes[0].code = OP_LINE;
+
+        assert_eq!(
+            writer.write(),
+            Err(AmxError::Unassemblable {
+                code: OP_LINE,
+                address: 8
+            })
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use std::ops::Deref;
+
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+
+// Container bytes, either read into memory or mapped from disk
+#[derive(Debug)]
+pub enum Contents {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(Mmap),
+}
+
+impl Contents {
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            Contents::Owned(bin) => bin,
+            #[cfg(feature = "mmap")]
+            Contents::Mapped(map) => map.to_vec(),
+        }
+    }
+}
+
+impl Deref for Contents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Contents::Owned(bin) => bin,
+            #[cfg(feature = "mmap")]
+            Contents::Mapped(map) => map,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Contents {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<Vec<u8>> for Contents {
+    fn from(bin: Vec<u8>) -> Contents {
+        Contents::Owned(bin)
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::{Contents, File, Version, LEGACY_MAGIC};
+use crate::error::{read_to, AmxError};
+
+const MAGIC_SIZE: usize = 4;
+// Section table entry fields
+const ENTRY_DISKSIZE: usize = 1;
+const ENTRY_OFFSET: usize = 13;
+
+impl File {
+    // Parses container starting at current stream position, reading header
+    // and section table first and then only as many bytes as sections
+    // span. Stream is left right after the container, so containers
+    // embedded into archives or concatenated streams are read exactly.
+    // Legacy containers last until stream end.
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<File, AmxError> {
+        let mut bin = vec![];
+        read_to(reader, &mut bin, MAGIC_SIZE, "file magic")?;
+        let version = match LittleEndian::read_u32(&bin) {
+            LEGACY_MAGIC => Version::Legacy,
+            _ => Version::V3,
+        };
+        read_to(reader, &mut bin, version.header_size(), "file header")?;
+        let header = File::parse(Contents::Owned(bin), None)?;
+
+        let mut bin = header.bin.into_vec();
+        let table_end = version.header_size() + version.entry_size() * header.sections as usize;
+        read_to(reader, &mut bin, table_end, "section table")?;
+
+        let end = match version {
+            Version::V3 => {
+                let mut end = table_end;
+                for i in 0..header.sections as usize {
+                    let entry = version.header_size() + version.entry_size() * i;
+                    let disksize = LittleEndian::read_u32(&bin[entry + ENTRY_DISKSIZE..]);
+                    let offset = LittleEndian::read_u32(&bin[entry + ENTRY_OFFSET..]);
+                    end = end.max(offset as usize + disksize as usize);
+                }
+                end
+            }
+            Version::Legacy => {
+                let position = reader.stream_position()?;
+                let end = reader.seek(SeekFrom::End(0))?;
+                reader.seek(SeekFrom::Start(position))?;
+                table_end + (end.saturating_sub(position)) as usize
+            }
+        };
+        read_to(reader, &mut bin, end, "section contents")?;
+
+        File::parse(Contents::Owned(bin), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::io::{Cursor, Read};
+
+    use super::File;
+    use crate::error::AmxError;
+    use crate::util::tests::load_fixture;
+
+    #[test]
+    fn it_read_container_from_stream() {
+        let amxmodx_bin = load_fixture("simple.amxx181");
+        // Container stored after other data and followed by another file
+        let mut stream = b"header".to_vec();
+        stream.extend_from_slice(&amxmodx_bin);
+        stream.extend_from_slice(b"trailer");
+        let mut reader = Cursor::new(stream);
+        reader.set_position(6);
+
+        let amxmodx_file = File::from_reader(&mut reader).unwrap();
+        let expected = File::try_from(amxmodx_bin).unwrap();
+        assert_eq!(&amxmodx_file.bin[..], &expected.bin[..]);
+        assert_eq!(
+            amxmodx_file.sections().unwrap(),
+            expected.sections().unwrap()
+        );
+
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "trailer");
+    }
+
+    #[test]
+    fn it_err_on_truncated_stream() {
+        let amxmodx_bin = load_fixture("simple.amxx183");
+        let truncated = amxmodx_bin[..amxmodx_bin.len() - 1].to_vec();
+
+        assert!(matches!(
+            File::from_reader(&mut Cursor::new(truncated)),
+            Err(AmxError::Eof {
+                what: "section contents",
+                ..
+            })
+        ));
+        assert!(matches!(
+            File::from_reader(&mut Cursor::new(b"XXMA".to_vec())),
+            Err(AmxError::Eof {
+                what: "file header",
+                ..
+            })
+        ));
+    }
+}
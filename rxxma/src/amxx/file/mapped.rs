@@ -0,0 +1,43 @@
+use std::fs::File as IoFile;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use super::{Contents, File};
+use crate::error::AmxError;
+
+impl File {
+    // Parses container straight from memory mapped file, only pages of header
+    // and sections actually read get loaded. File must not be modified while
+    // mapped.
+    pub fn open_mapped<P: AsRef<Path>>(path: P) -> Result<File, AmxError> {
+        let file = IoFile::open(path)?;
+        // Safety: mapping is read only, truncating file underneath is caller
+        // responsibility
+        let map = unsafe { Mmap::map(&file)? };
+
+        File::parse(Contents::Mapped(map), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::File;
+    use crate::amxx::file::Contents;
+    use crate::util::tests::load_fixture;
+
+    #[test]
+    fn it_open_mapped_file() {
+        let amxmodx_file = File::open_mapped("test/fixtures/simple.amxx183").unwrap();
+        assert!(matches!(amxmodx_file.bin, Contents::Mapped(_)));
+
+        let expected = File::try_from(load_fixture("simple.amxx183")).unwrap();
+        assert_eq!(
+            amxmodx_file.sections().unwrap(),
+            expected.sections().unwrap()
+        );
+        assert!(File::open_mapped("test/fixtures/unexistent").is_err());
+    }
+}
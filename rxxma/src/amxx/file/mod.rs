@@ -1,16 +1,64 @@
+mod contents;
+mod from_reader;
+#[cfg(feature = "mmap")]
+mod mapped;
+mod pack;
 mod sections;
+#[cfg(feature = "fs")]
 mod try_from_file;
 mod try_from_vec_u8;
 
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+pub use self::contents::Contents;
+use super::Section;
+
 // TODO: `core::num::<impl u32>::from_be_bytes` is not yet stable as a const fn
 // const MAGIC: u32 = u32::from_be_bytes(*b"XXMA");
 #[allow(clippy::unreadable_literal)]
-const MAGIC: u32 = 0x414d5858;
-const COMPATIBLE_VERSION: u16 = 768;
-const AMXX_HEADER_SIZE: usize = 7;
+pub(crate) const MAGIC: u32 = 0x414d5858;
+pub(crate) const COMPATIBLE_VERSION: u16 = 768;
+pub(crate) const AMXX_HEADER_SIZE: usize = 7;
+// Magic of containers older than versioned header, "BXMA"
+#[allow(clippy::unreadable_literal)]
+pub(crate) const LEGACY_MAGIC: u32 = 0x414d5842;
+const LEGACY_HEADER_SIZE: usize = 5;
+const LEGACY_ENTRY_SIZE: usize = 9;
+
+// Container layout, decides header and section table entry format
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Version {
+    // Legacy magic without version field (0x0200 era). Entry holds cellsize,
+    // memsize and offset, section contents last until the next section.
+    Legacy,
+    // 0x0300 written by amxxpc 1.x. Entry holds cellsize, disksize,
+    // imagesize, memsize and offset.
+    V3,
+}
+
+impl Version {
+    pub fn header_size(self) -> usize {
+        match self {
+            Version::Legacy => LEGACY_HEADER_SIZE,
+            Version::V3 => AMXX_HEADER_SIZE,
+        }
+    }
+
+    pub fn entry_size(self) -> usize {
+        match self {
+            Version::Legacy => LEGACY_ENTRY_SIZE,
+            Version::V3 => Section::SIZE,
+        }
+    }
+}
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct File {
-    pub bin: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub bin: Contents,
+    pub version: Version,
     pub sections: u8,
 }
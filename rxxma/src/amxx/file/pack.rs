@@ -0,0 +1,36 @@
+use super::super::Writer;
+use super::File;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+impl File {
+    // Single section container holding compressed plugin image
+    pub fn pack(plugin: &Plugin) -> Result<File, AmxError> {
+        Writer::new().section(plugin).write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::File;
+    use crate::amx::Plugin;
+    use crate::util::tests::load_fixture;
+
+    #[test]
+    fn it_pack_plugin_into_single_section() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+        let packed = File::pack(&amxmod_plugin).unwrap();
+        let sections = File::try_from(packed.bin.into_vec())
+            .unwrap()
+            .sections()
+            .unwrap();
+
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].cellsize, 4);
+        assert_eq!(sections[0].memsize, 16680);
+        assert_eq!(sections[0].unpack_plugin().unwrap(), amxmod_plugin);
+    }
+}
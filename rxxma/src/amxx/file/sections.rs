@@ -1,29 +1,57 @@
-use failure::Error;
-use log::trace;
+use byteorder::{ByteOrder, LittleEndian};
 
 use super::super::Section;
-use super::File;
-use super::AMXX_HEADER_SIZE;
+use super::{File, Version};
+use crate::error::AmxError;
+use crate::parse_trace::ParseTrace;
+
+// Offset field of legacy section table entry
+const LEGACY_ENTRY_OFFSET: usize = 5;
 
 impl File {
-    pub fn sections(&self) -> Result<Vec<Section>, Error> {
+    pub fn sections(&self) -> Result<Vec<Section>, AmxError> {
+        self.read_sections(None)
+    }
+
+    // Like `sections`, collecting section table fields into `trace`
+    pub fn sections_traced(&self, trace: &mut ParseTrace) -> Result<Vec<Section>, AmxError> {
+        self.read_sections(Some(trace))
+    }
+
+    fn read_sections(&self, mut trace: Option<&mut ParseTrace>) -> Result<Vec<Section>, AmxError> {
         let mut sections: Vec<Section> = vec![];
 
-        for i in 0..self.sections {
-            trace!("---------------");
-            trace!("Reading section {}", i + 1);
-            let section_offset = AMXX_HEADER_SIZE + (Section::SIZE * i as usize);
-            // TODO: Fix panic
-            // let section_bin = &self.bin[section_offset..];
-            let section = match Section::from(&self.bin, section_offset) {
-                Ok(s) => s,
-                Err(e) => return Err(e),
+        for i in 0..self.sections as usize {
+            let section_offset = self.version.header_size() + self.version.entry_size() * i;
+            let trace = trace.as_deref_mut();
+            let section = match self.version {
+                Version::V3 => Section::read(&self.bin, section_offset, trace)?,
+                Version::Legacy => {
+                    let end = self.legacy_end(i)?;
+                    Section::read_legacy(&self.bin, section_offset, end, trace)?
+                }
             };
             sections.push(section);
         }
 
         Ok(sections)
     }
+
+    // Legacy section contents end where the next section starts
+    fn legacy_end(&self, i: usize) -> Result<usize, AmxError> {
+        if i + 1 == self.sections as usize {
+            return Ok(self.bin.len());
+        }
+
+        let next = self.version.header_size() + self.version.entry_size() * (i + 1);
+        self.bin
+            .get(next + LEGACY_ENTRY_OFFSET..next + LEGACY_ENTRY_OFFSET + 4)
+            .map(|offset| LittleEndian::read_u32(offset) as usize)
+            .ok_or(AmxError::Eof {
+                what: "section offset",
+                offset: next + LEGACY_ENTRY_OFFSET,
+            })
+    }
 }
 
 #[cfg(test)]
@@ -34,6 +62,8 @@ mod tests {
 
     use super::super::super::Section;
     use super::File as AmxmodxFile;
+    use crate::error::AmxError;
+    use crate::parse_trace::ParseTrace;
 
     fn load_fixture(filename: &str) -> Vec<u8> {
         let mut file_bin: Vec<u8> = Vec::new();
@@ -71,12 +101,84 @@ mod tests {
         assert_eq!(extracted_sections, expected_sections);
     }
 
+    #[test]
+    fn it_trace_section_table() {
+        let amxmodx_bin = load_fixture("simple.amxx181");
+        let mut trace = ParseTrace::new();
+        let amxmodx_file = AmxmodxFile::parse_traced(amxmodx_bin, &mut trace).unwrap();
+        amxmodx_file.sections_traced(&mut trace).unwrap();
+
+        let fields: Vec<(&str, usize, u64)> = trace
+            .entries
+            .iter()
+            .map(|e| (e.field, e.offset, e.value))
+            .collect();
+        assert_eq!(fields.len(), 13);
+        assert_eq!(
+            fields[..4],
+            [
+                ("magic", 0, 0x414D5858),
+                ("version", 4, 768),
+                ("sections", 6, 2),
+                ("cellsize", 7, 4)
+            ]
+        );
+        assert_eq!(fields[12], ("offset", 37, 202));
+    }
+
+    #[test]
+    fn it_return_legacy_sections() {
+        use byteorder::{LittleEndian, WriteBytesExt};
+        use flate2::write::ZlibEncoder;
+        use flate2::Compression;
+
+        let image = load_fixture("simple.amx183");
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(&image).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        // "BXMA", one section: cellsize, memsize, offset
+        let mut amxmodx_bin = vec![];
+        amxmodx_bin.write_u32::<LittleEndian>(0x414D5842).unwrap();
+        amxmodx_bin.write_u8(1).unwrap();
+        amxmodx_bin.write_u8(4).unwrap();
+        amxmodx_bin.write_u32::<LittleEndian>(16680).unwrap();
+        amxmodx_bin.write_u32::<LittleEndian>(14).unwrap();
+        amxmodx_bin.extend(&compressed);
+
+        let amxmodx_file = AmxmodxFile::try_from(amxmodx_bin).unwrap();
+        let sections = amxmodx_file.sections().unwrap();
+
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].disksize as usize, compressed.len());
+        assert_eq!(sections[0].imagesize as usize, image.len());
+        assert_eq!(sections[0].memsize, 16680);
+        assert_eq!(sections[0].unpack_section().unwrap(), image);
+    }
+
     #[test]
     fn it_err_on_sections_parsing_eof() {
         // Correct magic, correct version, 2 sections, zero section headers
         let amxmodx_bin = vec![88, 88, 77, 65, 0, 3, 2];
         let amxmodx_file = AmxmodxFile::try_from(amxmodx_bin).unwrap();
-        let result = amxmodx_file.sections();
-        assert!(result.is_err());
+        assert_eq!(
+            amxmodx_file.sections().err(),
+            Some(AmxError::Eof {
+                what: "section cellsize",
+                offset: 7
+            })
+        );
+
+        // Second section contents cut off
+        let amxmodx_bin = load_fixture("simple.amxx181");
+        let truncated = amxmodx_bin[..amxmodx_bin.len() - 1].to_vec();
+        let amxmodx_file = AmxmodxFile::try_from(truncated).unwrap();
+        assert_eq!(
+            amxmodx_file.sections().err(),
+            Some(AmxError::Eof {
+                what: "section contents",
+                offset: 202
+            })
+        );
     }
 }
@@ -3,19 +3,18 @@ use std::fs::File as IoFile;
 use std::io::Read;
 use std::path::PathBuf;
 
-use failure::Error;
-
 use super::File;
+use crate::error::AmxError;
 
 impl TryFrom<PathBuf> for File {
-    type Error = Error;
+    type Error = AmxError;
 
     fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
         let mut open_result = IoFile::open(path)?;
         let mut file_contents: Vec<u8> = Vec::new();
         open_result.read_to_end(&mut file_contents)?;
 
-        Self::try_from(file_contents).map_err(|e| format_err!("{}", e))
+        Self::try_from(file_contents)
     }
 }
 
@@ -2,70 +2,71 @@ use std::convert::TryFrom;
 use std::io::Cursor;
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use failure::Error;
-use log::trace;
 
-use super::{File, COMPATIBLE_VERSION, MAGIC};
+use super::{Contents, File, Version, COMPATIBLE_VERSION, LEGACY_MAGIC, MAGIC};
+use crate::error::{read_at, AmxError};
+use crate::parse_trace::{ParseTrace, Tracer};
 
 impl TryFrom<Vec<u8>> for File {
-    type Error = Error;
+    type Error = AmxError;
 
     fn try_from(bin: Vec<u8>) -> Result<Self, Self::Error> {
-        let sections = {
-            let mut reader = Cursor::new(&bin);
+        File::parse(Contents::Owned(bin), None)
+    }
+}
+
+impl File {
+    // Like `try_from`, collecting header fields into `trace`
+    pub fn parse_traced(bin: Vec<u8>, trace: &mut ParseTrace) -> Result<File, AmxError> {
+        File::parse(Contents::Owned(bin), Some(trace))
+    }
+
+    pub(crate) fn parse(bin: Contents, trace: Option<&mut ParseTrace>) -> Result<File, AmxError> {
+        let (version, sections) = {
+            let mut reader = Cursor::new(&bin[..]);
+            let mut tracer = Tracer::new(trace);
 
             // magic
-            let magic = match reader.read_u32::<LittleEndian>() {
-                Ok(magic) => {
-                    if magic != MAGIC {
-                        return Err(format_err!(
-                            "Invalid file magic, expected: 0x{:X}, got: 0x{:X}",
-                            MAGIC,
-                            magic
-                        ));
-                    }
-                    magic
-                }
-                Err(_) => return Err(format_err!("Magic EOF")),
-            };
-            trace!("File magic is 0x{:X}", magic);
-
-            // version
-            let version = match reader.read_u16::<LittleEndian>() {
-                Ok(version) => {
-                    if version != COMPATIBLE_VERSION {
-                        return Err(format_err!(
-                            "Incompatible file version, expected: {}, got: {}",
-                            COMPATIBLE_VERSION,
-                            version
-                        ));
-                    }
-                    version
+            let magic = read_at(&mut reader, "file magic", |r| r.read_u32::<LittleEndian>())?;
+            if magic != MAGIC && magic != LEGACY_MAGIC {
+                return Err(AmxError::InvalidFileMagic(MAGIC, magic));
+            }
+            tracer.field("magic", &reader, magic);
+
+            // version, legacy containers have none
+            let version = if magic == LEGACY_MAGIC {
+                Version::Legacy
+            } else {
+                let version = read_at(&mut reader, "file version", |r| {
+                    r.read_u16::<LittleEndian>()
+                })?;
+                if version != COMPATIBLE_VERSION {
+                    return Err(AmxError::IncompatibleFileVersion(
+                        COMPATIBLE_VERSION,
+                        version,
+                    ));
                 }
-                Err(_) => return Err(format_err!("Version EOF")),
+                tracer.field("version", &reader, version);
+                Version::V3
             };
-            trace!("Version is 0x{:X}", version);
 
             // sections count
-            let sections = match reader.read_u8() {
-                Ok(s) => {
-                    if s < 1 {
-                        return Err(format_err!("Zero sections amount"));
-                    }
-
-                    if s > 2 {
-                        return Err(format_err!("More than two sections (malicious file?)"));
-                    }
-
-                    s
-                }
-                Err(_) => return Err(format_err!("Sections EOF")),
-            };
-            trace!("File has {} sections", sections);
-            sections
+            let sections = read_at(&mut reader, "sections count", |r| r.read_u8())?;
+            if sections < 1 {
+                return Err(AmxError::ZeroSections);
+            }
+            if sections > 2 {
+                return Err(AmxError::TooManySections(sections));
+            }
+            tracer.field("sections", &reader, sections);
+            (version, sections)
         };
 
-        Ok(File { bin, sections })
+        Ok(File {
+            bin,
+            version,
+            sections,
+        })
     }
 }
 
@@ -76,6 +77,8 @@ mod tests {
     use std::io::prelude::*;
 
     use super::File as AmxmodxFile;
+    use super::Version;
+    use crate::error::AmxError;
 
     fn load_fixture(filename: &str) -> Vec<u8> {
         let mut file_bin: Vec<u8> = Vec::new();
@@ -93,26 +96,51 @@ mod tests {
         assert!(AmxmodxFile::try_from(amxmodx_bin).is_ok());
     }
 
+    #[test]
+    fn it_detect_container_version() {
+        let amxmodx_file = AmxmodxFile::try_from(load_fixture("simple.amxx181")).unwrap();
+        assert_eq!(amxmodx_file.version, Version::V3);
+
+        // Legacy magic, no version field, one section
+        let amxmodx_bin = vec![66, 88, 77, 65, 1];
+        let amxmodx_file = AmxmodxFile::try_from(amxmodx_bin).unwrap();
+        assert_eq!(amxmodx_file.version, Version::Legacy);
+        assert_eq!(amxmodx_file.sections, 1);
+    }
+
     #[test]
     fn it_err_on_empty_file() {
         let amxmodx_bin = vec![];
         let result = AmxmodxFile::try_from(amxmodx_bin).err().unwrap();
-        assert_eq!(result.cause().to_string(), "Magic EOF");
+        assert_eq!(
+            result,
+            AmxError::Eof {
+                what: "file magic",
+                offset: 0
+            }
+        );
     }
 
     #[test]
     fn it_err_on_magic_eof() {
         let amxmodx_bin = vec![0, 0, 0];
         let result = AmxmodxFile::try_from(amxmodx_bin).err().unwrap();
-        assert_eq!(result.cause().to_string(), "Magic EOF");
+        assert_eq!(
+            result,
+            AmxError::Eof {
+                what: "file magic",
+                offset: 0
+            }
+        );
     }
 
     #[test]
     fn it_err_on_invalid_magic() {
         let amxmodx_bin = vec![0, 0, 0, 0];
         let result = AmxmodxFile::try_from(amxmodx_bin).err().unwrap();
+        assert_eq!(result, AmxError::InvalidFileMagic(0x414D5858, 0));
         assert_eq!(
-            result.cause().to_string(),
+            result.to_string(),
             "Invalid file magic, expected: 0x414D5858, got: 0x0"
         );
     }
@@ -122,7 +150,13 @@ mod tests {
         // Correct magic, incorrect version
         let amxmodx_bin = vec![88, 88, 77, 65, 0];
         let result = AmxmodxFile::try_from(amxmodx_bin).err().unwrap();
-        assert_eq!(result.cause().to_string(), "Version EOF");
+        assert_eq!(
+            result,
+            AmxError::Eof {
+                what: "file version",
+                offset: 4
+            }
+        );
     }
 
     #[test]
@@ -130,10 +164,7 @@ mod tests {
         // Correct magic, incorrect version
         let amxmodx_bin = vec![88, 88, 77, 65, 0, 4];
         let result = AmxmodxFile::try_from(amxmodx_bin).err().unwrap();
-        assert_eq!(
-            result.cause().to_string(),
-            "Incompatible file version, expected: 768, got: 1024"
-        );
+        assert_eq!(result, AmxError::IncompatibleFileVersion(768, 1024));
     }
 
     #[test]
@@ -141,7 +172,13 @@ mod tests {
         // Correct magic, correct version, no section byte
         let amxmodx_bin = vec![88, 88, 77, 65, 0, 3];
         let result = AmxmodxFile::try_from(amxmodx_bin).err().unwrap();
-        assert_eq!(result.cause().to_string(), "Sections EOF");
+        assert_eq!(
+            result,
+            AmxError::Eof {
+                what: "sections count",
+                offset: 6
+            }
+        );
     }
 
     #[test]
@@ -149,7 +186,7 @@ mod tests {
         // Correct magic, correct version, zero sections
         let amxmodx_bin = vec![88, 88, 77, 65, 0, 3, 0];
         let result = AmxmodxFile::try_from(amxmodx_bin).err().unwrap();
-        assert_eq!(result.cause().to_string(), "Zero sections amount");
+        assert_eq!(result, AmxError::ZeroSections);
     }
 
     #[test]
@@ -157,9 +194,6 @@ mod tests {
         // Correct magic, correct version, 3 sections
         let amxmodx_bin = vec![88, 88, 77, 65, 0, 3, 3];
         let result = AmxmodxFile::try_from(amxmodx_bin).err().unwrap();
-        assert_eq!(
-            result.cause().to_string(),
-            "More than two sections (malicious file?)"
-        );
+        assert_eq!(result, AmxError::TooManySections(3));
     }
 }
@@ -1,4 +1,8 @@
 mod file;
 mod section;
-pub use self::file::File;
+mod writer;
+pub use self::file::{Contents, File, Version};
+pub(crate) use self::file::{LEGACY_MAGIC, MAGIC};
 pub use self::section::Section;
+pub(crate) use self::section::GZIP_MAGIC;
+pub use self::writer::Writer;
@@ -1,76 +1,85 @@
 use std::convert::{TryFrom, TryInto};
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::io::{Cursor, Read};
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use failure::{Error, ResultExt};
-use flate2::read::ZlibDecoder;
+use flate2::bufread;
+use flate2::read::{GzDecoder, ZlibDecoder};
 use log::trace;
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
 use super::super::amx::Plugin;
+use crate::error::{read_at, AmxError};
+use crate::parse_trace::{ParseTrace, Tracer};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Section {
     pub cellsize: u8,
     pub disksize: u32,
     pub imagesize: u32,
     pub memsize: u32,
     pub offset: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub bin: Vec<u8>,
 }
 
-#[derive(Debug, Fail)]
-enum SectionParseError {
-    #[fail(display = "Invalid section cellsize, must be 4 or 8, got: {}", _0)]
-    InvalidCellSize(u8),
-    #[fail(display = "unexpected EOF on reading sections contents, disksize does not match")]
-    ContentsEof,
-    #[fail(display = "imagesize does not match section unpacked contents")]
-    ImageSizeMismatch,
-}
+pub(crate) const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+// Deflate expands input at most this many times, larger imagesize is a lie
+const MAX_DEFLATE_RATIO: usize = 1032;
 
 impl Section {
     pub const SIZE: usize = 17; // Packed section size
 
-    pub fn from(bin: &[u8], section_header_offset: usize) -> Result<Section, Error> {
+    pub fn from(bin: &[u8], section_header_offset: usize) -> Result<Section, AmxError> {
+        Section::read(bin, section_header_offset, None)
+    }
+
+    pub(crate) fn read(
+        bin: &[u8],
+        section_header_offset: usize,
+        trace: Option<&mut ParseTrace>,
+    ) -> Result<Section, AmxError> {
         let mut reader = Cursor::new(bin);
-        reader
-            .seek(SeekFrom::Start(section_header_offset as u64))
-            .context("EOF on offseting to section header (wtf?)")?;
+        reader.set_position(section_header_offset as u64);
+        let mut tracer = Tracer::new(trace);
 
-        let cellsize = reader.read_u8().context("EOF on section cellsize")?;
+        let cellsize = read_at(&mut reader, "section cellsize", |r| r.read_u8())?;
         if !(cellsize == 4 || cellsize == 8) {
-            Err(SectionParseError::InvalidCellSize(cellsize))?;
+            return Err(AmxError::InvalidCellSize(cellsize));
         }
-        trace!("cellsize:\t{}", cellsize);
-
-        let disksize = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on section disksize")?;
-        trace!("disksize:\t{}", disksize);
-
-        let imagesize = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on section imagesize")?;
-        trace!("imagesize:\t{}", imagesize);
-
-        let memsize = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on section memsize")?;
-        trace!("memsize:\t{}", memsize);
-
-        let offset = reader
-            .read_u32::<LittleEndian>()
-            .context("EOF on section offset")?;
-        trace!("offset:\t{}", offset);
-
-        let mut section_bin = vec![0; disksize as usize];
-        reader
-            .seek(SeekFrom::Start(offset.try_into().unwrap()))
-            .context("EOF on offseting to section contents")?;
-        reader
-            .read_exact(&mut section_bin)
-            .context(SectionParseError::ContentsEof)?;
-        trace!("section contents size match disksize");
+        tracer.field("cellsize", &reader, cellsize);
+
+        let disksize = read_at(&mut reader, "section disksize", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        tracer.field("disksize", &reader, disksize);
+
+        let imagesize = read_at(&mut reader, "section imagesize", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        tracer.field("imagesize", &reader, imagesize);
+
+        let memsize = read_at(&mut reader, "section memsize", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        tracer.field("memsize", &reader, memsize);
+
+        let offset = read_at(&mut reader, "section offset", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        tracer.field("offset", &reader, offset);
+
+        // disksize does not match contents left in file, checked before
+        // anything is allocated for them
+        let section_bin = (offset as usize)
+            .checked_add(disksize as usize)
+            .and_then(|end| bin.get(offset as usize..end))
+            .ok_or(AmxError::Eof {
+                what: "section contents",
+                offset: offset as usize,
+            })?
+            .to_vec();
 
         Ok(Section {
             cellsize,
@@ -82,27 +91,164 @@ impl Section {
         })
     }
 
-    pub fn unpack_section(&self) -> Result<Plugin, Error> {
-        let imagesize = self.imagesize as usize;
-        let mut amx_bin: Vec<u8> = Vec::with_capacity(imagesize);
+    // Legacy section table entry at `section_header_offset`, contents last
+    // until `end`. Sizes not stored by legacy table are taken from contents.
+    pub fn from_legacy(
+        bin: &[u8],
+        section_header_offset: usize,
+        end: usize,
+    ) -> Result<Section, AmxError> {
+        Section::read_legacy(bin, section_header_offset, end, None)
+    }
+
+    pub(crate) fn read_legacy(
+        bin: &[u8],
+        section_header_offset: usize,
+        end: usize,
+        trace: Option<&mut ParseTrace>,
+    ) -> Result<Section, AmxError> {
+        let mut reader = Cursor::new(bin);
+        reader.set_position(section_header_offset as u64);
+        let mut tracer = Tracer::new(trace);
+
+        let cellsize = read_at(&mut reader, "section cellsize", |r| r.read_u8())?;
+        if !(cellsize == 4 || cellsize == 8) {
+            return Err(AmxError::InvalidCellSize(cellsize));
+        }
+        tracer.field("cellsize", &reader, cellsize);
+
+        let memsize = read_at(&mut reader, "section memsize", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        tracer.field("memsize", &reader, memsize);
+        let offset = read_at(&mut reader, "section offset", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        tracer.field("offset", &reader, offset);
+
+        let section_bin = bin
+            .get(offset as usize..end)
+            .ok_or(AmxError::Malformed {
+                reason: "section contents outside of file",
+                offset: section_header_offset,
+            })?
+            .to_vec();
+
+        let mut section = Section {
+            cellsize,
+            disksize: section_bin.len() as u32,
+            imagesize: 0,
+            memsize,
+            offset: offset as usize,
+            bin: section_bin,
+        };
+        section.imagesize = section.inflate()?.len() as u32;
+        Ok(section)
+    }
+
+    // Payload may be zlib (amxxpc) or gzip compressed
+    fn inflate(&self) -> Result<Vec<u8>, AmxError> {
+        let capacity = (self.imagesize as usize).min(self.bin.len() * MAX_DEFLATE_RATIO);
+        let mut amx_bin: Vec<u8> = Vec::with_capacity(capacity);
         let reader = Cursor::new(&self.bin);
-        // TODO: test
-        ZlibDecoder::new(reader).read_to_end(&mut amx_bin)?;
+        let unpacked = if self.bin.starts_with(&GZIP_MAGIC) {
+            trace!("section is gzip compressed");
+            GzDecoder::new(reader).read_to_end(&mut amx_bin)
+        } else {
+            ZlibDecoder::new(reader).read_to_end(&mut amx_bin)
+        };
+        unpacked.map_err(|e| AmxError::Unpack(e.to_string()))?;
+        Ok(amx_bin)
+    }
+
+    // Raw amx image, ready for `Plugin::try_from`
+    pub fn unpack_section(&self) -> Result<Vec<u8>, AmxError> {
+        let amx_bin = self.inflate()?;
 
         // TODO: test
-        if amx_bin.len() != imagesize {
-            Err(SectionParseError::ImageSizeMismatch)?;
+        if amx_bin.len() != self.imagesize as usize {
+            return Err(AmxError::ImageSizeMismatch);
         }
 
+        Ok(amx_bin)
+    }
+
+    pub fn unpack_plugin(&self) -> Result<Plugin<'static>, AmxError> {
         // TODO: test
-        let plugin = Plugin::try_from(amx_bin);
-        plugin.map_err(|e| format_err!("{}", e))
+        Plugin::try_from(self.unpack_section()?)
+    }
+
+    // Compressed stream inflated from start of contents, with number of
+    // contents bytes it takes
+    fn inflate_stream(contents: &[u8]) -> Result<(Vec<u8>, usize), AmxError> {
+        let mut image = vec![];
+        let mut rest = contents;
+        let unpacked = if contents.starts_with(&GZIP_MAGIC) {
+            let mut decoder = bufread::GzDecoder::new(rest);
+            let unpacked = decoder.read_to_end(&mut image);
+            rest = decoder.into_inner();
+            unpacked
+        } else {
+            let mut decoder = bufread::ZlibDecoder::new(rest);
+            let unpacked = decoder.read_to_end(&mut image);
+            rest = decoder.into_inner();
+            unpacked
+        };
+        unpacked.map_err(|e| AmxError::Unpack(e.to_string()))?;
+        Ok((image, contents.len() - rest.len()))
+    }
+
+    // Compressed contents section header points to in file `bin`, e.g.
+    // for dumping payload or feeding it to external tools
+    pub fn raw<'a>(&self, bin: &'a [u8]) -> Result<&'a [u8], AmxError> {
+        let end = self.offset.checked_add(self.disksize as usize);
+        end.and_then(|end| bin.get(self.offset..end))
+            .ok_or(AmxError::Malformed {
+                reason: "section contents past file end",
+                offset: self.offset,
+            })
+    }
+
+    // Amx image inflated from `raw` contents, junk after compressed stream
+    // and imagesize mismatch are ignored
+    pub fn decompressed(&self, bin: &[u8]) -> Result<Vec<u8>, AmxError> {
+        Ok(Section::inflate_stream(self.raw(bin)?)?.0)
+    }
+
+    // Every inconsistency of section header with contents of file `bin`
+    // and of amx image inside, offsets of image ones are image offsets
+    pub fn validate(&self, bin: &[u8]) -> Vec<AmxError> {
+        let contents = match self.raw(bin) {
+            Ok(contents) => contents,
+            Err(e) => return vec![e],
+        };
+        let (image, compressed) = match Section::inflate_stream(contents) {
+            Ok(unpacked) => unpacked,
+            Err(e) => return vec![e],
+        };
+
+        let mut violations = vec![];
+        if compressed != contents.len() {
+            violations.push(AmxError::Malformed {
+                reason: "disksize does not match compressed length",
+                offset: self.offset + compressed,
+            });
+        }
+        if image.len() != self.imagesize as usize {
+            violations.push(AmxError::ImageSizeMismatch);
+        }
+        match Plugin::try_from(image) {
+            Ok(plugin) => violations.extend(plugin.validate()),
+            Err(e) => violations.push(e),
+        }
+        violations
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Section;
+    use crate::error::AmxError;
     use std::fs::File;
     use std::io::prelude::*;
 
@@ -124,11 +270,89 @@ mod tests {
     }
 
     #[test]
-    fn it_unpack_section_without_errors() {
+    fn it_unpack_plugin_without_errors() {
         // File with single section.
         let amxmodx_bin = load_fixture("simple.amxx183");
         let section = Section::from(&amxmodx_bin, AMXX_HEADER_SIZE).unwrap();
-        section.unpack_section().unwrap();
+        section.unpack_plugin().unwrap();
+    }
+
+    #[test]
+    fn it_unpack_gzip_section() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+
+        let amxmodx_bin = load_fixture("simple.amxx183");
+        let section = Section::from(&amxmodx_bin, AMXX_HEADER_SIZE).unwrap();
+        let image = section.unpack_section().unwrap();
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&image).unwrap();
+        let gzip_section = Section {
+            bin: encoder.finish().unwrap(),
+            ..section
+        };
+
+        assert_eq!(gzip_section.unpack_section().unwrap(), image);
+        assert_eq!(
+            gzip_section.unpack_plugin().unwrap(),
+            section.unpack_plugin().unwrap()
+        );
+    }
+
+    #[test]
+    fn it_access_raw_section_data() {
+        let amxmodx_bin = load_fixture("simple.amxx183");
+        let section = Section::from(&amxmodx_bin, AMXX_HEADER_SIZE).unwrap();
+
+        assert_eq!(section.raw(&amxmodx_bin).unwrap(), &section.bin[..]);
+        assert_eq!(
+            section.decompressed(&amxmodx_bin).unwrap(),
+            section.unpack_section().unwrap()
+        );
+
+        let truncated = &amxmodx_bin[..amxmodx_bin.len() - 1];
+        assert_eq!(
+            section.raw(truncated).err(),
+            Some(AmxError::Malformed {
+                reason: "section contents past file end",
+                offset: section.offset,
+            })
+        );
+    }
+
+    #[test]
+    fn it_validate_section() {
+        let amxmodx_bin = load_fixture("simple.amxx183");
+        let section = Section::from(&amxmodx_bin, AMXX_HEADER_SIZE).unwrap();
+        assert_eq!(section.validate(&amxmodx_bin), []);
+
+        // Junk after compressed stream is counted into disksize
+        let mut padded = amxmodx_bin.clone();
+        padded.extend_from_slice(&[0; 4]);
+        let padded_section = Section {
+            disksize: section.disksize + 4,
+            imagesize: section.imagesize + 1,
+            bin: vec![],
+            ..section
+        };
+        assert_eq!(
+            padded_section.validate(&padded),
+            [
+                AmxError::Malformed {
+                    reason: "disksize does not match compressed length",
+                    offset: section.offset + section.disksize as usize,
+                },
+                AmxError::ImageSizeMismatch,
+            ]
+        );
+        assert_eq!(
+            padded_section.validate(&amxmodx_bin),
+            [AmxError::Malformed {
+                reason: "section contents past file end",
+                offset: section.offset,
+            }]
+        );
     }
 
     #[test]
@@ -136,12 +360,11 @@ mod tests {
         // empty section header
         let section_bin = vec![];
         assert_eq!(
-            Section::from(&section_bin, 0)
-                .err()
-                .unwrap()
-                .cause()
-                .to_string(),
-            "EOF on section cellsize"
+            Section::from(&section_bin, 0).err().unwrap(),
+            AmxError::Eof {
+                what: "section cellsize",
+                offset: 0
+            }
         );
     }
 
@@ -150,12 +373,8 @@ mod tests {
         // invalid cellsize
         let section_bin = vec![0];
         assert_eq!(
-            Section::from(&section_bin, 0)
-                .err()
-                .unwrap()
-                .cause()
-                .to_string(),
-            "Invalid section cellsize, must be 4 or 8, got: 0"
+            Section::from(&section_bin, 0).err().unwrap(),
+            AmxError::InvalidCellSize(0)
         );
     }
 
@@ -165,12 +384,11 @@ mod tests {
         // empty disksize
         let section_bin = vec![4];
         assert_eq!(
-            Section::from(&section_bin, 0)
-                .err()
-                .unwrap()
-                .cause()
-                .to_string(),
-            "EOF on section disksize"
+            Section::from(&section_bin, 0).err().unwrap(),
+            AmxError::Eof {
+                what: "section disksize",
+                offset: 1
+            }
         );
     }
 
@@ -182,12 +400,11 @@ mod tests {
         let mut section_bin = vec![4, 0, 0, 0, 0];
         section_bin[0] = 4;
         assert_eq!(
-            Section::from(&section_bin, 0)
-                .err()
-                .unwrap()
-                .cause()
-                .to_string(),
-            "EOF on section imagesize"
+            Section::from(&section_bin, 0).err().unwrap(),
+            AmxError::Eof {
+                what: "section imagesize",
+                offset: 5
+            }
         );
     }
 
@@ -200,12 +417,11 @@ mod tests {
         let mut section_bin = vec![0; 9];
         section_bin[0] = 4;
         assert_eq!(
-            Section::from(&section_bin, 0)
-                .err()
-                .unwrap()
-                .cause()
-                .to_string(),
-            "EOF on section memsize"
+            Section::from(&section_bin, 0).err().unwrap(),
+            AmxError::Eof {
+                what: "section memsize",
+                offset: 9
+            }
         );
     }
 
@@ -219,12 +435,11 @@ mod tests {
         let mut section_bin = vec![0; 13];
         section_bin[0] = 4;
         assert_eq!(
-            Section::from(&section_bin, 0)
-                .err()
-                .unwrap()
-                .cause()
-                .to_string(),
-            "EOF on section offset"
+            Section::from(&section_bin, 0).err().unwrap(),
+            AmxError::Eof {
+                what: "section offset",
+                offset: 13
+            }
         );
     }
 
@@ -248,4 +463,29 @@ mod tests {
         };
         assert_eq!(extracted_section, expected_section);
     }
+
+    #[test]
+    fn it_err_on_disksize_past_file_end() {
+        // disksize far beyond 17 bytes of file
+        let mut section_bin = vec![0; 17];
+        section_bin[0] = 4;
+        section_bin[1..5].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        assert_eq!(
+            Section::from(&section_bin, 0).err().unwrap(),
+            AmxError::Eof {
+                what: "section contents",
+                offset: 0
+            }
+        );
+    }
+
+    #[test]
+    fn it_err_on_oversized_imagesize() {
+        let amxmodx_bin = load_fixture("simple.amxx183");
+        let section = Section {
+            imagesize: u32::MAX,
+            ..Section::from(&amxmodx_bin, AMXX_HEADER_SIZE).unwrap()
+        };
+        assert_eq!(section.unpack_section(), Err(AmxError::ImageSizeMismatch));
+    }
 }
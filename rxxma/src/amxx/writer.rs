@@ -0,0 +1,141 @@
+use std::io::Write;
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use super::file::{AMXX_HEADER_SIZE, COMPATIBLE_VERSION, MAGIC};
+use super::{File, Section, Version};
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+// Packs amx images into amxx container, one section per cellsize.
+// Images are zlib compressed like amxxpc does and follow section table.
+#[derive(Default)]
+pub struct Writer<'a> {
+    plugins: Vec<&'a Plugin<'a>>,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new() -> Writer<'a> {
+        Writer::default()
+    }
+
+    pub fn section(&mut self, plugin: &'a Plugin<'a>) -> &mut Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    pub fn write(&self) -> Result<File, AmxError> {
+        match self.plugins.len() {
+            0 => return Err(AmxError::ZeroSections),
+            1 | 2 => {}
+            n => return Err(AmxError::TooManySections(n as u8)),
+        }
+        if let [first, second] = self.plugins.as_slice() {
+            if first.cellsize() == second.cellsize() {
+                return Err(AmxError::DuplicateSection(first.cellsize() as u32 * 8));
+            }
+        }
+
+        let mut images = vec![];
+        for plugin in self.plugins.iter() {
+            let image = plugin.to_bytes();
+            let mut encoder = ZlibEncoder::new(vec![], Compression::best());
+            encoder.write_all(&image)?;
+            images.push((plugin, image.len(), encoder.finish()?));
+        }
+
+        let sections = self.plugins.len() as u8;
+        let mut bin: Vec<u8> =
+            Vec::with_capacity(AMXX_HEADER_SIZE + Section::SIZE * usize::from(sections));
+        bin.write_u32::<LittleEndian>(MAGIC)?;
+        bin.write_u16::<LittleEndian>(COMPATIBLE_VERSION)?;
+        bin.write_u8(sections)?;
+
+        let mut offset = AMXX_HEADER_SIZE + Section::SIZE * usize::from(sections);
+        for (plugin, imagesize, compressed) in images.iter() {
+            bin.write_u8(plugin.cellsize() as u8)?;
+            bin.write_u32::<LittleEndian>(compressed.len() as u32)?;
+            bin.write_u32::<LittleEndian>(*imagesize as u32)?;
+            bin.write_u32::<LittleEndian>(plugin.memsize() as u32)?;
+            bin.write_u32::<LittleEndian>(offset as u32)?;
+            offset += compressed.len();
+        }
+        for (_, _, compressed) in images {
+            bin.extend(compressed);
+        }
+
+        Ok(File {
+            bin: bin.into(),
+            version: Version::V3,
+            sections,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::Writer;
+    use crate::amx::Plugin;
+    use crate::amxx::File;
+    use crate::error::AmxError;
+    use crate::util::tests::load_fixture;
+
+    #[test]
+    fn it_repack_both_sections() {
+        let amxmodx_file = File::try_from(load_fixture("simple.amxx181")).unwrap();
+        let sections = amxmodx_file.sections().unwrap();
+        let plugins: Vec<Plugin> = sections
+            .iter()
+            .map(|s| s.unpack_plugin().unwrap())
+            .collect();
+
+        let packed = Writer::new()
+            .section(&plugins[0])
+            .section(&plugins[1])
+            .write()
+            .unwrap();
+        let repacked = File::try_from(packed.bin.into_vec())
+            .unwrap()
+            .sections()
+            .unwrap();
+
+        assert_eq!(repacked.len(), 2);
+        for (section, original) in repacked.iter().zip(sections.iter()) {
+            assert_eq!(section.cellsize, original.cellsize);
+            assert_eq!(section.imagesize, original.imagesize);
+            assert_eq!(section.memsize, original.memsize);
+            assert_eq!(
+                section.unpack_section().unwrap(),
+                original.unpack_section().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn it_err_on_invalid_section_set() {
+        let plugin = Plugin::try_from(load_fixture("simple.amx183")).unwrap();
+
+        assert_eq!(Writer::new().write().err(), Some(AmxError::ZeroSections));
+        assert_eq!(
+            Writer::new()
+                .section(&plugin)
+                .section(&plugin)
+                .write()
+                .err(),
+            Some(AmxError::DuplicateSection(32))
+        );
+        assert_eq!(
+            Writer::new()
+                .section(&plugin)
+                .section(&plugin)
+                .section(&plugin)
+                .write()
+                .err(),
+            Some(AmxError::TooManySections(3))
+        );
+    }
+}
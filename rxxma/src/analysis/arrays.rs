@@ -0,0 +1,158 @@
+use std::collections::BTreeSet;
+
+use super::entropy::referenced_addresses;
+use crate::amx::OpcodeType::*;
+use crate::amx::{DatString, Opcode, Plugin};
+use crate::error::AmxError;
+
+// Shorter regions are too likely a number pushed as argument
+const MIN_CELLS: usize = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatArray {
+    // DAT address of first cell
+    pub address: usize,
+    // Cells up to the next reference or string
+    pub size: usize,
+    // Initial values without zero tail, compiler pads arrays with zeroes
+    pub cells: Vec<u32>,
+}
+
+// Shorter strings than `strings` finds, e.g. "2", and text in general
+// is kept as string
+fn is_text(plugin: &Plugin, address: usize) -> bool {
+    plugin
+        .read_string_bytes(address)
+        .is_some_and(|b| b.iter().all(|&c| c >= 0x20 || b"\t\n\r".contains(&c)))
+}
+
+// Where DAT variables end: addresses code uses, public variables and
+// strings. Characters of string used on their own do not split it.
+pub(super) fn boundaries(
+    plugin: &Plugin,
+    strings: &[DatString],
+) -> Result<BTreeSet<usize>, AmxError> {
+    let mut boundaries = referenced_addresses(plugin, plugin.dat_slice()?.len())?;
+    boundaries.extend(plugin.pubvars()?.iter().map(|v| v.address));
+    boundaries.retain(|&b| {
+        !strings
+            .iter()
+            .any(|s| (s.address + 1..s.address + s.size).contains(&b))
+    });
+    boundaries.extend(strings.iter().map(|s| s.address));
+    Ok(boundaries)
+}
+
+// Size in cells of variable at address and its cells without zero tail
+pub(super) fn variable_cells(
+    plugin: &Plugin,
+    boundaries: &BTreeSet<usize>,
+    address: usize,
+) -> Result<(usize, Vec<u32>), AmxError> {
+    let dat = plugin.dat_slice()?;
+    let cellsize = plugin.cellsize();
+    let end = boundaries
+        .range(address + 1..)
+        .next()
+        .map_or(dat.len(), |&b| b.min(dat.len()));
+    let size = (end - address) / cellsize;
+    let mut cells: Vec<u32> = dat[address..address + size * cellsize]
+        .chunks_exact(cellsize)
+        .map(|cell| plugin.read_cell(cell) as u32)
+        .collect();
+    while cells.last() == Some(&0) {
+        cells.pop();
+    }
+    Ok((size, cells))
+}
+
+// DAT address taken by CONST or PUSH.C, byte count pushed in front of call
+// is not one
+fn address_operand(opcodes: &[Opcode], i: usize) -> Option<usize> {
+    let before_call = opcodes
+        .get(i + 1)
+        .is_some_and(|o| matches!(o.code, OP_CALL | OP_SYSREQ_C));
+    match opcodes[i].code {
+        OP_CONST_PRI | OP_CONST_ALT => opcodes[i].param.map(|p| p as usize),
+        OP_PUSH_C if !before_call => opcodes[i].param.map(|p| p as usize),
+        _ => None,
+    }
+}
+
+// Initialized arrays code takes address of, in DAT order. Array lasts until
+// the next reference or string, as in `dead_code`. Strings and arrays of
+// zeroes only are not arrays initialized by source.
+pub fn dat_arrays(plugin: &Plugin) -> Result<Vec<DatArray>, AmxError> {
+    let dat = plugin.dat_slice()?;
+    let cellsize = plugin.cellsize();
+    let opcodes = plugin.opcodes()?;
+    let strings = plugin.strings()?;
+
+    let boundaries = boundaries(plugin, &strings)?;
+
+    let addresses: BTreeSet<usize> = (0..opcodes.len())
+        .filter_map(|i| address_operand(&opcodes, i))
+        .filter(|&a| a < dat.len() && a.is_multiple_of(cellsize))
+        .filter(|&a| {
+            !strings
+                .iter()
+                .any(|s| (s.address..s.address + s.size).contains(&a))
+                && !is_text(plugin, a)
+        })
+        .collect();
+
+    let mut arrays = vec![];
+    for address in addresses {
+        let (size, cells) = variable_cells(plugin, &boundaries, address)?;
+        if size >= MIN_CELLS && !cells.is_empty() {
+            arrays.push(DatArray {
+                address,
+                size,
+                cells,
+            });
+        }
+    }
+    Ok(arrays)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{dat_arrays, DatArray};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_find_initialized_dat_arrays() {
+        let mut builder = PluginBuilder::new();
+        let message = builder.string("hello");
+        let table = builder.array(&[1, 2, 3, 0, 0]);
+        let counter = builder.array(&[0]);
+        let zeroes = builder.array(&[0, 0, 0]);
+        let log = builder.native("log_amx");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_LOAD_PRI, counter)
+            .op_param(OP_PUSH_C, zeroes)
+            .op_param(OP_PUSH_C, table)
+            .op_param(OP_PUSH_C, message)
+            .op_param(OP_PUSH_C, 12)
+            .op_param(OP_SYSREQ_C, log)
+            .op_param(OP_STACK, 16)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(
+            dat_arrays(&amxmod_plugin).unwrap(),
+            [DatArray {
+                address: table as usize,
+                size: 5,
+                cells: vec![1, 2, 3],
+            }]
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt::Write;
+
+use super::functions::{functions, Function};
+use crate::amx::OpcodeType::*;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+// Index into `CallGraph::functions`
+pub type FunctionId = usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSite {
+    // Cod address of OP_CALL
+    pub address: usize,
+    // Index of called function in `CallGraph::functions`
+    pub callee: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeSite {
+    // Cod address of SYSREQ.C
+    pub address: usize,
+    pub native: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallGraph {
+    pub functions: Vec<Function>,
+    // Call sites of every function in cod order, same indexes as `functions`
+    pub calls: Vec<Vec<CallSite>>,
+    // SYSREQ.C sites of every function in cod order, same indexes
+    pub natives: Vec<Vec<NativeSite>>,
+    // Functions using CALL.pri
+    pub indirect: Vec<FunctionId>,
+    // Treat CALL.pri as call of every function in reachability queries
+    pub conservative_indirect: bool,
+}
+
+impl CallGraph {
+    pub fn callees(&self, function: usize) -> &[CallSite] {
+        &self.calls[function]
+    }
+
+    pub fn callers(&self, function: usize) -> Vec<usize> {
+        (0..self.functions.len())
+            .filter(|&i| self.calls[i].iter().any(|c| c.callee == function))
+            .collect()
+    }
+
+    fn successors(&self, function: FunctionId) -> Vec<FunctionId> {
+        if self.conservative_indirect && self.indirect.contains(&function) {
+            return (0..self.functions.len()).collect();
+        }
+        self.calls[function].iter().map(|c| c.callee).collect()
+    }
+
+    // Functions executed by calling public, itself included
+    pub fn reachable_from(&self, public_name: &str) -> Vec<FunctionId> {
+        match self
+            .functions
+            .iter()
+            .position(|f| f.public && f.name == public_name)
+        {
+            Some(start) => self.reachable(&[start]),
+            None => vec![],
+        }
+    }
+
+    // Functions executed by calling any of `starts`, themselves included
+    pub fn reachable(&self, starts: &[FunctionId]) -> Vec<FunctionId> {
+        let mut reached: BTreeSet<FunctionId> = BTreeSet::new();
+        let mut stack = starts.to_vec();
+        while let Some(function) = stack.pop() {
+            if reached.insert(function) {
+                stack.extend(self.successors(function));
+            }
+        }
+
+        reached.into_iter().collect()
+    }
+
+    // Natives possibly called by executing public, sorted by name
+    pub fn reachable_natives(&self, public_name: &str) -> Vec<&str> {
+        let mut natives: Vec<&str> = self
+            .reachable_from(public_name)
+            .into_iter()
+            .flat_map(|f| self.natives[f].iter().map(|n| n.native.as_str()))
+            .collect();
+        natives.sort_unstable();
+        natives.dedup();
+        natives
+    }
+
+    // Graphviz digraph, publics are boxes, natives dashed ellipses and
+    // functions with CALL.pri bold as their callees are unknown
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph plugin {\n");
+        let mut natives: Vec<&str> = self
+            .natives
+            .iter()
+            .flatten()
+            .map(|n| n.native.as_str())
+            .collect();
+        natives.sort_unstable();
+        natives.dedup();
+
+        for (i, function) in self.functions.iter().enumerate() {
+            let mut attributes = vec![];
+            if function.public {
+                attributes.push("shape=box");
+            }
+            if self.indirect.contains(&i) {
+                attributes.push("style=bold");
+            }
+            if attributes.is_empty() {
+                writeln!(dot, "    {};", dot_id(&function.name)).unwrap();
+            } else {
+                let attributes = attributes.join(", ");
+                writeln!(dot, "    {} [{}];", dot_id(&function.name), attributes).unwrap();
+            }
+        }
+        for native in natives.iter() {
+            writeln!(dot, "    {} [style=dashed];", dot_id(native)).unwrap();
+        }
+
+        for (i, function) in self.functions.iter().enumerate() {
+            let mut callees: Vec<&str> = self.calls[i]
+                .iter()
+                .map(|c| self.functions[c.callee].name.as_str())
+                .chain(self.natives[i].iter().map(|n| n.native.as_str()))
+                .collect();
+            callees.sort_unstable();
+            callees.dedup();
+            for callee in callees {
+                writeln!(dot, "    {} -> {};", dot_id(&function.name), dot_id(callee)).unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    // Functions from which `function` can be reached through calls
+    pub fn callers_transitive(&self, function: FunctionId) -> Vec<FunctionId> {
+        let mut callers: Vec<Vec<FunctionId>> = vec![vec![]; self.functions.len()];
+        for (caller, calls) in self.calls.iter().enumerate() {
+            for call in calls {
+                callers[call.callee].push(caller);
+            }
+        }
+        // Conservative CALL.pri calls every function
+        let indirect: &[FunctionId] = if self.conservative_indirect {
+            &self.indirect
+        } else {
+            &[]
+        };
+
+        let mut reached = vec![false; self.functions.len()];
+        let mut queue = VecDeque::from(vec![function]);
+        while let Some(callee) = queue.pop_front() {
+            for &caller in callers[callee].iter().chain(indirect) {
+                if !reached[caller] {
+                    reached[caller] = true;
+                    queue.push_back(caller);
+                }
+            }
+        }
+
+        (0..reached.len()).filter(|&f| reached[f]).collect()
+    }
+
+    // Names of publics whose execution can reach `function`
+    pub fn triggerable_from(&self, function: FunctionId) -> Vec<&str> {
+        let mut functions = self.callers_transitive(function);
+        functions.push(function);
+        functions.sort();
+        functions.dedup();
+
+        functions
+            .into_iter()
+            .map(|f| &self.functions[f])
+            .filter(|f| f.public)
+            .map(|f| f.name.as_str())
+            .collect()
+    }
+}
+
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Direct OP_CALL edges between functions and SYSREQ.C native calls,
+// CALL.pri targets are unknown
+pub fn call_graph(plugin: &Plugin) -> Result<CallGraph, AmxError> {
+    let opcodes = plugin.opcodes()?;
+    let functions = functions(plugin)?;
+    let native_names = plugin.natives()?;
+    let indirect = (0..functions.len())
+        .filter(|&i| {
+            functions[i]
+                .opcodes(&opcodes)
+                .iter()
+                .any(|o| o.code == OP_CALL_PRI)
+        })
+        .collect();
+
+    let ids: HashMap<usize, FunctionId> = functions
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.address, i))
+        .collect();
+    let calls = functions
+        .iter()
+        .map(|f| {
+            f.opcodes(&opcodes)
+                .iter()
+                .filter(|o| o.code == OP_CALL)
+                .filter_map(|o| {
+                    let target = o.param? as usize;
+                    let callee = *ids.get(&target)?;
+                    Some(CallSite {
+                        address: o.address,
+                        callee,
+                    })
+                })
+                .collect()
+        })
+        .collect();
+
+    let natives = functions
+        .iter()
+        .map(|f| {
+            f.opcodes(&opcodes)
+                .iter()
+                .filter(|o| o.code == OP_SYSREQ_C)
+                .filter_map(|o| {
+                    let native = native_names.get(o.param? as usize)?;
+                    Some(NativeSite {
+                        address: o.address,
+                        native: native.name.to_string_lossy().into_owned(),
+                    })
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(CallGraph {
+        functions,
+        calls,
+        natives,
+        indirect,
+        conservative_indirect: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{call_graph, CallSite};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_link_calls_to_functions() {
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init").op(OP_PROC);
+        let call = builder.here();
+        builder
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_CALL, 0)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let stock = builder.here();
+        builder.op(OP_PROC).op(OP_ZERO_PRI).op(OP_RETN);
+        builder.patch(call + 12, stock);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let graph = call_graph(&amxmod_plugin).unwrap();
+
+        assert_eq!(
+            graph.callees(0),
+            [CallSite {
+                address: call as usize + 8,
+                callee: 1
+            }]
+        );
+        assert!(graph.callees(1).is_empty());
+        assert_eq!(graph.callers(1), [0]);
+    }
+
+    // plugin_init -> helper, client_connect calls nothing unless `indirect`
+    fn reachability_plugin(indirect: bool) -> Plugin<'static> {
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init").op(OP_PROC);
+        let call = builder.here();
+        builder
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_CALL, 0)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN)
+            .public("client_connect")
+            .op(OP_PROC);
+        if indirect {
+            builder.op(OP_CALL_PRI);
+        }
+        builder.op(OP_ZERO_PRI).op(OP_RETN);
+        let helper = builder.here();
+        builder.op(OP_PROC).op(OP_ZERO_PRI).op(OP_RETN);
+        builder.patch(call + 12, helper);
+
+        Plugin::try_from(builder.build()).unwrap()
+    }
+
+    #[test]
+    fn it_find_reachable_functions() {
+        let graph = call_graph(&reachability_plugin(false)).unwrap();
+
+        assert_eq!(graph.reachable_from("plugin_init"), [0, 2]);
+        assert_eq!(graph.reachable_from("client_connect"), [1]);
+        assert!(graph.reachable_from("missing").is_empty());
+        assert_eq!(graph.callers_transitive(2), [0]);
+        assert_eq!(graph.triggerable_from(2), ["plugin_init"]);
+    }
+
+    #[test]
+    fn it_treat_indirect_calls_conservatively() {
+        let mut graph = call_graph(&reachability_plugin(true)).unwrap();
+
+        assert_eq!(graph.reachable_from("client_connect"), [0, 1, 2]);
+        assert_eq!(graph.triggerable_from(2), ["plugin_init", "client_connect"]);
+
+        graph.conservative_indirect = false;
+        assert_eq!(graph.reachable_from("client_connect"), [1]);
+        assert_eq!(graph.triggerable_from(2), ["plugin_init"]);
+    }
+
+    #[test]
+    fn it_export_dot_with_natives() {
+        let mut builder = PluginBuilder::new();
+        let log = builder.native("log_amx");
+        builder.public("plugin_init").op(OP_PROC);
+        let call = builder.here();
+        builder
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_CALL, 0)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let helper = builder.here();
+        builder
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_SYSREQ_C, log)
+            .op_param(OP_STACK, 4)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        builder.patch(call + 12, helper);
+        let graph = call_graph(&Plugin::try_from(builder.build()).unwrap()).unwrap();
+
+        assert_eq!(graph.natives[1][0].native, "log_amx");
+        assert_eq!(graph.reachable_natives("plugin_init"), ["log_amx"]);
+        let helper = format!("\"sub_{:x}\"", helper);
+        assert_eq!(
+            graph.to_dot(),
+            format!(
+                "digraph plugin {{\n    \"plugin_init\" [shape=box];\n    {0};\n    \
+                 \"log_amx\" [style=dashed];\n    \"plugin_init\" -> {0};\n    \
+                 {0} -> \"log_amx\";\n}}\n",
+                helper
+            )
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use std::mem;
+
+use log::trace;
+
+use super::functions::{functions, Function};
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, Plugin};
+use crate::error::AmxError;
+
+// Deepest frame tracked cell by cell, larger STACK adjustments clobber it
+const MAX_FRAME_CELLS: usize = 0x4000;
+
+// Symbolic value of a register or stack cell
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CallArgument {
+    // Constant cell, may be a DAT address (strings and arrays)
+    Constant(u32),
+    // Address of frame slot (local array, PUSH.ADR)
+    FrameAddress(i32),
+    // Value of frame slot (local variable or function argument)
+    FrameValue(i32),
+    // Value of global variable
+    GlobalValue(u32),
+    // Value returned by native
+    NativeResult(String),
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeCall {
+    pub name: String,
+    pub index: usize,
+    // Cod address of SYSREQ.C
+    pub address: usize,
+    // Containing function name
+    pub function: String,
+    // Arguments in source order
+    pub args: Vec<CallArgument>,
+}
+
+impl NativeCall {
+    // Constant cell passed as argument `n`
+    pub fn constant_arg(&self, n: usize) -> Option<u32> {
+        match self.args.get(n)? {
+            CallArgument::Constant(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    // DAT string passed as argument `n`
+    pub fn string_arg(&self, plugin: &Plugin, n: usize) -> Option<String> {
+        match self.args.get(n)? {
+            CallArgument::Constant(addr) => plugin.read_string(*addr as usize),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Machine {
+    pri: CallArgument,
+    alt: CallArgument,
+    stack: Vec<CallArgument>,
+    cellsize: usize,
+}
+
+impl Machine {
+    fn new(cellsize: usize) -> Machine {
+        Machine {
+            pri: CallArgument::Unknown,
+            alt: CallArgument::Unknown,
+            stack: vec![],
+            cellsize,
+        }
+    }
+
+    fn pop(&mut self) -> CallArgument {
+        self.stack.pop().unwrap_or(CallArgument::Unknown)
+    }
+
+    fn drop_cells(&mut self, bytes: u32) {
+        let count = bytes as usize / self.cellsize;
+        self.stack.truncate(self.stack.len().saturating_sub(count));
+    }
+
+    fn reserve_cells(&mut self, bytes: u32) {
+        let depth = self.stack.len() + bytes as usize / self.cellsize;
+        if depth > MAX_FRAME_CELLS {
+            // Corrupted or huge frame, nothing below it is known anymore
+            trace!("STACK reserves {} bytes, clobbering tracked stack", bytes);
+            self.stack.clear();
+        } else {
+            self.stack.resize(depth, CallArgument::Unknown);
+        }
+    }
+
+    // Arguments of call are on top of the stack, preceded by their size in bytes
+    fn call_arguments(&self) -> Vec<CallArgument> {
+        let count = match self.stack.last() {
+            Some(CallArgument::Constant(size)) => *size as usize / self.cellsize,
+            _ => return vec![],
+        };
+
+        self.stack
+            .iter()
+            .rev()
+            .skip(1)
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    fn step(&mut self, opcode: &Opcode, natives: &[String]) -> Option<(usize, Vec<CallArgument>)> {
+        let param = opcode.param.unwrap_or(0);
+
+        match opcode.code {
+            OP_CONST_PRI => self.pri = CallArgument::Constant(param),
+            OP_CONST_ALT => self.alt = CallArgument::Constant(param),
+            OP_ZERO_PRI => self.pri = CallArgument::Constant(0),
+            OP_ZERO_ALT => self.alt = CallArgument::Constant(0),
+            OP_ADDR_PRI => self.pri = CallArgument::FrameAddress(param as i32),
+            OP_ADDR_ALT => self.alt = CallArgument::FrameAddress(param as i32),
+            OP_LOAD_S_PRI => self.pri = CallArgument::FrameValue(param as i32),
+            OP_LOAD_S_ALT => self.alt = CallArgument::FrameValue(param as i32),
+            OP_LOAD_PRI => self.pri = CallArgument::GlobalValue(param),
+            OP_LOAD_ALT => self.alt = CallArgument::GlobalValue(param),
+            OP_MOVE_PRI => self.pri = self.alt.clone(),
+            OP_MOVE_ALT => self.alt = self.pri.clone(),
+            OP_XCHG => mem::swap(&mut self.pri, &mut self.alt),
+            OP_PUSH_PRI => self.stack.push(self.pri.clone()),
+            OP_PUSH_ALT => self.stack.push(self.alt.clone()),
+            OP_PUSH_C => self.stack.push(CallArgument::Constant(param)),
+            OP_PUSH_S => self.stack.push(CallArgument::FrameValue(param as i32)),
+            OP_PUSH => self.stack.push(CallArgument::GlobalValue(param)),
+            OP_PUSHADDR => self.stack.push(CallArgument::FrameAddress(param as i32)),
+            OP_POP_PRI => self.pri = self.pop(),
+            OP_POP_ALT => self.alt = self.pop(),
+            OP_STACK => {
+                let bytes = param as i32;
+                if bytes > 0 {
+                    self.drop_cells(bytes as u32);
+                } else {
+                    self.reserve_cells(bytes.unsigned_abs());
+                }
+                self.alt = CallArgument::Unknown;
+            }
+            OP_HEAP => self.alt = CallArgument::Unknown,
+            OP_SYSREQ_C => {
+                let index = param as usize;
+                let args = self.call_arguments();
+                self.pri = match natives.get(index) {
+                    Some(name) => CallArgument::NativeResult(name.clone()),
+                    None => CallArgument::Unknown,
+                };
+                return Some((index, args));
+            }
+            OP_CALL => {
+                // Callee removes its arguments with RETN
+                let size = match self.pop() {
+                    CallArgument::Constant(size) => size,
+                    _ => 0,
+                };
+                self.drop_cells(size);
+                self.pri = CallArgument::Unknown;
+            }
+            // Opcodes which do not touch PRI
+            OP_STOR_PRI | OP_STOR_ALT | OP_STOR_S_PRI | OP_STOR_S_ALT | OP_SREF_PRI
+            | OP_SREF_ALT | OP_SREF_S_PRI | OP_SREF_S_ALT | OP_STOR_I | OP_STRB_I | OP_ZERO
+            | OP_ZERO_S | OP_INC | OP_INC_S | OP_DEC | OP_DEC_S | OP_BOUNDS | OP_BREAK | OP_NOP
+            | OP_PROC | OP_JUMP | OP_JZER | OP_JNZ | OP_ALIGN_ALT | OP_SHL_C_ALT | OP_SHR_C_ALT
+            | OP_INC_ALT | OP_DEC_ALT => {}
+            _ => self.pri = CallArgument::Unknown,
+        }
+
+        None
+    }
+}
+
+// Native calls of a single function in cod order
+pub fn function_native_calls(
+    function: &Function,
+    opcodes: &[Opcode],
+    natives: &[String],
+    cellsize: usize,
+) -> Vec<NativeCall> {
+    let mut machine = Machine::new(cellsize);
+    let mut calls = vec![];
+
+    for opcode in function.opcodes(opcodes) {
+        if let Some((index, args)) = machine.step(opcode, natives) {
+            let name = match natives.get(index) {
+                Some(name) => name.clone(),
+                None => {
+                    trace!("SYSREQ.C with invalid native index {}", index);
+                    continue;
+                }
+            };
+
+            calls.push(NativeCall {
+                name,
+                index,
+                address: opcode.address,
+                function: function.name.clone(),
+                args,
+            });
+        }
+    }
+
+    calls
+}
+
+// Recovers every SYSREQ.C call together with arguments pushed before it
+pub fn native_calls(plugin: &Plugin) -> Result<Vec<NativeCall>, AmxError> {
+    let opcodes = plugin.opcodes()?;
+    let natives: Vec<String> = plugin
+        .natives()?
+        .iter()
+        .map(|n| n.name.to_string_lossy().into_owned())
+        .collect();
+
+    let calls = functions(plugin)?
+        .iter()
+        .flat_map(|f| function_native_calls(f, &opcodes, &natives, plugin.cellsize()))
+        .collect();
+
+    Ok(calls)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{native_calls, CallArgument};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    #[test]
+    fn it_recover_native_call_arguments() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+        let calls = native_calls(&amxmod_plugin).unwrap();
+
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "register_plugin");
+        assert_eq!(calls[0].function, "plugin_init");
+        assert_eq!(calls[0].address, 0x34);
+        assert_eq!(
+            calls[0].args,
+            [
+                CallArgument::Constant(0),
+                CallArgument::Constant(0x38),
+                CallArgument::Constant(0x48),
+            ]
+        );
+        assert_eq!(
+            calls[0].string_arg(&amxmod_plugin, 2),
+            Some("Fedcomp".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_track_registers_into_arguments() {
+        let mut builder = PluginBuilder::new();
+        let get_user_flags = builder.native("get_user_flags");
+        let set_user_flags = builder.native("set_user_flags");
+        builder
+            .public("client_connect")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_S, 12)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, get_user_flags)
+            .op_param(OP_STACK, 8)
+            .op(OP_PUSH_PRI)
+            .op_param(OP_ADDR_PRI, 0xFFFF_FFFC)
+            .op(OP_PUSH_PRI)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, set_user_flags)
+            .op_param(OP_STACK, 12)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let calls = native_calls(&amxmod_plugin).unwrap();
+
+        assert_eq!(calls[0].args, [CallArgument::FrameValue(12)]);
+        assert_eq!(
+            calls[1].args,
+            [
+                CallArgument::FrameAddress(-4),
+                CallArgument::NativeResult("get_user_flags".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_clobber_stack_on_corrupted_stack_operand() {
+        let mut builder = PluginBuilder::new();
+        let server_cmd = builder.native("server_cmd");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, 1)
+            .op_param(OP_STACK, 0x8100_0010)
+            .op_param(OP_STACK, 0x7FFF_FFF0)
+            .op_param(OP_PUSH_C, 0x10)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, server_cmd)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let calls = native_calls(&amxmod_plugin).unwrap();
+
+        assert_eq!(calls[0].args, [CallArgument::Constant(0x10)]);
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, OpcodeType, OperandKind, CELLSIZE};
+
+pub fn is_conditional_jump(code: OpcodeType) -> bool {
+    code.info().operand == OperandKind::Branch
+}
+
+// Cod address `opcode` jumps to, `next` is address of instruction after it.
+// JREL is relative to `next`, SWITCH jumps to its case table. Operands of
+// relocated images are cod relative already, see `Plugin::derelocate`.
+pub fn jump_target(opcode: &Opcode, next: usize) -> Option<usize> {
+    let param = opcode.param?;
+    match opcode.code.info().operand {
+        OperandKind::Jump if opcode.code == OP_JREL => {
+            Some(next.wrapping_add(param as i32 as usize))
+        }
+        OperandKind::Jump | OperandKind::Branch | OperandKind::CaseTable => Some(param as usize),
+        _ => None,
+    }
+}
+
+// (index, target) of jumps, switches and case table records. Last opcode
+// ends at `cod_size`.
+pub fn jump_targets(opcodes: &[Opcode], cod_size: usize) -> Vec<(usize, usize)> {
+    opcodes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, opcode)| {
+            let next = opcodes.get(i + 1).map_or(cod_size, |o| o.address);
+            Some((i, jump_target(opcode, next)?))
+        })
+        .collect()
+}
+
+// Like `jump_targets`, calls included
+pub fn code_targets(opcodes: &[Opcode], cod_size: usize) -> Vec<(usize, usize)> {
+    let mut targets = jump_targets(opcodes, cod_size);
+    targets.extend(opcodes.iter().enumerate().filter_map(|(i, opcode)| {
+        match opcode.code.info().operand {
+            OperandKind::Function => Some((i, opcode.param? as usize)),
+            _ => None,
+        }
+    }));
+    targets.sort_unstable();
+    targets
+}
+
+// Jump target of opcode at `i`, last opcode is assumed to have 32 bit cells
+pub fn target_at(opcodes: &[Opcode], i: usize) -> Option<usize> {
+    let opcode = &opcodes[i];
+    let next = opcodes
+        .get(i + 1)
+        .map_or(opcode.address + 2 * CELLSIZE, |o| o.address);
+    jump_target(opcode, next)
+}
+
+// Index of instruction at cod address, `opcodes` are in cod order
+pub fn instruction_index(opcodes: &[Opcode], address: usize) -> Option<usize> {
+    opcodes.binary_search_by_key(&address, |o| o.address).ok()
+}
+
+// (case value, target) of case table at `casetbl` address, None value for
+// the default target
+pub fn case_table(opcodes: &[Opcode], casetbl: usize) -> Vec<(Option<u32>, usize)> {
+    let start = match opcodes
+        .iter()
+        .position(|o| o.address == casetbl && o.code == OP_CASETBL)
+    {
+        Some(i) => i + 1,
+        None => return vec![],
+    };
+
+    let mut cases = vec![];
+    let mut value = None;
+    for opcode in opcodes[start..].iter().take_while(|o| o.code.is_pseudo()) {
+        let param = opcode.param.unwrap_or(0);
+        match opcode.code {
+            OP_CASENONE => cases.push((None, param as usize)),
+            OP_CASE => value = Some(param),
+            _ => cases.push((value.take(), param as usize)),
+        }
+    }
+    cases
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    // Cod address of the first opcode
+    pub start: usize,
+    // Indexes into function opcodes
+    pub opcodes: Range<usize>,
+    pub successors: Vec<usize>,
+    // Leaves function: return, halt or jump with unknown target
+    pub exits: bool,
+}
+
+// Control flow graph of single function
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl Cfg {
+    pub fn from_opcodes(opcodes: &[Opcode]) -> Cfg {
+        let addresses: BTreeSet<usize> = opcodes.iter().map(|o| o.address).collect();
+
+        // Block leaders: first opcode, jump targets and opcodes after jumps
+        let mut leaders: BTreeSet<usize> = BTreeSet::new();
+        for (i, opcode) in opcodes.iter().enumerate() {
+            if i == 0 {
+                leaders.insert(opcode.address);
+            }
+            let is_jump =
+                matches!(opcode.code, OP_JUMP | OP_JREL) || is_conditional_jump(opcode.code);
+            if is_jump {
+                if let Some(target) = target_at(opcodes, i) {
+                    if addresses.contains(&target) {
+                        leaders.insert(target);
+                    }
+                }
+            }
+            if opcode.code == OP_SWITCH {
+                let casetbl = opcode.param.unwrap_or(0) as usize;
+                for (_, target) in case_table(opcodes, casetbl) {
+                    if addresses.contains(&target) {
+                        leaders.insert(target);
+                    }
+                }
+            }
+            // Case table is a block of its own
+            if opcode.code == OP_CASETBL {
+                leaders.insert(opcode.address);
+            }
+            let ends_table =
+                opcode.code.is_pseudo() && !opcodes.get(i + 1).is_some_and(|o| o.code.is_pseudo());
+            let ends_block = is_jump
+                || ends_table
+                || matches!(
+                    opcode.code,
+                    OP_RETN | OP_RET | OP_HALT | OP_SWITCH | OP_JUMP_PRI
+                );
+            if ends_block {
+                if let Some(next) = opcodes.get(i + 1) {
+                    leaders.insert(next.address);
+                }
+            }
+        }
+
+        let starts: Vec<usize> = opcodes
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| leaders.contains(&o.address))
+            .map(|(i, _)| i)
+            .collect();
+
+        let block_index = |address: usize| -> Option<usize> {
+            starts.iter().position(|&i| opcodes[i].address == address)
+        };
+
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(n, &first)| {
+                let end = starts.get(n + 1).cloned().unwrap_or(opcodes.len());
+                let last = &opcodes[end - 1];
+                let target = target_at(opcodes, end - 1).and_then(block_index);
+                let fallthrough = if end < opcodes.len() {
+                    Some(n + 1)
+                } else {
+                    None
+                };
+
+                let (successors, exits) = match last.code {
+                    OP_JUMP | OP_JREL => (target.into_iter().collect(), target.is_none()),
+                    code if is_conditional_jump(code) => (
+                        target.into_iter().chain(fallthrough).collect(),
+                        target.is_none() || fallthrough.is_none(),
+                    ),
+                    OP_SWITCH => {
+                        let casetbl = last.param.unwrap_or(0) as usize;
+                        let targets: Vec<Option<usize>> = case_table(opcodes, casetbl)
+                            .into_iter()
+                            .map(|(_, target)| block_index(target))
+                            .collect();
+                        let mut successors: Vec<usize> = vec![];
+                        for &target in targets.iter().flatten() {
+                            if !successors.contains(&target) {
+                                successors.push(target);
+                            }
+                        }
+                        let exits = targets.is_empty() || targets.contains(&None);
+                        (successors, exits)
+                    }
+                    // Case table is data, never executed
+                    code if code.is_pseudo() || code == OP_CASETBL => (vec![], false),
+                    OP_RETN | OP_RET | OP_HALT | OP_JUMP_PRI => (vec![], true),
+                    _ => (fallthrough.into_iter().collect(), fallthrough.is_none()),
+                };
+
+                BasicBlock {
+                    start: opcodes[first].address,
+                    opcodes: first..end,
+                    successors,
+                    exits,
+                }
+            })
+            .collect();
+
+        Cfg { blocks }
+    }
+
+    pub fn predecessors(&self, block: usize) -> Vec<usize> {
+        (0..self.blocks.len())
+            .filter(|&i| self.blocks[i].successors.contains(&block))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{case_table, instruction_index, jump_target, Cfg};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::{Opcode, OpcodeType};
+
+    fn op(code: OpcodeType, address: usize, param: Option<u32>) -> Opcode {
+        Opcode {
+            code,
+            address,
+            param,
+        }
+    }
+
+    #[test]
+    fn it_split_blocks_on_jumps() {
+        let opcodes = [
+            Opcode {
+                code: OP_PROC,
+                address: 0x8,
+                param: None,
+            },
+            Opcode {
+                code: OP_JZER,
+                address: 0xC,
+                param: Some(0x20),
+            },
+            Opcode {
+                code: OP_ZERO_PRI,
+                address: 0x14,
+                param: None,
+            },
+            Opcode {
+                code: OP_JUMP,
+                address: 0x18,
+                param: Some(0x8),
+            },
+            Opcode {
+                code: OP_RETN,
+                address: 0x20,
+                param: None,
+            },
+        ];
+        let cfg = Cfg::from_opcodes(&opcodes);
+
+        assert_eq!(cfg.blocks.len(), 3);
+        assert_eq!(cfg.blocks[0].successors, [2, 1]);
+        assert_eq!(cfg.blocks[1].successors, [0]);
+        assert!(cfg.blocks[2].exits);
+        assert_eq!(cfg.predecessors(0), [1]);
+    }
+
+    #[test]
+    fn it_resolve_relative_jumps() {
+        let opcodes = [
+            op(OP_PROC, 0x8, None),
+            op(OP_JREL, 0xC, Some(8)),
+            op(OP_ZERO_PRI, 0x14, None),
+            op(OP_INC_PRI, 0x18, None),
+            op(OP_JREL, 0x1C, Some(-0x10_i32 as u32)),
+        ];
+        let cfg = Cfg::from_opcodes(&opcodes);
+
+        assert_eq!(jump_target(&opcodes[1], 0x14), Some(0x1C));
+        assert_eq!(jump_target(&opcodes[2], 0x18), None);
+        assert_eq!(instruction_index(&opcodes, 0x1C), Some(4));
+        assert_eq!(instruction_index(&opcodes, 0x1D), None);
+        let starts: Vec<usize> = cfg.blocks.iter().map(|b| b.start).collect();
+        assert_eq!(starts, [0x8, 0x14, 0x1C]);
+        assert_eq!(cfg.blocks[0].successors, [2]);
+        // Last opcode, 32 bit cells assumed
+        assert_eq!(cfg.blocks[2].successors, [1]);
+        assert!(!cfg.blocks[2].exits);
+    }
+
+    #[test]
+    fn it_follow_switch_to_cases() {
+        let opcodes = [
+            op(OP_PROC, 0x8, None),
+            op(OP_SWITCH, 0xC, Some(0x30)),
+            op(OP_ZERO_PRI, 0x14, None),
+            op(OP_JUMP, 0x18, Some(0x4C)),
+            op(OP_CONST_PRI, 0x20, Some(1)),
+            op(OP_JUMP, 0x28, Some(0x4C)),
+            op(OP_CASETBL, 0x30, Some(2)),
+            op(OP_CASENONE, 0x38, Some(0x4C)),
+            op(OP_CASE, 0x3C, Some(5)),
+            op(OP_CASEJMP, 0x40, Some(0x14)),
+            op(OP_CASE, 0x44, Some(7)),
+            op(OP_CASEJMP, 0x48, Some(0x20)),
+            op(OP_RETN, 0x4C, None),
+        ];
+        let cfg = Cfg::from_opcodes(&opcodes);
+
+        assert_eq!(
+            case_table(&opcodes, 0x30),
+            [(None, 0x4C), (Some(5), 0x14), (Some(7), 0x20)]
+        );
+        let starts: Vec<usize> = cfg.blocks.iter().map(|b| b.start).collect();
+        assert_eq!(starts, [0x8, 0x14, 0x20, 0x30, 0x4C]);
+        assert_eq!(cfg.blocks[0].successors, [4, 1, 2]);
+        assert!(!cfg.blocks[0].exits);
+        assert!(cfg.blocks[3].successors.is_empty());
+        assert!(!cfg.blocks[3].exits);
+    }
+}
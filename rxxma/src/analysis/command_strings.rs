@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use super::calls::{native_calls, CallArgument};
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+// Natives executing console commands, with position of command argument
+const COMMAND_NATIVES: &[(&str, usize)] = &[("server_cmd", 0), ("client_cmd", 1)];
+// Natives writing formatted string into buffer: (name, buffer arg, format arg)
+const FORMAT_NATIVES: &[(&str, usize, usize)] = &[("format", 0, 2), ("formatex", 0, 2)];
+// Natives copying constant string into buffer: (name, buffer arg, source arg)
+const COPY_NATIVES: &[(&str, usize, usize)] = &[("copy", 0, 2)];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandValue {
+    // Whole command is a DAT string
+    Constant(String),
+    // Command buffer was filled by format/formatex, only format string is known
+    Formatted { format: String },
+    Dynamic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandString {
+    pub native: String,
+    // Cod address of SYSREQ.C
+    pub address: usize,
+    pub function: String,
+    pub value: CommandValue,
+}
+
+// Collects server_cmd / client_cmd command arguments
+pub fn command_strings(plugin: &Plugin) -> Result<Vec<CommandString>, AmxError> {
+    let mut result = vec![];
+    // Last known contents of buffers, reset on function change
+    let mut buffers: HashMap<CallArgument, CommandValue> = HashMap::new();
+    let mut current_function = String::new();
+
+    for call in native_calls(plugin)? {
+        if call.function != current_function {
+            current_function = call.function.clone();
+            buffers.clear();
+        }
+
+        if let Some((_, buffer, format)) = FORMAT_NATIVES.iter().find(|n| n.0 == call.name) {
+            if let Some(buffer) = call.args.get(*buffer) {
+                let value = match call.string_arg(plugin, *format) {
+                    Some(format) => CommandValue::Formatted { format },
+                    None => CommandValue::Dynamic,
+                };
+                buffers.insert(buffer.clone(), value);
+            }
+            continue;
+        }
+
+        if let Some((_, buffer, source)) = COPY_NATIVES.iter().find(|n| n.0 == call.name) {
+            if let Some(buffer) = call.args.get(*buffer) {
+                let value = match call.string_arg(plugin, *source) {
+                    Some(source) => CommandValue::Constant(source),
+                    None => CommandValue::Dynamic,
+                };
+                buffers.insert(buffer.clone(), value);
+            }
+            continue;
+        }
+
+        let command_arg = match COMMAND_NATIVES.iter().find(|n| n.0 == call.name) {
+            Some((_, arg)) => *arg,
+            None => continue,
+        };
+
+        let value = match call.args.get(command_arg) {
+            Some(arg) => match buffers.get(arg) {
+                Some(value) => value.clone(),
+                None => match call.string_arg(plugin, command_arg) {
+                    Some(command) => CommandValue::Constant(command),
+                    None => CommandValue::Dynamic,
+                },
+            },
+            None => CommandValue::Dynamic,
+        };
+
+        result.push(CommandString {
+            native: call.name,
+            address: call.address,
+            function: call.function,
+            value,
+        });
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{command_strings, CommandString, CommandValue};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    fn commands_plugin() -> Plugin<'static> {
+        let mut builder = PluginBuilder::new();
+        let server_cmd = builder.native("server_cmd");
+        let client_cmd = builder.native("client_cmd");
+        let formatex = builder.native("formatex");
+        let exec = builder.string("exec banned.cfg");
+        let kick = builder.string("kick %s");
+
+        // server_cmd("exec banned.cfg")
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, exec)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, server_cmd)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+
+        // new cmd[64]; formatex(cmd, 63, "kick %s", name); client_cmd(id, cmd)
+        builder
+            .public("client_putinserver")
+            .op(OP_PROC)
+            .op_param(OP_STACK, (-256i32) as u32)
+            .op_param(OP_PUSH_S, 16)
+            .op_param(OP_PUSH_C, kick)
+            .op_param(OP_PUSH_C, 63)
+            .op_param(OP_PUSHADDR, (-256i32) as u32)
+            .op_param(OP_PUSH_C, 16)
+            .op_param(OP_SYSREQ_C, formatex)
+            .op_param(OP_STACK, 20)
+            .op_param(OP_PUSHADDR, (-256i32) as u32)
+            .op_param(OP_PUSH_S, 12)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, client_cmd)
+            .op_param(OP_STACK, 12)
+            .op_param(OP_STACK, 256)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+
+        Plugin::try_from(builder.build()).unwrap()
+    }
+
+    #[test]
+    fn it_collect_constant_and_formatted_commands() {
+        let plugin = commands_plugin();
+
+        assert_eq!(
+            command_strings(&plugin).unwrap(),
+            [
+                CommandString {
+                    native: "server_cmd".to_owned(),
+                    address: 0x1c,
+                    function: "plugin_init".to_owned(),
+                    value: CommandValue::Constant("exec banned.cfg".to_owned()),
+                },
+                CommandString {
+                    native: "client_cmd".to_owned(),
+                    address: 0x90,
+                    function: "client_putinserver".to_owned(),
+                    value: CommandValue::Formatted {
+                        format: "kick %s".to_owned()
+                    },
+                },
+            ]
+        );
+    }
+}
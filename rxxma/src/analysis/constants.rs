@@ -0,0 +1,820 @@
+// Constant propagation through PRI, ALT, frame cells and globals written by
+// function itself. Values computed from constants at runtime, like XOR
+// decoded characters or arithmetic obfuscated flags, are resolved where
+// they are stored, pushed or returned. Only 32 bit plugins.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::cfg::Cfg;
+use super::functions::functions;
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, OpcodeType, Plugin};
+use crate::error::AmxError;
+
+const CELL: i32 = 4;
+// Longest string resolved from memory cells
+const MAX_STRING: i32 = 1024;
+// Most cells written one by one by PUSH.R, FILL or MOVS, past it memory is
+// forgotten instead
+const MAX_REPEAT: i32 = 0x4000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstantValue {
+    Cell(i32),
+    // Characters of array passed by address
+    String(String),
+}
+
+impl fmt::Display for ConstantValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstantValue::Cell(value) => write!(f, "{}", value),
+            ConstantValue::String(text) => write!(f, "{:?}", text),
+        }
+    }
+}
+
+// Where opcode takes resolved value from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Pri,
+    Alt,
+    // Opcode parameter or memory it addresses, e.g. PUSH.C or PUSHADDR
+    Param,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedValue {
+    // Cod address of opcode storing, pushing, returning or combining the
+    // value with unknown one
+    pub address: usize,
+    pub operand: Operand,
+    pub value: ConstantValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Unknown,
+    // Computed when arithmetic produced it at runtime
+    Constant { value: i32, computed: bool },
+    // Address of frame cell at offset
+    Frame(i32),
+}
+
+impl Value {
+    fn constant(value: i32) -> Value {
+        Value::Constant {
+            value,
+            computed: false,
+        }
+    }
+
+    fn is_computed(self) -> bool {
+        matches!(self, Value::Constant { computed: true, .. })
+    }
+
+    // Variable loaded into register, value was resolved when stored
+    fn loaded(self) -> Value {
+        match self {
+            Value::Constant { value, .. } => Value::constant(value),
+            other => other,
+        }
+    }
+
+    fn unary<F: Fn(i32) -> i32>(self, operation: F) -> Value {
+        match self {
+            Value::Constant { value, .. } => Value::Constant {
+                value: operation(value),
+                computed: true,
+            },
+            _ => Value::Unknown,
+        }
+    }
+
+    fn binary<F: Fn(i32, i32) -> Option<i32>>(self, other: Value, operation: F) -> Value {
+        match (self, other) {
+            (Value::Constant { value: a, .. }, Value::Constant { value: b, .. }) => {
+                match operation(a, b) {
+                    Some(value) => Value::Constant {
+                        value,
+                        computed: true,
+                    },
+                    None => Value::Unknown,
+                }
+            }
+            _ => Value::Unknown,
+        }
+    }
+
+    // Pointer moved by `bytes`, computed flag comes from operands
+    fn offset(self, bytes: Value) -> Value {
+        match (self, bytes) {
+            (Value::Frame(offset), Value::Constant { value, .. }) => Value::Frame(offset + value),
+            (Value::Constant { value: a, computed }, Value::Constant { value: b, .. }) => {
+                Value::Constant {
+                    value: a.wrapping_add(b),
+                    computed,
+                }
+            }
+            _ => Value::Unknown,
+        }
+    }
+}
+
+fn merge(a: Value, b: Value) -> Value {
+    if a == b {
+        a
+    } else {
+        Value::Unknown
+    }
+}
+
+fn merge_cells(a: &BTreeMap<i32, Value>, b: &BTreeMap<i32, Value>) -> BTreeMap<i32, Value> {
+    a.iter()
+        .filter(|&(key, value)| b.get(key) == Some(value))
+        .map(|(&key, &value)| (key, value))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    pri: Value,
+    alt: Value,
+    // Stack top relative to frame, None once unknown
+    stk: Option<i32>,
+    // Known cells by frame offset and by DAT address
+    frame: BTreeMap<i32, Value>,
+    globals: BTreeMap<i32, Value>,
+    // Frame address was handed out, callees may write frame
+    escaped: bool,
+}
+
+impl State {
+    fn entry() -> State {
+        State {
+            pri: Value::Unknown,
+            alt: Value::Unknown,
+            stk: Some(0),
+            frame: BTreeMap::new(),
+            globals: BTreeMap::new(),
+            escaped: false,
+        }
+    }
+
+    fn merge(&self, other: &State) -> State {
+        State {
+            pri: merge(self.pri, other.pri),
+            alt: merge(self.alt, other.alt),
+            stk: if self.stk == other.stk {
+                self.stk
+            } else {
+                None
+            },
+            frame: merge_cells(&self.frame, &other.frame),
+            globals: merge_cells(&self.globals, &other.globals),
+            escaped: self.escaped || other.escaped,
+        }
+    }
+
+    fn read(&self, pointer: Value) -> Value {
+        let cell = match pointer {
+            Value::Frame(offset) => self.frame.get(&offset),
+            Value::Constant { value, .. } => self.globals.get(&value),
+            Value::Unknown => None,
+        };
+        cell.cloned().unwrap_or(Value::Unknown)
+    }
+
+    fn write(&mut self, pointer: Value, value: Value) {
+        if let Value::Frame(_) = value {
+            self.escaped = true;
+        }
+        match pointer {
+            Value::Frame(offset) => {
+                self.frame.insert(offset, value);
+            }
+            Value::Constant { value: address, .. } => {
+                self.globals.insert(address, value);
+            }
+            Value::Unknown => self.clobber(),
+        }
+    }
+
+    // Write to unknown memory: globals and escaped frame cells
+    fn clobber(&mut self) {
+        self.globals.clear();
+        if self.escaped {
+            self.frame.clear();
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        if let Value::Frame(_) = value {
+            self.escaped = true;
+        }
+        self.stk = self.stk.and_then(|stk| stk.checked_sub(CELL));
+        match self.stk {
+            Some(stk) => {
+                self.frame.insert(stk, value);
+            }
+            None => self.clobber(),
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        let value = match self.stk {
+            Some(stk) => self.read(Value::Frame(stk)),
+            None => Value::Unknown,
+        };
+        self.stk = self.stk.and_then(|stk| stk.checked_add(CELL));
+        value
+    }
+
+    // Cells below released stack top are gone
+    fn release(&mut self, bytes: i32) {
+        self.stk = self.stk.and_then(|stk| stk.checked_add(bytes));
+        match self.stk {
+            Some(stk) => self.frame.retain(|&offset, _| offset >= stk),
+            None => self.frame.clear(),
+        }
+    }
+
+    // Call to function or native
+    fn call(&mut self) {
+        self.clobber();
+        self.pri = Value::Unknown;
+        self.alt = Value::Unknown;
+    }
+
+    fn forget_stack(&mut self) {
+        self.stk = None;
+        self.frame.clear();
+    }
+
+    // Block write of unknown extent
+    fn forget_memory(&mut self) {
+        self.globals.clear();
+        self.frame.clear();
+    }
+}
+
+// Outcome of conditional jump, if registers it tests are constants
+fn branch(code: OpcodeType, pri: Value, alt: Value) -> Option<bool> {
+    let pri = match pri {
+        Value::Constant { value, .. } => value,
+        _ => return None,
+    };
+    if let OP_JZER | OP_JNZ = code {
+        return Some((pri == 0) == (code == OP_JZER));
+    }
+    let alt = match alt {
+        Value::Constant { value, .. } => value,
+        _ => return None,
+    };
+    let taken = match code {
+        OP_JEQ => pri == alt,
+        OP_JNEQ => pri != alt,
+        OP_JLESS => (pri as u32) < alt as u32,
+        OP_JLEQ => pri as u32 <= alt as u32,
+        OP_JGRTR => pri as u32 > alt as u32,
+        OP_JGEQ => pri as u32 >= alt as u32,
+        OP_JSLESS => pri < alt,
+        OP_JSLEQ => pri <= alt,
+        OP_JSGRTR => pri > alt,
+        OP_JSGEQ => pri >= alt,
+        _ => return None,
+    };
+    Some(taken)
+}
+
+// Opcodes combining PRI with ALT
+fn is_binary(code: OpcodeType) -> bool {
+    matches!(
+        code,
+        OP_ADD
+            | OP_SUB
+            | OP_SUB_ALT
+            | OP_SMUL
+            | OP_UMUL
+            | OP_SDIV
+            | OP_UDIV
+            | OP_SDIV_ALT
+            | OP_UDIV_ALT
+            | OP_AND
+            | OP_OR
+            | OP_XOR
+            | OP_SHL
+            | OP_SHR
+            | OP_SSHR
+            | OP_EQ
+            | OP_NEQ
+            | OP_LESS
+            | OP_LEQ
+            | OP_GRTR
+            | OP_GEQ
+            | OP_SLESS
+            | OP_SLEQ
+            | OP_SGRTR
+            | OP_SGEQ
+    )
+}
+
+struct Propagation<'a> {
+    // Initial DAT, read for strings in globals not written by function
+    dat: &'a [u8],
+    resolved: Vec<ResolvedValue>,
+    // Conditional jumps with known registers, whether they are taken
+    branches: Vec<(usize, bool)>,
+}
+
+impl<'a> Propagation<'a> {
+    fn dat_cell(&self, address: i32) -> Option<i32> {
+        let start = address as usize;
+        if address < 0 || start + CELL as usize > self.dat.len() {
+            return None;
+        }
+        Some(LittleEndian::read_i32(&self.dat[start..]))
+    }
+
+    // Zero terminated characters at pointer, when at least one of them was
+    // computed
+    fn string(&self, state: &State, pointer: Value) -> Option<String> {
+        let mut bytes = vec![];
+        let mut computed = false;
+        for n in 0..MAX_STRING {
+            let cell = pointer.offset(Value::constant(n * CELL));
+            let character = match (state.read(cell), cell) {
+                (Value::Constant { value, computed: c }, _) => {
+                    computed |= c;
+                    value
+                }
+                (Value::Unknown, Value::Constant { value, .. })
+                    if !state.globals.contains_key(&value) =>
+                {
+                    self.dat_cell(value)?
+                }
+                _ => return None,
+            };
+            match character {
+                0 => break,
+                c @ 0x20..=0xFF | c @ 0x09..=0x0D => bytes.push(c as u8),
+                _ => return None,
+            }
+        }
+        if !computed || bytes.is_empty() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    // Computed cell, or string behind pushed pointer
+    fn consume(&mut self, opcode: &Opcode, state: &State, operand: Operand, value: Value) {
+        let pushed = matches!(
+            opcode.code,
+            OP_PUSH_PRI | OP_PUSH_ALT | OP_PUSH_C | OP_PUSH | OP_PUSH_S | OP_PUSHADDR
+        );
+        let value = match value {
+            Value::Constant {
+                value,
+                computed: true,
+            } => ConstantValue::Cell(value),
+            pointer if pushed => match self.string(state, pointer) {
+                Some(text) => ConstantValue::String(text),
+                None => return,
+            },
+            _ => return,
+        };
+        self.resolved.push(ResolvedValue {
+            address: opcode.address,
+            operand,
+            value,
+        });
+    }
+
+    // Effect of opcode on state, with values resolved when `report`
+    fn step(&mut self, state: &mut State, opcode: &Opcode, report: bool) {
+        let param = opcode.param.unwrap_or(0) as i32;
+        let (pri, alt) = (state.pri, state.alt);
+        let global = Value::constant(param);
+        let local = Value::Frame(param);
+
+        if report {
+            let consumed = match opcode.code {
+                OP_STOR_PRI | OP_STOR_S_PRI | OP_SREF_PRI | OP_SREF_S_PRI | OP_STOR_I
+                | OP_PUSH_PRI | OP_RETN => Some((Operand::Pri, pri)),
+                OP_STOR_ALT | OP_STOR_S_ALT | OP_SREF_ALT | OP_SREF_S_ALT | OP_PUSH_ALT => {
+                    Some((Operand::Alt, alt))
+                }
+                OP_PUSH_C => Some((Operand::Param, global)),
+                OP_PUSH => Some((Operand::Param, state.read(global).loaded())),
+                OP_PUSH_S => Some((Operand::Param, state.read(local).loaded())),
+                OP_PUSHADDR => Some((Operand::Param, local)),
+                // Computed operand mixed with unknown value
+                code if is_binary(code) && pri.is_computed() && alt == Value::Unknown => {
+                    Some((Operand::Pri, pri))
+                }
+                code if is_binary(code) && alt.is_computed() && pri == Value::Unknown => {
+                    Some((Operand::Alt, alt))
+                }
+                _ => None,
+            };
+            if let Some((operand, value)) = consumed {
+                self.consume(opcode, state, operand, value);
+            }
+            if let Some(taken) = branch(opcode.code, pri, alt) {
+                self.branches.push((opcode.address, taken));
+            }
+        }
+
+        match opcode.code {
+            OP_PROC => *state = State::entry(),
+            OP_LOAD_PRI => state.pri = state.read(global).loaded(),
+            OP_LOAD_ALT => state.alt = state.read(global).loaded(),
+            OP_LOAD_S_PRI => state.pri = state.read(local).loaded(),
+            OP_LOAD_S_ALT => state.alt = state.read(local).loaded(),
+            OP_LREF_PRI => state.pri = state.read(state.read(global)).loaded(),
+            OP_LREF_ALT => state.alt = state.read(state.read(global)).loaded(),
+            OP_LREF_S_PRI => state.pri = state.read(state.read(local)).loaded(),
+            OP_LREF_S_ALT => state.alt = state.read(state.read(local)).loaded(),
+            OP_LOAD_I => state.pri = state.read(pri).loaded(),
+            OP_CONST_PRI => state.pri = global,
+            OP_CONST_ALT => state.alt = global,
+            OP_ADDR_PRI => state.pri = local,
+            OP_ADDR_ALT => state.alt = local,
+            OP_ZERO_PRI => state.pri = Value::constant(0),
+            OP_ZERO_ALT => state.alt = Value::constant(0),
+            OP_MOVE_PRI => state.pri = alt,
+            OP_MOVE_ALT => state.alt = pri,
+            OP_XCHG => {
+                state.pri = alt;
+                state.alt = pri;
+            }
+
+            OP_STOR_PRI => state.write(global, pri),
+            OP_STOR_ALT => state.write(global, alt),
+            OP_STOR_S_PRI => state.write(local, pri),
+            OP_STOR_S_ALT => state.write(local, alt),
+            OP_SREF_PRI => state.write(state.read(global), pri),
+            OP_SREF_ALT => state.write(state.read(global), alt),
+            OP_SREF_S_PRI => state.write(state.read(local), pri),
+            OP_SREF_S_ALT => state.write(state.read(local), alt),
+            OP_STOR_I => state.write(alt, pri),
+            OP_STRB_I => state.write(alt, Value::Unknown),
+            OP_ZERO => state.write(global, Value::constant(0)),
+            OP_ZERO_S => state.write(local, Value::constant(0)),
+            OP_INC | OP_INC_S | OP_INC_I | OP_DEC | OP_DEC_S | OP_DEC_I => {
+                let pointer = match opcode.code {
+                    OP_INC | OP_DEC => global,
+                    OP_INC_S | OP_DEC_S => local,
+                    _ => pri,
+                };
+                let delta = if matches!(opcode.code, OP_INC | OP_INC_S | OP_INC_I) {
+                    1
+                } else {
+                    -1
+                };
+                let value = state.read(pointer).unary(|v| v.wrapping_add(delta));
+                state.write(pointer, value);
+            }
+            OP_FILL | OP_MOVS if param / CELL > MAX_REPEAT => state.forget_memory(),
+            OP_FILL => {
+                for n in 0..param / CELL {
+                    state.write(alt.offset(Value::constant(n * CELL)), pri);
+                }
+            }
+            OP_MOVS => {
+                for n in 0..param / CELL {
+                    let bytes = Value::constant(n * CELL);
+                    let value = state.read(pri.offset(bytes));
+                    state.write(alt.offset(bytes), value);
+                }
+            }
+
+            OP_LIDX => state.pri = state.read(alt.offset(pri.unary(|i| i * CELL))).loaded(),
+            OP_LIDX_B => state.pri = state.read(alt.offset(pri.unary(|i| i << param))).loaded(),
+            OP_IDXADDR => state.pri = alt.offset(pri.unary(|i| i * CELL)),
+            OP_IDXADDR_B => state.pri = alt.offset(pri.unary(|i| i << param)),
+
+            OP_PUSH_PRI => state.push(pri),
+            OP_PUSH_ALT => state.push(alt),
+            OP_PUSH_C => state.push(global),
+            OP_PUSH => state.push(state.read(global)),
+            OP_PUSH_S => state.push(state.read(local)),
+            OP_PUSHADDR => state.push(local),
+            OP_PUSH_R if param > MAX_REPEAT => state.forget_stack(),
+            OP_PUSH_R => {
+                for _ in 0..param {
+                    state.push(pri);
+                }
+            }
+            OP_POP_PRI => state.pri = state.pop(),
+            OP_POP_ALT => state.alt = state.pop(),
+            OP_STACK => {
+                state.alt = Value::Unknown;
+                state.release(param);
+            }
+            OP_HEAP => state.alt = Value::Unknown,
+
+            OP_ADD => state.pri = pri.binary(alt, |a, b| Some(a.wrapping_add(b))),
+            OP_SUB => state.pri = pri.binary(alt, |a, b| Some(a.wrapping_sub(b))),
+            OP_SUB_ALT => state.pri = alt.binary(pri, |a, b| Some(a.wrapping_sub(b))),
+            OP_SMUL | OP_UMUL => state.pri = pri.binary(alt, |a, b| Some(a.wrapping_mul(b))),
+            OP_SDIV | OP_UDIV | OP_SDIV_ALT | OP_UDIV_ALT => {
+                let (dividend, divisor) = match opcode.code {
+                    OP_SDIV | OP_UDIV => (pri, alt),
+                    _ => (alt, pri),
+                };
+                state.pri = dividend.binary(divisor, |a, b| a.checked_div(b));
+                state.alt = dividend.binary(divisor, |a, b| a.checked_rem(b));
+            }
+            OP_AND => state.pri = pri.binary(alt, |a, b| Some(a & b)),
+            OP_OR => state.pri = pri.binary(alt, |a, b| Some(a | b)),
+            OP_XOR => state.pri = pri.binary(alt, |a, b| Some(a ^ b)),
+            OP_SHL => state.pri = pri.binary(alt, |a, b| Some(a.wrapping_shl(b as u32))),
+            OP_SHR => {
+                state.pri = pri.binary(alt, |a, b| Some((a as u32).wrapping_shr(b as u32) as i32))
+            }
+            OP_SSHR => state.pri = pri.binary(alt, |a, b| Some(a.wrapping_shr(b as u32))),
+            OP_SHL_C_PRI => state.pri = pri.unary(|a| a.wrapping_shl(param as u32)),
+            OP_SHL_C_ALT => state.alt = alt.unary(|a| a.wrapping_shl(param as u32)),
+            OP_SHR_C_PRI => state.pri = pri.unary(|a| (a as u32).wrapping_shr(param as u32) as i32),
+            OP_SHR_C_ALT => state.alt = alt.unary(|a| (a as u32).wrapping_shr(param as u32) as i32),
+            OP_ADD_C => state.pri = pri.unary(|a| a.wrapping_add(param)),
+            OP_SMUL_C => state.pri = pri.unary(|a| a.wrapping_mul(param)),
+            OP_NOT => state.pri = pri.unary(|a| i32::from(a == 0)),
+            OP_NEG => state.pri = pri.unary(i32::wrapping_neg),
+            OP_INVERT => state.pri = pri.unary(|a| !a),
+            OP_INC_PRI => state.pri = pri.unary(|a| a.wrapping_add(1)),
+            OP_DEC_PRI => state.pri = pri.unary(|a| a.wrapping_sub(1)),
+            OP_INC_ALT => state.alt = alt.unary(|a| a.wrapping_add(1)),
+            OP_DEC_ALT => state.alt = alt.unary(|a| a.wrapping_sub(1)),
+            OP_SIGN_PRI => state.pri = pri.unary(|a| i32::from(a as i8)),
+            OP_SIGN_ALT => state.alt = alt.unary(|a| i32::from(a as i8)),
+            OP_EQ => state.pri = pri.binary(alt, |a, b| Some(i32::from(a == b))),
+            OP_NEQ => state.pri = pri.binary(alt, |a, b| Some(i32::from(a != b))),
+            OP_LESS => state.pri = pri.binary(alt, |a, b| Some(i32::from((a as u32) < b as u32))),
+            OP_LEQ => state.pri = pri.binary(alt, |a, b| Some(i32::from(a as u32 <= b as u32))),
+            OP_GRTR => state.pri = pri.binary(alt, |a, b| Some(i32::from(a as u32 > b as u32))),
+            OP_GEQ => state.pri = pri.binary(alt, |a, b| Some(i32::from(a as u32 >= b as u32))),
+            OP_SLESS => state.pri = pri.binary(alt, |a, b| Some(i32::from(a < b))),
+            OP_SLEQ => state.pri = pri.binary(alt, |a, b| Some(i32::from(a <= b))),
+            OP_SGRTR => state.pri = pri.binary(alt, |a, b| Some(i32::from(a > b))),
+            OP_SGEQ => state.pri = pri.binary(alt, |a, b| Some(i32::from(a >= b))),
+            OP_EQ_C_PRI => state.pri = pri.unary(|a| i32::from(a == param)),
+            OP_EQ_C_ALT => state.pri = alt.unary(|a| i32::from(a == param)),
+
+            // Callee removes its arguments and their size
+            OP_CALL => {
+                let size = state.read(state.stk.map_or(Value::Unknown, Value::Frame));
+                state.call();
+                match size {
+                    Value::Constant { value, .. } => match value.checked_add(CELL) {
+                        Some(bytes) => state.release(bytes),
+                        None => state.forget_stack(),
+                    },
+                    _ => state.forget_stack(),
+                }
+            }
+            OP_CALL_PRI | OP_SYSREQ_C | OP_SYSREQ_PRI | OP_SYSREQ_D => state.call(),
+            OP_SCTRL => state.forget_stack(),
+            OP_LCTRL | OP_LODB_I | OP_SWAP_PRI => state.pri = Value::Unknown,
+            OP_SWAP_ALT => state.alt = Value::Unknown,
+            OP_ALIGN_PRI => state.pri = Value::Unknown,
+            OP_ALIGN_ALT => state.alt = Value::Unknown,
+            _ => {}
+        }
+
+        // Swapped cell on stack top is replaced by register
+        if let OP_SWAP_PRI | OP_SWAP_ALT = opcode.code {
+            let register = if opcode.code == OP_SWAP_PRI { pri } else { alt };
+            match state.stk {
+                Some(stk) => {
+                    state.frame.insert(stk, register);
+                }
+                None => state.clobber(),
+            }
+        }
+    }
+}
+
+fn propagate<'a>(plugin: &'a Plugin, opcodes: &[Opcode]) -> Propagation<'a> {
+    let mut propagation = Propagation {
+        dat: plugin.dat_slice().unwrap_or_default(),
+        resolved: vec![],
+        branches: vec![],
+    };
+    if plugin.cellsize() != CELL as usize || opcodes.is_empty() {
+        return propagation;
+    }
+
+    // Entry states until nothing changes, values only lose precision
+    let cfg = Cfg::from_opcodes(opcodes);
+    let mut entries: Vec<Option<State>> = vec![None; cfg.blocks.len()];
+    entries[0] = Some(State::entry());
+    let mut worklist = vec![0];
+    while let Some(block) = worklist.pop() {
+        let mut state = match entries[block] {
+            Some(ref state) => state.clone(),
+            None => continue,
+        };
+        for opcode in opcodes[cfg.blocks[block].opcodes.clone()].iter() {
+            propagation.step(&mut state, opcode, false);
+        }
+        for &successor in cfg.blocks[block].successors.iter() {
+            let merged = match entries[successor] {
+                Some(ref entry) => entry.merge(&state),
+                None => state.clone(),
+            };
+            if entries[successor].as_ref() != Some(&merged) {
+                entries[successor] = Some(merged);
+                worklist.push(successor);
+            }
+        }
+    }
+
+    for (block, entry) in cfg.blocks.iter().zip(entries) {
+        let mut state = match entry {
+            Some(state) => state,
+            None => continue,
+        };
+        for opcode in opcodes[block.opcodes.clone()].iter() {
+            propagation.step(&mut state, opcode, true);
+        }
+    }
+    propagation.resolved.sort_by_key(|r| r.address);
+    propagation.branches.sort();
+    propagation
+}
+
+// Values of single function opcodes, starting at its PROC
+pub fn propagate_constants(plugin: &Plugin, opcodes: &[Opcode]) -> Vec<ResolvedValue> {
+    propagate(plugin, opcodes).resolved
+}
+
+// Conditional jumps of single function always or never taken, as
+// (address, taken). Compiler folds constant conditions, so these are
+// opaque predicates.
+pub fn decided_branches(plugin: &Plugin, opcodes: &[Opcode]) -> Vec<(usize, bool)> {
+    propagate(plugin, opcodes).branches
+}
+
+// Resolved values of every function, in cod order
+pub fn resolved_values(plugin: &Plugin) -> Result<Vec<ResolvedValue>, AmxError> {
+    let opcodes = plugin.opcodes()?;
+    Ok(functions(plugin)?
+        .iter()
+        .flat_map(|f| propagate_constants(plugin, f.opcodes(&opcodes)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{decided_branches, resolved_values, ConstantValue, Operand, ResolvedValue};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_resolve_arithmetic_obfuscation() {
+        let mut builder = PluginBuilder::new();
+        let get_user_flags = builder.native("get_user_flags");
+        builder.public("is_admin").op(OP_PROC);
+        let call = builder.here();
+        builder
+            .op_param(OP_PUSH_S, 0xC)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, get_user_flags)
+            .op_param(OP_STACK, 8)
+            .op(OP_PUSH_PRI)
+            // ADMIN_IMMUNITY hidden as 0x40 << 3 - 0x100 + 0x101
+            .op_param(OP_CONST_PRI, 0x40)
+            .op_param(OP_SHL_C_PRI, 3)
+            .op_param(OP_ADD_C, (-0x100i32) as u32)
+            .op_param(OP_ADD_C, 0x101)
+            .op(OP_POP_ALT)
+            .op(OP_AND)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(
+            resolved_values(&plugin).unwrap(),
+            [ResolvedValue {
+                address: call as usize + 72,
+                operand: Operand::Pri,
+                value: ConstantValue::Cell(0x201),
+            }]
+        );
+    }
+
+    #[test]
+    fn it_resolve_xor_decoded_string() {
+        let mut builder = PluginBuilder::new();
+        let server_cmd = builder.native("server_cmd");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_STACK, (-12i32) as u32);
+        // new cmd[3]; cmd[n] = encoded[n] ^ 0x1E, terminator included
+        for (n, cell) in [0x6F ^ 0x1E, 0x6B ^ 0x1E, 0x1E].iter().enumerate() {
+            builder
+                .op_param(OP_CONST_PRI, *cell)
+                .op_param(OP_CONST_ALT, 0x1E)
+                .op(OP_XOR)
+                .op(OP_PUSH_PRI)
+                .op_param(OP_CONST_PRI, n as u32)
+                .op_param(OP_ADDR_ALT, (-12i32) as u32)
+                .op(OP_IDXADDR)
+                .op(OP_MOVE_ALT)
+                .op(OP_POP_PRI)
+                .op(OP_STOR_I);
+        }
+        let push = builder.here();
+        builder
+            .op_param(OP_PUSHADDR, (-12i32) as u32)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, server_cmd)
+            .op_param(OP_STACK, 8)
+            .op_param(OP_STACK, 12)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let resolved = resolved_values(&plugin).unwrap();
+        let strings: Vec<&ResolvedValue> = resolved
+            .iter()
+            .filter(|r| matches!(r.value, ConstantValue::String(_)))
+            .collect();
+        assert_eq!(
+            strings,
+            [&ResolvedValue {
+                address: push as usize,
+                operand: Operand::Param,
+                value: ConstantValue::String("ok".to_owned()),
+            }]
+        );
+        assert_eq!(resolved[0].value, ConstantValue::Cell(0x6F));
+    }
+
+    #[test]
+    fn it_forget_values_changed_in_loop() {
+        let mut builder = PluginBuilder::new();
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, 0);
+        let top = builder.here();
+        builder
+            .op_param(OP_LOAD_S_PRI, (-4i32) as u32)
+            .op_param(OP_ADD_C, 1)
+            .op_param(OP_STOR_S_PRI, (-4i32) as u32)
+            .op_param(OP_JUMP, top)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(resolved_values(&plugin).unwrap(), []);
+    }
+
+    #[test]
+    fn it_decide_opaque_predicates() {
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init").op(OP_PROC);
+        let always = builder.here() + 16;
+        let unknown = always + 16;
+        builder
+            .op_param(OP_CONST_PRI, 7)
+            .op_param(OP_SMUL_C, 7)
+            .op_param(OP_JNZ, unknown)
+            .op_param(OP_LOAD_S_PRI, 0xC)
+            .op_param(OP_JZER, unknown + 8)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+        let opcodes = plugin.opcodes().unwrap();
+
+        assert_eq!(
+            decided_branches(&plugin, &opcodes[1..]),
+            [(always as usize, true)]
+        );
+    }
+
+    #[test]
+    fn it_survive_corrupted_stack_operands() {
+        let mut builder = PluginBuilder::new();
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_STACK, 0x7FFF_FFFF)
+            .op_param(OP_STACK, 0x7FFF_FFFF)
+            .op_param(OP_PUSH_R, 0x7FFF_FFFF)
+            .op_param(OP_FILL, 0x7FFF_FFFC)
+            .op(OP_POP_PRI)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(resolved_values(&plugin).unwrap(), []);
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use super::call_graph::call_graph;
+use super::entropy::referenced_addresses;
+use super::functions::Function;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnreferencedData {
+    // DAT address
+    pub address: usize,
+    pub size: usize,
+    // Text of region which is a whole DAT string
+    pub string: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadCode {
+    // Functions no public or main() reaches through calls
+    pub functions: Vec<Function>,
+    // DAT no cod operand or public variable points into
+    pub data: Vec<UnreferencedData>,
+}
+
+// DAT ranges of strings and referenced arrays together with strings nothing
+// references. Array lasts until the next reference or string.
+fn referenced_ranges(
+    plugin: &Plugin,
+) -> Result<(Vec<Range<usize>>, Vec<UnreferencedData>), AmxError> {
+    let dat_size = plugin.dat_slice()?.len();
+    let mut references = referenced_addresses(plugin, dat_size)?;
+    references.extend(plugin.pubvars()?.iter().map(|v| v.address));
+    let strings = plugin.strings()?;
+
+    let mut boundaries: BTreeSet<usize> = references.clone();
+    boundaries.extend(strings.iter().map(|s| s.address));
+    boundaries.insert(dat_size);
+
+    let mut ranges = vec![];
+    let mut unreferenced = vec![];
+    for string in strings.iter() {
+        let range = string.address..string.address + string.size;
+        if !references.iter().any(|r| range.contains(r)) {
+            unreferenced.push(UnreferencedData {
+                address: string.address,
+                size: string.size,
+                string: Some(string.to_string_lossy()),
+            });
+        }
+        ranges.push(range);
+    }
+    for &reference in references.iter() {
+        if !ranges.iter().any(|r| r.contains(&reference)) {
+            let end = boundaries
+                .range(reference + 1..)
+                .next()
+                .cloned()
+                .unwrap_or(dat_size);
+            ranges.push(reference..end);
+        }
+    }
+
+    Ok((ranges, unreferenced))
+}
+
+fn unreferenced_data(plugin: &Plugin) -> Result<Vec<UnreferencedData>, AmxError> {
+    let dat_size = plugin.dat_slice()?.len();
+    let (mut ranges, mut unreferenced) = referenced_ranges(plugin)?;
+    ranges.sort_by_key(|r| r.start);
+
+    // Gaps between strings and arrays
+    let mut position = 0;
+    for range in ranges.iter().chain(Some(&(dat_size..dat_size))) {
+        if range.start > position {
+            unreferenced.push(UnreferencedData {
+                address: position,
+                size: range.start - position,
+                string: None,
+            });
+        }
+        position = position.max(range.end);
+    }
+
+    unreferenced.sort_by_key(|d| d.address);
+    Ok(unreferenced)
+}
+
+// Functions unreachable from publics and main(), and unused DAT. Calls
+// through CALL.pri are assumed to reach every function.
+pub fn dead_code(plugin: &Plugin) -> Result<DeadCode, AmxError> {
+    let graph = call_graph(plugin)?;
+    let main = plugin.main();
+    let starts: Vec<usize> = (0..graph.functions.len())
+        .filter(|&i| graph.functions[i].public || Some(graph.functions[i].address) == main)
+        .collect();
+    let reached = graph.reachable(&starts);
+
+    let functions = graph
+        .functions
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !reached.contains(i))
+        .map(|(_, f)| f.clone())
+        .collect();
+
+    Ok(DeadCode {
+        functions,
+        data: unreferenced_data(plugin)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{dead_code, UnreferencedData};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_find_unreachable_functions_and_data() {
+        let mut builder = PluginBuilder::new();
+        let used = builder.string("used");
+        let unused = builder.string("backdoor");
+        let counter = builder.array(&[0, 0]);
+        let log = builder.native("log_amx");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_INC, counter)
+            .op_param(OP_PUSH_C, used)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, log)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let stub = builder.here();
+        builder.op(OP_PROC).op(OP_ZERO_PRI).op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let dead = dead_code(&amxmod_plugin).unwrap();
+
+        assert_eq!(dead.functions.len(), 1);
+        assert_eq!(dead.functions[0].address, stub as usize);
+        assert_eq!(
+            dead.data,
+            [UnreferencedData {
+                address: unused as usize,
+                size: 9 * 4,
+                string: Some("backdoor".to_owned()),
+            }]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use crate::amx::Opcode;
+use crate::amx::OpcodeType::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Variable {
+    // Frame offset, negative for locals and positive for arguments
+    Local(i32),
+    // DAT address
+    Global(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    Read(Variable),
+    Write(Variable),
+    // Address taken, variable may be written through reference
+    Escape(Variable),
+    // Store through pointer, target is unknown
+    UnknownWrite,
+}
+
+// Variable access performed by opcode, if any
+pub fn access(opcode: &Opcode) -> Option<Access> {
+    let param = opcode.param.unwrap_or(0);
+    let local = Variable::Local(param as i32);
+    let global = Variable::Global(param);
+
+    let access = match opcode.code {
+        OP_LOAD_PRI | OP_LOAD_ALT | OP_PUSH => Access::Read(global),
+        OP_LOAD_S_PRI | OP_LOAD_S_ALT | OP_PUSH_S | OP_LREF_S_PRI | OP_LREF_S_ALT => {
+            Access::Read(local)
+        }
+        OP_STOR_PRI | OP_STOR_ALT | OP_INC | OP_DEC | OP_ZERO => Access::Write(global),
+        OP_STOR_S_PRI | OP_STOR_S_ALT | OP_INC_S | OP_DEC_S | OP_ZERO_S => Access::Write(local),
+        OP_ADDR_PRI | OP_ADDR_ALT | OP_PUSHADDR => Access::Escape(local),
+        OP_STOR_I | OP_STRB_I | OP_SREF_PRI | OP_SREF_ALT | OP_SREF_S_PRI | OP_SREF_S_ALT
+        | OP_INC_I | OP_DEC_I | OP_MOVS | OP_FILL => Access::UnknownWrite,
+        _ => return None,
+    };
+
+    Some(access)
+}
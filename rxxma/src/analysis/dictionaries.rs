@@ -0,0 +1,177 @@
+use super::calls::native_calls;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+// Natives taking format string: (name, format arg)
+const FORMAT_NATIVES: &[(&str, usize)] = &[
+    ("format", 2),
+    ("formatex", 2),
+    ("server_print", 0),
+    ("console_print", 1),
+    ("client_print", 2),
+    ("client_print_color", 2),
+    ("show_hudmessage", 1),
+    ("show_dhudmessage", 1),
+    ("show_activity", 2),
+    ("log_amx", 0),
+    ("log_to_file", 1),
+    ("server_cmd", 0),
+    ("client_cmd", 1),
+];
+// Natives taking lang keys directly: (name, key args)
+const KEY_NATIVES: &[(&str, &[usize])] = &[("show_activity_key", &[0, 1])];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LangKey {
+    pub key: String,
+    pub native: String,
+    // Cod address of SYSREQ.C
+    pub address: usize,
+    pub function: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dictionaries {
+    // register_dictionary files, in registration order
+    pub files: Vec<String>,
+    pub lang_keys: Vec<LangKey>,
+}
+
+impl Dictionaries {
+    // Unique keys in usage order
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = vec![];
+        for lang_key in self.lang_keys.iter() {
+            if !keys.contains(&lang_key.key) {
+                keys.push(lang_key.key.clone());
+            }
+        }
+        keys
+    }
+}
+
+// Positions of %L key arguments among arguments following format string.
+// %L takes two arguments: language target and the key.
+fn lang_key_positions(format: &str) -> Vec<usize> {
+    let mut positions = vec![];
+    let mut argument = 0;
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+
+        // Skip flags, width and precision
+        let spec = chars
+            .by_ref()
+            .find(|c| !(c.is_ascii_digit() || "-+. ".contains(*c)));
+        match spec {
+            Some('%') | None => {}
+            Some('L') => {
+                positions.push(argument + 1);
+                argument += 2;
+            }
+            Some(_) => argument += 1,
+        }
+    }
+
+    positions
+}
+
+// register_dictionary files and constant %L keys
+pub fn dictionaries(plugin: &Plugin) -> Result<Dictionaries, AmxError> {
+    let mut result = Dictionaries::default();
+
+    for call in native_calls(plugin)? {
+        let mut key_args: Vec<usize> = vec![];
+
+        if call.name == "register_dictionary" {
+            if let Some(file) = call.string_arg(plugin, 0) {
+                if !result.files.contains(&file) {
+                    result.files.push(file);
+                }
+            }
+        } else if let Some((_, format)) = FORMAT_NATIVES.iter().find(|n| n.0 == call.name) {
+            if let Some(format_string) = call.string_arg(plugin, *format) {
+                key_args.extend(
+                    lang_key_positions(&format_string)
+                        .into_iter()
+                        .map(|p| format + 1 + p),
+                );
+            }
+        } else if let Some((_, args)) = KEY_NATIVES.iter().find(|n| n.0 == call.name) {
+            key_args.extend_from_slice(args);
+        }
+
+        for n in key_args {
+            if let Some(key) = call.string_arg(plugin, n) {
+                result.lang_keys.push(LangKey {
+                    key,
+                    native: call.name.clone(),
+                    address: call.address,
+                    function: call.function.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{dictionaries, lang_key_positions};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_find_lang_key_positions() {
+        assert_eq!(lang_key_positions("%L"), [1]);
+        assert_eq!(lang_key_positions("%d%% %-5s %L: %L"), [3, 5]);
+        assert!(lang_key_positions("100%%").is_empty());
+    }
+
+    #[test]
+    fn it_extract_dictionary_and_keys() {
+        let mut builder = PluginBuilder::new();
+        let register_dictionary = builder.native("register_dictionary");
+        let client_print = builder.native("client_print");
+        let file = builder.string("admin.txt");
+        let format = builder.string("[AMXX] %L");
+        let key = builder.string("ADMIN_KICK");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, file)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, register_dictionary)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN)
+            .public("cmd_kick")
+            .op(OP_PROC)
+            // Variadic arguments are passed by reference, LANG_PLAYER via heap
+            .op_param(OP_PUSH_C, key)
+            .op_param(OP_HEAP, 4)
+            .op(OP_PUSH_ALT)
+            .op_param(OP_PUSH_C, format)
+            .op_param(OP_PUSH_C, 3)
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_PUSH_C, 20)
+            .op_param(OP_SYSREQ_C, client_print)
+            .op_param(OP_STACK, 24)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let result = dictionaries(&amxmod_plugin).unwrap();
+
+        assert_eq!(result.files, ["admin.txt"]);
+        assert_eq!(result.keys(), ["ADMIN_KICK"]);
+        assert_eq!(result.lang_keys[0].native, "client_print");
+        assert_eq!(result.lang_keys[0].function, "cmd_kick");
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::amx::OpcodeType::*;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+// Window is flagged when its entropy reaches this part of the maximum possible one
+const HIGH_ENTROPY_RATIO: f64 = 0.8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropyRegion {
+    // DAT address
+    pub address: usize,
+    pub size: usize,
+    // Bits per byte
+    pub entropy: f64,
+}
+
+fn entropy_of_counts(counts: &[usize; 256], len: usize) -> f64 {
+    let len = len as f64;
+    counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// Shannon entropy in bits per byte
+pub fn shannon_entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; 256];
+    for &b in bytes {
+        counts[b as usize] += 1;
+    }
+
+    entropy_of_counts(&counts, bytes.len())
+}
+
+// DAT addresses used as operands by cod
+pub(crate) fn referenced_addresses(
+    plugin: &Plugin,
+    dat_size: usize,
+) -> Result<BTreeSet<usize>, AmxError> {
+    let cellsize = plugin.cellsize();
+    let addresses = plugin
+        .opcodes_lenient()?
+        .iter()
+        .filter(|o| {
+            matches!(
+                o.code,
+                OP_PUSH_C
+                    | OP_CONST_PRI
+                    | OP_CONST_ALT
+                    | OP_LOAD_PRI
+                    | OP_LOAD_ALT
+                    | OP_STOR_PRI
+                    | OP_STOR_ALT
+                    | OP_LREF_PRI
+                    | OP_LREF_ALT
+                    | OP_SREF_PRI
+                    | OP_SREF_ALT
+                    | OP_PUSH
+                    | OP_ZERO
+                    | OP_INC
+                    | OP_DEC
+            )
+        })
+        .filter_map(|o| o.param)
+        .map(|p| p as usize)
+        .filter(|&p| p < dat_size && p % cellsize == 0)
+        .collect();
+
+    Ok(addresses)
+}
+
+// Ranges of DAT explained by recognized strings and referenced arrays
+fn covered_ranges(plugin: &Plugin, dat: &[u8]) -> Result<Vec<Range<usize>>, AmxError> {
+    let strings: Vec<Range<usize>> = plugin
+        .strings()?
+        .iter()
+        .map(|s| s.address..s.address + s.size)
+        .collect();
+    let references = referenced_addresses(plugin, dat.len())?;
+
+    // Referenced array lasts until the next reference or string
+    let mut boundaries: BTreeSet<usize> = references.clone();
+    boundaries.extend(strings.iter().map(|s| s.start));
+    boundaries.insert(dat.len());
+
+    let mut ranges: Vec<Range<usize>> = references
+        .iter()
+        .filter(|r| !strings.iter().any(|s| s.contains(r)))
+        .map(|&r| {
+            let end = boundaries.range(r + 1..).next().cloned().unwrap_or(r);
+            r..end
+        })
+        .collect();
+    ranges.extend(strings);
+
+    Ok(ranges)
+}
+
+// Sliding window entropy over DAT, returns high entropy regions which are not
+// covered by any recognized string or referenced array.
+pub fn dat_entropy(plugin: &Plugin, window: usize) -> Result<Vec<EntropyRegion>, AmxError> {
+    let dat = plugin.dat_slice()?;
+    if window == 0 || dat.len() < window {
+        return Ok(vec![]);
+    }
+
+    let covered = covered_ranges(plugin, dat)?;
+    let max_entropy = (window.min(256) as f64).log2();
+
+    // Window slides by one cell, byte counts are updated incrementally
+    let cellsize = plugin.cellsize();
+    let mut counts = [0usize; 256];
+    for &b in &dat[..window] {
+        counts[b as usize] += 1;
+    }
+
+    let mut flagged: Vec<Range<usize>> = vec![];
+    let mut start = 0;
+    loop {
+        let range = start..start + window;
+        let is_covered = covered
+            .iter()
+            .any(|c| c.start < range.end && range.start < c.end);
+
+        if !is_covered && entropy_of_counts(&counts, window) >= max_entropy * HIGH_ENTROPY_RATIO {
+            match flagged.last_mut() {
+                Some(last) if last.end >= range.start => last.end = range.end,
+                _ => flagged.push(range.clone()),
+            }
+        }
+
+        if range.end + cellsize > dat.len() {
+            break;
+        }
+
+        for &b in &dat[range.start..range.start + cellsize] {
+            counts[b as usize] -= 1;
+        }
+        for &b in &dat[range.end..range.end + cellsize] {
+            counts[b as usize] += 1;
+        }
+        start += cellsize;
+    }
+
+    let regions = flagged
+        .into_iter()
+        .map(|r| EntropyRegion {
+            address: r.start,
+            size: r.len(),
+            entropy: shannon_entropy(&dat[r]),
+        })
+        .collect();
+
+    Ok(regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{dat_entropy, shannon_entropy};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    // xorshift, /dev/urandom alike bytes without extra dependencies
+    fn random_cells(count: usize) -> Vec<u32> {
+        let mut state: u32 = 0x1234_5678;
+        (0..count)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_calculate_shannon_entropy() {
+        assert_eq!(shannon_entropy(b""), 0.0);
+        assert_eq!(shannon_entropy(b"aaaa"), 0.0);
+        assert_eq!(shannon_entropy(b"abab"), 1.0);
+        assert_eq!(shannon_entropy(&(0..=255).collect::<Vec<u8>>()), 8.0);
+    }
+
+    #[test]
+    fn it_ignore_normal_plugin() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+
+        assert!(dat_entropy(&amxmod_plugin, 64).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_flag_unreferenced_random_block() {
+        let mut builder = PluginBuilder::new();
+        let server_cmd = builder.native("server_cmd");
+        let command = builder.string("echo hello");
+        let blob = builder.array(&random_cells(128));
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, command)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, server_cmd)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        // Garbage after last function does not stop the scan
+        let garbage = builder.here();
+        builder.op(OP_NOP).patch(garbage, 0xDEAD);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let regions = dat_entropy(&amxmod_plugin, 64).unwrap();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].address, blob as usize);
+        assert_eq!(regions[0].size, 128 * 4);
+        assert!(regions[0].entropy > 7.0);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeSet;
+
+use super::cfg::jump_target;
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, Plugin};
+use crate::error::AmxError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    // Cod address of OP_PROC, of the entry instruction for functions
+    // called without one
+    pub address: usize,
+    // Cod address right after the last function opcode
+    pub end: usize,
+    pub public: bool,
+}
+
+impl Function {
+    pub fn contains(&self, address: usize) -> bool {
+        self.address <= address && address < self.end
+    }
+
+    // `opcodes` are in cod order
+    pub fn opcodes<'a>(&self, opcodes: &'a [Opcode]) -> &'a [Opcode] {
+        let start = opcodes.partition_point(|o| o.address < self.address);
+        let end = opcodes.partition_point(|o| o.address < self.end);
+
+        &opcodes[start..end.max(start)]
+    }
+}
+
+// Cod addresses functions start at. Publics, call targets and debug
+// symbols are entries for sure. OP_PROC elsewhere starts uncalled stock
+// unless code before it jumps past it, then it is junk inside a function.
+pub(crate) fn function_starts(plugin: &Plugin, opcodes: &[Opcode]) -> Result<Vec<usize>, AmxError> {
+    let instructions: BTreeSet<usize> = opcodes
+        .iter()
+        .filter(|o| !o.code.is_pseudo())
+        .map(|o| o.address)
+        .collect();
+
+    let mut entries: BTreeSet<usize> = plugin.publics()?.iter().map(|p| p.address).collect();
+    entries.extend(
+        opcodes
+            .iter()
+            .filter(|o| o.code == OP_CALL)
+            .filter_map(|o| o.param.map(|p| p as usize)),
+    );
+    if let Ok(Some(info)) = plugin.debug_info() {
+        let symbols = info.symbols.iter().filter(|s| s.is_function());
+        entries.extend(symbols.map(|s| s.code_start));
+    }
+    entries.retain(|address| instructions.contains(address));
+
+    let mut starts = vec![];
+    // Farthest jump target of current function
+    let mut reach = 0;
+    for (i, opcode) in opcodes.iter().enumerate() {
+        let entry = entries.contains(&opcode.address);
+        let inside = !starts.is_empty() && opcode.address <= reach;
+        if entry || (opcode.code == OP_PROC && !inside) {
+            starts.push(opcode.address);
+            reach = 0;
+        }
+        let next = opcodes
+            .get(i + 1)
+            .map_or(plugin.cod_size(), |next| next.address);
+        if let Some(target) = jump_target(opcode, next) {
+            reach = reach.max(target);
+        }
+    }
+    Ok(starts)
+}
+
+// Functions in cod order, each lasting until the next one starts. Return
+// in the middle or code after the last RETN reached by jumps stays in.
+pub fn functions(plugin: &Plugin) -> Result<Vec<Function>, AmxError> {
+    let publics = plugin.publics()?;
+    let opcodes = plugin.opcodes()?;
+    let starts = function_starts(plugin, &opcodes)?;
+
+    let result = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &address)| {
+            let end = starts
+                .get(i + 1)
+                .cloned()
+                .unwrap_or_else(|| plugin.cod_size());
+            let public = publics.iter().find(|p| p.address == address);
+            let name = match public {
+                Some(p) => p.name.to_string_lossy().into_owned(),
+                None => format!("sub_{:x}", address),
+            };
+
+            Function {
+                name,
+                address,
+                end,
+                public: public.is_some(),
+            }
+        })
+        .collect();
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{functions, Function};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    #[test]
+    fn it_split_cod_into_functions() {
+        let amxmod_bin = load_fixture("two_natives.amx183");
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+
+        assert_eq!(
+            functions(&amxmod_plugin).unwrap(),
+            [Function {
+                name: "func".to_owned(),
+                address: 8,
+                end: 80,
+                public: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn it_find_functions_beyond_proc() {
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init").op(OP_PROC);
+        let call = builder.here();
+        builder
+            .op_param(OP_CALL, 0)
+            .op_param(OP_JZER, 0)
+            .op(OP_RETN);
+        // Junk PROC jumped over, then tail after the first RETN
+        let junk = builder.here();
+        builder.op(OP_PROC);
+        let tail = builder.here();
+        builder.op(OP_ZERO_PRI).op(OP_RETN);
+        // Called without PROC
+        let helper = builder.here();
+        builder.op(OP_ZERO_PRI).op(OP_RETN);
+        // Never called
+        let stock = builder.here();
+        builder.op(OP_PROC).op(OP_RETN);
+        let end = builder.here();
+        builder.patch(call + 4, helper).patch(call + 12, tail);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let functions = functions(&amxmod_plugin).unwrap();
+        let bounds: Vec<(u32, u32)> = functions
+            .iter()
+            .map(|f| (f.address as u32, f.end as u32))
+            .collect();
+        assert_eq!(bounds, [(call - 4, helper), (helper, stock), (stock, end)]);
+        assert!(functions[0].contains(junk as usize));
+        assert_eq!(functions[1].name, format!("sub_{:x}", helper));
+    }
+
+    #[test]
+    fn it_select_function_opcodes() {
+        let amxmod_bin = load_fixture("two_natives.amx183");
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+        let opcodes = amxmod_plugin.opcodes().unwrap();
+        let function = &functions(&amxmod_plugin).unwrap()[0];
+
+        assert_eq!(function.opcodes(&opcodes).len(), opcodes.len());
+        assert!(function.contains(8));
+        assert!(!function.contains(80));
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeSet;
+
+use super::arrays::{boundaries, variable_cells};
+use crate::amx::OpcodeType::*;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatGlobal {
+    // DAT address of first cell
+    pub address: usize,
+    // Cells up to the next reference or string, more than one for arrays
+    pub size: usize,
+    // Initial values without zero tail
+    pub cells: Vec<u32>,
+    // Unpacked string global starts with, e.g. initialized buffer
+    pub string: Option<Vec<u8>>,
+}
+
+// Globals cod reads, writes or pushes by DAT address, in DAT order. Array
+// lasts until the next reference or string, as in `dead_code`. Addresses
+// inside strings are characters of their string.
+pub fn dat_globals(plugin: &Plugin) -> Result<Vec<DatGlobal>, AmxError> {
+    let dat_size = plugin.dat_slice()?.len();
+    let strings = plugin.strings()?;
+    let boundaries = boundaries(plugin, &strings)?;
+
+    let addresses: BTreeSet<usize> = plugin
+        .opcodes()?
+        .iter()
+        .filter(|o| {
+            matches!(
+                o.code,
+                OP_LOAD_PRI
+                    | OP_LOAD_ALT
+                    | OP_STOR_PRI
+                    | OP_STOR_ALT
+                    | OP_LREF_PRI
+                    | OP_LREF_ALT
+                    | OP_SREF_PRI
+                    | OP_SREF_ALT
+                    | OP_PUSH
+                    | OP_ZERO
+                    | OP_INC
+                    | OP_DEC
+            )
+        })
+        .filter_map(|o| o.param)
+        .map(|p| p as usize)
+        .filter(|&a| a < dat_size && a.is_multiple_of(plugin.cellsize()))
+        .collect();
+
+    let mut globals = vec![];
+    for address in addresses {
+        let string = strings
+            .iter()
+            .find(|s| (s.address..s.address + s.size).contains(&address));
+        if string.is_some_and(|s| s.address != address) {
+            continue;
+        }
+        let (size, cells) = variable_cells(plugin, &boundaries, address)?;
+        globals.push(DatGlobal {
+            address,
+            size,
+            cells,
+            string: string.filter(|s| !s.packed).map(|s| s.bytes.clone()),
+        });
+    }
+    Ok(globals)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{dat_globals, DatGlobal};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_find_globals_by_loads_and_stores() {
+        let mut builder = PluginBuilder::new();
+        let name = builder.string("hello");
+        let count = builder.array(&[5]);
+        let buffer = builder.array(&[0, 0, 0, 0]);
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_LOAD_PRI, count)
+            .op_param(OP_STOR_PRI, buffer)
+            .op_param(OP_LOAD_ALT, name + 4)
+            .op_param(OP_PUSH, name)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(
+            dat_globals(&amxmod_plugin).unwrap(),
+            [
+                DatGlobal {
+                    address: name as usize,
+                    size: 6,
+                    cells: b"hello".iter().map(|&c| u32::from(c)).collect(),
+                    string: Some(b"hello".to_vec()),
+                },
+                DatGlobal {
+                    address: count as usize,
+                    size: 1,
+                    cells: vec![5],
+                    string: None,
+                },
+                DatGlobal {
+                    address: buffer as usize,
+                    size: 4,
+                    cells: vec![],
+                    string: None,
+                },
+            ]
+        );
+    }
+}
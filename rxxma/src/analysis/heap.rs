@@ -0,0 +1,298 @@
+use super::call_graph::{call_graph, CallGraph};
+use super::cfg::target_at;
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, Plugin};
+use crate::error::AmxError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionHeapUsage {
+    pub name: String,
+    pub address: usize,
+    // Peak of HEAP allocations of function itself
+    pub own: usize,
+    // Peak including called functions
+    pub worst_case: usize,
+    // Allocates inside loop or takes part in recursion
+    pub unbounded: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeapVerdict {
+    Fits,
+    // Static worst case fits, but some allocations can repeat
+    Unbounded,
+    Exceeds,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeapUsage {
+    // hea..stp from header, heap and stack share it
+    pub budget: usize,
+    pub worst_case: usize,
+    pub functions: Vec<FunctionHeapUsage>,
+    pub verdict: HeapVerdict,
+}
+
+// Address ranges between backward jump target and the jump itself
+fn loops(opcodes: &[Opcode]) -> Vec<(usize, usize)> {
+    (0..opcodes.len())
+        .filter_map(|i| {
+            let target = target_at(opcodes, i)?;
+            let address = opcodes[i].address;
+            if target <= address {
+                Some((target, address))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// Function being walked, `sites` are (heap in use, callee) of its calls
+struct Frame {
+    function: usize,
+    sites: Vec<(usize, usize)>,
+    next: usize,
+    worst: usize,
+    unbounded: bool,
+}
+
+struct Walker<'a> {
+    graph: &'a CallGraph,
+    // Opcodes of every function, same indexes as in call graph
+    bodies: Vec<&'a [Opcode]>,
+    // Memoized (worst case, unbounded)
+    results: Vec<Option<(usize, bool)>>,
+    in_progress: Vec<bool>,
+}
+
+impl<'a> Walker<'a> {
+    fn own(&self, function: usize) -> (usize, bool) {
+        let body = self.bodies[function];
+        let loops = loops(body);
+
+        let mut current: i64 = 0;
+        let mut peak: i64 = 0;
+        let mut in_loop = false;
+        for opcode in body.iter().filter(|o| o.code == OP_HEAP) {
+            let size = i64::from(opcode.param.unwrap_or(0) as i32);
+            current += size;
+            peak = peak.max(current);
+            if size > 0
+                && loops
+                    .iter()
+                    .any(|&(s, e)| s <= opcode.address && opcode.address <= e)
+            {
+                in_loop = true;
+            }
+        }
+
+        (peak as usize, in_loop)
+    }
+
+    fn frame(&mut self, function: usize) -> Frame {
+        self.in_progress[function] = true;
+        let (worst, unbounded) = self.own(function);
+
+        let mut sites = vec![];
+        let mut current: i64 = 0;
+        let mut calls = self.graph.callees(function).iter().peekable();
+        for opcode in self.bodies[function] {
+            if opcode.code == OP_HEAP {
+                current += i64::from(opcode.param.unwrap_or(0) as i32);
+            }
+            while let Some(site) = calls.next_if(|s| s.address == opcode.address) {
+                sites.push((current.max(0) as usize, site.callee));
+            }
+        }
+
+        Frame {
+            function,
+            sites,
+            next: 0,
+            worst,
+            unbounded,
+        }
+    }
+
+    // Depth first over calls with explicit stack, deep call chains of
+    // obfuscated plugins would overflow native one
+    fn worst_case(&mut self, function: usize) -> (usize, bool) {
+        if let Some(result) = self.results[function] {
+            return result;
+        }
+
+        let mut stack = vec![self.frame(function)];
+        while let Some(frame) = stack.last_mut() {
+            let (current, callee) = match frame.sites.get(frame.next) {
+                Some(&site) => site,
+                None => {
+                    let result = (frame.worst, frame.unbounded);
+                    self.in_progress[frame.function] = false;
+                    self.results[frame.function] = Some(result);
+                    stack.pop();
+                    continue;
+                }
+            };
+
+            let (callee_worst, callee_unbounded) = match self.results[callee] {
+                Some(result) => result,
+                // Recursion depth is unknown
+                None if self.in_progress[callee] => (0, true),
+                None => {
+                    let callee_frame = self.frame(callee);
+                    stack.push(callee_frame);
+                    continue;
+                }
+            };
+            frame.worst = frame.worst.max(current + callee_worst);
+            frame.unbounded |= callee_unbounded;
+            frame.next += 1;
+        }
+
+        self.results[function].unwrap_or((0, true))
+    }
+}
+
+// Static HEAP allocations per function and along call paths
+pub fn heap_usage(plugin: &Plugin) -> Result<HeapUsage, AmxError> {
+    let graph = call_graph(plugin)?;
+    let opcodes = plugin.opcodes()?;
+    let count = graph.functions.len();
+
+    let bodies = graph
+        .functions
+        .iter()
+        .map(|f| f.opcodes(&opcodes))
+        .collect();
+
+    let mut walker = Walker {
+        graph: &graph,
+        bodies,
+        results: vec![None; count],
+        in_progress: vec![false; count],
+    };
+
+    let functions: Vec<FunctionHeapUsage> = (0..count)
+        .map(|i| {
+            let (own, _) = walker.own(i);
+            let (worst_case, unbounded) = walker.worst_case(i);
+            FunctionHeapUsage {
+                name: graph.functions[i].name.clone(),
+                address: graph.functions[i].address,
+                own,
+                worst_case,
+                unbounded,
+            }
+        })
+        .collect();
+
+    let budget = plugin.heap_budget();
+    let worst_case = functions.iter().map(|f| f.worst_case).max().unwrap_or(0);
+    let verdict = if worst_case > budget {
+        HeapVerdict::Exceeds
+    } else if functions.iter().any(|f| f.unbounded) {
+        HeapVerdict::Unbounded
+    } else {
+        HeapVerdict::Fits
+    };
+
+    Ok(HeapUsage {
+        budget,
+        worst_case,
+        functions,
+        verdict,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{heap_usage, HeapVerdict};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    // plugin_init holds 1024 bytes of heap while calling stock using 256 more
+    fn build_plugin(loop_in_stock: bool) -> Plugin<'static> {
+        let mut builder = PluginBuilder::new();
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_HEAP, 1024);
+        let call = builder.here();
+        builder
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_CALL, 0)
+            .op_param(OP_HEAP, (-1024i32) as u32)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+
+        let stock = builder.here();
+        builder.op(OP_PROC);
+        let loop_start = builder.here();
+        builder
+            .op_param(OP_HEAP, 256)
+            .op_param(OP_HEAP, (-256i32) as u32);
+        if loop_in_stock {
+            builder.op_param(OP_JUMP, loop_start);
+        }
+        builder.op(OP_ZERO_PRI).op(OP_RETN);
+        builder.patch(call + 12, stock);
+
+        Plugin::try_from(builder.build()).unwrap()
+    }
+
+    #[test]
+    fn it_sum_heap_along_call_path() {
+        let usage = heap_usage(&build_plugin(false)).unwrap();
+
+        assert_eq!(usage.functions[0].own, 1024);
+        assert_eq!(usage.functions[1].own, 256);
+        assert_eq!(usage.functions[0].worst_case, 1280);
+        assert_eq!(usage.worst_case, 1280);
+        assert_eq!(usage.budget, 16384);
+        assert_eq!(usage.verdict, HeapVerdict::Fits);
+    }
+
+    #[test]
+    fn it_flag_allocations_in_loops() {
+        let usage = heap_usage(&build_plugin(true)).unwrap();
+
+        assert!(usage.functions[1].unbounded);
+        assert!(usage.functions[0].unbounded);
+        assert_eq!(usage.verdict, HeapVerdict::Unbounded);
+    }
+
+    #[test]
+    fn it_report_no_heap_usage() {
+        let amxmod_plugin = Plugin::try_from(load_fixture("simple.amx183")).unwrap();
+        let usage = heap_usage(&amxmod_plugin).unwrap();
+
+        assert_eq!(usage.worst_case, 0);
+        assert_eq!(usage.verdict, HeapVerdict::Fits);
+    }
+
+    #[test]
+    fn it_walk_deep_call_chains() {
+        // Every function holds 4 bytes while calling the next one
+        const DEPTH: usize = 20_000;
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init");
+        for i in 0..DEPTH {
+            let start = builder.here();
+            builder.op(OP_PROC).op_param(OP_HEAP, 4);
+            if i + 1 < DEPTH {
+                // PROC, HEAP, CALL, HEAP, RETN
+                builder.op_param(OP_CALL, start + 32);
+            }
+            builder.op_param(OP_HEAP, (-4i32) as u32).op(OP_RETN);
+        }
+        let usage = heap_usage(&Plugin::try_from(builder.build()).unwrap()).unwrap();
+
+        assert_eq!(usage.functions.len(), DEPTH);
+        assert_eq!(usage.worst_case, 4 * DEPTH);
+        assert_eq!(usage.verdict, HeapVerdict::Exceeds);
+    }
+}
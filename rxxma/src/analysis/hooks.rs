@@ -0,0 +1,278 @@
+use std::fmt;
+
+use super::calls::{native_calls, CallArgument, NativeCall};
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+const MSGID_NATIVE: &str = "get_user_msgid";
+
+// Ham enum of hamsandwich.inc up to weapon functions, later ones are
+// listed by number
+const HAM_FUNCTIONS: &[&str] = &[
+    "Ham_Spawn",
+    "Ham_Precache",
+    "Ham_Keyvalue",
+    "Ham_ObjectCaps",
+    "Ham_Activate",
+    "Ham_SetObjectCollisionBox",
+    "Ham_Classify",
+    "Ham_DeathNotice",
+    "Ham_TraceAttack",
+    "Ham_TakeDamage",
+    "Ham_TakeHealth",
+    "Ham_Killed",
+    "Ham_BloodColor",
+    "Ham_TraceBleed",
+    "Ham_IsTriggered",
+    "Ham_MyMonsterPointer",
+    "Ham_MySquadMonsterPointer",
+    "Ham_GetToggleState",
+    "Ham_AddPoints",
+    "Ham_AddPointsToTeam",
+    "Ham_AddPlayerItem",
+    "Ham_RemovePlayerItem",
+    "Ham_GiveAmmo",
+    "Ham_GetDelay",
+    "Ham_IsMoving",
+    "Ham_OverrideReset",
+    "Ham_DamageDecal",
+    "Ham_SetToggleState",
+    "Ham_StartSneaking",
+    "Ham_StopSneaking",
+    "Ham_OnControls",
+    "Ham_IsSneaking",
+    "Ham_IsAlive",
+    "Ham_IsBSPModel",
+    "Ham_ReflectGauss",
+    "Ham_HasTarget",
+    "Ham_IsInWorld",
+    "Ham_IsPlayer",
+    "Ham_IsNetClient",
+    "Ham_TeamId",
+    "Ham_GetNextTarget",
+    "Ham_Think",
+    "Ham_Touch",
+    "Ham_Use",
+    "Ham_Blocked",
+    "Ham_Respawn",
+    "Ham_UpdateOwner",
+    "Ham_FBecomeProne",
+    "Ham_Center",
+    "Ham_EyePosition",
+    "Ham_EarPosition",
+    "Ham_BodyTarget",
+    "Ham_Illumination",
+    "Ham_FVisible",
+    "Ham_FVecVisible",
+    "Ham_Player_Jump",
+    "Ham_Player_Duck",
+    "Ham_Player_PreThink",
+    "Ham_Player_PostThink",
+    "Ham_Player_GetGunPosition",
+    "Ham_Player_ShouldFadeOnDeath",
+    "Ham_Player_ImpulseCommands",
+    "Ham_Player_UpdateClientData",
+    "Ham_Item_AddToPlayer",
+    "Ham_Item_AddDuplicate",
+    "Ham_Item_CanDeploy",
+    "Ham_Item_Deploy",
+    "Ham_Item_CanHolster",
+    "Ham_Item_Holster",
+    "Ham_Item_UpdateItemInfo",
+    "Ham_Item_PreFrame",
+    "Ham_Item_PostFrame",
+    "Ham_Item_Drop",
+    "Ham_Item_Kill",
+    "Ham_Item_AttachToPlayer",
+    "Ham_Item_PrimaryAmmoIndex",
+    "Ham_Item_SecondaryAmmoIndex",
+    "Ham_Item_UpdateClientData",
+    "Ham_Item_GetWeaponPtr",
+    "Ham_Item_ItemSlot",
+    "Ham_Weapon_ExtractAmmo",
+    "Ham_Weapon_ExtractClipAmmo",
+    "Ham_Weapon_AddWeapon",
+    "Ham_Weapon_PlayEmptySound",
+    "Ham_Weapon_ResetEmptySound",
+    "Ham_Weapon_SendWeaponAnim",
+    "Ham_Weapon_IsUsable",
+    "Ham_Weapon_PrimaryAttack",
+    "Ham_Weapon_SecondaryAttack",
+    "Ham_Weapon_Reload",
+    "Ham_Weapon_WeaponIdle",
+];
+
+// Game event, entity function, engine forward or network message handler
+// registration. String arguments are None unless constant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hook {
+    // register_event, RegisterHam, register_forward or register_message
+    pub native: String,
+    // Cod address of the call
+    pub address: usize,
+    // Event or message name, Ham function or fakemeta forward number
+    pub target: Option<String>,
+    pub handler: Option<String>,
+    // Post hook, RegisterHam and register_forward only
+    pub post: Option<bool>,
+    // register_event flags and conditions, RegisterHam entity class
+    pub parameters: Vec<Option<String>>,
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unknown = |value: &Option<String>| value.clone().unwrap_or_else(|| "?".to_owned());
+        write!(
+            f,
+            "{} {} -> {}",
+            self.native,
+            unknown(&self.target),
+            unknown(&self.handler)
+        )?;
+        if self.post == Some(true) {
+            write!(f, " post")?;
+        }
+        if !self.parameters.is_empty() {
+            let parameters: Vec<String> = self.parameters.iter().map(unknown).collect();
+            write!(f, " ({})", parameters.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Message id returned by get_user_msgid called last before `call` in the
+// same function, i.e. register_message(get_user_msgid("SayText"), ...)
+fn message_name(plugin: &Plugin, calls: &[NativeCall], call: &NativeCall) -> Option<String> {
+    match call.args.first()? {
+        CallArgument::NativeResult(name) if name == MSGID_NATIVE => calls
+            .iter()
+            .rev()
+            .filter(|c| c.name == MSGID_NATIVE && c.function == call.function)
+            .find(|c| c.address < call.address)?
+            .string_arg(plugin, 0),
+        CallArgument::Constant(id) => Some(format!("#{}", id)),
+        _ => None,
+    }
+}
+
+// Every hook registration, in cod order
+pub fn hooks(plugin: &Plugin) -> Result<Vec<Hook>, AmxError> {
+    let calls = native_calls(plugin)?;
+    let mut result = vec![];
+
+    for call in calls.iter() {
+        let hook = |target, handler, post, parameters| Hook {
+            native: call.name.clone(),
+            address: call.address,
+            target,
+            handler: call.string_arg(plugin, handler),
+            post,
+            parameters,
+        };
+        let post = |n| call.constant_arg(n).map(|p| p != 0);
+
+        result.push(match call.name.as_str() {
+            "register_event" => hook(
+                call.string_arg(plugin, 0),
+                1,
+                None,
+                (2..call.args.len())
+                    .map(|n| call.string_arg(plugin, n))
+                    .collect(),
+            ),
+            "RegisterHam" => hook(
+                call.constant_arg(0)
+                    .map(|n| match HAM_FUNCTIONS.get(n as usize) {
+                        Some(name) => (*name).to_owned(),
+                        None => format!("Ham #{}", n),
+                    }),
+                2,
+                post(3),
+                vec![call.string_arg(plugin, 1)],
+            ),
+            "register_forward" => hook(
+                call.constant_arg(0).map(|n| format!("FM #{}", n)),
+                1,
+                post(2),
+                vec![],
+            ),
+            "register_message" => hook(message_name(plugin, &calls, call), 1, None, vec![]),
+            _ => continue,
+        });
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::hooks;
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_list_hooks() {
+        let mut builder = PluginBuilder::new();
+        let register_event = builder.native("register_event");
+        let register_ham = builder.native("RegisterHam");
+        let register_message = builder.native("register_message");
+        let get_user_msgid = builder.native("get_user_msgid");
+        let death = builder.string("DeathMsg");
+        let ev_death = builder.string("ev_death");
+        let flags = builder.string("a");
+        let condition = builder.string("1>0");
+        let player = builder.string("player");
+        let ham_spawn = builder.string("ham_spawn_post");
+        let say_text = builder.string("SayText");
+        let msg_say_text = builder.string("msg_say_text");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            // register_event("DeathMsg", "ev_death", "a", "1>0")
+            .op_param(OP_PUSH_C, condition)
+            .op_param(OP_PUSH_C, flags)
+            .op_param(OP_PUSH_C, ev_death)
+            .op_param(OP_PUSH_C, death)
+            .op_param(OP_PUSH_C, 16)
+            .op_param(OP_SYSREQ_C, register_event)
+            .op_param(OP_STACK, 20)
+            // RegisterHam(Ham_Spawn, "player", "ham_spawn_post", 1)
+            .op_param(OP_PUSH_C, 1)
+            .op_param(OP_PUSH_C, ham_spawn)
+            .op_param(OP_PUSH_C, player)
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_PUSH_C, 16)
+            .op_param(OP_SYSREQ_C, register_ham)
+            .op_param(OP_STACK, 20)
+            // register_message(get_user_msgid("SayText"), "msg_say_text")
+            .op_param(OP_PUSH_C, msg_say_text)
+            .op_param(OP_PUSH_C, say_text)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, get_user_msgid)
+            .op_param(OP_STACK, 8)
+            .op(OP_PUSH_PRI)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, register_message)
+            .op_param(OP_STACK, 12)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let hooks: Vec<String> = hooks(&amxmod_plugin)
+            .unwrap()
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        assert_eq!(
+            hooks,
+            [
+                "register_event DeathMsg -> ev_death (a, 1>0)",
+                "RegisterHam Ham_Spawn -> ham_spawn_post post (player)",
+                "register_message SayText -> msg_say_text",
+            ]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use super::calls::{native_calls, CallArgument, NativeCall};
+use super::known_natives::{infer_include, known_native};
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+const UNKNOWN_MODULE: &str = "Unknown module";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParameterKind {
+    Cell,
+    // Reference to local array or variable
+    Array,
+    // DAT string constant
+    String,
+}
+
+// Parameters observed across all calls of a native
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NativeArity {
+    pub min: usize,
+    pub max: usize,
+    kinds: Vec<ParameterKind>,
+}
+
+impl NativeArity {
+    fn observe(&mut self, plugin: &Plugin, call: &NativeCall, first: bool) {
+        let count = call.args.len();
+        if first {
+            self.min = count;
+        }
+        self.min = self.min.min(count);
+        self.max = self.max.max(count);
+
+        for (n, arg) in call.args.iter().enumerate() {
+            let kind = match arg {
+                CallArgument::FrameAddress(_) => ParameterKind::Array,
+                CallArgument::Constant(_) if call.string_arg(plugin, n).is_some() => {
+                    ParameterKind::String
+                }
+                _ => ParameterKind::Cell,
+            };
+
+            match self.kinds.get_mut(n) {
+                // Any array argument makes parameter an array
+                Some(k) if *k == ParameterKind::Cell => *k = kind,
+                Some(_) => {}
+                None => self.kinds.push(kind),
+            }
+        }
+    }
+
+    fn parameters(&self) -> String {
+        let mut parameters: Vec<String> = self
+            .kinds
+            .iter()
+            .enumerate()
+            .map(|(n, kind)| match kind {
+                ParameterKind::Cell => format!("arg{}", n),
+                ParameterKind::Array => format!("arg{}[]", n),
+                ParameterKind::String => format!("const arg{}[]", n),
+            })
+            .collect();
+
+        // Differing argument counts mean variadic native
+        if self.min != self.max {
+            parameters.truncate(self.min);
+            parameters.push("any:...".to_owned());
+        }
+
+        parameters.join(", ")
+    }
+}
+
+// Argument counts and kinds of every called native
+pub fn native_arities(plugin: &Plugin) -> Result<HashMap<String, NativeArity>, AmxError> {
+    let mut arities: HashMap<String, NativeArity> = HashMap::new();
+
+    for call in native_calls(plugin)? {
+        let first = !arities.contains_key(&call.name);
+        arities
+            .entry(call.name.clone())
+            .or_default()
+            .observe(plugin, &call, first);
+    }
+
+    Ok(arities)
+}
+
+// Include file with declarations of every native in natives table
+pub fn generate_inc(plugin: &Plugin) -> Result<String, AmxError> {
+    let natives: Vec<String> = plugin
+        .natives()?
+        .iter()
+        .map(|n| n.name.to_string_lossy().into_owned())
+        .collect();
+    let arities = native_arities(plugin)?;
+
+    // Modules in natives table order, unknown natives last
+    let mut modules: Vec<(&str, Vec<String>)> = vec![];
+    for name in natives.iter() {
+        let declaration = match known_native(name) {
+            Some(known) => known.prototype.to_owned(),
+            None => {
+                let parameters = match arities.get(name) {
+                    Some(arity) => arity.parameters(),
+                    // Never called, arity can't be inferred
+                    None => "...".to_owned(),
+                };
+                format!("native {}({});", name, parameters)
+            }
+        };
+
+        let module = infer_include(name).unwrap_or(UNKNOWN_MODULE);
+        match modules.iter_mut().find(|(m, _)| *m == module) {
+            Some((_, declarations)) => declarations.push(declaration),
+            None => modules.push((module, vec![declaration])),
+        }
+    }
+    modules.sort_by_key(|(m, _)| *m == UNKNOWN_MODULE);
+
+    let mut inc = String::from("// Natives used by plugin, generated by rxxma\n");
+    for (module, declarations) in modules {
+        inc.push('\n');
+        if module == UNKNOWN_MODULE {
+            inc.push_str(&format!("// {}\n", UNKNOWN_MODULE));
+        } else {
+            inc.push_str(&format!("// {}.inc\n", module));
+        }
+        for declaration in declarations {
+            inc.push_str(&declaration);
+            inc.push('\n');
+        }
+    }
+
+    Ok(inc)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::generate_inc;
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    #[test]
+    fn it_generate_inc_for_unknown_natives() {
+        let amxmod_bin = load_fixture("two_natives.amx183");
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+
+        assert_eq!(
+            generate_inc(&amxmod_plugin).unwrap(),
+            "// Natives used by plugin, generated by rxxma\n\
+             \n\
+             // Unknown module\n\
+             native native_one();\n\
+             native native_two();\n"
+        );
+    }
+
+    #[test]
+    fn it_use_known_prototypes() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+
+        assert_eq!(
+            generate_inc(&amxmod_plugin).unwrap(),
+            "// Natives used by plugin, generated by rxxma\n\
+             \n\
+             // amxmodx.inc\n\
+             native register_plugin(const plugin_name[], const version[], const author[]);\n"
+        );
+    }
+
+    #[test]
+    fn it_infer_parameters_and_modules() {
+        let mut builder = PluginBuilder::new();
+        let custom = builder.native("custom_log");
+        let armor = builder.native("cs_set_user_armor");
+        let message = builder.string("hello");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, 1)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, armor)
+            .op_param(OP_STACK, 8)
+            .op_param(OP_PUSHADDR, 0xFFFF_FFF0)
+            .op_param(OP_PUSH_C, message)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, custom)
+            .op_param(OP_STACK, 12)
+            .op_param(OP_PUSH_C, message)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, custom)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(
+            generate_inc(&amxmod_plugin).unwrap(),
+            "// Natives used by plugin, generated by rxxma\n\
+             \n\
+             // cstrike.inc\n\
+             native cs_set_user_armor(arg0);\n\
+             \n\
+             // Unknown module\n\
+             native custom_log(const arg0[], any:...);\n"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+// Natives of AMX Mod X 1.8.2 core and bundled modules with their includes
+
+pub struct KnownNative {
+    pub name: &'static str,
+    // Include file without extension
+    pub include: &'static str,
+    // Declaration exactly as in include file
+    pub prototype: &'static str,
+}
+
+macro_rules! known {
+    ($include:expr, $name:expr, $prototype:expr) => {
+        KnownNative {
+            name: $name,
+            include: $include,
+            prototype: $prototype,
+        }
+    };
+}
+
+pub const KNOWN_NATIVES: &[KnownNative] = &[
+    known!("amxmodx", "register_plugin", "native register_plugin(const plugin_name[], const version[], const author[]);"),
+    known!("amxmodx", "register_clcmd", "native register_clcmd(const client_cmd[], const function[], flags=-1, const info[]=\"\", FlagManager=-1);"),
+    known!("amxmodx", "register_concmd", "native register_concmd(const cmd[], const function[], flags=-1, const info[]=\"\", FlagManager=-1);"),
+    known!("amxmodx", "register_srvcmd", "native register_srvcmd(const server_cmd[], const function[], flags=-1, const info[]=\"\");"),
+    known!("amxmodx", "register_cvar", "native register_cvar(const name[], const string[], flags = 0, Float:fvalue = 0.0);"),
+    known!("amxmodx", "register_event", "native register_event(const event[], const function[], const flags[], const cond[]=\"\", ...);"),
+    known!("amxmodx", "register_logevent", "native register_logevent(const function[], argsnum, ...);"),
+    known!("amxmodx", "register_menucmd", "native register_menucmd(menuid, keys, const function[]);"),
+    known!("amxmodx", "register_menuid", "native register_menuid(const menu[], outside=0);"),
+    known!("amxmodx", "get_cvar_num", "native get_cvar_num(const cvarname[]);"),
+    known!("amxmodx", "get_pcvar_num", "native get_pcvar_num(pcvar);"),
+    known!("amxmodx", "server_cmd", "native server_cmd(const command[], any:...);"),
+    known!("amxmodx", "server_exec", "native server_exec();"),
+    known!("amxmodx", "server_print", "native server_print(const message[], any:...);"),
+    known!("amxmodx", "client_cmd", "native client_cmd(index, const command[], any:...);"),
+    known!("amxmodx", "client_print", "native client_print(index, type, const message[], any:...);"),
+    known!("amxmodx", "console_print", "native console_print(id, const message[], any:...);"),
+    known!("amxmodx", "log_amx", "native log_amx(const string[], any:...);"),
+    known!("amxmodx", "show_motd", "native show_motd(player, const message[], const header[]=\"\");"),
+    known!("amxmodx", "get_user_name", "native get_user_name(index, name[], len);"),
+    known!("amxmodx", "get_user_authid", "native get_user_authid(index, authid[], len);"),
+    known!("amxmodx", "get_user_ip", "native get_user_ip(index, ip[], len, without_port = 0);"),
+    known!("amxmodx", "get_user_flags", "native get_user_flags(index, id=0);"),
+    known!("amxmodx", "set_user_flags", "native set_user_flags(index, flags=-1, id=0);"),
+    known!("amxmodx", "get_user_team", "native get_user_team(index, team[]=\"\", len = 0);"),
+    known!("amxmodx", "get_user_weapon", "native get_user_weapon(index,&clip=0,&ammo=0);"),
+    known!("amxmodx", "is_user_alive", "native is_user_alive(index);"),
+    known!("amxmodx", "is_user_connected", "native is_user_connected(index);"),
+    known!("amxmodx", "is_user_bot", "native is_user_bot(index);"),
+    known!("amxmodx", "get_maxplayers", "native get_maxplayers();"),
+    known!("amxmodx", "get_players", "native get_players(players[32], &num, const flags[]=\"\", const team[]=\"\");"),
+    known!("amxmodx", "read_argv", "native read_argv(id, output[], len);"),
+    known!("amxmodx", "read_args", "native read_args(output[], len);"),
+    known!("amxmodx", "read_data", "native read_data(value, any:...);"),
+    known!("amxmodx", "set_task", "native set_task(Float:time, const function[], id = 0, const any:parameter[]=\"\", len = 0, const flags[]=\"\", repeat = 0);"),
+    known!("amxmodx", "remove_task", "native remove_task(id = 0, outside = 0);"),
+    known!("amxmodx", "random_num", "native random_num(a, b);"),
+    known!("amxmodx", "precache_model", "native precache_model(const name[]);"),
+    known!("amxmodx", "precache_sound", "native precache_sound(const name[]);"),
+    known!("amxmodx", "format", "native format(output[], len, const format[], any:...);"),
+    known!("amxmodx", "formatex", "native formatex(output[], len, const format[], any:...);"),
+    known!("amxmodx", "copy", "native copy(dest[], len, const src[]);"),
+    known!("amxmodx", "equal", "native equal(const a[], const b[], c=0);"),
+    known!("amxmodx", "equali", "native equali(const a[], const b[], c=0);"),
+    known!("amxmodx", "menu_create", "native menu_create(const title[], const handler[], ml=0);"),
+    known!("amxmodx", "menu_additem", "native menu_additem(menu, const name[], const info[]=\"\", paccess=0, callback=-1);"),
+    known!("amxmodx", "menu_display", "native menu_display(id, menu, page=0);"),
+    known!("fun", "set_user_health", "native set_user_health(index, health);"),
+    known!("fun", "set_user_godmode", "native set_user_godmode(index, godmode = 0);"),
+    known!("fun", "give_item", "native give_item(index, const item[]);"),
+    known!("cstrike", "cs_get_user_money", "native cs_get_user_money(index);"),
+    known!("cstrike", "cs_set_user_money", "native cs_set_user_money(index, money, flash = 1);"),
+    known!("engine", "entity_get_int", "native entity_get_int(iIndex, iKey);"),
+    known!("engine", "entity_set_int", "native entity_set_int(iIndex, iKey, iVal);"),
+    known!("engine", "register_think", "native register_think(const Classname[], const function[]);"),
+    known!("fakemeta", "pev", "native pev(_index,_value,any:...);"),
+    known!("fakemeta", "set_pev", "native set_pev(_index,_value,any:...);"),
+    known!("fakemeta", "engfunc", "native engfunc(type,any:...);"),
+    known!("fakemeta", "register_forward", "native register_forward(_forwardType,const _function[],_post=0);"),
+    known!("hamsandwich", "RegisterHam", "native HamHook:RegisterHam(Ham:function, const EntityClass[], const Callback[], Post=0);"),
+    known!("float", "float", "native Float:float(value);"),
+    known!("float", "floatstr", "native Float:floatstr(const string[]);"),
+    known!("float", "floatmul", "native Float:floatmul(Float:oper1, Float:oper2);"),
+    known!("float", "floatdiv", "native Float:floatdiv(Float:dividend, Float:divisor);"),
+    known!("float", "floatadd", "native Float:floatadd(Float:dividend, Float:divisor);"),
+    known!("float", "floatsub", "native Float:floatsub(Float:oper1, Float:oper2);"),
+    known!("float", "floatfract", "native Float:floatfract(Float:value);"),
+    known!("float", "floatround", "native floatround(Float:value, floatround_method:method=floatround_round);"),
+    known!("float", "floatcmp", "native floatcmp(Float:fOne, Float:fTwo);"),
+    known!("float", "floatsqroot", "native Float:floatsqroot(Float:value);"),
+    known!("float", "floatpower", "native Float:floatpower(Float:value, Float:exponent);"),
+    known!("float", "floatlog", "native Float:floatlog(Float:value, Float:base=10.0);"),
+    known!("float", "floatsin", "native Float:floatsin(Float:value, anglemode:mode=radian);"),
+    known!("float", "floatcos", "native Float:floatcos(Float:value, anglemode:mode=radian);"),
+    known!("float", "floattan", "native Float:floattan(Float:value, anglemode:mode=radian);"),
+    known!("float", "floatabs", "native Float:floatabs(Float:value);"),
+];
+
+// Name prefixes of module natives missing from KNOWN_NATIVES
+const INCLUDE_PREFIXES: &[(&str, &str)] = &[
+    ("cs_", "cstrike"),
+    ("entity_", "engine"),
+    ("find_ent", "engine"),
+    ("get_global_", "fakemeta"),
+    ("global_get", "fakemeta"),
+    ("dllfunc", "fakemeta"),
+    ("get_pdata_", "fakemeta"),
+    ("set_pdata_", "fakemeta"),
+    ("set_user_armor", "fun"),
+    ("set_user_gravity", "fun"),
+    ("set_user_maxspeed", "fun"),
+    ("set_user_noclip", "fun"),
+    ("set_user_rendering", "fun"),
+    ("strip_user_weapons", "fun"),
+    ("create_entity", "engine"),
+    ("remove_entity", "engine"),
+    ("ExecuteHam", "hamsandwich"),
+    ("GetHam", "hamsandwich"),
+    ("SetHam", "hamsandwich"),
+    ("SQL_", "sqlx"),
+    ("nvault_", "nvault"),
+    ("regex_", "regex"),
+    ("socket_", "sockets"),
+    ("geoip_", "geoip"),
+    ("dod_", "dodfun"),
+    ("ts_", "tsfun"),
+];
+
+// Include files amxmodx.inc already pulls in
+const AMXMODX_INCLUDES: &[&str] = &[
+    "amxmodx",
+    "core",
+    "float",
+    "amxconst",
+    "string",
+    "file",
+    "vault",
+    "lang",
+    "messages",
+    "vector",
+    "sorting",
+    "cellarray",
+    "celltrie",
+];
+
+impl KnownNative {
+    // Tag of returned value, e.g. Float for floatmul
+    pub fn return_tag(&self) -> Option<&'static str> {
+        let name = &self.prototype[..self.prototype.find('(')?];
+        let colon = name.find(':')?;
+        name[..colon].rsplit(' ').next()
+    }
+
+    // Declaration of parameter at position, variadic tail declares the rest
+    fn parameter(&self, position: usize) -> Option<&'static str> {
+        let start = self.prototype.find('(')? + 1;
+        let end = self.prototype.rfind(')')?;
+        let parameters: Vec<&'static str> = self.prototype[start..end].split(',').collect();
+        let parameter = match parameters.get(position) {
+            Some(p) => p,
+            None => parameters.last().filter(|p| p.ends_with("..."))?,
+        };
+        Some(parameter.trim().trim_start_matches("const ").trim_start())
+    }
+
+    // Tag of parameter at position, variadic tail tags the rest
+    pub fn parameter_tag(&self, position: usize) -> Option<&'static str> {
+        let parameter = self.parameter(position)?;
+        let parameter = parameter.trim_start_matches('&');
+        let colon = parameter.find(':')?;
+        let tag = &parameter[..colon];
+        if tag.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            Some(tag)
+        } else {
+            None
+        }
+    }
+    // How parameter at position is passed, None past the last one
+    pub fn parameter_kind(&self, position: usize) -> Option<ParameterKind> {
+        let parameter = self.parameter(position)?;
+        if parameter.ends_with("...") {
+            return Some(ParameterKind::Variadic);
+        }
+        let parameter = parameter.split('=').next().unwrap_or_default();
+        if let Some(open) = parameter.find('[') {
+            let size = parameter[open + 1..].trim_end_matches([']', ' ']);
+            return Some(match size.parse() {
+                Ok(size) => ParameterKind::Array(size),
+                Err(_) => ParameterKind::String,
+            });
+        }
+        if parameter.starts_with('&') {
+            Some(ParameterKind::Reference)
+        } else {
+            Some(ParameterKind::Value)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterKind {
+    Value,
+    Reference,
+    // Sized array like players[32]
+    Array(usize),
+    // Unsized array, almost always string
+    String,
+    // Passed by reference, either cell or string
+    Variadic,
+}
+
+pub fn known_native(name: &str) -> Option<&'static KnownNative> {
+    KNOWN_NATIVES.iter().find(|n| n.name == name)
+}
+
+// Include file declaring native, None for third party natives
+pub fn infer_include(name: &str) -> Option<&'static str> {
+    if let Some(native) = known_native(name) {
+        return Some(native.include);
+    }
+
+    INCLUDE_PREFIXES
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|(_, include)| *include)
+}
+
+// #include lines plugin source needs for natives, amxmodx first and the
+// rest sorted. Third party natives are skipped.
+pub fn infer_includes<'a, I>(natives: I) -> Vec<&'static str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut includes: Vec<&'static str> = natives
+        .into_iter()
+        .filter_map(infer_include)
+        .filter(|include| !AMXMODX_INCLUDES.contains(include))
+        .collect();
+    includes.sort_unstable();
+    includes.dedup();
+    includes.insert(0, "amxmodx");
+    includes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{infer_includes, known_native, ParameterKind};
+
+    #[test]
+    fn it_infer_includes_of_natives() {
+        let natives = [
+            "pev",
+            "register_plugin",
+            "floatmul",
+            "set_user_health",
+            "cs_set_user_team",
+            "set_pev",
+            "RegisterHam",
+            "third_party_native",
+        ];
+        assert_eq!(
+            infer_includes(natives.iter().cloned()),
+            ["amxmodx", "cstrike", "fakemeta", "fun", "hamsandwich"]
+        );
+        assert_eq!(infer_includes(vec![]), ["amxmodx"]);
+    }
+
+    #[test]
+    fn it_read_parameter_tags() {
+        let set_task = known_native("set_task").unwrap();
+        assert_eq!(set_task.parameter_tag(0), Some("Float"));
+        assert_eq!(set_task.parameter_tag(1), None);
+        assert_eq!(set_task.parameter_tag(7), None);
+
+        let pev = known_native("pev").unwrap();
+        assert_eq!(pev.parameter_tag(5), Some("any"));
+    }
+
+    #[test]
+    fn it_read_parameter_kinds() {
+        let get_players = known_native("get_players").unwrap();
+        assert_eq!(
+            get_players.parameter_kind(0),
+            Some(ParameterKind::Array(32))
+        );
+        assert_eq!(
+            get_players.parameter_kind(1),
+            Some(ParameterKind::Reference)
+        );
+        assert_eq!(get_players.parameter_kind(2), Some(ParameterKind::String));
+        assert_eq!(get_players.parameter_kind(4), None);
+
+        let client_cmd = known_native("client_cmd").unwrap();
+        assert_eq!(client_cmd.parameter_kind(0), Some(ParameterKind::Value));
+        assert_eq!(client_cmd.parameter_kind(5), Some(ParameterKind::Variadic));
+    }
+
+    #[test]
+    fn it_read_return_tag() {
+        assert_eq!(
+            known_native("floatmul").unwrap().return_tag(),
+            Some("Float")
+        );
+        assert_eq!(
+            known_native("RegisterHam").unwrap().return_tag(),
+            Some("HamHook")
+        );
+        assert_eq!(known_native("floatround").unwrap().return_tag(), None);
+    }
+}
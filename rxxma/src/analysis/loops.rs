@@ -0,0 +1,269 @@
+use std::collections::BTreeSet;
+
+use super::cfg::Cfg;
+use super::def_use::{access, Access, Variable};
+use super::functions::functions;
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, Plugin};
+use crate::error::AmxError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoopSeverity {
+    // Condition relies on native results or globals touched by calls
+    Low,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopIssue {
+    // No path leaves loop
+    NoExit,
+    // Variables tested by exit condition are not written inside loop
+    ConditionNeverChanges(Vec<Variable>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopDiagnostic {
+    pub function: String,
+    // Cod address of loop header
+    pub header: usize,
+    // Cod address of jump closing loop
+    pub back_edge: usize,
+    pub issue: LoopIssue,
+    pub severity: LoopSeverity,
+}
+
+// Blocks of natural loop formed by back edge `tail -> header`
+fn loop_body(cfg: &Cfg, header: usize, tail: usize) -> BTreeSet<usize> {
+    let mut body: BTreeSet<usize> = BTreeSet::new();
+    body.insert(header);
+    let mut stack = vec![tail];
+
+    while let Some(block) = stack.pop() {
+        if body.insert(block) {
+            stack.extend(cfg.predecessors(block));
+        }
+    }
+
+    body
+}
+
+fn is_call(opcode: &Opcode) -> bool {
+    matches!(
+        opcode.code,
+        OP_CALL | OP_CALL_PRI | OP_SYSREQ_C | OP_SYSREQ_PRI | OP_SYSREQ_D
+    )
+}
+
+fn diagnose(
+    cfg: &Cfg,
+    opcodes: &[Opcode],
+    header: usize,
+    tail: usize,
+) -> Option<(LoopIssue, LoopSeverity)> {
+    let body = loop_body(cfg, header, tail);
+    let body_opcodes = || {
+        body.iter()
+            .flat_map(move |&b| opcodes[cfg.blocks[b].opcodes.clone()].iter())
+    };
+
+    let exiting: Vec<usize> = body
+        .iter()
+        .cloned()
+        .filter(|&b| {
+            let block = &cfg.blocks[b];
+            block.exits || block.successors.iter().any(|s| !body.contains(s))
+        })
+        .collect();
+
+    if exiting.is_empty() {
+        return Some((LoopIssue::NoExit, LoopSeverity::High));
+    }
+
+    // Function returns from inside loop, exit does not depend on condition
+    if exiting.iter().any(|&b| cfg.blocks[b].exits) {
+        return None;
+    }
+
+    let mut tested: BTreeSet<Variable> = BTreeSet::new();
+    let mut native_condition = false;
+    for &b in exiting.iter() {
+        for opcode in &opcodes[cfg.blocks[b].opcodes.clone()] {
+            match access(opcode) {
+                Some(Access::Read(v)) => {
+                    tested.insert(v);
+                }
+                _ if is_call(opcode) => native_condition = true,
+                _ => {}
+            }
+        }
+    }
+
+    let mut written: BTreeSet<Variable> = BTreeSet::new();
+    let mut unknown_writes = false;
+    let mut calls = false;
+    for opcode in body_opcodes() {
+        match access(opcode) {
+            Some(Access::Write(v)) | Some(Access::Escape(v)) => {
+                written.insert(v);
+            }
+            Some(Access::UnknownWrite) => unknown_writes = true,
+            _ if is_call(opcode) => calls = true,
+            _ => {}
+        }
+    }
+
+    if tested.iter().any(|v| written.contains(v)) {
+        return None;
+    }
+
+    let globals_may_change =
+        (calls || unknown_writes) && tested.iter().any(|v| matches!(v, Variable::Global(_)));
+    let severity = if native_condition || globals_may_change || unknown_writes {
+        LoopSeverity::Low
+    } else {
+        LoopSeverity::High
+    };
+
+    Some((
+        LoopIssue::ConditionNeverChanges(tested.into_iter().collect()),
+        severity,
+    ))
+}
+
+// Loops which can't terminate or whose exit condition never changes
+pub fn loop_diagnostics(plugin: &Plugin) -> Result<Vec<LoopDiagnostic>, AmxError> {
+    let opcodes = plugin.opcodes()?;
+    let mut diagnostics = vec![];
+
+    for function in functions(plugin)? {
+        let body = function.opcodes(&opcodes);
+        let cfg = Cfg::from_opcodes(body);
+
+        for (tail, block) in cfg.blocks.iter().enumerate() {
+            for &header in block.successors.iter() {
+                if cfg.blocks[header].start > block.start {
+                    continue;
+                }
+
+                if let Some((issue, severity)) = diagnose(&cfg, body, header, tail) {
+                    let last = &body[block.opcodes.end - 1];
+                    diagnostics.push(LoopDiagnostic {
+                        function: function.name.clone(),
+                        header: cfg.blocks[header].start,
+                        back_edge: last.address,
+                        issue,
+                        severity,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{loop_diagnostics, LoopIssue, LoopSeverity};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::analysis::Variable;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    // i = 0; while (i < 10) { if `increment` i++ }
+    fn counted_loop(increment: bool) -> Plugin<'static> {
+        let mut builder = PluginBuilder::new();
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_STACK, 0xFFFF_FFFC)
+            .op_param(OP_ZERO_S, 0xFFFF_FFFC);
+        let header = builder.here();
+        builder
+            .op_param(OP_LOAD_S_PRI, 0xFFFF_FFFC)
+            .op_param(OP_CONST_ALT, 10);
+        let exit_jump = builder.here();
+        builder.op_param(OP_JSGEQ, 0);
+        if increment {
+            builder.op_param(OP_INC_S, 0xFFFF_FFFC);
+        }
+        builder.op_param(OP_JUMP, header);
+        let end = builder.here();
+        builder.op_param(OP_STACK, 4).op(OP_ZERO_PRI).op(OP_RETN);
+        builder.patch(exit_jump + 4, end);
+
+        Plugin::try_from(builder.build()).unwrap()
+    }
+
+    #[test]
+    fn it_accept_counted_loop() {
+        assert!(loop_diagnostics(&counted_loop(true)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_flag_unchanged_condition() {
+        let diagnostics = loop_diagnostics(&counted_loop(false)).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].function, "plugin_init");
+        assert_eq!(
+            diagnostics[0].issue,
+            LoopIssue::ConditionNeverChanges(vec![Variable::Local(-4)])
+        );
+        assert_eq!(diagnostics[0].severity, LoopSeverity::High);
+    }
+
+    #[test]
+    fn it_flag_infinite_loop() {
+        let mut builder = PluginBuilder::new();
+        let think = builder.native("think");
+        builder.public("plugin_init").op(OP_PROC);
+        let header = builder.here();
+        builder
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_SYSREQ_C, think)
+            .op_param(OP_STACK, 4)
+            .op_param(OP_JUMP, header)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let diagnostics = loop_diagnostics(&amxmod_plugin).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].header, header as usize);
+        assert_eq!(diagnostics[0].back_edge, header as usize + 24);
+        assert_eq!(diagnostics[0].issue, LoopIssue::NoExit);
+        assert_eq!(diagnostics[0].severity, LoopSeverity::High);
+    }
+
+    #[test]
+    fn it_lower_severity_for_native_condition() {
+        let mut builder = PluginBuilder::new();
+        let is_ready = builder.native("is_ready");
+        builder.public("plugin_init").op(OP_PROC);
+        let header = builder.here();
+        builder
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_SYSREQ_C, is_ready)
+            .op_param(OP_STACK, 4);
+        let exit_jump = builder.here();
+        builder.op_param(OP_JZER, 0).op_param(OP_JUMP, header);
+        let end = builder.here();
+        builder.op(OP_ZERO_PRI).op(OP_RETN);
+        builder.patch(exit_jump + 4, end);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let diagnostics = loop_diagnostics(&amxmod_plugin).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, LoopSeverity::Low);
+    }
+
+    #[test]
+    fn it_find_no_loops_in_simple_plugin() {
+        let amxmod_plugin = Plugin::try_from(load_fixture("simple.amx183")).unwrap();
+        assert!(loop_diagnostics(&amxmod_plugin).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use super::calls::native_calls;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+const REGISTER_NATIVE: &str = "register_plugin";
+const INIT_PUBLIC: &str = "plugin_init";
+
+// Arguments of register_plugin, as amx_plugins lists them. None for
+// arguments which are not constant strings.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+}
+
+// register_plugin call of plugin_init, or the first one elsewhere for
+// plugins registering late. None when plugin never registers.
+pub fn metadata(plugin: &Plugin) -> Result<Option<Metadata>, AmxError> {
+    let calls: Vec<_> = native_calls(plugin)?
+        .into_iter()
+        .filter(|c| c.name == REGISTER_NATIVE)
+        .collect();
+    let call = calls
+        .iter()
+        .find(|c| c.function == INIT_PUBLIC)
+        .or_else(|| calls.first());
+
+    Ok(call.map(|c| Metadata {
+        name: c.string_arg(plugin, 0),
+        version: c.string_arg(plugin, 1),
+        author: c.string_arg(plugin, 2),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{metadata, Metadata};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    #[test]
+    fn it_read_register_plugin_arguments() {
+        let amxmod_plugin = Plugin::try_from(load_fixture("simple.amx183")).unwrap();
+
+        assert_eq!(
+            metadata(&amxmod_plugin).unwrap(),
+            Some(Metadata {
+                name: Some("simple plugin".to_owned()),
+                version: Some("0.1".to_owned()),
+                author: Some("Fedcomp".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn it_prefer_plugin_init_registration() {
+        let mut builder = PluginBuilder::new();
+        let register_plugin = builder.native("register_plugin");
+        let late = builder.string("late");
+        let name = builder.string("Admin Tools");
+        let author = builder.string("alice");
+
+        builder
+            .public("plugin_cfg")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, late)
+            .op_param(OP_PUSH_C, late)
+            .op_param(OP_PUSH_C, late)
+            .op_param(OP_PUSH_C, 12)
+            .op_param(OP_SYSREQ_C, register_plugin)
+            .op_param(OP_STACK, 16)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        // Version built at runtime is not a constant
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, author)
+            .op_param(OP_PUSH_S, 12)
+            .op_param(OP_PUSH_C, name)
+            .op_param(OP_PUSH_C, 12)
+            .op_param(OP_SYSREQ_C, register_plugin)
+            .op_param(OP_STACK, 16)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(
+            metadata(&amxmod_plugin).unwrap(),
+            Some(Metadata {
+                name: Some("Admin Tools".to_owned()),
+                version: None,
+                author: Some("alice".to_owned()),
+            })
+        );
+        let empty = Plugin::try_from(PluginBuilder::new().build()).unwrap();
+        assert_eq!(metadata(&empty).unwrap(), None);
+    }
+}
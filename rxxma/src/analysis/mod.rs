@@ -0,0 +1,57 @@
+mod arrays;
+mod call_graph;
+mod calls;
+mod cfg;
+mod command_strings;
+mod constants;
+mod dead_code;
+mod def_use;
+mod dictionaries;
+mod entropy;
+mod functions;
+mod globals;
+mod heap;
+mod hooks;
+mod inc;
+mod known_natives;
+mod loops;
+mod metadata;
+mod network;
+mod registrations;
+mod resources;
+mod symbols;
+mod xrefs;
+
+pub use self::arrays::{dat_arrays, DatArray};
+pub use self::call_graph::{call_graph, CallGraph, CallSite, FunctionId, NativeSite};
+pub use self::calls::{function_native_calls, native_calls, CallArgument, NativeCall};
+pub use self::cfg::{
+    case_table, code_targets, instruction_index, is_conditional_jump, jump_target, jump_targets,
+    target_at, BasicBlock, Cfg,
+};
+pub use self::command_strings::{command_strings, CommandString, CommandValue};
+pub use self::constants::{
+    decided_branches, propagate_constants, resolved_values, ConstantValue, Operand, ResolvedValue,
+};
+pub use self::dead_code::{dead_code, DeadCode, UnreferencedData};
+pub use self::def_use::{access, Access, Variable};
+pub use self::dictionaries::{dictionaries, Dictionaries, LangKey};
+pub use self::entropy::{dat_entropy, shannon_entropy, EntropyRegion};
+pub(crate) use self::functions::function_starts;
+pub use self::functions::{functions, Function};
+pub use self::globals::{dat_globals, DatGlobal};
+pub use self::heap::{heap_usage, FunctionHeapUsage, HeapUsage, HeapVerdict};
+pub use self::hooks::{hooks, Hook};
+pub use self::inc::{generate_inc, native_arities, NativeArity};
+pub use self::known_natives::{
+    infer_include, infer_includes, known_native, KnownNative, ParameterKind, KNOWN_NATIVES,
+};
+pub use self::loops::{loop_diagnostics, LoopDiagnostic, LoopIssue, LoopSeverity};
+pub use self::metadata::{metadata, Metadata};
+pub use self::network::{network_calls, NetworkCall, NetworkKind};
+pub use self::registrations::{commands, cvars, registrations, Command, Cvar, Registrations};
+pub use self::resources::{precached_resources, PrecacheSite, PrecachedResource, ResourceKind};
+pub use self::symbols::{
+    symbol_anomalies, CharacterClasses, SymbolAnomalies, SymbolFinding, KNOWN_FORWARDS,
+};
+pub use self::xrefs::{xrefs, Xref, XrefKind, XrefTarget, Xrefs};
@@ -0,0 +1,156 @@
+use std::fmt;
+
+use super::calls::native_calls;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetworkKind {
+    // sqlx module, SQL_* natives
+    Sql,
+    // sockets module, socket_* natives
+    Socket,
+    // HTTPX and grip modules
+    Http,
+}
+
+fn kind(native: &str) -> Option<NetworkKind> {
+    if native.starts_with("SQL_") {
+        Some(NetworkKind::Sql)
+    } else if native.starts_with("socket_") {
+        Some(NetworkKind::Socket)
+    } else if native.starts_with("HTTPX_") || native.starts_with("grip_") {
+        Some(NetworkKind::Http)
+    } else {
+        None
+    }
+}
+
+// Call of networking native with constant arguments recovered where the
+// native takes them
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkCall {
+    pub kind: NetworkKind,
+    pub native: String,
+    // Cod address of the call
+    pub address: usize,
+    pub function: String,
+    // Hostname or URL
+    pub host: Option<String>,
+    pub port: Option<u32>,
+    // SQL query or data sent over socket
+    pub data: Option<String>,
+}
+
+impl fmt::Display for NetworkCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in {}", self.native, self.function)?;
+        if let Some(host) = &self.host {
+            write!(f, " host {:?}", host)?;
+        }
+        if let Some(port) = self.port {
+            write!(f, " port {}", port)?;
+        }
+        if let Some(data) = &self.data {
+            write!(f, " data {:?}", data)?;
+        }
+        Ok(())
+    }
+}
+
+// Every SQL, socket and HTTP native call, in cod order
+pub fn network_calls(plugin: &Plugin) -> Result<Vec<NetworkCall>, AmxError> {
+    let mut result = vec![];
+    for call in native_calls(plugin)? {
+        let kind = match kind(&call.name) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let string = |n| call.string_arg(plugin, n);
+        let (host, port, data) = match call.name.as_str() {
+            "SQL_MakeDbTuple" => (string(0), None, None),
+            "SQL_PrepareQuery" => (None, None, string(1)),
+            "SQL_ThreadQuery" => (None, None, string(2)),
+            "SQL_QueryAndIgnore" | "SQL_SimpleQuery" => (None, None, string(1)),
+            "socket_open" => (string(0), call.constant_arg(1), None),
+            "socket_send" | "socket_send2" => (None, None, string(1)),
+            "HTTPX_Download" | "grip_request" => (string(0), None, None),
+            _ => (None, None, None),
+        };
+
+        result.push(NetworkCall {
+            kind,
+            native: call.name,
+            address: call.address,
+            function: call.function,
+            host,
+            port,
+            data,
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{network_calls, NetworkKind};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_extract_endpoints() {
+        let mut builder = PluginBuilder::new();
+        let make_tuple = builder.native("SQL_MakeDbTuple");
+        let socket_open = builder.native("socket_open");
+        let socket_send = builder.native("socket_send");
+        let host = builder.string("db.example.com");
+        let user = builder.string("root");
+        let collector = builder.string("collector.example.net");
+        let payload = builder.string("rcon_password");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            // SQL_MakeDbTuple("db.example.com", "root", "root", "root")
+            .op_param(OP_PUSH_C, user)
+            .op_param(OP_PUSH_C, user)
+            .op_param(OP_PUSH_C, user)
+            .op_param(OP_PUSH_C, host)
+            .op_param(OP_PUSH_C, 16)
+            .op_param(OP_SYSREQ_C, make_tuple)
+            .op_param(OP_STACK, 20)
+            // socket_send(socket_open("collector.example.net", 27015), ...)
+            .op_param(OP_PUSH_C, 27015)
+            .op_param(OP_PUSH_C, collector)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, socket_open)
+            .op_param(OP_STACK, 12)
+            .op_param(OP_PUSH_C, 13)
+            .op_param(OP_PUSH_C, payload)
+            .op(OP_PUSH_PRI)
+            .op_param(OP_PUSH_C, 12)
+            .op_param(OP_SYSREQ_C, socket_send)
+            .op_param(OP_STACK, 16)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let calls = network_calls(&amxmod_plugin).unwrap();
+        let kinds: Vec<NetworkKind> = calls.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            [NetworkKind::Sql, NetworkKind::Socket, NetworkKind::Socket]
+        );
+        assert_eq!(
+            calls[0].to_string(),
+            "SQL_MakeDbTuple in plugin_init host \"db.example.com\""
+        );
+        assert_eq!(
+            calls[1].to_string(),
+            "socket_open in plugin_init host \"collector.example.net\" port 27015"
+        );
+        assert_eq!(calls[2].data.as_deref(), Some("rcon_password"));
+    }
+}
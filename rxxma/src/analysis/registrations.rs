@@ -0,0 +1,197 @@
+use super::calls::native_calls;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+const COMMAND_NATIVES: &[&str] = &["register_clcmd", "register_concmd", "register_srvcmd"];
+const CVAR_NATIVES: &[&str] = &["register_cvar", "create_cvar"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Registrations {
+    // Constant names in registration order, without duplicates
+    pub commands: Vec<String>,
+    pub cvars: Vec<String>,
+}
+
+// Console command registration, string arguments are None unless constant
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    // register_clcmd, register_concmd or register_srvcmd
+    pub native: String,
+    // Cod address of the call
+    pub address: usize,
+    pub name: Option<String>,
+    // Public handling the command
+    pub handler: Option<String>,
+    // Admin flags required, -1 for everyone
+    pub access: Option<i32>,
+    pub info: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cvar {
+    // register_cvar or create_cvar
+    pub native: String,
+    pub address: usize,
+    pub name: Option<String>,
+    pub value: Option<String>,
+    // FCVAR_* bits
+    pub flags: Option<u32>,
+    // create_cvar only
+    pub description: Option<String>,
+}
+
+// Every command registration, in cod order
+pub fn commands(plugin: &Plugin) -> Result<Vec<Command>, AmxError> {
+    Ok(native_calls(plugin)?
+        .into_iter()
+        .filter(|c| COMMAND_NATIVES.contains(&c.name.as_str()))
+        .map(|c| Command {
+            address: c.address,
+            name: c.string_arg(plugin, 0),
+            handler: c.string_arg(plugin, 1),
+            access: c.constant_arg(2).map(|a| a as i32),
+            info: c.string_arg(plugin, 3),
+            native: c.name,
+        })
+        .collect())
+}
+
+// Every cvar registration, in cod order
+pub fn cvars(plugin: &Plugin) -> Result<Vec<Cvar>, AmxError> {
+    Ok(native_calls(plugin)?
+        .into_iter()
+        .filter(|c| CVAR_NATIVES.contains(&c.name.as_str()))
+        .map(|c| Cvar {
+            address: c.address,
+            name: c.string_arg(plugin, 0),
+            value: c.string_arg(plugin, 1),
+            flags: c.constant_arg(2),
+            description: match c.name.as_str() {
+                "create_cvar" => c.string_arg(plugin, 3),
+                _ => None,
+            },
+            native: c.name,
+        })
+        .collect())
+}
+
+fn unique(names: impl Iterator<Item = Option<String>>) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    for name in names.flatten() {
+        if !result.contains(&name) {
+            result.push(name);
+        }
+    }
+    result
+}
+
+// Commands and cvars registered with constant names
+pub fn registrations(plugin: &Plugin) -> Result<Registrations, AmxError> {
+    Ok(Registrations {
+        commands: unique(commands(plugin)?.into_iter().map(|c| c.name)),
+        cvars: unique(cvars(plugin)?.into_iter().map(|c| c.name)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{commands, cvars, registrations, Command, Cvar};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_collect_commands_and_cvars() {
+        let mut builder = PluginBuilder::new();
+        let register_clcmd = builder.native("register_clcmd");
+        let register_cvar = builder.native("register_cvar");
+        let command = builder.string("say /rank");
+        let handler = builder.string("cmd_rank");
+        let cvar = builder.string("amx_rank_enabled");
+        let value = builder.string("1");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, handler)
+            .op_param(OP_PUSH_C, command)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, register_clcmd)
+            .op_param(OP_STACK, 12)
+            .op_param(OP_PUSH_C, value)
+            .op_param(OP_PUSH_C, cvar)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, register_cvar)
+            .op_param(OP_STACK, 12)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let result = registrations(&amxmod_plugin).unwrap();
+
+        assert_eq!(result.commands, ["say /rank"]);
+        assert_eq!(result.cvars, ["amx_rank_enabled"]);
+    }
+
+    #[test]
+    fn it_recover_registration_arguments() {
+        let mut builder = PluginBuilder::new();
+        let register_concmd = builder.native("register_concmd");
+        let create_cvar = builder.native("create_cvar");
+        let command = builder.string("amx_hidden");
+        let handler = builder.string("cmd_hidden");
+        let info = builder.string("<target>");
+        let cvar = builder.string("amx_hidden_mode");
+        let value = builder.string("0");
+        let description = builder.string("Hidden mode");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, 0xFFFF_FFFF)
+            .op_param(OP_PUSH_C, info)
+            .op_param(OP_PUSH_C, 1 << 1)
+            .op_param(OP_PUSH_C, handler)
+            .op_param(OP_PUSH_C, command)
+            .op_param(OP_PUSH_C, 20);
+        let concmd = builder.here() as usize;
+        builder
+            .op_param(OP_SYSREQ_C, register_concmd)
+            .op_param(OP_STACK, 24)
+            .op_param(OP_PUSH_C, description)
+            .op_param(OP_PUSH_S, 12)
+            .op_param(OP_PUSH_C, value)
+            .op_param(OP_PUSH_C, cvar)
+            .op_param(OP_PUSH_C, 16);
+        let create = builder.here() as usize;
+        builder
+            .op_param(OP_SYSREQ_C, create_cvar)
+            .op_param(OP_STACK, 20)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(
+            commands(&amxmod_plugin).unwrap(),
+            [Command {
+                native: "register_concmd".to_owned(),
+                address: concmd,
+                name: Some("amx_hidden".to_owned()),
+                handler: Some("cmd_hidden".to_owned()),
+                access: Some(2),
+                info: Some("<target>".to_owned()),
+            }]
+        );
+        // Flags passed in a variable are not recovered
+        assert_eq!(
+            cvars(&amxmod_plugin).unwrap(),
+            [Cvar {
+                native: "create_cvar".to_owned(),
+                address: create,
+                name: Some("amx_hidden_mode".to_owned()),
+                value: Some("0".to_owned()),
+                flags: None,
+                description: Some("Hidden mode".to_owned()),
+            }]
+        );
+    }
+}
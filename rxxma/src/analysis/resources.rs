@@ -0,0 +1,216 @@
+use super::calls::{native_calls, CallArgument, NativeCall};
+use crate::amx::Plugin;
+use crate::error::AmxError;
+
+// (native, path argument)
+const PRECACHE_NATIVES: &[(&str, usize)] = &[
+    ("precache_model", 0),
+    ("precache_sound", 0),
+    ("precache_generic", 0),
+];
+// fakemeta EngFunc_PrecacheModel and EngFunc_PrecacheSound
+const ENGFUNC_PRECACHE: &[u32] = &[1, 2];
+// Natives writing formatted string into first argument: (name, format arg)
+const FORMAT_NATIVES: &[(&str, usize)] = &[("format", 2), ("formatex", 2)];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResourceKind {
+    Model,
+    Sprite,
+    Sound,
+    Other,
+}
+
+impl ResourceKind {
+    fn classify(path: &str, native: &str) -> ResourceKind {
+        let extension = path
+            .rsplit('.')
+            .next()
+            .filter(|_| path.contains('.'))
+            .map(|e| e.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("mdl") => ResourceKind::Model,
+            Some("spr") => ResourceKind::Sprite,
+            Some("wav") | Some("mp3") => ResourceKind::Sound,
+            // Extension is formatted at runtime, guess from native
+            _ if native == "precache_sound" => ResourceKind::Sound,
+            _ if native == "precache_model" => ResourceKind::Model,
+            _ => ResourceKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecacheSite {
+    pub native: String,
+    pub function: String,
+    // Cod address of SYSREQ.C
+    pub address: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecachedResource {
+    // Resource path, or format string of dynamic path
+    pub path: String,
+    pub dynamic: bool,
+    pub kind: ResourceKind,
+    pub sites: Vec<PrecacheSite>,
+}
+
+fn path_arg(call: &NativeCall) -> Option<usize> {
+    if call.name == "engfunc" {
+        return match call.args.first() {
+            Some(CallArgument::Constant(n)) if ENGFUNC_PRECACHE.contains(n) => Some(1),
+            _ => None,
+        };
+    }
+
+    PRECACHE_NATIVES
+        .iter()
+        .find(|n| n.0 == call.name)
+        .map(|n| n.1)
+}
+
+// Format string of last format call in the same function writing into `buffer`
+fn format_string(
+    plugin: &Plugin,
+    calls: &[NativeCall],
+    call: &NativeCall,
+    buffer: &CallArgument,
+) -> Option<String> {
+    calls
+        .iter()
+        .rev()
+        .filter(|c| c.function == call.function && c.address < call.address)
+        .filter(|c| c.args.first() == Some(buffer))
+        .filter_map(|c| {
+            let (_, format) = FORMAT_NATIVES.iter().find(|n| n.0 == c.name)?;
+            c.string_arg(plugin, *format)
+        })
+        .next()
+}
+
+// Constant and formatted paths passed to precache natives
+pub fn precached_resources(plugin: &Plugin) -> Result<Vec<PrecachedResource>, AmxError> {
+    let calls = native_calls(plugin)?;
+    let mut resources: Vec<PrecachedResource> = vec![];
+
+    for call in calls.iter() {
+        let n = match path_arg(call) {
+            Some(n) => n,
+            None => continue,
+        };
+        let argument = match call.args.get(n) {
+            Some(argument) => argument,
+            None => continue,
+        };
+
+        let (path, dynamic) = match format_string(plugin, &calls, call, argument) {
+            Some(format) => (format, true),
+            None => match call.string_arg(plugin, n) {
+                Some(ref path) if path.is_empty() => continue,
+                Some(path) => (path, false),
+                None => continue,
+            },
+        };
+
+        let site = PrecacheSite {
+            native: call.name.clone(),
+            function: call.function.clone(),
+            address: call.address,
+        };
+        match resources
+            .iter_mut()
+            .find(|r| r.path == path && r.dynamic == dynamic)
+        {
+            Some(resource) => resource.sites.push(site),
+            None => resources.push(PrecachedResource {
+                kind: ResourceKind::classify(&path, &call.name),
+                path,
+                dynamic,
+                sites: vec![site],
+            }),
+        }
+    }
+
+    Ok(resources)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{precached_resources, ResourceKind};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_list_precached_model_and_sound() {
+        let mut builder = PluginBuilder::new();
+        let precache_model = builder.native("precache_model");
+        let precache_sound = builder.native("precache_sound");
+        let model = builder.string("models/rpgrocket.mdl");
+        let sound = builder.string("weapons/explode3.wav");
+        builder.public("plugin_precache").op(OP_PROC);
+        for &(path, native) in &[
+            (model, precache_model),
+            (sound, precache_sound),
+            (model, precache_model),
+        ] {
+            builder
+                .op_param(OP_PUSH_C, path)
+                .op_param(OP_PUSH_C, 4)
+                .op_param(OP_SYSREQ_C, native)
+                .op_param(OP_STACK, 8);
+        }
+        builder.op(OP_ZERO_PRI).op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let resources = precached_resources(&amxmod_plugin).unwrap();
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0].path, "models/rpgrocket.mdl");
+        assert_eq!(resources[0].kind, ResourceKind::Model);
+        assert_eq!(resources[0].sites.len(), 2);
+        assert_eq!(resources[1].path, "weapons/explode3.wav");
+        assert_eq!(resources[1].kind, ResourceKind::Sound);
+        assert!(!resources[1].dynamic);
+    }
+
+    #[test]
+    fn it_report_formatted_paths() {
+        let mut builder = PluginBuilder::new();
+        let formatex = builder.native("formatex");
+        let engfunc = builder.native("engfunc");
+        let format = builder.string("sprites/%s.spr");
+        let name = builder.string("laserbeam");
+        builder
+            .public("plugin_precache")
+            .op(OP_PROC)
+            .op_param(OP_STACK, (-256i32) as u32)
+            .op_param(OP_PUSH_C, name)
+            .op_param(OP_PUSH_C, format)
+            .op_param(OP_PUSH_C, 63)
+            .op_param(OP_PUSHADDR, (-256i32) as u32)
+            .op_param(OP_PUSH_C, 16)
+            .op_param(OP_SYSREQ_C, formatex)
+            .op_param(OP_STACK, 20)
+            .op_param(OP_PUSHADDR, (-256i32) as u32)
+            .op_param(OP_PUSH_C, 1)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, engfunc)
+            .op_param(OP_STACK, 12)
+            .op_param(OP_STACK, 256)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let resources = precached_resources(&amxmod_plugin).unwrap();
+
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].path, "sprites/%s.spr");
+        assert!(resources[0].dynamic);
+        assert_eq!(resources[0].kind, ResourceKind::Sprite);
+        assert_eq!(resources[0].sites[0].native, "engfunc");
+    }
+}
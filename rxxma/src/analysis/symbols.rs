@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+
+use super::calls::native_calls;
+use crate::amx::Plugin;
+use crate::error::AmxError;
+use crate::util::DebugU8;
+
+// Forwards called by AMX Mod X core and stock modules
+pub const KNOWN_FORWARDS: &[&str] = &[
+    "plugin_init",
+    "plugin_precache",
+    "plugin_cfg",
+    "plugin_end",
+    "plugin_pause",
+    "plugin_unpause",
+    "plugin_log",
+    "plugin_modules",
+    "plugin_natives",
+    "plugin_flags",
+    "client_connect",
+    "client_connectex",
+    "client_authorized",
+    "client_putinserver",
+    "client_disconnect",
+    "client_disconnected",
+    "client_remove",
+    "client_infochanged",
+    "client_command",
+    "client_impulse",
+    "client_PreThink",
+    "client_PostThink",
+    "client_spawn",
+    "client_damage",
+    "client_death",
+    "client_kill",
+    "server_frame",
+    "server_changelevel",
+    "inconsistent_file",
+    "pfn_touch",
+    "pfn_think",
+    "pfn_keyvalue",
+    "pfn_spawn",
+    "vexd_pfntouch",
+    "cs_buy_attempt",
+    "csdm_PreSpawn",
+    "csdm_PostSpawn",
+    "fw_think",
+];
+
+const SHORT_AVERAGE_LENGTH: f64 = 3.0;
+const RANDOM_NAME_MIN_LENGTH: usize = 6;
+const MAX_SCORE: u32 = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolFinding {
+    ShortNames { average_length: f64 },
+    SingleCharacterName(String),
+    RandomLookingName(String),
+    DuplicateName(String),
+    // Name with escaped non printable bytes
+    NonPrintableName(String),
+    // Public which is neither known forward nor registered as handler
+    UnknownPublic(String),
+}
+
+impl SymbolFinding {
+    pub fn weight(&self) -> u32 {
+        match self {
+            SymbolFinding::ShortNames { .. } => 20,
+            SymbolFinding::SingleCharacterName(_) => 10,
+            SymbolFinding::RandomLookingName(_) => 10,
+            SymbolFinding::DuplicateName(_) => 15,
+            SymbolFinding::NonPrintableName(_) => 25,
+            SymbolFinding::UnknownPublic(_) => 5,
+        }
+    }
+}
+
+// Fractions of nametable characters
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CharacterClasses {
+    pub letters: f64,
+    pub digits: f64,
+    pub underscores: f64,
+    pub other: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolAnomalies {
+    // 0 (clean) ..= 100 (certainly obfuscated)
+    pub score: u32,
+    pub average_length: f64,
+    pub classes: CharacterClasses,
+    pub findings: Vec<SymbolFinding>,
+}
+
+fn character_classes(names: &[Vec<u8>]) -> CharacterClasses {
+    let total = names.iter().map(Vec::len).sum::<usize>();
+    if total == 0 {
+        return CharacterClasses::default();
+    }
+
+    let count = |f: &dyn Fn(u8) -> bool| {
+        names.iter().flatten().filter(|&&c| f(c)).count() as f64 / total as f64
+    };
+
+    let letters = count(&|c| c.is_ascii_alphabetic());
+    let digits = count(&|c| c.is_ascii_digit());
+    let underscores = count(&|c| c == b'_');
+
+    CharacterClasses {
+        letters,
+        digits,
+        underscores,
+        other: 1.0 - letters - digits - underscores,
+    }
+}
+
+// Long names without vowels or mostly made of digits
+fn is_random_looking(name: &[u8]) -> bool {
+    if name.len() < RANDOM_NAME_MIN_LENGTH {
+        return false;
+    }
+
+    let letters: Vec<u8> = name
+        .iter()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(u8::to_ascii_lowercase)
+        .collect();
+    let vowels = letters.iter().filter(|c| b"aeiouy".contains(c)).count();
+    let digits = name.iter().filter(|c| c.is_ascii_digit()).count();
+
+    vowels == 0 || digits * 2 > name.len()
+}
+
+// Scores nametable symbols for obfuscation traits
+pub fn symbol_anomalies(plugin: &Plugin) -> Result<SymbolAnomalies, AmxError> {
+    let publics: Vec<Vec<u8>> = plugin
+        .publics()?
+        .into_iter()
+        .map(|p| p.name.into_bytes())
+        .collect();
+    let natives: Vec<Vec<u8>> = plugin
+        .natives()?
+        .into_iter()
+        .map(|n| n.name.into_bytes())
+        .collect();
+    let names: Vec<Vec<u8>> = publics.iter().chain(natives.iter()).cloned().collect();
+
+    let mut findings = vec![];
+
+    let average_length = if names.is_empty() {
+        0.0
+    } else {
+        names.iter().map(Vec::len).sum::<usize>() as f64 / names.len() as f64
+    };
+    if !names.is_empty() && average_length < SHORT_AVERAGE_LENGTH {
+        findings.push(SymbolFinding::ShortNames { average_length });
+    }
+
+    let mut occurrences: HashMap<&[u8], usize> = HashMap::new();
+    for name in names.iter() {
+        *occurrences.entry(name).or_insert(0) += 1;
+    }
+
+    let mut reported_duplicates = HashSet::new();
+    for name in names.iter() {
+        let printable = name.printable();
+
+        if name.iter().any(|c| !(0x20..0x7F).contains(c)) {
+            findings.push(SymbolFinding::NonPrintableName(printable.clone()));
+        }
+
+        if name.len() == 1 {
+            findings.push(SymbolFinding::SingleCharacterName(printable.clone()));
+        } else if is_random_looking(name) {
+            findings.push(SymbolFinding::RandomLookingName(printable.clone()));
+        }
+
+        if occurrences[&name[..]] > 1 && reported_duplicates.insert(name) {
+            findings.push(SymbolFinding::DuplicateName(printable));
+        }
+    }
+
+    // Publics referenced by name in native calls (register_clcmd, set_task, ...)
+    let handlers: HashSet<String> = native_calls(plugin)?
+        .iter()
+        .flat_map(|c| (0..c.args.len()).filter_map(move |i| c.string_arg(plugin, i)))
+        .collect();
+
+    for public in publics.iter() {
+        let name = String::from_utf8_lossy(public);
+        if !KNOWN_FORWARDS.contains(&name.as_ref()) && !handlers.contains(name.as_ref()) {
+            findings.push(SymbolFinding::UnknownPublic(public.printable()));
+        }
+    }
+
+    let score = findings
+        .iter()
+        .map(SymbolFinding::weight)
+        .sum::<u32>()
+        .min(MAX_SCORE);
+
+    Ok(SymbolAnomalies {
+        score,
+        average_length,
+        classes: character_classes(&names),
+        findings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{symbol_anomalies, SymbolFinding};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    #[test]
+    fn it_score_normal_plugin_low() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+        let anomalies = symbol_anomalies(&amxmod_plugin).unwrap();
+
+        assert_eq!(anomalies.score, 0);
+        assert!(anomalies.findings.is_empty());
+        assert!(anomalies.classes.letters > 0.9);
+    }
+
+    #[test]
+    fn it_accept_registered_handlers() {
+        let mut builder = PluginBuilder::new();
+        let register_clcmd = builder.native("register_clcmd");
+        let command = builder.string("say /menu");
+        let handler = builder.string("cmd_menu");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, handler)
+            .op_param(OP_PUSH_C, command)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, register_clcmd)
+            .op_param(OP_STACK, 12)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN)
+            .public("cmd_menu")
+            .op(OP_PROC)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        assert_eq!(symbol_anomalies(&amxmod_plugin).unwrap().score, 0);
+    }
+
+    #[test]
+    fn it_score_obfuscated_names_high() {
+        let mut builder = PluginBuilder::new();
+        builder.native("a");
+        for name in &["a", "b", "\x01x"] {
+            builder.public(name).op(OP_PROC).op(OP_ZERO_PRI).op(OP_RETN);
+        }
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let anomalies = symbol_anomalies(&amxmod_plugin).unwrap();
+
+        assert!(anomalies.score >= 50);
+        assert_eq!(
+            anomalies.findings,
+            [
+                SymbolFinding::ShortNames {
+                    average_length: 1.25
+                },
+                SymbolFinding::NonPrintableName("\\x01x".to_owned()),
+                SymbolFinding::SingleCharacterName("a".to_owned()),
+                SymbolFinding::DuplicateName("a".to_owned()),
+                SymbolFinding::SingleCharacterName("b".to_owned()),
+                SymbolFinding::SingleCharacterName("a".to_owned()),
+                SymbolFinding::UnknownPublic("\\x01x".to_owned()),
+                SymbolFinding::UnknownPublic("a".to_owned()),
+                SymbolFinding::UnknownPublic("b".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+
+use super::cfg::is_conditional_jump;
+use super::functions::{functions, Function};
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, Plugin};
+use crate::error::AmxError;
+
+// Cod and DAT are separate address spaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum XrefTarget {
+    Code(usize),
+    Data(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum XrefKind {
+    // CALL of function
+    Call,
+    // Jumps, SWITCH to its case table and case table records
+    Jump,
+    // Global read into register
+    Load,
+    // Global write, INC, DEC and ZERO included
+    Store,
+    // PUSH of global value
+    Push,
+    // Constant equal to address of DAT string, usually native argument
+    Address,
+}
+
+impl fmt::Display for XrefKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            XrefKind::Call => "call",
+            XrefKind::Jump => "jump",
+            XrefKind::Load => "load",
+            XrefKind::Store => "store",
+            XrefKind::Push => "push",
+            XrefKind::Address => "address",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Xref {
+    // Cod address of referencing opcode
+    pub from: usize,
+    pub to: XrefTarget,
+    pub kind: XrefKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Xrefs {
+    // In cod order
+    pub refs: Vec<Xref>,
+    pub functions: Vec<Function>,
+}
+
+impl Xrefs {
+    // Code locations referencing target
+    pub fn xrefs_to(&self, target: XrefTarget) -> Vec<&Xref> {
+        self.refs.iter().filter(|x| x.to == target).collect()
+    }
+
+    // References made by opcode at cod address
+    pub fn xrefs_from(&self, address: usize) -> Vec<&Xref> {
+        self.refs.iter().filter(|x| x.from == address).collect()
+    }
+
+    // Function referencing opcode belongs to
+    pub fn function_of(&self, xref: &Xref) -> Option<&Function> {
+        self.functions.iter().find(|f| f.contains(xref.from))
+    }
+
+    // Calls of function named like in `functions`, public or sub_<address>
+    pub fn callers_of(&self, function: &str) -> Vec<&Xref> {
+        match self.functions.iter().find(|f| f.name == function) {
+            Some(f) => self.xrefs_to(XrefTarget::Code(f.address)),
+            None => vec![],
+        }
+    }
+}
+
+fn reference(opcode: &Opcode, strings: &[usize]) -> Option<(XrefKind, XrefTarget)> {
+    let param = opcode.param? as usize;
+    let code = XrefTarget::Code(param);
+    let data = XrefTarget::Data(param);
+
+    let xref = match opcode.code {
+        OP_CALL => (XrefKind::Call, code),
+        OP_JUMP | OP_SWITCH | OP_CASENONE | OP_CASEJMP => (XrefKind::Jump, code),
+        c if is_conditional_jump(c) => (XrefKind::Jump, code),
+        OP_LOAD_PRI | OP_LOAD_ALT | OP_LREF_PRI | OP_LREF_ALT => (XrefKind::Load, data),
+        OP_STOR_PRI | OP_STOR_ALT | OP_SREF_PRI | OP_SREF_ALT | OP_INC | OP_DEC | OP_ZERO => {
+            (XrefKind::Store, data)
+        }
+        OP_PUSH => (XrefKind::Push, data),
+        // Small constants are too often plain numbers, only strings count
+        OP_CONST_PRI | OP_CONST_ALT | OP_PUSH_C if strings.contains(&param) => {
+            (XrefKind::Address, data)
+        }
+        _ => return None,
+    };
+    Some(xref)
+}
+
+// References from every cod opcode to functions, jump targets and globals
+pub fn xrefs(plugin: &Plugin) -> Result<Xrefs, AmxError> {
+    let strings: Vec<usize> = plugin.strings()?.iter().map(|s| s.address).collect();
+    let refs = plugin
+        .opcodes()?
+        .iter()
+        .filter_map(|o| {
+            let (kind, to) = reference(o, &strings)?;
+            Some(Xref {
+                from: o.address,
+                to,
+                kind,
+            })
+        })
+        .collect();
+
+    Ok(Xrefs {
+        refs,
+        functions: functions(plugin)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{xrefs, Xref, XrefKind, XrefTarget};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_collect_code_and_data_references() {
+        let mut builder = PluginBuilder::new();
+        let message = builder.string("hello");
+        let counter = builder.array(&[0]);
+        let log = builder.native("log_amx");
+        builder.public("plugin_init").op(OP_PROC);
+        let load = builder.here();
+        builder
+            .op_param(OP_LOAD_PRI, counter)
+            .op(OP_INC_PRI)
+            .op_param(OP_STOR_PRI, counter)
+            .op_param(OP_PUSH_C, message)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, log)
+            .op_param(OP_STACK, 8);
+        let call = builder.here();
+        builder.op_param(OP_CALL, 0).op(OP_ZERO_PRI).op(OP_RETN);
+        let helper = builder.here();
+        builder.op(OP_PROC).op(OP_ZERO_PRI).op(OP_RETN);
+        builder.patch(call + 4, helper);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let xrefs = xrefs(&amxmod_plugin).unwrap();
+        let counter = XrefTarget::Data(counter as usize);
+        let load = load as usize;
+
+        assert_eq!(
+            xrefs.xrefs_to(counter),
+            [
+                &Xref {
+                    from: load,
+                    to: counter,
+                    kind: XrefKind::Load
+                },
+                &Xref {
+                    from: load + 12,
+                    to: counter,
+                    kind: XrefKind::Store
+                }
+            ]
+        );
+        // Constant 4 is not a string address
+        assert_eq!(xrefs.xrefs_to(XrefTarget::Data(message as usize)).len(), 1);
+        assert!(xrefs.xrefs_to(XrefTarget::Data(4)).is_empty());
+
+        let calls = xrefs.callers_of(&format!("sub_{:x}", helper));
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].from, call as usize);
+        assert_eq!(xrefs.xrefs_from(call as usize), calls);
+        assert_eq!(xrefs.function_of(calls[0]).unwrap().name, "plugin_init");
+    }
+}
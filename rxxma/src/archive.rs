@@ -0,0 +1,236 @@
+// Plugins of release archives, .zip and .tar.gz packages bundling them
+// with sources, configs and sounds. Entries are picked by extension, the
+// rest of package is skipped unread.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use flate2::read::GzDecoder;
+
+use crate::amx::Plugin;
+use crate::error::AmxError;
+use crate::facade;
+
+// Extensions of entries picked from archive, case insensitive
+const EXTENSIONS: [&str; 2] = ["amxx", "amx"];
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const TAR_MAGIC: &[u8] = b"ustar";
+const TAR_MAGIC_OFFSET: usize = 257;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    // Tar compressed as a whole with gzip
+    TarGz,
+    Tar,
+}
+
+#[derive(Debug)]
+pub struct ArchivePlugin {
+    // Path inside archive
+    pub path: String,
+    // 32 bit plugin or reason entry failed to load
+    pub plugin: Result<Plugin<'static>, AmxError>,
+}
+
+fn is_plugin(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, e)| EXTENSIONS.iter().any(|x| x.eq_ignore_ascii_case(e)))
+}
+
+fn invalid<E: ToString>(error: E) -> AmxError {
+    AmxError::InvalidArchive(error.to_string())
+}
+
+fn plugin(path: String, bytes: &[u8]) -> ArchivePlugin {
+    ArchivePlugin {
+        path,
+        plugin: facade::load_plugin(bytes).map(Plugin::into_owned),
+    }
+}
+
+// Format by magic, stream is rewound to where it was
+pub fn detect_format<R: Read + Seek>(reader: &mut R) -> Result<ArchiveFormat, AmxError> {
+    let start = reader.stream_position()?;
+    let mut magic = vec![];
+    reader
+        .by_ref()
+        .take((TAR_MAGIC_OFFSET + TAR_MAGIC.len()) as u64)
+        .read_to_end(&mut magic)?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    if magic.starts_with(ZIP_MAGIC) {
+        Ok(ArchiveFormat::Zip)
+    } else if magic.starts_with(GZIP_MAGIC) {
+        Ok(ArchiveFormat::TarGz)
+    } else if magic.get(TAR_MAGIC_OFFSET..) == Some(TAR_MAGIC) {
+        Ok(ArchiveFormat::Tar)
+    } else {
+        Err(AmxError::UnknownFormat)
+    }
+}
+
+fn zip_plugins<R: Read + Seek>(reader: R) -> Result<Vec<ArchivePlugin>, AmxError> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(invalid)?;
+    let mut result = vec![];
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(invalid)?;
+        if !entry.is_file() || !is_plugin(entry.name()) {
+            continue;
+        }
+        let mut bytes = vec![];
+        entry.read_to_end(&mut bytes)?;
+        result.push(plugin(entry.name().to_owned(), &bytes));
+    }
+    Ok(result)
+}
+
+fn tar_plugins<R: Read>(reader: R) -> Result<Vec<ArchivePlugin>, AmxError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut result = vec![];
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        let path = entry
+            .path()
+            .map_err(invalid)?
+            .to_string_lossy()
+            .into_owned();
+        if !entry.header().entry_type().is_file() || !is_plugin(&path) {
+            continue;
+        }
+        let mut bytes = vec![];
+        entry.read_to_end(&mut bytes).map_err(invalid)?;
+        result.push(plugin(path, &bytes));
+    }
+    Ok(result)
+}
+
+/// Loads every .amxx and .amx entry of zip, tar.gz or tar archive, in
+/// archive order. Broken plugins are kept with their errors.
+///
+/// ```no_run
+/// let archive = std::fs::File::open("admin_tools_1.2.zip").unwrap();
+/// for entry in rxxma::archive::plugins(archive).unwrap() {
+///     println!("{}: {:?}", entry.path, entry.plugin.map(|p| p.metadata()));
+/// }
+/// ```
+pub fn plugins<R: Read + Seek>(mut reader: R) -> Result<Vec<ArchivePlugin>, AmxError> {
+    match detect_format(&mut reader)? {
+        ArchiveFormat::Zip => zip_plugins(reader),
+        ArchiveFormat::TarGz => tar_plugins(GzDecoder::new(reader)),
+        ArchiveFormat::Tar => tar_plugins(reader),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::{detect_format, plugins, ArchiveFormat};
+    use crate::error::AmxError;
+    use crate::util::tests::load_fixture;
+
+    // Release layout, compiled plugin next to its source and config
+    fn files() -> Vec<(&'static str, Vec<u8>)> {
+        vec![
+            ("addons/amxmodx/configs/simple.ini", b"; config".to_vec()),
+            (
+                "addons/amxmodx/plugins/simple.amxx",
+                load_fixture("simple.amxx183"),
+            ),
+            (
+                "addons/amxmodx/plugins/broken.AMX",
+                b"\0\0\0\0\xE0\xF1".to_vec(),
+            ),
+            ("addons/amxmodx/scripting/simple.sma", b"#include".to_vec()),
+        ]
+    }
+
+    fn zip_archive() -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+        for (path, contents) in files() {
+            writer
+                .start_file(path, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(&contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn tar_archive() -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (path, contents) in files() {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, &contents[..])
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn assert_plugins(archive: Vec<u8>) {
+        let entries = plugins(Cursor::new(archive)).unwrap();
+
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "addons/amxmodx/plugins/simple.amxx",
+                "addons/amxmodx/plugins/broken.AMX"
+            ]
+        );
+        let simple = entries[0].plugin.as_ref().unwrap();
+        assert_eq!(
+            simple.natives().unwrap()[0].name.to_str(),
+            Ok("register_plugin")
+        );
+        assert!(entries[1].plugin.is_err());
+    }
+
+    #[test]
+    fn it_load_plugins_from_zip() {
+        let archive = zip_archive();
+        assert_eq!(
+            detect_format(&mut Cursor::new(&archive)).unwrap(),
+            ArchiveFormat::Zip
+        );
+        assert_plugins(archive);
+    }
+
+    #[test]
+    fn it_load_plugins_from_tar() {
+        let archive = tar_archive();
+        assert_eq!(
+            detect_format(&mut Cursor::new(&archive)).unwrap(),
+            ArchiveFormat::Tar
+        );
+        assert_plugins(archive.clone());
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&archive).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(
+            detect_format(&mut Cursor::new(&compressed)).unwrap(),
+            ArchiveFormat::TarGz
+        );
+        assert_plugins(compressed);
+    }
+
+    #[test]
+    fn it_err_on_unknown_archive() {
+        assert_eq!(
+            plugins(Cursor::new(load_fixture("simple.amxx183"))).err(),
+            Some(AmxError::UnknownFormat)
+        );
+        assert!(matches!(
+            plugins(Cursor::new(b"PK\x03\x04junk".to_vec())),
+            Err(AmxError::InvalidArchive(_))
+        ));
+    }
+}
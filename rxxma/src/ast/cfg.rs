@@ -0,0 +1,184 @@
+// Control flow graph with labelled edges, base for structuring
+// decompiled functions into conditions, loops and switches.
+
+use super::super::amx::Opcode;
+use super::super::amx::OpcodeType::*;
+use super::Function;
+use super::Stmt;
+use crate::analysis::{case_table, is_conditional_jump, Cfg as BlockGraph};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EdgeKind {
+    // Unconditional JUMP
+    Jump,
+    // Conditional jump taken
+    Branch,
+    // Next block, also conditional jump not taken
+    Fallthrough,
+    // SWITCH case value, None for default case
+    Case(Option<u32>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Edge {
+    // Block indexes
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    // Cod address of the first opcode
+    pub start: usize,
+    pub opcodes: Vec<Opcode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cfg {
+    pub blocks: Vec<Block>,
+    pub edges: Vec<Edge>,
+}
+
+impl Cfg {
+    pub fn from_opcodes(opcodes: &[Opcode]) -> Cfg {
+        let graph = BlockGraph::from_opcodes(opcodes);
+        let block_at = |address: usize| graph.blocks.iter().position(|b| b.start == address);
+
+        let mut edges = vec![];
+        for (from, block) in graph.blocks.iter().enumerate() {
+            let last = &opcodes[block.opcodes.end - 1];
+
+            if last.code == OP_SWITCH {
+                let casetbl = last.param.unwrap_or(0) as usize;
+                for (value, target) in case_table(opcodes, casetbl) {
+                    if let Some(to) = block_at(target) {
+                        edges.push(Edge {
+                            from,
+                            to,
+                            kind: EdgeKind::Case(value),
+                        });
+                    }
+                }
+                continue;
+            }
+
+            // Jump target goes first in successors
+            let has_target = last.param.and_then(|p| block_at(p as usize)).is_some();
+            for (i, &to) in block.successors.iter().enumerate() {
+                let kind = match last.code {
+                    OP_JUMP => EdgeKind::Jump,
+                    code if is_conditional_jump(code) && i == 0 && has_target => EdgeKind::Branch,
+                    _ => EdgeKind::Fallthrough,
+                };
+                edges.push(Edge { from, to, kind });
+            }
+        }
+
+        let blocks = graph
+            .blocks
+            .iter()
+            .map(|b| Block {
+                start: b.start,
+                opcodes: opcodes[b.opcodes.clone()].to_vec(),
+            })
+            .collect();
+
+        Cfg { blocks, edges }
+    }
+
+    // Graph of function opcodes not yet turned into other statements
+    pub fn from_function(function: &Function) -> Cfg {
+        let opcodes: Vec<Opcode> = function
+            .statements
+            .iter()
+            .filter_map(|e| match *e {
+                Stmt::Emit(o) => Some(o),
+                _ => None,
+            })
+            .collect();
+
+        Cfg::from_opcodes(&opcodes)
+    }
+
+    pub fn successors(&self, block: usize) -> Vec<&Edge> {
+        self.edges.iter().filter(|e| e.from == block).collect()
+    }
+
+    pub fn predecessors(&self, block: usize) -> Vec<&Edge> {
+        self.edges.iter().filter(|e| e.to == block).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cfg, Edge, EdgeKind};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::{Opcode, OpcodeType};
+
+    fn op(code: OpcodeType, address: usize, param: Option<u32>) -> Opcode {
+        Opcode {
+            code,
+            address,
+            param,
+        }
+    }
+
+    fn edge(from: usize, to: usize, kind: EdgeKind) -> Edge {
+        Edge { from, to, kind }
+    }
+
+    #[test]
+    fn it_label_branches() {
+        // if (pri) { pri = 0 } else { pri = 1 }
+        let opcodes = [
+            op(OP_PROC, 0x8, None),
+            op(OP_JZER, 0xC, Some(0x20)),
+            op(OP_ZERO_PRI, 0x14, None),
+            op(OP_JUMP, 0x18, Some(0x28)),
+            op(OP_CONST_PRI, 0x20, Some(1)),
+            op(OP_RETN, 0x28, None),
+        ];
+        let cfg = Cfg::from_opcodes(&opcodes);
+
+        assert_eq!(cfg.blocks.len(), 4);
+        assert_eq!(cfg.blocks[1].opcodes.len(), 2);
+        assert_eq!(
+            cfg.edges,
+            [
+                edge(0, 2, EdgeKind::Branch),
+                edge(0, 1, EdgeKind::Fallthrough),
+                edge(1, 3, EdgeKind::Jump),
+                edge(2, 3, EdgeKind::Fallthrough),
+            ]
+        );
+        assert_eq!(cfg.predecessors(3).len(), 2);
+    }
+
+    #[test]
+    fn it_label_switch_cases() {
+        let opcodes = [
+            op(OP_PROC, 0x8, None),
+            op(OP_SWITCH, 0xC, Some(0x20)),
+            op(OP_ZERO_PRI, 0x14, None),
+            op(OP_JUMP, 0x18, Some(0x34)),
+            op(OP_CASETBL, 0x20, Some(1)),
+            op(OP_CASENONE, 0x28, Some(0x34)),
+            op(OP_CASE, 0x2C, Some(3)),
+            op(OP_CASEJMP, 0x30, Some(0x14)),
+            op(OP_RETN, 0x34, None),
+        ];
+        let cfg = Cfg::from_opcodes(&opcodes);
+
+        assert_eq!(
+            cfg.successors(0),
+            [
+                &edge(0, 3, EdgeKind::Case(None)),
+                &edge(0, 1, EdgeKind::Case(Some(3))),
+            ]
+        );
+        // Case table block is not entered
+        assert!(cfg.predecessors(2).is_empty());
+        assert!(cfg.successors(2).is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use super::super::amx::OpcodeType::*;
+use super::super::amx::{Opcode, OpcodeType};
+use super::expression::Expr;
+use super::printer::{Line, Style};
+use super::Stmt;
+use crate::error::AmxError;
+
+// Condition on PRI and ALT under which conditional jump is taken,
+// or not taken
+pub fn jump_condition(code: OpcodeType, taken: bool) -> &'static str {
+    let (when_taken, when_not_taken) = match code {
+        OP_JZER => ("!pri", "pri"),
+        OP_JNZ => ("pri", "!pri"),
+        OP_JEQ => ("pri == alt", "pri != alt"),
+        OP_JNEQ => ("pri != alt", "pri == alt"),
+        OP_JLESS | OP_JSLESS => ("pri < alt", "pri >= alt"),
+        OP_JLEQ | OP_JSLEQ => ("pri <= alt", "pri > alt"),
+        OP_JGRTR | OP_JSGRTR => ("pri > alt", "pri <= alt"),
+        OP_JGEQ | OP_JSGEQ => ("pri >= alt", "pri < alt"),
+        _ => ("?", "?"),
+    };
+
+    if taken {
+        when_taken
+    } else {
+        when_not_taken
+    }
+}
+
+pub fn render_block(elements: &[Stmt], ident: usize, style: &Style) -> Result<String, AmxError> {
+    let mut source = String::new();
+    for element in elements.iter() {
+        source.push_str(&element.render(ident, style)?);
+    }
+    Ok(source)
+}
+
+// Elements on single line separated by commas, e.g. in loop header
+pub fn render_inline(elements: &[Stmt], style: &Style) -> Result<String, AmxError> {
+    let style = Style {
+        max_line_length: None,
+        ..*style
+    };
+    let mut parts = vec![];
+    for element in elements.iter() {
+        let source = element.render(0, &style)?;
+        parts.push(source.trim().trim_end_matches(';').replace('\t', " "));
+    }
+    Ok(parts.join(", "))
+}
+
+// Leading operand of `&&` or `||` condition, compiler emits
+//     <a>; JZER else; <b>; JZER else; <then>          for a && b
+//     <a>; JNZ then; <b>; JZER else; then: <then>     for a || b
+#[derive(Debug, Clone)]
+pub struct ChainedJump {
+    // Jump testing operand computed in front of it
+    pub jump: Opcode,
+    // Compute PRI and ALT for the next jump, empty once evaluated into test
+    pub elements: Vec<Stmt>,
+}
+
+// if/else reconstructed from conditional jump over `then_elements`.
+// Condition is tested on PRI (and ALT), opcodes computing them stay
+// in front of the statement unless evaluated into `test`.
+#[derive(Debug, Clone)]
+pub struct If {
+    // Jump skipping then branch
+    pub jump: Opcode,
+    // Operands tested before `jump`, all joined by the same operator
+    pub chain: Vec<ChainedJump>,
+    pub then_elements: Vec<Stmt>,
+    pub else_elements: Option<Vec<Stmt>>,
+    // Condition for entering then branch as expression, whole chain
+    // included
+    pub test: Option<Expr>,
+}
+
+impl If {
+    // Chained jumps leave to else branch for `&&`, enter then branch
+    // for `||`
+    pub fn is_conjunction(&self) -> bool {
+        self.chain
+            .first()
+            .is_none_or(|c| c.jump.param == self.jump.param)
+    }
+
+    pub fn operator(&self) -> &'static str {
+        if self.is_conjunction() {
+            "&&"
+        } else {
+            "||"
+        }
+    }
+
+    // Condition for entering then branch, negation of the jump one
+    pub fn condition(&self, style: &Style) -> Result<Line, AmxError> {
+        let mut line = Line::default();
+        if let Some(ref test) = self.test {
+            test.write(&mut line, style);
+            return Ok(line);
+        }
+
+        // Operand is true when chained jump is not taken for `&&`
+        let taken = !self.is_conjunction();
+        let mut operands = vec![];
+        let mut computation = String::new();
+        let jumps = self.chain.iter().map(|c| (c.jump, taken));
+        for (i, (jump, taken)) in jumps.chain(Some((self.jump, false))).enumerate() {
+            let test = jump_condition(jump.code, taken);
+            if computation.is_empty() {
+                operands.push(test.to_owned());
+            } else {
+                operands.push(format!("({}, {})", computation, test));
+            }
+            computation = match self.chain.get(i) {
+                Some(c) => render_inline(&c.elements, style)?,
+                None => String::new(),
+            };
+        }
+        line.push(&operands.join(&format!(" {} ", self.operator())));
+        Ok(line)
+    }
+
+    // `keyword (condition)`, keyword is `if` or `else if`
+    fn header(&self, keyword: &str, style: &Style) -> Result<Line, AmxError> {
+        let mut line = Line::from(format!("{} (", keyword));
+        line.append(self.condition(style)?);
+        line.push(")");
+        Ok(line)
+    }
+
+    // Then branch and else chain following the header
+    fn branches(&self, source: &mut String, ident: usize, style: &Style) -> Result<(), AmxError> {
+        source.push_str(&render_block(&self.then_elements, ident + 1, style)?);
+
+        match self.else_elements.as_deref() {
+            Some([Stmt::If(nested)]) => {
+                source.push_str(&style.block_continue(ident, nested.header("else if", style)?));
+                nested.branches(source, ident, style)
+            }
+            Some(elements) => {
+                source.push_str(&style.block_continue(ident, Line::from("else")));
+                source.push_str(&render_block(elements, ident + 1, style)?);
+                source.push_str(&style.block_end(ident));
+                Ok(())
+            }
+            None => {
+                source.push_str(&style.block_end(ident));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
+        let mut source = style.block_start(ident, self.header("if", style)?);
+        self.branches(&mut source, ident, style)?;
+        Ok(source)
+    }
+}
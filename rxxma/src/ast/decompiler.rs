@@ -1,93 +1,436 @@
 use log::trace;
 
+use std::mem;
+use std::ops::Range;
+
+use super::super::amx::debug_info::{DebugInfo, DebugSymbol, IDENT_REFARRAY, IDENT_REFERENCE};
 use super::super::amx::Opcode;
 use super::super::amx::OpcodeType::*;
 use super::super::amx::Plugin as AmxPlugin;
-use super::super::amx::CELLSIZE;
-use super::function_call::{Argument, FunctionCall};
+use super::condition::{ChainedJump, If};
+use super::evaluator::{literal, Context, Evaluator};
+use super::expression::{Declaration, Expr, Identifier, Register};
+use super::loop_statement::{Loop, LoopKind};
+use super::passes::deobfuscate::{comment_decoding_loops, deobfuscate, function_opcodes};
+use super::passes::Pipeline;
+use super::switch_statement::{Case, Switch};
+use super::symbol_map::{rename_variables, SymbolMap};
 use super::Function as AstFunction;
 use super::Plugin as AstPlugin;
-use super::TreeElementType;
-use super::TreeElementType::*;
+use super::Stmt;
+use super::{FunctionVisibility, Parameter};
+use crate::analysis::{
+    case_table, dat_arrays, dat_globals, function_starts, infer_includes, is_conditional_jump,
+    propagate_constants, target_at, ResolvedValue,
+};
+use crate::error::AmxError;
+use crate::stocks::StockDatabase;
+use crate::util::Encoding;
 
-pub struct Decompiler {
-    pub amx_plugin: AmxPlugin,
+pub struct Decompiler<'a> {
+    pub amx_plugin: AmxPlugin<'a>,
     pub ast_plugin: AstPlugin,
+    // Encoding of DAT strings
+    pub encoding: Encoding,
+    // Names chosen by user, applied over debug symbols
+    pub symbols: SymbolMap,
+    // Stocks collapsed instead of decompiled
+    pub stocks: StockDatabase,
+    // Obfuscated values, commented in expressions using them
+    pub resolved: Vec<ResolvedValue>,
+    // Remove junk and opaque predicates, comment string decoding loops
+    pub deobfuscate: bool,
+    // Passes run by decompile_opcodes_by_templates
+    pub pipeline: Pipeline,
 }
 
-impl Decompiler {
-    pub fn from(amx_plugin: AmxPlugin) -> Decompiler {
-        let opcodes = amx_plugin.opcodes().unwrap();
+impl<'a> Decompiler<'a> {
+    pub fn from(amx_plugin: AmxPlugin<'a>) -> Result<Decompiler<'a>, AmxError> {
+        let opcodes = amx_plugin.opcodes()?;
+        Decompiler::from_opcodes(amx_plugin, opcodes)
+    }
 
-        Decompiler {
+    // For already decoded (possibly partial) opcodes
+    pub fn from_opcodes(
+        amx_plugin: AmxPlugin<'a>,
+        opcodes: Vec<Opcode>,
+    ) -> Result<Decompiler<'a>, AmxError> {
+        Ok(Decompiler {
             amx_plugin,
-            ast_plugin: AstPlugin::from(opcodes).unwrap(),
-        }
+            ast_plugin: AstPlugin::from(opcodes)?,
+            encoding: Encoding::default(),
+            symbols: SymbolMap::new(),
+            stocks: StockDatabase::new(),
+            resolved: vec![],
+            deobfuscate: false,
+            pipeline: Pipeline::default(),
+        })
     }
 
     pub fn into_tree(self) -> AstPlugin {
         self.ast_plugin
     }
 
-    pub fn opcodes_into_functions(&mut self) {
+    pub fn opcodes_into_functions(&mut self) -> Result<(), AmxError> {
         trace!("Pack opcodes into functions");
-        let public_list = self.amx_plugin.publics().unwrap();
+        let public_list = self.amx_plugin.publics()?;
+        // Without decodable cod every PROC starts a function
+        let starts: Option<Vec<usize>> = self
+            .amx_plugin
+            .opcodes()
+            .and_then(|opcodes| function_starts(&self.amx_plugin, &opcodes))
+            .ok();
 
-        let mut new_tree: Vec<TreeElementType> = vec![];
+        let mut new_tree: Vec<Stmt> = vec![];
         let mut current_function: Option<AstFunction> = None;
 
-        for element in self.ast_plugin.tree_elements.clone().into_iter() {
+        for element in self.ast_plugin.statements.clone().into_iter() {
             let opcode = match element {
-                OpcodeType(o) => o,
+                Stmt::Emit(o) => o,
                 _ => {
                     new_tree.push(element);
                     continue;
                 }
             };
 
-            // Open function
-            if opcode.code == OP_PROC {
+            // Close previous function and open new one, functions may
+            // return in the middle so RETN does not end them
+            let starts_function = match starts {
+                Some(ref starts) => starts.binary_search(&opcode.address).is_ok(),
+                None => opcode.code == OP_PROC,
+            };
+            if starts_function {
+                if let Some(f) = current_function.take() {
+                    new_tree.push(Stmt::Function(f));
+                }
                 // TODO: Check if func already exist
                 current_function = Some(AstFunction::from(&opcode, &public_list));
+                if opcode.code == OP_PROC {
+                    continue;
+                }
+            }
+
+            // Accumulate function opcodes
+            // should be the last before top level opcodes accumulation
+            if let Some(f) = current_function.as_mut() {
+                f.statements.push(Stmt::Emit(opcode));
                 continue;
             }
 
-            // Close function
-            if opcode.code == OP_RETN && current_function.is_some() {
-                new_tree.push(FunctionType(current_function.unwrap()));
-                current_function = None;
+            new_tree.push(Stmt::Emit(opcode));
+        }
+
+        if let Some(f) = current_function {
+            new_tree.push(Stmt::Function(f));
+        }
+
+        self.ast_plugin.statements = new_tree;
+        Ok(())
+    }
+
+    pub fn decompile_opcodes_by_templates(&mut self) -> Result<(), AmxError> {
+        // Passes get decompiler itself, pipeline is put back afterwards
+        let pipeline = mem::replace(&mut self.pipeline, Pipeline::new());
+        let result = pipeline.run(self);
+        self.pipeline = pipeline;
+        result
+    }
+
+    pub fn rename_symbols(&mut self) -> Result<(), AmxError> {
+        trace!("Apply user chosen names");
+        self.ast_plugin.rename(&self.symbols);
+        Ok(())
+    }
+
+    pub fn list_required_modules(&mut self) -> Result<(), AmxError> {
+        trace!("List required modules");
+        self.ast_plugin.libraries = self
+            .amx_plugin
+            .libraries()?
+            .iter()
+            .map(|l| l.to_string_lossy().into_owned())
+            .collect();
+        Ok(())
+    }
+
+    pub fn list_includes(&mut self) -> Result<(), AmxError> {
+        trace!("List includes");
+        let natives: Vec<String> = self
+            .amx_plugin
+            .natives()?
+            .iter()
+            .map(|n| n.name.to_string_lossy().into_owned())
+            .collect();
+        self.ast_plugin.includes = infer_includes(natives.iter().map(|n| n.as_str()))
+            .into_iter()
+            .map(|i| i.to_owned())
+            .collect();
+        Ok(())
+    }
+
+    pub fn collapse_known_stocks(&mut self) -> Result<(), AmxError> {
+        trace!("Collapse known stocks");
+        let matches = self.stocks.identify(&self.amx_plugin)?;
+
+        for element in self.ast_plugin.statements.iter_mut() {
+            let function = match *element {
+                Stmt::Function(ref mut f) => f,
+                _ => continue,
+            };
+            let stock = match matches.iter().find(|m| m.address == function.address) {
+                Some(m) => m.stock,
+                None => continue,
+            };
+
+            function.name = stock.name.clone();
+            function.collapsed = true;
+            if let Some(ref include) = stock.include {
+                if !self.ast_plugin.includes.contains(include) {
+                    self.ast_plugin.includes.push(include.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Public variables, then globals cod uses in DAT order with their
+    // initial values. Globals are named after debug symbols.
+    pub fn declare_public_variables(&mut self) -> Result<(), AmxError> {
+        trace!("Declare public variables and globals");
+        let pubvars = self.amx_plugin.pubvars()?;
+
+        self.ast_plugin.globals = pubvars
+            .iter()
+            .map(|v| Declaration {
+                variable: Identifier::Public(v.name.to_string_lossy().into_owned()),
+                value: None,
+                size: None,
+                tag: None,
+            })
+            .collect();
+
+        // Address, size and initialized cells of global with its value
+        let mut variables: Vec<(usize, usize, usize, Option<Expr>)> = vec![];
+        // Opcodes decompiler got may be the only ones that decode
+        for array in dat_arrays(&self.amx_plugin).unwrap_or_default() {
+            let cells = array.cells.iter().map(|&c| literal(c)).collect();
+            let value = Some(Expr::Array(cells));
+            variables.push((array.address, array.size, array.cells.len(), value));
+        }
+        for global in dat_globals(&self.amx_plugin).unwrap_or_default() {
+            if variables.iter().any(|v| v.0 == global.address) {
                 continue;
             }
+            let (initialized, value) = match global.string {
+                Some(bytes) => (
+                    bytes.len() + 1,
+                    Some(Expr::String(self.encoding.decode(&bytes))),
+                ),
+                None if global.size == 1 => (1, global.cells.first().map(|&c| literal(c))),
+                None if global.cells.is_empty() => (0, None),
+                None => (
+                    global.cells.len(),
+                    Some(Expr::Array(
+                        global.cells.iter().map(|&c| literal(c)).collect(),
+                    )),
+                ),
+            };
+            variables.push((global.address, global.size, initialized, value));
+        }
+        variables.sort_by_key(|v| v.0);
 
-            // Accumulate function opcodes
-            // should be the last before top level opcodes accumulation
-            if let Some(f) = current_function.as_mut() {
-                f.tree_elements.push(OpcodeType(opcode));
+        let info = self.amx_plugin.debug_info()?;
+        for (address, size, initialized, value) in variables {
+            if pubvars.iter().any(|v| v.address == address) {
                 continue;
             }
+            let mut variable = Identifier::Global(address as u32);
+            let symbol = info.as_ref().and_then(|info| {
+                info.symbols
+                    .iter()
+                    .find(|s| !s.is_function() && !s.is_local() && s.address as usize == address)
+            });
+            if let Some(symbol) = symbol {
+                variable.rename(symbol_name(symbol));
+            }
+
+            self.ast_plugin.globals.push(Declaration {
+                variable,
+                // Zero tail is left to array size
+                size: Some(size as u32).filter(|&s| s > 1 && s as usize > initialized),
+                tag: match value {
+                    Some(Expr::Float(_)) => Some("Float".to_owned()),
+                    _ => None,
+                },
+                value,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn deobfuscate_opcodes(&mut self) -> Result<(), AmxError> {
+        if !self.deobfuscate {
+            return Ok(());
+        }
+        trace!("Remove junk opcodes and opaque predicates");
+        for element in self.ast_plugin.statements.iter_mut() {
+            if let Stmt::Function(ref mut f) = *element {
+                deobfuscate(&self.amx_plugin, f);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn comment_decoding_loops(&mut self) -> Result<(), AmxError> {
+        if !self.deobfuscate {
+            return Ok(());
+        }
+        trace!("Comment string decoding loops");
+        comment_decoding_loops(&self.amx_plugin, &mut self.ast_plugin.statements);
+        Ok(())
+    }
 
-            new_tree.push(OpcodeType(opcode));
+    // Runs before control flow turns opcodes into nested statements
+    pub fn resolve_constants(&mut self) -> Result<(), AmxError> {
+        trace!("Resolve computed constants");
+        self.resolved.clear();
+        for element in self.ast_plugin.statements.iter() {
+            let function = match *element {
+                Stmt::Function(ref f) => f,
+                _ => continue,
+            };
+            let opcodes = function_opcodes(function);
+            self.resolved
+                .extend(propagate_constants(&self.amx_plugin, &opcodes));
         }
+        Ok(())
+    }
+
+    pub fn decompile_control_flow(&mut self) -> Result<(), AmxError> {
+        trace!("Decompile loops and conditions");
 
-        self.ast_plugin.tree_elements = new_tree;
+        for element in self.ast_plugin.statements.iter_mut() {
+            let function = match *element {
+                Stmt::Function(ref mut f) => f,
+                _ => continue,
+            };
+
+            let jumps = jumps(&function.statements);
+            let elements = function.statements.split_off(0);
+            function.statements = structure(elements, None, &jumps);
+        }
+        Ok(())
     }
 
-    pub fn decompile_opcodes_by_templates(&mut self) -> Result<(), &'static str> {
-        self.clean_functions_break()?;
-        self.decompile_native_calls()?;
+    pub fn decompile_expressions(&mut self) -> Result<(), AmxError> {
+        trace!("Decompile expressions and native calls");
+        let mut context = Context::new(&self.amx_plugin, self.encoding)?;
+        context.resolved = self.resolved.clone();
+
+        for element in self.ast_plugin.statements.iter_mut() {
+            let function = match *element {
+                Stmt::Function(ref mut f) => f,
+                _ => continue,
+            };
+
+            let elements = function.statements.split_off(0);
+            function.statements = Evaluator::function(&context, elements);
+            function.tag = return_tag(&context, &mut function.statements);
+        }
+        Ok(())
+    }
+
+    // Real names of stocks, their parameters and variables, for plugins
+    // compiled with debug info
+    pub fn name_debug_symbols(&mut self) -> Result<(), AmxError> {
+        trace!("Name functions and variables from debug symbols");
+        let info = match self.amx_plugin.debug_info()? {
+            Some(info) => info,
+            None => return Ok(()),
+        };
+        let cellsize = self.amx_plugin.cellsize();
+
+        // Globals and statics by DAT address
+        let globals: Vec<(Identifier, String)> = info
+            .symbols
+            .iter()
+            .filter(|s| !s.is_function() && !s.is_local())
+            .map(|s| (Identifier::Global(s.address as u32), symbol_name(s)))
+            .collect();
+
+        for element in self.ast_plugin.statements.iter_mut() {
+            let function = match *element {
+                Stmt::Function(ref mut f) => f,
+                _ => continue,
+            };
+            let mut names = globals.clone();
+
+            let symbol = info
+                .symbols
+                .iter()
+                .find(|s| s.is_function() && s.code_start == function.address);
+            if let Some(symbol) = symbol {
+                if function.visibility == FunctionVisibility::Stock {
+                    function.name = symbol_name(symbol);
+                }
+                if let Some(tag) = tag_name(&info, symbol.tag) {
+                    function.tag = Some(tag);
+                }
+
+                // Frame slots of function scope, arguments included
+                let locals: Vec<(Identifier, &DebugSymbol)> = info
+                    .symbols
+                    .iter()
+                    .filter(|s| {
+                        s.is_local()
+                            && symbol.code_start <= s.code_start
+                            && s.code_end <= symbol.code_end
+                    })
+                    .map(|s| (Identifier::from_frame_offset(s.address as i32, cellsize), s))
+                    .collect();
+                function.parameters = parameters(&info, &locals);
+                names.extend(locals.iter().map(|(v, s)| (v.clone(), symbol_name(s))));
+            }
+
+            rename_variables(&mut function.statements, &names);
+        }
         Ok(())
     }
 
-    pub fn clean_functions_break(&mut self) -> Result<(), &'static str> {
+    pub fn clean_functions_return(&mut self) -> Result<(), AmxError> {
+        trace!("Clean functions from closing return");
+
+        for element in self.ast_plugin.statements.iter_mut() {
+            let function = match *element {
+                Stmt::Function(ref mut f) => f,
+                _ => continue,
+            };
+
+            // Value of falling off the end, or left from native call
+            let is_implicit = match function.statements.last() {
+                Some(Stmt::Emit(o)) => o.code == OP_RETN,
+                Some(Stmt::Return(r)) => {
+                    r.value == Expr::Constant(0) || r.value == Expr::Register(Register::Pri)
+                }
+                _ => false,
+            };
+            if is_implicit {
+                function.statements.pop();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn clean_functions_break(&mut self) -> Result<(), AmxError> {
         trace!("Clean functions from trash break");
 
         let ast_plugin = &mut self.ast_plugin;
 
         let functions: Vec<_> = ast_plugin
-            .tree_elements
+            .statements
             .iter_mut()
             .map(|e| match *e {
-                FunctionType(ref mut f) => Some(f),
+                Stmt::Function(ref mut f) => Some(f),
                 _ => None,
             })
             .filter(Option::is_some)
@@ -96,7 +439,7 @@ impl Decompiler {
 
         for function in functions {
             let mut addr = 0;
-            let current_tree = &mut function.tree_elements;
+            let current_tree = &mut function.statements;
 
             // Iterate and modify over function tree
             while addr < current_tree.len() {
@@ -104,7 +447,7 @@ impl Decompiler {
                 let position = addr - 1;
 
                 let opcode = match current_tree[position] {
-                    OpcodeType(o) => o,
+                    Stmt::Emit(o) => o,
                     _ => continue,
                 };
 
@@ -118,186 +461,903 @@ impl Decompiler {
         }
         Ok(())
     }
+}
 
-    pub fn decompile_native_calls(&mut self) -> Result<(), &'static str> {
-        trace!("Decompile native calls");
-        let ast_plugin = &mut self.ast_plugin;
-        let amx_plugin = &mut self.amx_plugin;
-        // TODO: Error handling
-        let natives = amx_plugin.natives().unwrap();
+// Tag all returned values agree on
+fn return_tag(context: &Context, elements: &mut [Stmt]) -> Option<String> {
+    let mut tags = vec![];
+    let mut pending: Vec<&mut Stmt> = elements.iter_mut().collect();
+    while let Some(element) = pending.pop() {
+        if let Stmt::Return(ref r) = *element {
+            tags.push(context.tag_of(&r.value));
+        }
+        for children in element.children_mut() {
+            pending.extend(children.iter_mut());
+        }
+    }
 
-        let functions: Vec<_> = ast_plugin
-            .tree_elements
-            .iter_mut()
-            .map(|e| match *e {
-                FunctionType(ref mut f) => Some(f),
-                _ => None,
-            })
-            .filter(Option::is_some)
-            .map(Option::unwrap)
-            .collect();
+    let first = tags.first()?.clone();
+    if tags.iter().all(|t| *t == first) {
+        first
+    } else {
+        None
+    }
+}
 
-        for function in functions {
-            let mut addr = 0;
-            let current_tree = &mut function.tree_elements;
+fn symbol_name(symbol: &DebugSymbol) -> String {
+    symbol.name.to_string_lossy().into_owned()
+}
 
-            // Iterate and modify over function tree
-            while addr < current_tree.len() {
-                addr += 1;
-                let mut position = addr - 1;
+// Debug tag by id, untagged `_` is not written
+fn tag_name(info: &DebugInfo, id: i16) -> Option<String> {
+    info.tags
+        .iter()
+        .find(|t| t.id == id)
+        .map(|t| t.name.to_string_lossy().into_owned())
+        .filter(|t| t != "_")
+}
 
-                let opcode = match current_tree[position] {
-                    OpcodeType(o) => o,
-                    _ => continue,
-                };
+// Arguments in frame order, unnamed ones keep synthetic names
+fn parameters(info: &DebugInfo, locals: &[(Identifier, &DebugSymbol)]) -> Vec<Parameter> {
+    let count = locals
+        .iter()
+        .filter_map(|(v, _)| match *v {
+            Identifier::Argument(n) => Some(n + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
 
-                if opcode.code == OP_SYSREQ_C {
-                    let sysreq_opcode = &opcode;
-                    // Take previous PUSH.C to get args count
-                    let native_arguments_count = {
-                        let element = &current_tree[position - 1];
-
-                        let opcode = match element {
-                            OpcodeType(o) => o,
-                            _ => continue,
-                        };
-
-                        // Weird native call, ignore
-                        if opcode.code != OP_PUSH_C {
-                            trace!("Native call got no arguments definition");
-                            continue;
-                        }
-
-                        opcode.param.unwrap() as usize / CELLSIZE
-                    };
-
-                    // Sysreq.c (current) - PUSH.c with args count - args count
-                    let args_start = position - 1 - native_arguments_count;
-                    // Sysreq.c (current) - PUSH.c with args count
-                    let args_end = position - 1;
-                    let raw_args: Vec<_> = current_tree.drain(args_start..args_end).collect();
-                    addr -= args_end - args_start;
-                    position -= args_end - args_start;
-
-                    // Remove push.c with num of args
-                    current_tree.remove(position - 1);
-                    addr -= 1;
-                    position -= 1;
-
-                    let is_having_non_opcodes = raw_args.iter().any(|e| match *e {
-                        OpcodeType(_) => false,
-                        _ => true,
-                    });
-
-                    // Internal error
-                    // Something else modified ast tree
-                    if is_having_non_opcodes {
-                        trace!(
-                            "Function tree was modified by bad AST transformation. Ignoring call"
-                        );
-                        continue;
-                    }
-
-                    let args_opcodes: Vec<Opcode> = raw_args
-                        .iter()
-                        .map(|e| match *e {
-                            OpcodeType(o) => Some(o),
-                            _ => None,
-                        })
-                        .filter(Option::is_some)
-                        .map(Option::unwrap)
-                        .collect();
-
-                    let is_having_non_push_c_opcodes =
-                        args_opcodes.iter().any(|o| o.code != OP_PUSH_C);
-                    if is_having_non_push_c_opcodes {
-                        trace!("Invalid native call arguments");
-                        continue;
-                    }
-
-                    let native_args: Vec<_> = args_opcodes
-                        .iter()
-                        .map(|o| o.param.unwrap())
-                        .map(|addr| amx_plugin.read_constant_auto_type(addr as usize).unwrap())
-                        .map(Argument::from)
-                        .rev()
-                        .collect();
-
-                    let native_index = sysreq_opcode.param.unwrap() as usize;
-                    let native_name = (&natives[native_index].name).clone().into_string().unwrap();
-
-                    let ast_function_call = FunctionCall {
-                        name: native_name,
-                        args: Some(native_args),
-                    };
-
-                    current_tree[position] = FunctionCallType(ast_function_call);
-
-                    // Check for trash OP_STACK
-                    {
-                        let opcode_position = position + 1;
-
-                        let opcode = {
-                            let element = match current_tree.get(opcode_position) {
-                                Some(e) => e,
-                                None => break,
-                            };
-
-                            match element {
-                                OpcodeType(o) => o,
-                                _ => break,
-                            }
-                        };
-
-                        if opcode.code == OP_STACK {
-                            current_tree.remove(opcode_position);
-                        }
-                    }
-
-                    // Check for trash OP_ZERO_PRI
-                    {
-                        let opcode_position = position + 1;
-
-                        let opcode = {
-                            let element = match current_tree.get(opcode_position) {
-                                Some(e) => e,
-                                None => break,
-                            };
-
-                            match element {
-                                OpcodeType(o) => o,
-                                _ => break,
-                            }
-                        };
-
-                        if opcode.code == OP_ZERO_PRI {
-                            current_tree.remove(opcode_position);
-                        }
-                    }
-
-                    // Check for trash OP_BREAK
-                    {
-                        let opcode_position = position + 1;
-
-                        let opcode = {
-                            let element = match current_tree.get(opcode_position) {
-                                Some(e) => e,
-                                None => break,
-                            };
-
-                            match element {
-                                OpcodeType(o) => o,
-                                _ => break,
-                            }
-                        };
-
-                        if opcode.code == OP_BREAK {
-                            current_tree.remove(opcode_position);
-                        }
-                    }
-                }
+    (0..count)
+        .map(|n| {
+            let mut variable = Identifier::Argument(n);
+            let symbol = locals.iter().find(|(v, _)| *v == variable).map(|(_, s)| s);
+            if let Some(symbol) = symbol {
+                variable.rename(symbol_name(symbol));
+            }
+            Parameter {
+                variable,
+                tag: symbol.and_then(|s| tag_name(info, s.tag)),
+                reference: symbol.is_some_and(|s| s.ident == IDENT_REFERENCE),
+                dimensions: symbol.map_or(0, |s| match s.ident {
+                    IDENT_REFARRAY => s.dimensions.len().max(1),
+                    _ => 0,
+                }),
+            }
+        })
+        .collect()
+}
+
+// Position of opcode at `address`, elements length for `end` address
+// right after them
+fn position_of(elements: &[Stmt], address: usize, end: Option<usize>) -> Option<usize> {
+    if end == Some(address) {
+        return Some(elements.len());
+    }
+    elements.iter().position(|e| match *e {
+        Stmt::Emit(o) => o.address == address,
+        _ => false,
+    })
+}
+
+// (address, target) of jumps and case table entries
+fn jumps(elements: &[Stmt]) -> Vec<(usize, usize)> {
+    let opcodes: Vec<Opcode> = elements
+        .iter()
+        .filter_map(|e| match *e {
+            Stmt::Emit(o) => Some(o),
+            _ => None,
+        })
+        .collect();
+    (0..opcodes.len())
+        .filter_map(|i| Some((opcodes[i].address, target_at(&opcodes, i)?)))
+        .collect()
+}
+
+// Whether some jump not in `except` lands on `first..region.end` from
+// outside of `region`
+fn is_entered(
+    jumps: &[(usize, usize)],
+    region: Range<usize>,
+    first: usize,
+    except: &[usize],
+) -> bool {
+    jumps.iter().any(|&(address, target)| {
+        !except.contains(&address)
+            && !region.contains(&address)
+            && first <= target
+            && target < region.end
+    })
+}
+
+// Address of opcode at `position`, `end` right after elements
+fn address_at(elements: &[Stmt], position: usize, end: Option<usize>) -> Option<usize> {
+    match elements.get(position) {
+        Some(&Stmt::Emit(o)) => Some(o.address),
+        Some(_) => None,
+        None => end,
+    }
+}
+
+fn jump_opcode(element: &Stmt) -> Option<Opcode> {
+    match *element {
+        Stmt::Emit(o) if o.code == OP_JUMP || is_conditional_jump(o.code) => Some(o),
+        _ => None,
+    }
+}
+
+// Loop starting at `position` with its body range and position following
+// it, compiler emits
+//     while: top: <condition>; JZER exit; <body>; JUMP top; exit:
+//     for: JUMP test; top: <increment>; test: <condition>; JZER exit;
+//          <body>; JUMP top; exit:
+//     do-while: top: <body>; JNZ top
+//     endless: top: <body>; JUMP top
+fn find_loop(
+    elements: &[Stmt],
+    position: usize,
+    end: Option<usize>,
+    jumps: &[(usize, usize)],
+) -> Option<(Loop, Range<usize>, usize)> {
+    let start = address_at(elements, position, end)?;
+    let new_loop = |kind, jump| Loop {
+        kind,
+        condition_elements: vec![],
+        jump,
+        test: None,
+        increment_elements: vec![],
+        body: vec![],
+        comment: None,
+    };
+
+    // Jump skipping increment of for loop on first iteration
+    let (top, test) = match jump_opcode(&elements[position]) {
+        Some(o) if o.code == OP_JUMP => {
+            let test = position_of(elements, o.param? as usize, end)?;
+            if test <= position {
+                return None;
             }
+            (position + 1, test)
         }
-        Ok(())
+        _ => (position, position),
+    };
+    let top_address = address_at(elements, top, end)?;
+
+    // Condition tested in front of body
+    let tested = (test..elements.len())
+        .find(|&i| jump_opcode(&elements[i]).is_some())
+        .and_then(|check| {
+            let jump = jump_opcode(&elements[check])?;
+            let exit = jump.param? as usize;
+            if !is_conditional_jump(jump.code) || exit <= jump.address {
+                return None;
+            }
+            let exit_position = position_of(elements, exit, end)?;
+            let back = jump_opcode(&elements[exit_position - 1])?;
+            let is_back = exit_position - 1 > check
+                && back.code == OP_JUMP
+                && back.param == Some(top_address as u32);
+            if !is_back || is_entered(jumps, start..exit, start + 1, &[start]) {
+                return None;
+            }
+
+            let kind = if top == position {
+                LoopKind::While
+            } else {
+                LoopKind::For
+            };
+            let mut found = new_loop(kind, Some(jump));
+            found.condition_elements = elements[test..check].to_vec();
+            found.increment_elements = elements[top..test].to_vec();
+            Some((found, check + 1..exit_position - 1, exit_position))
+        });
+    if tested.is_some() || top != position {
+        return tested;
+    }
+
+    // Condition tested after body or none, outermost loop first
+    let back = (position + 1..elements.len())
+        .rev()
+        .find(|&i| jump_opcode(&elements[i]).is_some_and(|o| o.param == Some(start as u32)))?;
+    let jump = jump_opcode(&elements[back])?;
+    if is_entered(jumps, start..jump.address + 1, start + 1, &[start]) {
+        return None;
+    }
+    let found = if jump.code == OP_JUMP {
+        new_loop(LoopKind::For, None)
+    } else {
+        new_loop(LoopKind::DoWhile, Some(jump))
+    };
+
+    Some((found, position..back, back + 1))
+}
+
+// Switch at `position` with case body ranges and position following it,
+// compiler emits
+//     SWITCH table; case: <body>; JUMP exit; ...; table: CASETBL ...; exit:
+fn find_switch(
+    elements: &[Stmt],
+    position: usize,
+    end: Option<usize>,
+    jumps: &[(usize, usize)],
+) -> Option<(Switch, Vec<Range<usize>>, usize)> {
+    let table_address = match elements[position] {
+        Stmt::Emit(o) if o.code == OP_SWITCH => o.param? as usize,
+        _ => return None,
+    };
+    let table = position_of(elements, table_address, end)?;
+    if table <= position {
+        return None;
+    }
+    let exit_position = (table + 1..elements.len())
+        .find(|&i| !matches!(elements[i], Stmt::Emit(o) if o.code.is_pseudo()))
+        .unwrap_or(elements.len());
+    let start = address_at(elements, position, end)?;
+    let exit = address_at(elements, exit_position, end)?;
+    if is_entered(jumps, start..exit, start + 1, &[start]) {
+        return None;
+    }
+
+    let opcodes: Vec<Opcode> = elements[table..exit_position]
+        .iter()
+        .filter_map(|e| match *e {
+            Stmt::Emit(o) => Some(o),
+            _ => None,
+        })
+        .collect();
+    let mut targets: Vec<(usize, Case)> = vec![];
+    for (value, target) in case_table(&opcodes, table_address) {
+        if target == exit {
+            continue;
+        }
+        let body_start =
+            position_of(elements, target, end).filter(|&b| b > position && b < table)?;
+        let index = match targets.iter().position(|&(b, _)| b == body_start) {
+            Some(i) => i,
+            None => {
+                targets.push((
+                    body_start,
+                    Case {
+                        values: vec![],
+                        default: false,
+                        body: vec![],
+                    },
+                ));
+                targets.len() - 1
+            }
+        };
+        match value {
+            Some(v) => targets[index].1.values.push(v),
+            None => targets[index].1.default = true,
+        }
+    }
+    targets.sort_by_key(|&(b, _)| b);
+
+    // Any code between SWITCH and first case would be lost
+    let first_case = targets.first().map_or(table, |&(b, _)| b);
+    if first_case != position + 1 {
+        return None;
+    }
+
+    let mut bodies = vec![];
+    for (i, &(body_start, _)) in targets.iter().enumerate() {
+        let mut body_end = targets.get(i + 1).map_or(table, |&(b, _)| b);
+        // Jump out of switch closing case
+        if jump_opcode(&elements[body_end - 1])
+            .is_some_and(|o| o.code == OP_JUMP && o.param == Some(exit as u32))
+        {
+            body_end -= 1;
+        }
+        bodies.push(body_start..body_end);
+    }
+    let cases = targets.into_iter().map(|(_, case)| case).collect();
+
+    Some((Switch { cases, value: None }, bodies, exit_position))
+}
+
+// Positions of `&&` or `||` chain jumps starting with conditional jump at
+// `position`, operands in between only compute values. Jumps all go to
+// else label, or all but the last one to then label right after it.
+fn chain_jumps(elements: &[Stmt], position: usize, end: Option<usize>) -> Vec<usize> {
+    let mut chain = vec![position];
+    loop {
+        let last = chain[chain.len() - 1];
+        let next = match (last + 1..elements.len()).find(|&i| match elements[i] {
+            Stmt::Emit(o) => {
+                jump_opcode(&elements[i]).is_some()
+                    || matches!(o.code, OP_RETN | OP_SWITCH | OP_HALT)
+            }
+            _ => true,
+        }) {
+            Some(next) => next,
+            None => return chain,
+        };
+        let jump = match jump_opcode(&elements[next]) {
+            Some(o) if is_conditional_jump(o.code) && o.param > Some(o.address as u32) => o,
+            _ => return chain,
+        };
+
+        let then_start = address_at(elements, next + 1, end).map(|a| a as u32);
+        let targets_all = |target: Option<u32>| {
+            target.is_some()
+                && chain
+                    .iter()
+                    .all(|&i| jump_opcode(&elements[i]).is_some_and(|o| o.param == target))
+        };
+        if !targets_all(jump.param) && !targets_all(then_start) {
+            return chain;
+        }
+        chain.push(next);
+    }
+}
+
+// Positions of jumps testing condition, then branch end and else branch
+// end of forward conditional jump at `position`, compiler emits
+//     JZER else; <then>; JUMP end; else: <else>; end:
+fn find_if(
+    elements: &[Stmt],
+    position: usize,
+    end: Option<usize>,
+    jumps: &[(usize, usize)],
+) -> Option<(Vec<usize>, usize, Option<usize>)> {
+    let first = match elements[position] {
+        Stmt::Emit(o) if is_conditional_jump(o.code) => o,
+        _ => return None,
+    };
+    let mut chain = chain_jumps(elements, position, end);
+    // Chain which does not fit is tried shorter
+    let (last, target, then_end) = loop {
+        let &last = chain.last()?;
+        let jump = jump_opcode(&elements[last])?;
+        let target = jump.param? as usize;
+        match position_of(elements, target, end) {
+            Some(then_end) if target > jump.address => break (last, target, then_end),
+            _ => chain.pop(),
+        };
+    };
+    let addresses: Vec<usize> = chain
+        .iter()
+        .filter_map(|&i| address_at(elements, i, end))
+        .collect();
+    if is_entered(jumps, first.address..target, first.address + 1, &addresses) {
+        return None;
+    }
+
+    let else_end = match elements[then_end - 1] {
+        Stmt::Emit(o) if o.code == OP_JUMP && then_end - 1 > last => {
+            let else_end = o.param.unwrap_or(0) as usize;
+            position_of(elements, else_end, end).filter(|_| {
+                else_end > target && !is_entered(jumps, target..else_end, target, &addresses)
+            })
+        }
+        _ => None,
+    };
+
+    Some((chain, then_end, else_end))
+}
+
+// Turns jumps into loops and conditions, `end` is address right after
+// elements if known
+fn structure(elements: Vec<Stmt>, end: Option<usize>, jumps: &[(usize, usize)]) -> Vec<Stmt> {
+    let mut result = vec![];
+    let mut position = 0;
+
+    while position < elements.len() {
+        if let Some((mut found, bodies, next)) = find_switch(&elements, position, end, jumps) {
+            for (case, body) in found.cases.iter_mut().zip(bodies) {
+                let body_end = address_at(&elements, body.end, end);
+                case.body = structure(elements[body].to_vec(), body_end, jumps);
+            }
+            result.push(Stmt::Switch(found));
+            position = next;
+            continue;
+        }
+
+        if let Some((mut found, body, next)) = find_loop(&elements, position, end, jumps) {
+            let body_end = address_at(&elements, body.end, end);
+            found.body = structure(elements[body].to_vec(), body_end, jumps);
+            result.push(Stmt::Loop(found));
+            position = next;
+            continue;
+        }
+
+        let (chain, then_end, else_end) = match find_if(&elements, position, end, jumps) {
+            Some(found) => found,
+            None => {
+                result.push(elements[position].clone());
+                position += 1;
+                continue;
+            }
+        };
+
+        let opcode_at = |i: usize| match elements[i] {
+            Stmt::Emit(o) => o,
+            _ => unreachable!("chain consists of jumps"),
+        };
+        let last = chain[chain.len() - 1];
+        let mut then_elements = elements[last + 1..then_end].to_vec();
+        let else_elements = else_end.map(|else_end| {
+            // Jump over else branch
+            then_elements.pop();
+            let else_elements = elements[then_end..else_end].to_vec();
+            structure(else_elements, address_at(&elements, else_end, end), jumps)
+        });
+
+        result.push(Stmt::If(If {
+            jump: opcode_at(last),
+            chain: chain
+                .windows(2)
+                .map(|w| ChainedJump {
+                    jump: opcode_at(w[0]),
+                    elements: elements[w[0] + 1..w[1]].to_vec(),
+                })
+                .collect(),
+            then_elements: structure(then_elements, address_at(&elements, then_end, end), jumps),
+            else_elements,
+            test: None,
+        }));
+        position = else_end.unwrap_or(then_end);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{jumps, structure, Decompiler};
+    use crate::amx::debug_info::tests::{debug_symbol, symbols_chunk};
+    use crate::amx::debug_info::{IDENT_FUNCTION, IDENT_VARIABLE, VCLASS_GLOBAL, VCLASS_LOCAL};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::{Opcode, OpcodeType, Plugin};
+    use crate::ast::Stmt;
+    use crate::ast::{Identifier, Style, SymbolMap};
+    use crate::stocks::tests::plugin_with_stock;
+    use crate::stocks::StockDatabase;
+    use crate::util::tests::PluginBuilder;
+
+    fn op(code: OpcodeType, address: usize, param: Option<u32>) -> Stmt {
+        Stmt::Emit(Opcode {
+            code,
+            address,
+            param,
+        })
+    }
+
+    fn source(elements: Vec<Stmt>) -> String {
+        let jumps = jumps(&elements);
+        structure(elements, None, &jumps)
+            .iter()
+            .map(|e| e.render(0, &Style::default()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn it_decompile_if_else_chain() {
+        let elements = vec![
+            op(OP_JZER, 0x8, Some(0x1C)),
+            op(OP_ZERO_PRI, 0x10, None),
+            op(OP_JUMP, 0x14, Some(0x3C)),
+            op(OP_LOAD_ALT, 0x1C, Some(0x4)),
+            op(OP_JEQ, 0x24, Some(0x38)),
+            op(OP_ZERO_ALT, 0x2C, None),
+            op(OP_JUMP, 0x30, Some(0x3C)),
+            op(OP_INC_PRI, 0x38, None),
+            op(OP_RETN, 0x3C, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "if (pri) {\n    #emit ZERO.pri\n} else {\n    #emit LOAD.alt\t0x4\n    \
+             if (pri != alt) {\n        #emit ZERO.alt\n    } else {\n        #emit INC.pri\n    }\n}\n\
+             #emit RETN\n"
+        );
+    }
+
+    #[test]
+    fn it_chain_else_if() {
+        let elements = vec![
+            op(OP_JZER, 0x8, Some(0x1C)),
+            op(OP_ZERO_PRI, 0x10, None),
+            op(OP_JUMP, 0x14, Some(0x30)),
+            op(OP_JNZ, 0x1C, Some(0x30)),
+            op(OP_ZERO_ALT, 0x24, None),
+            op(OP_RETN, 0x30, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "if (pri) {\n    #emit ZERO.pri\n} else if (!pri) {\n    #emit ZERO.alt\n}\n\
+             #emit RETN\n"
+        );
+    }
+
+    #[test]
+    fn it_chain_jumps_into_else_branch() {
+        // if (a && b) {} else {}, both jumps enter else branch
+        let elements = vec![
+            op(OP_JZER, 0x8, Some(0x2C)),
+            op(OP_LOAD_PRI, 0x10, Some(0x4)),
+            op(OP_JZER, 0x18, Some(0x2C)),
+            op(OP_ZERO_PRI, 0x20, None),
+            op(OP_JUMP, 0x24, Some(0x30)),
+            op(OP_ZERO_ALT, 0x2C, None),
+            op(OP_RETN, 0x30, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "if (pri && (#emit LOAD.pri 0x4, pri)) {\n    #emit ZERO.pri\n} else {\n    \
+             #emit ZERO.alt\n}\n#emit RETN\n"
+        );
+    }
+
+    #[test]
+    fn it_recover_logical_conditions() {
+        let decompile = |conjunction: bool| {
+            let mut builder = PluginBuilder::new();
+            builder.op(OP_PROC).op_param(OP_LOAD_S_PRI, 0xC);
+            let first = builder.here();
+            match conjunction {
+                true => builder.op_param(OP_JZER, 0),
+                false => builder.op_param(OP_JNZ, 0),
+            };
+            builder.op_param(OP_LOAD_S_PRI, 0x10);
+            let second = builder.here();
+            builder.op_param(OP_JZER, 0);
+            let then = builder.here();
+            builder.op_param(OP_INC_S, 0xC);
+            let end = builder.here();
+            builder
+                .patch(first + 4, if conjunction { end } else { then })
+                .patch(second + 4, end)
+                .op(OP_ZERO_PRI)
+                .op(OP_RETN);
+            let plugin = Plugin::try_from(builder.build()).unwrap();
+
+            let mut decompiler = Decompiler::from(plugin).unwrap();
+            decompiler.opcodes_into_functions().unwrap();
+            decompiler.decompile_opcodes_by_templates().unwrap();
+            decompiler.into_tree().render(0, &Style::default()).unwrap()
+        };
+
+        assert!(decompile(true).contains("    if (arg_0 && arg_1) {\n        arg_0++;\n    }\n"));
+        assert!(decompile(false).contains("    if (arg_0 || arg_1) {\n        arg_0++;\n    }\n"));
+    }
+
+    #[test]
+    fn it_decompile_while_loop() {
+        let elements = vec![
+            op(OP_LOAD_S_PRI, 0x8, Some(0xFFFF_FFFC)),
+            op(OP_JZER, 0x10, Some(0x28)),
+            op(OP_INC_S, 0x18, Some(0xFFFF_FFFC)),
+            op(OP_JUMP, 0x20, Some(0x8)),
+            op(OP_RETN, 0x28, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "while (#emit LOAD.S.pri 0xFFFFFFFC, pri) {\n    #emit INC.S\t0xFFFFFFFC\n}\n\
+             #emit RETN\n"
+        );
+    }
+
+    #[test]
+    fn it_decompile_for_loop() {
+        let elements = vec![
+            op(OP_JUMP, 0x8, Some(0x18)),
+            op(OP_INC_S, 0x10, Some(0xFFFF_FFFC)),
+            op(OP_LOAD_S_PRI, 0x18, Some(0xFFFF_FFFC)),
+            op(OP_JZER, 0x20, Some(0x34)),
+            op(OP_ZERO_PRI, 0x28, None),
+            op(OP_JUMP, 0x2C, Some(0x10)),
+            op(OP_RETN, 0x34, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "for (; #emit LOAD.S.pri 0xFFFFFFFC, pri; #emit INC.S 0xFFFFFFFC) {\n    \
+             #emit ZERO.pri\n}\n#emit RETN\n"
+        );
+    }
+
+    #[test]
+    fn it_decompile_do_while_loop() {
+        let elements = vec![
+            op(OP_LOAD_S_PRI, 0x8, Some(0xFFFF_FFFC)),
+            op(OP_JZER, 0x10, Some(0x1C)),
+            op(OP_ZERO_PRI, 0x18, None),
+            op(OP_LOAD_S_PRI, 0x1C, Some(0xFFFF_FFFC)),
+            op(OP_JNZ, 0x24, Some(0x8)),
+            op(OP_RETN, 0x2C, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "do {\n    #emit LOAD.S.pri\t0xFFFFFFFC\n    if (pri) {\n        #emit ZERO.pri\n    }\n    \
+             #emit LOAD.S.pri\t0xFFFFFFFC\n} while (pri);\n#emit RETN\n"
+        );
+    }
+
+    #[test]
+    fn it_decompile_endless_loop() {
+        let elements = vec![
+            op(OP_LOAD_S_PRI, 0x8, Some(0xFFFF_FFFC)),
+            op(OP_JZER, 0x10, Some(0x20)),
+            op(OP_JUMP, 0x18, Some(0x30)),
+            op(OP_INC_S, 0x20, Some(0xFFFF_FFFC)),
+            op(OP_JUMP, 0x28, Some(0x8)),
+            op(OP_RETN, 0x30, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "for (;;) {\n    #emit LOAD.S.pri\t0xFFFFFFFC\n    if (pri) {\n        #emit JUMP\t0x30\n    \
+             }\n    #emit INC.S\t0xFFFFFFFC\n}\n#emit RETN\n"
+        );
+    }
+
+    #[test]
+    fn it_decompile_switch() {
+        let elements = vec![
+            op(OP_LOAD_S_PRI, 0x8, Some(0xFFFF_FFFC)),
+            op(OP_SWITCH, 0x10, Some(0x34)),
+            op(OP_ZERO_PRI, 0x18, None),
+            op(OP_JUMP, 0x1C, Some(0x50)),
+            op(OP_CONST_PRI, 0x24, Some(5)),
+            op(OP_JUMP, 0x2C, Some(0x50)),
+            op(OP_CASETBL, 0x34, Some(2)),
+            op(OP_CASENONE, 0x3C, Some(0x24)),
+            op(OP_CASE, 0x40, Some(1)),
+            op(OP_CASEJMP, 0x44, Some(0x18)),
+            op(OP_CASE, 0x48, Some(0xFFFF_FFFF)),
+            op(OP_CASEJMP, 0x4C, Some(0x18)),
+            op(OP_RETN, 0x50, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "#emit LOAD.S.pri\t0xFFFFFFFC\nswitch (pri) {\n    case 1, -1: {\n        #emit ZERO.pri\n    \
+             }\n    default: {\n        #emit CONST.pri\t0x5\n    }\n}\n#emit RETN\n"
+        );
+    }
+
+    #[test]
+    fn it_declare_globals_and_modules() {
+        let mut builder = PluginBuilder::new();
+        let count = builder.pubvar("g_iCount", &[0]);
+        builder.native("pev");
+        builder
+            .library("fakemeta")
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_INC, count)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+
+        assert_eq!(
+            decompiler.into_tree().render(0, &Style::default()).unwrap(),
+            "// Plugin source approximation starts here\n\n\
+             #include <amxmodx>\n#include <fakemeta>\n\n// Required modules: fakemeta\n\n\
+             public g_iCount;\n\n\
+             public plugin_init () {\n    g_iCount++;\n}\n\n"
+        );
+    }
+
+    #[test]
+    fn it_declare_initialized_arrays() {
+        let mut builder = PluginBuilder::new();
+        builder.string("hi");
+        let table = builder.array(&[1, 2, 3]);
+        let padded = builder.array(&[7, 0, 0, 0]);
+        let show = builder.native("show_table");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, padded)
+            .op_param(OP_PUSH_C, table)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, show)
+            .op_param(OP_STACK, 12)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+
+        assert!(decompiler
+            .into_tree()
+            .render(0, &Style::default())
+            .unwrap()
+            .ends_with(
+                "new g_var_C[] = {1, 2, 3};\nnew g_var_18[4] = {7};\n\n\
+             public plugin_init () {\n    show_table(g_var_C, g_var_18);\n}\n\n"
+            ));
+    }
+
+    #[test]
+    fn it_declare_globals_code_uses() {
+        let mut builder = PluginBuilder::new();
+        let name = builder.string("hi");
+        let speed = builder.array(&[1.5f32.to_bits()]);
+        let buffer = builder.array(&[0, 0, 0, 0]);
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_LOAD_PRI, speed)
+            .op_param(OP_STOR_PRI, buffer)
+            .op_param(OP_ZERO, name)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+
+        assert!(decompiler
+            .into_tree()
+            .render(0, &Style::default())
+            .unwrap()
+            .ends_with(
+                "new g_var_0[] = \"hi\";\nnew Float:g_var_C = 1.5;\nnew g_var_10[4];\n\n\
+             public plugin_init () {\n    g_var_10 = g_var_C;\n    g_var_0 = 0;\n}\n\n"
+            ));
+    }
+
+    #[test]
+    fn it_tag_function_return() {
+        let mut builder = PluginBuilder::new();
+        let float = builder.native("float");
+        builder
+            .op(OP_PROC)
+            .op_param(OP_PUSH_S, 0xC)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, float)
+            .op_param(OP_STACK, 8)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+
+        assert!(decompiler
+            .into_tree()
+            .render(0, &Style::default())
+            .unwrap()
+            .contains("Float:sub_8 () {"));
+    }
+
+    #[test]
+    fn it_name_debug_symbols() {
+        let mut builder = PluginBuilder::new();
+        let count = builder.array(&[0]);
+        let start = builder.here();
+        builder
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_LOAD_S_PRI, 0xC)
+            .op_param(OP_STOR_S_PRI, 0xFFFF_FFFC)
+            .op_param(OP_INC, count)
+            .op_param(OP_STACK, 4)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let end = builder.here();
+        builder.debug_info(&symbols_chunk(&[
+            debug_symbol(
+                "g_count",
+                count as i32,
+                0..end,
+                IDENT_VARIABLE,
+                VCLASS_GLOBAL,
+            ),
+            debug_symbol(
+                "add_score",
+                start as i32,
+                start..end,
+                IDENT_FUNCTION,
+                VCLASS_GLOBAL,
+            ),
+            debug_symbol("points", 0xC, start..end, IDENT_VARIABLE, VCLASS_LOCAL),
+            debug_symbol("total", -4, start + 8..end, IDENT_VARIABLE, VCLASS_LOCAL),
+        ]));
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+
+        assert_eq!(
+            decompiler.into_tree().render(0, &Style::default()).unwrap(),
+            "// Plugin source approximation starts here\n\n#include <amxmodx>\n\n\
+             new g_count;\n\n\
+             add_score (points) {\n    new total = 0;\n    total = points;\n    g_count++;\n}\n\n"
+        );
+    }
+
+    #[test]
+    fn it_rename_symbols_of_decompiled_tree() {
+        let mut builder = PluginBuilder::new();
+        let count = builder.array(&[0]);
+        builder
+            .op(OP_PROC)
+            .op_param(OP_LOAD_S_PRI, 0xC)
+            .op_param(OP_STOR_PRI, count)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.symbols.function(0x8, "on_player_spawn");
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+        let mut tree = decompiler.into_tree();
+        assert!(tree
+            .render(0, &Style::default())
+            .unwrap()
+            .contains("on_player_spawn () {\n    g_var_0 = arg_0;\n}"));
+
+        let mut symbols = SymbolMap::new();
+        symbols
+            .function(0x8, "on_spawn")
+            .global(count, "g_last")
+            .local(0x8, Identifier::Argument(0), "id");
+        tree.rename(&symbols);
+        symbols.local(0x8, Identifier::Argument(0), "player");
+        tree.rename(&symbols);
+
+        let source = tree.render(0, &Style::default()).unwrap();
+        assert!(source.contains("new g_last;\n"));
+        assert!(source.contains("on_spawn () {\n    g_last = player;\n}"));
+    }
+
+    #[test]
+    fn it_collapse_known_stocks() {
+        let mut stocks = StockDatabase::new();
+        stocks
+            .learn(&plugin_with_stock(0, true), Some("amxmisc"))
+            .unwrap();
+
+        let mut decompiler = Decompiler::from(plugin_with_stock(1, false)).unwrap();
+        decompiler.stocks = stocks;
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+        let source = decompiler.into_tree().render(0, &Style::default()).unwrap();
+
+        assert!(source.contains("#include <amxmodx>\n#include <amxmisc>\n\n"));
+        assert!(source.ends_with("// stock is_admin comes from include\n\n"));
+    }
+
+    #[test]
+    fn it_comment_resolved_constants() {
+        let mut builder = PluginBuilder::new();
+        let get_user_flags = builder.native("get_user_flags");
+        builder
+            .public("is_admin")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_S, 0xC)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, get_user_flags)
+            .op_param(OP_STACK, 8)
+            .op_param(OP_CONST_ALT, 0x40)
+            .op_param(OP_SHL_C_ALT, 3)
+            .op(OP_AND)
+            .op(OP_RETN);
+
+        let mut decompiler = Decompiler::from(Plugin::try_from(builder.build()).unwrap()).unwrap();
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+        let source = decompiler.into_tree().render(0, &Style::default()).unwrap();
+
+        assert!(source.contains("return get_user_flags(arg_0) & (64 << 3) /* = 512 */;\n"));
     }
 }
@@ -0,0 +1,1160 @@
+// Symbolic execution of function opcodes. PRI, ALT and values pushed to
+// stack are tracked as expressions and written out as statements once
+// memory is changed. Opcodes not understood are kept as they are, with
+// registers assigned in front so they see the same values.
+
+use std::mem;
+
+use super::super::amx::OpcodeType::*;
+use super::super::amx::Plugin as AmxPlugin;
+use super::super::amx::{Opcode, OpcodeType};
+use super::condition::If;
+use super::expression::{Assignment, Declaration, Expr, Identifier, Register, Return};
+use super::function_call::FunctionCall;
+use super::loop_statement::{Loop, LoopKind};
+use super::Stmt;
+use crate::analysis::{dat_arrays, known_native, Operand, ParameterKind, ResolvedValue};
+use crate::error::AmxError;
+use crate::util::float::float_constant;
+use crate::util::Encoding;
+
+// Plugin data evaluated code refers to
+pub struct Context<'a> {
+    pub plugin: &'a AmxPlugin<'a>,
+    // Native names by SYSREQ.C index
+    pub natives: Vec<String>,
+    // Public variable names with their DAT addresses
+    pub pubvars: Vec<(u32, String)>,
+    // Names from tags table
+    pub tags: Vec<String>,
+    // Encoding of DAT strings
+    pub encoding: Encoding,
+    // Obfuscated values by cod address of opcode using them
+    pub resolved: Vec<ResolvedValue>,
+    // DAT addresses of initialized arrays, see `dat_arrays`
+    pub arrays: Vec<u32>,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(plugin: &'a AmxPlugin<'a>, encoding: Encoding) -> Result<Context<'a>, AmxError> {
+        let natives = plugin
+            .natives()?
+            .iter()
+            .map(|n| n.name.to_string_lossy().into_owned())
+            .collect();
+        let pubvars = plugin
+            .pubvars()?
+            .iter()
+            .map(|v| (v.address as u32, v.name.to_string_lossy().into_owned()))
+            .collect();
+        let tags = plugin
+            .tags()?
+            .iter()
+            .map(|t| t.name.to_string_lossy().into_owned())
+            .collect();
+        // Opcodes decompiler got may be the only ones that decode
+        let arrays = dat_arrays(plugin)
+            .unwrap_or_default()
+            .iter()
+            .map(|a| a.address as u32)
+            .collect();
+
+        Ok(Context {
+            plugin,
+            natives,
+            pubvars,
+            tags,
+            encoding,
+            resolved: vec![],
+            arrays,
+        })
+    }
+
+    // Tag of value, from native prototypes and literals. Comparisons are
+    // bool only if plugin uses that tag.
+    pub fn tag_of(&self, value: &Expr) -> Option<String> {
+        let tag = match *value {
+            Expr::Float(_) => Some("Float"),
+            Expr::Call(ref c) => known_native(&c.name).and_then(|n| n.return_tag()),
+            Expr::Binary(_, operator, _)
+                if ["==", "!=", "<", "<=", ">", ">="].contains(&operator) =>
+            {
+                Some("bool")
+            }
+            Expr::Unary("!", _) => Some("bool"),
+            _ => None,
+        };
+
+        match tag {
+            Some("bool") if !self.tags.iter().any(|t| t == "bool") => None,
+            tag => tag.map(String::from),
+        }
+    }
+
+    // Value commented with constant it resolves to at opcode, if operand
+    // is the resolved one. Pushed value popped again is commented once.
+    fn annotate(&self, address: usize, operand: Operand, value: Expr) -> Expr {
+        if let Expr::Resolved(_, _) = value {
+            return value;
+        }
+        match self.resolved.iter().find(|r| r.address == address) {
+            Some(r) if r.operand == operand => value.resolved(r.value.to_string()),
+            _ => value,
+        }
+    }
+
+    // Variable at DAT address, public ones are named
+    fn global(&self, address: u32) -> Identifier {
+        match self.pubvars.iter().find(|&&(a, _)| a == address) {
+            Some((_, name)) => Identifier::Public(name.clone()),
+            None => Identifier::Global(address),
+        }
+    }
+
+    // Array being indexed by address in register
+    fn base(&self, address: Expr) -> Expr {
+        match address {
+            Expr::Address(array) => *array,
+            Expr::Constant(address) => Expr::Variable(self.global(address)),
+            pointer => pointer,
+        }
+    }
+
+    // Constant passed to native may be address of DAT string or array, or
+    // float if native prototype tags parameter as Float:
+    fn argument(&self, native: &str, position: usize, value: Expr) -> Expr {
+        let cell = match value {
+            Expr::Constant(cell) | Expr::Float(cell) => cell,
+            other => return other,
+        };
+
+        let known = known_native(native);
+        let tag = known.and_then(|n| n.parameter_tag(position));
+        if tag == Some("Float") && f32::from_bits(cell).is_finite() {
+            return Expr::Float(cell);
+        }
+        // Arrays are not text, even if their cells fit characters
+        let kind = known.and_then(|n| n.parameter_kind(position));
+        if kind != Some(ParameterKind::Value) && self.arrays.contains(&cell) {
+            return Expr::Variable(self.global(cell));
+        }
+        match self.plugin.read_string_bytes(cell as usize) {
+            Some(bytes) => Expr::String(self.encoding.decode(&bytes)),
+            None => value,
+        }
+    }
+}
+
+pub struct Evaluator<'a> {
+    context: &'a Context<'a>,
+    pri: Expr,
+    alt: Expr,
+    // Pushed values with their frame offset, not yet written out
+    stack: Vec<(Expr, i32)>,
+    // Bytes pushed below frame, None once unknown
+    depth: Option<i32>,
+    output: Vec<Stmt>,
+}
+
+// Constant loaded into register or pushed, floats are told apart by
+// their bits
+pub(super) fn literal(cell: u32) -> Expr {
+    match float_constant(cell) {
+        Some(_) => Expr::Float(cell),
+        None => Expr::Constant(cell),
+    }
+}
+
+// Value of NOT, only comparisons can be inverted in place
+fn not(value: Expr) -> Expr {
+    match value {
+        Expr::Binary(_, operator, _) if ["==", "!=", "<", "<=", ">", ">="].contains(&operator) => {
+            value.negate()
+        }
+        other => Expr::unary("!", other),
+    }
+}
+
+// Opcodes setting PRI without reading it
+fn overwrites_pri(code: OpcodeType) -> bool {
+    matches!(
+        code,
+        OP_LOAD_PRI
+            | OP_LOAD_S_PRI
+            | OP_LREF_PRI
+            | OP_LREF_S_PRI
+            | OP_CONST_PRI
+            | OP_ZERO_PRI
+            | OP_ADDR_PRI
+            | OP_MOVE_PRI
+            | OP_POP_PRI
+            | OP_SYSREQ_C
+    )
+}
+
+fn element_calls(element: &Stmt) -> usize {
+    match *element {
+        Stmt::Assignment(ref a) => a.target.call_count() + a.value.call_count(),
+        Stmt::Declaration(ref d) => d.value.as_ref().map_or(0, |v| v.call_count()),
+        Stmt::Return(ref r) => r.value.call_count(),
+        Stmt::Call(ref c) => Expr::Call(c.clone()).call_count(),
+        _ => 0,
+    }
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(context: &'a Context<'a>, depth: Option<i32>) -> Evaluator<'a> {
+        Evaluator {
+            context,
+            pri: Expr::Register(Register::Pri),
+            alt: Expr::Register(Register::Alt),
+            stack: vec![],
+            depth,
+            output: vec![],
+        }
+    }
+
+    // Frame is empty right after PROC
+    pub fn function(context: &Context, elements: Vec<Stmt>) -> Vec<Stmt> {
+        let mut evaluator = Evaluator::new(context, Some(0));
+        evaluator.run(elements);
+        evaluator.finish();
+        evaluator.output
+    }
+
+    // Registers keep values computed by the last elements
+    pub fn run(&mut self, elements: Vec<Stmt>) {
+        for element in elements {
+            match element {
+                Stmt::Emit(o) => self.step(o),
+                Stmt::If(mut c) => {
+                    self.declare();
+                    c.test = self.evaluate_chain(&mut c);
+                    c.then_elements = self.branch(c.then_elements);
+                    c.else_elements = c.else_elements.map(|e| self.branch(e));
+                    self.output.push(Stmt::If(c));
+                    self.reset();
+                }
+                Stmt::Loop(l) => {
+                    self.declare();
+                    let evaluated = self.evaluate_loop(l);
+                    self.output.push(Stmt::Loop(evaluated));
+                    self.reset();
+                }
+                Stmt::Switch(mut s) => {
+                    self.declare();
+                    s.value = Some(self.pri.clone());
+                    for case in s.cases.iter_mut() {
+                        case.body = self.branch(mem::take(&mut case.body));
+                    }
+                    self.output.push(Stmt::Switch(s));
+                    self.reset();
+                }
+                other => {
+                    self.materialize();
+                    self.output.push(other);
+                }
+            }
+        }
+    }
+
+    // Written out pushed values as local variables
+    pub fn declare(&mut self) {
+        if let Some(&(_, offset)) = self.stack.last() {
+            self.declare_slot(offset);
+        }
+    }
+
+    // Pushed value read or written as variable by its frame offset
+    fn declare_slot(&mut self, offset: i32) {
+        if let Some(i) = self.stack.iter().position(|&(_, o)| o == offset) {
+            let declared: Vec<_> = self.stack.drain(..=i).collect();
+            for (value, offset) in declared {
+                let variable = self.identifier(offset);
+                let tag = self.context.tag_of(&value);
+                self.output.push(Stmt::Declaration(Declaration {
+                    variable,
+                    value: Some(value),
+                    size: None,
+                    tag,
+                }));
+            }
+        }
+    }
+
+    // Stable name of frame slot
+    fn identifier(&self, offset: i32) -> Identifier {
+        Identifier::from_frame_offset(offset, self.context.plugin.cellsize())
+    }
+
+    fn reset(&mut self) {
+        self.pri = Expr::Register(Register::Pri);
+        self.alt = Expr::Register(Register::Alt);
+    }
+
+    // Nothing is left to be computed, calls are made
+    fn finish(&mut self) {
+        self.declare();
+        self.flush_calls(true);
+    }
+
+    fn branch(&self, elements: Vec<Stmt>) -> Vec<Stmt> {
+        let mut evaluator = Evaluator::new(self.context, self.depth);
+        evaluator.run(elements);
+        evaluator.finish();
+        evaluator.output
+    }
+
+    // Condition of if statement with `&&` or `||` operands merged, None
+    // when operand computation leaves statements which cannot be inlined
+    fn evaluate_chain(&mut self, c: &mut If) -> Option<Expr> {
+        let conjunction = c.is_conjunction();
+        let operator = c.operator();
+        let (mut pri, mut alt) = (self.pri.clone(), self.alt.clone());
+        let mut test: Option<Expr> = None;
+        for chained in c.chain.iter() {
+            let operand = Expr::jump_condition(chained.jump.code, pri, alt, !conjunction);
+            test = Some(match test {
+                Some(left) => Expr::binary(left, operator, operand),
+                None => operand,
+            });
+
+            let mut next = Evaluator::new(self.context, self.depth);
+            next.run(chained.elements.clone());
+            pri = next.pri.clone();
+            alt = next.alt.clone();
+            next.declare();
+            if !next.output.is_empty() {
+                self.materialize();
+                return None;
+            }
+        }
+        for chained in c.chain.iter_mut() {
+            chained.elements.clear();
+        }
+
+        let operand = Expr::jump_condition(c.jump.code, pri, alt, false);
+        Some(match test {
+            Some(left) => Expr::binary(left, operator, operand),
+            None => operand,
+        })
+    }
+
+    fn evaluate_loop(&self, mut l: Loop) -> Loop {
+        // Do-while condition is computed at the end of body
+        if l.kind == LoopKind::DoWhile {
+            let mut body = Evaluator::new(self.context, self.depth);
+            body.run(mem::take(&mut l.body));
+            l.test = l
+                .jump
+                .map(|j| Expr::jump_condition(j.code, body.pri.clone(), body.alt.clone(), true));
+            body.declare();
+            l.body = body.output;
+            return l;
+        }
+
+        let mut condition = Evaluator::new(self.context, self.depth);
+        condition.run(mem::take(&mut l.condition_elements));
+        l.test = l.jump.map(|j| {
+            Expr::jump_condition(j.code, condition.pri.clone(), condition.alt.clone(), false)
+        });
+        condition.declare();
+        l.condition_elements = condition.output;
+        l.increment_elements = self.branch(mem::take(&mut l.increment_elements));
+        l.body = self.branch(mem::take(&mut l.body));
+        l
+    }
+
+    // Assigning one register breaks expression of the other one
+    // if they refer to each other
+    fn is_resolvable(&self) -> bool {
+        let crossed =
+            self.pri.uses_register(Register::Alt) && self.alt.uses_register(Register::Pri);
+        !crossed || self.pri == self.alt
+    }
+
+    // Writes out tracked values so raw opcode sees them in place
+    fn materialize(&mut self) {
+        self.declare();
+        self.materialize_registers();
+    }
+
+    fn materialize_registers(&mut self) {
+        // Pushed values refer to registers before assignment
+        let uses_registers =
+            |v: &Expr| v.uses_register(Register::Pri) || v.uses_register(Register::Alt);
+        if let Some(&(_, offset)) = self.stack.iter().rev().find(|(v, _)| uses_registers(v)) {
+            self.declare_slot(offset);
+        }
+
+        let pri = mem::replace(&mut self.pri, Expr::Register(Register::Pri));
+        let alt = mem::replace(&mut self.alt, Expr::Register(Register::Alt));
+        let mut assignments = vec![];
+
+        if pri == alt {
+            if pri == Expr::Register(Register::Alt) {
+                assignments.push((Register::Pri, alt));
+            } else {
+                if pri != Expr::Register(Register::Pri) {
+                    assignments.push((Register::Pri, pri));
+                }
+                assignments.push((Register::Alt, Expr::Register(Register::Pri)));
+            }
+        } else {
+            let pri_assignment = (Register::Pri, pri);
+            let alt_assignment = (Register::Alt, alt);
+            if alt_assignment.1.uses_register(Register::Pri) {
+                assignments.push(alt_assignment);
+                assignments.push(pri_assignment);
+            } else {
+                assignments.push(pri_assignment);
+                assignments.push(alt_assignment);
+            }
+        }
+
+        for (register, value) in assignments {
+            if value == Expr::Register(register) {
+                continue;
+            }
+            self.output.push(Stmt::Assignment(Assignment {
+                target: Expr::Register(register),
+                value,
+            }));
+        }
+    }
+
+    // Calls in values not written out yet
+    fn calls(&self) -> usize {
+        self.pri.call_count()
+            + self.alt.call_count()
+            + self
+                .stack
+                .iter()
+                .map(|(v, _)| v.call_count())
+                .sum::<usize>()
+    }
+
+    // Makes pending calls, PRI with discarded result becomes call statement
+    fn flush_calls(&mut self, discards_pri: bool) {
+        if let Some(&(_, offset)) = self.stack.iter().rev().find(|(v, _)| v.call_count() > 0) {
+            self.declare_slot(offset);
+        }
+
+        let is_pri_referenced = self.alt.uses_register(Register::Pri)
+            || self
+                .stack
+                .iter()
+                .any(|(v, _)| v.uses_register(Register::Pri));
+        if discards_pri && !is_pri_referenced {
+            let pri = mem::replace(&mut self.pri, Expr::Register(Register::Pri));
+            match pri {
+                Expr::Call(call) => self.output.push(Stmt::Call(call)),
+                value if value.call_count() > 0 => self.output.push(Stmt::Assignment(Assignment {
+                    target: Expr::Register(Register::Pri),
+                    value,
+                })),
+                value => self.pri = value,
+            }
+        }
+
+        if self.calls() > 0 {
+            self.materialize_registers();
+        }
+    }
+
+    fn step(&mut self, opcode: Opcode) {
+        if self.attempt(opcode) {
+            return;
+        }
+        // Calls are neither repeated, dropped nor moved over other statements
+        if self.calls() > 0 {
+            self.flush_calls(overwrites_pri(opcode.code));
+            if self.attempt(opcode) {
+                return;
+            }
+        }
+        self.keep(opcode);
+    }
+
+    // State is left untouched if opcode can not be evaluated
+    fn attempt(&mut self, opcode: Opcode) -> bool {
+        let calls = self.calls();
+        let saved = (
+            self.pri.clone(),
+            self.alt.clone(),
+            self.stack.clone(),
+            self.depth,
+            self.output.len(),
+        );
+
+        if self.evaluate(opcode) && self.is_resolvable() {
+            let created = if opcode.code == OP_SYSREQ_C { 1 } else { 0 };
+            let emitted: usize = self.output[saved.4..].iter().map(element_calls).sum();
+            let pending = self.calls();
+            if pending + emitted == calls + created && (emitted == 0 || pending <= created) {
+                return true;
+            }
+        }
+
+        let (pri, alt, stack, depth, length) = saved;
+        self.pri = pri;
+        self.alt = alt;
+        self.stack = stack;
+        self.depth = depth;
+        self.output.truncate(length);
+        false
+    }
+
+    // Raw opcode with its effect on stack depth
+    fn keep(&mut self, opcode: Opcode) {
+        self.materialize();
+        self.output.push(Stmt::Emit(opcode));
+
+        let cell = self.context.plugin.cellsize() as i32;
+        self.depth = match opcode.code {
+            OP_PUSH_PRI | OP_PUSH_ALT | OP_PUSH_C | OP_PUSH | OP_PUSH_S | OP_PUSHADDR => {
+                self.depth.map(|d| d + cell)
+            }
+            OP_POP_PRI | OP_POP_ALT => self.depth.map(|d| d - cell),
+            OP_STACK => self.depth.map(|d| d - opcode.param.unwrap_or(0) as i32),
+            OP_PROC => Some(0),
+            // Callee removes arguments, their count is not tracked
+            OP_CALL | OP_CALL_PRI | OP_PUSH_R | OP_SCTRL => None,
+            _ => self.depth,
+        };
+    }
+
+    fn push(&mut self, value: Expr) -> bool {
+        let depth = match self.depth {
+            Some(d) => d + self.context.plugin.cellsize() as i32,
+            None => return false,
+        };
+        self.depth = Some(depth);
+        self.stack.push((value, -depth));
+        true
+    }
+
+    // Register holding call result refers to pushed copy, so call is made once
+    fn push_register(&mut self, register: Register) -> bool {
+        let value = self.register(register).clone();
+        let has_calls = value.call_count() > 0;
+        if !self.push(value) {
+            return false;
+        }
+
+        if has_calls {
+            let offset = self.stack.last().map_or(0, |&(_, o)| o);
+            let slot = Expr::Variable(self.identifier(offset));
+            match register {
+                Register::Pri => self.pri = slot,
+                Register::Alt => self.alt = slot,
+            }
+        }
+        true
+    }
+
+    fn pop(&mut self) -> Option<Expr> {
+        let depth = self.depth.filter(|&d| d > 0)?;
+        self.depth = Some(depth - self.context.plugin.cellsize() as i32);
+        match self.stack.pop() {
+            Some((value, _)) => Some(value),
+            // Value written out as variable before
+            None => Some(Expr::Variable(self.identifier(-depth))),
+        }
+    }
+
+    // STACK releasing cells, pushed values no longer needed are dropped
+    fn release(&mut self, size: i32) -> bool {
+        let depth = match self.depth {
+            Some(d) if size > 0 && size <= d => d - size,
+            _ => return false,
+        };
+        self.depth = Some(depth);
+        self.stack.retain(|&(_, offset)| -offset <= depth);
+        true
+    }
+
+    // STACK reserving cells for array, it starts at the new stack top
+    fn allocate(&mut self, size: i32) -> bool {
+        let cell = self.context.plugin.cellsize() as i32;
+        let depth = match self.depth {
+            Some(d) if size > 0 && size % cell == 0 => d + size,
+            _ => return false,
+        };
+        self.declare();
+        self.depth = Some(depth);
+        let variable = self.identifier(-depth);
+        self.output.push(Stmt::Declaration(Declaration {
+            variable,
+            value: None,
+            size: Some((size / cell) as u32),
+            tag: None,
+        }));
+        true
+    }
+
+    // FILL zeroing array right after its allocation, Pawn does it for
+    // every array declared without initializer
+    fn fill(&mut self, size: u32) -> bool {
+        let cell = self.context.plugin.cellsize() as u32;
+        let variable = match self.output.last() {
+            Some(Stmt::Declaration(Declaration {
+                variable,
+                value: None,
+                size: Some(cells),
+                ..
+            })) if cells * cell == size => variable.clone(),
+            _ => return false,
+        };
+        let array = Expr::Address(Box::new(Expr::Variable(variable)));
+        self.pri == Expr::Constant(0) && self.alt == array
+    }
+
+    // SYSREQ.C with arguments and their size on stack, they stay there
+    // until STACK
+    fn call(&mut self, index: usize) -> bool {
+        let name = match self.context.natives.get(index) {
+            Some(n) => n.clone(),
+            None => return false,
+        };
+        let cellsize = self.context.plugin.cellsize() as u32;
+        let count = match self.stack.last() {
+            Some(&(Expr::Constant(size), _)) if size % cellsize == 0 => (size / cellsize) as usize,
+            _ => return false,
+        };
+        if count >= self.stack.len() {
+            return false;
+        }
+
+        self.stack.pop();
+        let at = self.stack.len() - count;
+        let args: Vec<Expr> = self
+            .stack
+            .split_off(at)
+            .into_iter()
+            .rev()
+            .enumerate()
+            .map(|(n, (v, _))| self.context.argument(&name, n, v))
+            .collect();
+        if args.iter().any(|a| a.uses_register(Register::Alt)) {
+            return false;
+        }
+
+        // Native writes globals and memory passed by address
+        let is_clobbered = |value: &Expr| {
+            value.call_count() > 0
+                || value.reads_through_pointer()
+                || args.iter().any(|a| match *a.unresolved() {
+                    Expr::Address(ref v) => value.reads(v),
+                    _ => false,
+                })
+        };
+        if is_clobbered(&self.alt) {
+            return false;
+        }
+        if let Some(&(_, offset)) = self.stack.iter().rev().find(|(v, _)| is_clobbered(v)) {
+            self.declare_slot(offset);
+        }
+
+        self.pri = Expr::Call(FunctionCall { name, args });
+        true
+    }
+
+    // Memory write computed by `statement` as (target, value), `register`
+    // holds the value
+    fn store<F>(&mut self, register: Option<Register>, statement: F)
+    where
+        F: Fn(&Evaluator<'a>) -> (Expr, Expr),
+    {
+        // Values still on stack outlive the statement
+        self.declare();
+        let (mut target, mut value) = statement(self);
+
+        let aliased = [Register::Pri, Register::Alt]
+            .iter()
+            .filter(|&&r| Some(r) != register)
+            .any(|&r| self.register(r).reads(&target));
+        if aliased {
+            self.materialize();
+            let evaluated = statement(self);
+            target = evaluated.0;
+            value = evaluated.1;
+        }
+
+        self.output.push(Stmt::Assignment(Assignment {
+            target: target.clone(),
+            value,
+        }));
+        // Register now holds stored value
+        match register {
+            Some(Register::Pri) => self.pri = target,
+            Some(Register::Alt) => self.alt = target,
+            None => {}
+        }
+    }
+
+    fn register(&self, register: Register) -> &Expr {
+        match register {
+            Register::Pri => &self.pri,
+            Register::Alt => &self.alt,
+        }
+    }
+
+    // Whether opcode was turned into expressions
+    fn evaluate(&mut self, opcode: Opcode) -> bool {
+        let param = opcode.param.unwrap_or(0);
+        let global = Expr::Variable(self.context.global(param));
+        let local = Expr::Variable(self.identifier(param as i32));
+        let constant = Expr::Constant(param);
+        let one = Expr::Constant(1);
+        let context = self.context;
+        let annotate = |operand, value| context.annotate(opcode.address, operand, value);
+        self.pri = annotate(Operand::Pri, self.pri.clone());
+        self.alt = annotate(Operand::Alt, self.alt.clone());
+        let pri = self.pri.clone();
+        let alt = self.alt.clone();
+
+        match opcode.code {
+            OP_LOAD_S_PRI | OP_LOAD_S_ALT | OP_LREF_S_PRI | OP_LREF_S_ALT | OP_ADDR_PRI
+            | OP_ADDR_ALT | OP_PUSH_S | OP_PUSHADDR => self.declare_slot(param as i32),
+            _ => {}
+        }
+
+        match opcode.code {
+            // Debug information and checks do not change registers
+            OP_BREAK | OP_NOP | OP_BOUNDS | OP_LINE | OP_FILE | OP_SYMBOL | OP_SRANGE
+            | OP_SYMTAG => {}
+
+            OP_LOAD_PRI => self.pri = global,
+            OP_LOAD_ALT => self.alt = global,
+            OP_LOAD_S_PRI => self.pri = local,
+            OP_LOAD_S_ALT => self.alt = local,
+            OP_LREF_PRI => self.pri = Expr::Deref(Box::new(global)),
+            OP_LREF_ALT => self.alt = Expr::Deref(Box::new(global)),
+            OP_LREF_S_PRI => self.pri = Expr::Deref(Box::new(local)),
+            OP_LREF_S_ALT => self.alt = Expr::Deref(Box::new(local)),
+            OP_LOAD_I => self.pri = pri.deref(),
+            OP_CONST_PRI => self.pri = literal(param),
+            OP_CONST_ALT => self.alt = literal(param),
+            OP_ZERO_PRI => self.pri = Expr::Constant(0),
+            OP_ZERO_ALT => self.alt = Expr::Constant(0),
+            OP_ADDR_PRI => self.pri = Expr::Address(Box::new(local)),
+            OP_ADDR_ALT => self.alt = Expr::Address(Box::new(local)),
+            OP_MOVE_PRI => self.pri = alt,
+            OP_MOVE_ALT => self.alt = pri,
+            OP_LIDX => self.pri = Expr::Index(Box::new(self.context.base(alt)), Box::new(pri)),
+            OP_IDXADDR => {
+                let element = Expr::Index(Box::new(self.context.base(alt)), Box::new(pri));
+                self.pri = Expr::Address(Box::new(element));
+            }
+
+            OP_PUSH_PRI => return self.push_register(Register::Pri),
+            OP_PUSH_ALT => return self.push_register(Register::Alt),
+            OP_PUSH_C => return self.push(annotate(Operand::Param, literal(param))),
+            OP_PUSH => return self.push(annotate(Operand::Param, global)),
+            OP_PUSH_S => return self.push(annotate(Operand::Param, local)),
+            OP_PUSHADDR => {
+                let address = Expr::Address(Box::new(local));
+                return self.push(annotate(Operand::Param, address));
+            }
+            OP_POP_PRI => match self.pop() {
+                Some(value) => self.pri = value,
+                None => return false,
+            },
+            OP_POP_ALT => match self.pop() {
+                Some(value) => self.alt = value,
+                None => return false,
+            },
+            OP_STACK if (param as i32) < 0 => return self.allocate(-(param as i32)),
+            OP_STACK => return self.release(param as i32),
+            OP_FILL => return self.fill(param),
+            OP_SYSREQ_C => return self.call(param as usize),
+
+            OP_ADD => self.pri = Expr::binary(pri, "+", alt),
+            OP_SUB => self.pri = Expr::binary(pri, "-", alt),
+            OP_SUB_ALT => self.pri = Expr::binary(alt, "-", pri),
+            OP_SMUL | OP_UMUL => self.pri = Expr::binary(pri, "*", alt),
+            // Remainder goes to ALT
+            OP_SDIV | OP_UDIV => {
+                self.pri = Expr::binary(pri.clone(), "/", alt.clone());
+                self.alt = Expr::binary(pri, "%", alt);
+            }
+            OP_SDIV_ALT | OP_UDIV_ALT => {
+                self.pri = Expr::binary(alt.clone(), "/", pri.clone());
+                self.alt = Expr::binary(alt, "%", pri);
+            }
+            OP_AND => self.pri = Expr::binary(pri, "&", alt),
+            OP_OR => self.pri = Expr::binary(pri, "|", alt),
+            OP_XOR => self.pri = Expr::binary(pri, "^", alt),
+            OP_SHL => self.pri = Expr::binary(pri, "<<", alt),
+            OP_SHR => self.pri = Expr::binary(pri, ">>>", alt),
+            OP_SSHR => self.pri = Expr::binary(pri, ">>", alt),
+            OP_EQ => self.pri = Expr::binary(pri, "==", alt),
+            OP_NEQ => self.pri = Expr::binary(pri, "!=", alt),
+            OP_LESS | OP_SLESS => self.pri = Expr::binary(pri, "<", alt),
+            OP_LEQ | OP_SLEQ => self.pri = Expr::binary(pri, "<=", alt),
+            OP_GRTR | OP_SGRTR => self.pri = Expr::binary(pri, ">", alt),
+            OP_GEQ | OP_SGEQ => self.pri = Expr::binary(pri, ">=", alt),
+            OP_ADD_C => self.pri = Expr::binary(pri, "+", constant),
+            OP_SMUL_C => self.pri = Expr::binary(pri, "*", constant),
+            OP_SHL_C_PRI => self.pri = Expr::binary(pri, "<<", constant),
+            OP_SHL_C_ALT => self.alt = Expr::binary(alt, "<<", constant),
+            OP_SHR_C_PRI => self.pri = Expr::binary(pri, ">>>", constant),
+            OP_SHR_C_ALT => self.alt = Expr::binary(alt, ">>>", constant),
+            OP_EQ_C_PRI => self.pri = Expr::binary(pri, "==", constant),
+            OP_EQ_C_ALT => self.pri = Expr::binary(alt, "==", constant),
+            OP_NOT => self.pri = not(pri),
+            OP_NEG => self.pri = Expr::unary("-", pri),
+            OP_INVERT => self.pri = Expr::unary("~", pri),
+            OP_INC_PRI => self.pri = Expr::binary(pri, "+", one),
+            OP_DEC_PRI => self.pri = Expr::binary(pri, "-", one),
+            OP_INC_ALT => self.alt = Expr::binary(alt, "+", one),
+            OP_DEC_ALT => self.alt = Expr::binary(alt, "-", one),
+
+            OP_STOR_PRI => self.store(Some(Register::Pri), |e| (global.clone(), e.pri.clone())),
+            OP_STOR_ALT => self.store(Some(Register::Alt), |e| (global.clone(), e.alt.clone())),
+            OP_STOR_S_PRI => self.store(Some(Register::Pri), |e| (local.clone(), e.pri.clone())),
+            OP_STOR_S_ALT => self.store(Some(Register::Alt), |e| (local.clone(), e.alt.clone())),
+            OP_SREF_PRI | OP_SREF_ALT | OP_SREF_S_PRI | OP_SREF_S_ALT => {
+                let variable = match opcode.code {
+                    OP_SREF_PRI | OP_SREF_ALT => global,
+                    _ => local,
+                };
+                let register = match opcode.code {
+                    OP_SREF_PRI | OP_SREF_S_PRI => Register::Pri,
+                    _ => Register::Alt,
+                };
+                self.store(Some(register), |e| {
+                    let target = Expr::Deref(Box::new(variable.clone()));
+                    (target, e.register(register).clone())
+                });
+            }
+            OP_STOR_I => self.store(Some(Register::Pri), |e| {
+                (e.alt.clone().deref(), e.pri.clone())
+            }),
+            OP_INC | OP_INC_S | OP_DEC | OP_DEC_S => {
+                let target = match opcode.code {
+                    OP_INC | OP_DEC => global,
+                    _ => local,
+                };
+                let operator = match opcode.code {
+                    OP_INC | OP_INC_S => "+",
+                    _ => "-",
+                };
+                self.store(None, |_| {
+                    let value = Expr::binary(target.clone(), operator, one.clone());
+                    (target.clone(), value)
+                });
+            }
+            OP_INC_I | OP_DEC_I => {
+                let operator = if opcode.code == OP_INC_I { "+" } else { "-" };
+                self.store(None, |e| {
+                    let target = e.pri.clone().deref();
+                    let value = Expr::binary(target.clone(), operator, one.clone());
+                    (target, value)
+                });
+            }
+            OP_ZERO => self.store(None, |_| (global.clone(), Expr::Constant(0))),
+            OP_ZERO_S => self.store(None, |_| (local.clone(), Expr::Constant(0))),
+
+            OP_RETN => {
+                self.declare();
+                self.output.push(Stmt::Return(Return { value: pri }));
+                self.reset();
+            }
+
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{Context, Evaluator};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::{Opcode, OpcodeType, Plugin};
+    use crate::ast::Stmt;
+    use crate::ast::Style;
+    use crate::util::tests::PluginBuilder;
+    use crate::util::Encoding;
+
+    fn op(code: OpcodeType, param: Option<u32>) -> Stmt {
+        Stmt::Emit(Opcode {
+            code,
+            address: 0,
+            param,
+        })
+    }
+
+    fn source_with(builder: &PluginBuilder, elements: Vec<Stmt>) -> String {
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+        let context = Context::new(&plugin, Encoding::default()).unwrap();
+
+        Evaluator::function(&context, elements)
+            .iter()
+            .map(|e| e.render(0, &Style::default()).unwrap())
+            .collect()
+    }
+
+    fn source(elements: Vec<Stmt>) -> String {
+        source_with(&PluginBuilder::new(), elements)
+    }
+
+    #[test]
+    fn it_evaluate_assignment() {
+        // new a = 5; a = a + 5
+        let elements = vec![
+            op(OP_PUSH_C, Some(5)),
+            op(OP_BREAK, None),
+            op(OP_LOAD_S_PRI, Some(0xFFFF_FFFC)),
+            op(OP_ADD_C, Some(5)),
+            op(OP_STOR_S_PRI, Some(0xFFFF_FFFC)),
+        ];
+
+        assert_eq!(source(elements), "new local_1 = 5;\nlocal_1 += 5;\n");
+    }
+
+    #[test]
+    fn it_evaluate_stack_operands() {
+        // new a; global = arg + a * 2; a++
+        let elements = vec![
+            op(OP_PUSH_C, Some(0)),
+            op(OP_LOAD_S_PRI, Some(0xC)),
+            op(OP_PUSH_PRI, None),
+            op(OP_LOAD_S_PRI, Some(0xFFFF_FFFC)),
+            op(OP_SMUL_C, Some(2)),
+            op(OP_POP_ALT, None),
+            op(OP_ADD, None),
+            op(OP_STOR_PRI, Some(0x10)),
+            op(OP_INC_S, Some(0xFFFF_FFFC)),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "new local_1 = 0;\ng_var_10 = local_1 * 2 + arg_0;\nlocal_1++;\n"
+        );
+    }
+
+    #[test]
+    fn it_evaluate_array_element() {
+        // array[index] = array[index] + 1
+        let elements = vec![
+            op(OP_ADDR_ALT, Some(0xFFFF_FFD8)),
+            op(OP_LOAD_S_PRI, Some(0xFFFF_FFD4)),
+            op(OP_BOUNDS, Some(9)),
+            op(OP_IDXADDR, None),
+            op(OP_INC_I, None),
+        ];
+
+        assert_eq!(source(elements), "local_10[local_11]++;\n");
+    }
+
+    #[test]
+    fn it_assign_registers_before_raw_opcode() {
+        let elements = vec![
+            op(OP_LOAD_S_PRI, Some(0xC)),
+            op(OP_MOVE_ALT, None),
+            op(OP_ADD_C, Some(1)),
+            op(OP_LCTRL, Some(5)),
+            op(OP_RETN, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "pri = arg_0 + 1;\nalt = arg_0;\n#emit LCTRL\t0x5\nreturn pri;\n"
+        );
+    }
+
+    #[test]
+    fn it_keep_registers_read_before_store() {
+        // alt holds old value of overwritten variable
+        let elements = vec![
+            op(OP_LOAD_S_ALT, Some(0xFFFF_FFFC)),
+            op(OP_CONST_PRI, Some(1)),
+            op(OP_STOR_S_PRI, Some(0xFFFF_FFFC)),
+            op(OP_MOVE_PRI, None),
+            op(OP_RETN, None),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "pri = 1;\nalt = local_1;\nlocal_1 = pri;\nreturn alt;\n"
+        );
+    }
+
+    #[test]
+    fn it_evaluate_native_call() {
+        // new health = get_user_health(id); client_print(id, 3, "%d", health)
+        let mut builder = PluginBuilder::new();
+        let get_user_health = builder.native("get_user_health");
+        let client_print = builder.native("client_print");
+        builder.string("padding");
+        let format = builder.string("%d");
+        let elements = vec![
+            op(OP_PUSH_S, Some(0xC)),
+            op(OP_PUSH_C, Some(4)),
+            op(OP_SYSREQ_C, Some(get_user_health)),
+            op(OP_STACK, Some(8)),
+            op(OP_PUSH_PRI, None),
+            op(OP_BREAK, None),
+            op(OP_PUSH_S, Some(0xFFFF_FFFC)),
+            op(OP_PUSH_C, Some(format)),
+            op(OP_CONST_PRI, Some(3)),
+            op(OP_PUSH_PRI, None),
+            op(OP_PUSH_S, Some(0xC)),
+            op(OP_PUSH_C, Some(16)),
+            op(OP_SYSREQ_C, Some(client_print)),
+            op(OP_STACK, Some(20)),
+            op(OP_STACK, Some(4)),
+            op(OP_ZERO_PRI, None),
+            op(OP_RETN, None),
+        ];
+
+        assert_eq!(
+            source_with(&builder, elements),
+            "new local_1 = get_user_health(arg_0);\nclient_print(arg_0, 3, \"%d\", local_1);\n\
+             return 0;\n"
+        );
+    }
+
+    #[test]
+    fn it_keep_call_order() {
+        // Result of first call is discarded, second one is stored
+        let mut builder = PluginBuilder::new();
+        let random = builder.native("random");
+        let elements = vec![
+            op(OP_PUSH_C, Some(0)),
+            op(OP_SYSREQ_C, Some(random)),
+            op(OP_STACK, Some(4)),
+            op(OP_PUSH_C, Some(0)),
+            op(OP_SYSREQ_C, Some(random)),
+            op(OP_STACK, Some(4)),
+            op(OP_MOVE_ALT, None),
+            op(OP_STOR_PRI, Some(0x10)),
+            op(OP_STOR_ALT, Some(0x14)),
+        ];
+
+        assert_eq!(
+            source_with(&builder, elements),
+            "random();\npri = random();\ng_var_10 = pri;\ng_var_14 = pri;\n"
+        );
+    }
+
+    #[test]
+    fn it_declare_array() {
+        // new a; new array[10]; array[a] = 1
+        let elements = vec![
+            op(OP_PUSH_C, Some(0)),
+            op(OP_STACK, Some(0xFFFF_FFD8)),
+            op(OP_ZERO_PRI, None),
+            op(OP_ADDR_ALT, Some(0xFFFF_FFD4)),
+            op(OP_FILL, Some(40)),
+            op(OP_ADDR_ALT, Some(0xFFFF_FFD4)),
+            op(OP_LOAD_S_PRI, Some(0xFFFF_FFFC)),
+            op(OP_IDXADDR, None),
+            op(OP_MOVE_ALT, None),
+            op(OP_CONST_PRI, Some(1)),
+            op(OP_STOR_I, None),
+            op(OP_STACK, Some(44)),
+        ];
+
+        assert_eq!(
+            source(elements),
+            "new local_1 = 0;\nnew local_11[10];\nlocal_11[local_1] = 1;\n"
+        );
+    }
+
+    #[test]
+    fn it_evaluate_string_argument() {
+        // server_print("^"%s^" joined^n", name)
+        let mut builder = PluginBuilder::new();
+        let server_print = builder.native("server_print");
+        builder.string("padding");
+        let name = builder.string("Player");
+        let format = builder.string("\"%s\" joined\n");
+        let elements = vec![
+            op(OP_PUSH_C, Some(name)),
+            op(OP_PUSH_C, Some(format)),
+            op(OP_PUSH_C, Some(8)),
+            op(OP_SYSREQ_C, Some(server_print)),
+            op(OP_STACK, Some(12)),
+        ];
+
+        assert_eq!(
+            source_with(&builder, elements),
+            "server_print(\"^\"%s^\" joined^n\", \"Player\");\n"
+        );
+    }
+
+    #[test]
+    fn it_evaluate_float_constants() {
+        // set_task(0.0, "task"); global = 1.5
+        let mut builder = PluginBuilder::new();
+        let set_task = builder.native("set_task");
+        builder.string("padding");
+        let task = builder.string("task");
+        let elements = vec![
+            op(OP_PUSH_C, Some(task)),
+            op(OP_PUSH_C, Some(0)),
+            op(OP_PUSH_C, Some(8)),
+            op(OP_SYSREQ_C, Some(set_task)),
+            op(OP_STACK, Some(12)),
+            op(OP_CONST_PRI, Some(1.5f32.to_bits())),
+            op(OP_STOR_PRI, Some(0x10)),
+        ];
+
+        assert_eq!(
+            source_with(&builder, elements),
+            "set_task(0.0, \"task\");\ng_var_10 = 1.5;\n"
+        );
+    }
+
+    #[test]
+    fn it_name_public_variables() {
+        // g_iCount++; g_iLast = g_iScores[g_iCount]
+        let mut builder = PluginBuilder::new();
+        let count = builder.pubvar("g_iCount", &[0]);
+        let last = builder.pubvar("g_iLast", &[0]);
+        let scores = builder.pubvar("g_iScores", &[0; 4]);
+        let elements = vec![
+            op(OP_INC, Some(count)),
+            op(OP_LOAD_PRI, Some(count)),
+            op(OP_CONST_ALT, Some(scores)),
+            op(OP_LIDX, None),
+            op(OP_STOR_PRI, Some(last)),
+        ];
+
+        assert_eq!(
+            source_with(&builder, elements),
+            "g_iCount++;\ng_iLast = g_iScores[g_iCount];\n"
+        );
+    }
+
+    #[test]
+    fn it_tag_declarations() {
+        // new Float:x = floatmul(1.5, 2.0); new bool:b = arg == 1
+        let mut builder = PluginBuilder::new();
+        let floatmul = builder.native("floatmul");
+        builder.tag("bool", 1);
+        let elements = vec![
+            op(OP_PUSH_C, Some(2.0f32.to_bits())),
+            op(OP_PUSH_C, Some(1.5f32.to_bits())),
+            op(OP_PUSH_C, Some(8)),
+            op(OP_SYSREQ_C, Some(floatmul)),
+            op(OP_STACK, Some(12)),
+            op(OP_PUSH_PRI, None),
+            op(OP_LOAD_S_PRI, Some(0xC)),
+            op(OP_EQ_C_PRI, Some(1)),
+            op(OP_PUSH_PRI, None),
+        ];
+
+        assert_eq!(
+            source_with(&builder, elements),
+            "new Float:local_1 = floatmul(1.5, 2.0);\nnew bool:local_2 = arg_0 == 1;\n"
+        );
+    }
+}
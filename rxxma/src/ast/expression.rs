@@ -0,0 +1,567 @@
+use std::fmt;
+
+use super::super::amx::OpcodeType;
+use super::super::amx::OpcodeType::*;
+use super::function_call::FunctionCall;
+use super::printer::{Line, Style};
+use crate::error::AmxError;
+use crate::util::float::float_literal;
+
+// Frame cells in front of arguments: previous frame, return address
+// and argument size
+const FRAME_HEADER_CELLS: i32 = 3;
+
+// Variable named by its frame slot or DAT address
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Identifier {
+    // Cells below frame, starting from 1
+    Local(u32),
+    // Argument position, starting from 0
+    Argument(u32),
+    // Frame header or unaligned frame offset
+    Frame(i32),
+    Global(u32),
+    // Global declared public, its name is kept in pubvars table
+    Public(String),
+    // Name from debug symbols given to variable above
+    Named(String, Box<Identifier>),
+}
+
+impl Identifier {
+    pub fn from_frame_offset(offset: i32, cellsize: usize) -> Identifier {
+        let cell = cellsize as i32;
+        if offset % cell != 0 {
+            Identifier::Frame(offset)
+        } else if offset < 0 {
+            Identifier::Local(offset.unsigned_abs() / cell as u32)
+        } else if offset >= FRAME_HEADER_CELLS * cell {
+            Identifier::Argument((offset / cell - FRAME_HEADER_CELLS) as u32)
+        } else {
+            Identifier::Frame(offset)
+        }
+    }
+
+    // Slot or address variable is stored at, regardless of its name
+    pub fn original(&self) -> &Identifier {
+        match self {
+            Identifier::Named(_, original) => original.original(),
+            other => other,
+        }
+    }
+
+    pub fn rename(&mut self, name: String) {
+        let original = self.original().clone();
+        *self = Identifier::Named(name, Box::new(original));
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Identifier::Local(n) => write!(f, "local_{}", n),
+            Identifier::Argument(n) => write!(f, "arg_{}", n),
+            Identifier::Frame(offset) => write!(f, "frame_{}", offset),
+            Identifier::Global(address) => write!(f, "g_var_{:X}", address),
+            Identifier::Public(name) | Identifier::Named(name, _) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Register {
+    Pri,
+    Alt,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Constant(u32),
+    // Float bits
+    Float(u32),
+    // Decoded DAT string
+    String(String),
+    Variable(Identifier),
+    // Register value not known as expression
+    Register(Register),
+    // Arrays and reference arguments are passed by address
+    Address(Box<Expr>),
+    // Value at address held by variable, e.g. reference argument
+    Deref(Box<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    Unary(&'static str, Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+    Call(FunctionCall),
+    // Obfuscated value with what constant propagation resolved it to
+    Resolved(Box<Expr>, String),
+    // Array initializer, e.g. of global array held in DAT
+    Array(Vec<Expr>),
+}
+
+// Binding strength of binary operators
+fn precedence(operator: &str) -> u8 {
+    match operator {
+        "||" => 1,
+        "&&" => 2,
+        "|" => 3,
+        "^" => 4,
+        "&" => 5,
+        "==" | "!=" => 6,
+        "<" | "<=" | ">" | ">=" => 7,
+        "<<" | ">>" | ">>>" => 8,
+        "+" | "-" => 9,
+        _ => 10,
+    }
+}
+
+fn inverse(operator: &str) -> Option<&'static str> {
+    let inverse = match operator {
+        "==" => "!=",
+        "!=" => "==",
+        "<" => ">=",
+        "<=" => ">",
+        ">" => "<=",
+        ">=" => "<",
+        _ => return None,
+    };
+    Some(inverse)
+}
+
+// Quoted Pawn literal, ^ is escape character
+fn string_literal(value: &str) -> String {
+    let mut literal = String::from("\"");
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => literal.push_str("^\""),
+            '^' => literal.push_str("^^"),
+            '\n' => literal.push_str("^n"),
+            '\r' => literal.push_str("^r"),
+            '\t' => literal.push_str("^t"),
+            c if c.is_ascii_control() => {
+                literal.push_str(&format!("^{}", c as u32));
+                // Terminated so next digit is not read as part of code
+                if chars
+                    .peek()
+                    .is_some_and(|n| n.is_ascii_digit() || *n == ';')
+                {
+                    literal.push(';');
+                }
+            }
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+impl Expr {
+    pub fn binary(left: Expr, operator: &'static str, right: Expr) -> Expr {
+        Expr::Binary(Box::new(left), operator, Box::new(right))
+    }
+
+    pub fn unary(operator: &'static str, operand: Expr) -> Expr {
+        Expr::Unary(operator, Box::new(operand))
+    }
+
+    // Logical negation, comparisons are inverted
+    pub fn negate(self) -> Expr {
+        match self {
+            Expr::Unary("!", operand) => *operand,
+            Expr::Binary(left, operator, right) => match inverse(operator) {
+                Some(inverse) => Expr::Binary(left, inverse, right),
+                None => Expr::unary("!", Expr::Binary(left, operator, right)),
+            },
+            other => Expr::unary("!", other),
+        }
+    }
+
+    // Value with comment showing what it is at runtime
+    pub fn resolved(self, value: String) -> Expr {
+        Expr::Resolved(Box::new(self), value)
+    }
+
+    // Value without resolved comment
+    pub fn unresolved(&self) -> &Expr {
+        match *self {
+            Expr::Resolved(ref e, _) => e.unresolved(),
+            ref other => other,
+        }
+    }
+
+    // Value stored at address this expression evaluates to
+    pub fn deref(self) -> Expr {
+        match self {
+            Expr::Address(variable) => *variable,
+            other => Expr::Deref(Box::new(other)),
+        }
+    }
+
+    // Condition on PRI and ALT under which conditional jump is taken,
+    // or not taken
+    pub fn jump_condition(code: OpcodeType, pri: Expr, alt: Expr, taken: bool) -> Expr {
+        let condition = match code {
+            OP_JZER => pri.negate(),
+            OP_JNZ => pri,
+            OP_JEQ => Expr::binary(pri, "==", alt),
+            OP_JNEQ => Expr::binary(pri, "!=", alt),
+            OP_JLESS | OP_JSLESS => Expr::binary(pri, "<", alt),
+            OP_JLEQ | OP_JSLEQ => Expr::binary(pri, "<=", alt),
+            OP_JGRTR | OP_JSGRTR => Expr::binary(pri, ">", alt),
+            _ => Expr::binary(pri, ">=", alt),
+        };
+
+        if taken {
+            condition
+        } else {
+            condition.negate()
+        }
+    }
+
+    pub fn uses_register(&self, register: Register) -> bool {
+        match *self {
+            Expr::Register(r) => r == register,
+            Expr::Constant(_) | Expr::Float(_) | Expr::String(_) | Expr::Variable(_) => false,
+            Expr::Call(ref c) => c.args.iter().any(|a| a.uses_register(register)),
+            Expr::Array(ref cells) => cells.iter().any(|c| c.uses_register(register)),
+            Expr::Address(ref e)
+            | Expr::Deref(ref e)
+            | Expr::Unary(_, ref e)
+            | Expr::Resolved(ref e, _) => e.uses_register(register),
+            Expr::Index(ref a, ref b) | Expr::Binary(ref a, _, ref b) => {
+                a.uses_register(register) || b.uses_register(register)
+            }
+        }
+    }
+
+    // Same value after registers exchange their contents
+    pub fn swap_registers(self) -> Expr {
+        match self {
+            Expr::Register(Register::Pri) => Expr::Register(Register::Alt),
+            Expr::Register(Register::Alt) => Expr::Register(Register::Pri),
+            Expr::Address(e) => Expr::Address(Box::new(e.swap_registers())),
+            Expr::Deref(e) => Expr::Deref(Box::new(e.swap_registers())),
+            Expr::Unary(o, e) => Expr::unary(o, e.swap_registers()),
+            Expr::Resolved(e, value) => Expr::Resolved(Box::new(e.swap_registers()), value),
+            Expr::Index(a, b) => {
+                Expr::Index(Box::new(a.swap_registers()), Box::new(b.swap_registers()))
+            }
+            Expr::Binary(a, o, b) => Expr::binary(a.swap_registers(), o, b.swap_registers()),
+            Expr::Call(c) => Expr::Call(FunctionCall {
+                name: c.name,
+                args: c.args.into_iter().map(Expr::swap_registers).collect(),
+            }),
+            other => other,
+        }
+    }
+
+    // Whether value may change after memory at `target` is written
+    pub fn reads(&self, target: &Expr) -> bool {
+        match *self {
+            Expr::Variable(ref v) => match *target {
+                Expr::Variable(ref t) => v == t,
+                // Pointers reach globals and caller frames only
+                _ => matches!(v.original(), Identifier::Global(_) | Identifier::Public(_)),
+            },
+            Expr::Constant(_)
+            | Expr::Float(_)
+            | Expr::String(_)
+            | Expr::Register(_)
+            | Expr::Array(_) => false,
+            // Array address is fixed, pointer arguments are not written
+            // through pointers
+            Expr::Address(ref e) => match **e {
+                Expr::Index(ref base, ref index) => {
+                    index.reads(target)
+                        || (matches!(*target, Expr::Variable(_)) && base.reads(target))
+                }
+                Expr::Deref(ref pointer) => pointer.reads(target),
+                _ => false,
+            },
+            Expr::Deref(_) | Expr::Index(_, _) | Expr::Call(_) => true,
+            Expr::Unary(_, ref e) | Expr::Resolved(ref e, _) => e.reads(target),
+            Expr::Binary(ref a, _, ref b) => a.reads(target) || b.reads(target),
+        }
+    }
+
+    // Whether value may change after native writes memory it can reach
+    pub fn reads_through_pointer(&self) -> bool {
+        self.reads(&Expr::Deref(Box::new(Expr::Constant(0))))
+    }
+
+    // Variables value is computed from
+    pub fn identifiers_mut(&mut self) -> Vec<&mut Identifier> {
+        match *self {
+            Expr::Variable(ref mut v) => vec![v],
+            Expr::Call(ref mut c) => c.args.iter_mut().flat_map(Expr::identifiers_mut).collect(),
+            Expr::Address(ref mut e)
+            | Expr::Deref(ref mut e)
+            | Expr::Unary(_, ref mut e)
+            | Expr::Resolved(ref mut e, _) => e.identifiers_mut(),
+            Expr::Index(ref mut a, ref mut b) | Expr::Binary(ref mut a, _, ref mut b) => {
+                let mut identifiers = a.identifiers_mut();
+                identifiers.extend(b.identifiers_mut());
+                identifiers
+            }
+            _ => vec![],
+        }
+    }
+
+    // Calls to be made when value is computed
+    pub fn call_count(&self) -> usize {
+        match *self {
+            Expr::Call(ref c) => 1 + c.args.iter().map(|a| a.call_count()).sum::<usize>(),
+            Expr::Address(ref e)
+            | Expr::Deref(ref e)
+            | Expr::Unary(_, ref e)
+            | Expr::Resolved(ref e, _) => e.call_count(),
+            Expr::Index(ref a, ref b) | Expr::Binary(ref a, _, ref b) => {
+                a.call_count() + b.call_count()
+            }
+            _ => 0,
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match *self {
+            Expr::Binary(_, operator, _) => precedence(operator),
+            Expr::Unary(_, _) => 11,
+            _ => 12,
+        }
+    }
+
+    fn write_operand(&self, line: &mut Line, style: &Style, min_precedence: u8) {
+        if self.precedence() < min_precedence {
+            line.push("(");
+            self.write(line, style);
+            line.push(")");
+        } else {
+            self.write(line, style);
+        }
+    }
+
+    // Appends source of value in `style`
+    pub fn write(&self, line: &mut Line, style: &Style) {
+        match *self {
+            Expr::Constant(value) => line.push(&style.constant(value as i32)),
+            Expr::Float(bits) => line.push(&float_literal(f32::from_bits(bits))),
+            Expr::String(ref value) => line.push(&string_literal(value)),
+            Expr::Variable(ref variable) => line.push(&variable.to_string()),
+            Expr::Register(Register::Pri) => line.push("pri"),
+            Expr::Register(Register::Alt) => line.push("alt"),
+            Expr::Address(ref e) => e.write(line, style),
+            Expr::Deref(ref e) => match **e {
+                Expr::Variable(ref variable) => line.push(&variable.to_string()),
+                ref other => {
+                    line.push("[");
+                    other.write(line, style);
+                    line.push("]");
+                }
+            },
+            Expr::Index(ref base, ref index) => {
+                base.write_operand(line, style, 12);
+                line.push("[");
+                index.write(line, style);
+                line.push("]");
+            }
+            Expr::Unary(operator, ref operand) => {
+                line.push(operator);
+                operand.write_operand(line, style, 11);
+            }
+            Expr::Binary(ref left, operator, ref right) => {
+                let precedence = precedence(operator);
+                left.write_operand(line, style, precedence);
+                line.push(&format!(" {} ", operator));
+                // Left associative, equal precedence on the right needs parens
+                right.write_operand(line, style, precedence + 1);
+            }
+            Expr::Call(ref call) => call.write(line, style),
+            Expr::Array(ref cells) => {
+                line.push("{");
+                write_list(line, cells, style);
+                line.push("}");
+            }
+            Expr::Resolved(ref e, ref value) => {
+                // Parenthesized so comment covers whole value
+                e.write_operand(line, style, 12);
+                line.push(&format!(" /* = {} */", value));
+            }
+        }
+    }
+}
+
+// Comma separated values, line may be wrapped after every comma
+pub fn write_list(line: &mut Line, values: &[Expr], style: &Style) {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            line.push(", ");
+            line.wrap_point();
+        }
+        value.write(line, style);
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut line = Line::default();
+        self.write(&mut line, &Style::default());
+        write!(f, "{}", line)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub target: Expr,
+    pub value: Expr,
+}
+
+impl Assignment {
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
+        let mut line = Line::default();
+        self.target.write(&mut line, style);
+        match self.value {
+            Expr::Binary(ref left, operator, ref right) if **left == self.target => {
+                match (operator, &**right) {
+                    ("+", Expr::Constant(1)) => line.push("++"),
+                    ("-", Expr::Constant(1)) => line.push("--"),
+                    _ => {
+                        line.push(&format!(" {}= ", operator));
+                        right.write(&mut line, style);
+                    }
+                }
+            }
+            _ => {
+                line.push(" = ");
+                self.value.write(&mut line, style);
+            }
+        }
+        line.push(";");
+
+        Ok(style.line(ident, &line))
+    }
+}
+
+// Value pushed to stack and kept there as local variable, or array
+// allocated on stack
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub variable: Identifier,
+    pub value: Option<Expr>,
+    // Array size in cells
+    pub size: Option<u32>,
+    pub tag: Option<String>,
+}
+
+impl Declaration {
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
+        let keyword = match self.variable.original() {
+            Identifier::Public(_) => "public",
+            _ => "new",
+        };
+        let tag = match self.tag {
+            Some(ref tag) => format!("{}:", tag),
+            None => String::new(),
+        };
+        let mut line = Line::from(format!("{} {}{}", keyword, tag, self.variable));
+        match (self.size, &self.value) {
+            (Some(size), _) => line.push(&format!("[{}]", style.constant(size as i32))),
+            // Sized by initializer
+            (None, Some(Expr::Array(_) | Expr::String(_))) => line.push("[]"),
+            _ => {}
+        }
+        if let Some(ref value) = self.value {
+            line.push(" = ");
+            value.write(&mut line, style);
+        }
+        line.push(";");
+        Ok(style.line(ident, &line))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Return {
+    pub value: Expr,
+}
+
+impl Return {
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
+        let mut line = Line::from("return ");
+        self.value.write(&mut line, style);
+        line.push(";");
+        Ok(style.line(ident, &line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expr, Identifier, Register};
+    use crate::amx::OpcodeType::*;
+
+    fn local(offset: i32) -> Expr {
+        Expr::Variable(Identifier::from_frame_offset(offset, 4))
+    }
+
+    #[test]
+    fn it_parenthesize_by_precedence() {
+        let sum = Expr::binary(local(-4), "+", Expr::Constant(5));
+        let product = Expr::binary(sum.clone(), "*", local(12));
+        let difference = Expr::binary(local(-4), "-", sum);
+
+        assert_eq!(product.to_string(), "(local_1 + 5) * arg_0");
+        assert_eq!(difference.to_string(), "local_1 - (local_1 + 5)");
+    }
+
+    #[test]
+    fn it_invert_negated_comparison() {
+        let less = Expr::binary(local(-4), "<", Expr::Constant(10));
+        let condition = Expr::jump_condition(OP_JZER, less, Expr::Register(Register::Alt), false);
+
+        assert_eq!(condition.to_string(), "local_1 < 10");
+        assert_eq!(condition.negate().to_string(), "local_1 >= 10");
+    }
+
+    #[test]
+    fn it_name_frame_slots() {
+        assert_eq!(Identifier::from_frame_offset(-8, 4), Identifier::Local(2));
+        assert_eq!(
+            Identifier::from_frame_offset(0x10, 4),
+            Identifier::Argument(1)
+        );
+        assert_eq!(
+            Identifier::from_frame_offset(0x18, 8),
+            Identifier::Argument(0)
+        );
+        assert_eq!(Identifier::from_frame_offset(8, 4), Identifier::Frame(8));
+        assert_eq!(Identifier::from_frame_offset(-6, 4), Identifier::Frame(-6));
+        assert_eq!(
+            Identifier::from_frame_offset(i32::MIN, 4),
+            Identifier::Local(0x2000_0000)
+        );
+    }
+
+    #[test]
+    fn it_rename_variables() {
+        let mut sum = Expr::binary(local(-4), "+", local(12));
+        for variable in sum.identifiers_mut() {
+            if *variable == Identifier::Local(1) {
+                variable.rename(String::from("count"));
+                variable.rename(String::from("total"));
+            }
+        }
+
+        assert_eq!(sum.to_string(), "total + arg_0");
+        assert_eq!(*sum.identifiers_mut()[0].original(), Identifier::Local(1));
+    }
+
+    #[test]
+    fn it_comment_resolved_value() {
+        let key = Expr::binary(Expr::Constant(0x40), "<<", Expr::Constant(3));
+        let flags = Expr::binary(local(-4), "&", key.resolved("512".to_owned()));
+        assert_eq!(flags.to_string(), "local_1 & (64 << 3) /* = 512 */");
+    }
+
+    #[test]
+    fn it_escape_string_literal() {
+        let string = Expr::String(String::from("^4[Tag]^1 \"hi\"\n\u{1}2"));
+        assert_eq!(string.to_string(), r#""^^4[Tag]^^1 ^"hi^"^n^1;2""#);
+    }
+}
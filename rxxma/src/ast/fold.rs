@@ -0,0 +1,198 @@
+// Rewriting walk over decompiled tree, taking nodes by value and returning
+// their replacements. Like `visit`, methods default to the free functions
+// of the same name which fold children.
+
+use super::super::amx::Opcode;
+use super::expression::{Assignment, Declaration, Expr, Identifier, Return};
+use super::function::Function;
+use super::function_call::FunctionCall;
+use super::plugin::Plugin;
+use super::Stmt;
+
+pub trait Fold {
+    fn fold_plugin(&mut self, plugin: Plugin) -> Plugin {
+        fold_plugin(self, plugin)
+    }
+
+    fn fold_function(&mut self, function: Function) -> Function {
+        fold_function(self, function)
+    }
+
+    // Statements of one block, may drop or add statements
+    fn fold_block(&mut self, statements: Vec<Stmt>) -> Vec<Stmt> {
+        fold_block(self, statements)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        fold_stmt(self, stmt)
+    }
+
+    fn fold_declaration(&mut self, declaration: Declaration) -> Declaration {
+        fold_declaration(self, declaration)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_call(&mut self, call: FunctionCall) -> FunctionCall {
+        fold_call(self, call)
+    }
+
+    fn fold_identifier(&mut self, identifier: Identifier) -> Identifier {
+        identifier
+    }
+
+    fn fold_opcode(&mut self, opcode: Opcode) -> Opcode {
+        opcode
+    }
+}
+
+pub fn fold_plugin<F: Fold + ?Sized>(folder: &mut F, plugin: Plugin) -> Plugin {
+    Plugin {
+        globals: plugin
+            .globals
+            .into_iter()
+            .map(|g| folder.fold_declaration(g))
+            .collect(),
+        statements: folder.fold_block(plugin.statements),
+        ..plugin
+    }
+}
+
+pub fn fold_function<F: Fold + ?Sized>(folder: &mut F, mut function: Function) -> Function {
+    for parameter in function.parameters.iter_mut() {
+        let variable = parameter.variable.clone();
+        parameter.variable = folder.fold_identifier(variable);
+    }
+    function.statements = folder.fold_block(function.statements);
+    function
+}
+
+pub fn fold_block<F: Fold + ?Sized>(folder: &mut F, statements: Vec<Stmt>) -> Vec<Stmt> {
+    statements
+        .into_iter()
+        .map(|s| folder.fold_stmt(s))
+        .collect()
+}
+
+fn fold_test<F: Fold + ?Sized>(folder: &mut F, test: Option<Expr>) -> Option<Expr> {
+    test.map(|t| folder.fold_expr(t))
+}
+
+pub fn fold_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: Stmt) -> Stmt {
+    match stmt {
+        Stmt::Emit(o) => Stmt::Emit(folder.fold_opcode(o)),
+        Stmt::Function(f) => Stmt::Function(folder.fold_function(f)),
+        Stmt::Call(c) => Stmt::Call(folder.fold_call(c)),
+        Stmt::If(mut c) => {
+            for chained in c.chain.iter_mut() {
+                let elements = std::mem::take(&mut chained.elements);
+                chained.elements = folder.fold_block(elements);
+            }
+            c.test = fold_test(folder, c.test);
+            c.then_elements = folder.fold_block(c.then_elements);
+            c.else_elements = c.else_elements.map(|e| folder.fold_block(e));
+            Stmt::If(c)
+        }
+        Stmt::Loop(mut l) => {
+            l.condition_elements = folder.fold_block(l.condition_elements);
+            l.test = fold_test(folder, l.test);
+            l.increment_elements = folder.fold_block(l.increment_elements);
+            l.body = folder.fold_block(l.body);
+            Stmt::Loop(l)
+        }
+        Stmt::Switch(mut s) => {
+            s.value = fold_test(folder, s.value);
+            for case in s.cases.iter_mut() {
+                let body = std::mem::take(&mut case.body);
+                case.body = folder.fold_block(body);
+            }
+            Stmt::Switch(s)
+        }
+        Stmt::Assignment(a) => Stmt::Assignment(Assignment {
+            target: folder.fold_expr(a.target),
+            value: folder.fold_expr(a.value),
+        }),
+        Stmt::Declaration(d) => Stmt::Declaration(folder.fold_declaration(d)),
+        Stmt::Return(r) => Stmt::Return(Return {
+            value: folder.fold_expr(r.value),
+        }),
+    }
+}
+
+pub fn fold_declaration<F: Fold + ?Sized>(folder: &mut F, declaration: Declaration) -> Declaration {
+    Declaration {
+        variable: folder.fold_identifier(declaration.variable),
+        value: fold_test(folder, declaration.value),
+        ..declaration
+    }
+}
+
+pub fn fold_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    let mut fold = |e: Box<Expr>| Box::new(folder.fold_expr(*e));
+    match expr {
+        Expr::Variable(i) => Expr::Variable(folder.fold_identifier(i)),
+        Expr::Address(e) => Expr::Address(fold(e)),
+        Expr::Deref(e) => Expr::Deref(fold(e)),
+        Expr::Unary(operator, e) => Expr::Unary(operator, fold(e)),
+        Expr::Resolved(e, value) => Expr::Resolved(fold(e), value),
+        Expr::Index(a, b) => {
+            let a = fold(a);
+            Expr::Index(a, fold(b))
+        }
+        Expr::Binary(a, operator, b) => {
+            let a = fold(a);
+            Expr::Binary(a, operator, fold(b))
+        }
+        Expr::Call(c) => Expr::Call(folder.fold_call(c)),
+        Expr::Array(cells) => Expr::Array(cells.into_iter().map(|c| folder.fold_expr(c)).collect()),
+        other => other,
+    }
+}
+
+pub fn fold_call<F: Fold + ?Sized>(folder: &mut F, call: FunctionCall) -> FunctionCall {
+    FunctionCall {
+        args: call.args.into_iter().map(|a| folder.fold_expr(a)).collect(),
+        ..call
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{fold_call, Fold};
+    use crate::amx::Plugin;
+    use crate::ast::{Decompiler, FunctionCall, Style};
+    use crate::util::tests::load_fixture;
+
+    // Natives renamed after their wrappers, as a plugin port would
+    struct RenameNative;
+
+    impl Fold for RenameNative {
+        fn fold_call(&mut self, call: FunctionCall) -> FunctionCall {
+            let call = fold_call(self, call);
+            match call.name.as_str() {
+                "native_one" => FunctionCall {
+                    name: "wrapped_one".to_owned(),
+                    ..call
+                },
+                _ => call,
+            }
+        }
+    }
+
+    #[test]
+    fn it_fold_calls() {
+        let plugin = Plugin::try_from(load_fixture("two_natives.amx183")).unwrap();
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+
+        let tree = RenameNative.fold_plugin(decompiler.into_tree());
+        let source = tree.render(0, &Style::default()).unwrap();
+
+        assert!(source.contains("    wrapped_one();\n    native_two();\n"));
+    }
+}
@@ -1,7 +1,9 @@
 use super::super::amx::Opcode;
 use super::super::amx::Public;
-use super::TreeElement;
-use super::TreeElementType;
+use super::expression::Identifier;
+use super::printer::{Line, Style};
+use super::Stmt;
+use crate::error::AmxError;
 use std::fmt;
 
 #[derive(PartialEq, Debug, Clone)]
@@ -20,16 +22,45 @@ impl fmt::Display for FunctionVisibility {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub variable: Identifier,
+    pub tag: Option<String>,
+    // Passed by reference
+    pub reference: bool,
+    // Array dimensions, their sizes are not known
+    pub dimensions: usize,
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.reference {
+            write!(f, "&")?;
+        }
+        if let Some(ref tag) = self.tag {
+            write!(f, "{}:", tag)?;
+        }
+        write!(f, "{}{}", self.variable, "[]".repeat(self.dimensions))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Function {
     pub name: String,
-    pub tree_elements: Vec<TreeElementType>,
+    // Cod address of PROC
+    pub address: usize,
+    // Known only from debug symbols
+    pub parameters: Vec<Parameter>,
+    pub statements: Vec<Stmt>,
     pub visibility: FunctionVisibility,
+    // Tag of returned values
+    pub tag: Option<String>,
+    // Recognized include stock, body is not rendered
+    pub collapsed: bool,
 }
 
 impl Function {
     pub fn from(opcode: &Opcode, public_list: &[Public]) -> Function {
-        static mut STOCK_FUNCTION_COUNTER: u32 = 0;
         let opcode_public = public_list.iter().find(|x| x.address == opcode.address);
 
         let visibility = if opcode_public.is_some() {
@@ -39,39 +70,50 @@ impl Function {
         };
 
         let name = if let Some(p) = opcode_public {
-            p.name.to_str().unwrap().to_string()
+            p.name.to_string_lossy().into_owned()
         } else {
-            // I like to live dangerously
-            unsafe {
-                STOCK_FUNCTION_COUNTER += 1;
-                format!("sub_{:x}", STOCK_FUNCTION_COUNTER - 1)
-            }
+            // Same as `analysis::functions` and disassembly listings
+            format!("sub_{:x}", opcode.address)
         };
 
         Function {
             name,
-            tree_elements: vec![],
+            address: opcode.address,
+            parameters: vec![],
+            statements: vec![],
             visibility,
+            tag: None,
+            collapsed: false,
         }
     }
-}
 
-impl TreeElement for Function {
-    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
-        let mut source = String::new();
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
+        if self.collapsed {
+            return Ok(format!("// stock {} comes from include\n\n", self.name));
+        }
 
-        source.push_str(&format!(
-            "{visibility}{fname} () {{\n",
-            visibility = self.visibility,
-            fname = self.name
-        ));
+        let tag = match self.tag {
+            Some(ref tag) => format!("{}:", tag),
+            None => String::new(),
+        };
+        let mut header = Line::from(format!("{}{}{} (", self.visibility, tag, self.name));
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            if i > 0 {
+                header.push(", ");
+                header.wrap_point();
+            }
+            header.push(&parameter.to_string());
+        }
+        header.push(")");
+        let mut source = style.block_start(ident, header);
 
-        for element in self.tree_elements.iter() {
-            let element_source = element.to_string(ident + 1)?;
+        for element in self.statements.iter() {
+            let element_source = element.render(ident + 1, style)?;
             source.push_str(&element_source);
         }
 
-        source.push_str("}\n\n");
+        source.push_str(&style.block_end(ident));
+        source.push('\n');
         Ok(source)
     }
 }
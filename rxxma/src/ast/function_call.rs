@@ -1,54 +1,36 @@
-use super::TreeElement;
-use crate::amx::plugin::ConstantParam;
-use std::convert::From;
-use std::ffi::CString;
+use std::fmt;
 
-#[derive(Debug, Clone)]
-pub enum Argument {
-    String(CString),
-    Cell(u32),
-}
+use super::expression::{write_list, Expr};
+use super::printer::{Line, Style};
+use crate::error::AmxError;
 
-impl From<ConstantParam> for Argument {
-    fn from(constant: ConstantParam) -> Self {
-        match constant {
-            ConstantParam::Cell(v) => Argument::Cell(v),
-            ConstantParam::String(v) => Argument::String(v),
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FunctionCall {
     pub name: String,
-    pub args: Option<Vec<Argument>>,
+    // In source order
+    pub args: Vec<Expr>,
 }
 
-impl TreeElement for FunctionCall {
-    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
-        let mut source = String::new();
-
-        // Push ident
-        source.push_str(&format!("{:>width$}", "", width = (2 * ident)));
-
-        let args: String = self
-            .args
-            .clone()
-            .unwrap_or_else(|| vec![])
-            .into_iter()
-            .map(|arg| match arg {
-                Argument::String(s) => format!("{:?}", s),
-                Argument::Cell(n) => format!("{}", n),
-            })
-            .collect::<Vec<String>>()
-            .join(", ");
+impl FunctionCall {
+    pub fn write(&self, line: &mut Line, style: &Style) {
+        line.push(&self.name);
+        line.push("(");
+        write_list(line, &self.args, style);
+        line.push(")");
+    }
 
-        source.push_str(&format!(
-            "{native}({args});\n",
-            native = self.name,
-            args = args
-        ));
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
+        let mut line = Line::default();
+        self.write(&mut line, style);
+        line.push(";");
+        Ok(style.line(ident, &line))
+    }
+}
 
-        Ok(source)
+impl fmt::Display for FunctionCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut line = Line::default();
+        self.write(&mut line, &Style::default());
+        write!(f, "{}", line)
     }
 }
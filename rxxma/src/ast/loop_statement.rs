@@ -0,0 +1,98 @@
+use super::super::amx::Opcode;
+use super::condition::{jump_condition, render_block, render_inline};
+use super::expression::Expr;
+use super::printer::{Line, Style};
+use super::Stmt;
+use crate::error::AmxError;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LoopKind {
+    While,
+    DoWhile,
+    For,
+}
+
+// Loop reconstructed from backward jump
+#[derive(Debug, Clone)]
+pub struct Loop {
+    pub kind: LoopKind,
+    // Computes PRI and ALT for `jump` before every check
+    pub condition_elements: Vec<Stmt>,
+    // Jump leaving (while, for) or repeating (do-while) loop,
+    // None for endless loop
+    pub jump: Option<Opcode>,
+    // Condition for running body again as expression
+    pub test: Option<Expr>,
+    // Third expression of for loop
+    pub increment_elements: Vec<Stmt>,
+    pub body: Vec<Stmt>,
+    // Written above loop, e.g. what deobfuscation found out
+    pub comment: Option<String>,
+}
+
+impl Loop {
+    // Condition for running body again
+    pub fn condition(&self, style: &Style) -> Result<Line, AmxError> {
+        let jump = match self.jump {
+            Some(j) => j,
+            None => return Ok(Line::default()),
+        };
+
+        let mut line = Line::default();
+        if !self.condition_elements.is_empty() {
+            line.push(&render_inline(&self.condition_elements, style)?);
+            line.push(", ");
+        }
+        match self.test {
+            Some(ref test) => test.write(&mut line, style),
+            None => line.push(jump_condition(jump.code, self.kind == LoopKind::DoWhile)),
+        }
+        Ok(line)
+    }
+
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
+        let condition = self.condition(style)?;
+        let body = render_block(&self.body, ident + 1, style)?;
+
+        let mut source = match self.comment {
+            Some(ref comment) => style.line(ident, &Line::from(format!("// {}", comment))),
+            None => String::new(),
+        };
+        match self.kind {
+            LoopKind::While => {
+                let mut header = Line::from("while (");
+                header.append(condition);
+                header.push(")");
+                source.push_str(&style.block_start(ident, header));
+                source.push_str(&body);
+                source.push_str(&style.block_end(ident));
+            }
+            LoopKind::DoWhile => {
+                let mut end = Line::from("} while (");
+                end.append(condition);
+                end.push(");");
+                source.push_str(&style.block_start(ident, Line::from("do")));
+                source.push_str(&body);
+                source.push_str(&style.line(ident, &end));
+            }
+            LoopKind::For => {
+                let increment = render_inline(&self.increment_elements, style)?;
+                let mut header = Line::from("for (");
+                if condition.is_empty() && increment.is_empty() {
+                    header.push(";;");
+                } else {
+                    header.push("; ");
+                    header.append(condition);
+                    header.push("; ");
+                    header.push(&increment);
+                }
+                header.push(")");
+                source.push_str(&style.block_start(ident, header));
+                source.push_str(&body);
+                source.push_str(&style.block_end(ident));
+            }
+        }
+
+        Ok(source)
+    }
+}
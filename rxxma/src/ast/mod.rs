@@ -1,11 +1,31 @@
+pub mod cfg;
+mod condition;
 mod decompiler;
+mod evaluator;
+mod expression;
+pub mod fold;
 mod function;
 mod function_call;
+mod loop_statement;
+pub mod passes;
 mod plugin;
-mod tree_element;
+pub mod printer;
+mod stmt;
+mod switch_statement;
+mod symbol_map;
+pub mod visit;
 
+pub use self::condition::{ChainedJump, If};
 pub use self::decompiler::Decompiler;
+pub use self::expression::{Assignment, Declaration, Expr, Identifier, Register, Return};
+pub use self::fold::Fold;
 pub use self::function::*;
-pub use self::plugin::Plugin;
-pub use self::tree_element::TreeElement;
-pub use self::tree_element::TreeElementType;
+pub use self::function_call::FunctionCall;
+pub use self::loop_statement::{Loop, LoopKind};
+pub use self::passes::{Pass, Pipeline};
+pub use self::plugin::{FunctionRef, Plugin};
+pub use self::printer::{Braces, Style};
+pub use self::stmt::Stmt;
+pub use self::switch_statement::{Case, Switch};
+pub use self::symbol_map::SymbolMap;
+pub use self::visit::Visitor;
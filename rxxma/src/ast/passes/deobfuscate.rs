@@ -0,0 +1,359 @@
+// Undoing what AMXX obfuscators do to paid and leaked plugins: flooding
+// code with NOP and BREAK, opaque predicates guarding junk and strings
+// stored XOR encoded in DAT and decoded by loops at runtime. Opcode passes
+// run before control flow is recovered, loops are commented after it.
+
+use std::collections::BTreeSet;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::super::Function;
+use super::super::Stmt;
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, Plugin};
+use crate::analysis::{decided_branches, is_conditional_jump, Cfg};
+
+// Shorter decoded strings are most likely coincidences
+const MIN_DECODED_LENGTH: usize = 2;
+
+// Opcodes whose parameter is cod address inside function
+fn has_target(opcode: &Opcode) -> bool {
+    opcode.code == OP_JUMP
+        || is_conditional_jump(opcode.code)
+        || opcode.code == OP_CASENONE
+        || opcode.code == OP_CASEJMP
+}
+
+fn targets(opcodes: &[Opcode]) -> BTreeSet<usize> {
+    opcodes
+        .iter()
+        .filter(|o| has_target(o))
+        .filter_map(|o| o.param.map(|p| p as usize))
+        .collect()
+}
+
+// Opcodes left after removing marked ones, jumps to removed opcodes go to
+// the next kept one
+fn remove(opcodes: Vec<Opcode>, removed: &[bool]) -> Vec<Opcode> {
+    let next_kept = |address: usize| -> usize {
+        opcodes
+            .iter()
+            .zip(removed)
+            .find(|(o, &r)| !r && o.address >= address)
+            .map_or(address, |(o, _)| o.address)
+    };
+    let retargeted: Vec<Opcode> = opcodes
+        .iter()
+        .map(|&opcode| match opcode.param {
+            Some(target) if has_target(&opcode) => Opcode {
+                param: Some(next_kept(target as usize) as u32),
+                ..opcode
+            },
+            _ => opcode,
+        })
+        .collect();
+
+    retargeted
+        .into_iter()
+        .zip(removed)
+        .filter(|(_, &r)| !r)
+        .map(|(o, _)| o)
+        .collect()
+}
+
+// Opcodes without any effect: NOP and BREAK flooding, jumps to the next
+// opcode and pairs undoing each other
+pub fn remove_junk(opcodes: Vec<Opcode>) -> Vec<Opcode> {
+    let noops: Vec<bool> = opcodes
+        .iter()
+        .map(|o| o.code == OP_NOP || o.code == OP_BREAK)
+        .collect();
+    let opcodes = remove(opcodes, &noops);
+
+    let targets = targets(&opcodes);
+    let mut removed = vec![false; opcodes.len()];
+    let mut i = 0;
+    while i < opcodes.len() {
+        let (opcode, next) = (&opcodes[i], opcodes.get(i + 1));
+        if opcode.code == OP_JUMP && next.map(|n| n.address as u32) == opcode.param {
+            removed[i] = true;
+        }
+        if let Some(next) = next.filter(|n| !targets.contains(&n.address)) {
+            let undone = matches!(
+                (opcode.code, next.code),
+                (OP_PUSH_PRI, OP_POP_PRI) | (OP_PUSH_ALT, OP_POP_ALT) | (OP_XCHG, OP_XCHG)
+            );
+            if undone {
+                removed[i] = true;
+                removed[i + 1] = true;
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+    remove(opcodes, &removed)
+}
+
+// Function opcodes with its PROC, which function elements do not keep
+pub(crate) fn function_opcodes(function: &Function) -> Vec<Opcode> {
+    let proc = Opcode {
+        code: OP_PROC,
+        address: function.address,
+        param: None,
+    };
+    Some(proc)
+        .into_iter()
+        .chain(function.statements.iter().filter_map(|e| match *e {
+            Stmt::Emit(o) => Some(o),
+            _ => None,
+        }))
+        .collect()
+}
+
+// Conditional jumps with constant outcome become jumps or disappear, code
+// no longer reachable is dropped. `opcodes` start with PROC.
+pub fn fold_opaque_predicates(plugin: &Plugin, opcodes: Vec<Opcode>) -> Vec<Opcode> {
+    let decided = decided_branches(plugin, &opcodes);
+    if decided.is_empty() {
+        return opcodes;
+    }
+
+    let mut removed = vec![false; opcodes.len()];
+    let mut opcodes = opcodes;
+    for (n, opcode) in opcodes.iter_mut().enumerate() {
+        match decided
+            .iter()
+            .find(|&&(address, _)| address == opcode.address)
+        {
+            Some(&(_, true)) => opcode.code = OP_JUMP,
+            Some(&(_, false)) => removed[n] = true,
+            None => {}
+        }
+    }
+    let opcodes = remove(opcodes, &removed);
+
+    // Case tables are data, they are never reached by execution
+    let cfg = Cfg::from_opcodes(&opcodes);
+    let mut reachable = vec![false; cfg.blocks.len()];
+    let mut stack = vec![0];
+    while let Some(block) = stack.pop() {
+        if !reachable[block] {
+            reachable[block] = true;
+            stack.extend(cfg.blocks[block].successors.iter().cloned());
+        }
+    }
+    let mut removed = vec![false; opcodes.len()];
+    for (block, &reached) in cfg.blocks.iter().zip(reachable.iter()) {
+        let first = &opcodes[block.opcodes.start];
+        if !reached && first.code != OP_CASETBL && !first.code.is_pseudo() {
+            for n in block.opcodes.clone() {
+                removed[n] = true;
+            }
+        }
+    }
+    remove(opcodes, &removed)
+}
+
+// Opcodes of loop before evaluation, nested statements included
+fn loop_opcodes(elements: &[Stmt], opcodes: &mut Vec<Opcode>) {
+    for element in elements.iter() {
+        match *element {
+            Stmt::Emit(o) => opcodes.push(o),
+            Stmt::If(ref c) => {
+                for chained in c.chain.iter() {
+                    opcodes.push(chained.jump);
+                    loop_opcodes(&chained.elements, opcodes);
+                }
+                opcodes.push(c.jump);
+                loop_opcodes(&c.then_elements, opcodes);
+                if let Some(ref e) = c.else_elements {
+                    loop_opcodes(e, opcodes);
+                }
+            }
+            Stmt::Loop(ref l) => {
+                loop_opcodes(&l.condition_elements, opcodes);
+                opcodes.extend(l.jump);
+                loop_opcodes(&l.increment_elements, opcodes);
+                loop_opcodes(&l.body, opcodes);
+            }
+            _ => {}
+        }
+    }
+}
+
+// String loop computes by XOR of DAT array indexed by loop with constant
+// key, e.g. `for (i = 0; enc[i]; i++) buffer[i] = enc[i] ^ 0x1E`
+fn decoded_string(plugin: &Plugin, opcodes: &[Opcode]) -> Option<String> {
+    let xor = opcodes.iter().position(|o| o.code == OP_XOR)?;
+    let key = opcodes[..xor]
+        .iter()
+        .rev()
+        .take(3)
+        .find(|o| o.code == OP_CONST_PRI || o.code == OP_CONST_ALT)?
+        .param?;
+    let array = opcodes
+        .windows(2)
+        .find(|w| w[0].code == OP_CONST_ALT && w[1].code == OP_LIDX)?[0]
+        .param? as usize;
+
+    let dat = plugin.dat_slice().ok()?;
+    let mut decoded = vec![];
+    for cell in dat
+        .get(array..)?
+        .chunks_exact(4)
+        .map(LittleEndian::read_u32)
+    {
+        match cell ^ key {
+            _ if cell == 0 => break,
+            0 => break,
+            c @ 0x20..=0xFF | c @ 0x09..=0x0D => decoded.push(c as u8),
+            _ => return None,
+        }
+    }
+    if decoded.len() < MIN_DECODED_LENGTH {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+// Comments loops decoding strings with what they decode
+pub fn comment_decoding_loops(plugin: &Plugin, elements: &mut [Stmt]) {
+    for element in elements.iter_mut() {
+        if let Stmt::Loop(ref mut l) = *element {
+            let mut opcodes = vec![];
+            loop_opcodes(&l.condition_elements, &mut opcodes);
+            loop_opcodes(&l.increment_elements, &mut opcodes);
+            loop_opcodes(&l.body, &mut opcodes);
+            if let Some(text) = decoded_string(plugin, &opcodes) {
+                l.comment = Some(format!("decodes {:?}", text));
+                continue;
+            }
+        }
+        for children in element.children_mut() {
+            comment_decoding_loops(plugin, children);
+        }
+    }
+}
+
+// Opcode passes over function not yet structured
+pub fn deobfuscate(plugin: &Plugin, function: &mut Function) {
+    let opcodes = remove_junk(function_opcodes(function));
+    let opcodes = fold_opaque_predicates(plugin, opcodes);
+    function.statements = opcodes[1..].iter().map(|&o| Stmt::Emit(o)).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{fold_opaque_predicates, remove_junk};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::{Opcode, OpcodeType, Plugin};
+    use crate::ast::{Decompiler, Style};
+    use crate::util::tests::PluginBuilder;
+
+    fn op(code: OpcodeType, address: usize, param: Option<u32>) -> Opcode {
+        Opcode {
+            code,
+            address,
+            param,
+        }
+    }
+
+    #[test]
+    fn it_remove_junk_opcodes() {
+        let opcodes = vec![
+            op(OP_PROC, 0x8, None),
+            op(OP_JZER, 0xC, Some(0x1C)),
+            op(OP_BREAK, 0x14, None),
+            op(OP_JUMP, 0x18, Some(0x20)),
+            op(OP_NOP, 0x1C, None),
+            op(OP_PUSH_PRI, 0x20, None),
+            op(OP_POP_PRI, 0x24, None),
+            op(OP_RETN, 0x28, None),
+        ];
+
+        assert_eq!(
+            remove_junk(opcodes),
+            [
+                op(OP_PROC, 0x8, None),
+                op(OP_JZER, 0xC, Some(0x28)),
+                op(OP_RETN, 0x28, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_fold_opaque_predicates() {
+        let plugin = Plugin::try_from(PluginBuilder::new().build()).unwrap();
+        // if (7 * 7 != 49) { junk }
+        let opcodes = vec![
+            op(OP_PROC, 0x8, None),
+            op(OP_CONST_PRI, 0xC, Some(7)),
+            op(OP_SMUL_C, 0x14, Some(7)),
+            op(OP_EQ_C_PRI, 0x1C, Some(49)),
+            op(OP_JNZ, 0x24, Some(0x34)),
+            op(OP_ZERO_PRI, 0x2C, None),
+            op(OP_HALT, 0x30, Some(1)),
+            op(OP_RETN, 0x34, None),
+        ];
+
+        assert_eq!(
+            fold_opaque_predicates(&plugin, opcodes),
+            [
+                op(OP_PROC, 0x8, None),
+                op(OP_CONST_PRI, 0xC, Some(7)),
+                op(OP_SMUL_C, 0x14, Some(7)),
+                op(OP_EQ_C_PRI, 0x1C, Some(49)),
+                op(OP_JUMP, 0x24, Some(0x34)),
+                op(OP_RETN, 0x34, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_comment_string_decoding_loop() {
+        let key = 0x1E;
+        let mut builder = PluginBuilder::new();
+        let encoded: Vec<u32> = b"amx_cvar".iter().map(|&c| u32::from(c) ^ key).collect();
+        let array = builder.array(&[encoded, vec![0]].concat());
+        let buffer = builder.array(&[0; 9]);
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, 0);
+        let top = builder.here();
+        builder
+            .op_param(OP_LOAD_S_PRI, (-4i32) as u32)
+            .op_param(OP_CONST_ALT, array)
+            .op(OP_LIDX)
+            .op_param(OP_JZER, 0);
+        let exit = builder.here() - 4;
+        builder
+            .op_param(OP_LOAD_S_PRI, (-4i32) as u32)
+            .op_param(OP_CONST_ALT, array)
+            .op(OP_LIDX)
+            .op_param(OP_CONST_ALT, key)
+            .op(OP_XOR)
+            .op(OP_PUSH_PRI)
+            .op_param(OP_LOAD_S_PRI, (-4i32) as u32)
+            .op_param(OP_CONST_ALT, buffer)
+            .op(OP_IDXADDR)
+            .op(OP_MOVE_ALT)
+            .op(OP_POP_PRI)
+            .op(OP_STOR_I)
+            .op_param(OP_INC_S, (-4i32) as u32)
+            .op_param(OP_JUMP, top);
+        let end = builder.here();
+        builder.op_param(OP_STACK, 4).op(OP_ZERO_PRI).op(OP_RETN);
+        builder.patch(exit, end);
+
+        let mut decompiler = Decompiler::from(Plugin::try_from(builder.build()).unwrap()).unwrap();
+        decompiler.deobfuscate = true;
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+        let source = decompiler.into_tree().render(0, &Style::default()).unwrap();
+
+        assert!(source.contains("    // decodes \"amx_cvar\"\n    while ("));
+    }
+}
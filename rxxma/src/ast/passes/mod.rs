@@ -0,0 +1,204 @@
+// Transformations of function opcodes and statements between decompiler
+// stages. Every stage is a named pass of `Pipeline`, custom passes are
+// inserted relative to built in ones, e.g. after "control-flow" loops and
+// conditions exist but expressions are not recovered yet.
+
+pub mod deobfuscate;
+
+use std::fmt;
+
+use log::trace;
+
+use super::Decompiler;
+use crate::error::AmxError;
+
+pub trait Pass {
+    // Unique within pipeline, used to insert other passes around it
+    fn name(&self) -> &str;
+    fn run(&self, decompiler: &mut Decompiler) -> Result<(), AmxError>;
+}
+
+// Built in pass running decompiler method
+struct Stage {
+    name: &'static str,
+    run: fn(&mut Decompiler) -> Result<(), AmxError>,
+}
+
+impl Pass for Stage {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn run(&self, decompiler: &mut Decompiler) -> Result<(), AmxError> {
+        (self.run)(decompiler)
+    }
+}
+
+// Custom pass out of closure
+struct FnPass<F> {
+    name: String,
+    run: F,
+}
+
+impl<F> Pass for FnPass<F>
+where
+    F: Fn(&mut Decompiler) -> Result<(), AmxError>,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, decompiler: &mut Decompiler) -> Result<(), AmxError> {
+        (self.run)(decompiler)
+    }
+}
+
+pub fn pass<F>(name: &str, run: F) -> Box<dyn Pass>
+where
+    F: Fn(&mut Decompiler) -> Result<(), AmxError> + 'static,
+{
+    Box::new(FnPass {
+        name: name.to_owned(),
+        run,
+    })
+}
+
+fn stage(name: &'static str, run: fn(&mut Decompiler) -> Result<(), AmxError>) -> Box<dyn Pass> {
+    Box::new(Stage { name, run })
+}
+
+pub struct Pipeline {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl Pipeline {
+    // Pipeline without passes, see `default` for the one decompiler uses
+    pub fn new() -> Pipeline {
+        Pipeline { passes: vec![] }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.passes.iter().map(|p| p.name()).collect()
+    }
+
+    pub fn push(&mut self, pass: Box<dyn Pass>) -> &mut Self {
+        self.passes.push(pass);
+        self
+    }
+
+    pub fn insert_before(
+        &mut self,
+        name: &str,
+        pass: Box<dyn Pass>,
+    ) -> Result<&mut Self, AmxError> {
+        let position = self.position(name)?;
+        self.passes.insert(position, pass);
+        Ok(self)
+    }
+
+    pub fn insert_after(&mut self, name: &str, pass: Box<dyn Pass>) -> Result<&mut Self, AmxError> {
+        let position = self.position(name)?;
+        self.passes.insert(position + 1, pass);
+        Ok(self)
+    }
+
+    pub fn replace(&mut self, name: &str, pass: Box<dyn Pass>) -> Result<&mut Self, AmxError> {
+        let position = self.position(name)?;
+        self.passes[position] = pass;
+        Ok(self)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<&mut Self, AmxError> {
+        let position = self.position(name)?;
+        self.passes.remove(position);
+        Ok(self)
+    }
+
+    pub fn run(&self, decompiler: &mut Decompiler) -> Result<(), AmxError> {
+        for pass in self.passes.iter() {
+            trace!("Run pass {}", pass.name());
+            pass.run(decompiler)?;
+        }
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Result<usize, AmxError> {
+        self.passes
+            .iter()
+            .position(|p| p.name() == name)
+            .ok_or_else(|| AmxError::NoPass(name.to_owned()))
+    }
+}
+
+impl Default for Pipeline {
+    // Stages of decompilation, deobfuscation ones run only when enabled
+    fn default() -> Pipeline {
+        Pipeline {
+            passes: vec![
+                stage("clean-break", |d| d.clean_functions_break()),
+                stage("deobfuscate", |d| d.deobfuscate_opcodes()),
+                stage("resolve-constants", |d| d.resolve_constants()),
+                stage("control-flow", |d| d.decompile_control_flow()),
+                stage("decoding-loops", |d| d.comment_decoding_loops()),
+                stage("expressions", |d| d.decompile_expressions()),
+                stage("debug-symbols", |d| d.name_debug_symbols()),
+                stage("clean-return", |d| d.clean_functions_return()),
+                stage("public-variables", |d| d.declare_public_variables()),
+                stage("modules", |d| d.list_required_modules()),
+                stage("includes", |d| d.list_includes()),
+                stage("known-stocks", |d| d.collapse_known_stocks()),
+                stage("rename", |d| d.rename_symbols()),
+            ],
+        }
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{pass, Pipeline};
+    use crate::amx::Plugin;
+    use crate::ast::{Decompiler, Style};
+    use crate::error::AmxError;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_insert_custom_pass() {
+        let mut pipeline = Pipeline::default();
+        pipeline
+            .insert_after(
+                "includes",
+                pass("fakemeta", |decompiler| {
+                    decompiler.ast_plugin.includes.push("fakemeta".to_owned());
+                    Ok(())
+                }),
+            )
+            .unwrap()
+            .remove("modules")
+            .unwrap();
+        assert_eq!(
+            &pipeline.names()[9..12],
+            ["includes", "fakemeta", "known-stocks"]
+        );
+        assert_eq!(
+            pipeline.remove("unknown").unwrap_err(),
+            AmxError::NoPass("unknown".to_owned())
+        );
+
+        let plugin = Plugin::try_from(PluginBuilder::new().build()).unwrap();
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.pipeline = pipeline;
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+        let source = decompiler.into_tree().render(0, &Style::default()).unwrap();
+
+        assert!(source.contains("#include <amxmodx>\n#include <fakemeta>\n"));
+    }
+}
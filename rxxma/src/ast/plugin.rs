@@ -1,34 +1,176 @@
+use std::fmt;
+
 use super::super::amx::Opcode;
-use super::TreeElement;
-use super::TreeElementType;
-use super::TreeElementType::*;
+use super::expression::Declaration;
+use super::function::Function;
+use super::printer::Style;
+use super::symbol_map::{rename_variable, rename_variables, SymbolMap};
+use super::Stmt;
+use crate::error::AmxError;
+
+// Function picked by name or PROC address. Strings holding decimal or 0x
+// prefixed hex number are addresses, Pawn names never start with digit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FunctionRef<'a> {
+    Name(&'a str),
+    Address(usize),
+}
+
+impl<'a> From<&'a str> for FunctionRef<'a> {
+    fn from(function: &'a str) -> FunctionRef<'a> {
+        let address = match function.strip_prefix("0x") {
+            Some(hex) => usize::from_str_radix(hex, 16).ok(),
+            None => function.parse().ok(),
+        };
+        match address {
+            Some(address) => FunctionRef::Address(address),
+            None => FunctionRef::Name(function),
+        }
+    }
+}
+
+impl fmt::Display for FunctionRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FunctionRef::Name(name) => write!(f, "{}", name),
+            FunctionRef::Address(address) => write!(f, "0x{:X}", address),
+        }
+    }
+}
+
+impl From<usize> for FunctionRef<'_> {
+    fn from(address: usize) -> Self {
+        FunctionRef::Address(address)
+    }
+}
 
 pub struct Plugin {
-    pub tree_elements: Vec<TreeElementType>,
+    // Include files for #include lines, empty for no preamble
+    pub includes: Vec<String>,
+    // Modules plugin requires
+    pub libraries: Vec<String>,
+    // Global variables declared in front of functions
+    pub globals: Vec<Declaration>,
+    pub statements: Vec<Stmt>,
 }
 
 impl Plugin {
-    pub fn from(opcodes: Vec<Opcode>) -> Result<Plugin, &'static str> {
-        let mut tree_elements: Vec<TreeElementType> = vec![];
+    pub fn from(opcodes: Vec<Opcode>) -> Result<Plugin, AmxError> {
+        let mut statements: Vec<Stmt> = vec![];
 
         for opcode in opcodes.into_iter() {
-            tree_elements.push(OpcodeType(opcode));
+            statements.push(Stmt::Emit(opcode));
         }
 
-        Ok(Plugin { tree_elements })
+        Ok(Plugin {
+            includes: vec![],
+            libraries: vec![],
+            globals: vec![],
+            statements,
+        })
     }
-}
 
-// TODO: Plugin is not a tree element
-impl TreeElement for Plugin {
-    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
+    pub fn function<'a, F: Into<FunctionRef<'a>>>(&self, function: F) -> Option<&Function> {
+        let function = function.into();
+        self.statements
+            .iter()
+            .find_map(|element| match (element, function) {
+                (Stmt::Function(f), FunctionRef::Name(name)) if f.name == name => Some(f),
+                (Stmt::Function(f), FunctionRef::Address(address)) if f.address == address => {
+                    Some(f)
+                }
+                _ => None,
+            })
+    }
+
+    // Source of single function, rest of plugin is not rendered
+    pub fn decompile_function<'a, F: Into<FunctionRef<'a>>>(
+        &self,
+        function: F,
+    ) -> Result<String, AmxError> {
+        self.render_function(function, &Style::default())
+    }
+
+    // Like `decompile_function`, laid out in `style`
+    pub fn render_function<'a, F: Into<FunctionRef<'a>>>(
+        &self,
+        function: F,
+        style: &Style,
+    ) -> Result<String, AmxError> {
+        let function = function.into();
+        self.function(function)
+            .ok_or_else(|| AmxError::NoFunction(function.to_string()))?
+            .render(0, style)
+    }
+
+    // Gives user chosen names to already decompiled functions and
+    // variables, names missing from map are kept
+    pub fn rename(&mut self, symbols: &SymbolMap) {
+        let globals = symbols.global_names();
+        for global in self.globals.iter_mut() {
+            rename_variable(&mut global.variable, &globals);
+        }
+
+        for element in self.statements.iter_mut() {
+            let function = match *element {
+                Stmt::Function(ref mut f) => f,
+                _ => continue,
+            };
+            if let Some(name) = symbols.function_name(function.address) {
+                function.name = name.clone();
+            }
+
+            let names = symbols.names(function.address);
+            for parameter in function.parameters.iter_mut() {
+                rename_variable(&mut parameter.variable, &names);
+            }
+            rename_variables(&mut function.statements, &names);
+        }
+    }
+
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
         let mut source = String::from("// Plugin source approximation starts here\n\n");
 
-        for tree_element in self.tree_elements.iter() {
-            let element_str = &tree_element.to_string(ident + 1)?;
-            source.push_str(&element_str);
+        for include in self.includes.iter() {
+            source.push_str(&format!("#include <{}>\n", include));
+        }
+        if !self.includes.is_empty() {
+            source.push('\n');
+        }
+
+        if !self.libraries.is_empty() {
+            source.push_str(&format!(
+                "// Required modules: {}\n\n",
+                self.libraries.join(", ")
+            ));
+        }
+
+        for global in self.globals.iter() {
+            source.push_str(&global.render(ident, style)?);
+        }
+        if !self.globals.is_empty() {
+            source.push('\n');
+        }
+
+        for stmt in self.statements.iter() {
+            source.push_str(&stmt.render(ident, style)?);
         }
 
         Ok(source)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::FunctionRef;
+
+    #[test]
+    fn it_parse_function_ref() {
+        assert_eq!(FunctionRef::from("0x1C"), FunctionRef::Address(0x1C));
+        assert_eq!(FunctionRef::from("28"), FunctionRef::Address(28));
+        assert_eq!(
+            FunctionRef::from("plugin_init"),
+            FunctionRef::Name("plugin_init")
+        );
+    }
+}
@@ -0,0 +1,324 @@
+// Layout of rendered source. Statements render themselves through Style:
+// indentation, braces, long lines and constants.
+
+use std::fmt;
+
+use super::plugin::Plugin;
+use crate::error::AmxError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Braces {
+    // Opening brace ends the line of its statement
+    KAndR,
+    // Opening brace on its own line, else on its own line
+    Allman,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    // Spaces per nesting level, function bodies are one level deep
+    pub indent_width: usize,
+    pub braces: Braces,
+    // Longer lines are wrapped after commas where possible
+    pub max_line_length: Option<usize>,
+    // Constants above 9 written as hex
+    pub hex_constants: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            indent_width: 4,
+            braces: Braces::KAndR,
+            max_line_length: None,
+            hex_constants: false,
+        }
+    }
+}
+
+// Statement text with points where it may be wrapped, after commas
+// separating arguments and array cells
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Line {
+    parts: Vec<String>,
+}
+
+impl Line {
+    pub fn push(&mut self, text: &str) {
+        match self.parts.last_mut() {
+            Some(last) => last.push_str(text),
+            None => self.parts.push(text.to_owned()),
+        }
+    }
+
+    // Appends other line, its wrap points included
+    pub fn append(&mut self, other: Line) {
+        let mut parts = other.parts.into_iter();
+        if let Some(first) = parts.next() {
+            self.push(&first);
+        }
+        self.parts.extend(parts);
+    }
+
+    // Line may be broken here
+    pub fn wrap_point(&mut self) {
+        self.parts.push(String::new());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.iter().all(|p| p.is_empty())
+    }
+}
+
+impl From<&str> for Line {
+    fn from(text: &str) -> Line {
+        Line {
+            parts: vec![text.to_owned()],
+        }
+    }
+}
+
+impl From<String> for Line {
+    fn from(text: String) -> Line {
+        Line { parts: vec![text] }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for part in self.parts.iter() {
+            write!(f, "{}", part)?;
+        }
+        Ok(())
+    }
+}
+
+impl Style {
+    // Renders whole plugin in this style
+    pub fn print(&self, plugin: &Plugin) -> Result<String, AmxError> {
+        plugin.render(0, self)
+    }
+
+    // Leading spaces of nesting level, functions are at level 0
+    pub fn indent(&self, ident: usize) -> String {
+        " ".repeat(ident * self.indent_width)
+    }
+
+    pub fn constant(&self, value: i32) -> String {
+        match self.hex_constants && value.unsigned_abs() > 9 {
+            true if value < 0 => format!("-0x{:X}", value.unsigned_abs()),
+            true => format!("0x{:X}", value),
+            false => value.to_string(),
+        }
+    }
+
+    // Indented line, wrapped when longer than allowed
+    pub fn line(&self, ident: usize, line: &Line) -> String {
+        let indent = self.indent(ident);
+        let limit = match self.max_line_length {
+            Some(limit) => limit,
+            None => return format!("{}{}\n", indent, line),
+        };
+        let continuation = format!("{}{}", indent, " ".repeat(2 * self.indent_width));
+
+        let mut result = String::new();
+        let mut current = indent;
+        let mut empty = true;
+        for part in line.parts.iter() {
+            let width = current.chars().count() + part.trim_end().chars().count();
+            if !empty && width > limit {
+                result.push_str(current.trim_end());
+                result.push('\n');
+                current = continuation.clone();
+                current.push_str(part.trim_start());
+            } else {
+                current.push_str(part);
+            }
+            empty = false;
+        }
+        result.push_str(current.trim_end());
+        result.push('\n');
+        result
+    }
+
+    // Statement opening block, e.g. `if (...)`
+    pub fn block_start(&self, ident: usize, header: Line) -> String {
+        match self.braces {
+            Braces::KAndR => {
+                let mut header = header;
+                header.push(" {");
+                self.line(ident, &header)
+            }
+            Braces::Allman => self.line(ident, &header) + &self.line(ident, &Line::from("{")),
+        }
+    }
+
+    // Closes block and opens next one of the same statement, e.g. `else`
+    pub fn block_continue(&self, ident: usize, header: Line) -> String {
+        match self.braces {
+            Braces::KAndR => {
+                let mut line = Line::from("} ");
+                line.append(header);
+                line.push(" {");
+                self.line(ident, &line)
+            }
+            Braces::Allman => self.block_end(ident) + &self.block_start(ident, header),
+        }
+    }
+
+    pub fn block_end(&self, ident: usize) -> String {
+        self.line(ident, &Line::from("}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Braces, Style};
+    use crate::amx::Opcode;
+    use crate::amx::OpcodeType::*;
+    use crate::ast::{
+        Assignment, Expr, Function, FunctionCall, FunctionVisibility, Identifier, If, Stmt,
+    };
+
+    fn call(name: &str, args: Vec<Expr>) -> Expr {
+        Expr::Call(FunctionCall {
+            name: name.to_owned(),
+            args,
+        })
+    }
+
+    fn plugin_init() -> Function {
+        let arg_0 = || Expr::Variable(Identifier::Argument(0));
+        let kick = call(
+            "server_cmd",
+            vec![
+                Expr::String("kick #%d 1024 ☺".to_owned()),
+                call("get_user_userid", vec![arg_0()]),
+                Expr::Constant(4096),
+            ],
+        );
+        let branch = If {
+            jump: Opcode {
+                code: OP_JZER,
+                address: 0x20,
+                param: Some(0x80),
+            },
+            chain: vec![],
+            then_elements: vec![Stmt::Call(match kick {
+                Expr::Call(c) => c,
+                _ => unreachable!(),
+            })],
+            else_elements: Some(vec![Stmt::Assignment(Assignment {
+                target: Expr::Variable(Identifier::Local(1)),
+                value: Expr::Float(1.5f32.to_bits()),
+            })]),
+            test: Some(Expr::binary(
+                call("get_user_flags", vec![arg_0()]),
+                "&",
+                Expr::Constant(512),
+            )),
+        };
+
+        Function {
+            name: "plugin_init".to_owned(),
+            address: 0x8,
+            parameters: vec![],
+            statements: vec![Stmt::If(branch)],
+            visibility: FunctionVisibility::Public,
+            tag: None,
+            collapsed: false,
+        }
+    }
+
+    #[test]
+    fn it_keep_default_style() {
+        assert_eq!(
+            plugin_init().render(0, &Style::default()).unwrap(),
+            "public plugin_init () {\n    \
+             if (get_user_flags(arg_0) & 512) {\n        \
+             server_cmd(\"kick #%d 1024 ☺\", get_user_userid(arg_0), 4096);\n    \
+             } else {\n        \
+             local_1 = 1.5;\n    \
+             }\n\
+             }\n\n"
+        );
+    }
+
+    #[test]
+    fn it_indent_one_unit_per_level() {
+        let mut function = plugin_init();
+        let inner = function.statements.remove(0);
+        function.statements.push(Stmt::If(If {
+            jump: Opcode {
+                code: OP_JZER,
+                address: 0x10,
+                param: Some(0x90),
+            },
+            chain: vec![],
+            then_elements: vec![inner],
+            else_elements: None,
+            test: Some(Expr::Variable(Identifier::Argument(1))),
+        }));
+        let lines = |style: &Style| -> Vec<String> {
+            function
+                .render(0, style)
+                .unwrap()
+                .lines()
+                .map(|l| l.to_owned())
+                .collect()
+        };
+
+        let lines_4 = lines(&Style::default());
+        assert_eq!(lines_4[1], "    if (arg_1) {");
+        assert_eq!(lines_4[2], "        if (get_user_flags(arg_0) & 512) {");
+        assert!(lines_4[3].starts_with("            server_cmd("));
+        assert_eq!(lines_4[4], "        } else {");
+        assert_eq!(lines_4[7], "    }");
+
+        let style = Style {
+            indent_width: 3,
+            ..Style::default()
+        };
+        let lines_3 = lines(&style);
+        assert_eq!(lines_3[1], "   if (arg_1) {");
+        assert_eq!(lines_3[2], "      if (get_user_flags(arg_0) & 512) {");
+        assert!(lines_3[3].starts_with("         server_cmd("));
+    }
+
+    #[test]
+    fn it_format_allman_hex_wrapped() {
+        let style = Style {
+            indent_width: 2,
+            braces: Braces::Allman,
+            max_line_length: Some(52),
+            hex_constants: true,
+        };
+
+        assert_eq!(
+            plugin_init().render(0, &style).unwrap(),
+            "public plugin_init ()\n\
+             {\n  \
+             if (get_user_flags(arg_0) & 0x200)\n  \
+             {\n    \
+             server_cmd(\"kick #%d 1024 ☺\",\n        \
+             get_user_userid(arg_0), 0x1000);\n  \
+             }\n  \
+             else\n  \
+             {\n    \
+             local_1 = 1.5;\n  \
+             }\n\
+             }\n\n"
+        );
+    }
+
+    #[test]
+    fn it_write_negative_hex_constants() {
+        let style = Style {
+            hex_constants: true,
+            ..Style::default()
+        };
+        assert_eq!(style.constant(-4096), "-0x1000");
+        assert_eq!(style.constant(-5), "-5");
+        assert_eq!(style.constant(10), "0xA");
+    }
+}
@@ -0,0 +1,102 @@
+use super::super::amx::Opcode;
+use super::condition::If;
+use super::expression::{Assignment, Declaration, Identifier, Return};
+use super::function::Function;
+use super::function_call::FunctionCall;
+use super::loop_statement::Loop;
+use super::printer::{Line, Style};
+use super::switch_statement::Switch;
+use crate::error::AmxError;
+
+// Statement of decompiled tree. Functions are statements of plugin tree,
+// expressions of statements are typed `Expr` trees.
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    // Opcode left undecompiled, rendered as #emit
+    Emit(Opcode),
+    Function(Function),
+    Call(FunctionCall),
+    If(If),
+    Loop(Loop),
+    Switch(Switch),
+    Assignment(Assignment),
+    Declaration(Declaration),
+    Return(Return),
+}
+
+impl Stmt {
+    // Nested element lists, e.g. branches of if statement
+    pub fn children_mut(&mut self) -> Vec<&mut Vec<Stmt>> {
+        match *self {
+            Stmt::Function(ref mut f) => vec![&mut f.statements],
+            Stmt::If(ref mut c) => {
+                let mut children: Vec<_> = c.chain.iter_mut().map(|j| &mut j.elements).collect();
+                children.push(&mut c.then_elements);
+                children.extend(c.else_elements.as_mut());
+                children
+            }
+            Stmt::Loop(ref mut l) => vec![
+                &mut l.condition_elements,
+                &mut l.increment_elements,
+                &mut l.body,
+            ],
+            Stmt::Switch(ref mut s) => s.cases.iter_mut().map(|c| &mut c.body).collect(),
+            _ => vec![],
+        }
+    }
+
+    // Variables element itself refers to, children excluded
+    pub fn identifiers_mut(&mut self) -> Vec<&mut Identifier> {
+        let mut identifiers = vec![];
+        match *self {
+            Stmt::Call(ref mut c) => {
+                for arg in c.args.iter_mut() {
+                    identifiers.extend(arg.identifiers_mut());
+                }
+            }
+            Stmt::If(ref mut c) => {
+                identifiers.extend(c.test.iter_mut().flat_map(|t| t.identifiers_mut()));
+            }
+            Stmt::Loop(ref mut l) => {
+                identifiers.extend(l.test.iter_mut().flat_map(|t| t.identifiers_mut()));
+            }
+            Stmt::Switch(ref mut s) => {
+                identifiers.extend(s.value.iter_mut().flat_map(|v| v.identifiers_mut()));
+            }
+            Stmt::Assignment(ref mut a) => {
+                identifiers.extend(a.target.identifiers_mut());
+                identifiers.extend(a.value.identifiers_mut());
+            }
+            Stmt::Declaration(ref mut d) => {
+                identifiers.push(&mut d.variable);
+                identifiers.extend(d.value.iter_mut().flat_map(|v| v.identifiers_mut()));
+            }
+            Stmt::Return(ref mut r) => {
+                identifiers.extend(r.value.identifiers_mut());
+            }
+            Stmt::Emit(_) | Stmt::Function(_) => {}
+        }
+        identifiers
+    }
+
+    // Source nested `ident` levels deep, laid out in `style`
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
+        match *self {
+            Stmt::Emit(o) => {
+                let mut line = Line::from(format!("#emit {}", o.code));
+                if let Some(p) = o.param {
+                    line.push(&format!("\t0x{:X}", p));
+                }
+                Ok(style.line(ident, &line))
+            }
+            Stmt::Function(ref f) => f.render(ident, style),
+            Stmt::Call(ref c) => c.render(ident, style),
+            Stmt::If(ref c) => c.render(ident, style),
+            Stmt::Loop(ref l) => l.render(ident, style),
+            Stmt::Switch(ref s) => s.render(ident, style),
+            Stmt::Assignment(ref a) => a.render(ident, style),
+            Stmt::Declaration(ref d) => d.render(ident, style),
+            Stmt::Return(ref r) => r.render(ident, style),
+        }
+    }
+}
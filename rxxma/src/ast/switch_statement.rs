@@ -0,0 +1,56 @@
+use super::condition::render_block;
+use super::expression::Expr;
+use super::printer::{Line, Style};
+use super::Stmt;
+use crate::error::AmxError;
+
+#[derive(Debug, Clone)]
+pub struct Case {
+    // Empty for default case alone
+    pub values: Vec<u32>,
+    pub default: bool,
+    pub body: Vec<Stmt>,
+}
+
+// switch reconstructed from SWITCH and its case table, tests PRI
+#[derive(Debug, Clone)]
+pub struct Switch {
+    // In order of case bodies
+    pub cases: Vec<Case>,
+    // Tested value, PRI if not known
+    pub value: Option<Expr>,
+}
+
+impl Switch {
+    pub fn render(&self, ident: usize, style: &Style) -> Result<String, AmxError> {
+        let mut header = Line::from("switch (");
+        match self.value {
+            Some(ref value) => value.write(&mut header, style),
+            None => header.push("pri"),
+        }
+        header.push(")");
+        let mut source = style.block_start(ident, header);
+
+        for case in self.cases.iter() {
+            let mut labels: Vec<String> = vec![];
+            if !case.values.is_empty() {
+                let values: Vec<String> = case
+                    .values
+                    .iter()
+                    .map(|&v| style.constant(v as i32))
+                    .collect();
+                labels.push(format!("case {}:", values.join(", ")));
+            }
+            if case.default {
+                labels.push(String::from("default:"));
+            }
+
+            source.push_str(&style.block_start(ident + 1, Line::from(labels.join(" "))));
+            source.push_str(&render_block(&case.body, ident + 2, style)?);
+            source.push_str(&style.block_end(ident + 1));
+        }
+
+        source.push_str(&style.block_end(ident));
+        Ok(source)
+    }
+}
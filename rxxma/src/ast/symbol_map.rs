@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use super::expression::Identifier;
+use super::Stmt;
+
+// Names chosen by user, functions by cod address of their PROC, globals by
+// DAT address and frame slots by address of function they belong to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolMap {
+    functions: HashMap<usize, String>,
+    globals: HashMap<u32, String>,
+    locals: HashMap<(usize, Identifier), String>,
+}
+
+impl SymbolMap {
+    pub fn new() -> SymbolMap {
+        SymbolMap::default()
+    }
+
+    pub fn function(&mut self, address: usize, name: &str) -> &mut Self {
+        self.functions.insert(address, name.to_owned());
+        self
+    }
+
+    pub fn global(&mut self, address: u32, name: &str) -> &mut Self {
+        self.globals.insert(address, name.to_owned());
+        self
+    }
+
+    // Local or argument of function at `function` address
+    pub fn local(&mut self, function: usize, variable: Identifier, name: &str) -> &mut Self {
+        self.locals.insert((function, variable), name.to_owned());
+        self
+    }
+
+    pub fn function_name(&self, address: usize) -> Option<&String> {
+        self.functions.get(&address)
+    }
+
+    pub fn global_names(&self) -> Vec<(Identifier, String)> {
+        self.globals
+            .iter()
+            .map(|(&address, name)| (Identifier::Global(address), name.clone()))
+            .collect()
+    }
+
+    // Variable names visible in function at `function` address
+    pub fn names(&self, function: usize) -> Vec<(Identifier, String)> {
+        let locals = self
+            .locals
+            .iter()
+            .filter(|((f, _), _)| *f == function)
+            .map(|((_, variable), name)| (variable.clone(), name.clone()));
+        self.global_names().into_iter().chain(locals).collect()
+    }
+}
+
+// Names variable after `names` entries of its slot, slots reused by
+// differently named variables of sibling scopes stay as they are
+pub fn rename_variable(variable: &mut Identifier, names: &[(Identifier, String)]) {
+    let mut matching = names
+        .iter()
+        .filter(|(v, _)| v == variable.original())
+        .map(|(_, name)| name);
+    if let Some(name) = matching.next() {
+        if matching.all(|n| n == name) {
+            variable.rename(name.clone());
+        }
+    }
+}
+
+// Variables of elements and their children
+pub fn rename_variables(elements: &mut [Stmt], names: &[(Identifier, String)]) {
+    let mut pending: Vec<&mut Stmt> = elements.iter_mut().collect();
+    while let Some(element) = pending.pop() {
+        for variable in element.identifiers_mut() {
+            rename_variable(variable, names);
+        }
+        for children in element.children_mut() {
+            pending.extend(children.iter_mut());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SymbolMap;
+    use crate::ast::expression::Identifier;
+
+    #[test]
+    fn it_list_names_of_function() {
+        let mut symbols = SymbolMap::new();
+        symbols
+            .function(0x8, "on_player_spawn")
+            .global(0x10, "g_count")
+            .local(0x8, Identifier::Argument(0), "id")
+            .local(0x40, Identifier::Local(1), "other");
+
+        let mut names = symbols.names(0x8);
+        names.sort_by_key(|(_, name)| name.clone());
+
+        assert_eq!(
+            symbols.function_name(0x8).map(String::as_str),
+            Some("on_player_spawn")
+        );
+        assert_eq!(
+            names,
+            [
+                (Identifier::Global(0x10), String::from("g_count")),
+                (Identifier::Argument(0), String::from("id")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+// Read only walk over decompiled tree, for tools computing metrics or
+// linting decompiled plugins. Methods default to visiting children through
+// the free functions of the same name, overriding one and calling its free
+// function keeps walking deeper.
+
+use super::super::amx::Opcode;
+use super::expression::{Declaration, Expr, Identifier};
+use super::function::Function;
+use super::function_call::FunctionCall;
+use super::plugin::Plugin;
+use super::Stmt;
+
+pub trait Visitor {
+    fn visit_plugin(&mut self, plugin: &Plugin) {
+        visit_plugin(self, plugin)
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        visit_function(self, function)
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        visit_stmt(self, stmt)
+    }
+
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        visit_declaration(self, declaration)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        visit_expr(self, expr)
+    }
+
+    fn visit_call(&mut self, call: &FunctionCall) {
+        visit_call(self, call)
+    }
+
+    fn visit_identifier(&mut self, _identifier: &Identifier) {}
+
+    // Opcodes left undecompiled, jumps of statements are not visited
+    fn visit_opcode(&mut self, _opcode: &Opcode) {}
+}
+
+pub fn visit_plugin<V: Visitor + ?Sized>(visitor: &mut V, plugin: &Plugin) {
+    for global in plugin.globals.iter() {
+        visitor.visit_declaration(global);
+    }
+    visit_block(visitor, &plugin.statements);
+}
+
+pub fn visit_function<V: Visitor + ?Sized>(visitor: &mut V, function: &Function) {
+    for parameter in function.parameters.iter() {
+        visitor.visit_identifier(&parameter.variable);
+    }
+    visit_block(visitor, &function.statements);
+}
+
+pub fn visit_block<V: Visitor + ?Sized>(visitor: &mut V, statements: &[Stmt]) {
+    for stmt in statements.iter() {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn visit_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match *stmt {
+        Stmt::Emit(ref o) => visitor.visit_opcode(o),
+        Stmt::Function(ref f) => visitor.visit_function(f),
+        Stmt::Call(ref c) => visitor.visit_call(c),
+        Stmt::If(ref c) => {
+            for chained in c.chain.iter() {
+                visit_block(visitor, &chained.elements);
+            }
+            if let Some(ref test) = c.test {
+                visitor.visit_expr(test);
+            }
+            visit_block(visitor, &c.then_elements);
+            if let Some(ref e) = c.else_elements {
+                visit_block(visitor, e);
+            }
+        }
+        Stmt::Loop(ref l) => {
+            visit_block(visitor, &l.condition_elements);
+            if let Some(ref test) = l.test {
+                visitor.visit_expr(test);
+            }
+            visit_block(visitor, &l.increment_elements);
+            visit_block(visitor, &l.body);
+        }
+        Stmt::Switch(ref s) => {
+            if let Some(ref value) = s.value {
+                visitor.visit_expr(value);
+            }
+            for case in s.cases.iter() {
+                visit_block(visitor, &case.body);
+            }
+        }
+        Stmt::Assignment(ref a) => {
+            visitor.visit_expr(&a.target);
+            visitor.visit_expr(&a.value);
+        }
+        Stmt::Declaration(ref d) => visitor.visit_declaration(d),
+        Stmt::Return(ref r) => visitor.visit_expr(&r.value),
+    }
+}
+
+pub fn visit_declaration<V: Visitor + ?Sized>(visitor: &mut V, declaration: &Declaration) {
+    visitor.visit_identifier(&declaration.variable);
+    if let Some(ref value) = declaration.value {
+        visitor.visit_expr(value);
+    }
+}
+
+pub fn visit_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match *expr {
+        Expr::Variable(ref i) => visitor.visit_identifier(i),
+        Expr::Address(ref e)
+        | Expr::Deref(ref e)
+        | Expr::Unary(_, ref e)
+        | Expr::Resolved(ref e, _) => visitor.visit_expr(e),
+        Expr::Index(ref a, ref b) | Expr::Binary(ref a, _, ref b) => {
+            visitor.visit_expr(a);
+            visitor.visit_expr(b);
+        }
+        Expr::Call(ref c) => visitor.visit_call(c),
+        Expr::Array(ref cells) => {
+            for cell in cells.iter() {
+                visitor.visit_expr(cell);
+            }
+        }
+        Expr::Constant(_) | Expr::Float(_) | Expr::String(_) | Expr::Register(_) => {}
+    }
+}
+
+pub fn visit_call<V: Visitor + ?Sized>(visitor: &mut V, call: &FunctionCall) {
+    for arg in call.args.iter() {
+        visitor.visit_expr(arg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+
+    use super::{visit_call, visit_stmt, Visitor};
+    use crate::amx::Plugin;
+    use crate::ast::{Decompiler, FunctionCall, Stmt};
+    use crate::util::tests::load_fixture;
+
+    // Calls per function name, e.g. for metrics over plugin collection
+    #[derive(Default)]
+    struct CallCounter {
+        calls: BTreeMap<String, usize>,
+    }
+
+    impl Visitor for CallCounter {
+        fn visit_call(&mut self, call: &FunctionCall) {
+            *self.calls.entry(call.name.clone()).or_insert(0) += 1;
+            visit_call(self, call);
+        }
+    }
+
+    // Statements calling a function for its side effects, results ignored
+    #[derive(Default)]
+    struct CallStatements(usize);
+
+    impl Visitor for CallStatements {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            if let Stmt::Call(_) = stmt {
+                self.0 += 1;
+            }
+            visit_stmt(self, stmt);
+        }
+    }
+
+    #[test]
+    fn it_visit_calls() {
+        let plugin = Plugin::try_from(load_fixture("two_natives.amx183")).unwrap();
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+
+        let mut counter = CallCounter::default();
+        counter.visit_plugin(&decompiler.into_tree());
+
+        assert_eq!(counter.calls.get("native_one"), Some(&1));
+        assert_eq!(counter.calls.get("native_two"), Some(&1));
+    }
+
+    #[test]
+    fn it_visit_typed_statements() {
+        let plugin = Plugin::try_from(load_fixture("two_natives.amx183")).unwrap();
+        let mut decompiler = Decompiler::from(plugin).unwrap();
+        decompiler.opcodes_into_functions().unwrap();
+        decompiler.decompile_opcodes_by_templates().unwrap();
+
+        let mut statements = CallStatements::default();
+        statements.visit_plugin(&decompiler.into_tree());
+
+        assert_eq!(statements.0, 2);
+    }
+}
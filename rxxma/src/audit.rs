@@ -0,0 +1,345 @@
+// Whole server check the way admins do it: every plugin configs/plugins.ini
+// enables is loaded from plugins directory, summarized and scanned. Files
+// listed but absent and plugins present but not listed are reported too.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::amx::Plugin;
+use crate::analysis::{Command, Cvar, Metadata};
+use crate::error::AmxError;
+use crate::facade;
+use crate::fingerprint::PluginFingerprint;
+use crate::releases::{ReleaseDatabase, ReleaseMatch};
+use crate::scan::{self, Scan, Verdict};
+
+// Extensions of plugin files in plugins directory, case insensitive
+const EXTENSIONS: [&str; 2] = ["amxx", "amx"];
+const DEBUG_OPTION: &str = "debug";
+
+// Enabled plugin line of plugins.ini, `file [debug]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct IniEntry {
+    // 1 based
+    pub line: usize,
+    pub file: String,
+    pub debug: bool,
+}
+
+// Enabled plugins in file order. Lines commented out with `;` or `//`
+// disable plugin, text after `;` is a comment.
+pub fn parse_plugins_ini(text: &str) -> Vec<IniEntry> {
+    let mut entries = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with("//") {
+            continue;
+        }
+        let mut tokens = line.split(';').next().unwrap_or("").split_whitespace();
+        if let Some(file) = tokens.next() {
+            entries.push(IniEntry {
+                line: i + 1,
+                file: file.to_owned(),
+                debug: tokens.next() == Some(DEBUG_OPTION),
+            });
+        }
+    }
+    entries
+}
+
+#[derive(Debug)]
+pub struct PluginAudit {
+    pub metadata: Option<Metadata>,
+    pub natives: Vec<String>,
+    pub commands: Vec<Command>,
+    pub cvars: Vec<Cvar>,
+    pub scan: Scan,
+    pub fingerprint: PluginFingerprint,
+    // Set by `Audit::match_releases`
+    pub release: Option<ReleaseMatch>,
+}
+
+impl PluginAudit {
+    fn new(plugin: &Plugin) -> Result<PluginAudit, AmxError> {
+        Ok(PluginAudit {
+            metadata: plugin.metadata()?,
+            natives: plugin
+                .natives()?
+                .iter()
+                .map(|n| n.name.to_string_lossy().into_owned())
+                .collect(),
+            commands: plugin.commands()?,
+            cvars: plugin.cvars()?,
+            scan: scan::scan(plugin)?,
+            fingerprint: plugin.fingerprint()?,
+            release: None,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum AuditStatus {
+    // Listed but not in plugins directory, server fails to load it
+    Missing,
+    // Present but failed to parse or analyze
+    Broken(String),
+    Loaded(Box<PluginAudit>),
+}
+
+#[derive(Debug)]
+pub struct AuditEntry {
+    pub entry: IniEntry,
+    pub status: AuditStatus,
+}
+
+impl AuditEntry {
+    pub fn verdict(&self) -> Option<Verdict> {
+        match &self.status {
+            AuditStatus::Loaded(audit) => Some(audit.scan.verdict),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Audit {
+    // In plugins.ini order
+    pub entries: Vec<AuditEntry>,
+    // Plugin files of plugins directory plugins.ini does not enable, sorted
+    pub unlisted: Vec<String>,
+}
+
+impl Audit {
+    pub fn missing(&self) -> Vec<&AuditEntry> {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, AuditStatus::Missing))
+            .collect()
+    }
+
+    pub fn broken(&self) -> Vec<&AuditEntry> {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, AuditStatus::Broken(_)))
+            .collect()
+    }
+
+    // Loaded plugins with suspicious or malicious verdict
+    pub fn flagged(&self) -> Vec<&AuditEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.verdict().is_some_and(|v| v != Verdict::Clean))
+            .collect()
+    }
+
+    // Marks loaded plugins as matching known release or modified
+    pub fn match_releases(&mut self, database: &ReleaseDatabase) -> &mut Self {
+        for entry in self.entries.iter_mut() {
+            if let AuditStatus::Loaded(ref mut audit) = entry.status {
+                audit.release = Some(database.identify(&entry.entry.file, &audit.fingerprint));
+            }
+        }
+        self
+    }
+}
+
+fn names<T>(items: &[T], name: fn(&T) -> &Option<String>) -> String {
+    let names: Vec<&str> = items
+        .iter()
+        .map(|i| name(i).as_deref().unwrap_or("?"))
+        .collect();
+    names.join(", ")
+}
+
+impl fmt::Display for Audit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} plugins enabled, {} missing, {} broken, {} flagged",
+            self.entries.len(),
+            self.missing().len(),
+            self.broken().len(),
+            self.flagged().len()
+        )?;
+
+        for entry in self.entries.iter() {
+            write!(f, "\n{} (line {})", entry.entry.file, entry.entry.line)?;
+            if entry.entry.debug {
+                write!(f, " debug")?;
+            }
+            let audit = match &entry.status {
+                AuditStatus::Missing => {
+                    writeln!(f, ": missing")?;
+                    continue;
+                }
+                AuditStatus::Broken(error) => {
+                    writeln!(f, ": broken, {}", error)?;
+                    continue;
+                }
+                AuditStatus::Loaded(audit) => audit,
+            };
+
+            match &audit.metadata {
+                Some(metadata) => writeln!(
+                    f,
+                    ": {} {} by {}",
+                    metadata.name.as_deref().unwrap_or("?"),
+                    metadata.version.as_deref().unwrap_or("?"),
+                    metadata.author.as_deref().unwrap_or("?")
+                )?,
+                None => writeln!(f, ": not registered")?,
+            }
+            if let Some(ref release) = audit.release {
+                writeln!(f, "  Release: {}", release)?;
+            }
+            writeln!(f, "  Natives: {}", audit.natives.len())?;
+            if !audit.commands.is_empty() {
+                writeln!(f, "  Commands: {}", names(&audit.commands, |c| &c.name))?;
+            }
+            if !audit.cvars.is_empty() {
+                writeln!(f, "  Cvars: {}", names(&audit.cvars, |c| &c.name))?;
+            }
+            for line in audit.scan.to_string().lines() {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+
+        if !self.unlisted.is_empty() {
+            writeln!(f, "\nNot enabled: {}", self.unlisted.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn audit_file(path: &Path) -> AuditStatus {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return AuditStatus::Missing,
+    };
+    match facade::load_plugin(&bytes).and_then(|p| PluginAudit::new(&p)) {
+        Ok(audit) => AuditStatus::Loaded(Box::new(audit)),
+        Err(e) => AuditStatus::Broken(e.to_string()),
+    }
+}
+
+fn plugin_files(dir: &Path) -> Result<Vec<String>, AmxError> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_plugin = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| EXTENSIONS.iter().any(|x| x.eq_ignore_ascii_case(e)));
+        if let (true, Some(name)) = (is_plugin, path.file_name().and_then(|n| n.to_str())) {
+            files.push(name.to_owned());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Audits plugins `ini` enables against contents of `plugins_dir`.
+///
+/// ```no_run
+/// let audit = rxxma::audit::audit(
+///     "cstrike/addons/amxmodx/configs/plugins.ini",
+///     "cstrike/addons/amxmodx/plugins",
+/// )
+/// .unwrap();
+/// print!("{}", audit);
+/// ```
+pub fn audit<P, Q>(ini: P, plugins_dir: Q) -> Result<Audit, AmxError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let ini = fs::read_to_string(ini)?;
+    let dir = plugins_dir.as_ref();
+    let entries = parse_plugins_ini(&ini);
+
+    let unlisted = plugin_files(dir)?
+        .into_iter()
+        .filter(|name| entries.iter().all(|e| &e.file != name))
+        .collect();
+    let entries = entries
+        .into_iter()
+        .map(|entry| AuditEntry {
+            status: audit_file(&dir.join(&entry.file)),
+            entry,
+        })
+        .collect();
+
+    Ok(Audit { entries, unlisted })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{audit, parse_plugins_ini, AuditStatus, IniEntry};
+    use crate::releases::ReleaseDatabase;
+    use crate::util::tests::load_fixture;
+
+    #[test]
+    fn it_parse_plugins_ini() {
+        let ini = "; AMX Mod X plugins\n\
+                   admin.amxx\t\t; admin base\n\
+                   ;adminslots.amxx\n\
+                   // disabled.amxx\n\
+                   \n\
+                   statsx.amxx debug\n";
+
+        assert_eq!(
+            parse_plugins_ini(ini),
+            [
+                IniEntry {
+                    line: 2,
+                    file: "admin.amxx".to_owned(),
+                    debug: false,
+                },
+                IniEntry {
+                    line: 6,
+                    file: "statsx.amxx".to_owned(),
+                    debug: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_audit_server() {
+        let dir = std::env::temp_dir().join(format!("rxxma-audit-{}", std::process::id()));
+        let plugins = dir.join("plugins");
+        fs::create_dir_all(&plugins).unwrap();
+        fs::write(plugins.join("simple.amxx"), load_fixture("simple.amxx183")).unwrap();
+        fs::write(plugins.join("broken.amxx"), b"XXMA").unwrap();
+        fs::write(plugins.join("old.amxx"), load_fixture("simple.amxx183")).unwrap();
+        fs::write(
+            dir.join("plugins.ini"),
+            "simple.amxx\nbroken.amxx debug\nmissing.amxx\n",
+        )
+        .unwrap();
+
+        let mut audit = audit(dir.join("plugins.ini"), &plugins).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(audit.entries[0].status, AuditStatus::Loaded(_)));
+        assert_eq!(audit.broken().len(), 1);
+        assert_eq!(audit.missing()[0].entry.file, "missing.amxx");
+        assert_eq!(audit.unlisted, ["old.amxx"]);
+
+        let report = audit.to_string();
+        assert!(report.starts_with("3 plugins enabled, 1 missing, 1 broken, 0 flagged\n"));
+        assert!(report.contains("\nsimple.amxx (line 1): simple plugin 0.1 by Fedcomp\n"));
+        assert!(report.contains("\nmissing.amxx (line 3): missing\n"));
+        assert!(report.ends_with("\nNot enabled: old.amxx\n"));
+
+        let mut releases = ReleaseDatabase::new();
+        let simple = load_fixture("simple.amxx183");
+        let simple = crate::facade::load_plugin(&simple).unwrap();
+        releases.learn(&simple, "simple.amxx", "1.8.3").unwrap();
+        let report = audit.match_releases(&releases).to_string();
+        assert!(report.contains(" by Fedcomp\n  Release: matches simple.amxx of 1.8.3\n"));
+    }
+}
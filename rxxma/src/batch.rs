@@ -0,0 +1,150 @@
+// Whole plugins directory at once, files are parsed and decompiled in
+// parallel. Errors are kept per file, one broken plugin does not stop others.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::corpus::{Analysis, PluginSummary};
+use crate::error::AmxError;
+use crate::facade::{self, DecompileOptions};
+
+// Extensions of files picked from directory, case insensitive
+const EXTENSIONS: [&str; 2] = ["amxx", "amx"];
+
+#[derive(Debug)]
+pub struct FileResult {
+    pub path: PathBuf,
+    pub summary: Result<PluginSummary, String>,
+    // Decompiled source with default options
+    pub source: Result<String, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Batch {
+    // Sorted by path
+    pub files: Vec<FileResult>,
+}
+
+impl Batch {
+    // Cross-plugin indexes over successfully summarized files
+    pub fn analysis(&self) -> Analysis {
+        let mut analysis = Analysis::new();
+        for summary in self.files.iter().filter_map(|f| f.summary.as_ref().ok()) {
+            analysis.add(summary);
+        }
+        analysis
+    }
+
+    // Files plugin could not be read from, with reason
+    pub fn errors(&self) -> Vec<(&Path, &str)> {
+        self.files
+            .iter()
+            .filter_map(|f| match f.summary {
+                Err(ref e) => Some((f.path.as_path(), e.as_str())),
+                Ok(_) => None,
+            })
+            .collect()
+    }
+}
+
+fn is_plugin(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| EXTENSIONS.iter().any(|x| x.eq_ignore_ascii_case(e)))
+}
+
+fn analyze_file(path: PathBuf) -> FileResult {
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let error = e.to_string();
+            return FileResult {
+                path,
+                summary: Err(error.clone()),
+                source: Err(error),
+            };
+        }
+    };
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    FileResult {
+        summary: PluginSummary::from_bytes(&name, &bytes).map_err(|e| e.to_string()),
+        source: facade::decompile(&bytes, &DecompileOptions::default()).map_err(|e| e.to_string()),
+        path,
+    }
+}
+
+/// Summarizes and decompiles every .amxx and .amx file of directory,
+/// subdirectories are not entered.
+///
+/// ```no_run
+/// let batch = rxxma::batch::analyze_dir("cstrike/addons/amxmodx/plugins").unwrap();
+/// for (path, error) in batch.errors() {
+///     eprintln!("{}: {}", path.display(), error);
+/// }
+/// println!("{:?}", batch.analysis().duplicates());
+/// ```
+pub fn analyze_dir<P: AsRef<Path>>(path: P) -> Result<Batch, AmxError> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if is_plugin(&path) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    Ok(Batch {
+        files: paths.into_par_iter().map(analyze_file).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::analyze_dir;
+    use crate::util::tests::load_fixture;
+
+    #[test]
+    fn it_analyze_plugins_directory() {
+        let dir = std::env::temp_dir().join(format!("rxxma-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("simple.amxx"), load_fixture("simple.amxx183")).unwrap();
+        fs::write(
+            dir.join("two_natives.AMX"),
+            load_fixture("two_natives.amx183"),
+        )
+        .unwrap();
+        fs::write(dir.join("broken.amxx"), b"XXMA").unwrap();
+        fs::write(dir.join("readme.txt"), b"not a plugin").unwrap();
+
+        let batch = analyze_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<_> = batch
+            .files
+            .iter()
+            .map(|f| f.path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["broken.amxx", "simple.amxx", "two_natives.AMX"]);
+        assert_eq!(batch.errors().len(), 1);
+        assert!(batch.errors()[0].0.ends_with("broken.amxx"));
+        assert!(batch.files[1]
+            .source
+            .as_ref()
+            .unwrap()
+            .contains("register_plugin"));
+
+        let analysis = batch.analysis();
+        assert_eq!(analysis.plugins, ["simple.amxx", "two_natives.AMX"]);
+        assert_eq!(analysis.natives["native_one"], ["two_natives.AMX"]);
+    }
+}
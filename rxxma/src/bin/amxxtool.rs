@@ -0,0 +1,945 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use failure::Error;
+
+use rxxma::amxx::File;
+use rxxma::analysis::{self, CallGraph, CommandValue, XrefTarget};
+use rxxma::ast::{Braces, Style};
+use rxxma::emulator::{Argument, Emulator};
+use rxxma::facade::{self, DecompileOptions, Format};
+use rxxma::releases::ReleaseDatabase;
+use rxxma::rules::{match_rules, Rule};
+use rxxma::sigscan::{self, Signature};
+use rxxma::stocks::StockDatabase;
+use rxxma::verify::Verifier;
+use rxxma::{audit, batch, diff, hexdump, patch, report, scan};
+
+macro_rules! die {
+    ($fmt:expr) => ({
+        eprintln!($fmt);
+        std::process::exit(-1);
+    });
+    ($fmt:expr, $($arg:tt)*) => ({
+        eprintln!($fmt, $($arg)*);
+        std::process::exit(-1);
+    });
+}
+
+fn file_arg() -> Arg<'static, 'static> {
+    Arg::with_name("file")
+        .value_name("FILE")
+        .help("amxx or amx plugin")
+        .required(true)
+        .takes_value(true)
+}
+
+fn function_arg() -> Arg<'static, 'static> {
+    Arg::with_name("function")
+        .short("f")
+        .long("function")
+        .value_name("FUNCTION")
+        .help("Only function with this name or containing this address")
+        .takes_value(true)
+}
+
+fn output_arg() -> Arg<'static, 'static> {
+    Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .value_name("OUTPUT")
+        .help("Write result to OUTPUT")
+        .takes_value(true)
+}
+
+// Prints text result unless output path is given
+fn write_output(matches: &ArgMatches, contents: &[u8]) -> Result<(), Error> {
+    match matches.value_of("output") {
+        Some(path) => fs::write(path, contents)?,
+        None => print!("{}", String::from_utf8_lossy(contents)),
+    }
+    Ok(())
+}
+
+fn unpack(matches: &ArgMatches) -> Result<(), Error> {
+    let file_path = Path::new(matches.value_of("file").unwrap());
+    let bytes = fs::read(file_path)?;
+    let amxmod_plugin = facade::load_plugin(&bytes)?;
+    let output = match matches.value_of("output") {
+        Some(path) => PathBuf::from(path),
+        None => file_path.with_extension("amx"),
+    };
+    fs::write(output, amxmod_plugin.to_bytes())?;
+    Ok(())
+}
+
+fn disasm(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let opts = DecompileOptions::default();
+    let listing = match matches.value_of("function") {
+        Some(function) => facade::disassemble_function(&bytes, function, &opts)?,
+        None if matches.is_present("html") => {
+            let file = Path::new(matches.value_of("file").unwrap());
+            let title = file
+                .file_name()
+                .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+            facade::disassemble_html(&bytes, &title, &opts)?
+        }
+        None if matches.is_present("annotated") => facade::disassemble_annotated(&bytes, &opts)?,
+        None => facade::disassemble(&bytes, &opts)?,
+    };
+    write_output(matches, listing.as_bytes())
+}
+
+fn info(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    if matches.is_present("json") {
+        let json = serde_json::to_string_pretty(&facade::dump(&bytes)?)?;
+        return write_output(matches, format!("{}\n", json).as_bytes());
+    }
+    if matches.is_present("markdown") {
+        let file = Path::new(matches.value_of("file").unwrap());
+        let title = file
+            .file_name()
+            .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+        let text = facade::report_markdown(&bytes, &title)?;
+        return write_output(matches, text.as_bytes());
+    }
+
+    if facade::detect_format(&bytes)? == Format::Smx {
+        let info = facade::inspect(&bytes)?;
+        let text = format!(
+            "Format: Smx, {} bytes\nCode: {} bytes\nData: {} bytes\nPublics: {}\nNatives: {}\n",
+            bytes.len(),
+            info.cod_size,
+            info.dat_size,
+            info.publics.join(", "),
+            info.natives.join(", ")
+        );
+        return write_output(matches, text.as_bytes());
+    }
+
+    let text = report::report(&bytes)?.to_string();
+    write_output(matches, text.as_bytes())
+}
+
+fn strings(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let plugin = facade::load_plugin(&bytes)?;
+    let listing: String = plugin
+        .strings()?
+        .iter()
+        .map(|s| {
+            let kind = if s.packed { "packed" } else { "cell" };
+            let text = s.to_string_lossy().escape_debug().to_string();
+            format!("0x{:X}\t{}\t{}\n", s.address, kind, text)
+        })
+        .collect();
+    write_output(matches, listing.as_bytes())
+}
+
+// Quoted constant, ? for arguments not known statically
+fn quoted(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("\"{}\"", value.escape_debug()),
+        None => "?".to_owned(),
+    }
+}
+
+fn console(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let plugin = facade::load_plugin(&bytes)?;
+    let mut listing = String::new();
+    for command in plugin.commands()? {
+        listing += &format!(
+            "0x{:X}\t{}\t{}\t{}\taccess {}\t{}\n",
+            command.address,
+            command.native,
+            quoted(&command.name),
+            command.handler.as_deref().unwrap_or("?"),
+            command.access.map_or("?".to_owned(), |a| a.to_string()),
+            quoted(&command.info)
+        );
+    }
+    for cvar in plugin.cvars()? {
+        listing += &format!(
+            "0x{:X}\t{}\t{}\t{}\tflags {}\t{}\n",
+            cvar.address,
+            cvar.native,
+            quoted(&cvar.name),
+            quoted(&cvar.value),
+            cvar.flags.map_or("?".to_owned(), |f| format!("0x{:X}", f)),
+            quoted(&cvar.description)
+        );
+    }
+    write_output(matches, listing.as_bytes())
+}
+
+fn network(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let plugin = facade::load_plugin(&bytes)?;
+    let listing: String = analysis::network_calls(&plugin)?
+        .iter()
+        .map(|c| format!("0x{:X}\t{:?}\t{}\n", c.address, c.kind, c))
+        .collect();
+    write_output(matches, listing.as_bytes())
+}
+
+fn hexdump(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let color = matches.is_present("color");
+    let text: String = hexdump::dumps(&bytes)?
+        .iter()
+        .map(|d| d.render(color))
+        .collect::<Vec<String>>()
+        .join("\n");
+    write_output(matches, text.as_bytes())
+}
+
+fn scan(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let plugin = facade::load_plugin(&bytes)?;
+    let text = scan::scan(&plugin)?.to_string();
+    write_output(matches, text.as_bytes())
+}
+
+fn validate(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let violations = facade::validate(&bytes)?;
+    let text = match violations.is_empty() {
+        true => String::from("no violations\n"),
+        false => violations.iter().map(|v| format!("{}\n", v)).collect(),
+    };
+    write_output(matches, text.as_bytes())
+}
+
+fn sigscan(matches: &ArgMatches) -> Result<(), Error> {
+    let mut signatures = vec![];
+    if let Some(path) = matches.value_of("signatures") {
+        signatures = Signature::parse_list(&fs::read_to_string(path)?)?;
+    }
+    for pattern in matches.values_of("pattern").into_iter().flatten() {
+        signatures.push(Signature {
+            name: pattern.to_owned(),
+            pattern: pattern.parse()?,
+        });
+    }
+
+    let files: Vec<&str> = matches.values_of("file").unwrap().collect();
+    let mut listing = String::new();
+    for path in files.iter() {
+        let bytes = fs::read(path)?;
+        let plugin = facade::load_plugin(&bytes)?;
+        for found in sigscan::sigscan(&plugin, &signatures)? {
+            if files.len() > 1 {
+                listing += &format!("{}\t", path);
+            }
+            listing += &format!(
+                "0x{:X}\t{}\t{}\n",
+                found.address,
+                found.name,
+                found.function.unwrap_or_default()
+            );
+        }
+    }
+    write_output(matches, listing.as_bytes())
+}
+
+fn rules(matches: &ArgMatches) -> Result<(), Error> {
+    let rules = Rule::parse_list(&fs::read_to_string(matches.value_of("rules").unwrap())?)?;
+
+    let files: Vec<&str> = matches.values_of("file").unwrap().collect();
+    let mut listing = String::new();
+    for path in files.iter() {
+        let bytes = fs::read(path)?;
+        let plugin = facade::load_plugin(&bytes)?;
+        for found in match_rules(&plugin, &rules)? {
+            if files.len() > 1 {
+                listing += &format!("{}\t", path);
+            }
+            let hits: Vec<String> = found.hits.iter().map(|h| h.to_string()).collect();
+            listing += &format!("{}\t{}\n", found.rule, hits.join(", "));
+        }
+    }
+    write_output(matches, listing.as_bytes())
+}
+
+// Runs public with unstubbed natives, prints every native call with
+// arguments read from memory
+fn emulate(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let plugin = facade::load_plugin(&bytes)?;
+    let args: Vec<Argument> = matches
+        .values_of("args")
+        .into_iter()
+        .flatten()
+        .map(|arg| match arg.parse() {
+            Ok(cell) => Argument::Cell(cell),
+            Err(_) => Argument::String(arg.to_owned()),
+        })
+        .collect();
+
+    let mut emulator = Emulator::new(&plugin)?;
+    emulator.tracing(true);
+    let result = emulator.call(matches.value_of("public").unwrap(), &args);
+    let mut listing = emulator.call_log();
+    match result {
+        Ok(value) => listing += &format!("returned {}\n", value),
+        Err(e) => listing += &format!("{}\n", e),
+    }
+    write_output(matches, listing.as_bytes())
+}
+
+// Publics whose execution reaches function containing cod address
+fn triggerable_from(graph: &CallGraph, address: usize) -> String {
+    match graph.functions.iter().position(|f| f.contains(address)) {
+        Some(function) => graph.triggerable_from(function).join(", "),
+        None => String::new(),
+    }
+}
+
+fn xref(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let plugin = facade::load_plugin(&bytes)?;
+    let graph = analysis::call_graph(&plugin)?;
+    let mut listing = String::new();
+
+    if matches.is_present("commands") {
+        for command in analysis::command_strings(&plugin)? {
+            let value = match command.value {
+                CommandValue::Constant(ref c) => format!("\"{}\"", c.escape_debug()),
+                CommandValue::Formatted { ref format } => {
+                    format!("format \"{}\"", format.escape_debug())
+                }
+                CommandValue::Dynamic => "dynamic".to_owned(),
+            };
+            listing += &format!(
+                "0x{:X}\t{}\t{}\t{}\ttriggerable from: {}\n",
+                command.address,
+                command.native,
+                command.function,
+                value,
+                triggerable_from(&graph, command.address)
+            );
+        }
+        return write_output(matches, listing.as_bytes());
+    }
+
+    let xrefs = analysis::xrefs(&plugin)?;
+    let target = matches.value_of("target").unwrap();
+    let address = match target.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => target.parse().ok(),
+    };
+    let refs = match address {
+        Some(address) if matches.is_present("code") => xrefs.xrefs_to(XrefTarget::Code(address)),
+        Some(address) => xrefs.xrefs_to(XrefTarget::Data(address)),
+        None => xrefs.callers_of(target),
+    };
+
+    for xref in refs {
+        let function = xrefs.function_of(xref).map_or("", |f| f.name.as_str());
+        listing += &format!(
+            "0x{:X}\t{}\t{}\ttriggerable from: {}\n",
+            xref.from,
+            xref.kind,
+            function,
+            triggerable_from(&graph, xref.from)
+        );
+    }
+    write_output(matches, listing.as_bytes())
+}
+
+fn callgraph(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let plugin = facade::load_plugin(&bytes)?;
+    let dot = analysis::call_graph(&plugin)?.to_dot();
+    write_output(matches, dot.as_bytes())
+}
+
+fn decompile(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    if matches.is_present("emit-inc") {
+        let inc = analysis::generate_inc(&facade::load_plugin(&bytes)?)?;
+        return write_output(matches, inc.as_bytes());
+    }
+    let style = Style {
+        indent_width: match matches.value_of("indent") {
+            Some(width) => width.parse()?,
+            None => Style::default().indent_width,
+        },
+        braces: match matches.is_present("allman") {
+            true => Braces::Allman,
+            false => Braces::KAndR,
+        },
+        max_line_length: matches
+            .value_of("line-length")
+            .map(str::parse)
+            .transpose()?,
+        hex_constants: matches.is_present("hex"),
+    };
+    let opts = DecompileOptions {
+        deobfuscate: matches.is_present("deobfuscate"),
+        style,
+        ..DecompileOptions::default()
+    };
+    let stocks = match matches.value_of("stocks") {
+        Some(path) => StockDatabase::parse(&fs::read_to_string(path)?)?,
+        None => StockDatabase::new(),
+    };
+    let source = match matches.value_of("function") {
+        Some(function) => facade::decompile_function(&bytes, function, &opts)?,
+        None => facade::decompile_with_stocks(&bytes, &opts, &stocks)?,
+    };
+    write_output(matches, source.as_bytes())
+}
+
+fn diff(matches: &ArgMatches) -> Result<(), Error> {
+    let old = fs::read(matches.value_of("old").unwrap())?;
+    let new = fs::read(matches.value_of("new").unwrap())?;
+    let diff = diff::compare(&facade::load_plugin(&old)?, &facade::load_plugin(&new)?)?;
+    write_output(matches, diff.to_string().as_bytes())
+}
+
+fn verify(matches: &ArgMatches) -> Result<(), Error> {
+    let bytes = fs::read(matches.value_of("file").unwrap())?;
+    let mut verifier = Verifier::new(matches.value_of("amxxpc").unwrap());
+    if let Some(dir) = matches.value_of("include") {
+        verifier.include_dir(dir);
+    }
+
+    let verification = verifier.verify(&bytes)?;
+    let report = format!(
+        "{}fidelity: {:.2}\n",
+        verification.diff, verification.fidelity
+    );
+    write_output(matches, report.as_bytes())
+}
+
+fn learn_stocks(matches: &ArgMatches) -> Result<(), Error> {
+    let mut stocks = match matches.value_of("output") {
+        Some(path) if Path::new(path).is_file() => {
+            StockDatabase::parse(&fs::read_to_string(path)?)?
+        }
+        _ => StockDatabase::new(),
+    };
+    for path in matches.values_of("file").unwrap() {
+        let bytes = fs::read(path)?;
+        let learned = stocks.learn(&facade::load_plugin(&bytes)?, matches.value_of("include"))?;
+        eprintln!("{}: {} stocks", path, learned);
+    }
+    write_output(matches, stocks.to_string().as_bytes())
+}
+
+fn learn_releases(matches: &ArgMatches) -> Result<(), Error> {
+    let mut releases = match matches.value_of("output") {
+        Some(path) if Path::new(path).is_file() => {
+            ReleaseDatabase::parse(&fs::read_to_string(path)?)?
+        }
+        _ => ReleaseDatabase::new(),
+    };
+    let release = matches.value_of("release").unwrap();
+    for path in matches.values_of("file").unwrap() {
+        let bytes = fs::read(path)?;
+        let file = Path::new(path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        releases.learn(&facade::load_plugin(&bytes)?, &file, release)?;
+    }
+    write_output(matches, releases.to_string().as_bytes())
+}
+
+fn batch(matches: &ArgMatches) -> Result<(), Error> {
+    let batch = batch::analyze_dir(matches.value_of("dir").unwrap())?;
+
+    let mut listing = String::new();
+    for file in batch.files.iter() {
+        let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+        match file.summary {
+            Ok(ref summary) => {
+                listing += &format!("{}\tok\t{}\n", name, summary.findings.join(", "))
+            }
+            Err(ref e) => listing += &format!("{}\terror\t{}\n", name, e),
+        }
+
+        if let (Some(dir), Ok(source)) = (matches.value_of("sources"), &file.source) {
+            fs::write(Path::new(dir).join(&*name).with_extension("sma"), source)?;
+        }
+    }
+
+    if let Some(path) = matches.value_of("aggregate") {
+        fs::write(path, batch.analysis().to_json()?)?;
+    }
+    write_output(matches, listing.as_bytes())
+}
+
+fn audit(matches: &ArgMatches) -> Result<(), Error> {
+    let mut audit = audit::audit(
+        matches.value_of("ini").unwrap(),
+        matches.value_of("dir").unwrap(),
+    )?;
+    if let Some(path) = matches.value_of("releases") {
+        audit.match_releases(&ReleaseDatabase::parse(&fs::read_to_string(path)?)?);
+    }
+    write_output(matches, audit.to_string().as_bytes())
+}
+
+fn patch_string(matches: &ArgMatches) -> Result<(), Error> {
+    let file_path = matches.value_of("file").unwrap();
+    let address = matches.value_of("address").unwrap();
+    let address = match address.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16)?,
+        None => address.parse()?,
+    };
+
+    let bytes = fs::read(file_path)?;
+    let mut amxmod_plugin = facade::load_plugin(&bytes)?;
+    patch::replace_string(
+        &mut amxmod_plugin,
+        address,
+        matches.value_of("text").unwrap(),
+    )?;
+    let output = matches.value_of("output").unwrap_or(file_path);
+    fs::write(output, File::pack(&amxmod_plugin)?.bin)?;
+    Ok(())
+}
+
+fn rename_native(matches: &ArgMatches) -> Result<(), Error> {
+    let file_path = matches.value_of("file").unwrap();
+    let old = matches.value_of("old").unwrap();
+    let new = matches.value_of("new").unwrap();
+
+    let bytes = fs::read(file_path)?;
+    let mut amxmod_plugin = facade::load_plugin(&bytes)?;
+    if matches.is_present("retarget") {
+        patch::retarget_native(&mut amxmod_plugin, old, new)?;
+    } else {
+        patch::rename_native(&mut amxmod_plugin, old, new)?;
+    }
+    let output = matches.value_of("output").unwrap_or(file_path);
+    fs::write(output, File::pack(&amxmod_plugin)?.bin)?;
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let matches = App::new("amxxtool")
+        .version("0.0.1")
+        .about("Unpack, disassemble and decompile amxmodx plugins")
+        .author("Fedcomp")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("unpack")
+                .about("Extract raw 32 bit .amx image, FILE.amx by default")
+                .arg(file_arg())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("disasm")
+                .about("Print opcode listing")
+                .arg(file_arg())
+                .arg(
+                    Arg::with_name("annotated")
+                        .short("a")
+                        .long("annotated")
+                        .help("Add labels, raw cells and resolved names")
+                        .conflicts_with("function"),
+                )
+                .arg(
+                    Arg::with_name("html")
+                        .long("html")
+                        .help("Render annotated listing as HTML page with linked jumps and xrefs")
+                        .conflicts_with_all(&["function", "annotated"]),
+                )
+                .arg(function_arg())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Print plugin layout and symbols")
+                .arg(file_arg())
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Dump header, tables and opcodes as JSON"),
+                )
+                .arg(
+                    Arg::with_name("markdown")
+                        .long("markdown")
+                        .conflicts_with("json")
+                        .help("Render report as Markdown"),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("strings")
+                .about("Print DAT strings with their addresses")
+                .arg(file_arg())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("console")
+                .about("List console commands and cvars plugin registers")
+                .arg(file_arg())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("network")
+                .about("List SQL, socket and HTTP calls with constant hosts, ports and queries")
+                .arg(file_arg())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("hexdump")
+                .about("Hexdump with container, section and amx header fields annotated")
+                .arg(file_arg())
+                .arg(
+                    Arg::with_name("color")
+                        .long("color")
+                        .help("Color header field bytes with ANSI escapes"),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("scan")
+                .about("Look for backdoors, hidden commands and self-modifying code")
+                .arg(file_arg())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Cross-check section sizes, header offsets and name tables")
+                .arg(file_arg())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("rules")
+                .about("Match rules of strings, natives, opcodes and metadata")
+                .arg(file_arg().multiple(true))
+                .arg(
+                    Arg::with_name("rules")
+                        .short("r")
+                        .long("rules")
+                        .value_name("RULES")
+                        .help("File with \"rule NAME { ... }\" blocks")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("sigscan")
+                .about("Find cell patterns like \"PUSH.C ?? SYSREQ.C ??\" in cod")
+                .arg(file_arg().multiple(true))
+                .arg(
+                    Arg::with_name("pattern")
+                        .short("p")
+                        .long("pattern")
+                        .value_name("PATTERN")
+                        .help("Hex cells, opcode mnemonics and ?? wildcards")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .required_unless("signatures"),
+                )
+                .arg(
+                    Arg::with_name("signatures")
+                        .short("s")
+                        .long("signatures")
+                        .value_name("LIST")
+                        .help("File with \"name: pattern\" lines")
+                        .takes_value(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("emulate")
+                .about("Run public in emulator, natives return 0, log native calls")
+                .arg(file_arg())
+                .arg(
+                    Arg::with_name("public")
+                        .value_name("PUBLIC")
+                        .help("Public function to call")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("args")
+                        .value_name("ARGS")
+                        .help("Numbers passed as cells, anything else as string")
+                        .multiple(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("xref")
+                .about("List code referencing function or DAT address, or console commands run")
+                .arg(file_arg())
+                .arg(
+                    Arg::with_name("target")
+                        .value_name("TARGET")
+                        .help("Function name, DAT address or cod address with --code")
+                        .required_unless("commands"),
+                )
+                .arg(
+                    Arg::with_name("code")
+                        .long("code")
+                        .help("TARGET address is in cod, jumps and calls to it are listed"),
+                )
+                .arg(
+                    Arg::with_name("commands")
+                        .long("commands")
+                        .help("List server_cmd and client_cmd calls with commands they run"),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("callgraph")
+                .about("Print Graphviz call graph of functions and natives they call")
+                .arg(file_arg())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("decompile")
+                .about("Print decompiled source")
+                .arg(file_arg())
+                .arg(function_arg())
+                .arg(
+                    Arg::with_name("stocks")
+                        .short("s")
+                        .long("stocks")
+                        .value_name("DB")
+                        .help("Leave stocks known from learn-stocks database to includes")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("deobfuscate")
+                        .long("deobfuscate")
+                        .help("Drop junk opcodes and opaque predicates, comment string decoding loops"),
+                )
+                .arg(
+                    Arg::with_name("indent")
+                        .long("indent")
+                        .value_name("SPACES")
+                        .help("Spaces per nesting level")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("allman")
+                        .long("allman")
+                        .help("Opening braces on their own lines"),
+                )
+                .arg(
+                    Arg::with_name("line-length")
+                        .long("line-length")
+                        .value_name("COLUMNS")
+                        .help("Wrap longer lines after commas")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("hex")
+                        .long("hex")
+                        .help("Write constants as hex"),
+                )
+                .arg(
+                    Arg::with_name("emit-inc")
+                        .long("emit-inc")
+                        .help("Print .inc with declarations of used natives instead of source"),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("learn-stocks")
+                .about("Fingerprint named stocks of plugins compiled with debug info, OUTPUT is extended")
+                .arg(file_arg().multiple(true))
+                .arg(
+                    Arg::with_name("include")
+                        .short("i")
+                        .long("include")
+                        .value_name("INCLUDE")
+                        .help("Include stocks come from, e.g. amxmisc")
+                        .takes_value(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compare header, tables and functions of two plugins")
+                .arg(
+                    Arg::with_name("old")
+                        .value_name("OLD")
+                        .help("Previous plugin release")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .value_name("NEW")
+                        .help("Next plugin release")
+                        .required(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Recompile decompiled source and compare bytecode with original")
+                .arg(file_arg())
+                .arg(
+                    Arg::with_name("amxxpc")
+                        .long("amxxpc")
+                        .value_name("AMXXPC")
+                        .help("Path to amxxpc compiler")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("include")
+                        .short("i")
+                        .long("include")
+                        .value_name("DIR")
+                        .help("Include directory passed to amxxpc")
+                        .takes_value(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("batch")
+                .about("Summarize and decompile every plugin of directory in parallel")
+                .arg(
+                    Arg::with_name("dir")
+                        .value_name("DIR")
+                        .help("Plugins directory")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("aggregate")
+                        .long("aggregate")
+                        .value_name("JSON")
+                        .help("Write cross-plugin natives, commands and duplicates to JSON")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("sources")
+                        .long("sources")
+                        .value_name("SOURCES")
+                        .help("Write decompiled .sma files into SOURCES directory")
+                        .takes_value(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("audit")
+                .about("Check every plugin plugins.ini enables: metadata, natives, commands, findings")
+                .arg(
+                    Arg::with_name("ini")
+                        .value_name("INI")
+                        .help("configs/plugins.ini")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("dir")
+                        .value_name("DIR")
+                        .help("Plugins directory")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("releases")
+                        .short("r")
+                        .long("releases")
+                        .value_name("DB")
+                        .help("Tell official plugins from modified ones by learn-releases database")
+                        .takes_value(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("learn-releases")
+                .about("Fingerprint plugins of trusted build, OUTPUT is extended")
+                .arg(file_arg().multiple(true))
+                .arg(
+                    Arg::with_name("release")
+                        .short("r")
+                        .long("release")
+                        .value_name("RELEASE")
+                        .help("Release plugins come from, e.g. \"amxmodx 1.9.0\"")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("patch-string")
+                .about("Replace string constant at DAT address and repack, FILE is overwritten by default")
+                .arg(file_arg())
+                .arg(
+                    Arg::with_name("address")
+                        .value_name("ADDRESS")
+                        .help("DAT address of string, decimal or 0x prefixed hex")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("text")
+                        .value_name("TEXT")
+                        .help("New string, must not be longer than original")
+                        .required(true),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("rename-native")
+                .about("Rename imported native and repack, FILE is overwritten by default")
+                .arg(file_arg())
+                .arg(
+                    Arg::with_name("old")
+                        .value_name("OLD")
+                        .help("Native name plugin imports")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .value_name("NEW")
+                        .help("New native name")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("retarget")
+                        .short("r")
+                        .long("retarget")
+                        .help("Point calls of OLD to already imported native NEW instead"),
+                )
+                .arg(output_arg()),
+        )
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("unpack", Some(m)) => unpack(m),
+        ("disasm", Some(m)) => disasm(m),
+        ("info", Some(m)) => info(m),
+        ("strings", Some(m)) => strings(m),
+        ("console", Some(m)) => console(m),
+        ("network", Some(m)) => network(m),
+        ("hexdump", Some(m)) => hexdump(m),
+        ("scan", Some(m)) => scan(m),
+        ("validate", Some(m)) => validate(m),
+        ("rules", Some(m)) => rules(m),
+        ("sigscan", Some(m)) => sigscan(m),
+        ("emulate", Some(m)) => emulate(m),
+        ("xref", Some(m)) => xref(m),
+        ("callgraph", Some(m)) => callgraph(m),
+        ("decompile", Some(m)) => decompile(m),
+        ("learn-stocks", Some(m)) => learn_stocks(m),
+        ("diff", Some(m)) => diff(m),
+        ("verify", Some(m)) => verify(m),
+        ("batch", Some(m)) => batch(m),
+        ("audit", Some(m)) => audit(m),
+        ("learn-releases", Some(m)) => learn_releases(m),
+        ("patch-string", Some(m)) => patch_string(m),
+        ("rename-native", Some(m)) => rename_native(m),
+        _ => unreachable!(),
+    };
+
+    if let Err(e) = result {
+        die!("{}", e);
+    }
+}
@@ -0,0 +1,178 @@
+// Cross-plugin indexes over summaries of many plugins, e.g. whole server
+// plugins folder. Only names and hashes are kept, never plugin binaries.
+
+use std::collections::BTreeMap;
+use std::slice;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::analysis::{registrations, Registrations};
+use crate::error::AmxError;
+use crate::facade::{inspect, load_plugin, Format, PluginInfo};
+use crate::fingerprint::PluginFingerprint;
+use crate::scan::scan;
+
+// Per-plugin results fed into `Analysis`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginSummary {
+    pub name: String,
+    pub info: PluginInfo,
+    pub fingerprint: PluginFingerprint,
+    pub registrations: Registrations,
+    // Kinds of scan findings
+    pub findings: Vec<String>,
+}
+
+impl PluginSummary {
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Result<PluginSummary, AmxError> {
+        let info = inspect(bytes)?;
+        let plugin = load_plugin(bytes)?;
+        let mut fingerprint = PluginFingerprint::from(&plugin)?;
+        if info.format == Format::Amxx {
+            fingerprint = fingerprint.with_container(bytes);
+        }
+
+        Ok(PluginSummary {
+            name: name.to_owned(),
+            info,
+            fingerprint,
+            registrations: registrations(&plugin)?,
+            findings: scan(&plugin)?.kinds(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Analysis {
+    pub plugins: Vec<String>,
+    // Native name -> plugins using it
+    pub natives: BTreeMap<String, Vec<String>>,
+    // Cod hash -> plugins with that cod
+    pub cod_hashes: BTreeMap<String, Vec<String>>,
+    // Normalized cod hash -> plugins with that code, strings aside
+    pub normalized_cod_hashes: BTreeMap<String, Vec<String>>,
+    // Command or cvar name -> plugins registering it
+    pub commands: BTreeMap<String, Vec<String>>,
+    pub cvars: BTreeMap<String, Vec<String>>,
+    // Finding kind -> number of plugins having it
+    pub findings: BTreeMap<String, usize>,
+}
+
+fn index(map: &mut BTreeMap<String, Vec<String>>, keys: &[String], plugin: &str) {
+    for key in keys {
+        let plugins = map.entry(key.clone()).or_default();
+        if !plugins.iter().any(|p| p == plugin) {
+            plugins.push(plugin.to_owned());
+        }
+    }
+}
+
+impl Analysis {
+    pub fn new() -> Analysis {
+        Analysis::default()
+    }
+
+    pub fn add(&mut self, summary: &PluginSummary) {
+        let name = summary.name.as_str();
+        self.plugins.push(name.to_owned());
+
+        index(&mut self.natives, &summary.info.natives, name);
+        index(
+            &mut self.cod_hashes,
+            slice::from_ref(&summary.fingerprint.cod),
+            name,
+        );
+        if let Some(ref normalized) = summary.fingerprint.normalized_cod {
+            index(
+                &mut self.normalized_cod_hashes,
+                slice::from_ref(normalized),
+                name,
+            );
+        }
+        index(&mut self.commands, &summary.registrations.commands, name);
+        index(&mut self.cvars, &summary.registrations.cvars, name);
+
+        let mut kinds = summary.findings.clone();
+        kinds.sort();
+        kinds.dedup();
+        for kind in kinds {
+            *self.findings.entry(kind).or_insert(0) += 1;
+        }
+    }
+
+    // Groups of plugins sharing identical cod
+    pub fn duplicates(&self) -> Vec<&[String]> {
+        self.cod_hashes
+            .values()
+            .filter(|plugins| plugins.len() > 1)
+            .map(|plugins| plugins.as_slice())
+            .collect()
+    }
+
+    // Groups of plugins sharing code up to strings, e.g. re-branded copies,
+    // identical duplicates included
+    pub fn rebranded(&self) -> Vec<&[String]> {
+        self.normalized_cod_hashes
+            .values()
+            .filter(|plugins| plugins.len() > 1)
+            .map(|plugins| plugins.as_slice())
+            .collect()
+    }
+
+    #[cfg(feature = "serde_json")]
+    pub fn to_json(&self) -> Result<String, AmxError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Analysis, PluginSummary};
+    use crate::util::tests::load_fixture;
+
+    fn analysis() -> Analysis {
+        let mut analysis = Analysis::new();
+        for &(name, fixture) in &[
+            ("simple.amxx", "simple.amxx183"),
+            ("simple_copy.amxx", "simple.amxx183"),
+            ("two_natives.amxx", "two_natives.amxx"),
+        ] {
+            let summary = PluginSummary::from_bytes(name, &load_fixture(fixture)).unwrap();
+            analysis.add(&summary);
+        }
+        analysis
+    }
+
+    #[test]
+    fn it_detect_duplicates() {
+        let analysis = analysis();
+
+        assert_eq!(analysis.plugins.len(), 3);
+        assert_eq!(
+            analysis.duplicates(),
+            [&["simple.amxx".to_owned(), "simple_copy.amxx".to_owned()][..]]
+        );
+        assert_eq!(analysis.rebranded(), analysis.duplicates());
+    }
+
+    #[test]
+    fn it_index_native_usage() {
+        let analysis = analysis();
+
+        assert_eq!(
+            analysis.natives["register_plugin"],
+            ["simple.amxx", "simple_copy.amxx"]
+        );
+        assert_eq!(analysis.natives["native_one"], ["two_natives.amxx"]);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn it_serialize_aggregate() {
+        let json = analysis().to_json().unwrap();
+
+        assert!(json.contains("\"native_one\""));
+    }
+}
@@ -0,0 +1,674 @@
+// Function and opcode level comparison of two plugins.
+// Operands are normalized so code moved by unrelated changes still matches.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::mem;
+
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, OpcodeType, OperandKind, Plugin};
+use crate::analysis::{functions, Function};
+use crate::error::AmxError;
+
+// Private functions below this similarity are reported as added/removed
+const MATCH_THRESHOLD: f64 = 0.6;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Operand {
+    None,
+    Value(u32),
+    // Jump or call target
+    Code,
+    // Global variable address
+    Global,
+    // DAT string constant
+    String(String),
+    Native(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedOpcode {
+    pub code: OpcodeType,
+    pub operand: Operand,
+}
+
+impl fmt::Display for NormalizedOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operand {
+            Operand::None => write!(f, "{}", self.code),
+            Operand::Value(v) => write!(f, "{}\t0x{:X}", self.code, v),
+            Operand::Code => write!(f, "{}\t<code>", self.code),
+            Operand::Global => write!(f, "{}\t<global>", self.code),
+            Operand::String(ref s) => write!(f, "{}\t{:?}", self.code, s),
+            Operand::Native(ref n) => write!(f, "{}\t{}", self.code, n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    Keep(NormalizedOpcode),
+    Insert(NormalizedOpcode),
+    Delete(NormalizedOpcode),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FunctionStatus {
+    Identical,
+    Modified { similarity: f64, edits: Vec<Edit> },
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDiff {
+    // None for added function
+    pub old_name: Option<String>,
+    // None for removed function
+    pub new_name: Option<String>,
+    pub status: FunctionStatus,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl TableDiff {
+    fn from(old: &BTreeSet<String>, new: &BTreeSet<String>) -> TableDiff {
+        TableDiff {
+            added: new.difference(old).cloned().collect(),
+            removed: old.difference(new).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+// Header value differing between plugins
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderChange {
+    pub field: &'static str,
+    pub old: usize,
+    pub new: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginDiff {
+    pub header: Vec<HeaderChange>,
+    pub functions: Vec<FunctionDiff>,
+    pub natives: TableDiff,
+    pub publics: TableDiff,
+    // Strings referenced from code
+    pub strings: TableDiff,
+}
+
+impl PluginDiff {
+    pub fn is_identical(&self) -> bool {
+        self.header.is_empty()
+            && self.natives.is_empty()
+            && self.publics.is_empty()
+            && self.strings.is_empty()
+            && self
+                .functions
+                .iter()
+                .all(|f| f.status == FunctionStatus::Identical)
+    }
+
+    // Mean similarity of functions, added and removed ones count as 0
+    pub fn similarity(&self) -> f64 {
+        if self.functions.is_empty() {
+            return 1.0;
+        }
+
+        let total: f64 = self
+            .functions
+            .iter()
+            .map(|f| match f.status {
+                FunctionStatus::Identical => 1.0,
+                FunctionStatus::Modified { similarity, .. } => similarity,
+                FunctionStatus::Added | FunctionStatus::Removed => 0.0,
+            })
+            .sum();
+        total / self.functions.len() as f64
+    }
+
+    pub fn modified(&self) -> impl Iterator<Item = &FunctionDiff> {
+        self.functions
+            .iter()
+            .filter(|f| matches!(f.status, FunctionStatus::Modified { .. }))
+    }
+}
+
+fn write_table(f: &mut fmt::Formatter, title: &str, table: &TableDiff) -> fmt::Result {
+    if table.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(f, "{}:", title)?;
+    for name in table.added.iter() {
+        writeln!(f, "  + {:?}", name)?;
+    }
+    for name in table.removed.iter() {
+        writeln!(f, "  - {:?}", name)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for PluginDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.header.is_empty() {
+            writeln!(f, "header:")?;
+        }
+        for change in self.header.iter() {
+            writeln!(
+                f,
+                "  {}: 0x{:X} -> 0x{:X}",
+                change.field, change.old, change.new
+            )?;
+        }
+        write_table(f, "natives", &self.natives)?;
+        write_table(f, "publics", &self.publics)?;
+        write_table(f, "strings", &self.strings)?;
+
+        for function in self.functions.iter() {
+            let old = function.old_name.as_deref();
+            let new = function.new_name.as_deref();
+
+            match (&function.status, old, new) {
+                (FunctionStatus::Identical, Some(old), Some(new)) if old == new => {
+                    writeln!(f, "= {}", old)?
+                }
+                (FunctionStatus::Identical, Some(old), Some(new)) => {
+                    writeln!(f, "= {} -> {}", old, new)?
+                }
+                (FunctionStatus::Modified { similarity, edits }, Some(old), Some(new)) => {
+                    if old == new {
+                        writeln!(f, "~ {} ({:.2})", old, similarity)?;
+                    } else {
+                        writeln!(f, "~ {} -> {} ({:.2})", old, new, similarity)?;
+                    }
+                    for edit in edits.iter() {
+                        match edit {
+                            Edit::Keep(_) => {}
+                            Edit::Insert(o) => writeln!(f, "    + {}", o)?,
+                            Edit::Delete(o) => writeln!(f, "    - {}", o)?,
+                        }
+                    }
+                }
+                (FunctionStatus::Added, _, Some(new)) => writeln!(f, "+ {}", new)?,
+                (FunctionStatus::Removed, Some(old), _) => writeln!(f, "- {}", old)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) struct PluginFunctions {
+    pub(crate) functions: Vec<Function>,
+    // Normalized opcodes of each function, BREAK skipped
+    pub(crate) bodies: Vec<Vec<NormalizedOpcode>>,
+    natives: BTreeSet<String>,
+    publics: BTreeSet<String>,
+    strings: BTreeSet<String>,
+}
+
+fn normalize(plugin: &Plugin, opcode: &Opcode, natives: &[String]) -> NormalizedOpcode {
+    let param = match opcode.param {
+        Some(p) => p,
+        None => {
+            return NormalizedOpcode {
+                code: opcode.code,
+                operand: Operand::None,
+            }
+        }
+    };
+
+    let operand = match opcode.code {
+        OP_SYSREQ_C => match natives.get(param as usize) {
+            Some(name) => Operand::Native(name.clone()),
+            None => Operand::Value(param),
+        },
+        code if matches!(
+            code.info().operand,
+            OperandKind::Jump
+                | OperandKind::Branch
+                | OperandKind::CaseTable
+                | OperandKind::Function
+        ) =>
+        {
+            Operand::Code
+        }
+        code if code.info().operand == OperandKind::Dat => Operand::Global,
+        // Constants pointing to strings are compared by contents
+        OP_PUSH_C | OP_CONST_PRI | OP_CONST_ALT => match plugin.read_string(param as usize) {
+            Some(s) if !s.is_empty() => Operand::String(s),
+            _ => Operand::Value(param),
+        },
+        _ => Operand::Value(param),
+    };
+
+    NormalizedOpcode {
+        code: opcode.code,
+        operand,
+    }
+}
+
+pub(crate) fn plugin_functions(plugin: &Plugin) -> Result<PluginFunctions, AmxError> {
+    let opcodes = plugin.opcodes()?;
+    let native_names: Vec<String> = plugin
+        .natives()?
+        .iter()
+        .map(|n| n.name.to_string_lossy().into_owned())
+        .collect();
+    let publics = plugin
+        .publics()?
+        .iter()
+        .map(|p| p.name.to_string_lossy().into_owned())
+        .collect();
+
+    let functions = functions(plugin)?;
+    let bodies: Vec<Vec<NormalizedOpcode>> = functions
+        .iter()
+        .map(|f| {
+            f.opcodes(&opcodes)
+                .iter()
+                .filter(|o| o.code != OP_BREAK)
+                .map(|o| normalize(plugin, o, &native_names))
+                .collect()
+        })
+        .collect();
+
+    let strings = bodies
+        .iter()
+        .flatten()
+        .filter_map(|o| match o.operand {
+            Operand::String(ref s) => Some(s.clone()),
+            _ => None,
+        })
+        .collect();
+
+    Ok(PluginFunctions {
+        functions,
+        bodies,
+        natives: native_names.into_iter().collect(),
+        publics,
+        strings,
+    })
+}
+
+// Last row of LCS lengths, `row[j]` is LCS of `old` and first `j` of `new`.
+// Only two rows are kept.
+fn lcs_row<'a, I, J>(old: I, new: J) -> Vec<u32>
+where
+    I: Iterator<Item = &'a NormalizedOpcode>,
+    J: Iterator<Item = &'a NormalizedOpcode> + Clone,
+{
+    let mut row = vec![0u32; new.clone().count() + 1];
+    let mut next = row.clone();
+    for o in old {
+        for (j, n) in new.clone().enumerate() {
+            next[j + 1] = if o == n {
+                row[j] + 1
+            } else {
+                row[j + 1].max(next[j])
+            };
+        }
+        mem::swap(&mut row, &mut next);
+    }
+    row
+}
+
+fn similarity(old: &[NormalizedOpcode], new: &[NormalizedOpcode]) -> f64 {
+    if old.is_empty() && new.is_empty() {
+        return 1.0;
+    }
+    let common = lcs_row(old.iter(), new.iter())[new.len()];
+    2.0 * f64::from(common) / (old.len() + new.len()) as f64
+}
+
+// Upper bound of similarity judging only by lengths
+fn similarity_bound(old: usize, new: usize) -> f64 {
+    if old + new == 0 {
+        return 1.0;
+    }
+    2.0 * old.min(new) as f64 / (old + new) as f64
+}
+
+fn edit_script(old: &[NormalizedOpcode], new: &[NormalizedOpcode]) -> Vec<Edit> {
+    let mut edits = vec![];
+    push_edits(old, new, &mut edits);
+    edits
+}
+
+// Hirschberg's algorithm: `old` is split in half, `new` where LCS lengths
+// of both halves sum up to the whole, memory stays linear
+fn push_edits(old: &[NormalizedOpcode], new: &[NormalizedOpcode], edits: &mut Vec<Edit>) {
+    match *old {
+        [] => {
+            edits.extend(new.iter().cloned().map(Edit::Insert));
+            return;
+        }
+        [ref opcode] => {
+            match new.iter().position(|n| n == opcode) {
+                Some(k) => {
+                    edits.extend(new[..k].iter().cloned().map(Edit::Insert));
+                    edits.push(Edit::Keep(opcode.clone()));
+                    edits.extend(new[k + 1..].iter().cloned().map(Edit::Insert));
+                }
+                None => {
+                    edits.push(Edit::Delete(opcode.clone()));
+                    edits.extend(new.iter().cloned().map(Edit::Insert));
+                }
+            }
+            return;
+        }
+        _ if new.is_empty() => {
+            edits.extend(old.iter().cloned().map(Edit::Delete));
+            return;
+        }
+        _ => {}
+    }
+
+    let mid = old.len() / 2;
+    let left = lcs_row(old[..mid].iter(), new.iter());
+    let right = lcs_row(old[mid..].iter().rev(), new.iter().rev());
+    let split = (0..=new.len())
+        .max_by_key(|&j| (left[j] + right[new.len() - j], std::cmp::Reverse(j)))
+        .unwrap_or(0);
+
+    push_edits(&old[..mid], &new[..split], edits);
+    push_edits(&old[mid..], &new[split..], edits);
+}
+
+fn compare_bodies(old: &[NormalizedOpcode], new: &[NormalizedOpcode]) -> FunctionStatus {
+    if old == new {
+        return FunctionStatus::Identical;
+    }
+
+    FunctionStatus::Modified {
+        similarity: similarity(old, new),
+        edits: edit_script(old, new),
+    }
+}
+
+fn header_changes(old: &Plugin, new: &Plugin) -> Result<Vec<HeaderChange>, AmxError> {
+    let fields = |plugin: &Plugin| -> Result<[(&'static str, usize); 6], AmxError> {
+        Ok([
+            ("flags", usize::from(plugin.flags().bits())),
+            ("cellsize", plugin.cellsize()),
+            ("cod size", plugin.cod_size()),
+            ("dat size", plugin.dat_slice()?.len()),
+            ("heap budget", plugin.heap_budget()),
+            ("memsize", plugin.memsize()),
+        ])
+    };
+
+    Ok(fields(old)?
+        .iter()
+        .zip(fields(new)?.iter())
+        .filter(|(old, new)| old.1 != new.1)
+        .map(|(old, new)| HeaderChange {
+            field: old.0,
+            old: old.1,
+            new: new.1,
+        })
+        .collect())
+}
+
+// Matches functions by public name, then private ones by opcode similarity
+pub fn compare(old: &Plugin, new: &Plugin) -> Result<PluginDiff, AmxError> {
+    let header = header_changes(old, new)?;
+    let old = plugin_functions(old)?;
+    let new = plugin_functions(new)?;
+
+    let mut old_matched = vec![false; old.functions.len()];
+    let mut new_matched = vec![false; new.functions.len()];
+    let mut pairs: Vec<(usize, usize)> = vec![];
+
+    for (i, function) in old.functions.iter().enumerate() {
+        if !function.public {
+            continue;
+        }
+        let found = new
+            .functions
+            .iter()
+            .position(|f| f.public && f.name == function.name);
+        if let Some(j) = found {
+            old_matched[i] = true;
+            new_matched[j] = true;
+            pairs.push((i, j));
+        }
+    }
+
+    // Greedy best first matching of private functions
+    let unmatched_private = |functions: &[Function], matched: &[bool]| -> Vec<usize> {
+        (0..functions.len())
+            .filter(|&i| !matched[i] && !functions[i].public)
+            .collect()
+    };
+    let mut candidates: Vec<(f64, usize, usize)> = vec![];
+    for &i in unmatched_private(&old.functions, &old_matched).iter() {
+        for &j in unmatched_private(&new.functions, &new_matched).iter() {
+            let (a, b) = (&old.bodies[i], &new.bodies[j]);
+            if similarity_bound(a.len(), b.len()) < MATCH_THRESHOLD {
+                continue;
+            }
+            let score = similarity(a, b);
+            if score >= MATCH_THRESHOLD {
+                candidates.push((score, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+    for (_, i, j) in candidates {
+        if !old_matched[i] && !new_matched[j] {
+            old_matched[i] = true;
+            new_matched[j] = true;
+            pairs.push((i, j));
+        }
+    }
+    pairs.sort();
+
+    let mut result: Vec<FunctionDiff> = pairs
+        .into_iter()
+        .map(|(i, j)| FunctionDiff {
+            old_name: Some(old.functions[i].name.clone()),
+            new_name: Some(new.functions[j].name.clone()),
+            status: compare_bodies(&old.bodies[i], &new.bodies[j]),
+        })
+        .collect();
+
+    result.extend(
+        (0..old.functions.len())
+            .filter(|&i| !old_matched[i])
+            .map(|i| FunctionDiff {
+                old_name: Some(old.functions[i].name.clone()),
+                new_name: None,
+                status: FunctionStatus::Removed,
+            }),
+    );
+    result.extend(
+        (0..new.functions.len())
+            .filter(|&j| !new_matched[j])
+            .map(|j| FunctionDiff {
+                old_name: None,
+                new_name: Some(new.functions[j].name.clone()),
+                status: FunctionStatus::Added,
+            }),
+    );
+
+    Ok(PluginDiff {
+        header,
+        functions: result,
+        natives: TableDiff::from(&old.natives, &new.natives),
+        publics: TableDiff::from(&old.publics, &new.publics),
+        strings: TableDiff::from(&old.strings, &new.strings),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{
+        compare, edit_script, similarity, Edit, FunctionStatus, NormalizedOpcode, Operand,
+    };
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::amxx::File as AmxmodxFile;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    // Public plugin_init calling stock twice, `extra` adds one more native call
+    fn build_plugin(extra: bool) -> Plugin<'static> {
+        let mut builder = PluginBuilder::new();
+        let log_amx = builder.native("log_amx");
+        let hello = builder.string("hello");
+        let bye = builder.string("bye");
+
+        builder.public("plugin_init").op(OP_PROC);
+        let call_stock = builder.here();
+        builder
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_CALL, 0)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+
+        let stock = builder.here();
+        builder
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, hello)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, log_amx)
+            .op_param(OP_STACK, 8);
+        if extra {
+            builder
+                .op_param(OP_PUSH_C, bye)
+                .op_param(OP_PUSH_C, 4)
+                .op_param(OP_SYSREQ_C, log_amx)
+                .op_param(OP_STACK, 8);
+        }
+        builder.op(OP_ZERO_PRI).op(OP_RETN);
+        builder.patch(call_stock + 12, stock);
+
+        builder
+            .public("plugin_end")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, bye)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, log_amx)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+
+        Plugin::try_from(builder.build()).unwrap()
+    }
+
+    #[test]
+    fn it_report_identical_plugins() {
+        let amxmod_plugin = Plugin::try_from(load_fixture("simple.amx183")).unwrap();
+        let amxmodx_file = AmxmodxFile::try_from(load_fixture("simple.amxx183")).unwrap();
+        let unpacked_plugin = amxmodx_file.sections().unwrap()[0].unpack_plugin().unwrap();
+
+        let diff = compare(&amxmod_plugin, &unpacked_plugin).unwrap();
+        assert!(diff.is_identical());
+        assert_eq!(diff.functions.len(), 1);
+        assert_eq!(diff.similarity(), 1.0);
+    }
+
+    #[test]
+    fn it_find_single_modified_function() {
+        let diff = compare(&build_plugin(false), &build_plugin(true)).unwrap();
+
+        assert_eq!(diff.functions.len(), 3);
+        assert!(diff
+            .functions
+            .iter()
+            .all(|f| f.old_name.is_some() && f.new_name.is_some()));
+
+        let modified: Vec<_> = diff.modified().collect();
+        assert_eq!(modified.len(), 1);
+        // Private stock is matched by contents
+        assert_eq!(modified[0].old_name, modified[0].new_name);
+
+        let inserted: Vec<_> = match modified[0].status {
+            FunctionStatus::Modified { ref edits, .. } => edits
+                .iter()
+                .filter_map(|e| match e {
+                    Edit::Insert(o) => Some(o.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => unreachable!(),
+        };
+        assert_eq!(inserted.len(), 4);
+        assert_eq!(
+            inserted[0],
+            NormalizedOpcode {
+                code: OP_PUSH_C,
+                operand: Operand::String("bye".to_owned()),
+            }
+        );
+        assert!(diff.natives.is_empty());
+        assert!(diff.strings.is_empty());
+        assert!(diff.similarity() > 0.6 && diff.similarity() < 1.0);
+
+        let fields: Vec<_> = diff.header.iter().map(|c| c.field).collect();
+        assert_eq!(fields, ["cod size", "memsize"]);
+        assert_eq!(diff.header[0].new - diff.header[0].old, 8 * 4);
+        assert!(diff.to_string().starts_with("header:\n  cod size: 0x"));
+    }
+
+    #[test]
+    fn it_report_added_and_removed_functions() {
+        let mut builder = PluginBuilder::new();
+        builder
+            .public("client_putinserver")
+            .op(OP_PROC)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let other = Plugin::try_from(builder.build()).unwrap();
+
+        let diff = compare(&build_plugin(false), &other).unwrap();
+        let rendered = diff.to_string();
+
+        assert!(rendered.contains("publics:\n  + \"client_putinserver\"\n"));
+        assert!(rendered.contains("- plugin_init\n"));
+        assert!(rendered.contains("+ client_putinserver\n"));
+    }
+
+    #[test]
+    fn it_build_shortest_edit_script() {
+        let opcodes = |codes: &[crate::amx::OpcodeType]| -> Vec<NormalizedOpcode> {
+            codes
+                .iter()
+                .map(|&code| NormalizedOpcode {
+                    code,
+                    operand: Operand::None,
+                })
+                .collect()
+        };
+        let old = opcodes(&[OP_PROC, OP_PUSH_PRI, OP_POP_ALT, OP_ADD, OP_NOP, OP_RETN]);
+        let new = opcodes(&[OP_PROC, OP_ADD, OP_PUSH_PRI, OP_NOP, OP_SUB, OP_RETN]);
+
+        let edits = edit_script(&old, &new);
+        let kept = edits.iter().filter(|e| matches!(e, Edit::Keep(_))).count();
+        assert_eq!(kept, 4);
+        assert_eq!(edits.len(), old.len() + new.len() - kept);
+        assert_eq!(similarity(&old, &new), 8.0 / 12.0);
+
+        let replayed: Vec<_> = edits
+            .iter()
+            .filter_map(|e| match e {
+                Edit::Keep(o) | Edit::Insert(o) => Some(o.clone()),
+                Edit::Delete(_) => None,
+            })
+            .collect();
+        assert_eq!(replayed, new);
+    }
+}
@@ -0,0 +1,508 @@
+// Annotated cod listing: function and jump target labels, raw cells,
+// operands resolved to names and comments with strings and floats. Also
+// rendered as static HTML page with jumps, calls and natives hyperlinked.
+
+use std::collections::BTreeSet;
+
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, OpcodeType, Plugin};
+use crate::analysis::{dictionaries, jump_target, jump_targets, precached_resources};
+use crate::error::AmxError;
+use crate::util::float::{float_constant, float_literal};
+use crate::util::Encoding;
+
+// Raw cells shown in front of mnemonic, longer opcodes are cut
+const MAX_RAW_CELLS: usize = 3;
+const MNEMONIC_WIDTH: usize = 14;
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; }\n\
+                          pre { background: #f6f8fa; padding: 8px; }\n\
+                          pre a { color: #0550ae; }\n\
+                          .comment { color: #6e7781; }\n\
+                          :target { background: #fff8c5; }\n";
+
+// Label of jump target
+pub fn label(address: usize) -> String {
+    format!("l_{:X}", address)
+}
+
+// Anchor of cod address in HTML listing
+fn anchor(address: usize) -> String {
+    format!("a_{:X}", address)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Function names by PROC address: publics, debug symbols, then sub_ with
+// address like analysis::functions
+fn function_names(plugin: &Plugin, opcodes: &[Opcode]) -> Result<Vec<(usize, String)>, AmxError> {
+    let publics = plugin.publics()?;
+    let debug_info = plugin.debug_info().ok().and_then(|i| i);
+
+    Ok(opcodes
+        .iter()
+        .filter(|o| o.code == OP_PROC)
+        .map(|o| {
+            let public = publics.iter().find(|p| p.address == o.address);
+            let symbol = debug_info.as_ref().and_then(|i| {
+                i.symbols
+                    .iter()
+                    .find(|s| s.is_function() && s.code_start == o.address)
+            });
+            let name = match (public, symbol) {
+                (Some(p), _) => p.name.to_string_lossy().into_owned(),
+                (None, Some(s)) => s.name.to_string_lossy().into_owned(),
+                (None, None) => format!("sub_{:x}", o.address),
+            };
+            (o.address, name)
+        })
+        .collect())
+}
+
+struct Listing<'a> {
+    plugin: &'a Plugin<'a>,
+    cod: &'a [u8],
+    natives: Vec<String>,
+    functions: Vec<(usize, String)>,
+    encoding: Encoding,
+}
+
+impl<'a> Listing<'a> {
+    fn new(
+        plugin: &'a Plugin<'a>,
+        opcodes: &[Opcode],
+        encoding: Encoding,
+    ) -> Result<Listing<'a>, AmxError> {
+        let natives = plugin
+            .natives()?
+            .iter()
+            .map(|n| n.name.to_string_lossy().into_owned())
+            .collect();
+        Ok(Listing {
+            plugin,
+            cod: plugin.cod_slice()?,
+            natives,
+            functions: function_names(plugin, opcodes)?,
+            encoding,
+        })
+    }
+
+    fn function(&self, address: usize) -> Option<&String> {
+        self.functions
+            .iter()
+            .find(|&&(a, _)| a == address)
+            .map(|(_, name)| name)
+    }
+
+    fn raw_cells(&self, address: usize, end: usize) -> String {
+        let cellsize = self.plugin.cellsize();
+        let cells: Vec<String> = self
+            .cod
+            .get(address..end.min(self.cod.len()))
+            .unwrap_or_default()
+            .chunks(cellsize)
+            .map(|cell| cell.iter().rev().map(|b| format!("{:02X}", b)).collect())
+            .collect();
+
+        if cells.len() > MAX_RAW_CELLS {
+            format!("{} ..", cells[..MAX_RAW_CELLS].join(" "))
+        } else {
+            cells.join(" ")
+        }
+    }
+
+    fn operand(&self, opcode: &Opcode, param: u32, end: usize) -> String {
+        if let Some(target) = jump_target(opcode, end) {
+            return label(target);
+        }
+        match opcode.code {
+            OP_CALL => match self.function(param as usize) {
+                Some(name) => name.clone(),
+                None => format!("0x{:X}", param),
+            },
+            OP_SYSREQ_C => match self.natives.get(param as usize) {
+                Some(name) => name.clone(),
+                None => format!("0x{:X}", param),
+            },
+            _ => format!("0x{:X}", param),
+        }
+    }
+
+    // Whether DAT address is not in the middle of string, e.g. index of
+    // array cell
+    fn is_string_start(&self, address: usize) -> bool {
+        let cellsize = self.plugin.cellsize();
+        address < cellsize
+            || self.plugin.dat_slice().ok().is_some_and(|dat| {
+                dat.get(address - cellsize..address)
+                    .is_some_and(|cell| cell.iter().all(|&b| b == 0))
+            })
+    }
+
+    // DAT string or float constant operand may stand for
+    fn comment(&self, opcode: &Opcode, param: u32) -> Option<String> {
+        if !matches!(opcode.code, OP_CONST_PRI | OP_CONST_ALT | OP_PUSH_C) {
+            return None;
+        }
+        let string = self
+            .plugin
+            .read_string_bytes(param as usize)
+            .filter(|bytes| !bytes.is_empty() && self.is_string_start(param as usize))
+            .map(|bytes| format!("{:?}", self.encoding.decode(&bytes)));
+        string.or_else(|| float_constant(param).map(float_literal))
+    }
+
+    // Function containing cod address with offset into it, e.g. main+0x1C
+    fn location(&self, address: usize) -> String {
+        match self.functions.iter().rev().find(|&&(a, _)| a <= address) {
+            Some((start, name)) => format!("{}+0x{:X}", name, address - start),
+            None => format!("0x{:X}", address),
+        }
+    }
+
+    fn html_operand(&self, opcode: &Opcode, param: u32, end: usize) -> String {
+        let operand = escape_html(&self.operand(opcode, param, end));
+        if let Some(target) = jump_target(opcode, end) {
+            return format!("<a href=\"#{}\">{}</a>", anchor(target), operand);
+        }
+        match opcode.code {
+            OP_CALL => format!("<a href=\"#{}\">{}</a>", anchor(param as usize), operand),
+            OP_SYSREQ_C => format!("<a href=\"#native_{}\">{}</a>", param, operand),
+            _ => operand,
+        }
+    }
+
+    fn address(&self, opcode: &Opcode, end: usize) -> String {
+        format!(
+            "0x{:08X}  {:<width$}  ",
+            opcode.address,
+            self.raw_cells(opcode.address, end),
+            width = MAX_RAW_CELLS * (2 * self.plugin.cellsize() + 1) + 2
+        )
+    }
+
+    fn html_line(&self, opcode: &Opcode, end: usize) -> String {
+        let address = self.address(opcode, end);
+        let mut line = format!(
+            "<span id=\"{}\">{}</span>{}",
+            anchor(opcode.address),
+            &address[..10],
+            &address[10..]
+        );
+        let mnemonic = opcode.code.to_string();
+        match opcode.param {
+            Some(param) => {
+                line.push_str(&format!("{:<width$}", mnemonic, width = MNEMONIC_WIDTH));
+                line.push_str(&self.html_operand(opcode, param, end));
+                if let Some(comment) = self.comment(opcode, param) {
+                    line.push_str(&format!(
+                        "\t<span class=\"comment\">; {}</span>",
+                        escape_html(&comment)
+                    ));
+                }
+            }
+            None => line.push_str(&mnemonic),
+        }
+        line.push('\n');
+        line
+    }
+
+    fn line(&self, opcode: &Opcode, end: usize) -> String {
+        let mut line = self.address(opcode, end);
+        let mnemonic = opcode.code.to_string();
+        match opcode.param {
+            Some(param) => {
+                line.push_str(&format!(
+                    "{:<width$}{}",
+                    mnemonic,
+                    self.operand(opcode, param, end),
+                    width = MNEMONIC_WIDTH
+                ));
+                if let Some(comment) = self.comment(opcode, param) {
+                    line.push_str(&format!("\t; {}", comment));
+                }
+            }
+            None => line.push_str(&mnemonic),
+        }
+        line.push('\n');
+        line
+    }
+}
+
+// Last opcode ends at `cod_size`
+fn labels(opcodes: &[Opcode], cod_size: usize) -> BTreeSet<usize> {
+    jump_targets(opcodes, cod_size)
+        .into_iter()
+        .map(|(_, target)| target)
+        .collect()
+}
+
+/// Lists opcodes with labels, raw cells and resolved operands.
+///
+/// ```
+/// use std::convert::TryFrom;
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amx183").unwrap();
+/// let plugin = rxxma::amx::Plugin::try_from(bytes).unwrap();
+/// let opcodes = plugin.opcodes().unwrap();
+/// let listing = rxxma::disasm::listing(&plugin, &opcodes, Default::default()).unwrap();
+/// assert!(listing.starts_with("plugin_init:\n"));
+/// ```
+pub fn listing(
+    plugin: &Plugin,
+    opcodes: &[Opcode],
+    encoding: Encoding,
+) -> Result<String, AmxError> {
+    let listing = Listing::new(plugin, opcodes, encoding)?;
+    let targets = labels(opcodes, plugin.cod_size());
+
+    let mut source = String::new();
+    for (i, opcode) in opcodes.iter().enumerate() {
+        if let Some(name) = listing.function(opcode.address) {
+            if !source.is_empty() {
+                source.push('\n');
+            }
+            source.push_str(&format!("{}:\n", name));
+        }
+        if targets.contains(&opcode.address) {
+            source.push_str(&format!("{}:\n", label(opcode.address)));
+        }
+
+        let end = opcodes
+            .get(i + 1)
+            .map_or(plugin.cod_size(), |next| next.address);
+        source.push_str(&listing.line(opcode, end));
+    }
+
+    Ok(source)
+}
+
+// Links to call sites as list item text, "never" without any
+fn call_sites(listing: &Listing, sites: &[usize]) -> String {
+    if sites.is_empty() {
+        return String::from("never");
+    }
+    sites
+        .iter()
+        .map(|&a| {
+            let location = escape_html(&listing.location(a));
+            format!("<a href=\"#{}\">{}</a>", anchor(a), location)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Renders listing as standalone HTML page: function index, natives with
+/// their call sites, precached resources and dictionaries, then function
+/// listings with jumps, calls and natives linked.
+pub fn html(
+    plugin: &Plugin,
+    opcodes: &[Opcode],
+    encoding: Encoding,
+    title: &str,
+) -> Result<String, AmxError> {
+    let listing = Listing::new(plugin, opcodes, encoding)?;
+    let targets = labels(opcodes, plugin.cod_size());
+    let publics: Vec<usize> = plugin.publics()?.iter().map(|p| p.address).collect();
+    let sites = |code: OpcodeType, param: usize| -> Vec<usize> {
+        opcodes
+            .iter()
+            .filter(|o| o.code == code && o.param == Some(param as u32))
+            .map(|o| o.address)
+            .collect()
+    };
+
+    let title = escape_html(title);
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        title, HTML_STYLE, title
+    );
+
+    page.push_str("<h2>Functions</h2>\n<ul>\n");
+    for (address, name) in listing.functions.iter() {
+        let kind = if publics.contains(address) {
+            " (public)"
+        } else {
+            ""
+        };
+        page.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a>{}</li>\n",
+            anchor(*address),
+            escape_html(name),
+            kind
+        ));
+    }
+    page.push_str("</ul>\n");
+
+    page.push_str("<h2>Natives</h2>\n<ul>\n");
+    for (index, name) in listing.natives.iter().enumerate() {
+        page.push_str(&format!(
+            "<li id=\"native_{}\">{}: {}</li>\n",
+            index,
+            escape_html(name),
+            call_sites(&listing, &sites(OP_SYSREQ_C, index))
+        ));
+    }
+    page.push_str("</ul>\n");
+
+    // Analyses decode opcodes strictly, sections are left out of lenient
+    // listings of damaged plugins
+    let resources = precached_resources(plugin).unwrap_or_default();
+    if !resources.is_empty() {
+        page.push_str("<h2>Resources</h2>\n<ul>\n");
+        for resource in resources.iter() {
+            let addresses: Vec<usize> = resource.sites.iter().map(|s| s.address).collect();
+            page.push_str(&format!(
+                "<li>{:?} {}: {}</li>\n",
+                resource.kind,
+                escape_html(&resource.path),
+                call_sites(&listing, &addresses)
+            ));
+        }
+        page.push_str("</ul>\n");
+    }
+    let dictionaries = dictionaries(plugin).unwrap_or_default();
+    if !dictionaries.files.is_empty() || !dictionaries.lang_keys.is_empty() {
+        page.push_str("<h2>Dictionaries</h2>\n<ul>\n");
+        for file in dictionaries.files.iter() {
+            page.push_str(&format!("<li>{}</li>\n", escape_html(file)));
+        }
+        for key in dictionaries.keys() {
+            let addresses: Vec<usize> = dictionaries
+                .lang_keys
+                .iter()
+                .filter(|k| k.key == key)
+                .map(|k| k.address)
+                .collect();
+            page.push_str(&format!(
+                "<li>{}: {}</li>\n",
+                escape_html(&key),
+                call_sites(&listing, &addresses)
+            ));
+        }
+        page.push_str("</ul>\n");
+    }
+
+    let mut open = false;
+    for (i, opcode) in opcodes.iter().enumerate() {
+        if let Some(name) = listing.function(opcode.address) {
+            if open {
+                page.push_str("</pre>\n");
+            }
+            page.push_str(&format!(
+                "<h2>{}</h2>\n<p>Called from: {}</p>\n<pre>\n",
+                escape_html(name),
+                call_sites(&listing, &sites(OP_CALL, opcode.address))
+            ));
+            open = true;
+        } else if !open {
+            page.push_str("<pre>\n");
+            open = true;
+        }
+        if targets.contains(&opcode.address) {
+            page.push_str(&format!("{}:\n", label(opcode.address)));
+        }
+
+        let end = opcodes
+            .get(i + 1)
+            .map_or(plugin.cod_size(), |next| next.address);
+        page.push_str(&listing.html_line(opcode, end));
+    }
+    if open {
+        page.push_str("</pre>\n");
+    }
+    page.push_str("</body>\n</html>\n");
+
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{html, listing};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+    use crate::util::Encoding;
+
+    #[test]
+    fn it_annotate_listing() {
+        let mut builder = PluginBuilder::new();
+        let print = builder.native("server_print");
+        let message = builder.string("hi");
+        builder.public("plugin_init").op(OP_PROC);
+        let check = builder.here();
+        builder
+            .op_param(OP_JZER, check + 0x20)
+            .op_param(OP_PUSH_C, message)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, print)
+            .op_param(OP_STACK, 8);
+        builder.op(OP_ZERO_PRI).op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+        let opcodes = plugin.opcodes().unwrap();
+
+        let lines: Vec<String> = listing(&plugin, &opcodes, Encoding::default())
+            .unwrap()
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+
+        assert_eq!(
+            lines,
+            [
+                "plugin_init:",
+                "0x00000008 0000002E PROC",
+                "0x0000000C 00000035 0000002C JZER l_2C",
+                "0x00000014 00000027 00000000 PUSH.C 0x0 ; \"hi\"",
+                "0x0000001C 00000027 00000004 PUSH.C 0x4",
+                "0x00000024 0000007B 00000000 SYSREQ.C server_print",
+                "l_2C:",
+                "0x0000002C 0000002C 00000008 STACK 0x8",
+                "0x00000034 00000059 ZERO.pri",
+                "0x00000038 00000030 RETN",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_link_html_listing() {
+        let mut builder = PluginBuilder::new();
+        let print = builder.native("server_print");
+        let message = builder.string("<b>");
+        builder.public("plugin_init").op(OP_PROC);
+        let call = builder.here();
+        builder.op_param(OP_CALL, 0).op(OP_ZERO_PRI).op(OP_RETN);
+        let helper = builder.here();
+        builder
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, message)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, print)
+            .op_param(OP_JUMP, helper + 4)
+            .op(OP_RETN);
+        builder.patch(call + 4, helper);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+        let opcodes = plugin.opcodes().unwrap();
+
+        let page = html(&plugin, &opcodes, Encoding::default(), "a&b.amxx").unwrap();
+
+        assert!(page.contains("<title>a&amp;b.amxx</title>"));
+        assert!(page.contains("<li><a href=\"#a_8\">plugin_init</a> (public)</li>"));
+        assert!(page
+            .contains("<li id=\"native_0\">server_print: <a href=\"#a_30\">sub_1c+0x14</a></li>"));
+        assert!(page
+            .contains("<h2>sub_1c</h2>\n<p>Called from: <a href=\"#a_C\">plugin_init+0x4</a></p>"));
+        assert!(page.contains("CALL          <a href=\"#a_1C\">sub_1c</a>"));
+        assert!(page.contains("l_20:\n<span id=\"a_20\">0x00000020</span>"));
+        assert!(page.contains("JUMP          <a href=\"#a_20\">l_20</a>"));
+        assert!(page.contains("<span class=\"comment\">; &quot;&lt;b&gt;&quot;</span>"));
+    }
+}
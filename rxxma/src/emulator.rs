@@ -0,0 +1,1024 @@
+// AMX abstract machine running plugin code outside of server, e.g. to see
+// what string backdoor builds at runtime. Natives are stubs registered by
+// user, unknown ones return 0. Every native call is traced, in tracing mode
+// with arguments decoded. Only 32 bit cells, COD and DAT are separate so
+// self-modifying code fails.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+use byteorder::{ByteOrder, LittleEndian};
+use enum_primitive::FromPrimitive;
+
+use crate::amx::OpcodeType::*;
+use crate::amx::{OpcodeType, Plugin};
+use crate::analysis::{is_conditional_jump, known_native, ParameterKind};
+use crate::error::AmxError;
+
+const CELL: i32 = 4;
+// Instructions executed before run is considered endless
+const MAX_STEPS: usize = 1_000_000;
+// Longest text guessed from argument of unknown native
+const MAX_TEXT: usize = 4096;
+// Heap and stack of larger #pragma dynamic are cut, header values are not
+// trusted with allocation size
+const MAX_HEAP: usize = 16 * 1024 * 1024;
+
+// Registers and data memory: DAT, heap growing up and stack growing down
+#[derive(Debug, Clone, PartialEq)]
+pub struct Machine {
+    pub pri: i32,
+    pub alt: i32,
+    pub frm: i32,
+    pub stk: i32,
+    pub hea: i32,
+    // Cod address of executed instruction
+    pub cip: i32,
+    // Top of stack, memory size
+    stp: i32,
+    memory: Vec<u8>,
+}
+
+impl Machine {
+    fn error(&self, reason: &'static str) -> AmxError {
+        AmxError::Emulation {
+            address: self.cip as usize,
+            reason,
+        }
+    }
+
+    // Memory index of `size` bytes at data address
+    fn index(&self, address: i32, size: usize) -> Result<usize, AmxError> {
+        let end = (address as usize).checked_add(size);
+        if address < 0 || end.is_none_or(|end| end > self.memory.len()) {
+            return Err(self.error("memory access out of bounds"));
+        }
+        Ok(address as usize)
+    }
+
+    // Address arithmetic of emulated code, overflow is its fault
+    fn offset(&self, address: i32, offset: i32) -> Result<i32, AmxError> {
+        address
+            .checked_add(offset)
+            .ok_or_else(|| self.error("address overflow"))
+    }
+
+    // Address of `index` element of `size` bytes each
+    fn element(&self, base: i32, index: i32, size: i32) -> Result<i32, AmxError> {
+        index
+            .checked_mul(size)
+            .and_then(|offset| base.checked_add(offset))
+            .ok_or_else(|| self.error("address overflow"))
+    }
+
+    // Element size of LIDX.B and IDXADDR.B shift
+    fn scale(&self, shift: i32) -> Result<i32, AmxError> {
+        1i32.checked_shl(shift as u32)
+            .ok_or_else(|| self.error("address overflow"))
+    }
+
+    // Byte count of LODB.I and STRB.I, only 1, 2 and 4 are valid
+    fn width(&self, param: i32) -> Result<usize, AmxError> {
+        match param {
+            1 | 2 | 4 => Ok(param as usize),
+            _ => Err(self.error("invalid byte width")),
+        }
+    }
+
+    pub fn read_cell(&self, address: i32) -> Result<i32, AmxError> {
+        let index = self.index(address, CELL as usize)?;
+        Ok(LittleEndian::read_i32(&self.memory[index..]))
+    }
+
+    pub fn write_cell(&mut self, address: i32, value: i32) -> Result<(), AmxError> {
+        let index = self.index(address, CELL as usize)?;
+        LittleEndian::write_i32(&mut self.memory[index..], value);
+        Ok(())
+    }
+
+    // Unpacked zero terminated string, one character per cell
+    pub fn read_string(&self, address: i32) -> Result<String, AmxError> {
+        let mut bytes = vec![];
+        let mut address = address;
+        loop {
+            match self.read_cell(address)? {
+                0 => break,
+                c => bytes.push(c as u8),
+            }
+            address = self.offset(address, CELL)?;
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    // Unpacked string of printable characters, none when address does not
+    // look like one
+    fn read_text(&self, address: i32) -> Option<String> {
+        if address % CELL != 0 {
+            return None;
+        }
+        let mut bytes = vec![];
+        for n in 0..MAX_TEXT as i32 {
+            match self.read_cell(self.element(address, n, CELL).ok()?).ok()? {
+                0 => break,
+                c @ 0x20..=0xFF | c @ 0x09..=0x0D => bytes.push(c as u8),
+                _ => return None,
+            }
+        }
+        if bytes.is_empty() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    // Unpacked string truncated to `cells` with terminator, like natives
+    // filling output buffer do. Returns number of written characters.
+    pub fn write_string(
+        &mut self,
+        address: i32,
+        text: &str,
+        cells: usize,
+    ) -> Result<usize, AmxError> {
+        let length = text.len().min(cells.saturating_sub(1));
+        for (n, byte) in text.bytes().take(length).enumerate() {
+            self.write_cell(self.element(address, n as i32, CELL)?, i32::from(byte))?;
+        }
+        self.write_cell(self.element(address, length as i32, CELL)?, 0)?;
+        Ok(length)
+    }
+
+    // Copies cells to heap, returns their address
+    pub fn allot(&mut self, cells: &[i32]) -> Result<i32, AmxError> {
+        let address = self.hea;
+        let count = i32::try_from(cells.len()).unwrap_or(i32::MAX);
+        self.hea = self.element(address, count, CELL)?;
+        if self.hea >= self.stk {
+            return Err(self.error("heap collides with stack"));
+        }
+        for (n, cell) in cells.iter().enumerate() {
+            self.write_cell(self.element(address, n as i32, CELL)?, *cell)?;
+        }
+        Ok(address)
+    }
+
+    fn push(&mut self, value: i32) -> Result<(), AmxError> {
+        self.stk -= CELL;
+        if self.stk <= self.hea {
+            return Err(self.error("stack collides with heap"));
+        }
+        self.write_cell(self.stk, value)
+    }
+
+    fn pop(&mut self) -> Result<i32, AmxError> {
+        let value = self.read_cell(self.stk)?;
+        self.stk += CELL;
+        Ok(value)
+    }
+
+    fn copy(&mut self, from: i32, to: i32, size: i32) -> Result<(), AmxError> {
+        let size = size.max(0) as usize;
+        let from = self.index(from, size)?;
+        let to = self.index(to, size)?;
+        self.memory.copy_within(from..from + size, to);
+        Ok(())
+    }
+}
+
+pub type NativeStub = Box<dyn FnMut(&mut Machine, &[i32]) -> Result<i32, AmxError>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeInvocation {
+    pub name: String,
+    // Cod address of SYSREQ
+    pub address: usize,
+    // Cells passed, strings and arrays by data address
+    pub args: Vec<i32>,
+    // Arguments read from memory at call time, when tracing
+    pub decoded: Option<Vec<DecodedArgument>>,
+    pub result: i32,
+}
+
+impl fmt::Display for NativeInvocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args: Vec<String> = match self.decoded {
+            Some(ref decoded) => decoded.iter().map(|a| a.to_string()).collect(),
+            None => self.args.iter().map(|a| format!("0x{:X}", a)).collect(),
+        };
+        write!(f, "{}({}) = {}", self.name, args.join(", "), self.result)
+    }
+}
+
+// Native argument as seen by native, guided by prototype of known native
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodedArgument {
+    Cell(i32),
+    Float(f32),
+    String(String),
+    Array(Vec<i32>),
+    // Value of cell passed by reference
+    Reference(i32),
+}
+
+impl fmt::Display for DecodedArgument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodedArgument::Cell(value) => write!(f, "{}", value),
+            DecodedArgument::Float(value) => write!(f, "{:?}", value),
+            DecodedArgument::String(text) => write!(f, "{:?}", text),
+            DecodedArgument::Array(cells) => {
+                let cells: Vec<String> = cells.iter().map(|c| c.to_string()).collect();
+                write!(f, "{{{}}}", cells.join(", "))
+            }
+            DecodedArgument::Reference(value) => write!(f, "&{}", value),
+        }
+    }
+}
+
+// Reads argument the way parameter is declared, raw cell when memory
+// does not fit. Arguments of unknown natives are texts or cells.
+fn decode_argument(
+    machine: &Machine,
+    native: &str,
+    position: usize,
+    value: i32,
+) -> DecodedArgument {
+    let known = known_native(native);
+    let kind = known.and_then(|n| n.parameter_kind(position));
+    let tag = known.and_then(|n| n.parameter_tag(position));
+    let decoded = match kind {
+        Some(ParameterKind::Value) if tag == Some("Float") => {
+            Some(DecodedArgument::Float(f32::from_bits(value as u32)))
+        }
+        Some(ParameterKind::Value) => None,
+        Some(ParameterKind::Reference) => machine
+            .read_cell(value)
+            .ok()
+            .map(DecodedArgument::Reference),
+        Some(ParameterKind::Array(size)) => (0..size as i32)
+            .map(|n| machine.read_cell(machine.element(value, n, CELL)?))
+            .collect::<Result<Vec<i32>, AmxError>>()
+            .ok()
+            .map(DecodedArgument::Array),
+        Some(ParameterKind::String) => machine.read_string(value).ok().map(DecodedArgument::String),
+        Some(ParameterKind::Variadic) => machine
+            .read_text(value)
+            .map(DecodedArgument::String)
+            .or_else(|| {
+                machine
+                    .read_cell(value)
+                    .ok()
+                    .map(DecodedArgument::Reference)
+            }),
+        None => machine.read_text(value).map(DecodedArgument::String),
+    };
+    decoded.unwrap_or(DecodedArgument::Cell(value))
+}
+
+// Argument of called public, strings and arrays are copied to heap and
+// passed by address
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    Cell(i32),
+    String(String),
+    Array(Vec<i32>),
+}
+
+pub struct Emulator {
+    pub machine: Machine,
+    cod: Vec<i32>,
+    // Header COD to DAT distance, LCTRL 1 value
+    dat: i32,
+    natives: Vec<String>,
+    publics: Vec<(String, usize)>,
+    stubs: HashMap<String, NativeStub>,
+    // Native calls of every run, in order
+    pub trace: Vec<NativeInvocation>,
+    // Decode native arguments into trace
+    tracing: bool,
+    pub max_steps: usize,
+}
+
+// Quotient and remainder rounded towards minus infinity like Pawn does
+fn floored_div(dividend: i32, divisor: i32) -> Option<(i32, i32)> {
+    if divisor == 0 {
+        return None;
+    }
+    let mut quotient = dividend.wrapping_div(divisor);
+    let mut remainder = dividend.wrapping_rem(divisor);
+    if remainder != 0 && (remainder < 0) != (divisor < 0) {
+        quotient -= 1;
+        remainder += divisor;
+    }
+    Some((quotient, remainder))
+}
+
+// Whether conditional jump `code` is taken for given PRI and ALT
+fn is_taken(code: OpcodeType, pri: i32, alt: i32) -> bool {
+    let (upri, ualt) = (pri as u32, alt as u32);
+    match code {
+        OP_JZER => pri == 0,
+        OP_JNZ => pri != 0,
+        OP_JEQ => pri == alt,
+        OP_JNEQ => pri != alt,
+        OP_JLESS => upri < ualt,
+        OP_JLEQ => upri <= ualt,
+        OP_JGRTR => upri > ualt,
+        OP_JGEQ => upri >= ualt,
+        OP_JSLESS => pri < alt,
+        OP_JSLEQ => pri <= alt,
+        OP_JSGRTR => pri > alt,
+        OP_JSGEQ => pri >= alt,
+        _ => false,
+    }
+}
+
+impl Emulator {
+    pub fn new(plugin: &Plugin) -> Result<Emulator, AmxError> {
+        if plugin.cellsize() != CELL as usize {
+            return Err(AmxError::Unsupported("emulation of 64 bit plugins"));
+        }
+
+        let cod = plugin
+            .cod_slice()?
+            .chunks_exact(CELL as usize)
+            .map(LittleEndian::read_i32)
+            .collect();
+        let mut memory = plugin.dat_slice()?.to_vec();
+        memory.resize(memory.len() + plugin.heap_budget().min(MAX_HEAP), 0);
+        let hea = plugin.dat_slice()?.len() as i32;
+        let stp = i32::try_from(memory.len())
+            .map_err(|_| AmxError::Unsupported("emulation of DAT over 2 GB"))?;
+
+        Ok(Emulator {
+            machine: Machine {
+                pri: 0,
+                alt: 0,
+                frm: 0,
+                stk: stp,
+                hea,
+                cip: 0,
+                stp,
+                memory,
+            },
+            cod,
+            dat: plugin.cod_size() as i32,
+            natives: plugin
+                .natives()?
+                .iter()
+                .map(|n| n.name.to_string_lossy().into_owned())
+                .collect(),
+            publics: plugin
+                .publics()?
+                .iter()
+                .map(|p| (p.name.to_string_lossy().into_owned(), p.address))
+                .collect(),
+            stubs: HashMap::new(),
+            trace: vec![],
+            tracing: false,
+            max_steps: MAX_STEPS,
+        })
+    }
+
+    // Replaces native, its return value goes to PRI
+    pub fn native<F>(&mut self, name: &str, stub: F) -> &mut Self
+    where
+        F: FnMut(&mut Machine, &[i32]) -> Result<i32, AmxError> + 'static,
+    {
+        self.stubs.insert(name.to_owned(), Box::new(stub));
+        self
+    }
+
+    // Reads strings, arrays and references passed to natives while they
+    // are alive, see `call_log`
+    pub fn tracing(&mut self, enabled: bool) -> &mut Self {
+        self.tracing = enabled;
+        self
+    }
+
+    // Traced native calls, one per line with cod address
+    pub fn call_log(&self) -> String {
+        self.trace
+            .iter()
+            .map(|n| format!("0x{:X}\t{}\n", n.address, n))
+            .collect()
+    }
+
+    /// Runs public like amx_Exec does and returns its value. Globals keep
+    /// values between calls, heap and stack are reset.
+    ///
+    /// ```
+    /// use rxxma::emulator::Emulator;
+    ///
+    /// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+    /// let plugin = rxxma::facade::load_plugin(&bytes).unwrap();
+    /// let mut emulator = Emulator::new(&plugin).unwrap();
+    /// emulator.call("plugin_init", &[]).unwrap();
+    /// assert_eq!(emulator.trace[0].name, "register_plugin");
+    /// ```
+    pub fn call(&mut self, public: &str, args: &[Argument]) -> Result<i32, AmxError> {
+        let address = match self.publics.iter().find(|p| p.0 == public) {
+            Some(&(_, address)) => address,
+            None => return Err(AmxError::NoFunction(public.to_owned())),
+        };
+        self.call_address(address, args)
+    }
+
+    // Like `call` for function at cod address, e.g. main() or private one
+    pub fn call_address(&mut self, address: usize, args: &[Argument]) -> Result<i32, AmxError> {
+        let hea = self.machine.hea;
+        let result = self.enter(address, args).and_then(|_| self.run());
+        self.machine.hea = hea;
+        self.machine.stk = self.machine.stp;
+        result
+    }
+
+    fn enter(&mut self, address: usize, args: &[Argument]) -> Result<(), AmxError> {
+        let mut cells = vec![];
+        for arg in args.iter() {
+            let cell = match arg {
+                Argument::Cell(value) => *value,
+                Argument::String(text) => {
+                    let mut string: Vec<i32> = text.bytes().map(i32::from).collect();
+                    string.push(0);
+                    self.machine.allot(&string)?
+                }
+                Argument::Array(array) => self.machine.allot(array)?,
+            };
+            cells.push(cell);
+        }
+
+        for cell in cells.iter().rev() {
+            self.machine.push(*cell)?;
+        }
+        let count = i32::try_from(cells.len()).unwrap_or(i32::MAX);
+        let size = self.machine.element(0, count, CELL)?;
+        self.machine.push(size)?;
+        // Returning to HALT 0 compiler places at cod start
+        self.machine.push(0)?;
+        self.machine.cip = address as i32;
+        Ok(())
+    }
+
+    fn run(&mut self) -> Result<i32, AmxError> {
+        for _ in 0..self.max_steps {
+            if let Some(value) = self.step()? {
+                return Ok(value);
+            }
+        }
+        Err(self.machine.error("step limit reached"))
+    }
+
+    fn cod_cell(&self, address: i32) -> Result<i32, AmxError> {
+        if address < 0 || address % CELL != 0 {
+            return Err(self.machine.error("cip outside of cod"));
+        }
+        self.cod
+            .get((address / CELL) as usize)
+            .cloned()
+            .ok_or_else(|| self.machine.error("cip outside of cod"))
+    }
+
+    fn sysreq(&mut self, index: i32) -> Result<(), AmxError> {
+        let m = &mut self.machine;
+        let name = match self.natives.get(index as usize) {
+            Some(name) => name.clone(),
+            None => return Err(m.error("native index out of bounds")),
+        };
+        let count = m.read_cell(m.stk)? / CELL;
+        let args = (1..=count)
+            .map(|n| m.read_cell(m.element(m.stk, n, CELL)?))
+            .collect::<Result<Vec<i32>, AmxError>>()?;
+        // Before stub fills output buffers
+        let decoded = if self.tracing {
+            let args = args.iter().enumerate();
+            Some(
+                args.map(|(n, a)| decode_argument(m, &name, n, *a))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        m.pri = match self.stubs.get_mut(&name) {
+            Some(stub) => stub(m, &args)?,
+            None => 0,
+        };
+        self.trace.push(NativeInvocation {
+            name,
+            address: m.cip as usize,
+            args,
+            decoded,
+            result: m.pri,
+        });
+        Ok(())
+    }
+
+    fn switch(&mut self, casetbl: i32) -> Result<i32, AmxError> {
+        let m = &self.machine;
+        let count = self.cod_cell(m.element(casetbl, 1, CELL)?)?;
+        let mut target = self.cod_cell(m.element(casetbl, 2, CELL)?)?;
+        let records = m.element(casetbl, 3, CELL)?;
+        for n in 0..count {
+            let record = m.element(records, n, 2 * CELL)?;
+            if self.cod_cell(record)? == self.machine.pri {
+                target = self.cod_cell(m.offset(record, CELL)?)?;
+                break;
+            }
+        }
+        Ok(target)
+    }
+
+    // Executes instruction at cip, value of PRI once halted
+    fn step(&mut self) -> Result<Option<i32>, AmxError> {
+        let cip = self.machine.cip;
+        let code = match OpcodeType::from_i32(self.cod_cell(cip)?) {
+            Some(code) if !code.is_pseudo() => code,
+            _ => return Err(self.machine.error("invalid opcode")),
+        };
+        let param = if code.params() > 0 {
+            self.cod_cell(cip + CELL)?
+        } else {
+            0
+        };
+        let mut next = cip + (1 + code.params() as i32) * CELL;
+
+        let m = &mut self.machine;
+        let (pri, alt) = (m.pri, m.alt);
+        match code {
+            OP_LOAD_PRI => m.pri = m.read_cell(param)?,
+            OP_LOAD_ALT => m.alt = m.read_cell(param)?,
+            OP_LOAD_S_PRI => m.pri = m.read_cell(m.offset(m.frm, param)?)?,
+            OP_LOAD_S_ALT => m.alt = m.read_cell(m.offset(m.frm, param)?)?,
+            OP_LREF_PRI => m.pri = m.read_cell(m.read_cell(param)?)?,
+            OP_LREF_ALT => m.alt = m.read_cell(m.read_cell(param)?)?,
+            OP_LREF_S_PRI => m.pri = m.read_cell(m.read_cell(m.offset(m.frm, param)?)?)?,
+            OP_LREF_S_ALT => m.alt = m.read_cell(m.read_cell(m.offset(m.frm, param)?)?)?,
+            OP_LOAD_I => m.pri = m.read_cell(pri)?,
+            OP_LODB_I => {
+                let width = m.width(param)?;
+                let index = m.index(pri, width)?;
+                let bytes = &m.memory[index..index + width];
+                m.pri = match width {
+                    1 => i32::from(bytes[0]),
+                    2 => i32::from(LittleEndian::read_u16(bytes)),
+                    _ => LittleEndian::read_i32(bytes),
+                };
+            }
+            OP_CONST_PRI => m.pri = param,
+            OP_CONST_ALT => m.alt = param,
+            OP_ADDR_PRI => m.pri = m.offset(m.frm, param)?,
+            OP_ADDR_ALT => m.alt = m.offset(m.frm, param)?,
+            OP_STOR_PRI => m.write_cell(param, pri)?,
+            OP_STOR_ALT => m.write_cell(param, alt)?,
+            OP_STOR_S_PRI => m.write_cell(m.offset(m.frm, param)?, pri)?,
+            OP_STOR_S_ALT => m.write_cell(m.offset(m.frm, param)?, alt)?,
+            OP_SREF_PRI => m.write_cell(m.read_cell(param)?, pri)?,
+            OP_SREF_ALT => m.write_cell(m.read_cell(param)?, alt)?,
+            OP_SREF_S_PRI => m.write_cell(m.read_cell(m.offset(m.frm, param)?)?, pri)?,
+            OP_SREF_S_ALT => m.write_cell(m.read_cell(m.offset(m.frm, param)?)?, alt)?,
+            OP_STOR_I => m.write_cell(alt, pri)?,
+            OP_STRB_I => {
+                let width = m.width(param)?;
+                let index = m.index(alt, width)?;
+                m.memory[index..index + width].copy_from_slice(&pri.to_le_bytes()[..width]);
+            }
+            OP_LIDX => m.pri = m.read_cell(m.element(alt, pri, CELL)?)?,
+            OP_LIDX_B => m.pri = m.read_cell(m.element(alt, pri, m.scale(param)?)?)?,
+            OP_IDXADDR => m.pri = m.element(alt, pri, CELL)?,
+            OP_IDXADDR_B => m.pri = m.element(alt, pri, m.scale(param)?)?,
+            OP_ALIGN_PRI if param < CELL => m.pri ^= CELL - param,
+            OP_ALIGN_ALT if param < CELL => m.alt ^= CELL - param,
+            OP_ALIGN_PRI | OP_ALIGN_ALT => {}
+            OP_LCTRL => {
+                m.pri = match param {
+                    0 => 0,
+                    1 => self.dat,
+                    2 => m.hea,
+                    3 => m.stp,
+                    4 => m.stk,
+                    5 => m.frm,
+                    6 => cip,
+                    _ => return Err(m.error("invalid LCTRL register")),
+                }
+            }
+            OP_SCTRL => match param {
+                2 => m.hea = pri,
+                4 => m.stk = pri,
+                5 => m.frm = pri,
+                6 => next = pri,
+                _ => return Err(m.error("invalid SCTRL register")),
+            },
+            OP_MOVE_PRI => m.pri = alt,
+            OP_MOVE_ALT => m.alt = pri,
+            OP_XCHG => {
+                m.pri = alt;
+                m.alt = pri;
+            }
+            OP_PUSH_PRI => m.push(pri)?,
+            OP_PUSH_ALT => m.push(alt)?,
+            OP_PUSH_R => {
+                for _ in 0..param {
+                    m.push(pri)?;
+                }
+            }
+            OP_PUSH_C => m.push(param)?,
+            OP_PUSH => {
+                let value = m.read_cell(param)?;
+                m.push(value)?;
+            }
+            OP_PUSH_S => {
+                let value = m.read_cell(m.offset(m.frm, param)?)?;
+                m.push(value)?;
+            }
+            OP_PUSHADDR => m.push(m.offset(m.frm, param)?)?,
+            OP_POP_PRI => m.pri = m.pop()?,
+            OP_POP_ALT => m.alt = m.pop()?,
+            OP_STACK => {
+                m.alt = m.stk;
+                m.stk = m.offset(m.stk, param)?;
+                if m.stk <= m.hea || m.stk > m.stp {
+                    return Err(m.error("stack out of bounds"));
+                }
+            }
+            OP_HEAP => {
+                m.alt = m.hea;
+                m.hea = m.offset(m.hea, param)?;
+                if m.hea >= m.stk {
+                    return Err(m.error("heap collides with stack"));
+                }
+            }
+            OP_PROC => {
+                m.push(m.frm)?;
+                m.frm = m.stk;
+            }
+            OP_RET => {
+                m.frm = m.pop()?;
+                next = m.pop()?;
+            }
+            OP_RETN => {
+                m.frm = m.pop()?;
+                next = m.pop()?;
+                let size = m.read_cell(m.stk)?;
+                m.stk = m.offset(m.offset(m.stk, size)?, CELL)?;
+            }
+            OP_CALL => {
+                m.push(next)?;
+                next = param;
+            }
+            OP_CALL_PRI => {
+                m.push(next)?;
+                next = pri;
+            }
+            OP_JUMP => next = param,
+            OP_JREL => next = m.offset(next, param)?,
+            OP_JUMP_PRI => next = pri,
+            code if is_conditional_jump(code) => {
+                if is_taken(code, pri, alt) {
+                    next = param;
+                }
+            }
+            OP_SHL => m.pri = pri.wrapping_shl(alt as u32),
+            OP_SHR => m.pri = (pri as u32).wrapping_shr(alt as u32) as i32,
+            OP_SSHR => m.pri = pri.wrapping_shr(alt as u32),
+            OP_SHL_C_PRI => m.pri = pri.wrapping_shl(param as u32),
+            OP_SHL_C_ALT => m.alt = alt.wrapping_shl(param as u32),
+            OP_SHR_C_PRI => m.pri = (pri as u32).wrapping_shr(param as u32) as i32,
+            OP_SHR_C_ALT => m.alt = (alt as u32).wrapping_shr(param as u32) as i32,
+            OP_SMUL => m.pri = pri.wrapping_mul(alt),
+            OP_UMUL => m.pri = (pri as u32).wrapping_mul(alt as u32) as i32,
+            OP_SDIV | OP_SDIV_ALT => {
+                let (dividend, divisor) = if code == OP_SDIV {
+                    (pri, alt)
+                } else {
+                    (alt, pri)
+                };
+                let (quotient, remainder) =
+                    floored_div(dividend, divisor).ok_or_else(|| m.error("division by zero"))?;
+                m.pri = quotient;
+                m.alt = remainder;
+            }
+            OP_UDIV | OP_UDIV_ALT => {
+                let (dividend, divisor) = if code == OP_UDIV {
+                    (pri, alt)
+                } else {
+                    (alt, pri)
+                };
+                if divisor == 0 {
+                    return Err(m.error("division by zero"));
+                }
+                m.pri = ((dividend as u32) / (divisor as u32)) as i32;
+                m.alt = ((dividend as u32) % (divisor as u32)) as i32;
+            }
+            OP_ADD => m.pri = pri.wrapping_add(alt),
+            OP_SUB => m.pri = pri.wrapping_sub(alt),
+            OP_SUB_ALT => m.pri = alt.wrapping_sub(pri),
+            OP_AND => m.pri = pri & alt,
+            OP_OR => m.pri = pri | alt,
+            OP_XOR => m.pri = pri ^ alt,
+            OP_NOT => m.pri = i32::from(pri == 0),
+            OP_NEG => m.pri = pri.wrapping_neg(),
+            OP_INVERT => m.pri = !pri,
+            OP_ADD_C => m.pri = pri.wrapping_add(param),
+            OP_SMUL_C => m.pri = pri.wrapping_mul(param),
+            OP_ZERO_PRI => m.pri = 0,
+            OP_ZERO_ALT => m.alt = 0,
+            OP_ZERO => m.write_cell(param, 0)?,
+            OP_ZERO_S => m.write_cell(m.offset(m.frm, param)?, 0)?,
+            OP_SIGN_PRI => m.pri = i32::from(pri as i8),
+            OP_SIGN_ALT => m.alt = i32::from(alt as i8),
+            OP_EQ => m.pri = i32::from(pri == alt),
+            OP_NEQ => m.pri = i32::from(pri != alt),
+            OP_LESS => m.pri = i32::from((pri as u32) < (alt as u32)),
+            OP_LEQ => m.pri = i32::from((pri as u32) <= (alt as u32)),
+            OP_GRTR => m.pri = i32::from((pri as u32) > (alt as u32)),
+            OP_GEQ => m.pri = i32::from((pri as u32) >= (alt as u32)),
+            OP_SLESS => m.pri = i32::from(pri < alt),
+            OP_SLEQ => m.pri = i32::from(pri <= alt),
+            OP_SGRTR => m.pri = i32::from(pri > alt),
+            OP_SGEQ => m.pri = i32::from(pri >= alt),
+            OP_EQ_C_PRI => m.pri = i32::from(pri == param),
+            OP_EQ_C_ALT => m.pri = i32::from(alt == param),
+            OP_INC_PRI => m.pri = pri.wrapping_add(1),
+            OP_INC_ALT => m.alt = alt.wrapping_add(1),
+            OP_DEC_PRI => m.pri = pri.wrapping_sub(1),
+            OP_DEC_ALT => m.alt = alt.wrapping_sub(1),
+            OP_INC | OP_INC_S | OP_INC_I | OP_DEC | OP_DEC_S | OP_DEC_I => {
+                let address = match code {
+                    OP_INC | OP_DEC => param,
+                    OP_INC_S | OP_DEC_S => m.offset(m.frm, param)?,
+                    _ => pri,
+                };
+                let delta = if matches!(code, OP_INC | OP_INC_S | OP_INC_I) {
+                    1
+                } else {
+                    -1
+                };
+                let value = m.read_cell(address)?.wrapping_add(delta);
+                m.write_cell(address, value)?;
+            }
+            OP_MOVS => m.copy(pri, alt, param)?,
+            OP_CMPS => {
+                let size = param.max(0) as usize;
+                let (left, right) = (m.index(pri, size)?, m.index(alt, size)?);
+                let left = &m.memory[left..left + size];
+                let right = &m.memory[right..right + size];
+                m.pri = left
+                    .iter()
+                    .zip(right)
+                    .find(|(l, r)| l != r)
+                    .map_or(0, |(l, r)| i32::from(*l) - i32::from(*r));
+            }
+            OP_FILL => {
+                for n in 0..param / CELL {
+                    m.write_cell(m.element(alt, n, CELL)?, pri)?;
+                }
+            }
+            OP_HALT if param == 0 => return Ok(Some(pri)),
+            OP_HALT => return Err(AmxError::Halted(param as u32)),
+            OP_BOUNDS if pri as u32 > param as u32 => {
+                return Err(m.error("array index out of bounds"))
+            }
+            OP_BOUNDS => {}
+            OP_SYSREQ_PRI => self.sysreq(pri)?,
+            OP_SYSREQ_C => self.sysreq(param)?,
+            OP_SYSREQ_D => return Err(m.error("SYSREQ.D natives are not known by index")),
+            OP_SWITCH => next = self.switch(param)?,
+            OP_SWAP_PRI => {
+                m.pri = m.read_cell(m.stk)?;
+                m.write_cell(m.stk, pri)?;
+            }
+            OP_SWAP_ALT => {
+                m.alt = m.read_cell(m.stk)?;
+                m.write_cell(m.stk, alt)?;
+            }
+            OP_FILE | OP_SYMBOL => next = m.offset(next, param)?,
+            OP_LINE | OP_SRANGE | OP_SYMTAG | OP_NOP | OP_BREAK => {}
+            OP_NONE | OP_CASETBL => return Err(m.error("invalid opcode")),
+            // Case table records and unknown cells are never executed,
+            // conditional jumps are all handled above
+            _ => unreachable!(),
+        }
+
+        self.machine.cip = next;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::convert::TryFrom;
+    use std::rc::Rc;
+
+    use super::{floored_div, Argument, DecodedArgument, Emulator, MAX_HEAP};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::error::AmxError;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_divide_like_pawn() {
+        assert_eq!(floored_div(7, 2), Some((3, 1)));
+        assert_eq!(floored_div(-7, 2), Some((-4, 1)));
+        assert_eq!(floored_div(7, -2), Some((-4, -1)));
+        assert_eq!(floored_div(1, 0), None);
+    }
+
+    #[test]
+    fn it_call_public_with_arguments() {
+        let mut builder = PluginBuilder::new();
+        builder
+            .public("sum")
+            .op(OP_PROC)
+            .op_param(OP_LOAD_S_PRI, 0xC)
+            .op_param(OP_LOAD_S_ALT, 0x10)
+            .op(OP_ADD)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut emulator = Emulator::new(&plugin).unwrap();
+        let args = [Argument::Cell(2), Argument::Cell(40)];
+        assert_eq!(emulator.call("sum", &args), Ok(42));
+        // Stack is balanced, second call sees the same frame
+        assert_eq!(emulator.call("sum", &args), Ok(42));
+        assert_eq!(
+            emulator.call("missing", &[]),
+            Err(AmxError::NoFunction("missing".to_owned()))
+        );
+    }
+
+    #[test]
+    fn it_stub_and_trace_natives() {
+        let mut builder = PluginBuilder::new();
+        let server_cmd = builder.native("server_cmd");
+        let log_amx = builder.native("log_amx");
+        builder.public("backdoor").op(OP_PROC);
+        for native in [server_cmd, log_amx].iter() {
+            builder
+                .op_param(OP_PUSH_S, 0xC)
+                .op_param(OP_PUSH_C, 4)
+                .op_param(OP_SYSREQ_C, *native)
+                .op_param(OP_STACK, 8);
+        }
+        builder.op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let commands = Rc::new(RefCell::new(vec![]));
+        let seen = commands.clone();
+        let mut emulator = Emulator::new(&plugin).unwrap();
+        emulator.native("server_cmd", move |machine, args| {
+            seen.borrow_mut().push(machine.read_string(args[0])?);
+            Ok(1)
+        });
+
+        // log_amx is not stubbed and returns 0 into PRI
+        let result = emulator.call("backdoor", &[Argument::String("quit".to_owned())]);
+        assert_eq!(result, Ok(0));
+        assert_eq!(*commands.borrow(), ["quit"]);
+        let trace: Vec<(&str, i32)> = emulator
+            .trace
+            .iter()
+            .map(|n| (n.name.as_str(), n.result))
+            .collect();
+        assert_eq!(trace, [("server_cmd", 1), ("log_amx", 0)]);
+        assert_eq!(emulator.trace[0].args, emulator.trace[1].args);
+    }
+
+    #[test]
+    fn it_decode_traced_arguments() {
+        let mut builder = PluginBuilder::new();
+        let set_task = builder.native("set_task");
+        let client_print = builder.native("client_print");
+        let hidden = builder.native("hidden_native");
+        let function = builder.string("check");
+        let message = builder.string("%s has %d");
+        let name = builder.string("player");
+        let count = builder.array(&[7]);
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, function)
+            .op_param(OP_PUSH_C, 1.5f32.to_bits())
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, set_task)
+            .op_param(OP_STACK, 12)
+            .op_param(OP_PUSH_C, count)
+            .op_param(OP_PUSH_C, name)
+            .op_param(OP_PUSH_C, message)
+            .op_param(OP_PUSH_C, 2)
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_PUSH_C, 20)
+            .op_param(OP_SYSREQ_C, client_print)
+            .op_param(OP_STACK, 24)
+            .op_param(OP_PUSH_C, name)
+            .op_param(OP_PUSH_C, 3)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, hidden)
+            .op_param(OP_STACK, 12)
+            .op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut emulator = Emulator::new(&plugin).unwrap();
+        emulator.tracing(true).call("plugin_init", &[]).unwrap();
+
+        assert_eq!(
+            emulator.trace[0].decoded,
+            Some(vec![
+                DecodedArgument::Float(1.5),
+                DecodedArgument::String("check".to_owned())
+            ])
+        );
+        let log: Vec<String> = emulator.trace.iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            log,
+            [
+                "set_task(1.5, \"check\") = 0",
+                "client_print(0, 2, \"%s has %d\", \"player\", &7) = 0",
+                "hidden_native(3, \"player\") = 0",
+            ]
+        );
+        assert!(emulator.call_log().starts_with("0x"));
+    }
+
+    #[test]
+    fn it_err_on_address_overflow() {
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init").op(OP_PROC);
+        let stack = builder.here();
+        builder.op_param(OP_STACK, 0x7FFF_FFFF).op(OP_RETN);
+        builder.public("index").op(OP_PROC);
+        let index = builder.here();
+        builder
+            .op_param(OP_CONST_PRI, 0x4000_0000)
+            .op(OP_LIDX)
+            .op(OP_RETN);
+        let mut bin = builder.build();
+        // stp far beyond anything worth allocating
+        bin[24..28].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        let plugin = Plugin::try_from(bin).unwrap();
+
+        let mut emulator = Emulator::new(&plugin).unwrap();
+        assert!(emulator.machine.memory.len() <= plugin.dat_slice().unwrap().len() + MAX_HEAP);
+        assert_eq!(
+            emulator.call("plugin_init", &[]),
+            Err(AmxError::Emulation {
+                address: stack as usize,
+                reason: "address overflow"
+            })
+        );
+        assert_eq!(
+            emulator.call("index", &[]),
+            Err(AmxError::Emulation {
+                address: index as usize + 8,
+                reason: "address overflow"
+            })
+        );
+    }
+
+    #[test]
+    fn it_stop_endless_loop() {
+        let mut builder = PluginBuilder::new();
+        builder.public("plugin_init").op(OP_PROC);
+        let start = builder.here();
+        builder.op_param(OP_JUMP, start).op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut emulator = Emulator::new(&plugin).unwrap();
+        emulator.max_steps = 100;
+        assert_eq!(
+            emulator.call("plugin_init", &[]),
+            Err(AmxError::Emulation {
+                address: start as usize,
+                reason: "step limit reached"
+            })
+        );
+    }
+
+    #[test]
+    fn it_err_on_invalid_byte_width() {
+        let mut builder = PluginBuilder::new();
+        builder.public("load").op(OP_PROC);
+        let load = builder.here();
+        builder.op_param(OP_LODB_I, 3).op(OP_RETN);
+        builder.public("store").op(OP_PROC);
+        let store = builder.here();
+        builder.op_param(OP_STRB_I, 0).op(OP_RETN);
+        let plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let mut emulator = Emulator::new(&plugin).unwrap();
+        assert_eq!(
+            emulator.call("load", &[]),
+            Err(AmxError::Emulation {
+                address: load as usize,
+                reason: "invalid byte width"
+            })
+        );
+        assert_eq!(
+            emulator.call("store", &[]),
+            Err(AmxError::Emulation {
+                address: store as usize,
+                reason: "invalid byte width"
+            })
+        );
+    }
+}
@@ -0,0 +1,135 @@
+// Errors of reading, writing, unpacking and decompiling plugins. Offsets
+// are file offsets into amxx container, amx image or debug chunk being read.
+
+use std::io;
+use std::io::{Cursor, Read};
+
+use crate::amx::OpcodeType;
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum AmxError {
+    #[fail(display = "Unknown file format, neither amxx nor amx")]
+    UnknownFormat,
+    #[fail(display = "Invalid file magic, expected: 0x{:X}, got: 0x{:X}", _0, _1)]
+    InvalidFileMagic(u32, u32),
+    #[fail(display = "Incompatible file version, expected: {}, got: {}", _0, _1)]
+    IncompatibleFileVersion(u16, u16),
+    #[fail(display = "Zero sections amount")]
+    ZeroSections,
+    #[fail(display = "More than two sections (malicious file?)")]
+    TooManySections(u8),
+    #[fail(display = "File has no {} bit sections", _0)]
+    NoSection(u32),
+    #[fail(display = "File already has {} bit section", _0)]
+    DuplicateSection(u32),
+    #[fail(display = "Invalid section cellsize, must be 4 or 8, got: {}", _0)]
+    InvalidCellSize(u8),
+    #[fail(display = "Unable to unpack section: {}", _0)]
+    Unpack(String),
+    #[fail(display = "imagesize does not match section unpacked contents")]
+    ImageSizeMismatch,
+    #[fail(display = "Invalid amx magic, expected: 0x{:X}, got: 0x{:X}", _0, _1)]
+    InvalidAmxMagic(u16, u16),
+    #[fail(display = "Invalid file version, expected: {}, got: {}", _0, _1)]
+    InvalidAmxFileVersion(u8, u8),
+    #[fail(display = "Invalid amx version, expected: {}, got: {}", _0, _1)]
+    InvalidAmxVersion(u8, u8),
+    #[fail(
+        display = "Invalid bit value for amx flags (contains unknown flags) {}",
+        _0
+    )]
+    InvalidAmxFlags(u16),
+    #[fail(display = "Invalid defsize {}, expected 8, 16, 24 or 28", _0)]
+    InvalidDefsize(u16),
+    #[fail(display = "Invalid debug magic, expected: 0x{:X}, got: 0x{:X}", _0, _1)]
+    InvalidDebugMagic(u16, u16),
+    // Input ends in the middle of `what`
+    #[fail(display = "EOF on {} at 0x{:X}", what, offset)]
+    Eof { what: &'static str, offset: usize },
+    // Header or table points outside of image or its part
+    #[fail(display = "{} at 0x{:X}", reason, offset)]
+    Malformed { reason: &'static str, offset: usize },
+    #[fail(display = "Undecodable opcode at 0x{:X}: {}", offset, reason)]
+    InvalidOpcode { offset: usize, reason: &'static str },
+    #[fail(
+        display = "Unable to assemble {} at cod 0x{:X}, its operands are not kept",
+        code, address
+    )]
+    Unassemblable { code: OpcodeType, address: usize },
+    #[fail(display = "No code base fits relocated operands")]
+    NoCodeBase,
+    #[fail(display = "Code base is ambiguous, several candidates fit relocated operands")]
+    AmbiguousCodeBase,
+    #[fail(display = "Invalid pattern token {:?}: {}", _0, _1)]
+    InvalidPattern(String, &'static str),
+    #[fail(display = "Invalid rule at line {}: {}", _0, _1)]
+    InvalidRule(usize, &'static str),
+    #[fail(display = "Unable to decompile: {}", _0)]
+    Decompile(&'static str),
+    // Compiler output of failed compilation
+    #[fail(display = "Unable to compile decompiled source: {}", _0)]
+    Compile(String),
+    #[fail(display = "No function {} in plugin", _0)]
+    NoFunction(String),
+    #[fail(display = "No pass {} in pipeline", _0)]
+    NoPass(String),
+    #[fail(display = "Emulation stopped at cod 0x{:X}: {}", address, reason)]
+    Emulation {
+        address: usize,
+        reason: &'static str,
+    },
+    #[fail(display = "Plugin halted with error code {}", _0)]
+    Halted(u32),
+    #[fail(display = "Unsupported: {}", _0)]
+    Unsupported(&'static str),
+    #[fail(display = "Invalid archive: {}", _0)]
+    InvalidArchive(String),
+    #[fail(display = "{}", _0)]
+    Io(String),
+}
+
+impl From<io::Error> for AmxError {
+    fn from(error: io::Error) -> Self {
+        AmxError::Io(error.to_string())
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl From<serde_json::Error> for AmxError {
+    fn from(error: serde_json::Error) -> Self {
+        AmxError::Io(error.to_string())
+    }
+}
+
+// Reads `what` at cursor position, EOF reports offset reading started at
+pub(crate) fn read_at<T, R, F>(
+    reader: &mut Cursor<R>,
+    what: &'static str,
+    read: F,
+) -> Result<T, AmxError>
+where
+    F: FnOnce(&mut Cursor<R>) -> io::Result<T>,
+{
+    let offset = reader.position() as usize;
+    read(reader).map_err(|_| AmxError::Eof { what, offset })
+}
+
+// Appends bytes read from stream until `bin` is `len` long, EOF reports
+// offset appending started at
+pub(crate) fn read_to<R: Read>(
+    reader: &mut R,
+    bin: &mut Vec<u8>,
+    len: usize,
+    what: &'static str,
+) -> Result<(), AmxError> {
+    let offset = bin.len();
+    if len <= offset {
+        return Ok(());
+    }
+    // Grows with data actually read, sizes come from untrusted headers
+    reader.take((len - offset) as u64).read_to_end(bin)?;
+    match bin.len() == len {
+        true => Ok(()),
+        false => Err(AmxError::Eof { what, offset }),
+    }
+}
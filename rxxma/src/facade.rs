@@ -0,0 +1,596 @@
+// One call entry points running whole File -> Section -> Plugin -> AST
+// pipeline on raw file contents.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Read;
+
+use byteorder::{ByteOrder, LittleEndian};
+use flate2::read::GzDecoder;
+use log::trace;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::amx::plugin::AMXMOD_MAGIC;
+use crate::amx::OpcodeType::*;
+#[cfg(feature = "serde")]
+use crate::amx::{Native, PubVar, Public, Tag};
+use crate::amx::{Opcode, OpcodeType, Plugin};
+use crate::amxx::{File, GZIP_MAGIC, LEGACY_MAGIC, MAGIC};
+use crate::analysis::{
+    dictionaries, functions, heap_usage, precached_resources, Function, Metadata,
+};
+use crate::ast::{Decompiler, FunctionRef, Plugin as AstPlugin, Style};
+use crate::disasm;
+use crate::error::AmxError;
+use crate::report;
+use crate::sourcepawn::{SmxFile, SMX_MAGIC};
+use crate::stocks::StockDatabase;
+use crate::util::float::{float_constant, float_literal};
+use crate::util::Encoding;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Format {
+    // Compressed amxmodx container (.amxx)
+    Amxx,
+    // Raw amx image (.amx)
+    Amx,
+    // Gzip compressed amx image, AMX Mod era
+    GzipAmx,
+    // SourcePawn plugin (.smx), only inspected and validated
+    Smx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecompileOptions {
+    // Encoding of DAT strings
+    pub encoding: Encoding,
+    // Layout of decompiled source
+    pub style: Style,
+    // Decode junk cells as UNKNOWN opcodes instead of failing
+    pub lenient: bool,
+    // Section of amxx container to use, 4 or 8
+    pub cellsize: u8,
+    // Undo junk opcodes and opaque predicates of obfuscators
+    pub deobfuscate: bool,
+}
+
+impl Default for DecompileOptions {
+    fn default() -> Self {
+        DecompileOptions {
+            encoding: Encoding::default(),
+            style: Style::default(),
+            lenient: false,
+            cellsize: 4,
+            deobfuscate: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SectionInfo {
+    pub cellsize: u8,
+    pub disksize: u32,
+    pub imagesize: u32,
+    pub memsize: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PluginInfo {
+    pub format: Format,
+    // register_plugin arguments, None for unregistered and smx plugins
+    pub metadata: Option<Metadata>,
+    // Empty for raw amx images
+    pub sections: Vec<SectionInfo>,
+    pub cod_size: usize,
+    pub dat_size: usize,
+    pub publics: Vec<String>,
+    pub natives: Vec<String>,
+    // Modules plugin requires
+    pub libraries: Vec<String>,
+    // Header hea..stp size
+    pub heap_budget: usize,
+    // Static heap usage along worst call path, None if cod is not decodable
+    pub heap_worst_case: Option<usize>,
+    // register_dictionary files and constant %L keys
+    pub dictionaries: Vec<String>,
+    pub lang_keys: Vec<String>,
+    // Precached paths, format strings for dynamic ones
+    pub precached: Vec<String>,
+}
+
+// Summary with parsed header, tables and opcodes, for JSON export
+#[cfg(feature = "serde")]
+#[derive(Debug, Serialize)]
+pub struct PluginDump<'a> {
+    pub info: PluginInfo,
+    pub header: Plugin<'a>,
+    pub natives: Vec<Native>,
+    pub publics: Vec<Public>,
+    pub pubvars: Vec<PubVar>,
+    pub tags: Vec<Tag>,
+    // Junk cells decoded as UNKNOWN
+    pub opcodes: Vec<Opcode>,
+}
+
+pub fn detect_format(bytes: &[u8]) -> Result<Format, AmxError> {
+    if bytes.len() >= 4 && [MAGIC, LEGACY_MAGIC].contains(&LittleEndian::read_u32(bytes)) {
+        return Ok(Format::Amxx);
+    }
+
+    if bytes.len() >= 6 && LittleEndian::read_u16(&bytes[4..]) == AMXMOD_MAGIC {
+        return Ok(Format::Amx);
+    }
+
+    if bytes.starts_with(&GZIP_MAGIC) {
+        return Ok(Format::GzipAmx);
+    }
+
+    if bytes.len() >= 4 && LittleEndian::read_u32(bytes) == SMX_MAGIC {
+        return Ok(Format::Smx);
+    }
+
+    Err(AmxError::UnknownFormat)
+}
+
+pub(crate) fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, AmxError> {
+    let mut image = vec![];
+    GzDecoder::new(bytes)
+        .read_to_end(&mut image)
+        .map_err(|e| AmxError::Unpack(e.to_string()))?;
+    Ok(image)
+}
+
+// Raw amx image is borrowed, unpacked ones are owned
+fn read_plugin(
+    bytes: &[u8],
+    cellsize: u8,
+) -> Result<(Format, Vec<SectionInfo>, Plugin<'_>), AmxError> {
+    let format = detect_format(bytes)?;
+    trace!("Detected {:?} format", format);
+
+    match format {
+        Format::Amx => return Ok((format, vec![], Plugin::try_from(bytes)?)),
+        Format::GzipAmx => return Ok((format, vec![], Plugin::try_from(gunzip(bytes)?)?)),
+        Format::Smx => {
+            return Err(AmxError::Unsupported(
+                "disassembly and decompilation of SourcePawn plugins",
+            ))
+        }
+        Format::Amxx => {}
+    }
+
+    let sections = File::try_from(bytes.to_vec())?.sections()?;
+    let infos = sections
+        .iter()
+        .map(|s| SectionInfo {
+            cellsize: s.cellsize,
+            disksize: s.disksize,
+            imagesize: s.imagesize,
+            memsize: s.memsize,
+        })
+        .collect();
+
+    let section = sections
+        .into_iter()
+        .find(|s| s.cellsize == cellsize)
+        .ok_or(AmxError::NoSection(u32::from(cellsize) * 8))?;
+
+    Ok((format, infos, section.unpack_plugin()?))
+}
+
+// Plugin image together with container it came from
+#[derive(Debug, PartialEq)]
+pub struct Loaded<'a> {
+    pub format: Format,
+    // Empty unless loaded from amxx container
+    pub sections: Vec<SectionInfo>,
+    pub plugin: Plugin<'a>,
+}
+
+/// Loads 32 bit plugin image from raw amx, amxx container or gzip
+/// compressed amx, whichever file contents turn out to be.
+///
+/// ```
+/// use rxxma::facade::Format;
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let loaded = rxxma::load(&bytes).unwrap();
+/// assert_eq!(loaded.format, Format::Amxx);
+/// assert_eq!(loaded.plugin.natives().unwrap().len(), 1);
+/// ```
+pub fn load(bytes: &[u8]) -> Result<Loaded<'_>, AmxError> {
+    let (format, sections, plugin) = read_plugin(bytes, 4)?;
+    Ok(Loaded {
+        format,
+        sections,
+        plugin,
+    })
+}
+
+/// Loads 32 bit plugin image from amxx or amx file contents.
+///
+/// ```
+/// let bytes = std::fs::read("test/fixtures/simple.amx183").unwrap();
+/// let plugin = rxxma::facade::load_plugin(&bytes).unwrap();
+/// assert_eq!(plugin.natives().unwrap().len(), 1);
+/// ```
+pub fn load_plugin(bytes: &[u8]) -> Result<Plugin<'_>, AmxError> {
+    Ok(load(bytes)?.plugin)
+}
+
+fn read_opcodes(plugin: &Plugin, opts: &DecompileOptions) -> Result<Vec<Opcode>, AmxError> {
+    if opts.lenient {
+        plugin.opcodes_lenient()
+    } else {
+        plugin.opcodes()
+    }
+}
+
+/// Decompiles amxx or amx file contents into approximated source.
+///
+/// ```
+/// use rxxma::facade::{decompile, DecompileOptions};
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let source = decompile(&bytes, &DecompileOptions::default()).unwrap();
+/// assert!(source.contains("register_plugin(\"simple plugin\", \"0.1\", \"Fedcomp\");"));
+/// ```
+pub fn decompile(bytes: &[u8], opts: &DecompileOptions) -> Result<String, AmxError> {
+    decompile_with_stocks(bytes, opts, &StockDatabase::new())
+}
+
+/// Decompiles like `decompile`, stocks found in database are not
+/// decompiled but left to their include.
+pub fn decompile_with_stocks(
+    bytes: &[u8],
+    opts: &DecompileOptions,
+    stocks: &StockDatabase,
+) -> Result<String, AmxError> {
+    let (_, _, plugin) = read_plugin(bytes, opts.cellsize)?;
+    let opcodes = read_opcodes(&plugin, opts)?;
+
+    opts.style
+        .print(&decompile_tree(plugin, opcodes, opts, stocks)?)
+}
+
+/// Decompiles only one function, picked by public or debug symbol name or
+/// by address inside it, rest of cod is not decompiled.
+///
+/// ```
+/// use rxxma::facade::{decompile_function, DecompileOptions};
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let source = decompile_function(&bytes, "plugin_init", &DecompileOptions::default()).unwrap();
+/// assert!(source.starts_with("public plugin_init () {\n"));
+/// ```
+pub fn decompile_function(
+    bytes: &[u8],
+    function: &str,
+    opts: &DecompileOptions,
+) -> Result<String, AmxError> {
+    let (_, _, plugin) = read_plugin(bytes, opts.cellsize)?;
+    let function = select_function(&plugin, function)?;
+    let opcodes = function.opcodes(&read_opcodes(&plugin, opts)?).to_vec();
+
+    decompile_tree(plugin, opcodes, opts, &StockDatabase::new())?
+        .render_function(function.address, &opts.style)
+}
+
+fn decompile_tree(
+    plugin: Plugin,
+    opcodes: Vec<Opcode>,
+    opts: &DecompileOptions,
+    stocks: &StockDatabase,
+) -> Result<AstPlugin, AmxError> {
+    let mut decompiler = Decompiler::from_opcodes(plugin, opcodes)?;
+    decompiler.encoding = opts.encoding;
+    decompiler.stocks = stocks.clone();
+    decompiler.deobfuscate = opts.deobfuscate;
+    decompiler.opcodes_into_functions()?;
+    decompiler.decompile_opcodes_by_templates()?;
+    Ok(decompiler.into_tree())
+}
+
+// Function by name (public, debug symbol or sub_<address>) or by cod address
+fn select_function(plugin: &Plugin, function: &str) -> Result<Function, AmxError> {
+    let found = match FunctionRef::from(function) {
+        FunctionRef::Address(address) => plugin.function_at(address)?,
+        FunctionRef::Name(name) => {
+            let symbol = plugin.debug_info()?.and_then(|info| {
+                info.symbols
+                    .iter()
+                    .find(|s| s.is_function() && s.name.as_bytes() == name.as_bytes())
+                    .map(|s| s.code_start)
+            });
+            functions(plugin)?
+                .into_iter()
+                .find(|f| f.name == name || Some(f.address) == symbol)
+        }
+    };
+    found.ok_or_else(|| AmxError::NoFunction(function.to_owned()))
+}
+
+// Float value of constant opcode param, if it looks like float
+fn float_comment(code: OpcodeType, param: u32) -> Option<String> {
+    match code {
+        OP_CONST_PRI | OP_CONST_ALT | OP_PUSH_C => float_constant(param).map(float_literal),
+        _ => None,
+    }
+}
+
+/// Lists cod opcodes, one per line with cod address.
+///
+/// ```
+/// use rxxma::facade::{disassemble, DecompileOptions};
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let listing = disassemble(&bytes, &DecompileOptions::default()).unwrap();
+/// assert!(listing.starts_with("0x8\tPROC\n"));
+/// ```
+pub fn disassemble(bytes: &[u8], opts: &DecompileOptions) -> Result<String, AmxError> {
+    let (_, _, plugin) = read_plugin(bytes, opts.cellsize)?;
+    Ok(listing(&read_opcodes(&plugin, opts)?))
+}
+
+/// Lists opcodes of one function, picked like in `decompile_function`.
+///
+/// ```
+/// use rxxma::facade::{disassemble_function, DecompileOptions};
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let listing = disassemble_function(&bytes, "0x8", &DecompileOptions::default()).unwrap();
+/// assert!(listing.ends_with("\tRETN\n"));
+/// ```
+pub fn disassemble_function(
+    bytes: &[u8],
+    function: &str,
+    opts: &DecompileOptions,
+) -> Result<String, AmxError> {
+    let (_, _, plugin) = read_plugin(bytes, opts.cellsize)?;
+    let function = select_function(&plugin, function)?;
+    Ok(listing(function.opcodes(&read_opcodes(&plugin, opts)?)))
+}
+
+fn listing(opcodes: &[Opcode]) -> String {
+    opcodes
+        .iter()
+        .map(|opcode| match opcode.param {
+            Some(p) => match float_comment(opcode.code, p) {
+                Some(value) => format!(
+                    "0x{:X}\t{}\t0x{:X}\t; {}\n",
+                    opcode.address, opcode.code, p, value
+                ),
+                None => format!("0x{:X}\t{}\t0x{:X}\n", opcode.address, opcode.code, p),
+            },
+            None => format!("0x{:X}\t{}\n", opcode.address, opcode.code),
+        })
+        .collect()
+}
+
+/// Collects everything `inspect` reports together with parsed 32 bit plugin
+/// header, tables and opcodes.
+///
+/// ```
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let dump = rxxma::facade::dump(&bytes).unwrap();
+/// assert_eq!(dump.natives[0].name.to_str(), Ok("register_plugin"));
+/// assert_eq!(dump.opcodes.len(), 11);
+/// ```
+#[cfg(feature = "serde")]
+pub fn dump(bytes: &[u8]) -> Result<PluginDump<'_>, AmxError> {
+    let info = inspect(bytes)?;
+    let (_, _, plugin) = read_plugin(bytes, 4)?;
+
+    Ok(PluginDump {
+        info,
+        natives: plugin.natives()?,
+        publics: plugin.publics()?,
+        pubvars: plugin.pubvars()?,
+        tags: plugin.tags()?,
+        opcodes: plugin.opcodes_lenient()?,
+        header: plugin,
+    })
+}
+
+// Inconsistency found by `validate`, amxx ones name their section
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub cellsize: Option<u8>,
+    pub error: AmxError,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.cellsize {
+            Some(cellsize) => write!(f, "{} bit section: {}", cellsize * 8, self.error),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+/// Cross-checks container sections and amx headers, or smx code, data and
+/// symbol tables, listing every violation instead of failing on the first
+/// one. Files too damaged to
+/// find sections in are an error.
+///
+/// ```
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// assert!(rxxma::facade::validate(&bytes).unwrap().is_empty());
+/// ```
+pub fn validate(bytes: &[u8]) -> Result<Vec<Violation>, AmxError> {
+    let image = match detect_format(bytes)? {
+        Format::Amx => Plugin::try_from(bytes)?,
+        Format::GzipAmx => Plugin::try_from(gunzip(bytes)?)?,
+        Format::Smx => {
+            return Ok(SmxFile::try_from(bytes.to_vec())?
+                .validate()
+                .into_iter()
+                .map(|error| Violation {
+                    cellsize: None,
+                    error,
+                })
+                .collect())
+        }
+        Format::Amxx => {
+            let sections = File::try_from(bytes.to_vec())?.sections()?;
+            return Ok(sections
+                .iter()
+                .flat_map(|s| {
+                    s.validate(bytes).into_iter().map(move |error| Violation {
+                        cellsize: Some(s.cellsize),
+                        error,
+                    })
+                })
+                .collect());
+        }
+    };
+    Ok(image
+        .validate()
+        .into_iter()
+        .map(|error| Violation {
+            cellsize: None,
+            error,
+        })
+        .collect())
+}
+
+/// Lists cod opcodes with labels, raw cells and operands resolved to
+/// natives, functions and strings.
+///
+/// ```
+/// use rxxma::facade::{disassemble_annotated, DecompileOptions};
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let listing = disassemble_annotated(&bytes, &DecompileOptions::default()).unwrap();
+/// assert!(listing.contains("SYSREQ.C      register_plugin"));
+/// ```
+pub fn disassemble_annotated(bytes: &[u8], opts: &DecompileOptions) -> Result<String, AmxError> {
+    let (_, _, plugin) = read_plugin(bytes, opts.cellsize)?;
+    let opcodes = read_opcodes(&plugin, opts)?;
+    disasm::listing(&plugin, &opcodes, opts.encoding)
+}
+
+/// Renders annotated listing as standalone HTML page titled `title`, with
+/// jumps, calls and natives hyperlinked.
+///
+/// ```
+/// use rxxma::facade::{disassemble_html, DecompileOptions};
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let page = disassemble_html(&bytes, "simple.amxx", &DecompileOptions::default()).unwrap();
+/// assert!(page.contains("<a href=\"#native_0\">register_plugin</a>"));
+/// ```
+pub fn disassemble_html(
+    bytes: &[u8],
+    title: &str,
+    opts: &DecompileOptions,
+) -> Result<String, AmxError> {
+    let (_, _, plugin) = read_plugin(bytes, opts.cellsize)?;
+    let opcodes = read_opcodes(&plugin, opts)?;
+    disasm::html(&plugin, &opcodes, opts.encoding, title)
+}
+
+/// Renders `amxxtool info` report as Markdown document titled `title`,
+/// with precached resources and their call sites.
+///
+/// ```
+/// use rxxma::facade::report_markdown;
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let text = report_markdown(&bytes, "simple.amxx").unwrap();
+/// assert!(text.contains("| Publics | `plugin_init` |"));
+/// ```
+pub fn report_markdown(bytes: &[u8], title: &str) -> Result<String, AmxError> {
+    Ok(report::markdown(&report::report(bytes)?, title))
+}
+
+/// Summarizes file layout and symbols without decompiling.
+///
+/// ```
+/// use rxxma::facade::{inspect, Format};
+///
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let info = inspect(&bytes).unwrap();
+/// assert_eq!(info.format, Format::Amxx);
+/// assert_eq!(info.publics, ["plugin_init"]);
+/// ```
+pub fn inspect(bytes: &[u8]) -> Result<PluginInfo, AmxError> {
+    if detect_format(bytes)? == Format::Smx {
+        return inspect_smx(bytes);
+    }
+    let (format, sections, plugin) = read_plugin(bytes, 4)?;
+
+    let publics = plugin
+        .publics()?
+        .iter()
+        .map(|p| p.name.to_string_lossy().into_owned())
+        .collect();
+    let natives = plugin
+        .natives()?
+        .iter()
+        .map(|n| n.name.to_string_lossy().into_owned())
+        .collect();
+    let libraries = plugin
+        .libraries()?
+        .iter()
+        .map(|l| l.to_string_lossy().into_owned())
+        .collect();
+    let dictionaries = dictionaries(&plugin).unwrap_or_default();
+    let precached = precached_resources(&plugin)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|r| r.path)
+        .collect();
+
+    Ok(PluginInfo {
+        format,
+        metadata: plugin.metadata().unwrap_or_default(),
+        sections,
+        cod_size: plugin.cod_size(),
+        dat_size: plugin.dat_slice()?.len(),
+        publics,
+        natives,
+        libraries,
+        heap_budget: plugin.heap_budget(),
+        heap_worst_case: heap_usage(&plugin).ok().map(|h| h.worst_case),
+        lang_keys: dictionaries.keys(),
+        dictionaries: dictionaries.files,
+        precached,
+    })
+}
+
+// Symbols and sizes of SourcePawn plugin, analyses of amx cod do not apply
+fn inspect_smx(bytes: &[u8]) -> Result<PluginInfo, AmxError> {
+    let smx = SmxFile::try_from(bytes.to_vec())?;
+    let data = smx.data()?;
+    let publics = smx
+        .publics()?
+        .iter()
+        .map(|p| p.name.to_string_lossy().into_owned())
+        .collect();
+    let natives = smx
+        .natives()?
+        .iter()
+        .map(|n| n.name.to_string_lossy().into_owned())
+        .collect();
+
+    Ok(PluginInfo {
+        format: Format::Smx,
+        metadata: None,
+        sections: vec![],
+        cod_size: smx.code()?.bytes.len(),
+        dat_size: data.bytes.len(),
+        publics,
+        natives,
+        libraries: vec![],
+        heap_budget: data.memsize.saturating_sub(data.bytes.len()),
+        heap_worst_case: None,
+        dictionaries: vec![],
+        lang_keys: vec![],
+        precached: vec![],
+    })
+}
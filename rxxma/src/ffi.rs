@@ -0,0 +1,121 @@
+// extern "C" wrappers around facade, declarations are in include/rxxma.h.
+// Every returned string is owned by library and must be released
+// with amxx_free_string.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::error::AmxError;
+use crate::facade::{self, DecompileOptions};
+
+pub const AMXX_OK: i32 = 0;
+pub const AMXX_ERR_NULL_ARGUMENT: i32 = -1;
+pub const AMXX_ERR_INVALID_INPUT: i32 = -2;
+pub const AMXX_ERR_PANIC: i32 = -3;
+
+fn into_raw_string(s: String) -> *mut c_char {
+    // Strings produced by facade never contain NUL, strip them just in case
+    let s = CString::new(s.replace('\0', "")).unwrap();
+    s.into_raw()
+}
+
+unsafe fn write_string(target: *mut *mut c_char, s: String) {
+    if !target.is_null() {
+        *target = into_raw_string(s);
+    }
+}
+
+// Shared argument checks, result conversion and panic barrier
+unsafe fn call<F>(
+    buf: *const u8,
+    len: usize,
+    out: *mut *mut c_char,
+    err: *mut *mut c_char,
+    f: F,
+) -> i32
+where
+    F: FnOnce(&[u8]) -> Result<String, AmxError>,
+{
+    if !out.is_null() {
+        *out = ptr::null_mut();
+    }
+    if !err.is_null() {
+        *err = ptr::null_mut();
+    }
+
+    if out.is_null() || (buf.is_null() && len > 0) {
+        write_string(err, "null argument".to_owned());
+        return AMXX_ERR_NULL_ARGUMENT;
+    }
+
+    let bytes = if buf.is_null() {
+        &[][..]
+    } else {
+        slice::from_raw_parts(buf, len)
+    };
+
+    match panic::catch_unwind(AssertUnwindSafe(|| f(bytes))) {
+        Ok(Ok(result)) => {
+            *out = into_raw_string(result);
+            AMXX_OK
+        }
+        Ok(Err(e)) => {
+            write_string(err, e.to_string());
+            AMXX_ERR_INVALID_INPUT
+        }
+        Err(cause) => {
+            let message = cause
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| cause.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_owned());
+            write_string(err, format!("panic: {}", message));
+            AMXX_ERR_PANIC
+        }
+    }
+}
+
+/// # Safety
+///
+/// `buf` must point to `len` readable bytes, `out` and `err` (if not null)
+/// must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn amxx_decompile(
+    buf: *const u8,
+    len: usize,
+    out: *mut *mut c_char,
+    err: *mut *mut c_char,
+) -> i32 {
+    call(buf, len, out, err, |bytes| {
+        facade::decompile(bytes, &DecompileOptions::default())
+    })
+}
+
+/// # Safety
+///
+/// Same contract as `amxx_decompile`.
+#[no_mangle]
+pub unsafe extern "C" fn amxx_inspect_json(
+    buf: *const u8,
+    len: usize,
+    out: *mut *mut c_char,
+    err: *mut *mut c_char,
+) -> i32 {
+    call(buf, len, out, err, |bytes| {
+        let info = facade::inspect(bytes)?;
+        Ok(serde_json::to_string(&info)?)
+    })
+}
+
+/// # Safety
+///
+/// `s` must be null or returned by this library, and freed only once.
+#[no_mangle]
+pub unsafe extern "C" fn amxx_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
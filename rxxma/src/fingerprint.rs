@@ -0,0 +1,227 @@
+// Hashes of plugin parts for identifying known plugins and their edited copies.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::amx::Plugin;
+use crate::diff::{plugin_functions, Operand};
+use crate::error::AmxError;
+
+// Similarity weights, sum to 1.0
+const COD_WEIGHT: f32 = 0.15;
+const NORMALIZED_COD_WEIGHT: f32 = 0.1;
+const DAT_WEIGHT: f32 = 0.15;
+const NATIVES_WEIGHT: f32 = 0.2;
+const PUBLICS_WEIGHT: f32 = 0.2;
+const HISTOGRAM_WEIGHT: f32 = 0.1;
+const SIZE_WEIGHT: f32 = 0.1;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PluginFingerprint {
+    // Hex encoded SHA-256 digests
+    pub cod: String,
+    // Of cod with string contents, globals and code addresses left out,
+    // same for rebuilt or re-branded copies. None when cod does not decode.
+    pub normalized_cod: Option<String>,
+    pub dat: String,
+    // Of sorted name lists
+    pub natives: String,
+    pub publics: String,
+    // Of opcode counts, parameters ignored
+    pub opcode_histogram: String,
+    pub cod_size: usize,
+    // Whole .amxx file, None for plugins not loaded from container
+    pub container: Option<String>,
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn names_hash(mut names: Vec<String>) -> String {
+    names.sort();
+    sha256(names.join("\n").as_bytes())
+}
+
+// Function bodies in cod order, natives by name
+fn normalized_cod(plugin: &Plugin) -> Option<String> {
+    let functions = plugin_functions(plugin).ok()?;
+    let lines: Vec<String> = functions
+        .bodies
+        .iter()
+        .flatten()
+        .map(|o| match o.operand {
+            Operand::String(_) => format!("{}\t<string>", o.code),
+            _ => o.to_string(),
+        })
+        .collect();
+    Some(sha256(lines.join("\n").as_bytes()))
+}
+
+impl PluginFingerprint {
+    pub fn from(plugin: &Plugin) -> Result<PluginFingerprint, AmxError> {
+        let natives = plugin
+            .natives()?
+            .iter()
+            .map(|n| n.name.to_string_lossy().into_owned())
+            .collect();
+        let publics = plugin
+            .publics()?
+            .iter()
+            .map(|p| p.name.to_string_lossy().into_owned())
+            .collect();
+
+        let mut histogram: BTreeMap<u32, usize> = BTreeMap::new();
+        for opcode in plugin.opcodes_lenient()? {
+            *histogram.entry(opcode.code as u32).or_insert(0) += 1;
+        }
+        let histogram: Vec<String> = histogram
+            .iter()
+            .map(|(code, count)| format!("{}:{}", code, count))
+            .collect();
+
+        Ok(PluginFingerprint {
+            cod: sha256(plugin.cod_slice()?),
+            normalized_cod: normalized_cod(plugin),
+            dat: sha256(plugin.dat_slice()?),
+            natives: names_hash(natives),
+            publics: names_hash(publics),
+            opcode_histogram: sha256(histogram.join("\n").as_bytes()),
+            cod_size: plugin.cod_size(),
+            container: None,
+        })
+    }
+
+    // Adds hash of .amxx file plugin was unpacked from
+    pub fn with_container(mut self, container: &[u8]) -> PluginFingerprint {
+        self.container = Some(sha256(container));
+        self
+    }
+
+    // 1.0 for identical plugins, weighted share of matching parts otherwise
+    pub fn similarity(&self, other: &PluginFingerprint) -> f32 {
+        if self.container.is_some() && self.container == other.container {
+            return 1.0;
+        }
+
+        let matches = |a: &String, b: &String, weight: f32| if a == b { weight } else { 0.0 };
+        let size = if self.cod_size.max(other.cod_size) == 0 {
+            1.0
+        } else {
+            self.cod_size.min(other.cod_size) as f32 / self.cod_size.max(other.cod_size) as f32
+        };
+
+        let normalized = match (&self.normalized_cod, &other.normalized_cod) {
+            (Some(a), Some(b)) if a == b => NORMALIZED_COD_WEIGHT,
+            _ => 0.0,
+        };
+
+        matches(&self.cod, &other.cod, COD_WEIGHT)
+            + normalized
+            + matches(&self.dat, &other.dat, DAT_WEIGHT)
+            + matches(&self.natives, &other.natives, NATIVES_WEIGHT)
+            + matches(&self.publics, &other.publics, PUBLICS_WEIGHT)
+            + matches(
+                &self.opcode_histogram,
+                &other.opcode_histogram,
+                HISTOGRAM_WEIGHT,
+            )
+            + size * SIZE_WEIGHT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use byteorder::{ByteOrder, LittleEndian};
+
+    use super::PluginFingerprint;
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    fn fingerprint(bin: Vec<u8>) -> PluginFingerprint {
+        PluginFingerprint::from(&Plugin::try_from(bin).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn it_match_identical_plugins() {
+        let first = fingerprint(load_fixture("simple.amx183"));
+        let second = fingerprint(load_fixture("simple.amx183"));
+
+        assert_eq!(first, second);
+        assert_eq!(first.similarity(&second), 1.0);
+    }
+
+    #[test]
+    fn it_score_patched_copy_below_identical() {
+        let amxmod_bin = load_fixture("simple.amx183");
+        let original = fingerprint(amxmod_bin.clone());
+
+        // Change parameter of first opcode having one
+        let mut patched_bin = amxmod_bin.clone();
+        let amxmod_plugin = Plugin::try_from(amxmod_bin).unwrap();
+        let opcode = amxmod_plugin
+            .opcodes()
+            .unwrap()
+            .into_iter()
+            .find(|o| o.param.is_some())
+            .unwrap();
+        let cod = LittleEndian::read_u32(&patched_bin[12..]) as usize;
+        let at = cod + opcode.address + 4;
+        let param = LittleEndian::read_u32(&patched_bin[at..]);
+        LittleEndian::write_u32(&mut patched_bin[at..], param + 4);
+        let patched = fingerprint(patched_bin);
+
+        assert_ne!(original.cod, patched.cod);
+        assert_eq!(original.natives, patched.natives);
+        assert_eq!(original.publics, patched.publics);
+        let similarity = original.similarity(&patched);
+        assert!(similarity > 0.7 && similarity < 1.0);
+    }
+
+    fn branded(name: &str) -> PluginFingerprint {
+        let mut builder = PluginBuilder::new();
+        let server_print = builder.native("server_print");
+        let brand = builder.string(name);
+        let loaded = builder.array(&[0]);
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_INC, loaded)
+            .op_param(OP_PUSH_C, brand)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, server_print)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        Plugin::try_from(builder.build())
+            .unwrap()
+            .fingerprint()
+            .unwrap()
+    }
+
+    #[test]
+    fn it_match_rebranded_copy_by_normalized_cod() {
+        let original = branded("Admin Tools by alice");
+        let rebranded = branded("Best Admin Tools");
+
+        // Shorter string moves the global, changing exact cod
+        assert_ne!(original.cod, rebranded.cod);
+        assert_ne!(original.dat, rebranded.dat);
+        assert!(original.normalized_cod.is_some());
+        assert_eq!(original.normalized_cod, rebranded.normalized_cod);
+        assert_ne!(
+            original.normalized_cod,
+            fingerprint(load_fixture("simple.amx183")).normalized_cod
+        );
+    }
+}
@@ -0,0 +1,270 @@
+// Hexdump of plugin files with header fields and segment boundaries
+// annotated, for debugging malformed files. Containers are dumped as
+// stored, followed by unpacked image of every section. Parsing stops at
+// the first error, fields read until then are still annotated.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::amx::Plugin;
+use crate::amxx::File;
+use crate::error::AmxError;
+use crate::facade::{detect_format, gunzip, Format};
+use crate::parse_trace::ParseTrace;
+
+const LINE_WIDTH: usize = 16;
+// ANSI foreground colors fields cycle through
+const COLORS: &[u8] = &[31, 32, 33, 34, 35, 36];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpanKind {
+    // Header field with its raw value
+    Field(u64),
+    // Table, segment or section contents
+    Segment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub name: String,
+    pub range: Range<usize>,
+    pub kind: SpanKind,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            SpanKind::Field(value) => write!(f, "{}=0x{:X}", self.name, value),
+            SpanKind::Segment => write!(
+                f,
+                "[{} 0x{:X}..0x{:X}]",
+                self.name, self.range.start, self.range.end
+            ),
+        }
+    }
+}
+
+// One buffer, file as stored or unpacked image, with its spans
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dump {
+    pub title: String,
+    pub bytes: Vec<u8>,
+    pub spans: Vec<Span>,
+}
+
+fn field_spans(trace: &ParseTrace) -> Vec<Span> {
+    trace
+        .entries
+        .iter()
+        .map(|e| Span {
+            name: e.field.to_owned(),
+            range: e.offset..e.offset + e.size,
+            kind: SpanKind::Field(e.value),
+        })
+        .collect()
+}
+
+fn segment(name: String, range: Range<usize>) -> Span {
+    Span {
+        name,
+        range,
+        kind: SpanKind::Segment,
+    }
+}
+
+fn image_dump(title: &str, image: Vec<u8>) -> Dump {
+    let mut trace = ParseTrace::new();
+    let (title, segments) = match Plugin::parse_traced(&image, &mut trace) {
+        Ok(plugin) => (title.to_owned(), plugin.segments()),
+        Err(e) => (format!("{} ({})", title, e), vec![]),
+    };
+
+    let mut spans = field_spans(&trace);
+    spans.extend(
+        segments
+            .into_iter()
+            .filter(|(_, range)| !range.is_empty())
+            .map(|(name, range)| segment(name.to_owned(), range)),
+    );
+    Dump {
+        title,
+        bytes: image,
+        spans,
+    }
+}
+
+fn container_dumps(bytes: &[u8]) -> Vec<Dump> {
+    let mut trace = ParseTrace::new();
+    let sections = File::parse_traced(bytes.to_vec(), &mut trace)
+        .and_then(|file| file.sections_traced(&mut trace));
+
+    let mut spans = field_spans(&trace);
+    let mut title = "container".to_owned();
+    let mut images = vec![];
+    match sections {
+        Ok(sections) => {
+            for (i, section) in sections.iter().enumerate() {
+                let contents = section.offset..section.offset + section.disksize as usize;
+                spans.push(segment(format!("section {} contents", i + 1), contents));
+                let title = format!("section {} image, {} bit", i + 1, section.cellsize * 8);
+                match section.unpack_section() {
+                    Ok(image) => images.push(image_dump(&title, image)),
+                    Err(e) => images.push(Dump {
+                        title: format!("{} ({})", title, e),
+                        bytes: vec![],
+                        spans: vec![],
+                    }),
+                }
+            }
+        }
+        Err(e) => title = format!("container ({})", e),
+    }
+
+    let mut dumps = vec![Dump {
+        title,
+        bytes: bytes.to_vec(),
+        spans,
+    }];
+    dumps.extend(images);
+    dumps
+}
+
+/// Splits amxx, amx or gzipped amx file into annotated dumps.
+///
+/// ```
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let dumps = rxxma::hexdump::dumps(&bytes).unwrap();
+/// assert_eq!(dumps[0].title, "container");
+/// assert!(dumps[1].to_string().contains("magic=0xF1E0"));
+/// ```
+pub fn dumps(bytes: &[u8]) -> Result<Vec<Dump>, AmxError> {
+    Ok(match detect_format(bytes)? {
+        Format::Amxx => container_dumps(bytes),
+        Format::Amx => vec![image_dump("image", bytes.to_vec())],
+        Format::GzipAmx => vec![image_dump("gzip image", gunzip(bytes)?)],
+        Format::Smx => {
+            return Err(AmxError::Unsupported(
+                "SourcePawn plugins have no amx image",
+            ))
+        }
+    })
+}
+
+impl Dump {
+    // Innermost span containing offset, fields win over segments
+    fn field_at(&self, offset: usize) -> Option<usize> {
+        self.spans
+            .iter()
+            .position(|s| s.kind != SpanKind::Segment && s.range.contains(&offset))
+    }
+
+    // Classic 16 bytes per line dump, spans starting on line are listed
+    // after it. With `color` bytes of every field are ANSI colored.
+    pub fn render(&self, color: bool) -> String {
+        let mut result = format!("{}:\n", self.title);
+        for (line, chunk) in self.bytes.chunks(LINE_WIDTH).enumerate() {
+            let start = line * LINE_WIDTH;
+            result += &format!("{:08X} ", start);
+            for column in 0..LINE_WIDTH {
+                if column % 8 == 0 {
+                    result.push(' ');
+                }
+                let byte = match chunk.get(column) {
+                    Some(byte) => format!("{:02X}", byte),
+                    None => "  ".to_owned(),
+                };
+                match self.field_at(start + column) {
+                    Some(field) if color && column < chunk.len() => {
+                        let code = COLORS[field % COLORS.len()];
+                        result += &format!("\x1b[{}m{}\x1b[0m ", code, byte);
+                    }
+                    _ => result += &format!("{} ", byte),
+                }
+            }
+
+            let text: String = chunk
+                .iter()
+                .map(|&b| match b {
+                    0x20..=0x7E => b as char,
+                    _ => '.',
+                })
+                .collect();
+            result += &format!(" |{:<width$}|", text, width = LINE_WIDTH);
+
+            let line_range = start..start + LINE_WIDTH;
+            let spans: Vec<String> = self
+                .spans
+                .iter()
+                .filter(|s| line_range.contains(&s.range.start))
+                .map(|s| s.to_string())
+                .collect();
+            if !spans.is_empty() {
+                result += &format!("  {}", spans.join(" "));
+            }
+            result.push('\n');
+        }
+        result
+    }
+}
+
+impl fmt::Display for Dump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dumps, SpanKind};
+    use crate::util::tests::load_fixture;
+
+    #[test]
+    fn it_annotate_image_header_and_segments() {
+        let dumps = dumps(&load_fixture("simple.amx183")).unwrap();
+
+        assert_eq!(dumps.len(), 1);
+        let text = dumps[0].to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "image:");
+        assert!(lines[1].starts_with("00000000  "));
+        assert!(lines[1].ends_with(
+            "  size=0x128 magic=0xF1E0 file version=0x8 amx version=0x8 flags=0x2 \
+             defsize=0x8 cod=0x74 [header 0x0..0x38]"
+        ));
+        assert!(text.contains("[cod 0x74..0xC0]"));
+
+        let colored = dumps[0].render(true);
+        assert!(colored.contains("\x1b[31m"));
+    }
+
+    #[test]
+    fn it_keep_fields_read_before_error() {
+        let mut bytes = load_fixture("simple.amx183");
+        // Cod past image end
+        bytes[12] = 0xFF;
+        bytes[13] = 0xFF;
+        let dumps = dumps(&bytes).unwrap();
+
+        assert!(dumps[0].title.starts_with("image ("));
+        assert!(dumps[0].spans.iter().all(|s| s.kind != SpanKind::Segment));
+        assert!(dumps[0].to_string().contains("cod=0xFFFF"));
+    }
+
+    #[test]
+    fn it_dump_container_and_section_images() {
+        let dumps = dumps(&load_fixture("simple.amxx181")).unwrap();
+
+        let titles: Vec<&str> = dumps.iter().map(|d| d.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            [
+                "container",
+                "section 1 image, 32 bit",
+                "section 2 image, 64 bit"
+            ]
+        );
+        assert!(dumps[0]
+            .to_string()
+            .contains("[section 2 contents 0xCA..0x17B]"));
+    }
+}
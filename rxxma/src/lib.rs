@@ -9,5 +9,38 @@ extern crate failure;
 
 pub mod amx;
 pub mod amxx;
+pub mod analysis;
+#[cfg(feature = "archive")]
+pub mod archive;
 pub mod ast;
+#[cfg(feature = "fs")]
+pub mod audit;
+#[cfg(feature = "batch")]
+pub mod batch;
+pub mod corpus;
+pub mod diff;
+pub mod disasm;
+pub mod emulator;
+pub mod error;
+pub mod facade;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fingerprint;
+pub mod hexdump;
+pub mod parse_trace;
+pub mod patch;
+pub mod releases;
+pub mod report;
+pub mod rules;
+pub mod scan;
+pub mod sigscan;
+pub mod sourcepawn;
+pub mod stocks;
 pub mod util;
+#[cfg(feature = "fs")]
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use self::error::AmxError;
+pub use self::facade::{decompile, disassemble, inspect, load, DecompileOptions, PluginInfo};
@@ -1,16 +1,12 @@
-use failure::format_err;
-use log::trace;
-
-use std::convert::TryFrom;
+use std::fs;
 use std::path::PathBuf;
 
 use clap::{App, Arg};
 use failure::Error;
 
-use rxxma::amx::Plugin as AmxPlugin;
-use rxxma::amxx::File as AmxmodxFile;
-use rxxma::ast::Decompiler;
-use rxxma::ast::TreeElement;
+use rxxma::analysis;
+use rxxma::diff;
+use rxxma::facade::{self, DecompileOptions};
 
 macro_rules! die {
     ($fmt:expr) => ({
@@ -23,35 +19,22 @@ macro_rules! die {
     });
 }
 
-fn str_to_err(e: &str) -> Error {
-    format_err!("{}", e)
+fn decompile(file_path: PathBuf) -> Result<String, Error> {
+    let bytes = fs::read(file_path)?;
+    Ok(facade::decompile(&bytes, &DecompileOptions::default())?)
 }
 
-fn read_32bit_section(file_path: PathBuf) -> Result<AmxPlugin, Error> {
-    let amxmodx_file = AmxmodxFile::try_from(file_path)?;
-    let sections = amxmodx_file.sections()?;
-
-    let section_32bit = sections
-        .into_iter()
-        .find(|ref s| s.cellsize == 4)
-        .ok_or("File has no 32 bit sections. 64 bit are not supported")
-        .map_err(str_to_err)?;
-
-    trace!("-------------------------------------------");
-    trace!(" Reading amxmod plugin from 32 bit section ");
-    trace!("-------------------------------------------");
-    section_32bit.unpack_section()
+fn emit_inc(file_path: PathBuf) -> Result<String, Error> {
+    let bytes = fs::read(file_path)?;
+    let amxmod_plugin = facade::load_plugin(&bytes)?;
+    Ok(analysis::generate_inc(&amxmod_plugin)?)
 }
 
-fn decompile(file_path: PathBuf) -> Result<String, Error> {
-    let amxmod_plugin = read_32bit_section(file_path)?;
-
-    let mut decompiler = Decompiler::from(amxmod_plugin);
-    decompiler.opcodes_into_functions();
-    decompiler.decompile_opcodes_by_templates().unwrap();
-    let ast_plugin = decompiler.into_tree();
-
-    Ok(ast_plugin.to_string(0).map_err(str_to_err)?)
+fn diff(file_path: PathBuf, other_path: PathBuf) -> Result<String, Error> {
+    let (old_bytes, new_bytes) = (fs::read(file_path)?, fs::read(other_path)?);
+    let old_plugin = facade::load_plugin(&old_bytes)?;
+    let new_plugin = facade::load_plugin(&new_bytes)?;
+    Ok(diff::compare(&old_plugin, &new_plugin)?.to_string())
 }
 
 fn main() {
@@ -68,13 +51,33 @@ fn main() {
                 .required(true)
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("emit-inc")
+                .long("emit-inc")
+                .help("Print .inc with declarations of used natives instead of source"),
+        )
+        .arg(
+            Arg::with_name("diff")
+                .long("diff")
+                .value_name("OTHER")
+                .help("Compare functions and tables with OTHER plugin")
+                .takes_value(true),
+        )
         .get_matches();
 
     let file_path = matches.value_of("file").unwrap();
     let file_path_buf = PathBuf::from(file_path);
 
+    let result = if let Some(other) = matches.value_of("diff") {
+        diff(file_path_buf, PathBuf::from(other))
+    } else if matches.is_present("emit-inc") {
+        emit_inc(file_path_buf)
+    } else {
+        decompile(file_path_buf)
+    };
+
     let source = {
-        match decompile(file_path_buf) {
+        match result {
             Ok(s) => s,
             Err(e) => die!("{}", e),
         }
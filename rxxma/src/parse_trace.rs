@@ -0,0 +1,73 @@
+// Header fields read while parsing containers, sections and amx images,
+// for hexdump annotated views of headers without a global logger. Every
+// field is logged at trace level as well, collected or not.
+
+use std::io::Cursor;
+use std::mem;
+
+use log::trace;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TraceEntry {
+    pub field: &'static str,
+    // Offset into buffer being parsed, file for containers and sections,
+    // image for amx headers
+    pub offset: usize,
+    pub size: usize,
+    // Raw little endian value
+    pub value: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ParseTrace {
+    // In read order
+    pub entries: Vec<TraceEntry>,
+}
+
+impl ParseTrace {
+    pub fn new() -> ParseTrace {
+        ParseTrace::default()
+    }
+
+    // Field byte at `offset` belongs to
+    pub fn entry_at(&self, offset: usize) -> Option<&TraceEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.offset <= offset && offset < e.offset + e.size)
+    }
+}
+
+// Records fields into trace parser was given, if any
+pub(crate) struct Tracer<'t> {
+    trace: Option<&'t mut ParseTrace>,
+}
+
+impl<'t> Tracer<'t> {
+    pub(crate) fn new(trace: Option<&'t mut ParseTrace>) -> Tracer<'t> {
+        Tracer { trace }
+    }
+
+    // Field of `value` type size just read, ending at reader position
+    pub(crate) fn field<T, V>(&mut self, field: &'static str, reader: &Cursor<T>, value: V)
+    where
+        V: Into<u64>,
+    {
+        let size = mem::size_of::<V>();
+        let offset = reader.position() as usize - size;
+        let value = value.into();
+        trace!("{}:\t0x{:X} at 0x{:X}", field, value, offset);
+
+        if let Some(trace) = self.trace.as_mut() {
+            trace.entries.push(TraceEntry {
+                field,
+                offset,
+                size,
+                value,
+            });
+        }
+    }
+}
@@ -0,0 +1,802 @@
+// In place edits of amx images. Edits keep every offset in the image valid,
+// patched plugin can be serialized with `Plugin::to_bytes` or `File::pack`.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ops::Range;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::amx::plugin::{AmxFlags, INLINE_NAME_SIZE};
+use crate::amx::writer::opcode_cells;
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, Plugin, Writer, CELLSIZE};
+use crate::analysis::code_targets;
+
+// Header fields
+const SIZE: usize = 0;
+const COD: usize = 12;
+const DAT: usize = 16;
+const HEA: usize = 20;
+const STP: usize = 24;
+const NATIVES: usize = 36;
+const NAMETABLE: usize = 52;
+const DEFSIZE: usize = 8;
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum PatchError {
+    #[fail(display = "No string at dat address 0x{:X}", _0)]
+    NotAString(usize),
+    #[fail(
+        display = "String does not fit, {} bytes available, {} required",
+        available, required
+    )]
+    StringTooLong { available: usize, required: usize },
+    #[fail(display = "Native {} not found", _0)]
+    NativeNotFound(String),
+    #[fail(display = "Invalid name {:?}", _0)]
+    InvalidName(String),
+    #[fail(
+        display = "Range 0x{:X}..0x{:X} does not cover whole instructions",
+        start, end
+    )]
+    InvalidRange { start: usize, end: usize },
+    #[fail(display = "Jump at 0x{:X} targets 0x{:X} inside range", from, target)]
+    OrphanedJump { from: usize, target: usize },
+    #[fail(display = "Public {} starts inside range", _0)]
+    OrphanedPublic(String),
+    #[fail(display = "Patched image is invalid: {}", _0)]
+    InvalidImage(String),
+}
+
+fn read_header(bin: &[u8], field: usize) -> usize {
+    LittleEndian::read_u32(&bin[field..]) as usize
+}
+
+fn add_to_header(bin: &mut [u8], field: usize, value: usize) {
+    let current = read_header(bin, field);
+    LittleEndian::write_u32(&mut bin[field..], (current + value) as u32);
+}
+
+// Cells taken by string at `addr` including terminator, and whether it is packed
+fn string_footprint(dat: &[u8], addr: usize) -> Option<(usize, bool)> {
+    if !addr.is_multiple_of(CELLSIZE) {
+        return None;
+    }
+
+    let cells = dat
+        .get(addr..)?
+        .chunks_exact(CELLSIZE)
+        .map(LittleEndian::read_u32);
+    let mut packed = None;
+    for (n, cell) in cells.enumerate() {
+        // Packed strings keep first character in the highest byte
+        let is_packed = *packed.get_or_insert(cell > 0xFF);
+        if is_packed && cell.to_be_bytes().contains(&0) || !is_packed && cell == 0 {
+            return Some((n + 1, is_packed));
+        }
+        if !is_packed && cell > 0xFF {
+            return None;
+        }
+    }
+
+    None
+}
+
+// Rewrites string at dat address `addr`, new value must fit in original cells
+pub fn replace_string(plugin: &mut Plugin, addr: usize, new: &str) -> Result<(), PatchError> {
+    let dat = plugin
+        .dat_slice_mut()
+        .map_err(|_| PatchError::NotAString(addr))?;
+    let (cells, packed) = string_footprint(dat, addr).ok_or(PatchError::NotAString(addr))?;
+
+    let available = if packed {
+        cells * CELLSIZE - 1
+    } else {
+        cells - 1
+    };
+    if new.len() > available {
+        return Err(PatchError::StringTooLong {
+            available,
+            required: new.len(),
+        });
+    }
+
+    let target = &mut dat[addr..addr + cells * CELLSIZE];
+    let mut bytes = new.as_bytes().to_vec();
+    if packed {
+        bytes.resize(target.len(), 0);
+        for (cell, chars) in target.chunks_mut(CELLSIZE).zip(bytes.chunks(CELLSIZE)) {
+            LittleEndian::write_u32(cell, BigEndian::read_u32(chars));
+        }
+    } else {
+        bytes.resize(cells, 0);
+        for (cell, &c) in target.chunks_mut(CELLSIZE).zip(bytes.iter()) {
+            LittleEndian::write_u32(cell, u32::from(c));
+        }
+    }
+
+    Ok(())
+}
+
+// Renames native in place when new name fits, otherwise appends name to
+// nametable moving cod and everything after it. Inline names of older
+// file versions are only renamed in place.
+pub fn rename_native(plugin: &mut Plugin, old: &str, new: &str) -> Result<(), PatchError> {
+    if new.is_empty() || new.contains('\0') {
+        return Err(PatchError::InvalidName(new.to_owned()));
+    }
+
+    let natives = plugin
+        .natives()
+        .map_err(|e| PatchError::InvalidImage(e.to_string()))?;
+    let index = natives
+        .iter()
+        .position(|n| n.name.as_bytes() == old.as_bytes())
+        .ok_or_else(|| PatchError::NativeNotFound(old.to_owned()))?;
+
+    if plugin.inline_names() {
+        if new.len() >= INLINE_NAME_SIZE {
+            return Err(PatchError::InvalidName(new.to_owned()));
+        }
+        let cellsize = plugin.cellsize();
+        let bin = plugin.bin.to_mut();
+        let record = read_header(bin, NATIVES) + index * (cellsize + INLINE_NAME_SIZE) + cellsize;
+        let mut name = new.as_bytes().to_vec();
+        name.resize(INLINE_NAME_SIZE, 0);
+        bin[record..record + INLINE_NAME_SIZE].copy_from_slice(&name);
+        return Ok(());
+    }
+
+    let bin = plugin.bin.to_mut();
+    let record = read_header(bin, NATIVES) + index * DEFSIZE + 4;
+    let name_offset = read_header(bin, record);
+
+    if new.len() <= old.len() {
+        let mut name = new.as_bytes().to_vec();
+        name.resize(old.len() + 1, 0);
+        bin[name_offset..name_offset + name.len()].copy_from_slice(&name);
+        return Ok(());
+    }
+
+    let cod = read_header(bin, COD);
+    let mut name = new.as_bytes().to_vec();
+    name.push(0);
+    // Keep cod cell aligned
+    let padded = name.len().div_ceil(CELLSIZE) * CELLSIZE;
+    name.resize(padded, 0);
+
+    let mut patched = bin.clone();
+    patched.splice(cod..cod, name);
+    for &field in &[SIZE, COD, DAT, HEA, STP] {
+        add_to_header(&mut patched, field, padded);
+    }
+    LittleEndian::write_u32(&mut patched[record..], cod as u32);
+
+    // Nametable starts with longest name length
+    let nametable = read_header(&patched, NAMETABLE);
+    let max_length = LittleEndian::read_u16(&patched[nametable..]);
+    if new.len() > usize::from(max_length) {
+        LittleEndian::write_u16(&mut patched[nametable..], new.len() as u16);
+    }
+
+    *plugin = Plugin::try_from(patched).map_err(|e| PatchError::InvalidImage(e.to_string()))?;
+    Ok(())
+}
+
+// Points SYSREQ.C calls of native `old` to already imported native `new`,
+// e.g. when module renamed native and old name is kept for compatibility.
+// Returns number of retargeted calls.
+pub fn retarget_native(plugin: &mut Plugin, old: &str, new: &str) -> Result<usize, PatchError> {
+    let natives = plugin
+        .natives()
+        .map_err(|e| PatchError::InvalidImage(e.to_string()))?;
+    let index_of = |name: &str| {
+        natives
+            .iter()
+            .position(|n| n.name.as_bytes() == name.as_bytes())
+            .ok_or_else(|| PatchError::NativeNotFound(name.to_owned()))
+    };
+    let (old_index, new_index) = (index_of(old)?, index_of(new)?);
+
+    let opcodes = plugin
+        .opcodes()
+        .map_err(|e| PatchError::InvalidImage(e.to_string()))?;
+    let cod = read_header(&plugin.bin, COD);
+    let mut retargeted = 0;
+    for opcode in opcodes.iter() {
+        if opcode.code == OP_SYSREQ_C && opcode.param == Some(old_index as u32) {
+            let cell = cod + opcode.address + CELLSIZE;
+            LittleEndian::write_u32(&mut plugin.bin.to_mut()[cell..], new_index as u32);
+            retargeted += 1;
+        }
+    }
+
+    Ok(retargeted)
+}
+
+// Replaces instructions in `start..end` (cod addresses) with NOPs. Jumps from
+// outside into the middle of range are refused, or retargeted to `end`.
+pub fn nop_range(
+    plugin: &mut Plugin,
+    start: usize,
+    end: usize,
+    retarget_jumps: bool,
+) -> Result<(), PatchError> {
+    let opcodes = plugin
+        .opcodes()
+        .map_err(|e| PatchError::InvalidImage(e.to_string()))?;
+    let cod_size = plugin.cod_size();
+
+    // Case table cells are not instruction boundaries
+    let is_boundary = |address: usize| {
+        address == cod_size
+            || opcodes
+                .iter()
+                .any(|o| o.address == address && !o.code.is_pseudo())
+    };
+    if start >= end || !is_boundary(start) || !is_boundary(end) {
+        return Err(PatchError::InvalidRange { start, end });
+    }
+
+    let cod = read_header(&plugin.bin, COD);
+    let inside = |address: usize| start <= address && address < end;
+
+    // (cell holding target, retargeted value) of jumps from outside range
+    let mut orphaned = vec![];
+    for (i, target) in code_targets(&opcodes, cod_size) {
+        let opcode = opcodes[i];
+        // Case table of SWITCH is gone even when range starts with it
+        let is_orphaned = match opcode.code {
+            OP_SWITCH => inside(target),
+            _ => start < target && target < end,
+        };
+        if inside(opcode.address) || !is_orphaned {
+            continue;
+        }
+
+        // Case table jumps are operands themselves
+        let cell = match opcode.code {
+            OP_CASENONE | OP_CASEJMP => opcode.address,
+            _ => opcode.address + CELLSIZE,
+        };
+        let param = opcode.param.unwrap_or(0);
+        let value = match opcode.code {
+            OP_SWITCH => None,
+            // Relative to the next instruction, which stays in place
+            OP_JREL => Some(param.wrapping_add((end - target) as u32)),
+            _ => Some(end as u32),
+        };
+        match value.filter(|_| retarget_jumps) {
+            Some(value) => orphaned.push((cell, value)),
+            None => return Err(PatchError::OrphanedJump { from: cell, target }),
+        }
+    }
+
+    for (cell, value) in orphaned {
+        LittleEndian::write_u32(&mut plugin.bin.to_mut()[cod + cell..], value);
+    }
+
+    for cell in plugin.bin.to_mut()[cod + start..cod + end].chunks_mut(CELLSIZE) {
+        LittleEndian::write_u32(cell, OP_NOP as u32);
+    }
+
+    Ok(())
+}
+
+// Replaces instructions in `start..end` (cod addresses) with `new_opcodes`,
+// their addresses are ignored and operands written as they are. Code after
+// range moves, jumps, calls, case tables and publics pointing there follow
+// it. Debug info would point to moved code and is dropped.
+pub fn replace_range(
+    plugin: &mut Plugin,
+    start: usize,
+    end: usize,
+    new_opcodes: &[Opcode],
+) -> Result<(), PatchError> {
+    let mut writer =
+        Writer::from_plugin(plugin).map_err(|e| PatchError::InvalidImage(e.to_string()))?;
+    let cod_size = plugin.cod_size();
+
+    // Case table cells are not instruction boundaries
+    let is_boundary = |address: usize| {
+        address == cod_size
+            || writer
+                .opcodes
+                .iter()
+                .any(|o| o.address == address && !o.code.is_pseudo())
+    };
+    if start >= end || !is_boundary(start) || !is_boundary(end) {
+        return Err(PatchError::InvalidRange { start, end });
+    }
+
+    let mut size = 0;
+    for opcode in new_opcodes {
+        let cells = opcode_cells(opcode).map_err(|e| PatchError::InvalidImage(e.to_string()))?;
+        size += cells.len() * plugin.cellsize();
+    }
+    let moved = |address: usize| {
+        if address < end {
+            address
+        } else {
+            address + size - (end - start)
+        }
+    };
+    let inside = |address: usize| start < address && address < end;
+
+    let targets: HashMap<usize, usize> = code_targets(&writer.opcodes, cod_size)
+        .into_iter()
+        .collect();
+    let mut opcodes = vec![];
+    for (i, opcode) in writer.opcodes.iter().enumerate() {
+        if start <= opcode.address && opcode.address < end {
+            continue;
+        }
+        if opcode.address == end {
+            opcodes.extend_from_slice(new_opcodes);
+        }
+
+        let mut opcode = *opcode;
+        if let Some(&target) = targets.get(&i) {
+            if inside(target) {
+                // Case table jumps are operands themselves
+                let from = match opcode.code {
+                    OP_CASENONE | OP_CASEJMP => opcode.address,
+                    _ => opcode.address + plugin.cellsize(),
+                };
+                return Err(PatchError::OrphanedJump { from, target });
+            }
+            opcode.param = Some(match opcode.code {
+                // Relative to the next instruction, which may move as well
+                OP_JREL => {
+                    let next = writer.opcodes.get(i + 1).map_or(cod_size, |o| o.address);
+                    moved(target).wrapping_sub(moved(next)) as u32
+                }
+                _ => moved(target) as u32,
+            });
+        }
+        opcodes.push(opcode);
+    }
+    if end == cod_size {
+        opcodes.extend_from_slice(new_opcodes);
+    }
+
+    for public in writer.publics.iter_mut() {
+        if inside(public.address) {
+            return Err(PatchError::OrphanedPublic(
+                public.name.to_string_lossy().into_owned(),
+            ));
+        }
+        public.address = moved(public.address);
+    }
+    if writer.cip != u32::MAX {
+        writer.cip = moved(writer.cip as usize) as u32;
+    }
+    writer.opcodes = opcodes;
+    writer.flags &= !AmxFlags::DEBUG.bits();
+    writer.debug_info.clear();
+
+    let bin = writer
+        .write()
+        .map_err(|e| PatchError::InvalidImage(e.to_string()))?;
+    *plugin = Plugin::try_from(bin).map_err(|e| PatchError::InvalidImage(e.to_string()))?;
+    Ok(())
+}
+
+impl Plugin<'_> {
+    /// Replaces instructions in cod address `range` with `new_opcodes`, see
+    /// `replace_range`.
+    pub fn patch(&mut self, range: Range<usize>, new_opcodes: &[Opcode]) -> Result<(), PatchError> {
+        replace_range(self, range.start, range.end, new_opcodes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use byteorder::{ByteOrder, LittleEndian};
+
+    use super::{
+        nop_range, rename_native, replace_range, replace_string, retarget_native, PatchError,
+    };
+    use crate::amx::OpcodeType::*;
+    use crate::amx::{Opcode, OpcodeType, Plugin};
+    use crate::amxx::File;
+    use crate::analysis::native_calls;
+    use crate::facade::{decompile, DecompileOptions};
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    #[test]
+    fn it_replace_string_and_repack() {
+        let mut amxmod_plugin = Plugin::try_from(load_fixture("simple.amx183")).unwrap();
+        replace_string(&mut amxmod_plugin, 0x38, "2").unwrap();
+
+        assert_eq!(amxmod_plugin.read_string(0x38), Some("2".to_owned()));
+        let packed = File::pack(&amxmod_plugin).unwrap();
+        let source = decompile(&packed.bin, &DecompileOptions::default()).unwrap();
+        assert!(source.contains("\"2\""));
+        assert!(!source.contains("\"0.1\""));
+    }
+
+    #[test]
+    fn it_err_on_oversized_string() {
+        let mut amxmod_plugin = Plugin::try_from(load_fixture("simple.amx183")).unwrap();
+        let original = amxmod_plugin.to_bytes();
+
+        assert_eq!(
+            replace_string(&mut amxmod_plugin, 0x38, "0.1.2"),
+            Err(PatchError::StringTooLong {
+                available: 3,
+                required: 5
+            })
+        );
+        assert_eq!(amxmod_plugin.to_bytes(), original);
+    }
+
+    #[test]
+    fn it_replace_packed_string() {
+        let mut builder = PluginBuilder::new();
+        // "abcdef" packed into two cells
+        let addr = builder.array(&[0x6162_6364, 0x6566_0000]);
+        builder.public("plugin_init").op(OP_PROC).op(OP_RETN);
+        let mut amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        replace_string(&mut amxmod_plugin, addr as usize, "xyz").unwrap();
+
+        let dat = amxmod_plugin.dat_slice().unwrap();
+        let addr = addr as usize;
+        assert_eq!(&dat[addr..addr + 8], &[0, b'z', b'y', b'x', 0, 0, 0, 0][..]);
+    }
+
+    fn native_names(plugin: &Plugin) -> Vec<String> {
+        plugin
+            .natives()
+            .unwrap()
+            .iter()
+            .map(|n| n.name.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn it_rename_native_in_place() {
+        let mut amxmod_plugin = Plugin::try_from(load_fixture("two_natives.amx183")).unwrap();
+        let original = amxmod_plugin.to_bytes();
+        rename_native(&mut amxmod_plugin, "native_one", "native_1").unwrap();
+
+        assert!(amxmod_plugin.verify().is_ok());
+        assert_eq!(native_names(&amxmod_plugin), ["native_1", "native_two"]);
+        assert_eq!(amxmod_plugin.to_bytes().len(), original.len());
+    }
+
+    #[test]
+    fn it_rename_inline_native_name() {
+        let mut builder = PluginBuilder::new();
+        builder.inline_names();
+        builder.native("get_user_money");
+        let mut amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        rename_native(&mut amxmod_plugin, "get_user_money", "cs_get_user_money").unwrap();
+        assert!(amxmod_plugin.verify().is_ok());
+        assert_eq!(native_names(&amxmod_plugin), ["cs_get_user_money"]);
+        assert_eq!(
+            rename_native(
+                &mut amxmod_plugin,
+                "cs_get_user_money",
+                "custom_get_user_money"
+            ),
+            Err(PatchError::InvalidName("custom_get_user_money".to_owned()))
+        );
+    }
+
+    #[test]
+    fn it_rename_native_by_appending_name() {
+        let mut amxmod_plugin = Plugin::try_from(load_fixture("two_natives.amx183")).unwrap();
+        let opcodes = amxmod_plugin.opcodes().unwrap();
+        let dat = amxmod_plugin.dat_slice().unwrap().to_vec();
+        rename_native(&mut amxmod_plugin, "native_two", "custom_get_user_money").unwrap();
+
+        assert!(amxmod_plugin.verify().is_ok());
+        assert_eq!(
+            native_names(&amxmod_plugin),
+            ["native_one", "custom_get_user_money"]
+        );
+        assert_eq!(amxmod_plugin.opcodes().unwrap(), opcodes);
+        assert_eq!(amxmod_plugin.dat_slice().unwrap(), &dat[..]);
+
+        let packed = File::pack(&amxmod_plugin).unwrap();
+        let sections = File::try_from(packed.bin.into_vec())
+            .unwrap()
+            .sections()
+            .unwrap();
+        assert_eq!(sections[0].unpack_plugin().unwrap(), amxmod_plugin);
+    }
+
+    #[test]
+    fn it_retarget_native_calls() {
+        let mut amxmod_plugin = Plugin::try_from(load_fixture("two_natives.amx183")).unwrap();
+
+        assert_eq!(
+            retarget_native(&mut amxmod_plugin, "native_one", "native_two"),
+            Ok(1)
+        );
+        assert!(amxmod_plugin.verify().is_ok());
+        assert_eq!(
+            called_natives(&amxmod_plugin),
+            [
+                ("func".to_owned(), "native_two".to_owned()),
+                ("func".to_owned(), "native_two".to_owned())
+            ]
+        );
+        assert_eq!(
+            retarget_native(&mut amxmod_plugin, "native_one", "native_three"),
+            Err(PatchError::NativeNotFound("native_three".to_owned()))
+        );
+    }
+
+    #[test]
+    fn it_err_on_unknown_native() {
+        let mut amxmod_plugin = Plugin::try_from(load_fixture("two_natives.amx183")).unwrap();
+
+        assert_eq!(
+            rename_native(&mut amxmod_plugin, "native_three", "x"),
+            Err(PatchError::NativeNotFound("native_three".to_owned()))
+        );
+    }
+
+    // plugin_init calls `log` and `steal`, client_connect calls `log`
+    fn calling_plugin(jump_into_call: bool) -> (Plugin<'static>, usize, usize) {
+        let mut builder = PluginBuilder::new();
+        let log = builder.native("log");
+        let steal = builder.native("steal");
+        builder.public("plugin_init").op(OP_PROC);
+        let jump = builder.here();
+        builder
+            .op_param(OP_JUMP, 0)
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_SYSREQ_C, log)
+            .op_param(OP_STACK, 4);
+        let start = builder.here();
+        builder.op_param(OP_PUSH_C, 0);
+        let sysreq = builder.here();
+        builder.op_param(OP_SYSREQ_C, steal).op_param(OP_STACK, 4);
+        let end = builder.here();
+        builder
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN)
+            .public("client_connect")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_SYSREQ_C, log)
+            .op_param(OP_STACK, 4)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        builder.patch(jump + 4, if jump_into_call { sysreq } else { jump + 8 });
+
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        (amxmod_plugin, start as usize, end as usize)
+    }
+
+    fn called_natives(plugin: &Plugin) -> Vec<(String, String)> {
+        native_calls(plugin)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.function, c.name))
+            .collect()
+    }
+
+    #[test]
+    fn it_nop_native_call() {
+        let (mut amxmod_plugin, start, end) = calling_plugin(false);
+        let original = amxmod_plugin.to_bytes();
+        nop_range(&mut amxmod_plugin, start, end, false).unwrap();
+
+        assert!(amxmod_plugin.verify().is_ok());
+        assert_eq!(
+            called_natives(&amxmod_plugin),
+            [
+                ("plugin_init".to_owned(), "log".to_owned()),
+                ("client_connect".to_owned(), "log".to_owned())
+            ]
+        );
+        let opcodes = amxmod_plugin.opcodes().unwrap();
+        assert_eq!(
+            opcodes.iter().filter(|o| o.code == OP_NOP).count(),
+            (end - start) / 4
+        );
+
+        // Everything outside of range is byte identical
+        let cod = LittleEndian::read_u32(&original[12..]) as usize;
+        let patched = amxmod_plugin.to_bytes();
+        assert!((0..original.len())
+            .filter(|&i| original[i] != patched[i])
+            .all(|i| cod + start <= i && i < cod + end));
+    }
+
+    #[test]
+    fn it_refuse_orphaned_jump() {
+        let (mut amxmod_plugin, start, end) = calling_plugin(true);
+        let original = amxmod_plugin.to_bytes();
+
+        assert_eq!(
+            nop_range(&mut amxmod_plugin, start, end, false),
+            Err(PatchError::OrphanedJump {
+                from: 0x10,
+                target: start + 8
+            })
+        );
+        assert_eq!(amxmod_plugin.to_bytes(), original);
+
+        nop_range(&mut amxmod_plugin, start, end, true).unwrap();
+        let jump = amxmod_plugin.opcodes().unwrap()[1];
+        assert_eq!(jump.code, OP_JUMP);
+        assert_eq!(jump.param, Some(end as u32));
+    }
+
+    #[test]
+    fn it_refuse_orphaned_relative_jump_and_switch() {
+        let mut builder = PluginBuilder::new();
+        let steal = builder.native("steal");
+        builder.public("plugin_init").op(OP_PROC);
+        let jrel = builder.here();
+        builder.op_param(OP_JREL, 0);
+        let start = builder.here();
+        builder.op_param(OP_PUSH_C, 0);
+        let sysreq = builder.here();
+        builder.op_param(OP_SYSREQ_C, steal).op_param(OP_STACK, 4);
+        let end = builder.here();
+        builder.op_param(OP_CONST_PRI, 1);
+        let switch = builder.here();
+        builder.op_param(OP_SWITCH, 0).op(OP_RETN);
+        let table = builder.here();
+        builder
+            .cells(&[OP_CASETBL as u32, 0, switch + 8])
+            .op(OP_RETN)
+            .patch(jrel + 4, sysreq - start)
+            .patch(switch + 4, table);
+        let mut amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+        let (start, end) = (start as usize, end as usize);
+
+        assert_eq!(
+            nop_range(&mut amxmod_plugin, start, end, false),
+            Err(PatchError::OrphanedJump {
+                from: jrel as usize + 4,
+                target: sysreq as usize
+            })
+        );
+        nop_range(&mut amxmod_plugin, start, end, true).unwrap();
+        let jump = amxmod_plugin.opcodes().unwrap()[1];
+        assert_eq!(jump.code, OP_JREL);
+        assert_eq!(jump.param, Some((end - start) as u32));
+
+        let table = table as usize;
+        assert_eq!(
+            nop_range(&mut amxmod_plugin, table, table + 12, true),
+            Err(PatchError::OrphanedJump {
+                from: switch as usize + 4,
+                target: table
+            })
+        );
+    }
+
+    #[test]
+    fn it_refuse_partial_instructions() {
+        let (mut amxmod_plugin, start, end) = calling_plugin(false);
+
+        assert_eq!(
+            nop_range(&mut amxmod_plugin, start + 4, end, false),
+            Err(PatchError::InvalidRange {
+                start: start + 4,
+                end
+            })
+        );
+    }
+
+    fn opcode(code: OpcodeType, param: Option<u32>) -> Opcode {
+        Opcode {
+            code,
+            address: 0,
+            param,
+        }
+    }
+
+    #[test]
+    fn it_replace_native_call_with_longer_code() {
+        let (mut amxmod_plugin, start, end) = calling_plugin(false);
+        let log = 0;
+        let new_opcodes = [
+            opcode(OP_PUSH_C, Some(0)),
+            opcode(OP_PUSH_C, Some(0)),
+            opcode(OP_SYSREQ_C, Some(log)),
+            opcode(OP_STACK, Some(8)),
+        ];
+        let client_connect = amxmod_plugin.publics().unwrap()[0].address;
+
+        replace_range(&mut amxmod_plugin, start, end, &new_opcodes).unwrap();
+
+        assert!(amxmod_plugin.verify().is_ok());
+        assert_eq!(
+            called_natives(&amxmod_plugin),
+            [
+                ("plugin_init".to_owned(), "log".to_owned()),
+                ("plugin_init".to_owned(), "log".to_owned()),
+                ("client_connect".to_owned(), "log".to_owned())
+            ]
+        );
+        let publics = amxmod_plugin.publics().unwrap();
+        assert_eq!(publics[0].name.to_str(), Ok("client_connect"));
+        assert_eq!(publics[0].address, client_connect + 8);
+    }
+
+    #[test]
+    fn it_move_jumps_over_removed_code() {
+        let mut builder = PluginBuilder::new();
+        let log = builder.native("log");
+        builder.public("plugin_init").op(OP_PROC).op(OP_ZERO_PRI);
+        let jzer = builder.here();
+        builder.op_param(OP_JZER, 0);
+        let start = builder.here();
+        builder
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_SYSREQ_C, log)
+            .op_param(OP_STACK, 4);
+        let end = builder.here();
+        builder.patch(jzer + 4, end).op(OP_ZERO_PRI).op(OP_RETN);
+        let mut amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        replace_range(&mut amxmod_plugin, start as usize, end as usize, &[]).unwrap();
+
+        let opcodes = amxmod_plugin.opcodes().unwrap();
+        assert_eq!(opcodes[2].code, OP_JZER);
+        assert_eq!(opcodes[2].param, Some(start));
+        assert_eq!(opcodes[3].address, start as usize);
+        assert_eq!(opcodes[3].code, OP_ZERO_PRI);
+    }
+
+    #[test]
+    fn it_move_relative_jumps_over_patched_code() {
+        let mut builder = PluginBuilder::new();
+        let log = builder.native("log");
+        builder.public("plugin_init").op(OP_PROC).op(OP_ZERO_PRI);
+        let jrel = builder.here();
+        builder.op_param(OP_JREL, 0);
+        let start = builder.here();
+        builder
+            .op_param(OP_PUSH_C, 0)
+            .op_param(OP_SYSREQ_C, log)
+            .op_param(OP_STACK, 4);
+        let end = builder.here();
+        builder.op(OP_ZERO_PRI).op_param(OP_JREL, 0);
+        let back = builder.here();
+        builder
+            .op(OP_RETN)
+            .patch(jrel + 4, end - start)
+            .patch(back - 4, (jrel as i32 - back as i32) as u32);
+        let mut amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        amxmod_plugin
+            .patch(start as usize..end as usize, &[opcode(OP_NOP, None)])
+            .unwrap();
+
+        let opcodes = amxmod_plugin.opcodes().unwrap();
+        assert_eq!(opcodes[2].code, OP_JREL);
+        assert_eq!(opcodes[2].param, Some(4));
+        assert_eq!(opcodes[4].address, start as usize + 4);
+        assert_eq!(opcodes[5].code, OP_JREL);
+        // Lands on the first JREL again
+        let next = opcodes[6].address as i32;
+        assert_eq!(next + opcodes[5].param.unwrap() as i32, jrel as i32);
+    }
+
+    #[test]
+    fn it_refuse_to_replace_jump_target() {
+        let (mut amxmod_plugin, start, end) = calling_plugin(true);
+
+        assert_eq!(
+            replace_range(&mut amxmod_plugin, start, end, &[]),
+            Err(PatchError::OrphanedJump {
+                from: 0x10,
+                target: start + 8
+            })
+        );
+    }
+}
@@ -0,0 +1,195 @@
+// Hashes of plugins from trusted builds, e.g. official AMX Mod X releases,
+// telling installed plugins matching them apart from modified binaries.
+// Images are compared, not containers, so repacked plugins still match.
+
+use std::fmt;
+
+use crate::amx::Plugin;
+use crate::error::AmxError;
+use crate::fingerprint::PluginFingerprint;
+
+// Placeholder of missing normalized cod hash
+const NO_HASH: &str = "-";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownRelease {
+    // Hex encoded SHA-256 digests, see `PluginFingerprint`
+    pub cod: String,
+    pub dat: String,
+    pub normalized_cod: Option<String>,
+    // File name in plugins directory, e.g. admin.amxx
+    pub file: String,
+    // e.g. amxmodx 1.9.0
+    pub release: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReleaseMatch {
+    // Same cod and dat as known plugin
+    Official(KnownRelease),
+    // Same code up to strings or same file name as known plugin
+    Modified(KnownRelease),
+    Unknown,
+}
+
+impl fmt::Display for ReleaseMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReleaseMatch::Official(known) => {
+                write!(f, "matches {} of {}", known.file, known.release)
+            }
+            ReleaseMatch::Modified(known) => {
+                write!(f, "modified {} of {}", known.file, known.release)
+            }
+            ReleaseMatch::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReleaseDatabase {
+    pub plugins: Vec<KnownRelease>,
+}
+
+impl ReleaseDatabase {
+    pub fn new() -> ReleaseDatabase {
+        ReleaseDatabase::default()
+    }
+
+    pub fn add(&mut self, known: KnownRelease) -> &mut Self {
+        if !self.plugins.contains(&known) {
+            self.plugins.push(known);
+        }
+        self
+    }
+
+    // Remembers plugin of trusted build as `file` of `release`
+    pub fn learn(&mut self, plugin: &Plugin, file: &str, release: &str) -> Result<(), AmxError> {
+        let fingerprint = plugin.fingerprint()?;
+        self.add(KnownRelease {
+            cod: fingerprint.cod,
+            dat: fingerprint.dat,
+            normalized_cod: fingerprint.normalized_cod,
+            file: file.to_owned(),
+            release: release.to_owned(),
+        });
+        Ok(())
+    }
+
+    // Tab separated cod, dat and normalized cod hashes, file and release per
+    // line, # comments skipped
+    pub fn parse(text: &str) -> Result<ReleaseDatabase, AmxError> {
+        let mut database = ReleaseDatabase::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let (cod, dat, normalized_cod, file, release) = match fields[..] {
+                [cod, dat, normalized_cod, file, release] => {
+                    (cod, dat, normalized_cod, file, release)
+                }
+                _ => {
+                    return Err(AmxError::Unsupported(
+                        "release line is not cod, dat, normalized cod, file, release",
+                    ))
+                }
+            };
+            database.add(KnownRelease {
+                cod: cod.to_owned(),
+                dat: dat.to_owned(),
+                normalized_cod: match normalized_cod {
+                    NO_HASH => None,
+                    hash => Some(hash.to_owned()),
+                },
+                file: file.to_owned(),
+                release: release.to_owned(),
+            });
+        }
+        Ok(database)
+    }
+
+    // Exact match wherever plugin was renamed to, otherwise closest known
+    // plugin it could have been modified from
+    pub fn identify(&self, file: &str, fingerprint: &PluginFingerprint) -> ReleaseMatch {
+        let official = self
+            .plugins
+            .iter()
+            .find(|k| k.cod == fingerprint.cod && k.dat == fingerprint.dat);
+        if let Some(known) = official {
+            return ReleaseMatch::Official(known.clone());
+        }
+
+        let modified = self
+            .plugins
+            .iter()
+            .find(|k| k.normalized_cod.is_some() && k.normalized_cod == fingerprint.normalized_cod)
+            .or_else(|| self.plugins.iter().find(|k| k.file == file));
+        match modified {
+            Some(known) => ReleaseMatch::Modified(known.clone()),
+            None => ReleaseMatch::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for ReleaseDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for known in self.plugins.iter() {
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}",
+                known.cod,
+                known.dat,
+                known.normalized_cod.as_deref().unwrap_or(NO_HASH),
+                known.file,
+                known.release
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{ReleaseDatabase, ReleaseMatch};
+    use crate::amx::Plugin;
+    use crate::util::tests::load_fixture;
+
+    fn plugin(name: &str) -> Plugin<'static> {
+        Plugin::try_from(load_fixture(name)).unwrap()
+    }
+
+    #[test]
+    fn it_identify_release_plugins() {
+        let mut database = ReleaseDatabase::new();
+        database
+            .learn(&plugin("simple.amx183"), "simple.amxx", "amxmodx 1.8.3")
+            .unwrap();
+        let database = ReleaseDatabase::parse(&database.to_string()).unwrap();
+
+        let fingerprint = plugin("simple.amx183").fingerprint().unwrap();
+        let official = database.identify("renamed.amxx", &fingerprint);
+        assert!(matches!(official, ReleaseMatch::Official(_)));
+        assert_eq!(official.to_string(), "matches simple.amxx of amxmodx 1.8.3");
+
+        let mut modified = fingerprint.clone();
+        modified.dat = "0".repeat(64);
+        assert_eq!(
+            database.identify("renamed.amxx", &modified).to_string(),
+            "modified simple.amxx of amxmodx 1.8.3"
+        );
+
+        let other = plugin("two_natives.amx183").fingerprint().unwrap();
+        assert_eq!(
+            database.identify("other.amxx", &other),
+            ReleaseMatch::Unknown
+        );
+        assert!(matches!(
+            database.identify("simple.amxx", &other),
+            ReleaseMatch::Modified(_)
+        ));
+        assert!(ReleaseDatabase::parse("abc\tdef\n").is_err());
+    }
+}
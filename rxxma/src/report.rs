@@ -0,0 +1,418 @@
+// Human readable summary of plugin file, file(1) for amxx: container
+// sections, amx header, tables, DAT strings and opcode usage. Rendered as
+// plain text or as Markdown for audit notes and issue trackers.
+
+use std::fmt;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::amx::plugin::AmxFlags;
+use crate::amx::{OpcodeType, Plugin};
+use crate::analysis::{
+    dat_entropy, precached_resources, shannon_entropy, EntropyRegion, Hook, PrecachedResource,
+};
+use crate::error::AmxError;
+use crate::facade::{self, PluginInfo};
+
+// Sliding window dat_entropy looks for unexplained data with
+pub const ENTROPY_WINDOW: usize = 64;
+
+// Header fields as stored in 32 bit amx image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub size: u32,
+    pub magic: u16,
+    pub file_version: u8,
+    pub amx_version: u8,
+    pub flags: u16,
+    pub defsize: u16,
+    pub cod: u32,
+    pub dat: u32,
+    pub hea: u32,
+    pub stp: u32,
+    pub cip: u32,
+}
+
+impl Header {
+    fn read(bin: &[u8]) -> Result<Header, AmxError> {
+        let header = bin.get(..32).ok_or(AmxError::Eof {
+            what: "amx header",
+            offset: 0,
+        })?;
+
+        Ok(Header {
+            size: LittleEndian::read_u32(header),
+            magic: LittleEndian::read_u16(&header[4..]),
+            file_version: header[6],
+            amx_version: header[7],
+            flags: LittleEndian::read_u16(&header[8..]),
+            defsize: LittleEndian::read_u16(&header[10..]),
+            cod: LittleEndian::read_u32(&header[12..]),
+            dat: LittleEndian::read_u32(&header[16..]),
+            hea: LittleEndian::read_u32(&header[20..]),
+            stp: LittleEndian::read_u32(&header[24..]),
+            cip: LittleEndian::read_u32(&header[28..]),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    // Size of file report is made for
+    pub file_size: usize,
+    pub info: PluginInfo,
+    pub header: Header,
+    // Packed and unpacked strings found in DAT
+    pub strings: usize,
+    // Bits per byte over whole DAT
+    pub dat_entropy: f64,
+    // High entropy DAT windows not explained by strings or referenced arrays
+    pub high_entropy: Vec<EntropyRegion>,
+    // Events, Ham functions, forwards and messages hooked, empty when cod
+    // does not decode
+    pub hooks: Vec<Hook>,
+    // Models, sounds and sprites with their precache calls
+    pub resources: Vec<PrecachedResource>,
+    // Opcode counts, most used first, junk cells counted as UNKNOWN
+    pub histogram: Vec<(OpcodeType, usize)>,
+}
+
+fn histogram(plugin: &Plugin) -> Result<Vec<(OpcodeType, usize)>, AmxError> {
+    let mut histogram: Vec<(OpcodeType, usize)> = vec![];
+    for opcode in plugin.opcode_iter_lenient()? {
+        let opcode = opcode?;
+        match histogram.iter_mut().find(|(code, _)| *code == opcode.code) {
+            Some((_, count)) => *count += 1,
+            None => histogram.push((opcode.code, 1)),
+        }
+    }
+
+    histogram.sort_by(|(a, a_count), (b, b_count)| {
+        b_count
+            .cmp(a_count)
+            .then_with(|| (*a as usize).cmp(&(*b as usize)))
+    });
+    Ok(histogram)
+}
+
+/// Summarizes amxx or amx file contents for `amxxtool info`.
+///
+/// ```
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let report = rxxma::report::report(&bytes).unwrap();
+/// assert_eq!(report.strings, 3);
+/// assert!(report.to_string().contains("Natives: register_plugin\n"));
+/// ```
+pub fn report(bytes: &[u8]) -> Result<Report, AmxError> {
+    let info = facade::inspect(bytes)?;
+    let plugin = facade::load_plugin(bytes)?;
+    let dat = plugin.dat_slice()?;
+
+    Ok(Report {
+        file_size: bytes.len(),
+        info,
+        header: Header::read(&plugin.bin)?,
+        strings: plugin.strings()?.len(),
+        dat_entropy: shannon_entropy(dat),
+        high_entropy: dat_entropy(&plugin, ENTROPY_WINDOW).unwrap_or_default(),
+        hooks: plugin.hooks().unwrap_or_default(),
+        resources: precached_resources(&plugin).unwrap_or_default(),
+        histogram: histogram(&plugin)?,
+    })
+}
+
+fn percent(part: u32, whole: u32) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    f64::from(part) * 100.0 / f64::from(whole)
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let info = &self.info;
+        writeln!(f, "Format: {:?}, {} bytes", info.format, self.file_size)?;
+        if let Some(metadata) = &info.metadata {
+            let field = |value: &Option<String>| value.clone().unwrap_or_else(|| "?".to_owned());
+            writeln!(
+                f,
+                "Plugin: {} {} by {}",
+                field(&metadata.name),
+                field(&metadata.version),
+                field(&metadata.author)
+            )?;
+        }
+        for section in info.sections.iter() {
+            writeln!(
+                f,
+                "Section: {} bit, {} bytes on disk, {} bytes image ({:.1}%), {} bytes memory",
+                u32::from(section.cellsize) * 8,
+                section.disksize,
+                section.imagesize,
+                percent(section.disksize, section.imagesize),
+                section.memsize
+            )?;
+        }
+
+        let header = &self.header;
+        let flags = AmxFlags::from_bits_truncate(header.flags);
+        writeln!(
+            f,
+            "Header: size 0x{:X}, magic 0x{:X}, file version {}, amx version {}, defsize {}",
+            header.size, header.magic, header.file_version, header.amx_version, header.defsize
+        )?;
+        writeln!(f, "Flags: 0x{:X} {:?}", header.flags, flags)?;
+        writeln!(
+            f,
+            "Offsets: cod 0x{:X}, dat 0x{:X}, hea 0x{:X}, stp 0x{:X}, cip 0x{:X}",
+            header.cod, header.dat, header.hea, header.stp, header.cip
+        )?;
+
+        writeln!(f, "Cod: {} bytes", info.cod_size)?;
+        writeln!(
+            f,
+            "Dat: {} bytes, {} strings, entropy {:.2} bits/byte",
+            info.dat_size, self.strings, self.dat_entropy
+        )?;
+        for region in self.high_entropy.iter() {
+            writeln!(
+                f,
+                "High entropy dat: 0x{:X}..0x{:X}, {:.2} bits/byte",
+                region.address,
+                region.address + region.size,
+                region.entropy
+            )?;
+        }
+        match info.heap_worst_case {
+            Some(worst_case) => writeln!(
+                f,
+                "Heap: {} bytes budget, {} bytes worst case",
+                info.heap_budget, worst_case
+            )?,
+            None => writeln!(f, "Heap: {} bytes budget", info.heap_budget)?,
+        }
+
+        writeln!(f, "Publics: {}", info.publics.join(", "))?;
+        writeln!(f, "Natives: {}", info.natives.join(", "))?;
+        writeln!(f, "Libraries: {}", info.libraries.join(", "))?;
+        for hook in self.hooks.iter() {
+            writeln!(f, "Hook: {}", hook)?;
+        }
+        for resource in self.resources.iter() {
+            writeln!(f, "Precached: {:?} {}", resource.kind, resource.path)?;
+        }
+
+        let total: usize = self.histogram.iter().map(|(_, count)| count).sum();
+        writeln!(f, "Opcodes: {}", total)?;
+        for (code, count) in self.histogram.iter() {
+            writeln!(f, "  {:<14}{}", code.to_string(), count)?;
+        }
+        Ok(())
+    }
+}
+
+// Table cell text, pipes would end the cell even inside code spans
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn code_list(names: &[String]) -> String {
+    if names.is_empty() {
+        return "none".to_owned();
+    }
+    names
+        .iter()
+        .map(|n| format!("`{}`", escape_markdown(n)))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Renders report as Markdown document titled `title`: overview and header
+/// tables, symbols, hooks, precached resources and opcode histogram.
+///
+/// ```
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let report = rxxma::report::report(&bytes).unwrap();
+/// let text = rxxma::report::markdown(&report, "simple.amxx");
+/// assert!(text.starts_with("# simple.amxx\n"));
+/// assert!(text.contains("| Natives | `register_plugin` |\n"));
+/// ```
+pub fn markdown(report: &Report, title: &str) -> String {
+    let info = &report.info;
+    let mut page = format!("# {}\n\n", title);
+
+    page.push_str("| | |\n|---|---|\n");
+    page.push_str(&format!(
+        "| Format | {:?}, {} bytes |\n",
+        info.format, report.file_size
+    ));
+    if let Some(metadata) = &info.metadata {
+        let field = |value: &Option<String>| value.clone().unwrap_or_else(|| "?".to_owned());
+        page.push_str(&format!(
+            "| Plugin | {} {} by {} |\n",
+            escape_markdown(&field(&metadata.name)),
+            escape_markdown(&field(&metadata.version)),
+            escape_markdown(&field(&metadata.author))
+        ));
+    }
+    page.push_str(&format!("| Cod | {} bytes |\n", info.cod_size));
+    page.push_str(&format!(
+        "| Dat | {} bytes, {} strings, entropy {:.2} bits/byte |\n",
+        info.dat_size, report.strings, report.dat_entropy
+    ));
+    match info.heap_worst_case {
+        Some(worst_case) => page.push_str(&format!(
+            "| Heap | {} bytes budget, {} bytes worst case |\n",
+            info.heap_budget, worst_case
+        )),
+        None => page.push_str(&format!("| Heap | {} bytes budget |\n", info.heap_budget)),
+    }
+    page.push_str(&format!("| Publics | {} |\n", code_list(&info.publics)));
+    page.push_str(&format!("| Natives | {} |\n", code_list(&info.natives)));
+    page.push_str(&format!("| Libraries | {} |\n", code_list(&info.libraries)));
+
+    if !info.sections.is_empty() {
+        page.push_str("\n## Sections\n\n");
+        page.push_str("| Cell | On disk | Image | Ratio | Memory |\n|---|---|---|---|---|\n");
+        for section in info.sections.iter() {
+            page.push_str(&format!(
+                "| {} bit | {} | {} | {:.1}% | {} |\n",
+                u32::from(section.cellsize) * 8,
+                section.disksize,
+                section.imagesize,
+                percent(section.disksize, section.imagesize),
+                section.memsize
+            ));
+        }
+    }
+
+    let header = &report.header;
+    let flags = AmxFlags::from_bits_truncate(header.flags);
+    page.push_str("\n## Header\n\n| Field | Value |\n|---|---|\n");
+    for (field, value) in [
+        ("size", format!("0x{:X}", header.size)),
+        ("magic", format!("0x{:X}", header.magic)),
+        ("file version", header.file_version.to_string()),
+        ("amx version", header.amx_version.to_string()),
+        ("flags", format!("0x{:X} {:?}", header.flags, flags)),
+        ("defsize", header.defsize.to_string()),
+        ("cod", format!("0x{:X}", header.cod)),
+        ("dat", format!("0x{:X}", header.dat)),
+        ("hea", format!("0x{:X}", header.hea)),
+        ("stp", format!("0x{:X}", header.stp)),
+        ("cip", format!("0x{:X}", header.cip)),
+    ]
+    .iter()
+    {
+        page.push_str(&format!("| {} | {} |\n", field, value));
+    }
+
+    if !report.high_entropy.is_empty() {
+        page.push_str("\n## High entropy DAT\n\n| Range | Entropy |\n|---|---|\n");
+        for region in report.high_entropy.iter() {
+            page.push_str(&format!(
+                "| 0x{:X}..0x{:X} | {:.2} bits/byte |\n",
+                region.address,
+                region.address + region.size,
+                region.entropy
+            ));
+        }
+    }
+
+    if !report.hooks.is_empty() {
+        page.push_str("\n## Hooks\n\n");
+        for hook in report.hooks.iter() {
+            page.push_str(&format!("- `{}`\n", hook));
+        }
+    }
+
+    if !report.resources.is_empty() {
+        page.push_str("\n## Precached resources\n\n");
+        page.push_str("| Path | Kind | Dynamic | Precached by |\n|---|---|---|---|\n");
+        for resource in report.resources.iter() {
+            let sites: Vec<String> = resource
+                .sites
+                .iter()
+                .map(|s| format!("`{}` in `{}` at 0x{:X}", s.native, s.function, s.address))
+                .collect();
+            page.push_str(&format!(
+                "| `{}` | {:?} | {} | {} |\n",
+                escape_markdown(&resource.path),
+                resource.kind,
+                if resource.dynamic { "yes" } else { "no" },
+                escape_markdown(&sites.join(", "))
+            ));
+        }
+    }
+
+    let total: usize = report.histogram.iter().map(|(_, count)| count).sum();
+    page.push_str(&format!(
+        "\n## Opcodes\n\n{} total\n\n| Opcode | Count |\n|---|---|\n",
+        total
+    ));
+    for (code, count) in report.histogram.iter() {
+        page.push_str(&format!("| `{}` | {} |\n", code, count));
+    }
+
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{markdown, report};
+    use crate::amx::OpcodeType::*;
+    use crate::facade::Format;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_report_amxx_file() {
+        let bytes = fs::read("test/fixtures/simple.amxx183").unwrap();
+        let report = report(&bytes).unwrap();
+
+        assert_eq!(report.info.format, Format::Amxx);
+        assert_eq!(report.header.magic, 0xF1E0);
+        assert_eq!(report.header.defsize, 8);
+        assert_eq!(report.histogram.iter().map(|(_, c)| c).sum::<usize>(), 11);
+        assert_eq!(report.histogram[0], (OP_PUSH_C, 4));
+
+        let text = report.to_string();
+        assert!(text.starts_with(&format!("Format: Amxx, {} bytes\n", bytes.len())));
+        assert!(text.contains("Plugin: simple plugin 0.1 by Fedcomp\n"));
+        assert!(text.contains("Publics: plugin_init\n"));
+        assert!(text.contains("  SYSREQ.C      1\n"));
+    }
+
+    #[test]
+    fn it_render_markdown_report() {
+        let mut builder = PluginBuilder::new();
+        let precache_model = builder.native("precache_model");
+        let model = builder.string("models/a|b.mdl");
+        builder
+            .public("plugin_precache")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, model)
+            .op_param(OP_PUSH_C, 4);
+        let call = builder.here();
+        builder
+            .op_param(OP_SYSREQ_C, precache_model)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let bytes = builder.build();
+        let report = report(&bytes).unwrap();
+
+        assert_eq!(report.resources.len(), 1);
+        let text = markdown(&report, "precache.amx");
+        assert!(text.starts_with("# precache.amx\n\n| | |\n|---|---|\n| Format | Amx, "));
+        assert!(text.contains("| Publics | `plugin_precache` |\n"));
+        assert!(text.contains("| Libraries | none |\n"));
+        assert!(text.contains(&format!(
+            "| `models/a\\|b.mdl` | Model | no | `precache_model` in `plugin_precache` at 0x{:X} |\n",
+            call
+        )));
+        assert!(text.contains("| `SYSREQ.C` | 1 |\n"));
+        assert!(!text.contains("## Sections"));
+    }
+}
@@ -0,0 +1,492 @@
+// YARA alike rules for plugin contents, for sharing detection of known
+// backdoors as text files. Rule is a named block of conditions, one per
+// line, and optional quantifier of how many must hold:
+//
+//     rule steam_backdoor {
+//         string "STEAM_0:" nocase
+//         native set_user_flags
+//         opcodes PUSH.C ?? SYSREQ.C ?? STACK 8
+//         meta natives < 20
+//         condition all
+//     }
+//
+// Conditions: string (DAT string containing text), native, opcodes (cell
+// pattern as in sigscan), public, command, cvar, library and meta
+// comparing cod_size, dat_size, natives or publics with < = or >.
+// Quantifier is all (default), any or number of conditions.
+
+use std::fmt;
+
+use crate::amx::Plugin;
+use crate::analysis::{native_calls, registrations};
+use crate::error::AmxError;
+use crate::sigscan::Pattern;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetaKey {
+    CodSize,
+    DatSize,
+    Natives,
+    Publics,
+}
+
+impl MetaKey {
+    fn name(self) -> &'static str {
+        match self {
+            MetaKey::CodSize => "cod_size",
+            MetaKey::DatSize => "dat_size",
+            MetaKey::Natives => "natives",
+            MetaKey::Publics => "publics",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    String {
+        text: String,
+        nocase: bool,
+    },
+    Native(String),
+    Opcodes(Pattern),
+    Public(String),
+    Command(String),
+    Cvar(String),
+    Library(String),
+    Meta {
+        key: MetaKey,
+        operator: char,
+        value: usize,
+    },
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::String { text, nocase } => {
+                write!(
+                    f,
+                    "string {:?}{}",
+                    text,
+                    if *nocase { " nocase" } else { "" }
+                )
+            }
+            Condition::Native(name) => write!(f, "native {}", name),
+            Condition::Opcodes(pattern) => write!(f, "opcodes {}", pattern),
+            Condition::Public(name) => write!(f, "public {}", name),
+            Condition::Command(name) => write!(f, "command {}", name),
+            Condition::Cvar(name) => write!(f, "cvar {}", name),
+            Condition::Library(name) => write!(f, "library {}", name),
+            Condition::Meta {
+                key,
+                operator,
+                value,
+            } => write!(f, "meta {} {} {}", key.name(), operator, value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantifier {
+    All,
+    Any,
+    AtLeast(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    pub conditions: Vec<Condition>,
+    pub quantifier: Quantifier,
+}
+
+// Condition which held, with DAT addresses of strings or cod addresses of
+// native calls and patterns
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub condition: Condition,
+    pub addresses: Vec<usize>,
+}
+
+impl fmt::Display for Hit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.condition)?;
+        let addresses: Vec<String> = self
+            .addresses
+            .iter()
+            .map(|a| format!("0x{:X}", a))
+            .collect();
+        if !addresses.is_empty() {
+            write!(f, " at {}", addresses.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+    pub rule: String,
+    pub hits: Vec<Hit>,
+}
+
+// Quoted text with \" and \\ escapes, rest of line after closing quote
+fn quoted(text: &str) -> Option<(String, &str)> {
+    let mut chars = text.strip_prefix('"')?.char_indices();
+    let mut result = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((result, text[i + 2..].trim())),
+            '\\' => result.push(chars.next()?.1),
+            c => result.push(c),
+        }
+    }
+    None
+}
+
+fn condition(keyword: &str, rest: &str, line: usize) -> Result<Condition, AmxError> {
+    let invalid = |reason| AmxError::InvalidRule(line, reason);
+    let name = || match rest.split_whitespace().collect::<Vec<_>>()[..] {
+        [name] => Ok(name.to_owned()),
+        _ => Err(invalid("expected single name")),
+    };
+
+    Ok(match keyword {
+        "string" => {
+            let (text, modifier) = quoted(rest).ok_or_else(|| invalid("expected quoted text"))?;
+            let nocase = match modifier {
+                "" => false,
+                "nocase" => true,
+                _ => return Err(invalid("unknown string modifier")),
+            };
+            Condition::String { text, nocase }
+        }
+        "native" => Condition::Native(name()?),
+        "opcodes" => Condition::Opcodes(
+            rest.parse()
+                .map_err(|_| invalid("invalid opcodes pattern"))?,
+        ),
+        "public" => Condition::Public(name()?),
+        "command" => Condition::Command(name()?),
+        "cvar" => Condition::Cvar(name()?),
+        "library" => Condition::Library(name()?),
+        "meta" => {
+            let (key, operator, value) = match rest.split_whitespace().collect::<Vec<_>>()[..] {
+                [key, operator, value] => (key, operator, value),
+                _ => return Err(invalid("expected key, operator and value")),
+            };
+            let key = match key {
+                "cod_size" => MetaKey::CodSize,
+                "dat_size" => MetaKey::DatSize,
+                "natives" => MetaKey::Natives,
+                "publics" => MetaKey::Publics,
+                _ => return Err(invalid("unknown meta key")),
+            };
+            let operator = match operator {
+                "<" | "=" | ">" => operator.chars().next().unwrap_or_default(),
+                _ => return Err(invalid("unknown operator")),
+            };
+            let value = value.parse().map_err(|_| invalid("expected number"))?;
+            Condition::Meta {
+                key,
+                operator,
+                value,
+            }
+        }
+        _ => return Err(invalid("unknown condition")),
+    })
+}
+
+impl Rule {
+    // Rule blocks, empty lines and # comments are skipped
+    pub fn parse_list(list: &str) -> Result<Vec<Rule>, AmxError> {
+        let mut rules = vec![];
+        let mut current: Option<Rule> = None;
+
+        for (n, line) in list.lines().enumerate() {
+            let (n, line) = (n + 1, line.trim());
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = match line.find(char::is_whitespace) {
+                Some(space) => (&line[..space], line[space..].trim()),
+                None => (line, ""),
+            };
+
+            let rule = match current.as_mut() {
+                Some(rule) => rule,
+                None => {
+                    let name = match (keyword, rest.strip_suffix('{').map(str::trim)) {
+                        ("rule", Some(name)) if !name.is_empty() => name,
+                        _ => return Err(AmxError::InvalidRule(n, "expected rule NAME {")),
+                    };
+                    current = Some(Rule {
+                        name: name.to_owned(),
+                        conditions: vec![],
+                        quantifier: Quantifier::All,
+                    });
+                    continue;
+                }
+            };
+
+            match keyword {
+                "}" if rule.conditions.is_empty() => {
+                    return Err(AmxError::InvalidRule(n, "rule without conditions"))
+                }
+                "}" => rules.extend(current.take()),
+                "condition" => {
+                    rule.quantifier = match rest {
+                        "all" => Quantifier::All,
+                        "any" => Quantifier::Any,
+                        count => Quantifier::AtLeast(count.parse().map_err(|_| {
+                            AmxError::InvalidRule(n, "expected all, any or number")
+                        })?),
+                    }
+                }
+                keyword => rule.conditions.push(condition(keyword, rest, n)?),
+            }
+        }
+
+        match current {
+            Some(_) => Err(AmxError::InvalidRule(list.lines().count(), "unclosed rule")),
+            None => Ok(rules),
+        }
+    }
+}
+
+// Plugin parts conditions are checked against, read once for all rules
+struct Contents {
+    strings: Vec<(usize, String)>,
+    natives: Vec<String>,
+    calls: Vec<(String, usize)>,
+    publics: Vec<String>,
+    libraries: Vec<String>,
+    commands: Vec<String>,
+    cvars: Vec<String>,
+    cod_size: usize,
+    dat_size: usize,
+}
+
+impl Contents {
+    fn read(plugin: &Plugin) -> Result<Contents, AmxError> {
+        let registrations = registrations(plugin)?;
+        Ok(Contents {
+            strings: plugin
+                .strings()?
+                .into_iter()
+                .map(|s| (s.address, s.to_string_lossy()))
+                .collect(),
+            natives: plugin
+                .natives()?
+                .iter()
+                .map(|n| n.name.to_string_lossy().into_owned())
+                .collect(),
+            calls: native_calls(plugin)?
+                .into_iter()
+                .map(|c| (c.name, c.address))
+                .collect(),
+            publics: plugin
+                .publics()?
+                .iter()
+                .map(|p| p.name.to_string_lossy().into_owned())
+                .collect(),
+            libraries: plugin
+                .libraries()?
+                .iter()
+                .map(|l| l.to_string_lossy().into_owned())
+                .collect(),
+            commands: registrations.commands,
+            cvars: registrations.cvars,
+            cod_size: plugin.cod_size(),
+            dat_size: plugin.dat_slice()?.len(),
+        })
+    }
+
+    // Addresses condition holds at, None when it does not hold
+    fn check(
+        &self,
+        plugin: &Plugin,
+        condition: &Condition,
+    ) -> Result<Option<Vec<usize>>, AmxError> {
+        let holds = |found: bool| if found { Some(vec![]) } else { None };
+        let some = |addresses: Vec<usize>| Some(addresses).filter(|a| !a.is_empty());
+
+        Ok(match condition {
+            Condition::String { text, nocase } => {
+                let text = if *nocase {
+                    text.to_lowercase()
+                } else {
+                    text.clone()
+                };
+                some(
+                    self.strings
+                        .iter()
+                        .filter(|(_, s)| match nocase {
+                            true => s.to_lowercase().contains(&text),
+                            false => s.contains(&text),
+                        })
+                        .map(|(address, _)| *address)
+                        .collect(),
+                )
+            }
+            Condition::Native(name) if self.natives.contains(name) => Some(
+                self.calls
+                    .iter()
+                    .filter(|(native, _)| native == name)
+                    .map(|(_, address)| *address)
+                    .collect(),
+            ),
+            Condition::Native(_) => None,
+            Condition::Opcodes(pattern) => some(pattern.find(plugin)?),
+            Condition::Public(name) => holds(self.publics.contains(name)),
+            Condition::Command(name) => holds(self.commands.contains(name)),
+            Condition::Cvar(name) => holds(self.cvars.contains(name)),
+            Condition::Library(name) => holds(self.libraries.contains(name)),
+            Condition::Meta {
+                key,
+                operator,
+                value,
+            } => {
+                let actual = match key {
+                    MetaKey::CodSize => self.cod_size,
+                    MetaKey::DatSize => self.dat_size,
+                    MetaKey::Natives => self.natives.len(),
+                    MetaKey::Publics => self.publics.len(),
+                };
+                holds(match operator {
+                    '<' => actual < *value,
+                    '>' => actual > *value,
+                    _ => actual == *value,
+                })
+            }
+        })
+    }
+}
+
+// Rules holding for plugin, in rule order
+pub fn match_rules(plugin: &Plugin, rules: &[Rule]) -> Result<Vec<RuleMatch>, AmxError> {
+    let contents = Contents::read(plugin)?;
+
+    let mut matches = vec![];
+    for rule in rules.iter() {
+        let mut hits = vec![];
+        for condition in rule.conditions.iter() {
+            if let Some(addresses) = contents.check(plugin, condition)? {
+                hits.push(Hit {
+                    condition: condition.clone(),
+                    addresses,
+                });
+            }
+        }
+
+        let required = match rule.quantifier {
+            Quantifier::All => rule.conditions.len(),
+            Quantifier::Any => 1,
+            Quantifier::AtLeast(count) => count,
+        };
+        if hits.len() >= required {
+            matches.push(RuleMatch {
+                rule: rule.name.clone(),
+                hits,
+            });
+        }
+    }
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{match_rules, Condition, MetaKey, Quantifier, Rule};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::error::AmxError;
+    use crate::util::tests::PluginBuilder;
+
+    const RULES: &str = "# known backdoor\n\
+                         rule steam_backdoor {\n\
+                         \x20   string \"steam_0:1:\" nocase\n\
+                         \x20   native set_user_flags\n\
+                         \x20   opcodes PUSH.C ?? SYSREQ.C ?? STACK 8\n\
+                         \x20   meta natives < 5\n\
+                         }\n\
+                         \n\
+                         rule hud_plugin {\n\
+                         \x20   native show_hudmessage\n\
+                         \x20   public plugin_init\n\
+                         \x20   condition any\n\
+                         }\n\
+                         rule admin_menu {\n\
+                         \x20   library fakemeta\n\
+                         \x20   public plugin_init\n\
+                         \x20   condition 2\n\
+                         }\n";
+
+    #[test]
+    fn it_parse_rules() {
+        let rules = Rule::parse_list(RULES).unwrap();
+
+        assert_eq!(rules.len(), 3);
+        assert_eq!(
+            rules[0].conditions[0],
+            Condition::String {
+                text: "steam_0:1:".to_owned(),
+                nocase: true
+            }
+        );
+        assert_eq!(
+            rules[0].conditions[3],
+            Condition::Meta {
+                key: MetaKey::Natives,
+                operator: '<',
+                value: 5
+            }
+        );
+        assert_eq!(
+            rules[0].conditions[2].to_string(),
+            "opcodes 27 ?? 7B ?? 2C 8"
+        );
+        assert_eq!(rules[2].quantifier, Quantifier::AtLeast(2));
+
+        assert_eq!(
+            Rule::parse_list("rule broken {\n  sting \"x\"\n}\n"),
+            Err(AmxError::InvalidRule(2, "unknown condition"))
+        );
+        assert_eq!(
+            Rule::parse_list("rule open {\n  native x\n"),
+            Err(AmxError::InvalidRule(2, "unclosed rule"))
+        );
+    }
+
+    #[test]
+    fn it_match_rules() {
+        let mut builder = PluginBuilder::new();
+        let set_user_flags = builder.native("set_user_flags");
+        let owner = builder.string("STEAM_0:1:1337");
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, owner)
+            .op_param(OP_PUSH_C, 4);
+        let call = builder.here() as usize;
+        builder
+            .op_param(OP_SYSREQ_C, set_user_flags)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let rules = Rule::parse_list(RULES).unwrap();
+        let matches = match_rules(&amxmod_plugin, &rules).unwrap();
+
+        let names: Vec<&str> = matches.iter().map(|m| m.rule.as_str()).collect();
+        assert_eq!(names, ["steam_backdoor", "hud_plugin"]);
+        assert_eq!(
+            matches[0].hits[1].to_string(),
+            format!("native set_user_flags at 0x{:X}", call)
+        );
+        assert_eq!(matches[0].hits[2].addresses, [call - 8]);
+        assert_eq!(matches[1].hits.len(), 1);
+    }
+}
@@ -0,0 +1,556 @@
+// Heuristics for vetting plugins available only as .amxx: self-modifying
+// cod, hidden or dangerous console commands, backdoor admin access and
+// encoded data. Findings are hints for manual review, not proof.
+
+use std::fmt;
+
+use crate::amx::OpcodeType::*;
+use crate::amx::{Opcode, Plugin};
+use crate::analysis::{
+    call_graph, command_strings, dat_entropy, native_calls, network_calls, symbol_anomalies,
+    CallArgument, CallGraph, CommandValue, NetworkKind,
+};
+use crate::error::AmxError;
+
+// Sliding window for encoded blob search
+const ENTROPY_WINDOW: usize = 64;
+// Shorter base64/hex looking strings are often legit ids
+const MIN_ENCODED_LENGTH: usize = 32;
+// Symbol obfuscation score reported as finding
+const SYMBOL_SCORE_THRESHOLD: u32 = 50;
+// Score findings sum up to is capped at
+const MAX_SCORE: u32 = 100;
+const MALICIOUS_SCORE: u32 = 50;
+// Opcodes looked back at for CONST.alt feeding indirect store
+const INDIRECT_STORE_LOOKBEHIND: usize = 2;
+
+// Console commands and cvars worth attention when run by server_cmd/client_cmd
+const SENSITIVE_COMMANDS: &[&str] = &["rcon", "quit", "exit"];
+const SENSITIVE_CVARS: &[&str] = &["rcon_password", "sv_password"];
+// Natives comparing strings, used to check player name or steamid
+const COMPARE_NATIVES: &[&str] = &["equal", "equali", "strcmp", "contain", "containi"];
+const ACCESS_NATIVES: &[&str] = &["set_user_flags"];
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    pub fn weight(self) -> u32 {
+        match self {
+            Severity::Low => 10,
+            Severity::Medium => 25,
+            Severity::High => 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FindingKind {
+    // Store below DAT start, i.e. into cod or header
+    CodWrite {
+        address: usize,
+        target: i32,
+    },
+    // LCTRL of cod/dat base or SCTRL of cip, #emit tricks
+    ControlRegister {
+        address: usize,
+        register: u32,
+    },
+    // server_cmd/client_cmd command is not a visible constant
+    ObfuscatedCommand {
+        address: usize,
+        native: String,
+    },
+    SensitiveCommand {
+        address: usize,
+        command: String,
+    },
+    // set_user_flags in function comparing against constant string
+    BackdoorAccess {
+        address: usize,
+        compared: String,
+    },
+    // High entropy DAT not explained by strings or referenced arrays
+    EncodedBlob {
+        address: usize,
+        size: usize,
+    },
+    // Long base64 or hex looking DAT string
+    EncodedString {
+        address: usize,
+        value: String,
+    },
+    ObfuscatedSymbols {
+        score: u32,
+    },
+    // Socket or HTTP connection to host fixed in DAT
+    NetworkEndpoint {
+        address: usize,
+        native: String,
+        host: String,
+    },
+}
+
+impl FindingKind {
+    // Stable identifier, used by corpus aggregation
+    pub fn name(&self) -> &'static str {
+        match self {
+            FindingKind::CodWrite { .. } => "cod-write",
+            FindingKind::ControlRegister { .. } => "control-register",
+            FindingKind::ObfuscatedCommand { .. } => "obfuscated-command",
+            FindingKind::SensitiveCommand { .. } => "sensitive-command",
+            FindingKind::BackdoorAccess { .. } => "backdoor-access",
+            FindingKind::EncodedBlob { .. } => "encoded-blob",
+            FindingKind::EncodedString { .. } => "encoded-string",
+            FindingKind::ObfuscatedSymbols { .. } => "obfuscated-symbols",
+            FindingKind::NetworkEndpoint { .. } => "network-endpoint",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            FindingKind::CodWrite { .. } => Severity::High,
+            FindingKind::ControlRegister { .. } => Severity::Medium,
+            FindingKind::ObfuscatedCommand { .. } => Severity::Medium,
+            FindingKind::SensitiveCommand { .. } => Severity::High,
+            FindingKind::BackdoorAccess { .. } => Severity::High,
+            FindingKind::EncodedBlob { .. } => Severity::Medium,
+            FindingKind::EncodedString { .. } => Severity::Low,
+            FindingKind::ObfuscatedSymbols { .. } => Severity::Low,
+            FindingKind::NetworkEndpoint { .. } => Severity::Low,
+        }
+    }
+}
+
+impl fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FindingKind::CodWrite { address, target } => {
+                write!(f, "store to DAT{:+} at cod 0x{:X}", target, address)
+            }
+            FindingKind::ControlRegister { address, register } => {
+                write!(f, "control register {} at cod 0x{:X}", register, address)
+            }
+            FindingKind::ObfuscatedCommand { address, native } => {
+                write!(f, "{} with hidden command at cod 0x{:X}", native, address)
+            }
+            FindingKind::SensitiveCommand { address, command } => {
+                write!(f, "{:?} at cod 0x{:X}", command, address)
+            }
+            FindingKind::BackdoorAccess { address, compared } => write!(
+                f,
+                "access flags set after comparing with {:?} at cod 0x{:X}",
+                compared, address
+            ),
+            FindingKind::EncodedBlob { address, size } => {
+                write!(f, "{} bytes at dat 0x{:X}", size, address)
+            }
+            FindingKind::EncodedString { address, value } => {
+                write!(f, "{:?} at dat 0x{:X}", value, address)
+            }
+            FindingKind::ObfuscatedSymbols { score } => write!(f, "symbol score {}", score),
+            FindingKind::NetworkEndpoint {
+                address,
+                native,
+                host,
+            } => write!(f, "{} to {:?} at cod 0x{:X}", native, host, address),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub kind: FindingKind,
+    // Containing function of cod findings
+    pub function: Option<String>,
+    // Publics whose execution can reach the finding
+    pub triggerable_from: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Clean,
+    Suspicious,
+    Malicious,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scan {
+    pub findings: Vec<Finding>,
+    // 0 (clean) ..= 100, sum of finding severity weights
+    pub score: u32,
+    pub verdict: Verdict,
+}
+
+impl Scan {
+    // Distinct finding kind names
+    pub fn kinds(&self) -> Vec<String> {
+        let mut kinds: Vec<String> = self
+            .findings
+            .iter()
+            .map(|f| f.kind.name().to_owned())
+            .collect();
+        kinds.sort();
+        kinds.dedup();
+        kinds
+    }
+}
+
+impl fmt::Display for Scan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Verdict: {:?} (score {})", self.verdict, self.score)?;
+        for finding in self.findings.iter() {
+            write!(
+                f,
+                "[{:?}] {}: {}",
+                finding.kind.severity(),
+                finding.kind.name(),
+                finding.kind
+            )?;
+            if let Some(function) = &finding.function {
+                write!(f, " in {}", function)?;
+            }
+            if !finding.triggerable_from.is_empty() {
+                write!(
+                    f,
+                    ", triggerable from: {}",
+                    finding.triggerable_from.join(", ")
+                )?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// Direct stores with address below DAT and CONST.alt into such address
+// right before indirect store
+fn cod_writes(opcodes: &[Opcode]) -> Vec<FindingKind> {
+    let mut result = vec![];
+    for (i, opcode) in opcodes.iter().enumerate() {
+        let target = match opcode.code {
+            OP_STOR_PRI | OP_STOR_ALT | OP_ZERO | OP_INC | OP_DEC => opcode.param,
+            OP_STOR_I | OP_STRB_I | OP_MOVS | OP_FILL => opcodes
+                [i.saturating_sub(INDIRECT_STORE_LOOKBEHIND)..i]
+                .iter()
+                .rev()
+                .find(|o| o.code == OP_CONST_ALT)
+                .and_then(|o| o.param),
+            _ => None,
+        };
+
+        if let Some(target) = target.map(|t| t as i32).filter(|&t| t < 0) {
+            result.push(FindingKind::CodWrite {
+                address: opcode.address,
+                target,
+            });
+        }
+    }
+    result
+}
+
+// LCTRL 0/1 reads cod/dat base, SCTRL 6 jumps anywhere
+fn control_registers(opcodes: &[Opcode]) -> Vec<FindingKind> {
+    opcodes
+        .iter()
+        .filter(|o| match (o.code, o.param) {
+            (OP_LCTRL, Some(register)) => register <= 1,
+            (OP_SCTRL, Some(register)) => register == 6,
+            _ => false,
+        })
+        .map(|o| FindingKind::ControlRegister {
+            address: o.address,
+            register: o.param.unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn is_sensitive(command: &str) -> bool {
+    let command = command.to_ascii_lowercase();
+    SENSITIVE_CVARS.iter().any(|cvar| command.contains(cvar))
+        || command.split(';').any(|c| {
+            let first = c.split_whitespace().next().unwrap_or_default();
+            SENSITIVE_COMMANDS.contains(&first)
+        })
+}
+
+fn commands(plugin: &Plugin) -> Result<Vec<FindingKind>, AmxError> {
+    let mut result = vec![];
+    for command in command_strings(plugin)? {
+        let kind = match command.value {
+            CommandValue::Constant(text) | CommandValue::Formatted { format: text }
+                if is_sensitive(&text) =>
+            {
+                FindingKind::SensitiveCommand {
+                    address: command.address,
+                    command: text,
+                }
+            }
+            CommandValue::Formatted { format } if format.trim() != "%s" => continue,
+            CommandValue::Constant(_) => continue,
+            _ => FindingKind::ObfuscatedCommand {
+                address: command.address,
+                native: command.native,
+            },
+        };
+        result.push(kind);
+    }
+    Ok(result)
+}
+
+fn backdoors(plugin: &Plugin) -> Result<Vec<FindingKind>, AmxError> {
+    let calls = native_calls(plugin)?;
+    let mut result = vec![];
+
+    for call in calls
+        .iter()
+        .filter(|c| ACCESS_NATIVES.contains(&c.name.as_str()))
+    {
+        let compared = calls
+            .iter()
+            .filter(|c| c.function == call.function && c.address < call.address)
+            .filter(|c| COMPARE_NATIVES.contains(&c.name.as_str()))
+            .flat_map(|c| {
+                (0..c.args.len())
+                    .filter(move |&i| matches!(c.args[i], CallArgument::Constant(_)))
+                    .filter_map(move |i| c.string_arg(plugin, i))
+            })
+            .find(|s| !s.is_empty());
+
+        if let Some(compared) = compared {
+            result.push(FindingKind::BackdoorAccess {
+                address: call.address,
+                compared,
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+// SQL is left out, stats plugins talk to their databases all the time
+fn network_endpoints(plugin: &Plugin) -> Result<Vec<FindingKind>, AmxError> {
+    Ok(network_calls(plugin)?
+        .into_iter()
+        .filter(|c| c.kind != NetworkKind::Sql)
+        .filter_map(|c| {
+            Some(FindingKind::NetworkEndpoint {
+                address: c.address,
+                host: c.host?,
+                native: c.native,
+            })
+        })
+        .collect())
+}
+
+fn is_encoded(value: &str) -> bool {
+    let base64 = |c: char| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=';
+    value.len() >= MIN_ENCODED_LENGTH
+        && value.chars().all(base64)
+        && value.chars().any(|c| c.is_ascii_digit())
+        && value.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+fn encoded_data(plugin: &Plugin) -> Result<Vec<FindingKind>, AmxError> {
+    let mut result: Vec<FindingKind> = dat_entropy(plugin, ENTROPY_WINDOW)?
+        .into_iter()
+        .map(|r| FindingKind::EncodedBlob {
+            address: r.address,
+            size: r.size,
+        })
+        .collect();
+
+    result.extend(
+        plugin
+            .strings()?
+            .into_iter()
+            .map(|s| (s.address, s.to_string_lossy()))
+            .filter(|(_, value)| is_encoded(value))
+            .map(|(address, value)| FindingKind::EncodedString { address, value }),
+    );
+
+    Ok(result)
+}
+
+fn cod_finding(graph: &CallGraph, kind: FindingKind, address: usize) -> Finding {
+    let function = graph.functions.iter().position(|f| f.contains(address));
+    Finding {
+        kind,
+        function: function.map(|f| graph.functions[f].name.clone()),
+        triggerable_from: function
+            .map(|f| {
+                graph
+                    .triggerable_from(f)
+                    .into_iter()
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+// Cod address finding was made at
+fn cod_address(kind: &FindingKind) -> Option<usize> {
+    match kind {
+        FindingKind::CodWrite { address, .. }
+        | FindingKind::ControlRegister { address, .. }
+        | FindingKind::ObfuscatedCommand { address, .. }
+        | FindingKind::SensitiveCommand { address, .. }
+        | FindingKind::BackdoorAccess { address, .. }
+        | FindingKind::NetworkEndpoint { address, .. } => Some(*address),
+        _ => None,
+    }
+}
+
+/// Runs every heuristic over plugin.
+///
+/// ```
+/// let bytes = std::fs::read("test/fixtures/simple.amxx183").unwrap();
+/// let plugin = rxxma::facade::load_plugin(&bytes).unwrap();
+/// let scan = rxxma::scan::scan(&plugin).unwrap();
+/// assert_eq!(scan.verdict, rxxma::scan::Verdict::Clean);
+/// ```
+pub fn scan(plugin: &Plugin) -> Result<Scan, AmxError> {
+    let opcodes = plugin.opcodes()?;
+    let graph = call_graph(plugin)?;
+
+    let mut kinds = cod_writes(&opcodes);
+    kinds.extend(control_registers(&opcodes));
+    kinds.extend(commands(plugin)?);
+    kinds.extend(backdoors(plugin)?);
+    kinds.extend(network_endpoints(plugin)?);
+    kinds.extend(encoded_data(plugin)?);
+
+    let symbols = symbol_anomalies(plugin)?;
+    if symbols.score >= SYMBOL_SCORE_THRESHOLD {
+        kinds.push(FindingKind::ObfuscatedSymbols {
+            score: symbols.score,
+        });
+    }
+
+    let findings: Vec<Finding> = kinds
+        .into_iter()
+        .map(|kind| match cod_address(&kind) {
+            Some(address) => cod_finding(&graph, kind, address),
+            None => Finding {
+                kind,
+                function: None,
+                triggerable_from: vec![],
+            },
+        })
+        .collect();
+
+    let score = findings
+        .iter()
+        .map(|f| f.kind.severity().weight())
+        .sum::<u32>()
+        .min(MAX_SCORE);
+    let verdict = match score {
+        0 => Verdict::Clean,
+        s if s < MALICIOUS_SCORE => Verdict::Suspicious,
+        _ => Verdict::Malicious,
+    };
+
+    Ok(Scan {
+        findings,
+        score,
+        verdict,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{scan, FindingKind, Verdict};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::{load_fixture, PluginBuilder};
+
+    #[test]
+    fn it_pass_clean_plugin() {
+        let amxmod_plugin = Plugin::try_from(load_fixture("simple.amx183")).unwrap();
+        let scan = scan(&amxmod_plugin).unwrap();
+
+        assert_eq!(scan.findings, []);
+        assert_eq!(scan.verdict, Verdict::Clean);
+    }
+
+    #[test]
+    fn it_flag_backdoor_and_cod_write() {
+        let mut builder = PluginBuilder::new();
+        let equal = builder.native("equal");
+        let set_user_flags = builder.native("set_user_flags");
+        let server_cmd = builder.native("server_cmd");
+        let owner = builder.string("STEAM_0:1:1337");
+        let rcon = builder.string("rcon_password hacked");
+
+        // if (equal(authid, "STEAM_0:1:1337")) set_user_flags(id, -1)
+        builder
+            .public("client_authorized")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, owner)
+            .op_param(OP_PUSHADDR, (-64i32) as u32)
+            .op_param(OP_PUSH_C, 8)
+            .op_param(OP_SYSREQ_C, equal)
+            .op_param(OP_STACK, 12)
+            .op_param(OP_PUSH_C, 0xFFFF_FFFF)
+            .op_param(OP_PUSH_S, 12)
+            .op_param(OP_PUSH_C, 8);
+        let set_flags = builder.here() as usize;
+        builder
+            .op_param(OP_SYSREQ_C, set_user_flags)
+            .op_param(OP_STACK, 12)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+
+        // server_cmd("rcon_password hacked"), then patch cod
+        builder
+            .public("plugin_init")
+            .op(OP_PROC)
+            .op_param(OP_PUSH_C, rcon)
+            .op_param(OP_PUSH_C, 4);
+        let command = builder.here() as usize;
+        builder
+            .op_param(OP_SYSREQ_C, server_cmd)
+            .op_param(OP_STACK, 8)
+            .op_param(OP_CONST_PRI, 0x90);
+        let store = builder.here() as usize;
+        builder
+            .op_param(OP_STOR_PRI, (-0x40i32) as u32)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let scan = scan(&amxmod_plugin).unwrap();
+        let kinds: Vec<&FindingKind> = scan.findings.iter().map(|f| &f.kind).collect();
+
+        assert_eq!(
+            kinds,
+            [
+                &FindingKind::CodWrite {
+                    address: store,
+                    target: -0x40
+                },
+                &FindingKind::SensitiveCommand {
+                    address: command,
+                    command: String::from("rcon_password hacked")
+                },
+                &FindingKind::BackdoorAccess {
+                    address: set_flags,
+                    compared: String::from("STEAM_0:1:1337")
+                },
+            ]
+        );
+        assert_eq!(scan.verdict, Verdict::Malicious);
+        assert_eq!(scan.findings[0].triggerable_from, ["plugin_init"]);
+        assert_eq!(
+            scan.findings[2].function.as_deref(),
+            Some("client_authorized")
+        );
+    }
+}
@@ -0,0 +1,231 @@
+// IDA style pattern search over cod cells, for spotting known stocks or
+// known malicious snippets in plugins without source. Pattern token is one
+// cell: hex number, opcode mnemonic or ?? wildcard, e.g.
+// "PROC PUSH.C ?? SYSREQ.C ??". Mnemonics win over hex, write 0xADD for
+// number looking like opcode name.
+
+use std::fmt;
+use std::str::FromStr;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::amx::{OpcodeType, Plugin};
+use crate::analysis::functions;
+use crate::error::AmxError;
+
+const WILDCARD: &str = "??";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    // None matches any cell
+    cells: Vec<Option<u64>>,
+}
+
+impl FromStr for Pattern {
+    type Err = AmxError;
+
+    fn from_str(pattern: &str) -> Result<Pattern, AmxError> {
+        let cells = pattern
+            .split_whitespace()
+            .map(|token| {
+                if token == WILDCARD || token == "?" {
+                    return Ok(None);
+                }
+                if let Some(opcode) = OpcodeType::from_mnemonic(token) {
+                    return Ok(Some(opcode as u64));
+                }
+                let hex = token.strip_prefix("0x").unwrap_or(token);
+                u64::from_str_radix(hex, 16)
+                    .map(Some)
+                    .map_err(|_| AmxError::InvalidPattern(token.to_owned(), "not a cell"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if cells.is_empty() {
+            return Err(AmxError::InvalidPattern(pattern.to_owned(), "empty"));
+        }
+        if cells[0].is_none() {
+            return Err(AmxError::InvalidPattern(
+                pattern.to_owned(),
+                "starts with wildcard",
+            ));
+        }
+        Ok(Pattern { cells })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tokens: Vec<String> = self
+            .cells
+            .iter()
+            .map(|c| match c {
+                Some(cell) => format!("{:X}", cell),
+                None => WILDCARD.to_owned(),
+            })
+            .collect();
+        f.write_str(&tokens.join(" "))
+    }
+}
+
+impl Pattern {
+    // Number of cells pattern spans
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    fn matches(&self, cells: &[u64]) -> bool {
+        self.cells
+            .iter()
+            .zip(cells)
+            .all(|(pattern, cell)| pattern.is_none_or(|p| p == *cell))
+    }
+
+    // Cod addresses of every match, overlapping ones included
+    pub fn find(&self, plugin: &Plugin) -> Result<Vec<usize>, AmxError> {
+        let cellsize = plugin.cellsize();
+        let cells: Vec<u64> = plugin
+            .cod_slice()?
+            .chunks_exact(cellsize)
+            .map(|c| match cellsize {
+                8 => LittleEndian::read_u64(c),
+                _ => u64::from(LittleEndian::read_u32(c)),
+            })
+            .collect();
+
+        Ok(cells
+            .windows(self.len())
+            .enumerate()
+            .filter(|(_, window)| self.matches(window))
+            .map(|(n, _)| n * cellsize)
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub name: String,
+    pub pattern: Pattern,
+}
+
+impl Signature {
+    // "name: pattern" per line, empty lines and # comments are skipped
+    pub fn parse_list(list: &str) -> Result<Vec<Signature>, AmxError> {
+        list.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let colon = line
+                    .find(':')
+                    .ok_or_else(|| AmxError::InvalidPattern(line.to_owned(), "no name"))?;
+                Ok(Signature {
+                    name: line[..colon].trim().to_owned(),
+                    pattern: line[colon + 1..].parse()?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureMatch {
+    pub name: String,
+    // Cod address of first matched cell
+    pub address: usize,
+    // Containing function
+    pub function: Option<String>,
+}
+
+// Matches of every signature, in cod order
+pub fn sigscan(plugin: &Plugin, signatures: &[Signature]) -> Result<Vec<SignatureMatch>, AmxError> {
+    let functions = functions(plugin)?;
+
+    let mut matches = vec![];
+    for signature in signatures.iter() {
+        for address in signature.pattern.find(plugin)? {
+            matches.push(SignatureMatch {
+                name: signature.name.clone(),
+                address,
+                function: functions
+                    .iter()
+                    .find(|f| f.contains(address))
+                    .map(|f| f.name.clone()),
+            });
+        }
+    }
+    matches.sort_by_key(|m| m.address);
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{sigscan, Pattern, Signature, SignatureMatch};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::error::AmxError;
+    use crate::util::tests::PluginBuilder;
+
+    #[test]
+    fn it_parse_patterns() {
+        let pattern: Pattern = "PROC push.c ?? 0x7B 2a".parse().unwrap();
+        assert_eq!(pattern.len(), 5);
+        assert_eq!(pattern.to_string(), "2E 27 ?? 7B 2A");
+
+        assert_eq!(
+            "PUSH.C zz".parse::<Pattern>(),
+            Err(AmxError::InvalidPattern("zz".to_owned(), "not a cell"))
+        );
+        assert!("?? PROC".parse::<Pattern>().is_err());
+        assert!(" ".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn it_find_signatures_in_cod() {
+        let mut builder = PluginBuilder::new();
+        let log = builder.native("log_amx");
+        let rcon = builder.string("rcon_password");
+        builder.public("plugin_init").op(OP_PROC);
+        let push = builder.here();
+        builder
+            .op_param(OP_PUSH_C, rcon)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, log)
+            .op_param(OP_STACK, 8)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+        let amxmod_plugin = Plugin::try_from(builder.build()).unwrap();
+
+        let signatures = Signature::parse_list(
+            "# natives called with one argument\n\
+             one argument: PUSH.C 4 SYSREQ.C ?? STACK 8\n\
+             \n\
+             return zero: ZERO.pri RETN\n\
+             missing: RETN PROC PUSH.C\n",
+        )
+        .unwrap();
+        let matches = sigscan(&amxmod_plugin, &signatures).unwrap();
+
+        assert_eq!(
+            matches,
+            [
+                SignatureMatch {
+                    name: "one argument".to_owned(),
+                    address: push as usize + 8,
+                    function: Some("plugin_init".to_owned()),
+                },
+                SignatureMatch {
+                    name: "return zero".to_owned(),
+                    address: push as usize + 32,
+                    function: Some("plugin_init".to_owned()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,499 @@
+// SourcePawn .smx container: header, section table, string table and named
+// sections. Everything after `dataoffs` may be zlib compressed, the image
+// kept here is always uncompressed and starts with the header.
+
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::io::{Cursor, Read};
+
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+use flate2::read::ZlibDecoder;
+use log::trace;
+
+use crate::amx::{Native, Public};
+use crate::error::{read_at, AmxError};
+
+// "FFPS"
+#[allow(clippy::unreadable_literal)]
+pub const SMX_MAGIC: u32 = 0x53504646;
+const HEADER_SIZE: usize = 24;
+const SECTION_ENTRY_SIZE: usize = 12;
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZLIB: u8 = 1;
+
+// .code header: codesize, cellsize, codeversion, flags, main, code offset
+const CODE_HEADER_SIZE: usize = 16;
+// .data header: datasize, memsize, data offset
+const DATA_HEADER_SIZE: usize = 12;
+// .publics record: address, name offset
+const PUBLIC_SIZE: usize = 8;
+// .natives record: name offset
+const NATIVE_SIZE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmxSection {
+    pub name: String,
+    // Image offset
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmxCode<'a> {
+    pub cellsize: u8,
+    pub version: u8,
+    pub flags: u16,
+    // Code address of entry point
+    pub main: u32,
+    pub bytes: &'a [u8],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmxData<'a> {
+    // Data plus heap and stack
+    pub memsize: usize,
+    pub bytes: &'a [u8],
+}
+
+#[derive(Debug)]
+pub struct SmxFile {
+    pub version: u16,
+    pub compression: u8,
+    // Uncompressed image, header included
+    pub bin: Vec<u8>,
+    pub sections: Vec<SmxSection>,
+}
+
+fn inflate(bin: &[u8], dataoffs: usize, imagesize: usize) -> Result<Vec<u8>, AmxError> {
+    let compressed = bin.get(dataoffs..).ok_or(AmxError::Malformed {
+        reason: "compressed data offset points past file end",
+        offset: dataoffs,
+    })?;
+
+    let mut image = Vec::with_capacity(imagesize);
+    image.extend_from_slice(&bin[..dataoffs]);
+    ZlibDecoder::new(compressed)
+        .read_to_end(&mut image)
+        .map_err(|e| AmxError::Unpack(e.to_string()))?;
+
+    if image.len() != imagesize {
+        return Err(AmxError::ImageSizeMismatch);
+    }
+    Ok(image)
+}
+
+impl TryFrom<Vec<u8>> for SmxFile {
+    type Error = AmxError;
+
+    fn try_from(bin: Vec<u8>) -> Result<Self, Self::Error> {
+        let mut reader = Cursor::new(&bin);
+
+        let magic = read_at(&mut reader, "smx magic", |r| r.read_u32::<LittleEndian>())?;
+        if magic != SMX_MAGIC {
+            return Err(AmxError::InvalidFileMagic(SMX_MAGIC, magic));
+        }
+        let version = read_at(&mut reader, "smx version", |r| r.read_u16::<LittleEndian>())?;
+        let compression = read_at(&mut reader, "smx compression", |r| r.read_u8())?;
+        let _disksize = read_at(&mut reader, "smx disksize", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        let imagesize = read_at(&mut reader, "smx imagesize", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        let sections = read_at(&mut reader, "smx sections count", |r| r.read_u8())?;
+        let stringtab = read_at(&mut reader, "smx string table", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        let dataoffs = read_at(&mut reader, "smx data offset", |r| {
+            r.read_u32::<LittleEndian>()
+        })?;
+        trace!(
+            "smx version 0x{:X}, compression {}, {} sections",
+            version,
+            compression,
+            sections
+        );
+
+        let bin = match compression {
+            COMPRESSION_NONE => bin,
+            COMPRESSION_ZLIB => inflate(&bin, dataoffs as usize, imagesize as usize)?,
+            _ => {
+                return Err(AmxError::Malformed {
+                    reason: "unknown smx compression",
+                    offset: 6,
+                })
+            }
+        };
+
+        let mut entries = vec![];
+        for i in 0..usize::from(sections) {
+            let offset = HEADER_SIZE + i * SECTION_ENTRY_SIZE;
+            let entry = bin
+                .get(offset..offset + SECTION_ENTRY_SIZE)
+                .ok_or(AmxError::Eof {
+                    what: "smx section entry",
+                    offset,
+                })?;
+            let name_offset = stringtab as usize + LittleEndian::read_u32(entry) as usize;
+            let name = string_at(&bin, name_offset)?;
+            let section = SmxSection {
+                name: name.to_string_lossy().into_owned(),
+                offset: LittleEndian::read_u32(&entry[4..]) as usize,
+                size: LittleEndian::read_u32(&entry[8..]) as usize,
+            };
+            if bin
+                .get(section.offset..section.offset + section.size)
+                .is_none()
+            {
+                return Err(AmxError::Malformed {
+                    reason: "smx section points past image end",
+                    offset,
+                });
+            }
+            entries.push(section);
+        }
+
+        Ok(SmxFile {
+            version,
+            compression,
+            bin,
+            sections: entries,
+        })
+    }
+}
+
+// Zero terminated string at image offset
+fn string_at(bin: &[u8], offset: usize) -> Result<&CStr, AmxError> {
+    bin.get(offset..)
+        .and_then(|rest| rest.iter().position(|&b| b == 0).map(|end| &rest[..=end]))
+        .and_then(|bytes| CStr::from_bytes_with_nul(bytes).ok())
+        .ok_or(AmxError::Malformed {
+            reason: "smx name is not terminated",
+            offset,
+        })
+}
+
+impl SmxFile {
+    // Contents of first section with `name`
+    pub fn section(&self, name: &str) -> Option<&[u8]> {
+        self.sections
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| &self.bin[s.offset..s.offset + s.size])
+    }
+
+    // Image offset and contents of section, `missing` is reported without it
+    fn required_section(
+        &self,
+        name: &str,
+        missing: &'static str,
+    ) -> Result<(usize, &[u8]), AmxError> {
+        let section = self
+            .sections
+            .iter()
+            .find(|s| s.name == name)
+            .ok_or(AmxError::Malformed {
+                reason: missing,
+                offset: HEADER_SIZE,
+            })?;
+        Ok((
+            section.offset,
+            &self.bin[section.offset..section.offset + section.size],
+        ))
+    }
+
+    pub fn code(&self) -> Result<SmxCode<'_>, AmxError> {
+        let (offset, section) = self.required_section(".code", "smx has no .code section")?;
+        let header = section.get(..CODE_HEADER_SIZE).ok_or(AmxError::Eof {
+            what: "smx code header",
+            offset,
+        })?;
+        let size = LittleEndian::read_u32(header) as usize;
+        let start = LittleEndian::read_u32(&header[12..]) as usize;
+
+        Ok(SmxCode {
+            cellsize: header[4],
+            version: header[5],
+            flags: LittleEndian::read_u16(&header[6..]),
+            main: LittleEndian::read_u32(&header[8..]),
+            bytes: section
+                .get(start..start + size)
+                .ok_or(AmxError::Malformed {
+                    reason: "smx code points past section end",
+                    offset,
+                })?,
+        })
+    }
+
+    pub fn data(&self) -> Result<SmxData<'_>, AmxError> {
+        let (offset, section) = self.required_section(".data", "smx has no .data section")?;
+        let header = section.get(..DATA_HEADER_SIZE).ok_or(AmxError::Eof {
+            what: "smx data header",
+            offset,
+        })?;
+        let size = LittleEndian::read_u32(header) as usize;
+        let start = LittleEndian::read_u32(&header[8..]) as usize;
+
+        Ok(SmxData {
+            memsize: LittleEndian::read_u32(&header[4..]) as usize,
+            bytes: section
+                .get(start..start + size)
+                .ok_or(AmxError::Malformed {
+                    reason: "smx data points past section end",
+                    offset,
+                })?,
+        })
+    }
+
+    // Name at .names offset
+    fn name(&self, offset: u32) -> Result<CString, AmxError> {
+        let (names, _) = self.required_section(".names", "smx has no .names section")?;
+        Ok(string_at(&self.bin, names + offset as usize)?.to_owned())
+    }
+
+    pub fn publics(&self) -> Result<Vec<Public>, AmxError> {
+        let section = match self.section(".publics") {
+            Some(section) => section,
+            None => return Ok(vec![]),
+        };
+        section
+            .chunks_exact(PUBLIC_SIZE)
+            .map(|record| {
+                Ok(Public {
+                    name: self.name(LittleEndian::read_u32(&record[4..]))?,
+                    address: LittleEndian::read_u32(record) as usize,
+                })
+            })
+            .collect()
+    }
+
+    // Natives are bound by index, address is the index
+    pub fn natives(&self) -> Result<Vec<Native>, AmxError> {
+        let section = match self.section(".natives") {
+            Some(section) => section,
+            None => return Ok(vec![]),
+        };
+        section
+            .chunks_exact(NATIVE_SIZE)
+            .enumerate()
+            .map(|(index, record)| {
+                Ok(Native {
+                    name: self.name(LittleEndian::read_u32(record))?,
+                    address: index,
+                })
+            })
+            .collect()
+    }
+
+    // Every violation of code, data and symbol tables, the container
+    // itself is checked while reading
+    pub fn validate(&self) -> Vec<AmxError> {
+        let mut violations = vec![];
+        let code = match self.code() {
+            Ok(code) => Some(code),
+            Err(e) => {
+                violations.push(e);
+                None
+            }
+        };
+        if let Some(ref code) = code {
+            if code.cellsize != 4 {
+                violations.push(AmxError::InvalidCellSize(code.cellsize));
+            }
+        }
+        let code_size = code.map(|c| c.bytes.len());
+        let points_past_code = |address: usize| code_size.is_some_and(|size| address >= size);
+
+        match self.data() {
+            Ok(data) if data.memsize < data.bytes.len() => violations.push(AmxError::Malformed {
+                reason: "smx memsize is less than data size",
+                offset: self.section_offset(".data"),
+            }),
+            Ok(_) => {}
+            Err(e) => violations.push(e),
+        }
+
+        match self.publics() {
+            Ok(publics) => {
+                violations.extend(publics.iter().filter(|p| points_past_code(p.address)).map(
+                    |_| AmxError::Malformed {
+                        reason: "smx public points past code end",
+                        offset: self.section_offset(".publics"),
+                    },
+                ))
+            }
+            Err(e) => violations.push(e),
+        }
+        if let Err(e) = self.natives() {
+            violations.push(e);
+        }
+
+        violations
+    }
+
+    // Image offset of section, header size for missing one
+    fn section_offset(&self, name: &str) -> usize {
+        self.sections
+            .iter()
+            .find(|s| s.name == name)
+            .map_or(HEADER_SIZE, |s| s.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::io::Write;
+
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    use super::{SmxFile, SMX_MAGIC};
+    use crate::error::AmxError;
+
+    // Minimal smx with one public, two natives, 3 code cells and one data cell
+    fn build_smx(compressed: bool) -> Vec<u8> {
+        let names = b"OnPluginStart\0PrintToServer\0CreateConVar\0";
+        // codesize, cellsize 4, codeversion 1, flags, main, code offset
+        let mut code = vec![];
+        code.write_u32::<LittleEndian>(12).unwrap();
+        code.write_u8(4).unwrap();
+        code.write_u8(1).unwrap();
+        code.write_u16::<LittleEndian>(0).unwrap();
+        code.write_u32::<LittleEndian>(0).unwrap();
+        code.write_u32::<LittleEndian>(16).unwrap();
+        for cell in &[0x2Eu32, 0x59, 0x30] {
+            code.write_u32::<LittleEndian>(*cell).unwrap();
+        }
+        let mut data = vec![];
+        for field in &[4u32, 0x1004, 12, 7] {
+            data.write_u32::<LittleEndian>(*field).unwrap();
+        }
+        let mut publics = vec![];
+        publics.write_u32::<LittleEndian>(0).unwrap();
+        publics.write_u32::<LittleEndian>(0).unwrap();
+        let mut natives = vec![];
+        natives.write_u32::<LittleEndian>(14).unwrap();
+        natives.write_u32::<LittleEndian>(28).unwrap();
+
+        let sections: [(&[u8], &[u8]); 5] = [
+            (b".code\0", &code),
+            (b".data\0", &data),
+            (b".publics\0", &publics),
+            (b".natives\0", &natives),
+            (b".names\0", names),
+        ];
+        let stringtab = 24 + 12 * sections.len();
+        let section_names: Vec<u8> = sections.iter().flat_map(|(n, _)| n.to_vec()).collect();
+        let dataoffs = stringtab + section_names.len();
+
+        let mut table = vec![];
+        let mut body = vec![];
+        let mut name_offset = 0;
+        for (name, contents) in sections.iter() {
+            table.write_u32::<LittleEndian>(name_offset).unwrap();
+            table
+                .write_u32::<LittleEndian>((dataoffs + body.len()) as u32)
+                .unwrap();
+            table
+                .write_u32::<LittleEndian>(contents.len() as u32)
+                .unwrap();
+            name_offset += name.len() as u32;
+            body.extend_from_slice(contents);
+        }
+
+        let imagesize = dataoffs + body.len();
+        let body = if compressed {
+            let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+            encoder.write_all(&body).unwrap();
+            encoder.finish().unwrap()
+        } else {
+            body
+        };
+
+        let mut smx = vec![];
+        smx.write_u32::<LittleEndian>(SMX_MAGIC).unwrap();
+        smx.write_u16::<LittleEndian>(0x0102).unwrap();
+        smx.write_u8(compressed as u8).unwrap();
+        smx.write_u32::<LittleEndian>((dataoffs + body.len()) as u32)
+            .unwrap();
+        smx.write_u32::<LittleEndian>(imagesize as u32).unwrap();
+        smx.write_u8(sections.len() as u8).unwrap();
+        smx.write_u32::<LittleEndian>(stringtab as u32).unwrap();
+        smx.write_u32::<LittleEndian>(dataoffs as u32).unwrap();
+        smx.extend(table);
+        smx.extend(section_names);
+        smx.extend(body);
+        smx
+    }
+
+    #[test]
+    fn it_read_smx_sections_and_tables() {
+        for &compressed in &[false, true] {
+            let smx = SmxFile::try_from(build_smx(compressed)).unwrap();
+
+            let names: Vec<&str> = smx.sections.iter().map(|s| s.name.as_str()).collect();
+            assert_eq!(names, [".code", ".data", ".publics", ".natives", ".names"]);
+            let code = smx.code().unwrap();
+            assert_eq!(code.cellsize, 4);
+            assert_eq!(code.bytes.len(), 12);
+            let data = smx.data().unwrap();
+            assert_eq!((data.bytes, data.memsize), (&[7, 0, 0, 0][..], 0x1004));
+            let publics = smx.publics().unwrap();
+            assert_eq!(publics[0].name.to_str(), Ok("OnPluginStart"));
+            let natives: Vec<String> = smx
+                .natives()
+                .unwrap()
+                .iter()
+                .map(|n| n.name.to_string_lossy().into_owned())
+                .collect();
+            assert_eq!(natives, ["PrintToServer", "CreateConVar"]);
+        }
+    }
+
+    #[test]
+    fn it_inspect_smx() {
+        let info = crate::facade::inspect(&build_smx(true)).unwrap();
+
+        assert_eq!(info.format, crate::facade::Format::Smx);
+        assert_eq!(info.publics, ["OnPluginStart"]);
+        assert_eq!(info.natives, ["PrintToServer", "CreateConVar"]);
+        assert_eq!(
+            (info.cod_size, info.dat_size, info.heap_budget),
+            (12, 4, 0x1000)
+        );
+        assert_eq!(
+            crate::facade::load(&build_smx(false)).err(),
+            Some(AmxError::Unsupported(
+                "disassembly and decompilation of SourcePawn plugins"
+            ))
+        );
+    }
+
+    #[test]
+    fn it_validate_smx() {
+        let bin = build_smx(false);
+        assert!(crate::facade::validate(&bin).unwrap().is_empty());
+
+        // Public address past 12 bytes of code
+        let mut smx = SmxFile::try_from(bin).unwrap();
+        let publics = smx.sections[2].offset;
+        smx.bin[publics] = 12;
+        assert_eq!(
+            smx.validate(),
+            [AmxError::Malformed {
+                reason: "smx public points past code end",
+                offset: publics
+            }]
+        );
+    }
+
+    #[test]
+    fn it_err_on_invalid_smx_magic() {
+        assert_eq!(
+            SmxFile::try_from(vec![0; 24]).err(),
+            Some(AmxError::InvalidFileMagic(SMX_MAGIC, 0))
+        );
+    }
+}
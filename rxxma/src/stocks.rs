@@ -0,0 +1,221 @@
+// Recognition of stocks from standard includes, FLIRT alike. Stock is known
+// by hash of its normalized opcodes, so the same stock compiled into other
+// plugin at other addresses still matches. Database is learned from plugins
+// compiled with debug info (amxxpc -d2) calling the stocks.
+
+use std::fmt;
+
+use sha2::{Digest, Sha256};
+
+use crate::amx::Plugin;
+use crate::diff::plugin_functions;
+use crate::error::AmxError;
+
+// Shorter functions are too generic to be told apart
+const MIN_STOCK_OPCODES: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownStock {
+    // Hex encoded SHA-256 of normalized opcodes
+    pub hash: String,
+    pub name: String,
+    // Include file without extension, if known
+    pub include: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StockDatabase {
+    pub stocks: Vec<KnownStock>,
+}
+
+// Stock recognized in plugin
+#[derive(Debug, Clone, PartialEq)]
+pub struct StockMatch<'a> {
+    // Cod address of PROC
+    pub address: usize,
+    pub stock: &'a KnownStock,
+}
+
+// Hashes of plugin functions long enough to be recognized, by PROC address
+fn function_hashes(plugin: &Plugin) -> Result<Vec<(usize, String)>, AmxError> {
+    let functions = plugin_functions(plugin)?;
+    Ok(functions
+        .functions
+        .iter()
+        .zip(functions.bodies.iter())
+        .filter(|(_, body)| body.len() >= MIN_STOCK_OPCODES)
+        .map(|(function, body)| {
+            let mut hasher = Sha256::new();
+            for opcode in body.iter() {
+                hasher.update(opcode.to_string().as_bytes());
+                hasher.update(b"\n");
+            }
+            let hash = hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            (function.address, hash)
+        })
+        .collect())
+}
+
+impl StockDatabase {
+    pub fn new() -> StockDatabase {
+        StockDatabase::default()
+    }
+
+    pub fn add(&mut self, stock: KnownStock) -> &mut Self {
+        if !self.stocks.contains(&stock) {
+            self.stocks.push(stock);
+        }
+        self
+    }
+
+    // Remembers every non public function named by debug symbols,
+    // returns number of learned stocks
+    pub fn learn(&mut self, plugin: &Plugin, include: Option<&str>) -> Result<usize, AmxError> {
+        let info = match plugin.debug_info()? {
+            Some(info) => info,
+            None => return Ok(0),
+        };
+        let publics: Vec<usize> = plugin.publics()?.iter().map(|p| p.address).collect();
+
+        let before = self.stocks.len();
+        for (address, hash) in function_hashes(plugin)? {
+            if publics.contains(&address) {
+                continue;
+            }
+            if let Some(symbol) = info.function_at(address) {
+                self.add(KnownStock {
+                    hash,
+                    name: symbol.name.to_string_lossy().into_owned(),
+                    include: include.map(|i| i.to_owned()),
+                });
+            }
+        }
+        Ok(self.stocks.len() - before)
+    }
+
+    // Tab separated hash, name and include per line, # comments skipped
+    pub fn parse(text: &str) -> Result<StockDatabase, AmxError> {
+        let mut database = StockDatabase::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let (hash, name) = match fields[..] {
+                [hash, name] | [hash, name, _] => (hash, name),
+                _ => {
+                    return Err(AmxError::Unsupported(
+                        "stock line is not hash, name, include",
+                    ))
+                }
+            };
+            database.add(KnownStock {
+                hash: hash.to_owned(),
+                name: name.to_owned(),
+                include: fields.get(2).map(|i| i.to_string()),
+            });
+        }
+        Ok(database)
+    }
+
+    // Known stocks among plugin functions, in cod order
+    pub fn identify(&self, plugin: &Plugin) -> Result<Vec<StockMatch<'_>>, AmxError> {
+        Ok(function_hashes(plugin)?
+            .into_iter()
+            .filter_map(|(address, hash)| {
+                let stock = self.stocks.iter().find(|s| s.hash == hash)?;
+                Some(StockMatch { address, stock })
+            })
+            .collect())
+    }
+}
+
+impl fmt::Display for StockDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for stock in self.stocks.iter() {
+            write!(f, "{}\t{}", stock.hash, stock.name)?;
+            if let Some(ref include) = stock.include {
+                write!(f, "\t{}", include)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::convert::TryFrom;
+
+    use super::StockDatabase;
+    use crate::amx::debug_info::tests::{debug_symbol, symbols_chunk};
+    use crate::amx::debug_info::{IDENT_FUNCTION, VCLASS_GLOBAL};
+    use crate::amx::OpcodeType::*;
+    use crate::amx::Plugin;
+    use crate::util::tests::PluginBuilder;
+
+    // Public calling `is_admin` stock, `padding` cells shift the stock
+    pub fn plugin_with_stock(padding: usize, debug: bool) -> Plugin<'static> {
+        let mut builder = PluginBuilder::new();
+        let get_user_flags = builder.native("get_user_flags");
+        builder.public("client_putinserver").op(OP_PROC);
+        for _ in 0..padding {
+            builder.op(OP_NOP);
+        }
+        let call = builder.here();
+        builder
+            .op_param(OP_PUSH_S, 0xC)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_CALL, 0)
+            .op(OP_ZERO_PRI)
+            .op(OP_RETN);
+
+        let stock = builder.here();
+        builder
+            .op(OP_PROC)
+            .op_param(OP_PUSH_S, 0xC)
+            .op_param(OP_PUSH_C, 4)
+            .op_param(OP_SYSREQ_C, get_user_flags)
+            .op_param(OP_STACK, 8)
+            .op_param(OP_CONST_ALT, 0x0200_0000)
+            .op(OP_AND)
+            .op(OP_NOT)
+            .op(OP_RETN);
+        let end = builder.here();
+        builder.patch(call + 20, stock);
+        if debug {
+            builder.debug_info(&symbols_chunk(&[debug_symbol(
+                "is_admin",
+                stock as i32,
+                stock..end,
+                IDENT_FUNCTION,
+                VCLASS_GLOBAL,
+            )]));
+        }
+
+        Plugin::try_from(builder.build()).unwrap()
+    }
+
+    #[test]
+    fn it_recognize_learned_stock() {
+        let mut database = StockDatabase::new();
+        let learned = database
+            .learn(&plugin_with_stock(0, true), Some("amxmisc"))
+            .unwrap();
+        assert_eq!(learned, 1);
+        assert_eq!(database.stocks[0].name, "is_admin");
+
+        let database = StockDatabase::parse(&database.to_string()).unwrap();
+        let stripped = plugin_with_stock(3, false);
+        let matches = database.identify(&stripped).unwrap();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].address, 0x8 + 4 + 3 * 4 + 8 * 4);
+        assert_eq!(matches[0].stock.include.as_deref(), Some("amxmisc"));
+        assert!(StockDatabase::parse("abc\n").is_err());
+    }
+}
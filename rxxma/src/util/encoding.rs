@@ -0,0 +1,60 @@
+// String encodings used by plugin authors for DAT strings
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Latin1,
+    // Default codepage of russian Windows, very common for amxx plugins
+    Windows1251,
+}
+
+// Windows-1251 0x80..0xC0, 0xC0..=0xFF map to U+0410..=U+044F
+const WINDOWS_1251_HIGH: [char; 64] = [
+    '\u{0402}', '\u{0403}', '\u{201A}', '\u{0453}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{20AC}', '\u{2030}', '\u{0409}', '\u{2039}', '\u{040A}', '\u{040C}', '\u{040B}', '\u{040F}',
+    '\u{0452}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{FFFD}', '\u{2122}', '\u{0459}', '\u{203A}', '\u{045A}', '\u{045C}', '\u{045B}', '\u{045F}',
+    '\u{00A0}', '\u{040E}', '\u{045E}', '\u{0408}', '\u{00A4}', '\u{0490}', '\u{00A6}', '\u{00A7}',
+    '\u{0401}', '\u{00A9}', '\u{0404}', '\u{00AB}', '\u{00AC}', '\u{00AD}', '\u{00AE}', '\u{0407}',
+    '\u{00B0}', '\u{00B1}', '\u{0406}', '\u{0456}', '\u{0491}', '\u{00B5}', '\u{00B6}', '\u{00B7}',
+    '\u{0451}', '\u{2116}', '\u{0454}', '\u{00BB}', '\u{0458}', '\u{0405}', '\u{0455}', '\u{0457}',
+];
+
+impl Encoding {
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Latin1 => bytes.iter().map(|&b| char::from(b)).collect(),
+            Encoding::Windows1251 => bytes
+                .iter()
+                .map(|&b| match b {
+                    0x00..=0x7F => char::from(b),
+                    0x80..=0xBF => WINDOWS_1251_HIGH[usize::from(b - 0x80)],
+                    _ => std::char::from_u32(0x0410 + u32::from(b - 0xC0)).unwrap(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encoding;
+
+    #[test]
+    fn it_decode_ascii_in_any_encoding() {
+        for encoding in &[Encoding::Utf8, Encoding::Latin1, Encoding::Windows1251] {
+            assert_eq!(encoding.decode(b"simple plugin"), "simple plugin");
+        }
+    }
+
+    #[test]
+    fn it_decode_non_ascii() {
+        assert_eq!(Encoding::Utf8.decode(b"\xD0\x9F\xD1\x80"), "\u{41F}\u{440}");
+        assert_eq!(Encoding::Latin1.decode(b"caf\xE9"), "caf\u{E9}");
+        assert_eq!(
+            Encoding::Windows1251.decode(b"\xCF\xF0\xE8\xE2\xE5\xF2 \xA8\xB9"),
+            "\u{41F}\u{440}\u{438}\u{432}\u{435}\u{442} \u{401}\u{2116}"
+        );
+    }
+}
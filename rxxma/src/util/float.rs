@@ -0,0 +1,64 @@
+// Pawn keeps Float: values as raw IEEE-754 bits in cells
+
+// Magnitudes of floats written by hand in plugins
+const MIN_MAGNITUDE: f32 = 0.0001;
+const MAX_MAGNITUDE: f32 = 10_000_000.0;
+const MAX_SIGNIFICANT_DIGITS: usize = 6;
+
+// Cell which most likely holds float rather than integer. Integers up to
+// 2^23 are denormals and single bits are flags, large integers are floats
+// with long fraction.
+pub fn float_constant(cell: u32) -> Option<f32> {
+    let value = f32::from_bits(cell);
+    if !value.is_normal() || cell.count_ones() == 1 {
+        return None;
+    }
+    if value.abs() < MIN_MAGNITUDE || value.abs() > MAX_MAGNITUDE {
+        return None;
+    }
+    if significant_digits(value) > MAX_SIGNIFICANT_DIGITS {
+        return None;
+    }
+
+    Some(value)
+}
+
+// Shortest Pawn literal reading back as the same float
+pub fn float_literal(value: f32) -> String {
+    format!("{:?}", value)
+}
+
+fn significant_digits(value: f32) -> usize {
+    let digits: String = float_literal(value.abs())
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    digits.trim_start_matches('0').trim_end_matches('0').len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{float_constant, float_literal};
+
+    #[test]
+    fn it_detect_float_constants() {
+        assert_eq!(float_constant(1.5f32.to_bits()), Some(1.5));
+        assert_eq!(float_constant((-0.25f32).to_bits()), Some(-0.25));
+        assert_eq!(float_constant(100.0f32.to_bits()), Some(100.0));
+        assert_eq!(float_constant(0.1f32.to_bits()), Some(0.1));
+    }
+
+    #[test]
+    fn it_keep_integer_constants() {
+        for &cell in &[0, 1, 100, 100_000, 1 << 30, 1_000_000_000, 0xFFFF_FFFF] {
+            assert_eq!(float_constant(cell), None, "{}", cell);
+        }
+    }
+
+    #[test]
+    fn it_render_float_literal() {
+        assert_eq!(float_literal(1.0), "1.0");
+        assert_eq!(float_literal(-0.25), "-0.25");
+        assert_eq!(float_literal(0.0001), "0.0001");
+    }
+}
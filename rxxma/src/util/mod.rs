@@ -1,6 +1,11 @@
 pub mod debug_u8;
+pub mod encoding;
+pub mod float;
+#[cfg(feature = "serde")]
+pub mod serialize;
 pub mod string_zero;
 pub use self::debug_u8::DebugU8;
+pub use self::encoding::Encoding;
 pub use self::string_zero::ReadByteString;
 
 #[cfg(test)]
@@ -0,0 +1,10 @@
+// Serializers for fields serde has no readable form for
+
+use std::ffi::CString;
+
+use serde::Serializer;
+
+// Symbol names are not guaranteed to be utf-8
+pub fn lossy_name<S: Serializer>(name: &CString, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&name.to_string_lossy())
+}
@@ -1,6 +1,9 @@
 use std::fs::File;
 use std::io::prelude::*;
 
+mod plugin_builder;
+pub use self::plugin_builder::PluginBuilder;
+
 pub fn load_fixture(filename: &str) -> Vec<u8> {
     let mut file_bin: Vec<u8> = Vec::new();
     let mut file = File::open(format!("test/fixtures/{}", filename)).unwrap();
@@ -0,0 +1,244 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use crate::amx::OpcodeType;
+use crate::amx::OpcodeType::*;
+use crate::amx::CELLSIZE;
+
+const HEADER_SIZE: usize = 56;
+const TABLE_ENTRY_SIZE: usize = 8;
+// Address and inline name of file version 6 records
+const INLINE_ENTRY_SIZE: usize = 24;
+const STACK_SIZE: u32 = 16384;
+
+// Assembles minimal amx images for tests, layout follows amxxpc 1.8.3 output.
+pub struct PluginBuilder {
+    flags: u16,
+    // File version 6 image without nametable
+    inline_names: bool,
+    publics: Vec<(String, u32)>,
+    natives: Vec<String>,
+    libraries: Vec<String>,
+    pubvars: Vec<(String, u32)>,
+    tags: Vec<(String, u32)>,
+    // Appended after image
+    debug_info: Vec<u8>,
+    cod: Vec<u32>,
+    dat: Vec<u32>,
+}
+
+impl Default for PluginBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PluginBuilder {
+    pub fn new() -> PluginBuilder {
+        PluginBuilder {
+            flags: 0,
+            inline_names: false,
+            publics: vec![],
+            natives: vec![],
+            libraries: vec![],
+            pubvars: vec![],
+            tags: vec![],
+            debug_info: vec![],
+            // Compiler always starts cod with HALT 0
+            cod: vec![OP_HALT as u32, 0],
+            dat: vec![],
+        }
+    }
+
+    pub fn flags(&mut self, flags: u16) -> &mut Self {
+        self.flags = flags;
+        self
+    }
+
+    // Emit file version 6 image, names stored in table records
+    pub fn inline_names(&mut self) -> &mut Self {
+        self.inline_names = true;
+        self
+    }
+
+    // Returns native index for SYSREQ.C
+    pub fn native(&mut self, name: &str) -> u32 {
+        self.natives.push(name.to_owned());
+        (self.natives.len() - 1) as u32
+    }
+
+    // Returns dat address of unpacked zero terminated string
+    pub fn string(&mut self, value: &str) -> u32 {
+        let mut cells: Vec<u32> = value.bytes().map(u32::from).collect();
+        cells.push(0);
+        self.array(&cells)
+    }
+
+    // Returns dat address of raw cells
+    pub fn array(&mut self, cells: &[u32]) -> u32 {
+        let address = self.dat.len() * CELLSIZE;
+        self.dat.extend_from_slice(cells);
+        address as u32
+    }
+
+    // Returns dat address of public variable holding `cells`
+    pub fn pubvar(&mut self, name: &str, cells: &[u32]) -> u32 {
+        let address = self.array(cells);
+        self.pubvars.push((name.to_owned(), address));
+        address
+    }
+
+    // Library (module) required by plugin
+    pub fn library(&mut self, name: &str) -> &mut Self {
+        self.libraries.push(name.to_owned());
+        self
+    }
+
+    // Tag table entry, `id` with flag bits
+    pub fn tag(&mut self, name: &str, id: u32) -> &mut Self {
+        self.tags.push((name.to_owned(), id));
+        self
+    }
+
+    // Debug chunk, sets debug flag
+    pub fn debug_info(&mut self, chunk: &[u8]) -> &mut Self {
+        self.flags |= 0x02;
+        self.debug_info = chunk.to_vec();
+        self
+    }
+
+    // Marks current cod address as public function entry
+    pub fn public(&mut self, name: &str) -> &mut Self {
+        let address = self.here();
+        self.publics.push((name.to_owned(), address));
+        self
+    }
+
+    // Current cod address
+    pub fn here(&self) -> u32 {
+        (self.cod.len() * CELLSIZE) as u32
+    }
+
+    pub fn op(&mut self, code: OpcodeType) -> &mut Self {
+        self.cod.push(code as u32);
+        self
+    }
+
+    pub fn op_param(&mut self, code: OpcodeType, param: u32) -> &mut Self {
+        self.cod.push(code as u32);
+        self.cod.push(param);
+        self
+    }
+
+    pub fn cells(&mut self, cells: &[u32]) -> &mut Self {
+        self.cod.extend_from_slice(cells);
+        self
+    }
+
+    // Overwrite already emitted cell, used to patch forward jumps
+    pub fn patch(&mut self, address: u32, value: u32) -> &mut Self {
+        self.cod[address as usize / CELLSIZE] = value;
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        // amx_FindPublic does binary search, table must be sorted
+        let mut publics = self.publics.clone();
+        publics.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let entry_size = match self.inline_names {
+            true => INLINE_ENTRY_SIZE,
+            false => TABLE_ENTRY_SIZE,
+        };
+        let publics_offset = HEADER_SIZE;
+        let natives_offset = publics_offset + publics.len() * entry_size;
+        let libraries_offset = natives_offset + self.natives.len() * entry_size;
+        let pubvars_offset = libraries_offset + self.libraries.len() * entry_size;
+        let tags_offset = pubvars_offset + self.pubvars.len() * entry_size;
+        let nametable_offset = tags_offset + self.tags.len() * entry_size;
+
+        let mut nametable: Vec<u8> = vec![];
+        // Record name part, offset into nametable or name itself
+        let mut record_names: Vec<Vec<u8>> = vec![];
+        if !self.inline_names {
+            nametable.write_u16::<LittleEndian>(31).unwrap();
+        }
+        let symbols = publics
+            .iter()
+            .map(|p| &p.0)
+            .chain(self.natives.iter())
+            .chain(self.libraries.iter())
+            .chain(self.pubvars.iter().map(|p| &p.0))
+            .chain(self.tags.iter().map(|t| &t.0));
+        for name in symbols {
+            if self.inline_names {
+                let mut inline = name.as_bytes().to_vec();
+                inline.resize(INLINE_ENTRY_SIZE - CELLSIZE, 0);
+                record_names.push(inline);
+                continue;
+            }
+            let offset = (nametable_offset + nametable.len()) as u32;
+            record_names.push(offset.to_le_bytes().to_vec());
+            nametable.extend_from_slice(name.as_bytes());
+            nametable.push(0);
+        }
+        // Cod starts cell aligned
+        while !(nametable_offset + nametable.len()).is_multiple_of(CELLSIZE) {
+            nametable.push(0);
+        }
+
+        let cod = nametable_offset + nametable.len();
+        let dat = cod + self.cod.len() * CELLSIZE;
+        let hea = dat + self.dat.len() * CELLSIZE;
+
+        let mut bin: Vec<u8> = vec![];
+        bin.write_u32::<LittleEndian>(hea as u32).unwrap();
+        bin.write_u16::<LittleEndian>(0xF1E0).unwrap();
+        let version = if self.inline_names { 6 } else { 8 };
+        bin.push(version); // file version
+        bin.push(version); // amx version
+        bin.write_u16::<LittleEndian>(self.flags).unwrap();
+        bin.write_u16::<LittleEndian>(entry_size as u16).unwrap();
+        for value in &[
+            cod,
+            dat,
+            hea,
+            hea + STACK_SIZE as usize,
+            0xFFFF_FFFF, // no main()
+            publics_offset,
+            natives_offset,
+            libraries_offset,
+            pubvars_offset,
+            tags_offset,
+            nametable_offset,
+        ] {
+            bin.write_u32::<LittleEndian>(*value as u32).unwrap();
+        }
+
+        let mut names = record_names.into_iter();
+        for (_, address) in publics.iter() {
+            bin.write_u32::<LittleEndian>(*address).unwrap();
+            bin.extend_from_slice(&names.next().unwrap());
+        }
+        for _ in self.natives.iter() {
+            bin.write_u32::<LittleEndian>(0).unwrap();
+            bin.extend_from_slice(&names.next().unwrap());
+        }
+
+        for _ in self.libraries.iter() {
+            bin.write_u32::<LittleEndian>(0).unwrap();
+            bin.extend_from_slice(&names.next().unwrap());
+        }
+        for (_, value) in self.pubvars.iter().chain(self.tags.iter()) {
+            bin.write_u32::<LittleEndian>(*value).unwrap();
+            bin.extend_from_slice(&names.next().unwrap());
+        }
+
+        bin.extend_from_slice(&nametable);
+        for cell in self.cod.iter().chain(self.dat.iter()) {
+            bin.write_u32::<LittleEndian>(*cell).unwrap();
+        }
+        bin.extend_from_slice(&self.debug_info);
+
+        bin
+    }
+}
@@ -0,0 +1,209 @@
+// Round trip check of decompiler: decompiled source is compiled back with
+// amxxpc and resulting bytecode is compared with the original function by
+// function. Differences point at decompiler bugs or unsupported constructs.
+
+use std::fs;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::diff::{compare, PluginDiff};
+use crate::error::AmxError;
+use crate::facade::{self, DecompileOptions};
+
+// Distinguishes work directories of verifications running at once
+static RUN: AtomicUsize = AtomicUsize::new(0);
+// Taken directory names skipped before giving up
+const WORK_DIR_ATTEMPTS: usize = 100;
+
+// Fresh private directory in temp dir, existing path is never reused as
+// anybody could have prepared it
+fn work_dir() -> Result<PathBuf, AmxError> {
+    for _ in 0..WORK_DIR_ATTEMPTS {
+        let dir = std::env::temp_dir().join(format!(
+            "rxxma-verify-{}-{}",
+            std::process::id(),
+            RUN.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        builder.mode(0o700);
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(AmxError::Io(
+        "cannot create verification directory".to_owned(),
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    // Decompiled source given to compiler
+    pub source: String,
+    // Original plugin compared to recompiled one
+    pub diff: PluginDiff,
+    // 0.0 to 1.0, 1.0 when every function compiled back identically
+    pub fidelity: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    amxxpc: PathBuf,
+    include_dir: Option<PathBuf>,
+    options: DecompileOptions,
+}
+
+impl Verifier {
+    pub fn new<P: AsRef<Path>>(amxxpc: P) -> Verifier {
+        Verifier {
+            amxxpc: amxxpc.as_ref().to_path_buf(),
+            include_dir: None,
+            options: DecompileOptions::default(),
+        }
+    }
+
+    // Directory with amxmodx.inc and module includes, amxxpc looks next
+    // to itself by default
+    pub fn include_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.include_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn options(&mut self, options: DecompileOptions) -> &mut Self {
+        self.options = options;
+        self
+    }
+
+    fn compile(&self, source: &str, dir: &Path) -> Result<Vec<u8>, AmxError> {
+        let input = dir.join("plugin.sma");
+        let output = dir.join("plugin.amxx");
+        fs::write(&input, source)?;
+
+        let mut command = Command::new(&self.amxxpc);
+        command
+            .current_dir(dir)
+            .arg(&input)
+            .arg(format!("-o{}", output.display()));
+        if let Some(ref include_dir) = self.include_dir {
+            command.arg(format!("-i{}", include_dir.display()));
+        }
+
+        // amxxpc reports errors on stdout
+        let result = command.output()?;
+        if !result.status.success() || !output.is_file() {
+            let mut log = String::from_utf8_lossy(&result.stdout).into_owned();
+            log.push_str(&String::from_utf8_lossy(&result.stderr));
+            return Err(AmxError::Compile(log.trim().to_owned()));
+        }
+        Ok(fs::read(output)?)
+    }
+
+    /// Decompiles amxx or amx file contents, compiles the source back and
+    /// compares both plugins.
+    ///
+    /// ```no_run
+    /// use rxxma::verify::Verifier;
+    ///
+    /// let bytes = std::fs::read("plugin.amxx").unwrap();
+    /// let verification = Verifier::new("/opt/amxmodx/scripting/amxxpc")
+    ///     .verify(&bytes)
+    ///     .unwrap();
+    /// println!("{}fidelity: {:.2}", verification.diff, verification.fidelity);
+    /// ```
+    pub fn verify(&self, bytes: &[u8]) -> Result<Verification, AmxError> {
+        let source = facade::decompile(bytes, &self.options)?;
+
+        let dir = work_dir()?;
+        let compiled = self.compile(&source, &dir);
+        fs::remove_dir_all(&dir)?;
+        let compiled = compiled?;
+
+        let original = facade::load_plugin(bytes)?;
+        let diff = compare(&original, &facade::load_plugin(&compiled)?)?;
+        Ok(Verification {
+            source,
+            fidelity: diff.similarity(),
+            diff,
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+
+    use super::{work_dir, Verifier};
+    use crate::error::AmxError;
+    use crate::util::tests::load_fixture;
+
+    // Shell script standing in for amxxpc
+    fn fake_compiler(name: &str, script: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn it_verify_recompiled_plugin() {
+        let fixture = std::env::current_dir()
+            .unwrap()
+            .join("test/fixtures/simple.amxx183");
+        // Writes original plugin to -o path whatever the source is
+        let amxxpc = fake_compiler(
+            "rxxma-amxxpc-copy",
+            &format!(
+                "for arg in \"$@\"; do case \"$arg\" in -o*) cp {:?} \"${{arg#-o}}\";; esac; done",
+                fixture
+            ),
+        );
+
+        let verification = Verifier::new(&amxxpc)
+            .verify(&load_fixture("simple.amxx183"))
+            .unwrap();
+        fs::remove_file(&amxxpc).unwrap();
+
+        assert!(verification.source.starts_with("// Plugin source"));
+        assert!(verification.diff.is_identical());
+        assert_eq!(verification.fidelity, 1.0);
+    }
+
+    #[test]
+    fn it_report_compiler_errors() {
+        let amxxpc = fake_compiler(
+            "rxxma-amxxpc-fail",
+            "echo 'plugin.sma(3) : error 017: undefined symbol \"sub_1c\"'; exit 1",
+        );
+
+        let error = Verifier::new(&amxxpc)
+            .include_dir("/nonexistent")
+            .verify(&load_fixture("simple.amxx183"))
+            .unwrap_err();
+        fs::remove_file(&amxxpc).unwrap();
+
+        assert_eq!(
+            error,
+            AmxError::Compile("plugin.sma(3) : error 017: undefined symbol \"sub_1c\"".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_create_private_work_dirs() {
+        let first = work_dir().unwrap();
+        let second = work_dir().unwrap();
+        let mode = fs::metadata(&first).unwrap().permissions().mode();
+        fs::remove_dir(&first).unwrap();
+        fs::remove_dir(&second).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(mode & 0o777, 0o700);
+    }
+}
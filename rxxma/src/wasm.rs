@@ -0,0 +1,24 @@
+// wasm-bindgen interface, build with
+// `wasm-pack build --no-default-features --features wasm`.
+// Errors are thrown as JS exceptions carrying error message.
+
+use wasm_bindgen::prelude::*;
+
+use crate::facade::{self, DecompileOptions};
+
+fn into_js_error<E: std::fmt::Display>(e: E) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+// Decompiles .amxx or .amx file contents, `bytes` is Uint8Array on JS side
+#[wasm_bindgen]
+pub fn decompile(bytes: &[u8]) -> Result<String, JsError> {
+    facade::decompile(bytes, &DecompileOptions::default()).map_err(into_js_error)
+}
+
+// Returns PluginInfo as plain JS object
+#[wasm_bindgen]
+pub fn inspect(bytes: &[u8]) -> Result<JsValue, JsError> {
+    let info = facade::inspect(bytes).map_err(into_js_error)?;
+    serde_wasm_bindgen::to_value(&info).map_err(into_js_error)
+}
@@ -0,0 +1,263 @@
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::process::Command;
+
+fn amxxtool(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_amxxtool"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("amxxtool-{}-{}", std::process::id(), name))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn it_unpack_amx_image() {
+    let output = temp_path("simple.amx");
+    amxxtool(&["unpack", "test/fixtures/simple.amxx183", "-o", &output]);
+
+    let image = fs::read(&output).unwrap();
+    fs::remove_file(&output).unwrap();
+    let expected = rxxma::facade::load_plugin(&image).unwrap().to_bytes();
+    assert_eq!(image, expected);
+    assert_eq!(&image[4..6], &[0xE0, 0xF1]);
+}
+
+#[test]
+fn it_disassemble_and_decompile() {
+    let listing = amxxtool(&["disasm", "test/fixtures/simple.amxx183"]);
+    assert!(listing.contains("SYSREQ.C\t0x0"));
+    let listing = amxxtool(&["disasm", "-a", "test/fixtures/simple.amxx183"]);
+    assert!(listing.starts_with("plugin_init:\n"));
+    let page = amxxtool(&["disasm", "--html", "test/fixtures/simple.amxx183"]);
+    assert!(page.contains("<title>simple.amxx183</title>"));
+
+    let output = temp_path("simple.sma");
+    amxxtool(&["decompile", "test/fixtures/simple.amxx183", "-o", &output]);
+    let source = fs::read_to_string(&output).unwrap();
+    fs::remove_file(&output).unwrap();
+    assert!(source.contains("register_plugin"));
+
+    let listing = amxxtool(&[
+        "disasm",
+        "-f",
+        "plugin_init",
+        "test/fixtures/simple.amxx183",
+    ]);
+    assert!(listing.starts_with("0x8\tPROC\n"));
+    let source = amxxtool(&["decompile", "-f", "0x8", "test/fixtures/simple.amxx183"]);
+    assert!(source.starts_with("public plugin_init () {\n"));
+}
+
+#[test]
+fn it_print_info() {
+    let info = amxxtool(&["info", "test/fixtures/simple.amxx183"]);
+    assert!(info.contains("Publics: plugin_init\n"));
+
+    let json = amxxtool(&["info", "--json", "test/fixtures/simple.amxx183"]);
+    let dump: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(dump["natives"][0]["name"], "register_plugin");
+    assert_eq!(dump["opcodes"][0]["code"], "PROC");
+    assert_eq!(dump["header"]["cellsize"], 4);
+}
+
+#[test]
+fn it_print_strings() {
+    let strings = amxxtool(&["strings", "test/fixtures/simple.amxx183"]);
+    assert_eq!(strings.lines().nth(1), Some("0x38\tcell\t0.1"));
+}
+
+#[test]
+fn it_list_xrefs() {
+    let listing = amxxtool(&["xref", "test/fixtures/simple.amxx183", "0x0"]);
+    assert!(listing.ends_with("\taddress\tplugin_init\ttriggerable from: plugin_init\n"));
+
+    let listing = amxxtool(&["xref", "--commands", "test/fixtures/simple.amxx183"]);
+    assert!(listing.is_empty());
+}
+
+#[test]
+fn it_print_call_graph() {
+    let dot = amxxtool(&["callgraph", "test/fixtures/simple.amxx183"]);
+    assert!(dot.starts_with("digraph plugin {\n"));
+    assert!(dot.contains("    \"plugin_init\" -> \"register_plugin\";\n"));
+}
+
+#[test]
+fn it_scan_clean_plugin() {
+    let scan = amxxtool(&["scan", "test/fixtures/two_natives.amxx"]);
+    assert_eq!(scan, "Verdict: Clean (score 0)\n");
+}
+
+#[test]
+fn it_patch_string() {
+    let output = temp_path("patched.amxx");
+    amxxtool(&[
+        "patch-string",
+        "test/fixtures/simple.amx183",
+        "0x38",
+        "2",
+        "-o",
+        &output,
+    ]);
+
+    let source = amxxtool(&["decompile", &output]);
+    fs::remove_file(&output).unwrap();
+    assert!(source.contains("\"2\""));
+}
+
+#[test]
+fn it_rename_native() {
+    let output = temp_path("renamed.amxx");
+    amxxtool(&[
+        "rename-native",
+        "test/fixtures/two_natives.amx183",
+        "native_one",
+        "native_renamed",
+        "-o",
+        &output,
+    ]);
+    let info = amxxtool(&["info", &output]);
+    assert!(info.contains("Natives: native_renamed, native_two\n"));
+
+    amxxtool(&[
+        "rename-native",
+        &output,
+        "native_two",
+        "native_renamed",
+        "-r",
+    ]);
+    let listing = amxxtool(&["disasm", &output]);
+    fs::remove_file(&output).unwrap();
+    assert_eq!(listing.matches("SYSREQ.C\t0x0").count(), 2);
+}
+
+#[test]
+fn it_batch_analyze_directory() {
+    let dir = temp_path("plugins");
+    fs::create_dir_all(&dir).unwrap();
+    fs::copy(
+        "test/fixtures/simple.amxx183",
+        format!("{}/simple.amxx", dir),
+    )
+    .unwrap();
+    fs::write(format!("{}/broken.amxx", dir), b"XXMA").unwrap();
+    let aggregate = temp_path("server.json");
+
+    let listing = amxxtool(&["batch", &dir, "--aggregate", &aggregate, "--sources", &dir]);
+    let source = fs::read_to_string(format!("{}/simple.sma", dir)).unwrap();
+    let json = fs::read_to_string(&aggregate).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_file(&aggregate).unwrap();
+
+    assert!(listing.starts_with("broken.amxx\terror\t"));
+    assert!(listing.contains("simple.amxx\tok\t\n"));
+    assert!(source.contains("register_plugin"));
+    let analysis: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(analysis["natives"]["register_plugin"][0], "simple.amxx");
+}
+
+#[test]
+fn it_audit_plugins_ini() {
+    let dir = temp_path("server");
+    fs::create_dir_all(&dir).unwrap();
+    fs::copy(
+        "test/fixtures/simple.amxx183",
+        format!("{}/simple.amxx", dir),
+    )
+    .unwrap();
+    let ini = format!("{}/plugins.ini", dir);
+    fs::write(&ini, "; default plugins\nsimple.amxx\nmissing.amxx\n").unwrap();
+
+    let report = amxxtool(&["audit", &ini, &dir]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(report.starts_with("2 plugins enabled, 1 missing, 0 broken, 0 flagged\n"));
+    assert!(report.contains("simple.amxx (line 2): simple plugin 0.1 by Fedcomp\n"));
+}
+
+#[test]
+fn it_diff_plugins() {
+    let same = amxxtool(&[
+        "diff",
+        "test/fixtures/simple.amxx183",
+        "test/fixtures/simple.amx183",
+    ]);
+    assert_eq!(same, "= plugin_init\n");
+
+    let other = amxxtool(&[
+        "diff",
+        "test/fixtures/simple.amxx183",
+        "test/fixtures/two_natives.amx183",
+    ]);
+    assert!(other.starts_with("header:\n  cod size: 0x4C -> 0x50\n"));
+    assert!(other.contains("natives:\n  + \"native_one\"\n"));
+    assert!(other.ends_with("- plugin_init\n+ func\n"));
+}
+
+#[test]
+fn it_emulate_public() {
+    let trace = amxxtool(&["emulate", "test/fixtures/two_natives.amx183", "func"]);
+    assert_eq!(
+        trace,
+        "0x1C\tnative_one() = 0\n0x38\tnative_two() = 0\nreturned 0\n"
+    );
+}
+
+#[test]
+fn it_sigscan_plugins() {
+    let listing = amxxtool(&[
+        "sigscan",
+        "test/fixtures/two_natives.amx183",
+        "-p",
+        "SYSREQ.C ??",
+    ]);
+    assert_eq!(
+        listing,
+        "0x1C\tSYSREQ.C ??\tfunc\n0x38\tSYSREQ.C ??\tfunc\n"
+    );
+
+    let listing = amxxtool(&[
+        "sigscan",
+        "test/fixtures/simple.amxx183",
+        "test/fixtures/two_natives.amx183",
+        "-p",
+        "PROC",
+    ]);
+    assert!(listing.starts_with("test/fixtures/simple.amxx183\t0x8\tPROC\tplugin_init\n"));
+}
+
+#[test]
+fn it_match_rules() {
+    let rules = temp_path("plugin.rules");
+    fs::write(
+        &rules,
+        "rule two_natives {\n  native native_one\n  native missing\n  condition any\n}\n",
+    )
+    .unwrap();
+    let listing = amxxtool(&["rules", "test/fixtures/two_natives.amx183", "-r", &rules]);
+    fs::remove_file(&rules).unwrap();
+
+    assert_eq!(listing, "two_natives\tnative native_one at 0x1C\n");
+}
+
+#[test]
+fn it_validate_plugin() {
+    let report = amxxtool(&["validate", "test/fixtures/simple.amxx183"]);
+    assert_eq!(report, "no violations\n");
+}
+
+#[test]
+fn it_hexdump_plugin() {
+    let dump = amxxtool(&["hexdump", "test/fixtures/simple.amx183"]);
+    assert!(dump.starts_with("image:\n00000000  28 01 00 00 E0 F1 08 08"));
+    assert!(dump.contains("[cod 0x74..0xC0]"));
+}
@@ -0,0 +1,228 @@
+use std::fs;
+
+use rxxma::ast::Style;
+use rxxma::facade::{decompile_function, detect_format, disassemble_function, Format, SectionInfo};
+use rxxma::util::Encoding;
+use rxxma::{decompile, disassemble, inspect, load, AmxError, DecompileOptions};
+
+fn load_fixture(filename: &str) -> Vec<u8> {
+    fs::read(format!("test/fixtures/{}", filename)).unwrap()
+}
+
+#[test]
+fn it_detect_file_format() {
+    assert_eq!(
+        detect_format(&load_fixture("simple.amxx183")).unwrap(),
+        Format::Amxx
+    );
+    assert_eq!(
+        detect_format(&load_fixture("simple.amx183")).unwrap(),
+        Format::Amx
+    );
+    assert_eq!(detect_format(b"garbage"), Err(AmxError::UnknownFormat));
+}
+
+#[test]
+fn it_load_gzip_compressed_amx() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let amx_bin = load_fixture("simple.amx183");
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&amx_bin).unwrap();
+    let gzip_bin = encoder.finish().unwrap();
+
+    let loaded = load(&gzip_bin).unwrap();
+    assert_eq!(loaded.format, Format::GzipAmx);
+    assert!(loaded.sections.is_empty());
+    assert_eq!(loaded.plugin, load(&amx_bin).unwrap().plugin);
+    assert!(matches!(
+        load(&gzip_bin[..20]).err(),
+        Some(AmxError::Unpack(_))
+    ));
+}
+
+#[test]
+fn it_decompile_amxx_and_amx_alike() {
+    let opts = DecompileOptions::default();
+    let from_amxx = decompile(&load_fixture("simple.amxx183"), &opts).unwrap();
+    let from_amx = decompile(&load_fixture("simple.amx183"), &opts).unwrap();
+
+    assert_eq!(from_amxx, from_amx);
+    assert_eq!(
+        from_amxx,
+        "// Plugin source approximation starts here\n\n#include <amxmodx>\n\n\
+         public plugin_init () {\n    \
+         register_plugin(\"simple plugin\", \"0.1\", \"Fedcomp\");\n\
+         }\n\n"
+    );
+}
+
+#[test]
+fn it_decompile_64_bit_section() {
+    let opts = DecompileOptions {
+        cellsize: 8,
+        ..DecompileOptions::default()
+    };
+    let bytes = load_fixture("simple.amxx181");
+    let source = decompile(&bytes, &opts).unwrap();
+
+    assert_eq!(
+        source,
+        decompile(&bytes, &DecompileOptions::default()).unwrap()
+    );
+    let listing = disassemble(&bytes, &opts).unwrap();
+    assert!(listing.starts_with("0x10\tPROC\n"));
+    assert_eq!(
+        decompile(&load_fixture("simple.amxx183"), &opts),
+        Err(AmxError::NoSection(64))
+    );
+}
+
+#[test]
+fn it_decompile_if_statement() {
+    let source = decompile(
+        &load_fixture("shl_minimal_case.amxx"),
+        &DecompileOptions::default(),
+    )
+    .unwrap();
+
+    // Fixture is compiled with debug info
+    assert!(source.contains("    new f = 1;\n    if (f) {\n        nfunc(\"\");\n    }\n"));
+    assert!(source.contains("    if (1 << weaponid) {\n    }\n"));
+}
+
+#[test]
+fn it_decompile_with_custom_indent() {
+    let opts = DecompileOptions {
+        style: Style {
+            indent_width: 2,
+            ..Style::default()
+        },
+        ..DecompileOptions::default()
+    };
+    let source = decompile(&load_fixture("two_natives.amxx"), &opts).unwrap();
+
+    assert!(source.contains("\n  native_one();\n  native_two();\n"));
+}
+
+#[test]
+fn it_decompile_with_encoding() {
+    let opts = DecompileOptions {
+        encoding: Encoding::Latin1,
+        ..DecompileOptions::default()
+    };
+    let source = decompile(&load_fixture("cell_constants.amxx"), &opts).unwrap();
+
+    assert!(source.contains("some_native(\"simple plugin\", 100000, 1);"));
+}
+
+#[test]
+fn it_disassemble_opcodes() {
+    let listing = disassemble(
+        &load_fixture("two_natives.amxx"),
+        &DecompileOptions::default(),
+    )
+    .unwrap();
+
+    assert!(listing.contains("\tSYSREQ.C\t0x0\n"));
+    assert!(listing.contains("\tSYSREQ.C\t0x1\n"));
+    assert!(listing.ends_with("\tRETN\n"));
+}
+
+#[test]
+fn it_select_single_function() {
+    let bytes = load_fixture("shl_minimal_case.amxx");
+    let opts = DecompileOptions::default();
+
+    let source = decompile_function(&bytes, "func1", &opts).unwrap();
+    assert!(source.starts_with("public func1 () {\n"));
+    assert!(source.contains("if (1 << weaponid) {"));
+    assert!(!source.contains("func2"));
+    // Any address inside function picks it
+    assert_eq!(decompile_function(&bytes, "0x74", &opts).unwrap(), source);
+
+    let listing = disassemble_function(&bytes, "func2", &opts).unwrap();
+    assert!(listing.starts_with("0x8\tPROC\n"));
+    assert!(listing.ends_with("0x60\tRETN\n"));
+
+    assert_eq!(
+        decompile_function(&bytes, "func3", &opts),
+        Err(AmxError::NoFunction("func3".to_owned()))
+    );
+}
+
+#[test]
+fn it_disassemble_shl_without_param() {
+    let listing = disassemble(
+        &load_fixture("shl_minimal_case.amxx"),
+        &DecompileOptions::default(),
+    )
+    .unwrap();
+
+    assert!(listing.contains("\tSHL\n0x88\tJZER\t0x90\n"));
+}
+
+#[test]
+fn it_disassemble_junk_cell_in_lenient_mode() {
+    let mut amx_bin = load_fixture("simple.amx183");
+    // Corrupt PROC of plugin_init
+    let cod = u32::from_le_bytes([amx_bin[12], amx_bin[13], amx_bin[14], amx_bin[15]]) as usize;
+    amx_bin[cod + 8..cod + 12].copy_from_slice(&[0xFF; 4]);
+
+    match disassemble(&amx_bin, &DecompileOptions::default()) {
+        Err(AmxError::InvalidOpcode { offset, .. }) => assert_eq!(offset, cod + 8),
+        result => panic!("expected invalid opcode, got {:?}", result),
+    }
+
+    let opts = DecompileOptions {
+        lenient: true,
+        ..DecompileOptions::default()
+    };
+    let listing = disassemble(&amx_bin, &opts).unwrap();
+    assert!(listing.starts_with("0x8\tUNKNOWN\t0xFFFFFFFF\n0xC\tBREAK\n"));
+    assert!(listing.ends_with("0x44\tZERO.pri\n0x48\tRETN\n"));
+}
+
+#[test]
+fn it_inspect_sections_and_symbols() {
+    let info = inspect(&load_fixture("simple.amxx181")).unwrap();
+
+    assert_eq!(info.format, Format::Amxx);
+    assert_eq!(
+        info.sections,
+        [
+            SectionInfo {
+                cellsize: 4,
+                disksize: 161,
+                imagesize: 288,
+                memsize: 16672,
+            },
+            SectionInfo {
+                cellsize: 8,
+                disksize: 177,
+                imagesize: 488,
+                memsize: 33256,
+            },
+        ]
+    );
+    assert_eq!(info.publics, ["plugin_init"]);
+    assert_eq!(info.natives, ["register_plugin"]);
+    assert!(info.libraries.is_empty());
+    assert!(info.cod_size > 0);
+    assert!(info.dat_size > 0);
+    assert_eq!(info.heap_budget, 16384);
+    assert_eq!(info.heap_worst_case, Some(0));
+    assert!(info.dictionaries.is_empty());
+    assert!(info.precached.is_empty());
+}
+
+#[test]
+fn it_inspect_raw_amx() {
+    let info = inspect(&load_fixture("two_natives.amx183")).unwrap();
+
+    assert_eq!(info.format, Format::Amx);
+    assert!(info.sections.is_empty());
+    assert_eq!(info.natives, ["native_one", "native_two"]);
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "ffi")]
+
+use std::ffi::CStr;
+use std::fs;
+use std::os::raw::c_char;
+use std::ptr;
+
+use rxxma::ffi::{
+    amxx_decompile, amxx_free_string, amxx_inspect_json, AMXX_ERR_INVALID_INPUT,
+    AMXX_ERR_NULL_ARGUMENT, AMXX_OK,
+};
+
+type FfiFn = unsafe extern "C" fn(*const u8, usize, *mut *mut c_char, *mut *mut c_char) -> i32;
+
+// Returns code, out and err converted to owned strings
+fn call(f: FfiFn, bytes: &[u8]) -> (i32, Option<String>, Option<String>) {
+    let mut out: *mut c_char = ptr::null_mut();
+    let mut err: *mut c_char = ptr::null_mut();
+    let code = unsafe { f(bytes.as_ptr(), bytes.len(), &mut out, &mut err) };
+
+    let take = |s: *mut c_char| {
+        if s.is_null() {
+            return None;
+        }
+        let owned = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_owned();
+        unsafe { amxx_free_string(s) };
+        Some(owned)
+    };
+
+    (code, take(out), take(err))
+}
+
+#[test]
+fn it_decompile_through_ffi() {
+    let bytes = fs::read("test/fixtures/simple.amxx183").unwrap();
+    let (code, out, err) = call(amxx_decompile, &bytes);
+
+    assert_eq!(code, AMXX_OK);
+    assert!(out.unwrap().contains("public plugin_init ()"));
+    assert_eq!(err, None);
+}
+
+#[test]
+fn it_inspect_json_through_ffi() {
+    let bytes = fs::read("test/fixtures/two_natives.amx183").unwrap();
+    let (code, out, _) = call(amxx_inspect_json, &bytes);
+
+    assert_eq!(code, AMXX_OK);
+    let json: serde_json::Value = serde_json::from_str(&out.unwrap()).unwrap();
+    assert_eq!(json["format"], "Amx");
+    assert_eq!(json["natives"][1], "native_two");
+}
+
+#[test]
+fn it_report_garbage_input_through_ffi() {
+    let (code, out, err) = call(amxx_decompile, b"definitely not a plugin");
+
+    assert_eq!(code, AMXX_ERR_INVALID_INPUT);
+    assert_eq!(out, None);
+    assert_eq!(err.unwrap(), "Unknown file format, neither amxx nor amx");
+}
+
+#[test]
+fn it_reject_null_output() {
+    let bytes = [0u8; 4];
+    let code = unsafe { amxx_decompile(bytes.as_ptr(), 4, ptr::null_mut(), ptr::null_mut()) };
+
+    assert_eq!(code, AMXX_ERR_NULL_ARGUMENT);
+}
+
+#[test]
+fn it_err_on_broken_public_name() {
+    let mut bytes = fs::read("test/fixtures/simple.amx183").unwrap();
+    // Name offset of plugin_init public outside nametable
+    bytes[60..64].copy_from_slice(&0u32.to_le_bytes());
+    let (code, out, err) = call(amxx_decompile, &bytes);
+
+    assert_eq!(code, AMXX_ERR_INVALID_INPUT);
+    assert_eq!(out, None);
+    assert!(!err.unwrap().starts_with("panic: "));
+}
@@ -0,0 +1,31 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::*;
+
+use rxxma::wasm::{decompile, inspect};
+
+const SIMPLE_AMXX: &[u8] = include_bytes!("../test/fixtures/simple.amxx183");
+
+#[wasm_bindgen_test]
+fn it_decompile_in_wasm() {
+    let source = decompile(SIMPLE_AMXX).unwrap();
+
+    assert!(source.contains("register_plugin(\"simple plugin\", \"0.1\", \"Fedcomp\");"));
+}
+
+#[wasm_bindgen_test]
+fn it_inspect_into_js_object() {
+    let info = inspect(SIMPLE_AMXX).unwrap();
+    let natives = js_sys::Reflect::get(&info, &JsValue::from_str("natives")).unwrap();
+
+    assert_eq!(
+        js_sys::Array::from(&natives).get(0),
+        JsValue::from_str("register_plugin")
+    );
+}
+
+#[wasm_bindgen_test]
+fn it_throw_on_garbage() {
+    assert!(decompile(b"garbage").is_err());
+}